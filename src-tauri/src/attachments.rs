@@ -0,0 +1,721 @@
+use crate::file_manager::{
+    ensure_library_subdirectory, load_markdown_library, metadata_is_symlink_or_reparse_point,
+    note_index_entry, now_millis, read_trusted_file_bytes, sanitize_folder_path, sha256_hex,
+    system_time_to_millis, write_library_file_atomically, NoteLoadState, TrustedLibraryRoot,
+};
+use crate::note_crypto;
+use crate::note_history::HISTORY_DIRECTORY_NAME;
+use crate::note_trash::TRASH_DIRECTORY_NAME;
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+use regex::Regex;
+use serde::Serialize;
+
+pub(crate) const ATTACHMENTS_DIRECTORY_NAME: &str = ".hwan-note-assets";
+const MAX_ATTACHMENT_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_EXTENSION_LENGTH: usize = 10;
+const FALLBACK_EXTENSION: &str = "bin";
+/// Attachments younger than this are never reported as unreferenced: a pasted
+/// image is saved before the note that links to it is autosaved.
+const UNREFERENCED_MIN_AGE_MILLIS: u64 = 60 * 60 * 1000;
+
+static ATTACHMENT_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[0-9a-f]{64}\.[a-z0-9]{1,10}$").unwrap());
+static ATTACHMENT_REFERENCE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"((?:\.\./)*)\.hwan-note-assets/([0-9a-f]{64}\.[a-z0-9]{1,10})").unwrap()
+});
+
+// ── Types ──
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedAttachment {
+    pub file_name: String,
+    /// Markdown link target relative to the note's folder.
+    pub link_target: String,
+    pub mime_type: String,
+    pub size: u64,
+    /// False when identical content was already stored.
+    pub created: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentInfo {
+    pub file_name: String,
+    pub size: u64,
+    pub modified_at: u64,
+}
+
+// ── Naming ──
+
+pub(crate) fn is_attachment_file_name(file_name: &str) -> bool {
+    ATTACHMENT_NAME_RE.is_match(file_name)
+}
+
+fn attachment_extension(original_name: &str) -> String {
+    Path::new(original_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .filter(|extension| {
+            !extension.is_empty()
+                && extension.len() <= MAX_EXTENSION_LENGTH
+                && extension.chars().all(|ch| ch.is_ascii_alphanumeric())
+        })
+        .unwrap_or_else(|| FALLBACK_EXTENSION.to_string())
+}
+
+/// Content type for serving an attachment. Anything outside this list is
+/// served as an opaque download.
+pub(crate) fn attachment_mime_type(file_name: &str) -> &'static str {
+    match file_name.rsplit('.').next().unwrap_or_default() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain; charset=utf-8",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+fn attachment_link_target(directory_depth: usize, file_name: &str) -> String {
+    format!(
+        "{}{ATTACHMENTS_DIRECTORY_NAME}/{file_name}",
+        "../".repeat(directory_depth)
+    )
+}
+
+fn directory_depth(relative_path: &str) -> usize {
+    relative_path.matches('/').count()
+}
+
+// ── References ──
+
+/// Attachment file names linked from `markdown`, however the link is prefixed.
+pub(crate) fn referenced_attachments(markdown: &str) -> impl Iterator<Item = &str> {
+    ATTACHMENT_REFERENCE_RE
+        .captures_iter(markdown)
+        .filter_map(|captures| captures.get(2).map(|name| name.as_str()))
+}
+
+/// Rewrites attachment links written for a note at `from_path` so they resolve
+/// from `to_path` (both library-relative). Links that did not resolve from the
+/// old location are left alone. Returns `None` when nothing changes.
+pub(crate) fn rebase_attachment_links(
+    markdown: &str,
+    from_path: &str,
+    to_path: &str,
+) -> Option<String> {
+    let from_prefix = "../".repeat(directory_depth(from_path));
+    let to_prefix = "../".repeat(directory_depth(to_path));
+    if from_prefix == to_prefix {
+        return None;
+    }
+
+    let mut changed = false;
+    let rewritten = ATTACHMENT_REFERENCE_RE.replace_all(markdown, |captures: &regex::Captures| {
+        if captures[1] == from_prefix {
+            changed = true;
+            format!("{to_prefix}{ATTACHMENTS_DIRECTORY_NAME}/{}", &captures[2])
+        } else {
+            captures[0].to_string()
+        }
+    });
+    changed.then(|| rewritten.into_owned())
+}
+
+// ── Storage ──
+
+/// Stores `bytes` under a content-addressed name in the library's attachments
+/// directory and returns a link target for a note in `folder_path`. When the
+/// note is already indexed its real location wins over `folder_path`.
+pub fn save_attachment(
+    auto_save_dir: &Path,
+    note_id: Option<&str>,
+    folder_path: Option<&str>,
+    original_name: &str,
+    bytes: &[u8],
+) -> Result<SavedAttachment, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    if bytes.len() as u64 > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "save_attachment failed for {original_name}: attachments are limited to {} MiB",
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        ));
+    }
+    let entry = match note_id {
+        Some(note_id) => note_index_entry(&trusted_root, note_id)?,
+        None => None,
+    };
+    // Attachments are stored in the clear, so an encrypted note may not link
+    // to one.
+    if let Some(entry) = &entry {
+        let note_path = trusted_root.file_path(&entry.relative_path, true)?;
+        let note_bytes =
+            read_trusted_file_bytes(&trusted_root, &note_path, "read_attachment_note")?;
+        if note_crypto::is_armored_bytes(&note_bytes) {
+            return Err(format!(
+                "save_attachment failed for {original_name}: encrypted notes cannot hold attachments"
+            ));
+        }
+    }
+    let depth = match entry.map(|entry| directory_depth(&entry.relative_path)) {
+        Some(depth) => depth,
+        None => {
+            let folder = sanitize_folder_path(folder_path)?;
+            if folder.is_empty() {
+                0
+            } else {
+                folder.split('/').count()
+            }
+        }
+    };
+
+    let assets_dir =
+        ensure_library_subdirectory(&trusted_root, Path::new(ATTACHMENTS_DIRECTORY_NAME))?;
+    let file_name = format!(
+        "{}.{}",
+        sha256_hex(bytes),
+        attachment_extension(original_name)
+    );
+    let path = assets_dir.join(&file_name);
+    let existing = match fs::symlink_metadata(&path) {
+        Ok(_) => Some(read_trusted_file_bytes(
+            &trusted_root,
+            &path,
+            "read_attachment",
+        )?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => {
+            return Err(format!(
+                "save_attachment failed for {}: {error}",
+                path.display()
+            ))
+        }
+    };
+    let created = match existing {
+        Some(existing) if existing == bytes => {
+            // Refresh the age so a re-pasted file is not collected before the
+            // note that links to it is saved.
+            if let Err(error) = fs::OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(SystemTime::now()))
+            {
+                tracing::warn!("touch_attachment failed for {}: {error}", path.display());
+            }
+            false
+        }
+        _ => {
+            write_library_file_atomically(&trusted_root, &path, bytes)?;
+            true
+        }
+    };
+
+    Ok(SavedAttachment {
+        link_target: attachment_link_target(depth, &file_name),
+        mime_type: attachment_mime_type(&file_name).to_string(),
+        size: bytes.len() as u64,
+        file_name,
+        created,
+    })
+}
+
+/// Copies a file from outside the library (a drop from the file manager) into
+/// the attachments directory.
+pub fn import_attachment(
+    auto_save_dir: &Path,
+    note_id: Option<&str>,
+    folder_path: Option<&str>,
+    source_path: &Path,
+) -> Result<SavedAttachment, String> {
+    let metadata = fs::metadata(source_path).map_err(|error| {
+        format!(
+            "import_attachment failed for {}: {error}",
+            source_path.display()
+        )
+    })?;
+    if !metadata.is_file() {
+        return Err(format!(
+            "import_attachment failed for {}: not a regular file",
+            source_path.display()
+        ));
+    }
+    if metadata.len() > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "import_attachment failed for {}: attachments are limited to {} MiB",
+            source_path.display(),
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        ));
+    }
+    let bytes = fs::read(source_path).map_err(|error| {
+        format!(
+            "import_attachment failed for {}: {error}",
+            source_path.display()
+        )
+    })?;
+    let original_name = source_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    save_attachment(auto_save_dir, note_id, folder_path, &original_name, &bytes)
+}
+
+/// Reads one attachment by its content-addressed name. Only names the app
+/// itself generates are accepted, so the webview cannot reach other files.
+pub fn read_attachment(auto_save_dir: &Path, file_name: &str) -> Result<Vec<u8>, String> {
+    if !is_attachment_file_name(file_name) {
+        return Err(format!(
+            "read_attachment failed for {file_name}: not an attachment name"
+        ));
+    }
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let path =
+        trusted_root.file_path(&format!("{ATTACHMENTS_DIRECTORY_NAME}/{file_name}"), true)?;
+    read_trusted_file_bytes(&trusted_root, &path, "read_attachment")
+}
+
+/// Checks that an attachment name is one the app generates and matches the
+/// digest of `bytes`, so a damaged or forged backup cannot plant a file.
+pub(crate) fn validate_attachment_contents(file_name: &str, bytes: &[u8]) -> Result<(), String> {
+    let digest = sha256_hex(bytes);
+    if is_attachment_file_name(file_name) && file_name.split('.').next() == Some(digest.as_str()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "validate_attachment failed for {file_name}: name does not match its contents"
+        ))
+    }
+}
+
+// ── Backup ──
+
+/// Every attachment in the library with its bytes, sorted by name, for
+/// backups and snapshots.
+pub(crate) fn read_all_attachments(
+    trusted_root: &TrustedLibraryRoot,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    list_attachments(trusted_root)?
+        .into_iter()
+        .map(|attachment| {
+            let path = trusted_root.file_path(
+                &format!("{ATTACHMENTS_DIRECTORY_NAME}/{}", attachment.file_name),
+                true,
+            )?;
+            let bytes = read_trusted_file_bytes(trusted_root, &path, "read_backup_attachment")?;
+            Ok((attachment.file_name, bytes))
+        })
+        .collect()
+}
+
+/// Writes attachments from a backup or snapshot into the library. An
+/// attachment already present has the same content under the same name, so a
+/// restore only adds files and never replaces or removes one. Returns the
+/// number written.
+pub(crate) fn restore_attachments(
+    trusted_root: &TrustedLibraryRoot,
+    attachments: &[(String, Vec<u8>)],
+) -> Result<usize, String> {
+    if attachments.is_empty() {
+        return Ok(0);
+    }
+    let assets_dir =
+        ensure_library_subdirectory(trusted_root, Path::new(ATTACHMENTS_DIRECTORY_NAME))?;
+    let mut written = 0;
+    for (file_name, bytes) in attachments {
+        validate_attachment_contents(file_name, bytes)?;
+        let path = assets_dir.join(file_name);
+        match fs::symlink_metadata(&path) {
+            Ok(_) => continue,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(format!(
+                    "restore_attachment failed for {}: {error}",
+                    path.display()
+                ))
+            }
+        }
+        write_library_file_atomically(trusted_root, &path, bytes)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+// ── Garbage collection ──
+
+pub(crate) fn list_attachments(
+    trusted_root: &TrustedLibraryRoot,
+) -> Result<Vec<AttachmentInfo>, String> {
+    let assets_dir = trusted_root.path().join(ATTACHMENTS_DIRECTORY_NAME);
+    match fs::symlink_metadata(&assets_dir) {
+        Ok(metadata) if metadata.is_dir() && !metadata_is_symlink_or_reparse_point(&metadata) => {}
+        Ok(_) => {
+            return Err(format!(
+                "list_attachments failed for {}: not a trusted directory",
+                assets_dir.display()
+            ));
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(format!(
+                "list_attachments failed for {}: {error}",
+                assets_dir.display()
+            ));
+        }
+    }
+
+    let entries = fs::read_dir(&assets_dir).map_err(|error| {
+        format!(
+            "list_attachments failed for {}: {error}",
+            assets_dir.display()
+        )
+    })?;
+    let mut attachments = Vec::new();
+    for entry in entries.flatten() {
+        let Some(file_name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if !is_attachment_file_name(&file_name) {
+            continue;
+        }
+        let Ok(metadata) = fs::symlink_metadata(entry.path()) else {
+            continue;
+        };
+        if !metadata.is_file() || metadata_is_symlink_or_reparse_point(&metadata) {
+            continue;
+        }
+        let modified_at = metadata
+            .modified()
+            .map(system_time_to_millis)
+            .unwrap_or_default();
+        attachments.push(AttachmentInfo {
+            file_name,
+            size: metadata.len(),
+            modified_at,
+        });
+    }
+    attachments.sort_by(|left, right| left.file_name.cmp(&right.file_name));
+    Ok(attachments)
+}
+
+/// Attachment names linked from any note, kept note revision, or trashed
+/// note. Those count so restoring history or the trash never brings back a
+/// broken image. Returns `None` when an encrypted note or revision hides its
+/// links, which keeps every attachment.
+fn collect_references(
+    trusted_root: &TrustedLibraryRoot,
+) -> Result<Option<HashSet<String>>, String> {
+    let load = load_markdown_library(trusted_root.path());
+    if load.load_state != NoteLoadState::Ready {
+        return Err(format!(
+            "collect_attachment_references failed for {}: the library did not load completely",
+            trusted_root.path().display()
+        ));
+    }
+    if load.notes.iter().any(|note| note.is_encrypted) {
+        return Ok(None);
+    }
+    let mut references = load
+        .notes
+        .iter()
+        .flat_map(|note| referenced_attachments(&note.markdown))
+        .map(str::to_string)
+        .collect::<HashSet<_>>();
+
+    let mut pending = vec![
+        trusted_root.path().join(HISTORY_DIRECTORY_NAME),
+        trusted_root.path().join(TRASH_DIRECTORY_NAME),
+    ];
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if metadata_is_symlink_or_reparse_point(&metadata) {
+                continue;
+            }
+            if metadata.is_dir() {
+                pending.push(path);
+            } else if let Ok(bytes) = read_trusted_file_bytes(trusted_root, &path, "read_kept_note")
+            {
+                if note_crypto::is_armored_bytes(&bytes) {
+                    return Ok(None);
+                }
+                references.extend(
+                    referenced_attachments(&String::from_utf8_lossy(&bytes)).map(str::to_string),
+                );
+            }
+        }
+    }
+    Ok(Some(references))
+}
+
+fn unreferenced_attachments(
+    trusted_root: &TrustedLibraryRoot,
+) -> Result<Vec<AttachmentInfo>, String> {
+    let Some(references) = collect_references(trusted_root)? else {
+        return Ok(Vec::new());
+    };
+    let now = now_millis();
+    Ok(list_attachments(trusted_root)?
+        .into_iter()
+        .filter(|attachment| !references.contains(&attachment.file_name))
+        .filter(|attachment| {
+            now.saturating_sub(attachment.modified_at) >= UNREFERENCED_MIN_AGE_MILLIS
+        })
+        .collect())
+}
+
+/// Lists attachments no note or note revision links to any more.
+pub fn find_unreferenced_attachments(auto_save_dir: &Path) -> Result<Vec<AttachmentInfo>, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    unreferenced_attachments(&trusted_root)
+}
+
+/// Removes the named attachments through `delete_file`, re-checking first so a
+/// name that gained a reference since it was reported is kept. Returns the
+/// names actually removed.
+pub fn remove_unreferenced_attachments<F>(
+    auto_save_dir: &Path,
+    file_names: &[String],
+    mut delete_file: F,
+) -> Result<Vec<String>, String>
+where
+    F: FnMut(&Path) -> Result<(), String>,
+{
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let requested = file_names.iter().collect::<BTreeSet<_>>();
+    let mut removed = Vec::new();
+    for attachment in unreferenced_attachments(&trusted_root)? {
+        if !requested.contains(&attachment.file_name) {
+            continue;
+        }
+        let path = trusted_root.file_path(
+            &format!("{ATTACHMENTS_DIRECTORY_NAME}/{}", attachment.file_name),
+            true,
+        )?;
+        delete_file(&path)?;
+        removed.push(attachment.file_name);
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{
+        auto_save_markdown_note, encrypt_note, migrate_notes, rename_folder, AutoSavePayload,
+    };
+    use crate::note_history::NoteHistoryRetention;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-attachments-{}-{}-{}",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_temp_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn payload(note_id: &str, content: &str, folder: Option<&str>) -> AutoSavePayload {
+        AutoSavePayload {
+            note_id: note_id.to_string(),
+            title: String::new(),
+            content: content.to_string(),
+            folder_path: folder.map(str::to_string),
+            is_title_manual: Some(false),
+            is_pinned: None,
+        }
+    }
+
+    fn age_attachment(dir: &Path, file_name: &str) -> Result<(), String> {
+        fs::OpenOptions::new()
+            .write(true)
+            .open(dir.join(ATTACHMENTS_DIRECTORY_NAME).join(file_name))
+            .and_then(|file| file.set_modified(SystemTime::now() - Duration::from_secs(7200)))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn attachments_are_content_addressed_and_only_served_by_name() {
+        let dir = make_temp_dir("save");
+        let result = (|| -> Result<(), String> {
+            let first = save_attachment(&dir, None, Some("projects/a"), "Shot.PNG", b"png")?;
+            assert!(first.created);
+            assert!(is_attachment_file_name(&first.file_name));
+            assert!(first.file_name.ends_with(".png"));
+            assert_eq!(
+                first.link_target,
+                format!("../../{ATTACHMENTS_DIRECTORY_NAME}/{}", first.file_name)
+            );
+            assert_eq!(first.mime_type, "image/png");
+
+            let again = save_attachment(&dir, None, None, "copy.png", b"png")?;
+            assert!(!again.created);
+            assert_eq!(again.file_name, first.file_name);
+            assert_eq!(
+                again.link_target,
+                format!("{ATTACHMENTS_DIRECTORY_NAME}/{}", first.file_name)
+            );
+
+            save_attachment(&dir, None, None, "notes.md", b"# Not a note")?;
+            assert!(load_markdown_library(&dir).notes.is_empty());
+
+            assert_eq!(read_attachment(&dir, &first.file_name)?, b"png");
+            assert!(read_attachment(&dir, "../.hwan-note-index.json").is_err());
+            assert!(read_attachment(&dir, "notes.md").is_err());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn attachments_follow_notes_through_folder_moves_and_migration() {
+        let dir = make_temp_dir("move");
+        let target = make_temp_dir("move-target");
+        let result = (|| -> Result<(), String> {
+            let saved = save_attachment(&dir, None, Some("projects"), "shot.png", b"png")?;
+            let note = auto_save_markdown_note(
+                &dir,
+                &payload(
+                    "note-a",
+                    &format!("# Shot\n\n![shot]({})", saved.link_target),
+                    Some("projects"),
+                ),
+            )?;
+            let note_name = Path::new(&note.file_path).file_name().unwrap().to_owned();
+
+            let renamed = rename_folder(
+                &dir,
+                "projects",
+                "archive/projects",
+                &NoteHistoryRetention::default(),
+            )?;
+            assert_eq!(renamed.relinked_notes.len(), 1);
+            let moved = fs::read_to_string(dir.join("archive/projects").join(&note_name))
+                .map_err(|e| e.to_string())?;
+            assert!(moved.contains(&format!(
+                "](../../{ATTACHMENTS_DIRECTORY_NAME}/{})",
+                saved.file_name
+            )));
+
+            let migration = migrate_notes(&dir, &target)?;
+            assert_eq!(migration.attachments_copied, 1);
+            assert_eq!(read_attachment(&target, &saved.file_name)?, b"png");
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        cleanup_temp_dir(&target);
+        result.unwrap();
+    }
+
+    #[test]
+    fn garbage_collection_keeps_referenced_and_recent_attachments() {
+        let dir = make_temp_dir("gc");
+        let result = (|| -> Result<(), String> {
+            let kept = save_attachment(&dir, None, None, "kept.png", b"kept")?;
+            let orphan = save_attachment(&dir, None, None, "orphan.png", b"orphan")?;
+            let recent = save_attachment(&dir, None, None, "recent.png", b"recent")?;
+            auto_save_markdown_note(
+                &dir,
+                &payload("note-a", &format!("![kept]({})", kept.link_target), None),
+            )?;
+            age_attachment(&dir, &kept.file_name)?;
+            age_attachment(&dir, &orphan.file_name)?;
+
+            let unreferenced = find_unreferenced_attachments(&dir)?;
+            assert_eq!(
+                unreferenced
+                    .iter()
+                    .map(|attachment| attachment.file_name.as_str())
+                    .collect::<Vec<_>>(),
+                vec![orphan.file_name.as_str()]
+            );
+
+            let removed = remove_unreferenced_attachments(
+                &dir,
+                &[
+                    kept.file_name.clone(),
+                    orphan.file_name.clone(),
+                    recent.file_name,
+                ],
+                |path| fs::remove_file(path).map_err(|e| e.to_string()),
+            )?;
+            assert_eq!(removed, vec![orphan.file_name.clone()]);
+            assert!(read_attachment(&dir, &orphan.file_name).is_err());
+            assert!(read_attachment(&dir, &kept.file_name).is_ok());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn encrypted_notes_pin_attachments_and_refuse_new_ones() {
+        let dir = make_temp_dir("encrypted");
+        let result = (|| -> Result<(), String> {
+            let orphan = save_attachment(&dir, None, None, "orphan.png", b"orphan")?;
+            age_attachment(&dir, &orphan.file_name)?;
+            auto_save_markdown_note(&dir, &payload("note-a", "secret", None))?;
+            auto_save_markdown_note(
+                &dir,
+                &payload(
+                    "note-b",
+                    &format!("![orphan]({})", orphan.link_target),
+                    None,
+                ),
+            )?;
+            assert!(encrypt_note(&dir, "note-b", "correct horse")
+                .unwrap_err()
+                .contains("attachments"));
+
+            encrypt_note(&dir, "note-a", "correct horse")?;
+            assert!(
+                save_attachment(&dir, Some("note-a"), None, "shot.png", b"png")
+                    .unwrap_err()
+                    .contains("encrypted")
+            );
+            assert!(find_unreferenced_attachments(&dir)?.is_empty());
+            let removed = remove_unreferenced_attachments(
+                &dir,
+                std::slice::from_ref(&orphan.file_name),
+                |path| fs::remove_file(path).map_err(|e| e.to_string()),
+            )?;
+            assert!(removed.is_empty());
+            assert!(read_attachment(&dir, &orphan.file_name).is_ok());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+}
@@ -9,12 +9,34 @@ use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
 use tauri_plugin_dialog::DialogExt;
 
 use crate::atomic_file;
+use crate::attachments::{self, AttachmentInfo, SavedAttachment};
 use crate::config_manager;
 use crate::config_manager::{LibrarySource, LocalAutoSaveDirState};
+use crate::daily_notes::{self, DailyNote, DailyNoteDirection, DailyNoteSettings};
+use crate::enex_import::{self, EnexImportResult};
+use crate::fallback_sync::{
+    self, FallbackChange, FallbackChangesSummary, FallbackReconcileResult, ReconcileOutcome,
+};
 use crate::file_manager::{
-    self, AutoSavePayload, AutoSaveResult, FolderDeleteResult, LoadedNote, NoteLoadIssue,
-    NoteLoadState,
+    self, AutoSavePayload, AutoSaveResult, ExternalFileFormat, ExternalFileVersion,
+    ExternalLibraryChanges, ExternalSaveResult, FolderDeleteResult, LibraryCheckReport,
+    LibraryIssueKind, LibraryRepairResult, LoadedNote, NoteBatchOperation, NoteBatchResult,
+    NoteLoadIssue, NoteLoadState, NoteMoveResult, TrashRestoreResult,
+};
+use crate::library_backup::{self, BackupExportResult, BackupImportResult};
+use crate::library_snapshot::{
+    self, ScheduledSnapshot, SnapshotRestoreResult, SnapshotSettings, SnapshotSummary,
 };
+use crate::library_watcher::LibraryWatcher;
+use crate::note_history::{self, NoteHistoryRetention, NoteRevision, NoteRevisionSummary};
+use crate::note_links::{self, NoteLinksResult, RelinkedNote};
+use crate::note_templates::{self, NoteTemplate, TemplateNoteRequest};
+use crate::note_trash::{self, TrashSettings, TrashedItem};
+use crate::search_index::{self, NoteSearchQuery, NoteSearchResult};
+use crate::site_export::{self, SiteExportCliRequest, SiteExportOptions, SiteExportResult};
+use crate::sync_conflicts::{SyncConflict, SyncConflictResolution};
+use crate::text_encoding::TextFormat;
+use crate::vault_import::{self, VaultImportOptions, VaultImportResult};
 
 // ── State for pending update ──
 
@@ -99,7 +121,16 @@ impl CalendarWriteGuard {
     }
 }
 
+#[derive(Default)]
+pub struct LibraryWatcherState(Mutex<Option<LibraryWatcher>>);
+
 pub const OPEN_INTENT_EVENT: &str = "note:open-intent";
+pub const NOTES_EXTERNAL_ADDED_EVENT: &str = "note:external-added";
+pub const NOTES_EXTERNAL_CHANGED_EVENT: &str = "note:external-changed";
+pub const NOTES_EXTERNAL_REMOVED_EVENT: &str = "note:external-removed";
+pub const NOTES_EXTERNAL_MOVED_EVENT: &str = "note:external-moved";
+pub const NOTES_RELINKED_EVENT: &str = "note:links-rewritten";
+pub const NOTES_SYNC_CONFLICTS_EVENT: &str = "note:sync-conflicts";
 
 // ── Response types ──
 
@@ -120,6 +151,9 @@ pub struct ImportedFile {
     title: String,
     content: String,
     file_path: String,
+    file_format: ExternalFileFormat,
+    text_format: TextFormat,
+    version: ExternalFileVersion,
 }
 
 #[derive(Clone, Serialize)]
@@ -152,6 +186,9 @@ pub struct CloudSyncStatus {
     active_source: String,
     resolved_source: Option<String>,
     cloud_unavailable: bool,
+    /// Changes written to the local library while the cloud library was
+    /// unreachable, waiting for `cmd_cloud_reconcile_fallback`.
+    fallback_changes: FallbackChangesSummary,
 }
 
 #[derive(Clone, Serialize)]
@@ -180,6 +217,15 @@ pub struct CalendarLoadResult {
     error: Option<String>,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyNoteOpenPayload {
+    date_key: String,
+    loaded_from: String,
+    #[serde(default)]
+    utc_offset_minutes: i32,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CalendarSavePayload {
@@ -212,6 +258,7 @@ pub struct NoteLoadResult {
     issues: Vec<NoteLoadIssue>,
     index_source_path: Option<String>,
     index_backup_path: Option<String>,
+    conflicts: Vec<SyncConflict>,
 }
 
 #[derive(Deserialize)]
@@ -452,6 +499,34 @@ fn resolve_note_library_mutation_dir(
     )
 }
 
+/// Runs `change` against `dir`, a library a note command is about to change.
+/// When that is the local library standing in for an unreachable cloud
+/// library, every note and the calendar it touched are recorded so they can
+/// be reconciled once the cloud library is back.
+fn record_fallback_changes<T>(
+    dir: &Path,
+    loaded_from: ResolvedStorageSource,
+    change: impl FnOnce() -> T,
+) -> T {
+    if loaded_from != ResolvedStorageSource::LocalFallback {
+        return change();
+    }
+    let before = match fallback_sync::capture_library_state(dir) {
+        Ok(before) => Some(before),
+        Err(e) => {
+            tracing::warn!("Failed to read the fallback library before a change: {}", e);
+            None
+        }
+    };
+    let result = change();
+    if let Some(before) = before {
+        if let Err(e) = fallback_sync::record_library_changes(dir, &before) {
+            tracing::warn!("Failed to record fallback library changes: {}", e);
+        }
+    }
+    result
+}
+
 fn select_note_library_mutation_dir<F>(
     local_dir: F,
     cloud_dir: Option<PathBuf>,
@@ -980,6 +1055,70 @@ fn write_calendar_data(dir: &Path, data: &str) -> Result<(), String> {
     write_calendar_data_with_root(&trusted_root, data)
 }
 
+const EMPTY_CALENDAR_DATA: &str = r#"{"version":4,"todos":{},"inbox":[],"noteLinks":{}}"#;
+
+/// Adds a note to a day's `noteLinks`, returning the new calendar JSON or
+/// `None` when the note is already linked.
+fn add_calendar_note_link(
+    data: &str,
+    date_key: &str,
+    note_id: &str,
+) -> Result<Option<String>, String> {
+    validate_calendar_data_for_confirmation(data)?;
+    let mut parsed: serde_json::Value = serde_json::from_str(data)
+        .map_err(|error| format!("Calendar note link rejected: {}", error))?;
+    let note_links = parsed
+        .as_object_mut()
+        .map(|object| {
+            object
+                .entry("noteLinks")
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+        })
+        .and_then(serde_json::Value::as_object_mut)
+        .ok_or_else(|| "Calendar note link rejected: noteLinks must be an object.".to_string())?;
+    let day_links = note_links
+        .entry(date_key.to_string())
+        .or_insert_with(|| serde_json::Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(|| {
+            format!(
+                "Calendar note link rejected: noteLinks.{} must be an array.",
+                date_key
+            )
+        })?;
+    if day_links.iter().any(|link| link.as_str() == Some(note_id)) {
+        return Ok(None);
+    }
+    day_links.push(serde_json::Value::String(note_id.to_string()));
+    serde_json::to_string_pretty(&parsed)
+        .map(Some)
+        .map_err(|error| error.to_string())
+}
+
+fn link_calendar_note_with_root(
+    trusted_root: &file_manager::TrustedLibraryRoot,
+    date_key: &str,
+    note_id: &str,
+) -> Result<bool, String> {
+    let calendar_path = trusted_root.file_path(file_manager::CALENDAR_FILENAME, false)?;
+    let data = match fs::read_to_string(&calendar_path) {
+        Ok(data) => data,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            EMPTY_CALENDAR_DATA.to_string()
+        }
+        Err(error) => {
+            return Err(format!("Failed to read calendar.json: {}", error));
+        }
+    };
+    match add_calendar_note_link(&data, date_key, note_id)? {
+        Some(next_data) => {
+            write_calendar_data_with_root(trusted_root, &next_data)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
 // ── Window commands ──
 
 #[tauri::command]
@@ -1008,126 +1147,921 @@ pub fn cmd_app_exit(app: AppHandle) {
     app.exit(0);
 }
 
-// ── Note commands ──
-
+// ── Note commands ──
+
+#[tauri::command]
+pub fn cmd_note_save(app: AppHandle, file_path: String, content: String) -> Result<bool, String> {
+    let library_root = resolve_effective_dir(&app)?;
+    file_manager::save_markdown_file(&library_root, std::path::Path::new(&file_path), &content)?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn cmd_note_read(app: AppHandle, file_path: String) -> Result<String, String> {
+    let library_root = resolve_effective_dir(&app)?;
+    file_manager::read_markdown_file(&library_root, std::path::Path::new(&file_path))
+}
+
+#[tauri::command]
+pub fn cmd_note_list(app: AppHandle, dir_path: String) -> Result<Vec<String>, String> {
+    let library_root = resolve_effective_dir(&app)?;
+    file_manager::list_markdown_files(&library_root, std::path::Path::new(&dir_path))
+}
+
+#[tauri::command]
+pub fn cmd_note_auto_save(
+    app: AppHandle,
+    payload: NoteAutoSavePayload,
+) -> Result<AutoSaveResult, String> {
+    let loaded_from = parse_resolved_storage_source(&payload.loaded_from)?;
+    let (_, current_source) = resolve_calendar_dir(&app)?;
+
+    if !can_save_note(loaded_from, current_source) {
+        return Err(format!(
+            "Note save rejected: loaded from {}, but current storage resolves to {}.",
+            resolved_storage_source_to_str(loaded_from),
+            resolved_storage_source_to_str(current_source)
+        ));
+    }
+
+    let target_dir = resolve_loaded_storage_dir(&app, loaded_from)?;
+    if loaded_from == ResolvedStorageSource::LocalFallback {
+        if let Err(e) = fallback_sync::record_note_change(&target_dir, &payload.note_id, false) {
+            tracing::warn!("Failed to record fallback note change: {}", e);
+        }
+    }
+    let file_payload = AutoSavePayload {
+        note_id: payload.note_id,
+        title: payload.title,
+        content: payload.content,
+        folder_path: payload.folder_path,
+        is_title_manual: payload.is_title_manual,
+        is_pinned: payload.is_pinned,
+    };
+
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let result = file_manager::auto_save_markdown_note_with_history(
+        &target_dir,
+        &file_payload,
+        &history_retention,
+    )?;
+    emit_relinked_notes(&app, &result.relinked_notes);
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn cmd_note_load_all(app: AppHandle) -> Result<NoteLoadResult, String> {
+    let (effective_dir, loaded_from) = resolve_calendar_dir(&app)?;
+    let library = file_manager::load_markdown_library(&effective_dir);
+    if library.load_state == NoteLoadState::Ready {
+        ensure_library_watcher(&app, &effective_dir);
+        purge_expired_trash_in_background(&app, &effective_dir);
+    }
+
+    Ok(NoteLoadResult {
+        notes: library.notes,
+        folders: library.folders,
+        loaded_from: resolved_storage_source_to_str(loaded_from).to_string(),
+        cloud_unavailable: loaded_from == ResolvedStorageSource::LocalFallback,
+        load_state: library.load_state,
+        issues: library.issues,
+        index_source_path: library.index_source_path,
+        index_backup_path: library.index_backup_path,
+        conflicts: library.conflicts,
+    })
+}
+
+fn purge_expired_trash_in_background(app: &AppHandle, library_dir: &Path) {
+    let settings = config_manager::get_trash_settings(app);
+    let library_dir = library_dir.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(error) = file_manager::purge_expired_trash(&library_dir, &settings) {
+            tracing::warn!("Trash auto-purge failed: {error}");
+        }
+    });
+}
+
+fn emit_external_library_changes(app: &AppHandle, changes: ExternalLibraryChanges) {
+    for issue in &changes.issues {
+        tracing::warn!("External library change skipped: {}", issue.display());
+    }
+    if !changes.added.is_empty() {
+        let _ = app.emit(NOTES_EXTERNAL_ADDED_EVENT, &changes.added);
+    }
+    if !changes.changed.is_empty() {
+        let _ = app.emit(NOTES_EXTERNAL_CHANGED_EVENT, &changes.changed);
+    }
+    if !changes.removed.is_empty() {
+        let _ = app.emit(NOTES_EXTERNAL_REMOVED_EVENT, &changes.removed);
+    }
+    if !changes.moved.is_empty() {
+        let _ = app.emit(NOTES_EXTERNAL_MOVED_EVENT, &changes.moved);
+    }
+    if !changes.conflicts.is_empty() {
+        let _ = app.emit(NOTES_SYNC_CONFLICTS_EVENT, &changes.conflicts);
+    }
+}
+
+fn emit_relinked_notes(app: &AppHandle, relinked_notes: &[RelinkedNote]) {
+    if !relinked_notes.is_empty() {
+        let _ = app.emit(NOTES_RELINKED_EVENT, relinked_notes);
+    }
+}
+
+fn ensure_library_watcher(app: &AppHandle, library_dir: &Path) {
+    let state = app.state::<LibraryWatcherState>();
+    let mut current = state.0.lock().unwrap_or_else(|error| error.into_inner());
+    let canonical_dir = fs::canonicalize(library_dir).unwrap_or_else(|_| library_dir.to_path_buf());
+    if current
+        .as_ref()
+        .is_some_and(|watcher| watcher.root() == canonical_dir)
+    {
+        return;
+    }
+
+    current.take();
+    let event_app = app.clone();
+    match LibraryWatcher::start(library_dir, move |changes| {
+        emit_external_library_changes(&event_app, changes)
+    }) {
+        Ok(watcher) => *current = Some(watcher),
+        Err(error) => tracing::warn!("Library watcher unavailable: {error}"),
+    }
+}
+
+#[tauri::command]
+pub async fn cmd_note_search(
+    app: AppHandle,
+    query: NoteSearchQuery,
+) -> Result<NoteSearchResult, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || search_index::search_notes(&effective_dir, &query))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_note_links(app: AppHandle, note_id: String) -> Result<NoteLinksResult, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || note_links::note_links(&effective_dir, &note_id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_site_export(
+    app: AppHandle,
+    output_dir: String,
+    options: SiteExportOptions,
+) -> Result<SiteExportResult, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        site_export::export_static_site(&effective_dir, Path::new(&output_dir), &options)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_library_backup_export(
+    app: AppHandle,
+    bundle_path: String,
+) -> Result<BackupExportResult, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        library_backup::export_library_backup(&effective_dir, Path::new(&bundle_path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_library_backup_import(
+    app: AppHandle,
+    bundle_path: String,
+    loaded_from: String,
+) -> Result<BackupImportResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Backup import")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            library_backup::import_library_backup(Path::new(&bundle_path), &target_dir)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_library_import_enex(
+    app: AppHandle,
+    enex_paths: Vec<String>,
+    folder_path: Option<String>,
+    loaded_from: String,
+) -> Result<EnexImportResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Evernote import")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let enex_paths: Vec<PathBuf> = enex_paths.iter().map(PathBuf::from).collect();
+        record_fallback_changes(&target_dir, loaded_from, || {
+            enex_import::import_enex_files(&enex_paths, folder_path.as_deref(), &target_dir)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_library_import_vault(
+    app: AppHandle,
+    source_dir: String,
+    options: VaultImportOptions,
+    loaded_from: String,
+) -> Result<VaultImportResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Vault import")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            vault_import::import_vault(Path::new(&source_dir), &options, &target_dir)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_library_resolve_sync_conflict(
+    app: AppHandle,
+    note_id: String,
+    conflict_path: String,
+    resolution: SyncConflictResolution,
+    loaded_from: String,
+) -> Result<(), String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Conflict resolution")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            file_manager::resolve_sync_conflict(
+                &target_dir,
+                &note_id,
+                Path::new(&conflict_path),
+                &resolution,
+                &history_retention,
+            )
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// ── Attachment commands ──
+
+/// Serves `hwan-asset://localhost/<name>` to the webview. Only
+/// content-addressed attachment names resolve; everything else is a 404.
+pub fn serve_attachment(
+    app: &AppHandle,
+    request: &tauri::http::Request<Vec<u8>>,
+) -> tauri::http::Response<Vec<u8>> {
+    let file_name = request.uri().path().trim_start_matches('/');
+    let bytes = if request.method() == tauri::http::Method::GET {
+        resolve_effective_dir(app)
+            .and_then(|dir| attachments::read_attachment(&dir, file_name))
+            .map_err(|error| tracing::debug!("Attachment request rejected: {error}"))
+            .ok()
+    } else {
+        None
+    };
+    let builder = tauri::http::Response::builder()
+        .header("X-Content-Type-Options", "nosniff")
+        .header(
+            "Content-Security-Policy",
+            "default-src 'none'; style-src 'unsafe-inline'",
+        );
+    let response = match bytes {
+        Some(bytes) => builder
+            .status(200)
+            .header("Content-Type", attachments::attachment_mime_type(file_name))
+            .header("Cache-Control", "private, max-age=31536000, immutable")
+            .body(bytes),
+        None => builder.status(404).body(Vec::new()),
+    };
+    response.unwrap_or_else(|_| tauri::http::Response::new(Vec::new()))
+}
+
+#[tauri::command]
+pub async fn cmd_attachment_save(
+    app: AppHandle,
+    note_id: Option<String>,
+    folder_path: Option<String>,
+    file_name: String,
+    data: Vec<u8>,
+    loaded_from: String,
+) -> Result<SavedAttachment, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Attachment save")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        attachments::save_attachment(
+            &target_dir,
+            note_id.as_deref(),
+            folder_path.as_deref(),
+            &file_name,
+            &data,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_attachment_import(
+    app: AppHandle,
+    note_id: Option<String>,
+    folder_path: Option<String>,
+    source_path: String,
+    loaded_from: String,
+) -> Result<SavedAttachment, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Attachment import")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        attachments::import_attachment(
+            &target_dir,
+            note_id.as_deref(),
+            folder_path.as_deref(),
+            Path::new(&source_path),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_attachment_find_unreferenced(
+    app: AppHandle,
+) -> Result<Vec<AttachmentInfo>, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        attachments::find_unreferenced_attachments(&effective_dir)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_attachment_remove_unreferenced(
+    app: AppHandle,
+    file_names: Vec<String>,
+    loaded_from: String,
+) -> Result<Vec<String>, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Attachment cleanup")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        attachments::remove_unreferenced_attachments(&target_dir, &file_names, |path| {
+            trash::delete(path).map_err(|e| e.to_string())
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_library_check(app: AppHandle) -> Result<LibraryCheckReport, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || file_manager::check_library(&effective_dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_library_repair(
+    app: AppHandle,
+    kinds: Vec<LibraryIssueKind>,
+    loaded_from: String,
+) -> Result<LibraryRepairResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Library repair")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            file_manager::repair_library(&target_dir, &kinds, |path| {
+                trash::delete(path).map_err(|e| e.to_string())
+            })
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Runs a `--export-site` request from the command line against the library
+/// the GUI would open, unless the request names one with `--library`. It runs
+/// before the app starts, so the library is found through the config in
+/// `config_dir`.
+pub fn export_site_from_cli(
+    config_dir: &Path,
+    request: SiteExportCliRequest,
+) -> Result<SiteExportResult, String> {
+    let library_dir = match request.library_dir {
+        Some(dir) => dir,
+        None => {
+            let documents = dirs::document_dir().unwrap_or_else(|| PathBuf::from("."));
+            let location = config_manager::get_library_location(
+                config_dir,
+                &file_manager::get_auto_save_dir(&documents),
+            );
+            let local_dir = || match location.local_dir {
+                LocalAutoSaveDirState::Unset(path) | LocalAutoSaveDirState::Available(path) => {
+                    Ok(path)
+                }
+                LocalAutoSaveDirState::Unavailable(path) => {
+                    Err(configured_local_dir_unavailable_error(&path))
+                }
+            };
+            let cloud_dir = match location.active_source {
+                LibrarySource::Cloud => location.cloud_notes_dir,
+                LibrarySource::Local => None,
+            };
+            resolve_storage_dir_with_local_dir(local_dir, cloud_dir, location.active_source)?.0
+        }
+    };
+    site_export::export_static_site(
+        &library_dir,
+        &request.output_dir,
+        &SiteExportOptions {
+            folder_path: request.folder_path,
+        },
+    )
+}
+
+#[tauri::command]
+pub fn cmd_folder_list(app: AppHandle) -> Result<Vec<String>, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    file_manager::list_folders(&effective_dir)
+}
+
+#[tauri::command]
+pub fn cmd_folder_create(
+    app: AppHandle,
+    folder_path: String,
+    loaded_from: String,
+) -> Result<Vec<String>, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Folder creation")?;
+    file_manager::create_folder(&target_dir, &folder_path)
+}
+
+#[tauri::command]
+pub fn cmd_folder_rename(
+    app: AppHandle,
+    from: String,
+    to: String,
+    loaded_from: String,
+) -> Result<Vec<String>, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Folder rename")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let result = record_fallback_changes(&target_dir, loaded_from, || {
+        file_manager::rename_folder(&target_dir, &from, &to, &history_retention)
+    })?;
+    emit_relinked_notes(&app, &result.relinked_notes);
+    Ok(result.folders)
+}
+
+#[tauri::command]
+pub fn cmd_folder_delete(
+    app: AppHandle,
+    folder_path: String,
+    loaded_from: String,
+) -> Result<FolderDeleteResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Folder deletion")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let result = record_fallback_changes(&target_dir, loaded_from, || {
+        file_manager::delete_folder(&target_dir, &folder_path, &history_retention)
+    })?;
+    emit_relinked_notes(&app, &result.relinked_notes);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn cmd_note_delete(
+    app: AppHandle,
+    note_id: String,
+    loaded_from: String,
+) -> Result<bool, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Note deletion")?;
+    let in_fallback =
+        parse_resolved_storage_source(&loaded_from)? == ResolvedStorageSource::LocalFallback;
+    tauri::async_runtime::spawn_blocking(move || {
+        // Record the note's current version first; a deletion alone has no base.
+        let record = |deleted| {
+            if let Err(e) = fallback_sync::record_note_change(&target_dir, &note_id, deleted) {
+                tracing::warn!("Failed to record fallback note change: {}", e);
+            }
+        };
+        if in_fallback {
+            record(false);
+        }
+        let trashed = file_manager::trash_note(&target_dir, &note_id)?;
+        if in_fallback && trashed {
+            record(true);
+        }
+        Ok(trashed)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_note_move(
+    app: AppHandle,
+    note_ids: Vec<String>,
+    folder_path: Option<String>,
+    loaded_from: String,
+) -> Result<NoteMoveResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Note move")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            file_manager::move_notes(
+                &target_dir,
+                &note_ids,
+                folder_path.as_deref(),
+                &history_retention,
+            )
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    emit_relinked_notes(&app, &result.relinked_notes);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn cmd_note_batch(
+    app: AppHandle,
+    operations: Vec<NoteBatchOperation>,
+    loaded_from: String,
+) -> Result<NoteBatchResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Note batch")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            file_manager::apply_note_batch(&target_dir, &operations, &history_retention)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    emit_relinked_notes(&app, &result.relinked_notes);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn cmd_note_encrypt(
+    app: AppHandle,
+    note_id: String,
+    passphrase: String,
+    loaded_from: String,
+) -> Result<LoadedNote, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Note encryption")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            file_manager::encrypt_note(&target_dir, &note_id, &passphrase)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_note_decrypt(
+    app: AppHandle,
+    note_id: String,
+    passphrase: String,
+    loaded_from: String,
+) -> Result<LoadedNote, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Note decryption")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            file_manager::decrypt_note(&target_dir, &note_id, &passphrase)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_note_unlock(
+    app: AppHandle,
+    note_id: String,
+    passphrase: Option<String>,
+) -> Result<LoadedNote, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        file_manager::unlock_note(&effective_dir, &note_id, passphrase.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn cmd_note_lock(app: AppHandle, note_id: String) -> Result<bool, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    file_manager::lock_note(&effective_dir, &note_id)
+}
+
 #[tauri::command]
-pub fn cmd_note_save(app: AppHandle, file_path: String, content: String) -> Result<bool, String> {
-    let library_root = resolve_effective_dir(&app)?;
-    file_manager::save_markdown_file(&library_root, std::path::Path::new(&file_path), &content)?;
-    Ok(true)
+pub fn cmd_template_list(app: AppHandle) -> Result<Vec<NoteTemplate>, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    note_templates::list_templates(&effective_dir)
 }
 
 #[tauri::command]
-pub fn cmd_note_read(app: AppHandle, file_path: String) -> Result<String, String> {
-    let library_root = resolve_effective_dir(&app)?;
-    file_manager::read_markdown_file(&library_root, std::path::Path::new(&file_path))
+pub fn cmd_template_save(
+    app: AppHandle,
+    name: String,
+    markdown: String,
+    loaded_from: String,
+) -> Result<NoteTemplate, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Template save")?;
+    note_templates::save_template(&target_dir, &name, &markdown)
 }
 
 #[tauri::command]
-pub fn cmd_note_list(app: AppHandle, dir_path: String) -> Result<Vec<String>, String> {
-    let library_root = resolve_effective_dir(&app)?;
-    file_manager::list_markdown_files(&library_root, std::path::Path::new(&dir_path))
+pub async fn cmd_note_create_from_template(
+    app: AppHandle,
+    request: TemplateNoteRequest,
+    loaded_from: String,
+) -> Result<LoadedNote, String> {
+    let target_dir =
+        resolve_note_library_mutation_dir(&app, &loaded_from, "Note creation from template")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            note_templates::create_note_from_template(&target_dir, &request, &history_retention)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-pub fn cmd_note_auto_save(
+pub async fn cmd_daily_note_open(
     app: AppHandle,
-    payload: NoteAutoSavePayload,
-) -> Result<AutoSaveResult, String> {
+    payload: DailyNoteOpenPayload,
+) -> Result<DailyNote, String> {
+    let target_dir =
+        resolve_note_library_mutation_dir(&app, &payload.loaded_from, "Daily note creation")?;
     let loaded_from = parse_resolved_storage_source(&payload.loaded_from)?;
-    let (_, current_source) = resolve_calendar_dir(&app)?;
-
-    if !can_save_note(loaded_from, current_source) {
-        return Err(format!(
-            "Note save rejected: loaded from {}, but current storage resolves to {}.",
-            resolved_storage_source_to_str(loaded_from),
-            resolved_storage_source_to_str(current_source)
-        ));
-    }
-
-    let target_dir = resolve_loaded_storage_dir(&app, loaded_from)?;
-    let file_payload = AutoSavePayload {
-        note_id: payload.note_id,
-        title: payload.title,
-        content: payload.content,
-        folder_path: payload.folder_path,
-        is_title_manual: payload.is_title_manual,
-        is_pinned: payload.is_pinned,
-    };
-
-    file_manager::auto_save_markdown_note(&target_dir, &file_payload)
+    let settings = config_manager::get_daily_note_settings(&app);
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            let mut daily_note = daily_notes::open_daily_note(
+                &target_dir,
+                &payload.date_key,
+                &settings,
+                payload.utc_offset_minutes,
+                &history_retention,
+            )?;
+
+            // The note exists either way; a blocked calendar only leaves it unlinked.
+            let trusted_root = file_manager::TrustedLibraryRoot::open(&target_dir)?;
+            let calendar_path = trusted_root.file_path(file_manager::CALENDAR_FILENAME, false)?;
+            let mut linked = false;
+            let link_result =
+                handle
+                    .state::<CalendarWriteGuard>()
+                    .write_if_allowed(&calendar_path, || {
+                        linked = link_calendar_note_with_root(
+                            &trusted_root,
+                            &daily_note.date_key,
+                            &daily_note.note.note_id,
+                        )?;
+                        Ok(())
+                    });
+            if let Err(error) = link_result {
+                tracing::warn!("Daily note was not linked in the calendar: {error}");
+            }
+            daily_note.calendar_linked = linked;
+            Ok(daily_note)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-pub fn cmd_note_load_all(app: AppHandle) -> Result<NoteLoadResult, String> {
-    let (effective_dir, loaded_from) = resolve_calendar_dir(&app)?;
-    let library = file_manager::load_markdown_library(&effective_dir);
-
-    Ok(NoteLoadResult {
-        notes: library.notes,
-        folders: library.folders,
-        loaded_from: resolved_storage_source_to_str(loaded_from).to_string(),
-        cloud_unavailable: loaded_from == ResolvedStorageSource::LocalFallback,
-        load_state: library.load_state,
-        issues: library.issues,
-        index_source_path: library.index_source_path,
-        index_backup_path: library.index_backup_path,
+pub async fn cmd_daily_note_adjacent(
+    app: AppHandle,
+    date_key: String,
+    direction: DailyNoteDirection,
+) -> Result<Option<DailyNote>, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    let settings = config_manager::get_daily_note_settings(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        daily_notes::adjacent_daily_note(&effective_dir, &date_key, direction, &settings)
     })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-pub fn cmd_folder_list(app: AppHandle) -> Result<Vec<String>, String> {
+pub async fn cmd_trash_list(app: AppHandle) -> Result<Vec<TrashedItem>, String> {
     let effective_dir = resolve_effective_dir(&app)?;
-    file_manager::list_folders(&effective_dir)
+    let settings = config_manager::get_trash_settings(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(error) = file_manager::purge_expired_trash(&effective_dir, &settings) {
+            tracing::warn!("Trash auto-purge failed: {error}");
+        }
+        note_trash::list_trash(&effective_dir)
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-pub fn cmd_folder_create(
+pub async fn cmd_trash_restore(
     app: AppHandle,
-    folder_path: String,
+    trash_id: String,
     loaded_from: String,
-) -> Result<Vec<String>, String> {
-    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Folder creation")?;
-    file_manager::create_folder(&target_dir, &folder_path)
+) -> Result<TrashRestoreResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Trash restore")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&target_dir, loaded_from, || {
+            file_manager::restore_from_trash(&target_dir, &trash_id, &history_retention)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    emit_relinked_notes(&app, &result.relinked_notes);
+    Ok(result)
 }
 
 #[tauri::command]
-pub fn cmd_folder_rename(
+pub async fn cmd_trash_purge(
     app: AppHandle,
-    from: String,
-    to: String,
+    trash_ids: Vec<String>,
     loaded_from: String,
 ) -> Result<Vec<String>, String> {
-    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Folder rename")?;
-    file_manager::rename_folder(&target_dir, &from, &to)
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Trash purge")?;
+    tauri::async_runtime::spawn_blocking(move || file_manager::purge_trash(&target_dir, &trash_ids))
+        .await
+        .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-pub fn cmd_folder_delete(
+pub fn cmd_note_history_list(
     app: AppHandle,
-    folder_path: String,
-    loaded_from: String,
-) -> Result<FolderDeleteResult, String> {
-    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Folder deletion")?;
-    file_manager::delete_folder(&target_dir, &folder_path)
+    note_id: String,
+) -> Result<Vec<NoteRevisionSummary>, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    note_history::list_note_revisions(&effective_dir, &note_id)
 }
 
 #[tauri::command]
-pub async fn cmd_note_delete(
+pub fn cmd_note_history_read(
+    app: AppHandle,
+    note_id: String,
+    revision_id: String,
+) -> Result<NoteRevision, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    note_history::read_note_revision(&effective_dir, &note_id, &revision_id)
+}
+
+#[tauri::command]
+pub fn cmd_note_history_restore(
     app: AppHandle,
     note_id: String,
+    revision_id: String,
     loaded_from: String,
-) -> Result<bool, String> {
-    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Note deletion")?;
+) -> Result<AutoSaveResult, String> {
+    let target_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Revision restore")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let result = record_fallback_changes(&target_dir, loaded_from, || {
+        note_history::restore_note_revision(&target_dir, &note_id, &revision_id, &history_retention)
+    })?;
+    emit_relinked_notes(&app, &result.relinked_notes);
+    Ok(result)
+}
+
+// ── Snapshot commands ──
+
+const SNAPSHOT_SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Background loop that snapshots the effective library whenever one is due.
+/// After an attempt it waits a full interval, so an unchanged library is not
+/// re-read every tick.
+pub fn run_snapshot_scheduler(app: AppHandle) {
+    let mut next_check = 0;
+    loop {
+        std::thread::sleep(SNAPSHOT_SCHEDULER_TICK);
+        let settings = config_manager::get_snapshot_settings(&app);
+        let now = file_manager::now_millis();
+        if !settings.enabled || now < next_check {
+            continue;
+        }
+        let Ok(library_dir) = resolve_effective_dir(&app) else {
+            continue;
+        };
+        let target_dir = config_manager::get_snapshot_dir(&app, &settings);
+        match library_snapshot::take_scheduled_snapshot(&library_dir, &target_dir, &settings, now) {
+            Ok(ScheduledSnapshot::NotDue) => {}
+            Ok(ScheduledSnapshot::Unchanged) => next_check = now + settings.interval_millis(),
+            Ok(ScheduledSnapshot::Created(summary)) => {
+                tracing::info!("Library snapshot {} created", summary.snapshot_id);
+                next_check = now + settings.interval_millis();
+            }
+            Err(error) => {
+                tracing::warn!("Scheduled library snapshot failed: {}", error);
+                next_check = now + settings.interval_millis();
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn cmd_snapshot_list(app: AppHandle) -> Result<Vec<SnapshotSummary>, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    let settings = config_manager::get_snapshot_settings(&app);
+    let target_dir = config_manager::get_snapshot_dir(&app, &settings);
     tauri::async_runtime::spawn_blocking(move || {
-        file_manager::delete_note_file_and_index(&target_dir, &note_id, |path| {
-            trash::delete(path).map_err(|e| e.to_string())
+        library_snapshot::list_snapshots(&effective_dir, &target_dir)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_snapshot_create(app: AppHandle) -> Result<SnapshotSummary, String> {
+    let effective_dir = resolve_effective_dir(&app)?;
+    let settings = config_manager::get_snapshot_settings(&app);
+    let target_dir = config_manager::get_snapshot_dir(&app, &settings);
+    tauri::async_runtime::spawn_blocking(move || {
+        library_snapshot::create_snapshot(&effective_dir, &target_dir, &settings)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn cmd_snapshot_restore_library(
+    app: AppHandle,
+    snapshot_id: String,
+    loaded_from: String,
+) -> Result<SnapshotRestoreResult, String> {
+    let library_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Snapshot restore")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let settings = config_manager::get_snapshot_settings(&app);
+    let target_dir = config_manager::get_snapshot_dir(&app, &settings);
+    tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&library_dir, loaded_from, || {
+            library_snapshot::restore_library_snapshot(
+                &library_dir,
+                &target_dir,
+                &snapshot_id,
+                &settings,
+            )
         })
     })
     .await
     .map_err(|e| e.to_string())?
 }
 
+#[tauri::command]
+pub async fn cmd_snapshot_restore_notes(
+    app: AppHandle,
+    snapshot_id: String,
+    note_ids: Vec<String>,
+    loaded_from: String,
+) -> Result<Vec<AutoSaveResult>, String> {
+    let library_dir = resolve_note_library_mutation_dir(&app, &loaded_from, "Snapshot restore")?;
+    let loaded_from = parse_resolved_storage_source(&loaded_from)?;
+    let settings = config_manager::get_snapshot_settings(&app);
+    let target_dir = config_manager::get_snapshot_dir(&app, &settings);
+    let history_retention = config_manager::get_note_history_retention(&app);
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        record_fallback_changes(&library_dir, loaded_from, || {
+            library_snapshot::restore_snapshot_notes(
+                &library_dir,
+                &target_dir,
+                &snapshot_id,
+                &note_ids,
+                &history_retention,
+            )
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    for result in &results {
+        emit_relinked_notes(&app, &result.relinked_notes);
+    }
+    Ok(results)
+}
+
 // ── Calendar commands ──
 
 #[tauri::command]
@@ -1256,6 +2190,49 @@ pub fn cmd_calendar_confirm_loaded(
     Ok(())
 }
 
+fn record_fallback_calendar_change(dir: &Path, loaded_from: ResolvedStorageSource) {
+    if loaded_from != ResolvedStorageSource::LocalFallback {
+        return;
+    }
+    if let Err(e) = fallback_sync::record_calendar_change(dir) {
+        tracing::warn!("Failed to record fallback calendar change: {}", e);
+    }
+}
+
+/// Pushes the local calendar into the cloud library after fallback edits,
+/// keeping it as a backup copy there when the cloud calendar changed too.
+fn reconcile_fallback_calendar(
+    local_dir: &Path,
+    cloud_dir: &Path,
+    change: &FallbackChange,
+) -> Result<(ReconcileOutcome, Option<PathBuf>), String> {
+    let local_root = file_manager::TrustedLibraryRoot::open(local_dir)?;
+    let local_path = local_root.file_path(file_manager::CALENDAR_FILENAME, false)?;
+    if !local_path.exists() {
+        return Ok((ReconcileOutcome::Unchanged, None));
+    }
+    if file_manager::migrate_calendar_file(local_dir, cloud_dir)? {
+        return Ok((ReconcileOutcome::Copied, None));
+    }
+
+    let local_bytes =
+        file_manager::read_trusted_file_bytes(&local_root, &local_path, "read_fallback_calendar")?;
+    let cloud_root = file_manager::TrustedLibraryRoot::open(cloud_dir)?;
+    let cloud_path = cloud_root.file_path(file_manager::CALENDAR_FILENAME, true)?;
+    let cloud_bytes =
+        file_manager::read_trusted_file_bytes(&cloud_root, &cloud_path, "read_cloud_calendar")?;
+    if cloud_bytes == local_bytes {
+        return Ok((ReconcileOutcome::Unchanged, None));
+    }
+    if change.base_digest.as_deref() == Some(file_manager::sha256_hex(&cloud_bytes).as_str()) {
+        let data = String::from_utf8(local_bytes).map_err(|e| e.to_string())?;
+        write_calendar_data_with_root(&cloud_root, &data)?;
+        return Ok((ReconcileOutcome::Updated, None));
+    }
+    let copy_path = write_unique_calendar_backup_with_root(&cloud_root, &local_bytes)?;
+    Ok((ReconcileOutcome::Conflict, Some(copy_path)))
+}
+
 #[tauri::command]
 pub fn cmd_calendar_save(app: AppHandle, payload: CalendarSavePayload) -> Result<(), String> {
     let loaded_from = parse_resolved_storage_source(&payload.loaded_from)?;
@@ -1270,6 +2247,7 @@ pub fn cmd_calendar_save(app: AppHandle, payload: CalendarSavePayload) -> Result
     }
 
     let dir = resolve_loaded_storage_dir(&app, loaded_from)?;
+    record_fallback_calendar_change(&dir, loaded_from);
     let trusted_root = file_manager::TrustedLibraryRoot::open(&dir)?;
     let calendar_path = trusted_root.file_path(file_manager::CALENDAR_FILENAME, false)?;
     app.state::<CalendarWriteGuard>()
@@ -1293,6 +2271,7 @@ pub fn cmd_calendar_reset(app: AppHandle, payload: CalendarSavePayload) -> Resul
     }
 
     let dir = resolve_loaded_storage_dir(&app, loaded_from)?;
+    record_fallback_calendar_change(&dir, loaded_from);
     let trusted_root = file_manager::TrustedLibraryRoot::open(&dir)?;
     let calendar_path = trusted_root.file_path(file_manager::CALENDAR_FILENAME, false)?;
     app.state::<CalendarWriteGuard>().reset(&calendar_path, || {
@@ -1352,14 +2331,21 @@ pub fn cmd_session_load(app: AppHandle) -> SessionData {
 }
 
 #[tauri::command]
-pub fn cmd_note_import_txt(window: WebviewWindow) -> Result<Option<Vec<ImportedFile>>, String> {
-    let result = window
+pub fn cmd_note_import_txt(
+    app: AppHandle,
+    window: WebviewWindow,
+) -> Result<Option<Vec<ImportedFile>>, String> {
+    let text_extensions = config_manager::get_text_extensions(&app);
+    let text_filter: Vec<&str> = text_extensions.iter().map(String::as_str).collect();
+    let mut dialog = window
         .dialog()
         .file()
         .set_title("텍스트 파일 가져오기")
-        .add_filter("Text Files", &["txt"])
-        .add_filter("All Files", &["*"])
-        .blocking_pick_files();
+        .add_filter("Markdown", &["md", "markdown"]);
+    if !text_filter.is_empty() {
+        dialog = dialog.add_filter("Text Files", &text_filter);
+    }
+    let result = dialog.add_filter("All Files", &["*"]).blocking_pick_files();
 
     let paths = match result {
         Some(paths) => paths,
@@ -1369,31 +2355,42 @@ pub fn cmd_note_import_txt(window: WebviewWindow) -> Result<Option<Vec<ImportedF
     let mut imported = Vec::new();
     for file_response in paths {
         let path_buf = file_response.into_path().map_err(|e| e.to_string())?;
-        let content = file_manager::read_text_file(&path_buf)?;
-        let title = file_manager::title_from_filename(&path_buf);
-        imported.push(ImportedFile {
-            title,
-            content,
-            file_path: path_buf.to_string_lossy().to_string(),
-        });
+        let file_format = file_manager::external_file_format(&path_buf, &text_extensions)
+            .unwrap_or(ExternalFileFormat::Txt);
+        imported.push(read_external_file(&path_buf, file_format, None)?);
     }
 
     Ok(Some(imported))
 }
 
-#[tauri::command]
-pub fn cmd_note_read_external_txt(file_path: String) -> Result<ImportedFile, String> {
-    let normalized = file_manager::normalize_external_txt_path(&file_path, None)?;
-    let content = file_manager::read_text_file(&normalized)?;
-    let title = file_manager::title_from_filename(&normalized);
-
+fn read_external_file(
+    path: &Path,
+    file_format: ExternalFileFormat,
+    encoding: Option<&str>,
+) -> Result<ImportedFile, String> {
+    let (content, text_format, version) = file_manager::read_text_file(path, encoding)?;
     Ok(ImportedFile {
-        title,
+        title: file_manager::title_from_filename(path),
         content,
-        file_path: normalized.to_string_lossy().to_string(),
+        file_path: path.to_string_lossy().to_string(),
+        file_format,
+        text_format,
+        version,
     })
 }
 
+#[tauri::command]
+pub fn cmd_note_read_external_txt(
+    app: AppHandle,
+    file_path: String,
+    encoding: Option<String>,
+) -> Result<ImportedFile, String> {
+    let text_extensions = config_manager::get_text_extensions(&app);
+    let (normalized, file_format) =
+        file_manager::normalize_external_document_path(&file_path, None, &text_extensions)?;
+    read_external_file(&normalized, file_format, encoding.as_deref())
+}
+
 #[tauri::command]
 pub fn cmd_note_drain_open_intents(state: tauri::State<PendingOpenIntents>) -> Vec<String> {
     let mut queue = state.0.lock().unwrap();
@@ -1446,9 +2443,18 @@ pub fn cmd_note_pick_save_path(
 }
 
 #[tauri::command]
-pub fn cmd_note_save_txt(file_path: String, content: String) -> Result<bool, String> {
-    file_manager::save_text_file(std::path::Path::new(&file_path), &content)?;
-    Ok(true)
+pub fn cmd_note_save_txt(
+    file_path: String,
+    content: String,
+    text_format: Option<TextFormat>,
+    expected_version: Option<ExternalFileVersion>,
+) -> Result<ExternalSaveResult, String> {
+    file_manager::save_text_file(
+        std::path::Path::new(&file_path),
+        &content,
+        &text_format.unwrap_or_default(),
+        expected_version.as_ref(),
+    )
 }
 
 // ── Settings commands ──
@@ -1493,6 +2499,76 @@ pub fn cmd_settings_get_autosave_dir(app: AppHandle) -> AutoSaveDirInfo {
     build_auto_save_dir_info(state)
 }
 
+#[tauri::command]
+pub fn cmd_settings_get_note_history(app: AppHandle) -> NoteHistoryRetention {
+    config_manager::get_note_history_retention(&app)
+}
+
+#[tauri::command]
+pub fn cmd_settings_set_note_history(
+    app: AppHandle,
+    retention: NoteHistoryRetention,
+) -> Result<NoteHistoryRetention, String> {
+    config_manager::set_note_history_retention(&app, retention)?;
+    Ok(config_manager::get_note_history_retention(&app))
+}
+
+#[tauri::command]
+pub fn cmd_settings_get_trash(app: AppHandle) -> TrashSettings {
+    config_manager::get_trash_settings(&app)
+}
+
+#[tauri::command]
+pub fn cmd_settings_set_trash(
+    app: AppHandle,
+    settings: TrashSettings,
+) -> Result<TrashSettings, String> {
+    config_manager::set_trash_settings(&app, settings)?;
+    Ok(config_manager::get_trash_settings(&app))
+}
+
+#[tauri::command]
+pub fn cmd_settings_get_daily_notes(app: AppHandle) -> DailyNoteSettings {
+    config_manager::get_daily_note_settings(&app)
+}
+
+#[tauri::command]
+pub fn cmd_settings_set_daily_notes(
+    app: AppHandle,
+    settings: DailyNoteSettings,
+) -> Result<DailyNoteSettings, String> {
+    config_manager::set_daily_note_settings(&app, settings)?;
+    Ok(config_manager::get_daily_note_settings(&app))
+}
+
+#[tauri::command]
+pub fn cmd_settings_get_text_extensions(app: AppHandle) -> Vec<String> {
+    config_manager::get_text_extensions(&app)
+}
+
+#[tauri::command]
+pub fn cmd_settings_set_text_extensions(
+    app: AppHandle,
+    extensions: Vec<String>,
+) -> Result<Vec<String>, String> {
+    config_manager::set_text_extensions(&app, &extensions)?;
+    Ok(config_manager::get_text_extensions(&app))
+}
+
+#[tauri::command]
+pub fn cmd_settings_get_snapshots(app: AppHandle) -> SnapshotSettings {
+    config_manager::get_snapshot_settings(&app)
+}
+
+#[tauri::command]
+pub fn cmd_settings_set_snapshots(
+    app: AppHandle,
+    settings: SnapshotSettings,
+) -> Result<SnapshotSettings, String> {
+    config_manager::set_snapshot_settings(&app, settings)?;
+    Ok(config_manager::get_snapshot_settings(&app))
+}
+
 // ── Updater commands ──
 
 #[tauri::command]
@@ -1707,8 +2783,17 @@ pub fn cmd_shell_open_external(app: AppHandle, url: String) -> Result<(), String
 // ── Cloud sync commands ──
 
 #[tauri::command]
-pub fn cmd_cloud_detect_providers() -> Vec<config_manager::CloudProviderInfo> {
-    config_manager::detect_cloud_providers()
+pub fn cmd_cloud_detect_providers(app: AppHandle) -> Vec<config_manager::CloudProviderInfo> {
+    config_manager::detect_cloud_providers(&app)
+}
+
+#[tauri::command]
+pub fn cmd_cloud_set_custom_folder(
+    app: AppHandle,
+    dir: Option<String>,
+) -> Result<Vec<config_manager::CloudProviderInfo>, String> {
+    config_manager::set_custom_sync_folder(&app, dir.as_deref())?;
+    Ok(config_manager::detect_cloud_providers(&app))
 }
 
 #[tauri::command]
@@ -1717,10 +2802,7 @@ pub async fn cmd_cloud_sync_enable(
     provider: String,
     copy_existing: bool,
 ) -> Result<CloudSyncResult, String> {
-    let providers = config_manager::detect_cloud_providers();
-    let info = providers
-        .iter()
-        .find(|p| p.id == provider)
+    let info = config_manager::find_cloud_provider(&app, &provider)
         .ok_or_else(|| format!("Unknown provider: {}", provider))?;
 
     if !info.available {
@@ -1754,7 +2836,9 @@ pub async fn cmd_cloud_sync_enable(
         (
             file_manager::MigrationResult {
                 files_copied: 0,
+                attachments_copied: 0,
                 index_copied: false,
+                notes: Vec::new(),
             },
             false,
         )
@@ -1795,15 +2879,16 @@ pub fn cmd_cloud_sync_status(app: AppHandle) -> CloudSyncStatus {
     let cloud_unavailable = active_source == LibrarySource::Cloud
         && config_manager::get_cloud_notes_dir(&app).is_none_or(|path| !path.is_dir());
 
-    let sync_folder = if enabled {
-        let providers = config_manager::detect_cloud_providers();
-        providers
-            .into_iter()
-            .find(|p| Some(&p.id) == provider.as_ref())
-            .and_then(|p| p.sync_folder)
-    } else {
-        None
-    };
+    let sync_folder = provider
+        .as_deref()
+        .and_then(|provider| config_manager::find_cloud_provider(&app, provider))
+        .and_then(|p| p.sync_folder);
+
+    let documents = dirs::document_dir().unwrap_or_else(|| PathBuf::from("."));
+    let fallback_changes = get_calendar_local_dir(&app, &documents)
+        .and_then(|dir| fallback_sync::read_changes(&dir))
+        .map(|changes| fallback_sync::summarize(&changes))
+        .unwrap_or_default();
 
     CloudSyncStatus {
         enabled,
@@ -1813,7 +2898,53 @@ pub fn cmd_cloud_sync_status(app: AppHandle) -> CloudSyncStatus {
         resolved_source: resolved_source
             .map(|source| resolved_storage_source_to_str(source).to_string()),
         cloud_unavailable,
+        fallback_changes,
+    }
+}
+
+/// Pushes notes and calendar changes made in the local library while the
+/// cloud library was unreachable into the cloud library, once it is back.
+#[tauri::command]
+pub async fn cmd_cloud_reconcile_fallback(
+    app: AppHandle,
+) -> Result<FallbackReconcileResult, String> {
+    let (cloud_dir, source) = resolve_storage_dir(&app, false)?;
+    if source != ResolvedStorageSource::Cloud {
+        return Err("Cloud storage directory is not available.".to_string());
     }
+    let documents = dirs::document_dir().unwrap_or_else(|| PathBuf::from("."));
+    let local_dir = get_calendar_local_dir(&app, &documents)?;
+    let history_retention = config_manager::get_note_history_retention(&app);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let changes = fallback_sync::read_changes(&local_dir)?;
+        let notes = file_manager::reconcile_fallback_notes(
+            &local_dir,
+            &cloud_dir,
+            &changes.notes,
+            &history_retention,
+        )?;
+        let (calendar, calendar_copy_path) = match &changes.calendar {
+            Some(change) => {
+                let (outcome, copy_path) =
+                    reconcile_fallback_calendar(&local_dir, &cloud_dir, change)?;
+                (Some(outcome), copy_path)
+            }
+            None => (None, None),
+        };
+        fallback_sync::clear_changes(&local_dir, &changes)?;
+        Ok(FallbackReconcileResult {
+            notes,
+            calendar,
+            calendar_copy_path: calendar_copy_path.map(|path| {
+                normalize_user_visible_path(&path)
+                    .to_string_lossy()
+                    .to_string()
+            }),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
@@ -1840,18 +2971,19 @@ pub fn cmd_cloud_sync_set_active_source(
 #[cfg(test)]
 mod tests {
     use super::{
-        backup_calendar_file, build_auto_save_dir_info, calendar_recovery_copy_candidate,
-        can_save_calendar, can_save_note, configured_local_dir_unavailable_error,
-        create_unique_calendar_temp, loaded_source_writes_to_cloud,
-        preserve_calendar_recovery_copy, resolve_storage_dir_with_local_dir,
-        select_loaded_storage_dir, select_note_library_mutation_dir,
-        validate_calendar_data_for_confirmation, validate_empty_calendar_reset,
-        verify_calendar_snapshot, write_calendar_data, write_unique_calendar_backup,
-        CalendarWriteGuard, CloudSyncStatus, ResolvedStorageSource,
+        add_calendar_note_link, backup_calendar_file, build_auto_save_dir_info,
+        calendar_recovery_copy_candidate, can_save_calendar, can_save_note,
+        configured_local_dir_unavailable_error, create_unique_calendar_temp,
+        loaded_source_writes_to_cloud, preserve_calendar_recovery_copy,
+        resolve_storage_dir_with_local_dir, select_loaded_storage_dir,
+        select_note_library_mutation_dir, validate_calendar_data_for_confirmation,
+        validate_empty_calendar_reset, verify_calendar_snapshot, write_calendar_data,
+        write_unique_calendar_backup, CalendarWriteGuard, CloudSyncStatus, ResolvedStorageSource,
         MAX_CALENDAR_RECOVERY_COPY_BYTES, MAX_CALENDAR_RECOVERY_COPY_COUNT,
     };
     use crate::config_manager::{LibrarySource, LocalAutoSaveDirState};
     use crate::file_manager::{self, AutoSavePayload};
+    use crate::note_history::NoteHistoryRetention;
     use std::fs;
     use std::path::PathBuf;
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -2172,6 +3304,33 @@ mod tests {
         .is_err());
     }
 
+    #[test]
+    fn calendar_note_link_is_added_once_and_keeps_other_data() {
+        let data = r#"{"version":4,"todos":{"2026-08-11":{"items":[]}},"inbox":[],"noteLinks":{"2026-08-11":["note-1"]}}"#;
+        let linked = add_calendar_note_link(data, "2026-08-11", "daily-1")
+            .unwrap()
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&linked).unwrap();
+        assert_eq!(
+            parsed["noteLinks"]["2026-08-11"],
+            serde_json::json!(["note-1", "daily-1"])
+        );
+        assert_eq!(
+            parsed["todos"]["2026-08-11"]["items"],
+            serde_json::json!([])
+        );
+        assert_eq!(
+            add_calendar_note_link(&linked, "2026-08-11", "daily-1").unwrap(),
+            None
+        );
+        assert!(
+            add_calendar_note_link(r#"{"todos":{}}"#, "2026-08-12", "daily-2")
+                .unwrap()
+                .is_some()
+        );
+        assert!(add_calendar_note_link("{", "2026-08-12", "daily-2").is_err());
+    }
+
     #[test]
     fn calendar_reset_accepts_only_the_empty_current_schema() {
         assert!(validate_empty_calendar_reset(
@@ -2350,7 +3509,13 @@ mod tests {
             "Folder rename",
         )
         .unwrap();
-        file_manager::rename_folder(&target_dir, "alpha", "beta").unwrap();
+        file_manager::rename_folder(
+            &target_dir,
+            "alpha",
+            "beta",
+            &NoteHistoryRetention::default(),
+        )
+        .unwrap();
 
         assert_eq!(
             file_manager::list_folders(&local_dir).unwrap(),
@@ -2545,6 +3710,7 @@ mod tests {
             active_source: "cloud".to_string(),
             resolved_source: None,
             cloud_unavailable: true,
+            fallback_changes: Default::default(),
         };
 
         let serialized = serde_json::to_value(status).unwrap();
@@ -1,9 +1,18 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use quick_xml::escape::{resolve_predefined_entity, unescape_with};
+use quick_xml::events::Event;
+use quick_xml::Reader;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager};
 
+use crate::daily_notes::DailyNoteSettings;
+use crate::file_manager::{normalize_text_extensions, sha256_hex, DEFAULT_TEXT_EXTENSIONS};
+use crate::library_snapshot::SnapshotSettings;
+use crate::note_history::NoteHistoryRetention;
+use crate::note_trash::TrashSettings;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 struct AppConfig {
@@ -13,8 +22,34 @@ struct AppConfig {
     cloud_sync_provider: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     cloud_sync_source: Option<LibrarySource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    custom_sync_folder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note_history: Option<NoteHistoryRetention>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshots: Option<SnapshotSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trash: Option<TrashSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    daily_notes: Option<DailyNoteSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text_extensions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    machine_id: Option<String>,
 }
 
+/// Provider that syncs through a folder the user picks, such as a NAS share.
+const CUSTOM_PROVIDER_ID: &str = "custom";
+const CLOUD_PROVIDER_IDS: [&str; 7] = [
+    "onedrive",
+    "google_drive",
+    "dropbox",
+    "nextcloud",
+    "owncloud",
+    "syncthing",
+    CUSTOM_PROVIDER_ID,
+];
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum LibrarySource {
@@ -63,13 +98,45 @@ fn get_config_path(app: &AppHandle) -> PathBuf {
 }
 
 fn read_config(app: &AppHandle) -> AppConfig {
-    let config_path = get_config_path(app);
-    match fs::read_to_string(&config_path) {
+    read_config_at(&get_config_path(app))
+}
+
+fn read_config_at(config_path: &Path) -> AppConfig {
+    match fs::read_to_string(config_path) {
         Ok(raw) => serde_json::from_str::<AppConfig>(&raw).unwrap_or_default(),
         Err(_) => AppConfig::default(),
     }
 }
 
+/// Where the library lives according to the config in `config_dir`, read
+/// without a running app, as a command-line export needs before the app
+/// starts.
+pub struct LibraryLocation {
+    pub local_dir: LocalAutoSaveDirState,
+    pub active_source: LibrarySource,
+    pub cloud_notes_dir: Option<PathBuf>,
+}
+
+pub fn get_library_location(config_dir: &Path, default_local_dir: &Path) -> LibraryLocation {
+    let config = read_config_at(&config_dir.join("config.json"));
+    let provider = config
+        .cloud_sync_provider
+        .as_deref()
+        .filter(|provider| !provider.is_empty());
+    LibraryLocation {
+        local_dir: classify_local_auto_save_dir(
+            classify_custom_auto_save_dir(&config),
+            default_local_dir,
+        ),
+        active_source: match provider {
+            Some(_) => config.cloud_sync_source.unwrap_or(LibrarySource::Cloud),
+            None => LibrarySource::Local,
+        },
+        cloud_notes_dir: provider
+            .and_then(|provider| get_cloud_notes_dir_for_provider(&config, provider)),
+    }
+}
+
 fn write_config(app: &AppHandle, config: &AppConfig) -> Result<(), String> {
     let config_path = get_config_path(app);
     if let Some(parent) = config_path.parent() {
@@ -86,23 +153,20 @@ fn normalize_path_for_compare(path: &str) -> String {
         .to_lowercase()
 }
 
-fn get_cloud_provider_root(provider: &str) -> Option<String> {
-    detect_cloud_providers()
-        .into_iter()
-        .find(|info| info.id == provider)
-        .and_then(|info| info.sync_folder)
+fn get_cloud_provider_root(config: &AppConfig, provider: &str) -> Option<String> {
+    detect_cloud_provider(config, provider).and_then(|info| info.sync_folder)
 }
 
-fn get_cloud_hwan_dir_for_provider(provider: &str) -> Option<PathBuf> {
-    get_cloud_provider_root(provider).map(|root| PathBuf::from(root).join("HwanNote"))
+fn get_cloud_hwan_dir_for_provider(config: &AppConfig, provider: &str) -> Option<PathBuf> {
+    get_cloud_provider_root(config, provider).map(|root| PathBuf::from(root).join("HwanNote"))
 }
 
-fn get_cloud_notes_dir_for_provider(provider: &str) -> Option<PathBuf> {
-    get_cloud_hwan_dir_for_provider(provider).map(|dir| dir.join("Notes"))
+fn get_cloud_notes_dir_for_provider(config: &AppConfig, provider: &str) -> Option<PathBuf> {
+    get_cloud_hwan_dir_for_provider(config, provider).map(|dir| dir.join("Notes"))
 }
 
-fn is_cloud_notes_dir_for_provider(provider: &str, dir: &str) -> bool {
-    get_cloud_notes_dir_for_provider(provider)
+fn is_cloud_notes_dir_for_provider(config: &AppConfig, provider: &str, dir: &str) -> bool {
+    get_cloud_notes_dir_for_provider(config, provider)
         .map(|cloud_dir| {
             normalize_path_for_compare(cloud_dir.to_string_lossy().as_ref())
                 == normalize_path_for_compare(dir)
@@ -148,7 +212,7 @@ fn classify_custom_auto_save_dir(config: &AppConfig) -> CustomAutoSaveDirState {
     if config
         .cloud_sync_provider
         .as_deref()
-        .is_some_and(|provider| is_cloud_notes_dir_for_provider(provider, dir))
+        .is_some_and(|provider| is_cloud_notes_dir_for_provider(config, provider, dir))
     {
         return CustomAutoSaveDirState::Unset;
     }
@@ -189,12 +253,15 @@ pub fn get_cloud_sync_provider(app: &AppHandle) -> Option<String> {
 pub fn set_cloud_sync_provider(app: &AppHandle, provider: Option<&str>) -> Result<(), String> {
     let mut config = read_config(app);
     if let Some(existing_dir) = config.auto_save_dir.clone() {
-        let matches_previous_cloud_path = config
-            .cloud_sync_provider
-            .as_deref()
-            .is_some_and(|current| is_cloud_notes_dir_for_provider(current, &existing_dir));
-        let matches_next_cloud_path =
-            provider.is_some_and(|next| is_cloud_notes_dir_for_provider(next, &existing_dir));
+        let matches_previous_cloud_path =
+            config
+                .cloud_sync_provider
+                .as_deref()
+                .is_some_and(|current| {
+                    is_cloud_notes_dir_for_provider(&config, current, &existing_dir)
+                });
+        let matches_next_cloud_path = provider
+            .is_some_and(|next| is_cloud_notes_dir_for_provider(&config, next, &existing_dir));
         if matches_previous_cloud_path || matches_next_cloud_path {
             config.auto_save_dir = None;
         }
@@ -241,9 +308,139 @@ pub fn set_cloud_sync_source(app: &AppHandle, source: LibrarySource) -> Result<(
     write_config(app, &config)
 }
 
+pub fn get_note_history_retention(app: &AppHandle) -> NoteHistoryRetention {
+    read_config(app).note_history.unwrap_or_default()
+}
+
+pub fn set_note_history_retention(
+    app: &AppHandle,
+    retention: NoteHistoryRetention,
+) -> Result<(), String> {
+    let mut config = read_config(app);
+    config.note_history = Some(retention);
+    write_config(app, &config)
+}
+
+pub fn get_snapshot_settings(app: &AppHandle) -> SnapshotSettings {
+    read_config(app).snapshots.unwrap_or_default()
+}
+
+pub fn set_snapshot_settings(app: &AppHandle, settings: SnapshotSettings) -> Result<(), String> {
+    if let Some(dir) = settings.target_dir.as_deref() {
+        if !Path::new(dir).is_absolute() {
+            return Err("Path must be absolute".to_string());
+        }
+    }
+    let mut config = read_config(app);
+    config.snapshots = Some(settings);
+    write_config(app, &config)
+}
+
+pub fn get_trash_settings(app: &AppHandle) -> TrashSettings {
+    read_config(app).trash.unwrap_or_default()
+}
+
+pub fn set_trash_settings(app: &AppHandle, settings: TrashSettings) -> Result<(), String> {
+    let mut config = read_config(app);
+    config.trash = Some(settings);
+    write_config(app, &config)
+}
+
+pub fn get_daily_note_settings(app: &AppHandle) -> DailyNoteSettings {
+    read_config(app).daily_notes.unwrap_or_default()
+}
+
+pub fn set_daily_note_settings(app: &AppHandle, settings: DailyNoteSettings) -> Result<(), String> {
+    settings.validate()?;
+    let mut config = read_config(app);
+    config.daily_notes = Some(settings);
+    write_config(app, &config)
+}
+
+/// Extensions besides `.md` and `.markdown` that open as external text files.
+pub fn get_text_extensions(app: &AppHandle) -> Vec<String> {
+    read_config(app).text_extensions.unwrap_or_else(|| {
+        DEFAULT_TEXT_EXTENSIONS
+            .iter()
+            .map(|ext| ext.to_string())
+            .collect()
+    })
+}
+
+pub fn set_text_extensions(app: &AppHandle, extensions: &[String]) -> Result<(), String> {
+    let extensions = normalize_text_extensions(extensions)?;
+    let mut config = read_config(app);
+    config.text_extensions = Some(extensions);
+    write_config(app, &config)
+}
+
+/// Identifies this installation in the note index, so each machine sharing a
+/// cloud library can remember which version of a note it last saw.
+pub fn get_or_create_machine_id(app: &AppHandle) -> String {
+    let mut config = read_config(app);
+    if let Some(machine_id) = config.machine_id.clone().filter(|id| !id.is_empty()) {
+        return machine_id;
+    }
+    let seed = format!(
+        "{}:{}:{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default(),
+        std::process::id(),
+        std::env::var("COMPUTERNAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_default(),
+    );
+    let machine_id = sha256_hex(seed.as_bytes())[..16].to_string();
+    config.machine_id = Some(machine_id.clone());
+    if let Err(e) = write_config(app, &config) {
+        tracing::warn!("Failed to save machine id: {}", e);
+    }
+    machine_id
+}
+
+/// Directory that holds library snapshots: the configured target, or
+/// `snapshots` under the app data directory.
+pub fn get_snapshot_dir(app: &AppHandle, settings: &SnapshotSettings) -> PathBuf {
+    match settings.target_dir.as_deref().filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path()
+            .app_data_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join("snapshots"),
+    }
+}
+
 pub fn get_cloud_notes_dir(app: &AppHandle) -> Option<PathBuf> {
-    let provider = get_cloud_sync_provider(app)?;
-    get_cloud_notes_dir_for_provider(&provider)
+    let config = read_config(app);
+    let provider = config
+        .cloud_sync_provider
+        .as_deref()
+        .filter(|p| !p.is_empty())?;
+    get_cloud_notes_dir_for_provider(&config, provider)
+}
+
+/// Sets the folder the `custom` provider syncs through, such as a NAS share
+/// or a folder another sync tool manages. It cannot change while that
+/// provider is enabled, since the cloud library would move with it.
+pub fn set_custom_sync_folder(app: &AppHandle, dir: Option<&str>) -> Result<(), String> {
+    if let Some(d) = dir {
+        let path = Path::new(d);
+        if !path.is_absolute() {
+            return Err("Path must be absolute".to_string());
+        }
+        if !path.is_dir() {
+            return Err("Path must be an existing directory".to_string());
+        }
+    }
+    let mut config = read_config(app);
+    if config.cloud_sync_provider.as_deref() == Some(CUSTOM_PROVIDER_ID) {
+        return Err("Turn off cloud sync before changing the custom sync folder".to_string());
+    }
+    config.custom_sync_folder = dir.map(String::from);
+    write_config(app, &config)
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -314,24 +511,208 @@ fn detect_google_drive() -> Option<String> {
     None
 }
 
-pub fn detect_cloud_providers() -> Vec<CloudProviderInfo> {
-    let onedrive = detect_onedrive();
-    let google_drive = detect_google_drive();
+/// Where a sync client's folder was found: the first folder that exists,
+/// or else the first one configured so its status can still be shown.
+fn first_existing_folder(folders: Vec<String>) -> Option<String> {
+    folders
+        .iter()
+        .find(|folder| Path::new(folder).is_dir())
+        .or(folders.first())
+        .cloned()
+}
 
-    vec![
-        CloudProviderInfo {
-            id: "onedrive".to_string(),
-            name: "OneDrive".to_string(),
-            available: onedrive.is_some(),
-            sync_folder: onedrive,
-        },
-        CloudProviderInfo {
-            id: "google_drive".to_string(),
-            name: "Google Drive".to_string(),
-            available: google_drive.is_some(),
-            sync_folder: google_drive,
-        },
+/// Expands a leading `~` and drops trailing separators from a folder path
+/// read from a sync client's configuration.
+fn normalize_client_folder(path: &str) -> Option<String> {
+    let path = path.trim();
+    let expanded = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            format!("{}{rest}", dirs::home_dir()?.display())
+        }
+        _ => path.to_string(),
+    };
+    let trimmed = expanded.trim_end_matches(['/', '\\']);
+    match trimmed {
+        "" if expanded.is_empty() => None,
+        "" => Some(expanded[..1].to_string()),
+        _ => Some(trimmed.to_string()),
+    }
+}
+
+/// Dropbox lists the folder of each linked account in `info.json`.
+fn dropbox_folders_from_info(raw: &str) -> Vec<String> {
+    let Ok(info) = serde_json::from_str::<serde_json::Value>(raw) else {
+        return Vec::new();
+    };
+    ["personal", "business"]
+        .iter()
+        .filter_map(|account| info.get(account)?.get("path")?.as_str())
+        .filter_map(normalize_client_folder)
+        .collect()
+}
+
+fn detect_dropbox() -> Option<String> {
+    let mut candidates = Vec::new();
+    #[cfg(windows)]
+    for var in ["APPDATA", "LOCALAPPDATA"] {
+        if let Some(dir) = std::env::var_os(var) {
+            candidates.push(PathBuf::from(dir).join("Dropbox").join("info.json"));
+        }
+    }
+    if let Some(home) = dirs::home_dir() {
+        candidates.push(home.join(".dropbox").join("info.json"));
+    }
+    let folders = candidates
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|raw| dropbox_folders_from_info(&raw))
+        .collect();
+    first_existing_folder(folders)
+}
+
+/// The Nextcloud and ownCloud desktop clients keep a `localPath` for each
+/// synced folder in the `[Accounts]` section of their settings file, under
+/// keys such as `0\Folders\1\localPath`.
+fn sync_client_folders_from_config(raw: &str) -> Vec<String> {
+    let mut in_accounts = false;
+    let mut folders = Vec::new();
+    for line in raw.lines().map(str::trim) {
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_accounts = section == "Accounts";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if !in_accounts || !key.trim_end().ends_with("\\localPath") {
+            continue;
+        }
+        let value = value.trim().trim_matches('"').replace("\\\\", "\\");
+        folders.extend(normalize_client_folder(&value));
+    }
+    folders
+}
+
+fn detect_sync_client_folder(client_dir: &str, config_file: &str) -> Option<String> {
+    // The client writes its settings to the config directory, or to
+    // ~/Library/Preferences on macOS.
+    let folders = [dirs::config_dir(), dirs::preference_dir()]
+        .into_iter()
+        .flatten()
+        .filter_map(|dir| fs::read_to_string(dir.join(client_dir).join(config_file)).ok())
+        .flat_map(|raw| sync_client_folders_from_config(&raw))
+        .collect();
+    first_existing_folder(folders)
+}
+
+/// Syncthing lists each shared folder as a `<folder path="...">` element of
+/// its `config.xml`.
+fn syncthing_folders_from_config(raw: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(raw);
+    let mut depth = 0usize;
+    let mut folders = Vec::new();
+    loop {
+        let (element, is_start) = match reader.read_event() {
+            Ok(Event::Start(element)) => (element, true),
+            Ok(Event::Empty(element)) => (element, false),
+            Ok(Event::End(_)) => {
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        if depth == 1 && element.local_name().as_ref() == b"folder" {
+            if let Some(attribute) = element.try_get_attribute("path").ok().flatten() {
+                let raw = String::from_utf8_lossy(&attribute.value).into_owned();
+                let path = unescape_with(&raw, resolve_predefined_entity)
+                    .map(|value| value.into_owned())
+                    .unwrap_or(raw);
+                folders.extend(normalize_client_folder(&path));
+            }
+        }
+        if is_start {
+            depth += 1;
+        }
+    }
+    folders
+}
+
+fn detect_syncthing() -> Option<String> {
+    // Syncthing 1.27+ keeps its config in the XDG state directory on Linux,
+    // older versions in the config directory; Windows and macOS use the
+    // local application data directory.
+    let folders = [
+        dirs::state_dir().map(|dir| dir.join("syncthing")),
+        dirs::config_dir().map(|dir| dir.join("syncthing")),
+        dirs::data_local_dir().map(|dir| dir.join("Syncthing")),
     ]
+    .into_iter()
+    .flatten()
+    .filter_map(|dir| fs::read_to_string(dir.join("config.xml")).ok())
+    .flat_map(|raw| syncthing_folders_from_config(&raw))
+    .collect();
+    sole_syncthing_folder(folders)
+}
+
+/// Syncthing shares any number of unrelated folders, so one is only used
+/// when it is the only folder shared; otherwise the user picks theirs with
+/// the custom provider.
+fn sole_syncthing_folder(mut folders: Vec<String>) -> Option<String> {
+    folders.sort();
+    folders.dedup();
+    match folders.as_slice() {
+        [folder] => Some(folder.clone()),
+        _ => None,
+    }
+}
+
+fn detect_cloud_provider(config: &AppConfig, id: &str) -> Option<CloudProviderInfo> {
+    let (name, sync_folder) = match id {
+        "onedrive" => ("OneDrive", detect_onedrive()),
+        "google_drive" => ("Google Drive", detect_google_drive()),
+        "dropbox" => ("Dropbox", detect_dropbox()),
+        "nextcloud" => (
+            "Nextcloud",
+            detect_sync_client_folder("Nextcloud", "nextcloud.cfg"),
+        ),
+        "owncloud" => (
+            "ownCloud",
+            detect_sync_client_folder("ownCloud", "owncloud.cfg"),
+        ),
+        "syncthing" => ("Syncthing", detect_syncthing()),
+        CUSTOM_PROVIDER_ID => (
+            "Custom folder",
+            config
+                .custom_sync_folder
+                .clone()
+                .filter(|folder| !folder.is_empty()),
+        ),
+        _ => return None,
+    };
+    Some(CloudProviderInfo {
+        id: id.to_string(),
+        name: name.to_string(),
+        available: sync_folder
+            .as_deref()
+            .is_some_and(|folder| Path::new(folder).is_dir()),
+        sync_folder,
+    })
+}
+
+fn detect_cloud_providers_with_config(config: &AppConfig) -> Vec<CloudProviderInfo> {
+    CLOUD_PROVIDER_IDS
+        .iter()
+        .filter_map(|id| detect_cloud_provider(config, id))
+        .collect()
+}
+
+pub fn detect_cloud_providers(app: &AppHandle) -> Vec<CloudProviderInfo> {
+    detect_cloud_providers_with_config(&read_config(app))
+}
+
+pub fn find_cloud_provider(app: &AppHandle, provider: &str) -> Option<CloudProviderInfo> {
+    detect_cloud_provider(&read_config(app), provider)
 }
 
 /// One-time migration: copy legacy Electron config to Tauri config directory.
@@ -385,7 +766,7 @@ pub fn migrate_legacy_cloud_sync_config(app: &AppHandle) -> Result<(), String> {
         return Ok(());
     };
 
-    let providers = detect_cloud_providers();
+    let providers = detect_cloud_providers_with_config(&config);
     let Some((provider, source)) = classify_legacy_cloud_sync_dir(auto_save_dir, &providers) else {
         return Ok(());
     };
@@ -404,8 +785,9 @@ pub fn migrate_legacy_cloud_sync_config(_app: &AppHandle) -> Result<(), String>
 mod tests {
     use super::{
         classify_custom_auto_save_dir, classify_legacy_cloud_sync_dir,
-        classify_local_auto_save_dir, AppConfig, CloudProviderInfo, CustomAutoSaveDirState,
-        LibrarySource, LocalAutoSaveDirState,
+        classify_local_auto_save_dir, detect_cloud_provider, dropbox_folders_from_info,
+        sole_syncthing_folder, sync_client_folders_from_config, syncthing_folders_from_config,
+        AppConfig, CloudProviderInfo, CustomAutoSaveDirState, LibrarySource, LocalAutoSaveDirState,
     };
     use std::fs;
     use std::path::PathBuf;
@@ -495,8 +877,7 @@ mod tests {
         let missing_dir = root.join("detached");
         let config = AppConfig {
             auto_save_dir: Some(missing_dir.to_string_lossy().to_string()),
-            cloud_sync_provider: None,
-            cloud_sync_source: None,
+            ..AppConfig::default()
         };
 
         let state = classify_custom_auto_save_dir(&config);
@@ -513,8 +894,7 @@ mod tests {
         fs::write(&file_path, "not a library directory").unwrap();
         let config = AppConfig {
             auto_save_dir: Some(file_path.to_string_lossy().to_string()),
-            cloud_sync_provider: None,
-            cloud_sync_source: None,
+            ..AppConfig::default()
         };
 
         assert_eq!(
@@ -530,8 +910,7 @@ mod tests {
         let dir = make_temp_dir("custom-dir-restore");
         let config = AppConfig {
             auto_save_dir: Some(dir.to_string_lossy().to_string()),
-            cloud_sync_provider: None,
-            cloud_sync_source: None,
+            ..AppConfig::default()
         };
 
         assert_eq!(
@@ -572,4 +951,66 @@ mod tests {
         assert_eq!(serialized["cloudSyncProvider"], "onedrive");
         assert!(serialized.get("localAutoSaveDirState").is_none());
     }
+
+    #[test]
+    fn sync_client_configs_list_their_sync_folders() {
+        let dropbox = r#"{"personal": {"path": "/home/me/Dropbox", "is_team": false},
+            "business": {"path": "/home/me/Dropbox (Team)/", "is_team": true}}"#;
+        assert_eq!(
+            dropbox_folders_from_info(dropbox),
+            vec!["/home/me/Dropbox", "/home/me/Dropbox (Team)"]
+        );
+
+        let nextcloud =
+            "[General]\nlocalPath=/ignored\n\n[Accounts]\n0\\url=https://cloud.example\n\
+            0\\Folders\\1\\localPath=/home/me/Nextcloud/\n\
+            1\\FoldersWithPlaceholders\\2\\localPath=C:/Users/me/Work/\n";
+        assert_eq!(
+            sync_client_folders_from_config(nextcloud),
+            vec!["/home/me/Nextcloud", "C:/Users/me/Work"]
+        );
+
+        let syncthing = r#"<configuration version="37">
+            <folder id="default" label="Default Folder" path="/srv/sync" type="sendreceive">
+                <device id="ABC"></device>
+            </folder>
+            <folder id="nas" path="/mnt/nas &amp; more/"/>
+            <device id="ABC" name="laptop"></device>
+        </configuration>"#;
+        assert_eq!(
+            syncthing_folders_from_config(syncthing),
+            vec!["/srv/sync", "/mnt/nas & more"]
+        );
+        assert_eq!(
+            sole_syncthing_folder(syncthing_folders_from_config(syncthing)),
+            None
+        );
+        assert_eq!(
+            sole_syncthing_folder(vec!["/srv/sync".to_string(), "/srv/sync".to_string()]),
+            Some("/srv/sync".to_string())
+        );
+    }
+
+    #[test]
+    fn custom_provider_reports_the_configured_folder_status() {
+        let dir = make_temp_dir("custom-sync-folder");
+        let folder = dir.to_string_lossy().to_string();
+        let config = AppConfig {
+            custom_sync_folder: Some(folder.clone()),
+            ..AppConfig::default()
+        };
+
+        let info = detect_cloud_provider(&config, "custom").unwrap();
+        assert!(info.available);
+        assert_eq!(info.sync_folder.as_deref(), Some(folder.as_str()));
+
+        fs::remove_dir_all(&dir).unwrap();
+        let info = detect_cloud_provider(&config, "custom").unwrap();
+        assert!(!info.available);
+        assert_eq!(info.sync_folder.as_deref(), Some(folder.as_str()));
+
+        let unset = detect_cloud_provider(&AppConfig::default(), "custom").unwrap();
+        assert!(!unset.available && unset.sync_folder.is_none());
+        assert!(detect_cloud_provider(&config, "icloud").is_none());
+    }
 }
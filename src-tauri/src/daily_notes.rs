@@ -0,0 +1,353 @@
+use crate::file_manager::{
+    auto_save_markdown_note_with_history, folder_path_for_relative_path, note_index_entries,
+    now_millis, read_loaded_note, sanitize_folder_path, slugify_title, AutoSavePayload, LoadedNote,
+    TrustedLibraryRoot,
+};
+use crate::front_matter::{civil_from_days, days_from_civil};
+use crate::note_history::NoteHistoryRetention;
+use crate::note_templates::{read_template, render_template, TemplateContext};
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+const YEAR_TOKEN: &str = "YYYY";
+const MONTH_TOKEN: &str = "MM";
+const DAY_TOKEN: &str = "DD";
+const MAX_FILENAME_PATTERN_CHARS: usize = 50;
+const MILLIS_PER_MINUTE: i64 = 60 * 1000;
+const MILLIS_PER_DAY: i64 = 24 * 60 * MILLIS_PER_MINUTE;
+
+static DATE_KEY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d{4})-(\d{2})-(\d{2})$").unwrap());
+
+// Finding and creating a day's note must not interleave, or two opens of the
+// same day could each create a note.
+static DAILY_NOTE_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+fn lock_daily_notes() -> MutexGuard<'static, ()> {
+    DAILY_NOTE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// ── Types ──
+
+/// Where daily notes live and how they are named. The filename pattern uses
+/// `YYYY`, `MM` and `DD` for the day; the rendered name is also the note's
+/// title.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DailyNoteSettings {
+    pub folder_path: String,
+    pub filename_pattern: String,
+    /// Template rendered into new daily notes; `{{date}}` is the note's day.
+    pub template_name: Option<String>,
+}
+
+impl Default for DailyNoteSettings {
+    fn default() -> Self {
+        Self {
+            folder_path: "Daily".to_string(),
+            filename_pattern: "YYYY-MM-DD".to_string(),
+            template_name: None,
+        }
+    }
+}
+
+impl DailyNoteSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        sanitize_folder_path(Some(&self.folder_path))?;
+        let pattern = self.filename_pattern.as_str();
+        let has_each_token_once = [YEAR_TOKEN, MONTH_TOKEN, DAY_TOKEN]
+            .iter()
+            .all(|token| pattern.matches(token).count() == 1);
+        if !has_each_token_once
+            || pattern.starts_with('.')
+            || pattern.chars().count() > MAX_FILENAME_PATTERN_CHARS
+            || slugify_title(pattern) != pattern
+        {
+            return Err(format!("Invalid daily note filename pattern: {pattern}"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DailyNoteDirection {
+    Previous,
+    Next,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyNote {
+    pub date_key: String,
+    pub created: bool,
+    /// Whether this open added the note to the day's calendar links.
+    pub calendar_linked: bool,
+    pub note: LoadedNote,
+}
+
+// ── Dates and names ──
+
+/// Days since the Unix epoch for a `YYYY-MM-DD` calendar key.
+fn parse_date_key(date_key: &str) -> Result<i64, String> {
+    let invalid = || format!("Invalid date key: {date_key}");
+    let captures = DATE_KEY_RE.captures(date_key).ok_or_else(invalid)?;
+    let year: i64 = captures[1].parse().map_err(|_| invalid())?;
+    let month: u32 = captures[2].parse().map_err(|_| invalid())?;
+    let day: u32 = captures[3].parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) || day == 0 {
+        return Err(invalid());
+    }
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return Err(invalid());
+    }
+    Ok(days)
+}
+
+fn format_date_key(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn daily_note_name(pattern: &str, days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    pattern
+        .replacen(YEAR_TOKEN, &format!("{year:04}"), 1)
+        .replacen(MONTH_TOKEN, &format!("{month:02}"), 1)
+        .replacen(DAY_TOKEN, &format!("{day:02}"), 1)
+}
+
+fn daily_note_name_regex(pattern: &str) -> Result<Regex, String> {
+    let expression = regex::escape(pattern)
+        .replacen(YEAR_TOKEN, r"(?P<year>\d{4})", 1)
+        .replacen(MONTH_TOKEN, r"(?P<month>\d{2})", 1)
+        .replacen(DAY_TOKEN, r"(?P<day>\d{2})", 1);
+    Regex::new(&format!("^{expression}$"))
+        .map_err(|error| format!("Invalid daily note filename pattern {pattern}: {error}"))
+}
+
+/// Daily notes in the configured folder by day. When several files name the
+/// same day, the oldest note wins.
+fn daily_notes_by_day(
+    trusted_root: &TrustedLibraryRoot,
+    settings: &DailyNoteSettings,
+) -> Result<BTreeMap<i64, String>, String> {
+    let folder = sanitize_folder_path(Some(&settings.folder_path))?;
+    let name_re = daily_note_name_regex(&settings.filename_pattern)?;
+    let mut by_day: BTreeMap<i64, (u64, String)> = BTreeMap::new();
+    for (note_id, entry) in note_index_entries(trusted_root)? {
+        if folder_path_for_relative_path(&entry.relative_path) != folder {
+            continue;
+        }
+        let Some(stem) = Path::new(&entry.relative_path)
+            .file_stem()
+            .and_then(|value| value.to_str())
+        else {
+            continue;
+        };
+        let Some(captures) = name_re.captures(stem) else {
+            continue;
+        };
+        let date_key = format!(
+            "{}-{}-{}",
+            &captures["year"], &captures["month"], &captures["day"]
+        );
+        let Ok(days) = parse_date_key(&date_key) else {
+            continue;
+        };
+        let current = by_day
+            .entry(days)
+            .or_insert((entry.created_at, note_id.clone()));
+        if (entry.created_at, &note_id) < (current.0, &current.1) {
+            *current = (entry.created_at, note_id);
+        }
+    }
+    Ok(by_day
+        .into_iter()
+        .map(|(days, (_, note_id))| (days, note_id))
+        .collect())
+}
+
+fn daily_note(
+    trusted_root: &TrustedLibraryRoot,
+    days: i64,
+    note_id: &str,
+    created: bool,
+) -> Result<DailyNote, String> {
+    let note = read_loaded_note(trusted_root, note_id)?
+        .ok_or_else(|| format!("Note not found: {note_id}"))?;
+    Ok(DailyNote {
+        date_key: format_date_key(days),
+        created,
+        calendar_linked: false,
+        note,
+    })
+}
+
+// ── Public API ──
+
+/// Returns the note for a calendar day, creating it from the configured
+/// template when the day has none yet.
+pub fn open_daily_note(
+    auto_save_dir: &Path,
+    date_key: &str,
+    settings: &DailyNoteSettings,
+    utc_offset_minutes: i32,
+    history_retention: &NoteHistoryRetention,
+) -> Result<DailyNote, String> {
+    settings.validate()?;
+    let days = parse_date_key(date_key)?;
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _daily_guard = lock_daily_notes();
+    if let Some(note_id) = daily_notes_by_day(&trusted_root, settings)?.get(&days) {
+        return daily_note(&trusted_root, days, note_id, false);
+    }
+
+    let folder = sanitize_folder_path(Some(&settings.folder_path))?;
+    let title = daily_note_name(&settings.filename_pattern, days);
+    let now = now_millis();
+    let content = match settings.template_name.as_deref() {
+        Some(template_name) => {
+            // Render on the note's day at the current local time of day.
+            let local_now = now as i64 + i64::from(utc_offset_minutes) * MILLIS_PER_MINUTE;
+            let rendered_at = days * MILLIS_PER_DAY + local_now.rem_euclid(MILLIS_PER_DAY);
+            render_template(
+                &read_template(&trusted_root, template_name)?.markdown,
+                &TemplateContext {
+                    now: u64::try_from(rendered_at).unwrap_or_default(),
+                    utc_offset_minutes: 0,
+                    title: &title,
+                    folder: &folder,
+                    prompts: &HashMap::new(),
+                },
+            )
+        }
+        None => String::new(),
+    };
+    let note_id = format!("daily-{}-{now}", format_date_key(days));
+    auto_save_markdown_note_with_history(
+        auto_save_dir,
+        &AutoSavePayload {
+            note_id: note_id.clone(),
+            title,
+            content,
+            folder_path: Some(folder),
+            is_title_manual: Some(true),
+            is_pinned: None,
+        },
+        history_retention,
+    )?;
+    daily_note(&trusted_root, days, &note_id, true)
+}
+
+/// Finds the nearest existing daily note before or after a calendar day.
+pub fn adjacent_daily_note(
+    auto_save_dir: &Path,
+    date_key: &str,
+    direction: DailyNoteDirection,
+    settings: &DailyNoteSettings,
+) -> Result<Option<DailyNote>, String> {
+    settings.validate()?;
+    let days = parse_date_key(date_key)?;
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let by_day = daily_notes_by_day(&trusted_root, settings)?;
+    let adjacent = match direction {
+        DailyNoteDirection::Previous => by_day.range(..days).next_back(),
+        DailyNoteDirection::Next => by_day.range(days + 1..).next(),
+    };
+    adjacent
+        .map(|(&day, note_id)| daily_note(&trusted_root, day, note_id, false))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_templates::save_template;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-{}-{}-{}",
+            name,
+            process::id(),
+            now_millis()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn daily_note_settings_reject_ambiguous_patterns_and_bad_dates() {
+        let with_pattern = |pattern: &str| DailyNoteSettings {
+            filename_pattern: pattern.to_string(),
+            ..DailyNoteSettings::default()
+        };
+        assert!(with_pattern("Journal-YYYY.MM.DD").validate().is_ok());
+        assert!(with_pattern("YYYY-MM").validate().is_err());
+        assert!(with_pattern("YYYY-MM-DD-DD").validate().is_err());
+        assert!(with_pattern("YYYY MM DD").validate().is_err());
+        assert!(with_pattern("YYYY/MM/DD").validate().is_err());
+
+        assert_eq!(
+            format_date_key(parse_date_key("2024-02-29").unwrap()),
+            "2024-02-29"
+        );
+        assert!(parse_date_key("2023-02-29").is_err());
+        assert!(parse_date_key("2024-13-01").is_err());
+        assert!(parse_date_key("24-01-01").is_err());
+    }
+
+    #[test]
+    fn daily_note_is_created_once_from_template_and_navigates_by_day() {
+        let dir = make_temp_dir("daily-note");
+        let result = (|| -> Result<(), String> {
+            save_template(&dir, "Day", "# {{title}}\nDate: {{date}}")?;
+            let settings = DailyNoteSettings {
+                folder_path: "journal".to_string(),
+                filename_pattern: "Log-YYYY-MM-DD".to_string(),
+                template_name: Some("Day".to_string()),
+            };
+            let retention = NoteHistoryRetention::default();
+
+            let first = open_daily_note(&dir, "2024-03-01", &settings, 540, &retention)?;
+            assert!(first.created);
+            assert_eq!(first.note.title, "Log-2024-03-01");
+            assert_eq!(first.note.folder_path, "journal");
+            assert!(first
+                .note
+                .markdown
+                .contains("# Log-2024-03-01\nDate: 2024-03-01"));
+            let reopened = open_daily_note(&dir, "2024-03-01", &settings, 540, &retention)?;
+            assert!(!reopened.created);
+            assert_eq!(reopened.note.note_id, first.note.note_id);
+
+            open_daily_note(&dir, "2024-02-20", &settings, 540, &retention)?;
+            let previous =
+                adjacent_daily_note(&dir, "2024-03-01", DailyNoteDirection::Previous, &settings)?
+                    .unwrap();
+            assert_eq!(previous.date_key, "2024-02-20");
+            let next =
+                adjacent_daily_note(&dir, "2024-02-21", DailyNoteDirection::Next, &settings)?
+                    .unwrap();
+            assert_eq!(next.note.note_id, first.note.note_id);
+            assert!(
+                adjacent_daily_note(&dir, "2024-03-01", DailyNoteDirection::Next, &settings)?
+                    .is_none()
+            );
+            Ok(())
+        })();
+        let _ = fs::remove_dir_all(&dir);
+        result.unwrap();
+    }
+}
@@ -0,0 +1,966 @@
+use crate::attachments;
+use crate::file_manager::{
+    now_millis, sanitize_folder_path, slugify_title, MigratedNote, TrustedLibraryRoot,
+};
+use crate::front_matter::{days_from_civil, render_front_matter, FrontMatter};
+use crate::import_staging::{ImportStaging, StagedNote, UniqueNotePaths};
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use md5::{Digest, Md5};
+use quick_xml::escape::{resolve_predefined_entity, unescape_with};
+use quick_xml::events::{BytesRef, BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+
+const FALLBACK_NOTEBOOK_NAME: &str = "Evernote";
+const ENCRYPTED_CONTENT_REASON: &str = "the note contains Evernote-encrypted text";
+
+// ── Types ──
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnexImportFailure {
+    pub source_path: String,
+    /// Title of the note that failed, or `None` when the whole file did.
+    pub title: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnexImportResult {
+    pub imported: Vec<MigratedNote>,
+    pub attachments_imported: u32,
+    pub failures: Vec<EnexImportFailure>,
+}
+
+#[derive(Debug, Default)]
+struct EnexResource {
+    data: String,
+    mime: String,
+    file_name: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct EnexNote {
+    title: String,
+    content: String,
+    created: Option<u64>,
+    updated: Option<u64>,
+    tags: Vec<String>,
+    resources: Vec<EnexResource>,
+}
+
+/// A decoded resource, stored as an attachment and linked by its ENML hash.
+struct MediaLink {
+    name: String,
+    link_target: String,
+    is_image: bool,
+}
+
+// ── ENEX parsing ──
+
+fn xml_error(error: impl std::fmt::Display) -> String {
+    format!("the XML is malformed: {error}")
+}
+
+fn resolve_reference(reference: &BytesRef<'_>) -> Result<String, String> {
+    if let Some(ch) = reference.resolve_char_ref().map_err(xml_error)? {
+        return Ok(ch.to_string());
+    }
+    let name = reference.decode().map_err(xml_error)?;
+    resolve_predefined_entity(&name)
+        .map(str::to_string)
+        .ok_or_else(|| xml_error(format!("unknown entity &{name};")))
+}
+
+fn attribute(element: &BytesStart<'_>, name: &str) -> Option<String> {
+    let attribute = element.try_get_attribute(name).ok().flatten()?;
+    let raw = String::from_utf8_lossy(&attribute.value).into_owned();
+    Some(match unescape_with(&raw, resolve_predefined_entity) {
+        Ok(value) => value.into_owned(),
+        Err(_) => raw,
+    })
+}
+
+/// ENEX dates are compact UTC timestamps such as `20240301T120000Z`.
+fn parse_enex_timestamp(text: &str) -> Option<u64> {
+    let text = text.trim().strip_suffix('Z')?;
+    let (date, time) = text.split_once('T')?;
+    if date.len() != 8
+        || time.len() != 6
+        || !(date.chars().chain(time.chars())).all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let number = |value: &str| value.parse::<u32>().ok();
+    let (year, month, day) = (
+        number(&date[..4])?,
+        number(&date[4..6])?,
+        number(&date[6..])?,
+    );
+    let (hours, minutes, seconds) = (
+        number(&time[..2])?,
+        number(&time[2..4])?,
+        number(&time[4..])?,
+    );
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hours > 23
+        || minutes > 59
+        || seconds > 60
+    {
+        return None;
+    }
+    let days = days_from_civil(i64::from(year), month, day);
+    let seconds = days * 86_400 + i64::from(hours * 3_600 + minutes * 60 + seconds);
+    u64::try_from(seconds * 1_000).ok()
+}
+
+fn parse_enex(xml: &str) -> Result<Vec<EnexNote>, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut notes = Vec::new();
+    let mut note: Option<EnexNote> = None;
+    let mut resource: Option<EnexResource> = None;
+    let mut is_export = false;
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+                match name.as_str() {
+                    "en-export" if path.is_empty() => is_export = true,
+                    "note" => note = Some(EnexNote::default()),
+                    "resource" => resource = Some(EnexResource::default()),
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let parent = path.last().map(String::as_str).unwrap_or_default();
+                let value = std::mem::take(&mut text);
+                match (parent, name.as_str()) {
+                    (_, "note") => notes.extend(note.take()),
+                    ("note", "resource") => {
+                        if let (Some(note), Some(resource)) = (note.as_mut(), resource.take()) {
+                            note.resources.push(resource);
+                        }
+                    }
+                    ("note", field) => {
+                        if let Some(note) = note.as_mut() {
+                            match field {
+                                "title" => note.title = value.trim().to_string(),
+                                "content" => note.content = value,
+                                "created" => note.created = parse_enex_timestamp(&value),
+                                "updated" => note.updated = parse_enex_timestamp(&value),
+                                "tag" if !value.trim().is_empty() => {
+                                    note.tags.push(value.trim().to_string())
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    ("resource", "data") => {
+                        if let Some(resource) = resource.as_mut() {
+                            resource.data = value;
+                        }
+                    }
+                    ("resource", "mime") => {
+                        if let Some(resource) = resource.as_mut() {
+                            resource.mime = value.trim().to_string();
+                        }
+                    }
+                    ("resource-attributes", "file-name") => {
+                        if let Some(resource) = resource.as_mut() {
+                            resource.file_name =
+                                Some(value.trim().to_string()).filter(|name| !name.is_empty());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Text(content) => text.push_str(&content.decode().map_err(xml_error)?),
+            Event::CData(content) => text.push_str(&content.decode().map_err(xml_error)?),
+            Event::GeneralRef(reference) => text.push_str(&resolve_reference(&reference)?),
+            Event::Eof if !path.is_empty() => {
+                return Err(xml_error(format!("<{}> is never closed", path.join("><"))));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !is_export {
+        return Err("the file is not an Evernote export".to_string());
+    }
+    Ok(notes)
+}
+
+// ── ENML conversion ──
+
+enum Closer {
+    Nothing,
+    Line,
+    Block,
+    Inline(&'static str),
+    Link(String),
+    List,
+    Quote,
+    Pre,
+    Table,
+    Row,
+    Cell,
+}
+
+struct ListState {
+    ordered: bool,
+    next_number: u32,
+    todo: bool,
+    /// Indentation of this list's items, from the enclosing items' markers.
+    indent: String,
+    /// Width of the marker of the item being written.
+    marker_width: usize,
+}
+
+#[derive(Default)]
+struct TableState {
+    rows: Vec<Vec<String>>,
+    cell: Option<String>,
+}
+
+/// Writes ENML (Evernote's XHTML subset) as Markdown. Block structure is
+/// tracked with a stack of closers so each end tag undoes its own start.
+struct MarkdownWriter<'a> {
+    out: String,
+    line: String,
+    line_prefix: String,
+    lists: Vec<ListState>,
+    quote_depth: usize,
+    pre_depth: usize,
+    table: Option<TableState>,
+    media: &'a HashMap<String, MediaLink>,
+    used_media: HashSet<String>,
+}
+
+fn style_has(element: &BytesStart<'_>, declaration: &str) -> bool {
+    attribute(element, "style").is_some_and(|style| {
+        style
+            .split(';')
+            .any(|part| part.replace(' ', "").eq_ignore_ascii_case(declaration))
+    })
+}
+
+fn escape_link_text(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
+
+fn link_destination(href: &str) -> Cow<'_, str> {
+    if href.contains([' ', '(', ')']) {
+        Cow::Owned(format!("<{href}>"))
+    } else {
+        Cow::Borrowed(href)
+    }
+}
+
+impl<'a> MarkdownWriter<'a> {
+    fn new(media: &'a HashMap<String, MediaLink>) -> Self {
+        Self {
+            out: String::new(),
+            line: String::new(),
+            line_prefix: String::new(),
+            lists: Vec::new(),
+            quote_depth: 0,
+            pre_depth: 0,
+            table: None,
+            media,
+            used_media: HashSet::new(),
+        }
+    }
+
+    fn target(&mut self) -> &mut String {
+        match self.table.as_mut().and_then(|table| table.cell.as_mut()) {
+            Some(cell) => cell,
+            None => &mut self.line,
+        }
+    }
+
+    fn in_cell(&self) -> bool {
+        self.table
+            .as_ref()
+            .is_some_and(|table| table.cell.is_some())
+    }
+
+    fn push_raw(&mut self, text: &str) {
+        self.target().push_str(text);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.pre_depth > 0 {
+            // Code blocks written as one `<div>` per line often carry the
+            // source's indentation between elements; only real text counts.
+            let between_lines = self.line.is_empty() || self.line.ends_with('\n');
+            if !(between_lines && text.trim().is_empty() && text.contains('\n')) {
+                self.line.push_str(text);
+            }
+            return;
+        }
+        let target = self.target();
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                if !target.is_empty() && !target.ends_with(' ') {
+                    target.push(' ');
+                }
+            } else {
+                target.push(ch);
+            }
+        }
+    }
+
+    fn end_line(&mut self) {
+        if self.in_cell() {
+            let cell = self.target();
+            if !cell.trim().is_empty() && !cell.ends_with("<br>") {
+                let trimmed = cell.trim_end().len();
+                cell.truncate(trimmed);
+                cell.push_str("<br>");
+            }
+            return;
+        }
+        if self.pre_depth > 0 {
+            if !self.line.is_empty() && !self.line.ends_with('\n') {
+                self.line.push('\n');
+            }
+            return;
+        }
+        let text = self.line.trim();
+        if !text.is_empty() {
+            let quote = "> ".repeat(self.quote_depth);
+            self.out
+                .push_str(&format!("{quote}{}{text}\n", self.line_prefix));
+            self.line_prefix.clear();
+        }
+        self.line.clear();
+    }
+
+    fn end_block(&mut self) {
+        self.end_line();
+        if self.in_cell() || self.pre_depth > 0 {
+            return;
+        }
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn push_media(&mut self, hash: &str) {
+        let Some(media) = self.media.get(hash) else {
+            return;
+        };
+        self.used_media.insert(hash.to_string());
+        let bang = if media.is_image { "!" } else { "" };
+        let link = format!(
+            "{bang}[{}]({})",
+            escape_link_text(&media.name),
+            media.link_target
+        );
+        self.push_raw(&link);
+    }
+
+    fn push_todo(&mut self, checked: bool) {
+        let checkbox = if checked { "[x] " } else { "[ ] " };
+        if self.line.trim().is_empty() && !self.in_cell() {
+            if self.line_prefix.ends_with("- ") {
+                self.line_prefix.push_str(checkbox);
+            } else if self.line_prefix.is_empty() {
+                self.line_prefix = format!("- {checkbox}");
+            }
+        } else {
+            self.push_raw(checkbox);
+        }
+    }
+
+    fn start_pre(&mut self) -> Closer {
+        self.end_block();
+        if self.pre_depth == 0 {
+            self.out.push_str("```\n");
+        }
+        self.pre_depth += 1;
+        Closer::Pre
+    }
+
+    fn start(&mut self, element: &BytesStart<'_>) -> Result<Closer, String> {
+        let name = String::from_utf8_lossy(element.local_name().as_ref()).to_ascii_lowercase();
+        let closer = match name.as_str() {
+            "en-crypt" => return Err(ENCRYPTED_CONTENT_REASON.to_string()),
+            "div" if style_has(element, "-en-codeblock:true") => self.start_pre(),
+            "div" => {
+                self.end_line();
+                Closer::Line
+            }
+            "p" => {
+                self.end_block();
+                Closer::Block
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.end_block();
+                if !self.in_cell() {
+                    let level = usize::from(name.as_bytes()[1] - b'0');
+                    self.line_prefix = format!("{} ", "#".repeat(level));
+                }
+                Closer::Block
+            }
+            "pre" => self.start_pre(),
+            "blockquote" => {
+                self.end_block();
+                self.quote_depth += 1;
+                Closer::Quote
+            }
+            "hr" => {
+                self.end_block();
+                if !self.in_cell() {
+                    self.out.push_str("---\n\n");
+                }
+                Closer::Nothing
+            }
+            "br" => {
+                if self.pre_depth > 0 {
+                    self.line.push('\n');
+                } else if self.line.trim().is_empty() && !self.in_cell() {
+                    self.end_block();
+                } else {
+                    self.end_line();
+                }
+                Closer::Nothing
+            }
+            "ul" | "ol" => {
+                let indent = match self.lists.last() {
+                    Some(parent) => format!("{}{}", parent.indent, " ".repeat(parent.marker_width)),
+                    None => {
+                        self.end_block();
+                        String::new()
+                    }
+                };
+                self.end_line();
+                self.lists.push(ListState {
+                    ordered: name == "ol",
+                    next_number: attribute(element, "start")
+                        .and_then(|start| start.parse().ok())
+                        .unwrap_or(1),
+                    todo: style_has(element, "--en-todo:true"),
+                    indent,
+                    marker_width: 2,
+                });
+                Closer::List
+            }
+            "li" => {
+                self.end_line();
+                let checked = style_has(element, "--en-checked:true");
+                if let Some(list) = self.lists.last_mut() {
+                    let marker = if list.ordered {
+                        let marker = format!("{}. ", list.next_number);
+                        list.next_number += 1;
+                        marker
+                    } else if list.todo {
+                        format!("- [{}] ", if checked { "x" } else { " " })
+                    } else {
+                        "- ".to_string()
+                    };
+                    list.marker_width = if list.todo { 2 } else { marker.len() };
+                    self.line_prefix = format!("{}{marker}", list.indent);
+                }
+                Closer::Line
+            }
+            "table" if self.table.is_none() => {
+                self.end_block();
+                self.table = Some(TableState::default());
+                Closer::Table
+            }
+            "tr" if !self.in_cell() => {
+                if let Some(table) = self.table.as_mut() {
+                    table.rows.push(Vec::new());
+                }
+                Closer::Row
+            }
+            "td" | "th" if self.table.is_some() && !self.in_cell() => {
+                if let Some(table) = self.table.as_mut() {
+                    table.cell = Some(String::new());
+                }
+                Closer::Cell
+            }
+            "td" | "th" => {
+                self.push_raw(" ");
+                Closer::Nothing
+            }
+            "b" | "strong" => {
+                self.push_raw("**");
+                Closer::Inline("**")
+            }
+            "i" | "em" => {
+                self.push_raw("*");
+                Closer::Inline("*")
+            }
+            "s" | "strike" | "del" => {
+                self.push_raw("~~");
+                Closer::Inline("~~")
+            }
+            "code" if self.pre_depth == 0 => {
+                self.push_raw("`");
+                Closer::Inline("`")
+            }
+            "a" => match attribute(element, "href").filter(|href| !href.trim().is_empty()) {
+                Some(href) => {
+                    self.push_raw("[");
+                    Closer::Link(href.trim().to_string())
+                }
+                None => Closer::Nothing,
+            },
+            "img" => {
+                if let Some(src) = attribute(element, "src") {
+                    let alt = attribute(element, "alt").unwrap_or_default();
+                    let image =
+                        format!("![{}]({})", escape_link_text(&alt), link_destination(&src));
+                    self.push_raw(&image);
+                }
+                Closer::Nothing
+            }
+            "en-media" => {
+                if let Some(hash) = attribute(element, "hash") {
+                    self.push_media(&hash.to_ascii_lowercase());
+                }
+                Closer::Nothing
+            }
+            "en-todo" => {
+                self.push_todo(attribute(element, "checked").as_deref() == Some("true"));
+                Closer::Nothing
+            }
+            _ => Closer::Nothing,
+        };
+        Ok(closer)
+    }
+
+    fn end(&mut self, closer: Closer) {
+        match closer {
+            Closer::Nothing => {}
+            Closer::Line => self.end_line(),
+            Closer::Block => self.end_block(),
+            Closer::Inline(marker) => self.push_raw(marker),
+            Closer::Link(href) => {
+                let destination = format!("]({})", link_destination(&href));
+                self.push_raw(&destination);
+            }
+            Closer::List => {
+                self.end_line();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.end_block();
+                }
+            }
+            Closer::Quote => {
+                self.end_block();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            Closer::Pre => {
+                self.pre_depth = self.pre_depth.saturating_sub(1);
+                if self.pre_depth == 0 {
+                    let code = std::mem::take(&mut self.line);
+                    self.out.push_str(code.trim_end_matches('\n'));
+                    self.out.push_str("\n```\n\n");
+                }
+            }
+            Closer::Row => {}
+            Closer::Cell => {
+                if let Some(table) = self.table.as_mut() {
+                    let cell = table.cell.take().unwrap_or_default();
+                    let cell = cell.trim().trim_end_matches("<br>").replace('|', "\\|");
+                    if let Some(row) = table.rows.last_mut() {
+                        row.push(cell);
+                    }
+                }
+            }
+            Closer::Table => {
+                let table = self.table.take().unwrap_or_default();
+                let rows: Vec<_> = table
+                    .rows
+                    .into_iter()
+                    .filter(|row| !row.is_empty())
+                    .collect();
+                let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+                for (index, row) in rows.iter().enumerate() {
+                    let cells: Vec<&str> = (0..columns)
+                        .map(|column| row.get(column).map(String::as_str).unwrap_or_default())
+                        .collect();
+                    self.out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                    if index == 0 {
+                        self.out
+                            .push_str(&format!("|{}\n", " --- |".repeat(columns)));
+                    }
+                }
+                self.end_block();
+            }
+        }
+    }
+}
+
+/// Converts an ENML document to Markdown. Resources no element referenced
+/// are listed at the end so nothing in the note is dropped.
+fn enml_to_markdown(enml: &str, media: &HashMap<String, MediaLink>) -> Result<String, String> {
+    let mut reader = Reader::from_str(enml);
+    let mut writer = MarkdownWriter::new(media);
+    let mut closers: Vec<Closer> = Vec::new();
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(element) => {
+                let closer = writer.start(&element)?;
+                closers.push(closer);
+            }
+            Event::Empty(element) => {
+                let closer = writer.start(&element)?;
+                writer.end(closer);
+            }
+            Event::End(_) => {
+                let closer = closers.pop().unwrap_or(Closer::Nothing);
+                writer.end(closer);
+            }
+            Event::Text(content) => writer.push_text(&content.decode().map_err(xml_error)?),
+            Event::CData(content) => writer.push_text(&content.decode().map_err(xml_error)?),
+            Event::GeneralRef(reference) => {
+                let text = resolve_reference(&reference)?.replace('\u{a0}', " ");
+                writer.push_text(&text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    writer.end_block();
+
+    let mut unused: Vec<_> = media
+        .iter()
+        .filter(|(hash, _)| !writer.used_media.contains(*hash))
+        .map(|(_, media)| media)
+        .collect();
+    unused.sort_by(|left, right| left.name.cmp(&right.name));
+    for media in unused {
+        let bang = if media.is_image { "!" } else { "" };
+        writer.out.push_str(&format!(
+            "{bang}[{}]({})\n\n",
+            escape_link_text(&media.name),
+            media.link_target
+        ));
+    }
+
+    Ok(format!("{}\n", writer.out.trim_end()))
+}
+
+// ── Staging ──
+
+fn extension_for_mime(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/bmp" => "bmp",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        "audio/mpeg" => "mp3",
+        "audio/wav" | "audio/x-wav" => "wav",
+        "video/mp4" => "mp4",
+        _ => "bin",
+    }
+}
+
+/// Stores a note's resources as attachments in the staging library, keyed by
+/// the MD5 hash ENML uses to reference them.
+fn stage_resources(
+    staging_dir: &Path,
+    folder: &str,
+    resources: &[EnexResource],
+) -> Result<HashMap<String, MediaLink>, String> {
+    let mut media = HashMap::new();
+    for (index, resource) in resources.iter().enumerate() {
+        let encoded: String = resource
+            .data
+            .chars()
+            .filter(|ch| !ch.is_whitespace())
+            .collect();
+        let bytes = BASE64
+            .decode(encoded)
+            .map_err(|error| format!("resource {} could not be decoded: {error}", index + 1))?;
+        let name = resource.file_name.clone().unwrap_or_else(|| {
+            format!(
+                "attachment-{}.{}",
+                index + 1,
+                extension_for_mime(&resource.mime)
+            )
+        });
+        let saved = attachments::save_attachment(staging_dir, None, Some(folder), &name, &bytes)?;
+        let hash = Md5::digest(&bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        media.insert(
+            hash,
+            MediaLink {
+                name,
+                link_target: saved.link_target,
+                is_image: resource.mime.starts_with("image/"),
+            },
+        );
+    }
+    Ok(media)
+}
+
+/// Folder for an ENEX file: Evernote exports one notebook per file, named
+/// after the notebook.
+fn notebook_folder(enex_path: &Path, parent_folder: &str) -> String {
+    let name: String = enex_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .chars()
+        .filter(|ch| {
+            !ch.is_control() && !matches!(ch, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+        })
+        .collect();
+    let name = name.trim().trim_matches('.').trim();
+    let join = |name: &str| {
+        if parent_folder.is_empty() {
+            name.to_string()
+        } else {
+            format!("{parent_folder}/{name}")
+        }
+    };
+    match sanitize_folder_path(Some(&join(name))) {
+        Ok(folder) if !name.is_empty() => folder,
+        _ => join(FALLBACK_NOTEBOOK_NAME),
+    }
+}
+
+fn stage_note(
+    staging: &mut ImportStaging,
+    paths: &mut UniqueNotePaths,
+    folder: &str,
+    note: &EnexNote,
+) -> Result<(), String> {
+    let media = stage_resources(staging.path(), folder, &note.resources)?;
+    let body = enml_to_markdown(&note.content, &media)?;
+    let title = Some(note.title.clone()).filter(|title| !title.is_empty());
+    let created_at = note.created.or(note.updated).unwrap_or_else(now_millis);
+    let markdown = render_front_matter(
+        &FrontMatter {
+            title: title.clone(),
+            tags: note.tags.clone(),
+            created: Some(created_at),
+            ..FrontMatter::default()
+        },
+        &body,
+    );
+    let relative_path = paths.allocate(folder, &slugify_title(title.as_deref().unwrap_or("")));
+    staging.add_note(
+        &relative_path,
+        StagedNote {
+            markdown: &markdown,
+            created_at,
+            updated_at: note.updated.or(note.created),
+            manual_title: title,
+        },
+    )
+}
+
+// ── Public API ──
+
+/// Imports Evernote `.enex` exports into `folder_path`, one folder per
+/// notebook. Notes are converted into a staging library and merged with
+/// `migrate_notes`, so nothing in the library is ever overwritten. Notes that
+/// cannot be converted are reported and skipped.
+pub fn import_enex_files(
+    enex_paths: &[PathBuf],
+    folder_path: Option<&str>,
+    auto_save_dir: &Path,
+) -> Result<EnexImportResult, String> {
+    let parent_folder = sanitize_folder_path(folder_path)?;
+    TrustedLibraryRoot::open(auto_save_dir)?;
+
+    let mut staging = ImportStaging::create("enex")?;
+    let mut paths = UniqueNotePaths::default();
+    let mut failures = Vec::new();
+    for enex_path in enex_paths {
+        let source_path = enex_path.to_string_lossy().to_string();
+        let notes = fs::read_to_string(enex_path)
+            .map_err(|error| error.to_string())
+            .and_then(|xml| parse_enex(&xml));
+        let notes = match notes {
+            Ok(notes) => notes,
+            Err(reason) => {
+                failures.push(EnexImportFailure {
+                    source_path,
+                    title: None,
+                    reason,
+                });
+                continue;
+            }
+        };
+        let folder = notebook_folder(enex_path, &parent_folder);
+        for note in &notes {
+            if let Err(reason) = stage_note(&mut staging, &mut paths, &folder, note) {
+                failures.push(EnexImportFailure {
+                    source_path: source_path.clone(),
+                    title: Some(note.title.clone()),
+                    reason,
+                });
+            }
+        }
+    }
+    let result = if staging.is_empty() {
+        Ok(None)
+    } else {
+        staging.merge_into(auto_save_dir).map(Some)
+    };
+
+    let migration = result?;
+    Ok(EnexImportResult {
+        imported: migration
+            .as_ref()
+            .map(|migration| migration.notes.clone())
+            .unwrap_or_default(),
+        attachments_imported: migration
+            .map(|migration| migration.attachments_copied)
+            .unwrap_or_default(),
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{load_markdown_library, read_index};
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-{}-{}-{}",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn enml_converts_checklists_headings_links_and_tables() {
+        let enml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><h2>Plan&nbsp;A</h2>
+<div><en-todo checked="true"/>Book flights</div>
+<div><en-todo/>Pack <b>light</b></div>
+<ul style="--en-todo:true;"><li style="--en-checked:false;"><div>Call home</div></li></ul>
+<div><br/></div>
+<div>See <a href="https://example.com/a b">the docs</a></div>
+<ol><li>One<ul><li>Nested</li></ul></li><li>Two</li></ol>
+<table><tr><th>Day</th><th>City</th></tr><tr><td>Mon</td><td>Seoul | Busan</td></tr></table>
+<div style="-en-codeblock:true;"><div>let x = 1;</div><div>x + 1</div></div>
+<div><en-media hash="ABC" type="image/png"/></div>
+</en-note>"#;
+        let media = HashMap::from([(
+            "abc".to_string(),
+            MediaLink {
+                name: "map.png".to_string(),
+                link_target: "../.hwan-note-assets/map.png".to_string(),
+                is_image: true,
+            },
+        )]);
+        assert_eq!(
+            enml_to_markdown(enml, &media).unwrap(),
+            "## Plan A\n\n\
+             - [x] Book flights\n\
+             - [ ] Pack **light**\n\n\
+             - [ ] Call home\n\n\
+             See [the docs](<https://example.com/a b>)\n\n\
+             1. One\n   - Nested\n2. Two\n\n\
+             | Day | City |\n| --- | --- |\n| Mon | Seoul \\| Busan |\n\n\
+             ```\nlet x = 1;\nx + 1\n```\n\n\
+             ![map.png](../.hwan-note-assets/map.png)\n"
+        );
+        assert!(enml_to_markdown("<en-note><en-crypt>x</en-crypt></en-note>", &media).is_err());
+        assert_eq!(
+            parse_enex_timestamp("20240301T120000Z"),
+            Some(1_709_294_400_000)
+        );
+    }
+
+    #[test]
+    fn enex_import_keeps_times_tags_and_resources_and_reports_failures() {
+        let src = make_temp_dir("enex-src");
+        let dst = make_temp_dir("enex-dst");
+        let result = (|| -> Result<(), String> {
+            let png = BASE64.encode(b"not really a png");
+            let hash = Md5::digest(b"not really a png")
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>();
+            let enex = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export>
+<note><title>Trip &amp; plans</title>
+<content><![CDATA[<en-note><div>Hello<en-media hash="{hash}" type="image/png"/></div></en-note>]]></content>
+<created>20240301T120000Z</created><updated>20240302T080000Z</updated>
+<tag>travel</tag><tag>2024</tag>
+<resource><data encoding="base64">
+{png}
+</data><mime>image/png</mime><resource-attributes><file-name>map.png</file-name></resource-attributes></resource>
+</note>
+<note><title>Secret</title><content><![CDATA[<en-note><en-crypt>abc</en-crypt></en-note>]]></content></note>
+</en-export>"#
+            );
+            let enex_path = src.join("Travel Log.enex");
+            fs::write(&enex_path, enex).unwrap();
+            let broken_path = src.join("Broken.enex");
+            fs::write(&broken_path, "<en-export><note>").unwrap();
+
+            let imported = import_enex_files(&[enex_path, broken_path], Some("imports"), &dst)?;
+            assert_eq!(imported.imported.len(), 1);
+            assert_eq!(imported.attachments_imported, 1);
+            assert_eq!(imported.failures.len(), 2);
+            assert_eq!(imported.failures[0].title.as_deref(), Some("Secret"));
+            assert_eq!(imported.failures[1].title, None);
+
+            let index = read_index(&dst)?;
+            let entry = index.entries.values().next().unwrap();
+            assert_eq!(entry.relative_path, "imports/Travel Log/Trip-&-plans.md");
+            assert_eq!(entry.created_at, 1_709_294_400_000);
+
+            let load = load_markdown_library(&dst);
+            let note = &load.notes[0];
+            assert_eq!(note.title, "Trip & plans");
+            assert_eq!(note.tags, vec!["travel", "2024"]);
+            assert_eq!(note.updated_at, 1_709_366_400_000);
+            let attachment = attachments::referenced_attachments(&note.markdown)
+                .next()
+                .unwrap()
+                .to_string();
+            assert!(note.markdown.contains(&format!(
+                "Hello![map.png](../../.hwan-note-assets/{attachment})"
+            )));
+            assert!(dst
+                .join(attachments::ATTACHMENTS_DIRECTORY_NAME)
+                .join(&attachment)
+                .exists());
+            Ok(())
+        })();
+        let _ = fs::remove_dir_all(&src);
+        let _ = fs::remove_dir_all(&dst);
+        result.unwrap();
+    }
+}
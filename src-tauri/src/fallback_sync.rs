@@ -0,0 +1,338 @@
+use crate::file_manager::{
+    note_file_state, note_file_states, read_trusted_file_bytes, sanitize_note_id, sha256_hex,
+    write_library_file_atomically, NoteFileState, TrustedLibraryRoot, CALENDAR_FILENAME,
+};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+/// Library file listing what was written to the local library while the
+/// cloud library was unreachable.
+const FALLBACK_CHANGES_FILENAME: &str = ".hwan-note-fallback-changes.json";
+
+static FALLBACK_CHANGES_LOCK: Mutex<()> = Mutex::new(());
+
+fn lock_fallback_changes() -> MutexGuard<'static, ()> {
+    FALLBACK_CHANGES_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// ── Types ──
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FallbackChanges {
+    #[serde(default)]
+    pub notes: BTreeMap<String, FallbackChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<FallbackChange>,
+}
+
+/// One file changed in fallback mode. `base_digest` is the SHA-256 of the
+/// file before its first fallback change, or `None` when it did not exist;
+/// the cloud copy still matching it means nobody else changed it since.
+/// `base_path` is where a note was before that change, so a note moved in
+/// fallback mode can be moved in the cloud library too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FallbackChange {
+    pub base_digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_path: Option<String>,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Every note's file and the calendar digest, taken before a command changes
+/// the local library so `record_library_changes` can tell what it touched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LibraryState {
+    notes: BTreeMap<String, NoteFileState>,
+    calendar: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FallbackChangesSummary {
+    pub notes: usize,
+    pub deleted_notes: usize,
+    pub calendar: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileOutcome {
+    /// Added to the cloud library under its own path and id.
+    Copied,
+    /// Added under a new path or id because the original was taken.
+    Renamed,
+    /// Replaced the cloud copy, which nobody else had changed.
+    Updated,
+    /// The cloud copy already matched.
+    Unchanged,
+    /// Moved the cloud copy to the trash.
+    Deleted,
+    /// Both sides changed. The local version was added next to the cloud
+    /// one, or for a deletion the cloud copy was kept.
+    Conflict,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciledNote {
+    pub note_id: String,
+    pub destination_path: Option<String>,
+    pub outcome: ReconcileOutcome,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FallbackReconcileResult {
+    pub notes: Vec<ReconciledNote>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<ReconcileOutcome>,
+    /// Where the local calendar was kept when both calendars changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calendar_copy_path: Option<String>,
+}
+
+// ── Change journal ──
+
+fn read_changes_unlocked(trusted_root: &TrustedLibraryRoot) -> Result<FallbackChanges, String> {
+    let path = trusted_root.file_path(FALLBACK_CHANGES_FILENAME, false)?;
+    match fs::symlink_metadata(&path) {
+        Ok(_) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Ok(FallbackChanges::default())
+        }
+        Err(error) => {
+            return Err(format!(
+                "read_fallback_changes failed for {}: {error}",
+                path.display()
+            ))
+        }
+    }
+    let bytes = read_trusted_file_bytes(trusted_root, &path, "read_fallback_changes")?;
+    serde_json::from_slice(&bytes).map_err(|error| {
+        format!(
+            "read_fallback_changes failed for {}: {error}",
+            path.display()
+        )
+    })
+}
+
+fn write_changes_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    changes: &FallbackChanges,
+) -> Result<(), String> {
+    let path = trusted_root.file_path(FALLBACK_CHANGES_FILENAME, false)?;
+    let bytes = serde_json::to_vec_pretty(changes).map_err(|error| {
+        format!(
+            "write_fallback_changes failed for {}: {error}",
+            path.display()
+        )
+    })?;
+    write_library_file_atomically(trusted_root, &path, &bytes)
+}
+
+fn calendar_digest(trusted_root: &TrustedLibraryRoot) -> Result<Option<String>, String> {
+    let path = trusted_root.file_path(CALENDAR_FILENAME, false)?;
+    match fs::symlink_metadata(&path) {
+        Ok(_) => Ok(Some(sha256_hex(&read_trusted_file_bytes(
+            trusted_root,
+            &path,
+            "read_fallback_calendar",
+        )?))),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!(
+            "read_fallback_calendar failed for {}: {error}",
+            path.display()
+        )),
+    }
+}
+
+/// Notes that a note is about to be saved, or was just trashed when
+/// `deleted`, in the local library while the cloud library is unreachable.
+/// Call it before saving so the note's previous version is recorded.
+pub(crate) fn record_note_change(
+    local_dir: &Path,
+    note_id: &str,
+    deleted: bool,
+) -> Result<(), String> {
+    let trusted_root = TrustedLibraryRoot::open(local_dir)?;
+    let note_id = sanitize_note_id(note_id);
+    let _guard = lock_fallback_changes();
+    let mut changes = read_changes_unlocked(&trusted_root)?;
+    if let Some(change) = changes.notes.get_mut(&note_id) {
+        // Already recorded: keep the first base and only note a deletion.
+        if change.deleted == deleted {
+            return Ok(());
+        }
+        change.deleted = deleted;
+    } else {
+        let base = note_file_state(&trusted_root, &note_id)?;
+        changes.notes.insert(
+            note_id,
+            FallbackChange {
+                base_digest: base.as_ref().and_then(|base| base.digest.clone()),
+                base_path: base.map(|base| base.relative_path),
+                deleted,
+            },
+        );
+    }
+    write_changes_unlocked(&trusted_root, &changes)
+}
+
+/// Same as `record_note_change`, for the calendar file.
+pub(crate) fn record_calendar_change(local_dir: &Path) -> Result<(), String> {
+    let trusted_root = TrustedLibraryRoot::open(local_dir)?;
+    let _guard = lock_fallback_changes();
+    let mut changes = read_changes_unlocked(&trusted_root)?;
+    if changes.calendar.is_some() {
+        return Ok(());
+    }
+    changes.calendar = Some(FallbackChange {
+        base_digest: calendar_digest(&trusted_root)?,
+        base_path: None,
+        deleted: false,
+    });
+    write_changes_unlocked(&trusted_root, &changes)
+}
+
+pub(crate) fn capture_library_state(local_dir: &Path) -> Result<LibraryState, String> {
+    let trusted_root = TrustedLibraryRoot::open(local_dir)?;
+    Ok(LibraryState {
+        notes: note_file_states(&trusted_root)?,
+        calendar: calendar_digest(&trusted_root)?,
+    })
+}
+
+/// Records every note and the calendar that changed in the local library
+/// since `before` was captured, keeping the first base of anything already
+/// recorded. Notes that left the index are recorded as deleted.
+pub(crate) fn record_library_changes(
+    local_dir: &Path,
+    before: &LibraryState,
+) -> Result<(), String> {
+    let after = capture_library_state(local_dir)?;
+    if after == *before {
+        return Ok(());
+    }
+    let trusted_root = TrustedLibraryRoot::open(local_dir)?;
+    let _guard = lock_fallback_changes();
+    let mut changes = read_changes_unlocked(&trusted_root)?;
+    let note_ids = before
+        .notes
+        .keys()
+        .chain(after.notes.keys())
+        .collect::<BTreeSet<_>>();
+    for note_id in note_ids {
+        let previous = before.notes.get(note_id);
+        let current = after.notes.get(note_id);
+        if previous == current {
+            continue;
+        }
+        let deleted = current.is_none();
+        changes
+            .notes
+            .entry(note_id.clone())
+            .and_modify(|change| change.deleted = deleted)
+            .or_insert_with(|| FallbackChange {
+                base_digest: previous.and_then(|previous| previous.digest.clone()),
+                base_path: previous.map(|previous| previous.relative_path.clone()),
+                deleted,
+            });
+    }
+    if after.calendar != before.calendar && changes.calendar.is_none() {
+        changes.calendar = Some(FallbackChange {
+            base_digest: before.calendar.clone(),
+            base_path: None,
+            deleted: false,
+        });
+    }
+    write_changes_unlocked(&trusted_root, &changes)
+}
+
+pub(crate) fn read_changes(local_dir: &Path) -> Result<FallbackChanges, String> {
+    let trusted_root = TrustedLibraryRoot::open(local_dir)?;
+    let _guard = lock_fallback_changes();
+    read_changes_unlocked(&trusted_root)
+}
+
+pub(crate) fn summarize(changes: &FallbackChanges) -> FallbackChangesSummary {
+    let deleted_notes = changes
+        .notes
+        .values()
+        .filter(|change| change.deleted)
+        .count();
+    FallbackChangesSummary {
+        notes: changes.notes.len() - deleted_notes,
+        deleted_notes,
+        calendar: changes.calendar.is_some(),
+    }
+}
+
+/// Forgets the changes in `reconciled` once they reached the cloud library,
+/// keeping anything recorded since it was read.
+pub(crate) fn clear_changes(local_dir: &Path, reconciled: &FallbackChanges) -> Result<(), String> {
+    let trusted_root = TrustedLibraryRoot::open(local_dir)?;
+    let _guard = lock_fallback_changes();
+    let mut changes = read_changes_unlocked(&trusted_root)?;
+    changes
+        .notes
+        .retain(|note_id, change| reconciled.notes.get(note_id) != Some(change));
+    if changes.calendar == reconciled.calendar {
+        changes.calendar = None;
+    }
+    if changes != FallbackChanges::default() {
+        return write_changes_unlocked(&trusted_root, &changes);
+    }
+    let path = trusted_root.file_path(FALLBACK_CHANGES_FILENAME, false)?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(format!(
+            "clear_fallback_changes failed for {}: {error}",
+            path.display()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_saved_and_deleted_notes() {
+        let change = |deleted| FallbackChange {
+            base_digest: None,
+            base_path: None,
+            deleted,
+        };
+        let changes = FallbackChanges {
+            notes: BTreeMap::from([
+                ("a".to_string(), change(false)),
+                ("b".to_string(), change(true)),
+                ("c".to_string(), change(false)),
+            ]),
+            calendar: Some(change(false)),
+        };
+        assert_eq!(
+            summarize(&changes),
+            FallbackChangesSummary {
+                notes: 2,
+                deleted_notes: 1,
+                calendar: true,
+            }
+        );
+        assert_eq!(
+            summarize(&FallbackChanges::default()),
+            FallbackChangesSummary::default()
+        );
+    }
+}
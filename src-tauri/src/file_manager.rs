@@ -1,6 +1,18 @@
 use crate::atomic_file::{publish_temp_file, sync_parent_directory};
-
-use std::collections::{HashMap, HashSet};
+use crate::attachments;
+use crate::fallback_sync::{FallbackChange, ReconcileOutcome, ReconciledNote};
+use crate::front_matter::{render_front_matter, split_front_matter, FrontMatter};
+use crate::note_crypto;
+use crate::note_history::{self, NoteHistoryRetention};
+use crate::note_links::{self, LinkNode, PlannedRelink, RelinkedNote};
+use crate::note_merge::{self, NoteMerge};
+use crate::note_templates;
+use crate::note_trash::{self, FolderNoteMove};
+use crate::search_index::{self, SearchDocument};
+use crate::sync_conflicts::{conflict_copy_originals, SyncConflict, SyncConflictResolution};
+use crate::text_encoding::{decode_text, encode_text, encoding_for_label, TextFormat};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
@@ -13,10 +25,11 @@ use serde::{Deserialize, Serialize};
 use sha1::Sha1;
 use sha2::{Digest, Sha256};
 
-const INDEX_FILENAME: &str = ".hwan-note-index.json";
+pub(crate) const INDEX_FILENAME: &str = ".hwan-note-index.json";
 pub const CALENDAR_FILENAME: &str = "calendar.json";
 const AUTOSAVE_JOURNAL_FILENAME: &str = ".hwan-note-autosave.json";
 const AUTOSAVE_JOURNAL_TEMP_FILENAME: &str = ".hwan-note-autosave.json.next";
+const ENCRYPTED_NOTE_FILE_STEM: &str = "encrypted-note";
 const AUTOSAVE_TRANSACTION_VERSION: u32 = 1;
 
 static TOGGLE_BLOCK_RE: LazyLock<Regex> =
@@ -32,6 +45,18 @@ static PLAIN_TASK_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^(\s*)- \[[ xX]\]\s*").unwrap());
 static NOTE_INDEX_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 static AUTOSAVE_OPERATION_COUNTER: AtomicU64 = AtomicU64::new(0);
+static KNOWN_NOTE_DIGESTS: LazyLock<Mutex<HashMap<(PathBuf, String), String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Top-level library directories that hold app-managed data rather than notes.
+/// They are skipped by library scans and cannot be used as folder names.
+const RESERVED_LIBRARY_DIRECTORIES: &[&str] = &[
+    note_history::HISTORY_DIRECTORY_NAME,
+    attachments::ATTACHMENTS_DIRECTORY_NAME,
+    note_trash::TRASH_DIRECTORY_NAME,
+    note_templates::TEMPLATES_DIRECTORY_NAME,
+    note_merge::MERGE_BASES_DIRECTORY_NAME,
+];
 
 const TOGGLE_BLOCK_END: &str = ":::";
 const MANUAL_TITLE_META_PREFIX: &str = "<!-- hwan-note:manual-title:";
@@ -54,6 +79,10 @@ pub struct NoteIndexEntry {
     pub manual_title: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub is_pinned: Option<bool>,
+    /// SHA-256 digest of the note file each machine last saw, by machine id.
+    /// A stored copy of that version is the base for merging concurrent edits.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub seen_by: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -161,7 +190,7 @@ impl NoteLoadIssue {
         }
     }
 
-    fn display(&self) -> String {
+    pub(crate) fn display(&self) -> String {
         format!(
             "{} failed for {}: {}",
             self.operation, self.path, self.reason
@@ -178,6 +207,8 @@ pub struct MarkdownLibraryLoadResult {
     pub issues: Vec<NoteLoadIssue>,
     pub index_source_path: Option<String>,
     pub index_backup_path: Option<String>,
+    /// Sync-client conflict copies that were left out of the index.
+    pub conflicts: Vec<SyncConflict>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -199,6 +230,10 @@ pub struct AutoSaveResult {
     pub note_id: String,
     pub created_at: u64,
     pub updated_at: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relinked_notes: Vec<RelinkedNote>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge: Option<NoteMerge>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -214,6 +249,13 @@ pub struct LoadedNote {
     pub updated_at: u64,
     pub file_path: String,
     pub is_pinned: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Encrypted notes load with an empty title and body until unlocked.
+    #[serde(default)]
+    pub is_encrypted: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -221,21 +263,149 @@ pub struct LoadedNote {
 pub struct FolderDeleteResult {
     pub folders: Vec<String>,
     pub moved_note_ids: Vec<String>,
+    #[serde(default)]
+    pub relinked_notes: Vec<RelinkedNote>,
+    pub trash_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderRenameResult {
+    pub folders: Vec<String>,
+    pub relinked_notes: Vec<RelinkedNote>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MovedNote {
+    pub note_id: String,
+    pub previous_file_path: String,
+    pub file_path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteMoveResult {
+    pub moved: Vec<MovedNote>,
+    pub relinked_notes: Vec<RelinkedNote>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum NoteBatchOperation {
+    SetPinned {
+        note_id: String,
+        is_pinned: bool,
+    },
+    Move {
+        note_id: String,
+        folder_path: Option<String>,
+    },
+    Trash {
+        note_id: String,
+    },
+    SetManualTitle {
+        note_id: String,
+        title: Option<String>,
+    },
+}
+
+impl NoteBatchOperation {
+    fn note_id(&self) -> &str {
+        match self {
+            Self::SetPinned { note_id, .. }
+            | Self::Move { note_id, .. }
+            | Self::Trash { note_id }
+            | Self::SetManualTitle { note_id, .. } => note_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteBatchItemStatus {
+    Applied,
+    Failed,
+    NotApplied,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteBatchItemResult {
+    pub note_id: String,
+    pub status: NoteBatchItemStatus,
+    pub file_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteBatchResult {
+    pub committed: bool,
+    pub items: Vec<NoteBatchItemResult>,
+    pub relinked_notes: Vec<RelinkedNote>,
+}
+
+/// Absolute paths reported by the library watcher for one debounced batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ExternalChangeBatch {
+    pub(crate) changed_paths: Vec<PathBuf>,
+    pub(crate) renamed_paths: Vec<(PathBuf, PathBuf)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalNoteRemoval {
+    pub note_id: String,
+    pub file_path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalNoteMove {
+    pub note: LoadedNote,
+    pub previous_file_path: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalLibraryChanges {
+    pub added: Vec<LoadedNote>,
+    pub changed: Vec<LoadedNote>,
+    pub removed: Vec<ExternalNoteRemoval>,
+    pub moved: Vec<ExternalNoteMove>,
+    pub conflicts: Vec<SyncConflict>,
+    pub issues: Vec<NoteLoadIssue>,
+}
+
+impl ExternalLibraryChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.changed.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.conflicts.is_empty()
+            && self.issues.is_empty()
+    }
 }
 
 // ── Time helpers ──
 
-fn system_time_to_millis(time: SystemTime) -> u64 {
+pub(crate) fn system_time_to_millis(time: SystemTime) -> u64 {
     time.duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_millis() as u64
 }
 
-fn now_millis() -> u64 {
+pub(crate) fn now_millis() -> u64 {
     system_time_to_millis(SystemTime::now())
 }
 
-fn sha256_hex(bytes: &[u8]) -> String {
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
     let digest = Sha256::digest(bytes);
     format!("{digest:x}")
 }
@@ -295,6 +465,12 @@ fn is_invalid_folder_segment(segment: &str) -> bool {
         })
 }
 
+pub(crate) fn is_reserved_library_directory(name: &str) -> bool {
+    RESERVED_LIBRARY_DIRECTORIES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+}
+
 fn validate_folder_segment(segment: &str) -> Result<(), String> {
     if is_invalid_folder_segment(segment) {
         return Err(format!("Invalid folder name segment: {}", segment));
@@ -326,6 +502,12 @@ pub fn sanitize_folder_path(folder_path: Option<&str>) -> Result<String, String>
     for segment in &segments {
         validate_folder_segment(segment)?;
     }
+    if segments
+        .first()
+        .is_some_and(|segment| is_reserved_library_directory(segment))
+    {
+        return Err(format!("Reserved folder name: {}", segments[0]));
+    }
 
     let normalized = strip_inbox_root_alias(&segments.join("/"));
 
@@ -360,6 +542,7 @@ fn normalize_manual_title(title: &str) -> Option<String> {
     }
 }
 
+#[cfg(test)]
 fn encode_manual_title_hex(title: &str) -> String {
     let mut encoded = String::with_capacity(title.len() * 2);
     for byte in title.as_bytes() {
@@ -404,25 +587,27 @@ fn parse_manual_title_metadata_line(line: &str) -> Option<String> {
     decode_manual_title_hex(encoded)
 }
 
-fn extract_manual_title_metadata(markdown: &str) -> (Option<String>, String) {
+/// Splits note metadata from the Markdown body. Reads the YAML front matter
+/// block and, for notes written by older versions, the hex manual-title
+/// comment line, whose title is folded into the returned front matter.
+pub(crate) fn extract_note_metadata(markdown: &str) -> (FrontMatter, String) {
     let normalized = markdown.replace("\r\n", "\n");
+    let (first_line, rest) = normalized
+        .split_once('\n')
+        .unwrap_or((normalized.as_str(), ""));
+    let (legacy_title, markdown) = match parse_manual_title_metadata_line(first_line) {
+        Some(title) => (Some(title), rest),
+        None => (None, normalized.as_str()),
+    };
 
-    match normalized.split_once('\n') {
-        Some((first_line, rest)) => {
-            if let Some(title) = parse_manual_title_metadata_line(first_line) {
-                (Some(title), rest.to_string())
-            } else {
-                (None, normalized)
-            }
-        }
-        None => {
-            if let Some(title) = parse_manual_title_metadata_line(&normalized) {
-                (Some(title), String::new())
-            } else {
-                (None, normalized)
-            }
-        }
-    }
+    let (front_matter, body) = split_front_matter(markdown);
+    let mut front_matter = front_matter.unwrap_or_default();
+    front_matter.title = front_matter
+        .title
+        .as_deref()
+        .and_then(normalize_manual_title)
+        .or(legacy_title);
+    (front_matter, body.to_string())
 }
 
 #[derive(Debug)]
@@ -507,7 +692,7 @@ impl TrustedLibraryRoot {
     }
 }
 
-fn metadata_is_symlink_or_reparse_point(metadata: &fs::Metadata) -> bool {
+pub(crate) fn metadata_is_symlink_or_reparse_point(metadata: &fs::Metadata) -> bool {
     let is_symlink = metadata.file_type().is_symlink();
 
     #[cfg(windows)]
@@ -776,7 +961,7 @@ fn canonicalize_expected_library_path(
     Ok(candidate)
 }
 
-fn ensure_library_subdirectory(
+pub(crate) fn ensure_library_subdirectory(
     trusted_root: &TrustedLibraryRoot,
     relative_path: &Path,
 ) -> Result<PathBuf, String> {
@@ -843,28 +1028,32 @@ fn ensure_library_subdirectory(
     Ok(current)
 }
 
-fn embed_manual_title_metadata(markdown: &str, manual_title: Option<&str>) -> String {
-    let (_, stripped_markdown) = extract_manual_title_metadata(markdown);
-    let Some(title) = manual_title.and_then(normalize_manual_title) else {
-        return stripped_markdown;
-    };
-
-    let metadata_line = format!(
-        "{}{}{}",
-        MANUAL_TITLE_META_PREFIX,
-        encode_manual_title_hex(&title),
-        MANUAL_TITLE_META_SUFFIX
-    );
-
-    if stripped_markdown.is_empty() {
-        metadata_line
+/// Builds the stored form of a note: the editor body under a front matter
+/// block. Metadata the editor does not manage (tags, aliases, unknown keys) is
+/// carried over from the previous file unless the content brings its own block.
+fn embed_note_metadata(
+    content: &str,
+    previous_markdown: Option<&str>,
+    manual_title: Option<&str>,
+    is_pinned: bool,
+    created_at: u64,
+) -> String {
+    let (content_metadata, body) = extract_note_metadata(content);
+    let mut front_matter = if content_metadata != FrontMatter::default() {
+        content_metadata
     } else {
-        format!("{}\n{}", metadata_line, stripped_markdown)
-    }
+        previous_markdown
+            .map(|markdown| extract_note_metadata(markdown).0)
+            .unwrap_or_default()
+    };
+    front_matter.title = manual_title.and_then(normalize_manual_title);
+    front_matter.pinned = is_pinned.then_some(true);
+    front_matter.created = Some(created_at);
+    render_front_matter(&front_matter, &body)
 }
 
 pub fn derive_title(markdown: &str) -> String {
-    let (_, normalized) = extract_manual_title_metadata(markdown);
+    let (_, normalized) = extract_note_metadata(markdown);
     let first_line = normalized
         .split('\n')
         .map(|line| line.trim())
@@ -896,7 +1085,7 @@ pub fn derive_title(markdown: &str) -> String {
 }
 
 pub fn markdown_to_plain_text(markdown: &str) -> String {
-    let (_, normalized) = extract_manual_title_metadata(markdown);
+    let (_, normalized) = extract_note_metadata(markdown);
     normalized
         .split('\n')
         .map(|line| {
@@ -1151,6 +1340,18 @@ fn require_index_snapshot(trusted_root: &TrustedLibraryRoot) -> Result<IndexSnap
     }
 }
 
+fn index_snapshot_digest(snapshot: &IndexSnapshot) -> Option<String> {
+    snapshot.original_bytes.as_deref().map(sha256_hex)
+}
+
+/// Digest of the index bytes currently on disk, or `None` when no index has
+/// been written yet. Derived caches use it to detect that they are stale.
+pub(crate) fn current_index_digest(
+    trusted_root: &TrustedLibraryRoot,
+) -> Result<Option<String>, String> {
+    require_index_snapshot(trusted_root).map(|snapshot| index_snapshot_digest(&snapshot))
+}
+
 #[cfg(test)]
 pub fn read_index(auto_save_dir: &Path) -> Result<NoteIndex, String> {
     let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
@@ -1309,8 +1510,9 @@ fn index_write_failure_to_string(failure: IndexWriteFailure) -> String {
     }
 }
 
-#[cfg(test)]
-pub fn write_index(auto_save_dir: &Path, index: &NoteIndex) -> Result<(), String> {
+/// Replaces the index without taking the index lock, so it is only for
+/// libraries nothing else can see yet, such as an import's staging directory.
+pub(crate) fn write_index(auto_save_dir: &Path, index: &NoteIndex) -> Result<(), String> {
     let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
     let expected = require_index_snapshot(&trusted_root)?;
     write_index_from_snapshot(&trusted_root, &expected, index)
@@ -1547,6 +1749,12 @@ fn scan_library_directory<F: LibraryFileSystem>(
         };
 
         match entry_type {
+            LibraryEntryType::Directory
+                if path.parent() == Some(trusted_root.path())
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(is_reserved_library_directory) => {}
             LibraryEntryType::Directory => {
                 let relative = strip_inbox_root_alias(&relative_path(trusted_root.path(), &path));
                 if !relative.is_empty() {
@@ -1562,75 +1770,7 @@ fn scan_library_directory<F: LibraryFileSystem>(
                 );
             }
             LibraryEntryType::File if include_markdown && is_markdown_path(&path) => {
-                let canonical_path = match file_system.canonicalize_path(&path) {
-                    Ok(canonical_path) if canonical_path.starts_with(trusted_root.path()) => {
-                        canonical_path
-                    }
-                    Ok(canonical_path) => {
-                        scan.issues.push(NoteLoadIssue::new(
-                            NoteLoadIssueKind::Scan,
-                            "validate_canonical_file_path",
-                            &path,
-                            format!(
-                                "resolved to {} outside the canonical note library root {}",
-                                canonical_path.display(),
-                                trusted_root.path().display()
-                            ),
-                        ));
-                        continue;
-                    }
-                    Err(error) => {
-                        scan.issues.push(scan_issue(error));
-                        continue;
-                    }
-                };
-                let markdown = file_system.read_markdown(&canonical_path);
-                let metadata = file_system.markdown_metadata(&canonical_path);
-
-                let markdown = match markdown {
-                    Ok(markdown) => Some(markdown),
-                    Err(error) => {
-                        scan.issues.push(scan_issue(error));
-                        None
-                    }
-                };
-                let timestamps = match metadata {
-                    Ok(metadata) => match metadata.modified() {
-                        Ok(modified) => {
-                            let updated_at = system_time_to_millis(modified);
-                            let created_at = metadata
-                                .created()
-                                .map(system_time_to_millis)
-                                .unwrap_or(updated_at);
-                            Some((created_at, updated_at))
-                        }
-                        Err(error) => {
-                            scan.issues.push(NoteLoadIssue::new(
-                                NoteLoadIssueKind::FileMetadata,
-                                "read_modified_time",
-                                &canonical_path,
-                                error.to_string(),
-                            ));
-                            None
-                        }
-                    },
-                    Err(error) => {
-                        scan.issues.push(scan_issue(error));
-                        None
-                    }
-                };
-
-                if let (Some(markdown), Some((created_at, updated_at))) = (markdown, timestamps) {
-                    scan.files.insert(
-                        relative_path(trusted_root.path(), &canonical_path),
-                        ScannedMarkdown {
-                            full_path: canonical_path,
-                            markdown,
-                            created_at,
-                            updated_at,
-                        },
-                    );
-                }
+                scan_markdown_file(file_system, trusted_root, &path, scan);
             }
             LibraryEntryType::Symlink => {
                 scan.issues.push(NoteLoadIssue::new(
@@ -1645,6 +1785,81 @@ fn scan_library_directory<F: LibraryFileSystem>(
     }
 }
 
+fn scan_markdown_file<F: LibraryFileSystem>(
+    file_system: &F,
+    trusted_root: &TrustedLibraryRoot,
+    path: &Path,
+    scan: &mut LibraryScan,
+) {
+    let canonical_path = match file_system.canonicalize_path(path) {
+        Ok(canonical_path) if canonical_path.starts_with(trusted_root.path()) => canonical_path,
+        Ok(canonical_path) => {
+            scan.issues.push(NoteLoadIssue::new(
+                NoteLoadIssueKind::Scan,
+                "validate_canonical_file_path",
+                path,
+                format!(
+                    "resolved to {} outside the canonical note library root {}",
+                    canonical_path.display(),
+                    trusted_root.path().display()
+                ),
+            ));
+            return;
+        }
+        Err(error) => {
+            scan.issues.push(scan_issue(error));
+            return;
+        }
+    };
+    let markdown = file_system.read_markdown(&canonical_path);
+    let metadata = file_system.markdown_metadata(&canonical_path);
+
+    let markdown = match markdown {
+        Ok(markdown) => Some(markdown),
+        Err(error) => {
+            scan.issues.push(scan_issue(error));
+            None
+        }
+    };
+    let timestamps = match metadata {
+        Ok(metadata) => match metadata.modified() {
+            Ok(modified) => {
+                let updated_at = system_time_to_millis(modified);
+                let created_at = metadata
+                    .created()
+                    .map(system_time_to_millis)
+                    .unwrap_or(updated_at);
+                Some((created_at, updated_at))
+            }
+            Err(error) => {
+                scan.issues.push(NoteLoadIssue::new(
+                    NoteLoadIssueKind::FileMetadata,
+                    "read_modified_time",
+                    &canonical_path,
+                    error.to_string(),
+                ));
+                None
+            }
+        },
+        Err(error) => {
+            scan.issues.push(scan_issue(error));
+            None
+        }
+    };
+
+    if let (Some(markdown), Some((created_at, updated_at))) = (markdown, timestamps) {
+        scan.files.insert(
+            relative_path(trusted_root.path(), &canonical_path),
+            ScannedMarkdown {
+                full_path: canonical_path,
+                markdown,
+                created_at,
+                updated_at,
+            },
+        );
+    }
+}
+
 fn scan_library_tree<F: LibraryFileSystem>(
     file_system: &F,
     trusted_root: &TrustedLibraryRoot,
@@ -1851,7 +2066,7 @@ fn validate_existing_trusted_file(
     Ok(())
 }
 
-fn read_trusted_file_bytes(
+pub(crate) fn read_trusted_file_bytes(
     trusted_root: &TrustedLibraryRoot,
     path: &Path,
     operation: &str,
@@ -2072,7 +2287,7 @@ fn current_index_matches_next_index(
     Ok(require_index_snapshot(trusted_root)?.index == *expected_index)
 }
 
-fn validate_trusted_publish_destination(
+pub(crate) fn validate_trusted_publish_destination(
     trusted_root: &TrustedLibraryRoot,
     destination: &Path,
     operation: &str,
@@ -2665,7 +2880,7 @@ fn recover_pending_note_save_unlocked(trusted_root: &TrustedLibraryRoot) -> Resu
 fn write_note_file_atomically_after_temp_hook<F>(
     trusted_root: &TrustedLibraryRoot,
     destination: &Path,
-    content: &str,
+    content: &[u8],
     before_destination_validation: F,
 ) -> Result<(), String>
 where
@@ -2674,7 +2889,7 @@ where
     validate_note_destination_before_replace(trusted_root, destination)?;
     let (tmp_path, mut tmp_file) = unique_note_temp_file(destination)?;
     let write_result = tmp_file
-        .write_all(content.as_bytes())
+        .write_all(content)
         .and_then(|()| tmp_file.sync_all());
     if let Err(error) = write_result {
         drop(tmp_file);
@@ -2699,12 +2914,22 @@ where
     Ok(())
 }
 
-fn write_note_file_atomically(
+pub(crate) fn write_note_file_atomically(
     trusted_root: &TrustedLibraryRoot,
     destination: &Path,
     content: &str,
 ) -> Result<(), String> {
-    write_note_file_atomically_after_temp_hook(trusted_root, destination, content, || {})
+    write_note_file_atomically_after_temp_hook(trusted_root, destination, content.as_bytes(), || {})
+}
+
+/// Same atomic replace as `write_note_file_atomically`, for binary library
+/// files such as attachments.
+pub(crate) fn write_library_file_atomically(
+    trusted_root: &TrustedLibraryRoot,
+    destination: &Path,
+    bytes: &[u8],
+) -> Result<(), String> {
+    write_note_file_atomically_after_temp_hook(trusted_root, destination, bytes, || {})
 }
 
 fn relative_path(from: &Path, to: &Path) -> String {
@@ -2714,6 +2939,48 @@ fn relative_path(from: &Path, to: &Path) -> String {
     }
 }
 
+/// Takes sync-client conflict copies of indexed notes out of `scan`, so they
+/// are reported for resolution instead of being indexed as unrelated notes.
+/// A copy whose original is missing is left alone and indexed as usual.
+fn split_sync_conflicts(index: &NoteIndex, scan: &mut LibraryScan) -> Vec<SyncConflict> {
+    let ids_by_path: HashMap<&str, &str> = index
+        .entries
+        .iter()
+        .map(|(note_id, entry)| (entry.relative_path.as_str(), note_id.as_str()))
+        .collect();
+
+    let mut conflicts = Vec::new();
+    let mut relative_paths = scan.files.keys().cloned().collect::<Vec<_>>();
+    relative_paths.sort();
+    for relative_path in relative_paths {
+        if ids_by_path.contains_key(relative_path.as_str()) {
+            continue;
+        }
+        let original = conflict_copy_originals(&relative_path)
+            .into_iter()
+            .find_map(|original| {
+                let note_id = ids_by_path.get(original.as_str())?;
+                let scanned = scan.files.get(&original)?;
+                Some((note_id.to_string(), scanned.full_path.clone()))
+            });
+        let Some((note_id, original_path)) = original else {
+            continue;
+        };
+        let copy = scan
+            .files
+            .remove(&relative_path)
+            .expect("relative path came from the scan map");
+        conflicts.push(SyncConflict {
+            note_id,
+            file_path: original_path.to_string_lossy().to_string(),
+            conflict_path: copy.full_path.to_string_lossy().to_string(),
+            conflict_markdown: copy.markdown,
+            conflict_updated_at: copy.updated_at,
+        });
+    }
+    conflicts
+}
+
 fn reconcile_index_with_scan(index: &NoteIndex, scan: &LibraryScan) -> (NoteIndex, bool) {
     let mut reconciled = index.clone();
     let mut used_paths: HashSet<String> = HashSet::new();
@@ -2748,15 +3015,16 @@ fn reconcile_index_with_scan(index: &NoteIndex, scan: &LibraryScan) -> (NoteInde
             .get(&rel_path)
             .expect("relative path came from the scan map");
         let generated_id = ensure_unique_note_id(&existing_ids, &rel_path);
-        let manual_title = extract_manual_title_metadata(&scanned.markdown).0;
+        let metadata = extract_note_metadata(&scanned.markdown).0;
 
         reconciled.entries.insert(
             generated_id.clone(),
             NoteIndexEntry {
                 relative_path: rel_path.clone(),
-                created_at: scanned.created_at,
-                manual_title,
-                is_pinned: None,
+                created_at: metadata.created.unwrap_or(scanned.created_at),
+                manual_title: metadata.title,
+                is_pinned: metadata.pinned,
+                seen_by: BTreeMap::new(),
             },
         );
         existing_ids.insert(generated_id);
@@ -2765,14 +3033,19 @@ fn reconcile_index_with_scan(index: &NoteIndex, scan: &LibraryScan) -> (NoteInde
     }
 
     for entry in reconciled.entries.values_mut() {
-        if entry.manual_title.is_some() {
+        if entry.manual_title.is_some() && entry.is_pinned.is_some() {
             continue;
         }
         let Some(scanned) = scan.files.get(&entry.relative_path) else {
             continue;
         };
-        if let Some(recovered_title) = extract_manual_title_metadata(&scanned.markdown).0 {
-            entry.manual_title = Some(recovered_title);
+        let metadata = extract_note_metadata(&scanned.markdown).0;
+        if entry.manual_title.is_none() && metadata.title.is_some() {
+            entry.manual_title = metadata.title;
+            index_changed = true;
+        }
+        if entry.is_pinned.is_none() && metadata.pinned.is_some() {
+            entry.is_pinned = metadata.pinned;
             index_changed = true;
         }
     }
@@ -2797,7 +3070,7 @@ fn ensure_unique_note_id(existing_ids: &HashSet<String>, seed: &str) -> String {
     }
 }
 
-fn remove_trusted_directory_tree(
+pub(crate) fn remove_trusted_directory_tree(
     trusted_root: &TrustedLibraryRoot,
     directory: &Path,
 ) -> Result<(), String> {
@@ -3061,7 +3334,12 @@ pub fn create_folder(auto_save_dir: &Path, folder_path: &str) -> Result<Vec<Stri
     list_folders_with_root(&trusted_root, &ProductionFileSystem)
 }
 
-pub fn rename_folder(auto_save_dir: &Path, from: &str, to: &str) -> Result<Vec<String>, String> {
+pub fn rename_folder(
+    auto_save_dir: &Path,
+    from: &str,
+    to: &str,
+    history_retention: &NoteHistoryRetention,
+) -> Result<FolderRenameResult, String> {
     let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
 
     let from_path = sanitize_folder_path(Some(from))?;
@@ -3071,7 +3349,10 @@ pub fn rename_folder(auto_save_dir: &Path, from: &str, to: &str) -> Result<Vec<S
         return Err("Folder path is required.".to_string());
     }
     if from_path == to_path {
-        return list_folders_with_root(&trusted_root, &ProductionFileSystem);
+        return Ok(FolderRenameResult {
+            folders: list_folders_with_root(&trusted_root, &ProductionFileSystem)?,
+            relinked_notes: Vec::new(),
+        });
     }
     if to_path.starts_with(&format!("{}/", from_path)) {
         return Err("Cannot move a folder into its own child.".to_string());
@@ -3085,6 +3366,7 @@ pub fn rename_folder(auto_save_dir: &Path, from: &str, to: &str) -> Result<Vec<S
     recover_pending_note_save_unlocked(&trusted_root)?;
     let index_snapshot = require_index_snapshot(&trusted_root)?;
     let mut index = index_snapshot.index.clone();
+    ensure_link_graph_unlocked(&trusted_root, &index_snapshot.index);
 
     validate_no_symlink_beneath_root(&trusted_root, &from_relative)
         .map_err(|error| error.display("validate_source_folder"))?;
@@ -3098,6 +3380,16 @@ pub fn rename_folder(auto_save_dir: &Path, from: &str, to: &str) -> Result<Vec<S
         Err(error) => return Err(error.to_string()),
     }
     ensure_directory_tree_trusted(&trusted_root, &from_relative)?;
+    let from_prefix = format!("{}/", from_path);
+    for (note_id, entry) in &index_snapshot.index.entries {
+        if !entry.relative_path.starts_with(&from_prefix) {
+            continue;
+        }
+        let path = validated_library_file_path(&trusted_root, &entry.relative_path)?;
+        if encrypted_note_is_locked(&trusted_root, note_id, &path)? {
+            return Err(locked_note_move_error(&path));
+        }
+    }
 
     validate_no_symlink_beneath_root(&trusted_root, &to_relative)
         .map_err(|error| error.display("validate_target_folder"))?;
@@ -3117,29 +3409,74 @@ pub fn rename_folder(auto_save_dir: &Path, from: &str, to: &str) -> Result<Vec<S
         .map_err(|error| error.display("validate_target_folder"))?;
     fs::rename(&source_dir, &target_dir).map_err(|e| e.to_string())?;
 
-    let from_prefix = format!("{}/", from_path);
     let to_prefix = format!("{}/", to_path);
-    let mut index_changed = false;
+    let mut moved_notes = Vec::new();
+    let mut attachment_moves = Vec::new();
 
-    for entry in index.entries.values_mut() {
+    for (note_id, entry) in index.entries.iter_mut() {
         if entry.relative_path.starts_with(&from_prefix) {
+            let previous_relative_path = entry.relative_path.clone();
             entry.relative_path =
                 format!("{}{}", to_prefix, &entry.relative_path[from_prefix.len()..]);
-            index_changed = true;
+            moved_notes.push((note_id.clone(), entry.relative_path.clone()));
+            attachment_moves.push((
+                note_id.clone(),
+                previous_relative_path,
+                entry.relative_path.clone(),
+            ));
         }
     }
 
-    if index_changed {
-        write_index_from_snapshot(&trusted_root, &index_snapshot, &index)
-            .map_err(index_write_failure_to_string)?;
+    let undo_rename = || {
+        if let Err(error) = fs::rename(&target_dir, &source_dir) {
+            tracing::warn!(
+                "Failed to roll back folder rename of {}: {error}",
+                target_dir.display()
+            );
+        }
+    };
+    let mut relinked_notes = Vec::new();
+    if !moved_notes.is_empty() {
+        let rewrites = match stage_note_rewrites_unlocked(
+            &trusted_root,
+            note_links::moved_nodes(&trusted_root, &moved_notes),
+            &HashSet::new(),
+            &attachment_moves,
+            MovedNoteFiles::AtNextPaths,
+            history_retention,
+        ) {
+            Ok(rewrites) => rewrites,
+            Err(error) => {
+                undo_rename();
+                return Err(error);
+            }
+        };
+        if let Err(error) = write_index_from_snapshot(&trusted_root, &index_snapshot, &index)
+            .map_err(index_write_failure_to_string)
+        {
+            rewrites.rollback(&trusted_root);
+            undo_rename();
+            return Err(error);
+        }
+        search_index::record_notes_moved(
+            &trusted_root,
+            index_snapshot_digest(&index_snapshot).as_deref(),
+            current_index_digest(&trusted_root)?.as_deref(),
+            &moved_notes,
+        );
+        relinked_notes = rewrites.finish(&trusted_root, &index);
     }
 
-    list_folders_with_root(&trusted_root, &ProductionFileSystem)
+    Ok(FolderRenameResult {
+        folders: list_folders_with_root(&trusted_root, &ProductionFileSystem)?,
+        relinked_notes,
+    })
 }
 
 pub fn delete_folder(
     auto_save_dir: &Path,
     folder_path: &str,
+    history_retention: &NoteHistoryRetention,
 ) -> Result<FolderDeleteResult, String> {
     let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
 
@@ -3157,6 +3494,8 @@ pub fn delete_folder(
         .map_err(|error| error.display("validate_folder_path"))?;
     ensure_directory_tree_trusted(&trusted_root, &folder_relative)?;
 
+    ensure_link_graph_unlocked(&trusted_root, &index_snapshot.index);
+
     let source_dir = trusted_root.path().join(&folder_relative);
     let prefix = format!("{}/", normalized);
     let mut index = index_snapshot.index.clone();
@@ -3183,40 +3522,115 @@ pub fn delete_folder(
     if !source_exists && matching_entries.is_empty() {
         return Err("Folder not found.".to_string());
     }
+    for (note_id, relative_path) in &matching_entries {
+        let path = validated_library_file_path(&trusted_root, relative_path)?;
+        if encrypted_note_is_locked(&trusted_root, note_id, &path)? {
+            return Err(locked_note_move_error(&path));
+        }
+    }
 
     let mut moved_note_ids = Vec::new();
+    let mut moved_notes = Vec::new();
+    let mut attachment_moves = Vec::new();
+    let mut trash_moves = Vec::new();
+
+    let mut renamed: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let undo_renames = |renamed: &[(PathBuf, PathBuf)]| {
+        for (old_path, new_path) in renamed.iter().rev() {
+            if let Err(error) = fs::rename(new_path, old_path) {
+                tracing::warn!(
+                    "Failed to roll back folder note move of {}: {error}",
+                    new_path.display()
+                );
+            }
+        }
+    };
 
     for (note_id, old_relative_path) in matching_entries {
-        let old_path = validated_library_file_path(&trusted_root, &old_relative_path)?;
-        validate_existing_trusted_file(&trusted_root, &old_path, "validate_folder_note")?;
-
-        let base_name = old_path
-            .file_stem()
-            .and_then(|value| value.to_str())
-            .filter(|value| !value.is_empty())
-            .unwrap_or("untitled");
-        let new_path = ensure_unique_file_path(trusted_root.path(), base_name, None)?;
-        validate_note_destination_before_replace(&trusted_root, &new_path)?;
-        validate_existing_trusted_file(&trusted_root, &old_path, "validate_folder_note")?;
-        validate_note_destination_before_replace(&trusted_root, &new_path)?;
-        fs::rename(&old_path, &new_path).map_err(|e| e.to_string())?;
+        let moved = (|| -> Result<PathBuf, String> {
+            let old_path = validated_library_file_path(&trusted_root, &old_relative_path)?;
+            validate_existing_trusted_file(&trusted_root, &old_path, "validate_folder_note")?;
+
+            let base_name = old_path
+                .file_stem()
+                .and_then(|value| value.to_str())
+                .filter(|value| !value.is_empty())
+                .unwrap_or("untitled");
+            let new_path = ensure_unique_file_path(trusted_root.path(), base_name, None)?;
+            validate_note_destination_before_replace(&trusted_root, &new_path)?;
+            validate_existing_trusted_file(&trusted_root, &old_path, "validate_folder_note")?;
+            validate_note_destination_before_replace(&trusted_root, &new_path)?;
+            fs::rename(&old_path, &new_path).map_err(|e| e.to_string())?;
+            renamed.push((old_path, new_path.clone()));
+            Ok(new_path)
+        })();
+        let new_path = match moved {
+            Ok(new_path) => new_path,
+            Err(error) => {
+                undo_renames(&renamed);
+                return Err(error);
+            }
+        };
 
+        let new_relative_path = relative_path(trusted_root.path(), &new_path);
         if let Some(entry) = index.entries.get_mut(&note_id) {
-            entry.relative_path = relative_path(trusted_root.path(), &new_path);
+            entry.relative_path = new_relative_path.clone();
         }
+        attachment_moves.push((
+            note_id.clone(),
+            old_relative_path.clone(),
+            new_relative_path.clone(),
+        ));
+        trash_moves.push(FolderNoteMove {
+            note_id: note_id.clone(),
+            original_relative_path: old_relative_path,
+            moved_relative_path: new_relative_path.clone(),
+        });
+        moved_notes.push((note_id.clone(), new_relative_path));
         moved_note_ids.push(note_id);
     }
 
-    write_index_from_snapshot(&trusted_root, &index_snapshot, &index)
-        .map_err(index_write_failure_to_string)?;
+    let rewrites = match stage_note_rewrites_unlocked(
+        &trusted_root,
+        note_links::moved_nodes(&trusted_root, &moved_notes),
+        &HashSet::new(),
+        &attachment_moves,
+        MovedNoteFiles::AtNextPaths,
+        history_retention,
+    ) {
+        Ok(rewrites) => rewrites,
+        Err(error) => {
+            undo_renames(&renamed);
+            return Err(error);
+        }
+    };
+    if let Err(error) = write_index_from_snapshot(&trusted_root, &index_snapshot, &index)
+        .map_err(index_write_failure_to_string)
+    {
+        rewrites.rollback(&trusted_root);
+        undo_renames(&renamed);
+        return Err(error);
+    }
+    search_index::record_notes_moved(
+        &trusted_root,
+        index_snapshot_digest(&index_snapshot).as_deref(),
+        current_index_digest(&trusted_root)?.as_deref(),
+        &moved_notes,
+    );
+    let relinked_notes = rewrites.finish(&trusted_root, &index);
 
-    if source_exists {
-        remove_trusted_directory_tree(&trusted_root, &source_dir)?;
-    }
+    let trash_id = note_trash::trash_folder_unlocked(
+        &trusted_root,
+        &normalized,
+        source_exists.then_some(source_dir.as_path()),
+        trash_moves,
+    )?;
 
     Ok(FolderDeleteResult {
         folders: list_folders_with_root(&trusted_root, &ProductionFileSystem)?,
         moved_note_ids,
+        relinked_notes,
+        trash_id,
     })
 }
 
@@ -3263,97 +3677,52 @@ fn validate_autosave_note_publish_target(
     }
 }
 
-fn auto_save_markdown_note_with_faults(
+struct JournaledNoteWrite<'a> {
+    note_id: &'a str,
+    folder_path: &'a Path,
+    existing_path: Option<&'a Path>,
+    next_file_path: &'a Path,
+    previous_relative_path: Option<String>,
+    previous_note_digest: Option<String>,
+    note_bytes: &'a [u8],
+}
+
+struct JournaledNoteCommit {
+    journal: AutosaveTransactionJournal,
+    index_bytes: Vec<u8>,
+    updated_at: u64,
+}
+
+/// Runs one note write through the autosave journal: stage the note and index
+/// temps, publish the note, publish the index, then clean up the previous
+/// file. A crash at any point is rolled forward or back by
+/// `recover_pending_note_save_unlocked`.
+fn commit_journaled_note_write_unlocked(
     trusted_root: &TrustedLibraryRoot,
-    payload: &AutoSavePayload,
+    index_snapshot: &IndexSnapshot,
+    next_index: &NoteIndex,
+    write: JournaledNoteWrite<'_>,
     faults: &impl AutosaveFaultInjector,
-) -> Result<AutoSaveResult, String> {
-    let safe_id = {
-        let sanitized = sanitize_note_id(&payload.note_id);
-        if sanitized.is_empty() {
-            "note".to_string()
-        } else {
-            sanitized
-        }
-    };
-    let safe_folder = sanitize_folder_path(payload.folder_path.as_deref())?;
-    let safe_folder_path = if safe_folder.is_empty() {
-        PathBuf::new()
-    } else {
-        normalize_library_relative_path(trusted_root.path(), &safe_folder)
-            .map_err(|error| error.display("validate_note_folder"))?
-    };
-
-    let _index_guard = lock_note_index();
-    recover_pending_note_save_unlocked(trusted_root)?;
-    let index_snapshot = require_index_snapshot(trusted_root)?;
-    let mut next_index = index_snapshot.index.clone();
-
-    let target_dir = ensure_library_subdirectory(trusted_root, &safe_folder_path)?;
-    let existing_entry = next_index.entries.get(&safe_id).cloned();
-    let existing_path = existing_entry
-        .as_ref()
-        .map(|entry| validated_library_file_path(trusted_root, &entry.relative_path))
-        .transpose()?;
-
-    let title_for_slug = if payload.title.is_empty() {
-        derive_title(&payload.content)
-    } else {
-        payload.title.clone()
-    };
-    let base_name = slugify_title(&title_for_slug);
-    let next_file_path =
-        ensure_unique_file_path(&target_dir, &base_name, existing_path.as_deref())?;
-    let manual_title = if payload.is_title_manual.unwrap_or(false) {
-        normalize_manual_title(&payload.title)
-    } else {
-        None
-    };
-    let stored_markdown = embed_manual_title_metadata(&payload.content, manual_title.as_deref());
-    let note_bytes = to_platform_line_endings(&stored_markdown).into_bytes();
-    let created_at = existing_entry
-        .as_ref()
-        .map(|entry| entry.created_at)
-        .unwrap_or_else(now_millis);
-    let previous_note_digest = existing_path
-        .as_ref()
-        .map(|path| read_existing_file_digest(trusted_root, path, "read_previous_note_digest"))
-        .transpose()?
-        .flatten();
-    let previous_relative_path = existing_path
-        .as_ref()
-        .zip(previous_note_digest.as_ref())
-        .map(|(path, _)| relative_path(trusted_root.path(), path));
+) -> Result<JournaledNoteCommit, String> {
     let operation_id = next_autosave_operation_id();
-    let note_temp_path = note_temp_path_for_operation(&next_file_path, &operation_id)?;
+    let note_temp_path = note_temp_path_for_operation(write.next_file_path, &operation_id)?;
     let index_temp_path = index_temp_path_for_operation(trusted_root, &operation_id);
 
-    let relative_path_string = relative_path(trusted_root.path(), &next_file_path);
-    next_index.entries.insert(
-        safe_id.clone(),
-        NoteIndexEntry {
-            relative_path: relative_path_string.clone(),
-            created_at,
-            manual_title: manual_title.clone(),
-            is_pinned: payload.is_pinned,
-        },
-    );
-
     let journal_base = AutosaveTransactionJournal {
         version: AUTOSAVE_TRANSACTION_VERSION,
         operation_id: operation_id.clone(),
         phase: AutosaveTransactionPhase::Prepared,
-        note_id: safe_id.clone(),
-        previous_relative_path,
-        next_relative_path: relative_path_string,
+        note_id: write.note_id.to_string(),
+        previous_relative_path: write.previous_relative_path,
+        next_relative_path: relative_path(trusted_root.path(), write.next_file_path),
         note_temp_relative_path: relative_path(trusted_root.path(), &note_temp_path),
         index_temp_relative_path: relative_path(trusted_root.path(), &index_temp_path),
-        expected_index_digest: index_snapshot.original_bytes.as_deref().map(sha256_hex),
+        expected_index_digest: index_snapshot_digest(index_snapshot),
         next_index: next_index.clone(),
-        next_note_digest: sha256_hex(&note_bytes),
-        previous_note_digest,
+        next_note_digest: sha256_hex(write.note_bytes),
+        previous_note_digest: write.previous_note_digest,
     };
-    let journal_index_bytes = serde_json::to_vec_pretty(&next_index).map_err(|error| {
+    let journal_index_bytes = serde_json::to_vec_pretty(next_index).map_err(|error| {
         format!(
             "serialize_autosave_index failed for {}: {error}",
             get_index_path(trusted_root.path()).display()
@@ -3363,7 +3732,7 @@ fn auto_save_markdown_note_with_faults(
     persist_autosave_journal(trusted_root, &journal_base, faults)?;
     create_synced_temp_file_with_faults(
         &note_temp_path,
-        &note_bytes,
+        write.note_bytes,
         "write_note_temp",
         AutosaveFaultPoint::NoteTempCreate,
         AutosaveFaultPoint::NoteTempWrite,
@@ -3384,12 +3753,12 @@ fn auto_save_markdown_note_with_faults(
     staged_journal.phase = AutosaveTransactionPhase::Staged;
     persist_autosave_journal(trusted_root, &staged_journal, faults)?;
 
-    validate_no_symlink_beneath_root(trusted_root, &safe_folder_path)
+    validate_no_symlink_beneath_root(trusted_root, write.folder_path)
         .map_err(|error| error.display("validate_note_folder"))?;
     validate_autosave_note_publish_target(
         trusted_root,
-        &next_file_path,
-        existing_path.as_deref(),
+        write.next_file_path,
+        write.existing_path,
         staged_journal.previous_note_digest.as_deref(),
         &staged_journal.next_note_digest,
         "publish_note",
@@ -3406,7 +3775,7 @@ fn auto_save_markdown_note_with_faults(
         ));
     }
     faults.check(AutosaveFaultPoint::NotePublish)?;
-    publish_temp_file(&note_temp_path, &next_file_path, "publish_note")?;
+    publish_temp_file(&note_temp_path, write.next_file_path, "publish_note")?;
     faults.check(AutosaveFaultPoint::NotePublishReported)?;
 
     let mut note_published_journal = staged_journal.clone();
@@ -3424,93 +3793,1440 @@ fn auto_save_markdown_note_with_faults(
     let resolved_index_published = resolve_autosave_journal(trusted_root, index_published_journal)?;
     cleanup_index_published_autosave_transaction(trusted_root, &resolved_index_published, faults)?;
 
-    let metadata = fs::symlink_metadata(&next_file_path).map_err(|error| {
+    let metadata = fs::symlink_metadata(write.next_file_path).map_err(|error| {
         format!(
             "read_saved_note_metadata failed for {}: {}",
-            next_file_path.display(),
+            write.next_file_path.display(),
             error
         )
     })?;
     if metadata_is_symlink_or_reparse_point(&metadata) {
         return Err(format!(
             "validate_saved_note failed for {}: symbolic links and reparse points are not allowed",
-            next_file_path.display()
+            write.next_file_path.display()
         ));
     }
     let updated_at = system_time_to_millis(metadata.modified().map_err(|error| {
         format!(
             "read_saved_note_modified_time failed for {}: {}",
-            next_file_path.display(),
+            write.next_file_path.display(),
             error
         )
     })?);
 
+    Ok(JournaledNoteCommit {
+        journal: journal_base,
+        index_bytes: journal_index_bytes,
+        updated_at,
+    })
+}
+
+fn auto_save_markdown_note_with_faults(
+    trusted_root: &TrustedLibraryRoot,
+    payload: &AutoSavePayload,
+    history_retention: &NoteHistoryRetention,
+    faults: &impl AutosaveFaultInjector,
+) -> Result<AutoSaveResult, String> {
+    let safe_id = {
+        let sanitized = sanitize_note_id(&payload.note_id);
+        if sanitized.is_empty() {
+            "note".to_string()
+        } else {
+            sanitized
+        }
+    };
+    let safe_folder = sanitize_folder_path(payload.folder_path.as_deref())?;
+    let safe_folder_path = if safe_folder.is_empty() {
+        PathBuf::new()
+    } else {
+        normalize_library_relative_path(trusted_root.path(), &safe_folder)
+            .map_err(|error| error.display("validate_note_folder"))?
+    };
+
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(trusted_root)?;
+    let index_snapshot = require_index_snapshot(trusted_root)?;
+    let mut next_index = index_snapshot.index.clone();
+    ensure_link_graph_unlocked(trusted_root, &index_snapshot.index);
+
+    let target_dir = ensure_library_subdirectory(trusted_root, &safe_folder_path)?;
+    let existing_entry = next_index.entries.get(&safe_id).cloned();
+    let existing_path = existing_entry
+        .as_ref()
+        .map(|entry| validated_library_file_path(trusted_root, &entry.relative_path))
+        .transpose()?;
+
+    let manual_title = if payload.is_title_manual.unwrap_or(false) {
+        normalize_manual_title(&payload.title)
+    } else {
+        None
+    };
+    let created_at = existing_entry
+        .as_ref()
+        .map(|entry| entry.created_at)
+        .unwrap_or_else(now_millis);
+    let previous_note_digest = existing_path
+        .as_ref()
+        .map(|path| read_existing_file_digest(trusted_root, path, "read_previous_note_digest"))
+        .transpose()?
+        .flatten();
+    let previous_relative_path = existing_path
+        .as_ref()
+        .zip(previous_note_digest.as_ref())
+        .map(|(path, _)| relative_path(trusted_root.path(), path));
+    let previous_bytes = existing_path
+        .as_ref()
+        .filter(|_| previous_note_digest.is_some())
+        .map(|path| read_trusted_file_bytes(trusted_root, path, "read_previous_note"))
+        .transpose()?;
+    let previous_markdown = previous_bytes
+        .as_deref()
+        .map(|bytes| String::from_utf8_lossy(bytes).to_string());
+    let encryption = match (&existing_path, &previous_markdown) {
+        (Some(path), Some(previous)) if note_crypto::is_armored(previous) => {
+            let key = note_crypto::session_key(trusted_root.path(), &safe_id).ok_or_else(|| {
+                format!(
+                    "autosave failed for {}: the note is encrypted and locked",
+                    path.display()
+                )
+            })?;
+            let plaintext = key
+                .decrypt(previous)
+                .map_err(|error| format!("autosave failed for {}: {error}", path.display()))?;
+            Some((key, plaintext, path.clone()))
+        }
+        _ => None,
+    };
+
+    // Encrypted notes keep their neutral file name so the title never leaks
+    // into the library listing.
+    let base_name = match &encryption {
+        Some((_, _, path)) => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| ENCRYPTED_NOTE_FILE_STEM.to_string()),
+        None if payload.title.is_empty() => slugify_title(&derive_title(&payload.content)),
+        None => slugify_title(&payload.title),
+    };
+    let next_file_path =
+        ensure_unique_file_path(&target_dir, &base_name, existing_path.as_deref())?;
+    let mut stored_markdown = embed_note_metadata(
+        &payload.content,
+        match &encryption {
+            Some((_, plaintext, _)) => Some(plaintext.as_str()),
+            None => previous_markdown.as_deref(),
+        },
+        manual_title.as_deref(),
+        payload.is_pinned == Some(true),
+        created_at,
+    );
+    // Another machine saved the note since this one last saw it: merge both
+    // edits onto that version instead of overwriting theirs.
+    let mut merge = None;
+    if let (None, Some(entry), Some(previous), Some(previous_digest)) = (
+        &encryption,
+        &existing_entry,
+        &previous_markdown,
+        &previous_note_digest,
+    ) {
+        if let Some((base_digest, base)) =
+            note_merge::read_merge_base(trusted_root, &entry.seen_by)?
+        {
+            if base_digest != *previous_digest {
+                let merged = note_merge::merge_note(
+                    &base.replace("\r\n", "\n"),
+                    &stored_markdown,
+                    &previous.replace("\r\n", "\n"),
+                );
+                stored_markdown = merged.text;
+                merge = Some(merged.conflicts);
+            }
+        }
+    }
+    let platform_markdown = to_platform_line_endings(&stored_markdown);
+    let note_bytes = match &encryption {
+        // Only ciphertext reaches the temp file and the note; an unchanged
+        // note keeps its previous ciphertext instead of churning the nonce.
+        Some((_, plaintext, _)) if *plaintext == platform_markdown => {
+            previous_bytes.clone().unwrap_or_default()
+        }
+        Some((key, _, _)) => key.encrypt(&platform_markdown)?.into_bytes(),
+        None => platform_markdown.into_bytes(),
+    };
+    if let Some(previous_bytes) = previous_bytes.filter(|bytes| *bytes != note_bytes) {
+        note_history::record_revision_unlocked(
+            trusted_root,
+            &safe_id,
+            &previous_bytes,
+            history_retention,
+        )?;
+    }
+    let relative_path_string = relative_path(trusted_root.path(), &next_file_path);
+    next_index.entries.insert(
+        safe_id.clone(),
+        NoteIndexEntry {
+            relative_path: relative_path_string.clone(),
+            created_at,
+            manual_title: manual_title.clone().filter(|_| encryption.is_none()),
+            is_pinned: payload.is_pinned,
+            seen_by: note_merge::record_seen_version(
+                trusted_root,
+                &existing_entry
+                    .as_ref()
+                    .map(|entry| entry.seen_by.clone())
+                    .unwrap_or_default(),
+                &note_bytes,
+            ),
+        },
+    );
+
+    let (saved_metadata, saved_markdown, saved_title) = if encryption.is_some() {
+        (FrontMatter::default(), String::new(), String::new())
+    } else {
+        let (metadata, markdown) = extract_note_metadata(&stored_markdown);
+        let title = manual_title
+            .clone()
+            .unwrap_or_else(|| derive_title(&markdown));
+        (metadata, markdown, title)
+    };
+    let rewrites = stage_note_rewrites_unlocked(
+        trusted_root,
+        vec![(
+            safe_id.clone(),
+            LinkNode::from_markdown(
+                &relative_path_string,
+                &saved_title,
+                &saved_metadata.aliases,
+                &saved_markdown,
+            ),
+        )],
+        &HashSet::from([safe_id.clone()]),
+        &[],
+        MovedNoteFiles::AtPreviousPaths,
+        history_retention,
+    )?;
+
+    let commit = match commit_journaled_note_write_unlocked(
+        trusted_root,
+        &index_snapshot,
+        &next_index,
+        JournaledNoteWrite {
+            note_id: &safe_id,
+            folder_path: &safe_folder_path,
+            existing_path: existing_path.as_deref(),
+            next_file_path: &next_file_path,
+            previous_relative_path,
+            previous_note_digest,
+            note_bytes: &note_bytes,
+        },
+        faults,
+    ) {
+        Ok(commit) => commit,
+        Err(error) => {
+            if journaled_commit_landed_unlocked(trusted_root, &safe_id, &relative_path_string) {
+                rewrites.finish(trusted_root, &next_index);
+            } else {
+                rewrites.rollback(trusted_root);
+            }
+            return Err(error);
+        }
+    };
+    let journal_base = commit.journal;
+    let journal_index_bytes = commit.index_bytes;
+    let updated_at = commit.updated_at;
+    if let Some(entry) = &existing_entry {
+        note_merge::prune_superseded_merge_base(trusted_root, &entry.seen_by, &next_index);
+    }
+    remember_note_digest(
+        trusted_root,
+        &safe_id,
+        journal_base.next_note_digest.clone(),
+    );
+    search_index::record_note_saved(
+        trusted_root,
+        journal_base.expected_index_digest.as_deref(),
+        Some(&sha256_hex(&journal_index_bytes)),
+        &safe_id,
+        if encryption.is_some() {
+            SearchDocument::from_markdown(&journal_base.next_relative_path, None, "", updated_at)
+        } else {
+            SearchDocument::from_markdown(
+                &journal_base.next_relative_path,
+                manual_title.as_deref(),
+                if merge.is_some() {
+                    &saved_markdown
+                } else {
+                    &payload.content
+                },
+                updated_at,
+            )
+        },
+    );
+    let relinked_notes = rewrites.finish(trusted_root, &next_index);
+
     Ok(AutoSaveResult {
         file_path: next_file_path.to_string_lossy().to_string(),
         note_id: safe_id,
         created_at,
         updated_at,
+        relinked_notes,
+        merge: merge.map(|conflicts| NoteMerge {
+            markdown: saved_markdown,
+            conflicts,
+        }),
     })
 }
 
+#[cfg(test)]
 pub fn auto_save_markdown_note(
     auto_save_dir: &Path,
     payload: &AutoSavePayload,
+) -> Result<AutoSaveResult, String> {
+    auto_save_markdown_note_with_history(auto_save_dir, payload, &NoteHistoryRetention::default())
+}
+
+pub fn auto_save_markdown_note_with_history(
+    auto_save_dir: &Path,
+    payload: &AutoSavePayload,
+    history_retention: &NoteHistoryRetention,
 ) -> Result<AutoSaveResult, String> {
     let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
-    auto_save_markdown_note_with_faults(&trusted_root, payload, &NoopAutosaveFaultInjector)
+    auto_save_markdown_note_with_faults(
+        &trusted_root,
+        payload,
+        history_retention,
+        &NoopAutosaveFaultInjector,
+    )
 }
 
-fn materialize_notes(index: &NoteIndex, scan: &LibraryScan) -> Vec<LoadedNote> {
-    let mut notes = Vec::new();
+pub fn move_notes(
+    auto_save_dir: &Path,
+    note_ids: &[String],
+    folder_path: Option<&str>,
+    history_retention: &NoteHistoryRetention,
+) -> Result<NoteMoveResult, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    move_notes_with_faults(
+        &trusted_root,
+        note_ids,
+        folder_path,
+        &NoopAutosaveFaultInjector,
+        history_retention,
+    )
+}
 
-    for (note_id, entry) in &index.entries {
-        let Some(scanned) = scan.files.get(&entry.relative_path) else {
+/// Moves each note into `folder_path` as its own journaled transaction. The
+/// link rewrites a note's move needs are written just before its commit and
+/// restored if the commit fails, and the note bytes are then published under a
+/// collision-free name, so a crash mid-batch leaves every note either fully
+/// moved or untouched.
+fn move_notes_with_faults(
+    trusted_root: &TrustedLibraryRoot,
+    note_ids: &[String],
+    folder_path: Option<&str>,
+    faults: &impl AutosaveFaultInjector,
+    history_retention: &NoteHistoryRetention,
+) -> Result<NoteMoveResult, String> {
+    let safe_folder = sanitize_folder_path(folder_path)?;
+    let safe_folder_path = if safe_folder.is_empty() {
+        PathBuf::new()
+    } else {
+        normalize_library_relative_path(trusted_root.path(), &safe_folder)
+            .map_err(|error| error.display("validate_target_folder"))?
+    };
+
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(trusted_root)?;
+    let initial_snapshot = require_index_snapshot(trusted_root)?;
+    let mut requested_ids = Vec::new();
+    for note_id in note_ids {
+        let safe_id = sanitize_note_id(note_id);
+        let Some(entry) = initial_snapshot.index.entries.get(&safe_id) else {
+            return Err(format!("Note not found: {note_id}"));
+        };
+        let path = validated_library_file_path(trusted_root, &entry.relative_path)?;
+        if encrypted_note_is_locked(trusted_root, &safe_id, &path)? {
+            return Err(locked_note_move_error(&path));
+        }
+        if !requested_ids.contains(&safe_id) {
+            requested_ids.push(safe_id);
+        }
+    }
+    ensure_link_graph_unlocked(trusted_root, &initial_snapshot.index);
+    let target_dir = ensure_library_subdirectory(trusted_root, &safe_folder_path)?;
+
+    let mut moved = Vec::new();
+    let mut relinked_notes = Vec::new();
+    for safe_id in requested_ids {
+        let index_snapshot = require_index_snapshot(trusted_root)?;
+        let Some(entry) = index_snapshot.index.entries.get(&safe_id).cloned() else {
             continue;
         };
-        let (embedded_manual_title, markdown) = extract_manual_title_metadata(&scanned.markdown);
-        let indexed_manual_title = entry
-            .manual_title
-            .as_deref()
-            .and_then(normalize_manual_title);
-        let effective_manual_title = indexed_manual_title.or(embedded_manual_title);
-        let title = effective_manual_title
-            .clone()
-            .unwrap_or_else(|| derive_title(&markdown));
-        let folder_path = entry
-            .relative_path
-            .rfind('/')
-            .map(|index| strip_inbox_root_alias(&entry.relative_path[..index]))
-            .unwrap_or_default();
+        if folder_path_for_relative_path(&entry.relative_path) == safe_folder {
+            continue;
+        }
+        let existing_path = validated_library_file_path(trusted_root, &entry.relative_path)?;
+        let base_name = existing_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| slugify_title(""));
+        let next_file_path = ensure_unique_file_path(&target_dir, &base_name, None)?;
+        let next_relative_path = relative_path(trusted_root.path(), &next_file_path);
+        let moved_path = vec![(safe_id.clone(), next_relative_path.clone())];
+        let rewrites = stage_note_rewrites_unlocked(
+            trusted_root,
+            note_links::moved_nodes(trusted_root, &moved_path),
+            &HashSet::new(),
+            &[(
+                safe_id.clone(),
+                entry.relative_path.clone(),
+                next_relative_path.clone(),
+            )],
+            MovedNoteFiles::AtPreviousPaths,
+            history_retention,
+        )?;
 
-        notes.push(LoadedNote {
-            note_id: note_id.clone(),
-            title,
-            is_title_manual: effective_manual_title.is_some(),
-            plain_text: markdown_to_plain_text(&markdown),
-            markdown,
-            folder_path,
-            created_at: entry.created_at,
-            updated_at: scanned.updated_at,
-            file_path: scanned.full_path.to_string_lossy().to_string(),
-            is_pinned: entry.is_pinned.unwrap_or(false),
+        let mut next_index = index_snapshot.index.clone();
+        if let Some(next_entry) = next_index.entries.get_mut(&safe_id) {
+            next_entry.relative_path = next_relative_path.clone();
+        }
+        let committed = read_trusted_file_bytes(trusted_root, &existing_path, "read_moved_note")
+            .and_then(|note_bytes| {
+                let note_digest = sha256_hex(&note_bytes);
+                commit_journaled_note_write_unlocked(
+                    trusted_root,
+                    &index_snapshot,
+                    &next_index,
+                    JournaledNoteWrite {
+                        note_id: &safe_id,
+                        folder_path: &safe_folder_path,
+                        existing_path: Some(&existing_path),
+                        next_file_path: &next_file_path,
+                        previous_relative_path: Some(entry.relative_path.clone()),
+                        previous_note_digest: Some(note_digest.clone()),
+                        note_bytes: &note_bytes,
+                    },
+                    faults,
+                )
+                .map(|_| note_digest)
+            });
+        let failure = match committed {
+            Ok(note_digest) => {
+                remember_note_digest(trusted_root, &safe_id, note_digest);
+                None
+            }
+            Err(error)
+                if journaled_commit_landed_unlocked(
+                    trusted_root,
+                    &safe_id,
+                    &next_relative_path,
+                ) =>
+            {
+                Some(error)
+            }
+            Err(error) => {
+                rewrites.rollback(trusted_root);
+                return Err(error);
+            }
+        };
+
+        search_index::record_notes_moved(
+            trusted_root,
+            index_snapshot_digest(&index_snapshot).as_deref(),
+            current_index_digest(trusted_root)?.as_deref(),
+            &moved_path,
+        );
+        relinked_notes.extend(rewrites.finish(trusted_root, &next_index));
+        moved.push(MovedNote {
+            note_id: safe_id,
+            previous_file_path: existing_path.to_string_lossy().to_string(),
+            file_path: next_file_path.to_string_lossy().to_string(),
         });
+        if let Some(error) = failure {
+            return Err(error);
+        }
     }
 
-    notes.sort_by(|left, right| {
-        right
-            .updated_at
-            .cmp(&left.updated_at)
-            .then_with(|| left.note_id.cmp(&right.note_id))
-    });
-    notes
+    Ok(NoteMoveResult {
+        moved,
+        relinked_notes,
+    })
 }
 
-fn incomplete_load_result(
+/// Applies a list of note operations under one index lock. Operations that
+/// name an unknown note or an invalid folder fail on their own. The rest are
+/// planned first and then staged in order: link rewrites, trash moves, and one
+/// journaled commit per changed note that moves its file and rewrites its front
+/// matter. A failure in any step undoes the steps already taken and reports the
+/// batch as not committed; a failed link rewrite or index write fails the
+/// whole batch.
+pub fn apply_note_batch(
+    auto_save_dir: &Path,
+    operations: &[NoteBatchOperation],
+    history_retention: &NoteHistoryRetention,
+) -> Result<NoteBatchResult, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    apply_note_batch_with_faults(
+        &trusted_root,
+        operations,
+        history_retention,
+        &NoopAutosaveFaultInjector,
+    )
+}
+
+/// A note the batch changes: where its file is, where it goes, and which
+/// operations asked for it.
+struct BatchNoteChange {
+    note_id: String,
+    previous_path: PathBuf,
+    next_path: PathBuf,
+    item_indices: Vec<usize>,
+    rewrite_front_matter: bool,
+    title_changed: bool,
+}
+
+/// A note commit the batch has made, kept so a later failure can move the note
+/// back to its previous path and bytes.
+struct CommittedBatchNote {
+    note_id: String,
+    previous_entry: NoteIndexEntry,
+    previous_path: PathBuf,
+    next_path: PathBuf,
+    previous_bytes: Vec<u8>,
+    note_bytes: Vec<u8>,
+    updated_at: u64,
+}
+
+fn apply_note_batch_with_faults(
     trusted_root: &TrustedLibraryRoot,
-    index: &NoteIndex,
-    scan: LibraryScan,
-    issues: Vec<NoteLoadIssue>,
+    operations: &[NoteBatchOperation],
+    history_retention: &NoteHistoryRetention,
+    faults: &impl AutosaveFaultInjector,
+) -> Result<NoteBatchResult, String> {
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(trusted_root)?;
+    let index_snapshot = require_index_snapshot(trusted_root)?;
+    let mut index = index_snapshot.index.clone();
+    ensure_link_graph_unlocked(trusted_root, &index_snapshot.index);
+
+    let mut items = Vec::with_capacity(operations.len());
+    let mut changes: Vec<BatchNoteChange> = Vec::new();
+    let mut trashed = Vec::new();
+    let mut reserved_paths = HashSet::new();
+    let mut planning_failed = false;
+
+    for operation in operations {
+        let safe_id = sanitize_note_id(operation.note_id());
+        let mut item = NoteBatchItemResult {
+            note_id: safe_id.clone(),
+            status: NoteBatchItemStatus::Applied,
+            file_path: None,
+            error: None,
+        };
+        if planning_failed {
+            item.status = NoteBatchItemStatus::NotApplied;
+            items.push(item);
+            continue;
+        }
+
+        let item_index = items.len();
+        let outcome = (|| -> Result<Result<PathBuf, String>, String> {
+            let Some(entry) = index.entries.get_mut(&safe_id) else {
+                return Ok(Err("Note not found.".to_string()));
+            };
+            // Nothing moves while planning: the file is still at its previous
+            // path even after an earlier move in the same batch.
+            let (file_path, planned_path) =
+                match changes.iter().find(|change| change.note_id == safe_id) {
+                    Some(change) => (change.previous_path.clone(), change.next_path.clone()),
+                    None => {
+                        let path = validated_library_file_path(trusted_root, &entry.relative_path)?;
+                        (path.clone(), path)
+                    }
+                };
+            let mut rewrite_front_matter = false;
+            let mut title_changed = false;
+            let next_path = match operation {
+                NoteBatchOperation::SetPinned { is_pinned, .. } => {
+                    entry.is_pinned = Some(*is_pinned);
+                    rewrite_front_matter = true;
+                    planned_path
+                }
+                NoteBatchOperation::SetManualTitle { title, .. } => {
+                    let bytes =
+                        read_trusted_file_bytes(trusted_root, &file_path, "read_batch_note")?;
+                    if note_crypto::is_armored_bytes(&bytes) {
+                        return Ok(Err(
+                            "Encrypted notes keep their title inside the note.".to_string()
+                        ));
+                    }
+                    entry.manual_title = title.as_deref().and_then(normalize_manual_title);
+                    rewrite_front_matter = true;
+                    title_changed = true;
+                    planned_path
+                }
+                NoteBatchOperation::Move { folder_path, .. } => {
+                    let safe_folder = match sanitize_folder_path(folder_path.as_deref()) {
+                        Ok(folder) => folder,
+                        Err(error) => return Ok(Err(error)),
+                    };
+                    if folder_path_for_relative_path(&entry.relative_path) == safe_folder {
+                        return Ok(Ok(planned_path));
+                    }
+                    let safe_folder_path = if safe_folder.is_empty() {
+                        PathBuf::new()
+                    } else {
+                        match normalize_library_relative_path(trusted_root.path(), &safe_folder) {
+                            Ok(path) => path,
+                            Err(error) => return Ok(Err(error.display("validate_target_folder"))),
+                        }
+                    };
+
+                    validate_existing_trusted_file(
+                        trusted_root,
+                        &file_path,
+                        "validate_batch_move",
+                    )?;
+                    if encrypted_note_is_locked(trusted_root, &safe_id, &file_path)? {
+                        return Ok(Err(locked_note_move_error(&file_path)));
+                    }
+                    let target_dir = ensure_library_subdirectory(trusted_root, &safe_folder_path)?;
+                    let base_name = file_path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_else(|| slugify_title(""));
+                    let next_path = unique_batch_file_path(
+                        &target_dir,
+                        &base_name,
+                        &file_path,
+                        &reserved_paths,
+                    )?;
+                    validate_note_destination_before_replace(trusted_root, &next_path)?;
+                    reserved_paths.insert(next_path.clone());
+                    entry.relative_path = relative_path(trusted_root.path(), &next_path);
+                    next_path
+                }
+                NoteBatchOperation::Trash { .. } => {
+                    validate_existing_trusted_file(
+                        trusted_root,
+                        &file_path,
+                        "validate_batch_trash",
+                    )?;
+                    // Earlier changes to a trashed note are dropped with it, so
+                    // the trash keeps the note as it is on disk.
+                    let trashed_entry = index_snapshot
+                        .index
+                        .entries
+                        .get(&safe_id)
+                        .cloned()
+                        .unwrap_or_else(|| entry.clone());
+                    index.entries.remove(&safe_id);
+                    changes.retain(|change| change.note_id != safe_id);
+                    trashed.push((
+                        item_index,
+                        safe_id.clone(),
+                        file_path.clone(),
+                        trashed_entry,
+                    ));
+                    return Ok(Ok(file_path));
+                }
+            };
+
+            match changes.iter_mut().find(|change| change.note_id == safe_id) {
+                Some(change) => {
+                    change.next_path = next_path.clone();
+                    change.item_indices.push(item_index);
+                    change.rewrite_front_matter |= rewrite_front_matter;
+                    change.title_changed |= title_changed;
+                }
+                None => changes.push(BatchNoteChange {
+                    note_id: safe_id.clone(),
+                    previous_path: file_path,
+                    next_path: next_path.clone(),
+                    item_indices: vec![item_index],
+                    rewrite_front_matter,
+                    title_changed,
+                }),
+            }
+            Ok(Ok(next_path))
+        })();
+
+        match outcome {
+            Ok(Ok(path)) => {
+                item.file_path = Some(path.to_string_lossy().to_string());
+            }
+            Ok(Err(error)) => {
+                item.status = NoteBatchItemStatus::Failed;
+                item.error = Some(error);
+            }
+            Err(error) => {
+                item.status = NoteBatchItemStatus::Failed;
+                item.error = Some(error);
+                planning_failed = true;
+            }
+        }
+        items.push(item);
+    }
+    if planning_failed {
+        return Ok(uncommitted_note_batch(items));
+    }
+
+    // Link rewrites and attachment rebases are staged first, with every file
+    // still at its previous path; each later step undoes them on failure.
+    let mut link_updates = Vec::new();
+    for change in &changes {
+        let Some(entry) = index.entries.get(&change.note_id) else {
+            continue;
+        };
+        let bytes =
+            read_trusted_file_bytes(trusted_root, &change.previous_path, "read_batch_note")?;
+        let stored_markdown = String::from_utf8_lossy(&bytes).to_string();
+        let encrypted = note_crypto::is_armored(&stored_markdown);
+        let (metadata, markdown) = if encrypted {
+            (FrontMatter::default(), String::new())
+        } else {
+            extract_note_metadata(&stored_markdown)
+        };
+        let manual_title = batch_note_manual_title(entry, &metadata, change.title_changed);
+        let title = match manual_title {
+            Some(title) => title,
+            None if encrypted => String::new(),
+            None => derive_title(&markdown),
+        };
+        link_updates.push((
+            change.note_id.clone(),
+            LinkNode::from_markdown(&entry.relative_path, &title, &metadata.aliases, &markdown),
+        ));
+    }
+    let attachment_moves = changes
+        .iter()
+        .filter(|change| change.previous_path != change.next_path)
+        .map(|change| {
+            (
+                change.note_id.clone(),
+                relative_path(trusted_root.path(), &change.previous_path),
+                relative_path(trusted_root.path(), &change.next_path),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut rewrites = stage_note_rewrites_unlocked(
+        trusted_root,
+        link_updates,
+        &HashSet::new(),
+        &attachment_moves,
+        MovedNoteFiles::AtPreviousPaths,
+        history_retention,
+    )?;
+
+    let mut failure = None;
+    let mut trash_moves = Vec::new();
+    for (item_index, note_id, path, entry) in &trashed {
+        let title = trashed_note_title(trusted_root, entry, path);
+        match note_trash::trash_note_unlocked(trusted_root, note_id, entry, path, &title) {
+            Ok(trash_id) => trash_moves.push((trash_id, path.clone())),
+            Err(error) => {
+                failure = Some((vec![*item_index], error));
+                break;
+            }
+        }
+    }
+
+    // Each changed note lands as its own journaled commit on top of the
+    // previous one; the first also drops the trashed notes from the index.
+    let mut next_index = index_snapshot.index.clone();
+    for (_, note_id, _, _) in &trashed {
+        next_index.entries.remove(note_id);
+    }
+    let mut committed = Vec::new();
+    for change in &changes {
+        if failure.is_some() {
+            break;
+        }
+        let (Some(previous_entry), Some(planned_entry)) = (
+            index_snapshot.index.entries.get(&change.note_id),
+            index.entries.get(&change.note_id),
+        ) else {
+            continue;
+        };
+        let prepared = (|| -> Result<(Vec<u8>, Vec<u8>, IndexSnapshot), String> {
+            let previous_bytes =
+                read_trusted_file_bytes(trusted_root, &change.previous_path, "read_batch_note")?;
+            let note_bytes = batch_note_bytes(&previous_bytes, planned_entry, change);
+            if note_bytes != previous_bytes {
+                note_history::record_revision_unlocked(
+                    trusted_root,
+                    &change.note_id,
+                    &previous_bytes,
+                    history_retention,
+                )?;
+            }
+            Ok((
+                previous_bytes,
+                note_bytes,
+                require_index_snapshot(trusted_root)?,
+            ))
+        })();
+        let (previous_bytes, note_bytes, snapshot) = match prepared {
+            Ok(prepared) => prepared,
+            Err(error) => {
+                failure = Some((change.item_indices.clone(), error));
+                break;
+            }
+        };
+
+        let mut next_entry = planned_entry.clone();
+        next_entry.seen_by =
+            note_merge::record_seen_version(trusted_root, &previous_entry.seen_by, &note_bytes);
+        next_index
+            .entries
+            .insert(change.note_id.clone(), next_entry);
+        let next_relative_path = relative_path(trusted_root.path(), &change.next_path);
+        let commit = commit_journaled_note_write_unlocked(
+            trusted_root,
+            &snapshot,
+            &next_index,
+            JournaledNoteWrite {
+                note_id: &change.note_id,
+                folder_path: &batch_note_folder_path(&next_relative_path),
+                existing_path: Some(&change.previous_path),
+                next_file_path: &change.next_path,
+                previous_relative_path: Some(relative_path(
+                    trusted_root.path(),
+                    &change.previous_path,
+                )),
+                previous_note_digest: Some(sha256_hex(&previous_bytes)),
+                note_bytes: &note_bytes,
+            },
+            faults,
+        );
+        let (updated_at, error) = match commit {
+            Ok(commit) => (commit.updated_at, None),
+            Err(error) => (now_millis(), Some(error)),
+        };
+        if error.is_none()
+            || journaled_commit_landed_unlocked(trusted_root, &change.note_id, &next_relative_path)
+        {
+            committed.push(CommittedBatchNote {
+                note_id: change.note_id.clone(),
+                previous_entry: previous_entry.clone(),
+                previous_path: change.previous_path.clone(),
+                next_path: change.next_path.clone(),
+                previous_bytes,
+                note_bytes,
+                updated_at,
+            });
+        }
+        if let Some(error) = error {
+            failure = Some((change.item_indices.clone(), error));
+        }
+    }
+    if failure.is_none() && committed.is_empty() && !trashed.is_empty() {
+        if let Err(error) = write_index_from_snapshot(trusted_root, &index_snapshot, &next_index)
+            .map_err(index_write_failure_to_string)
+        {
+            for (trash_id, original_path) in trash_moves.iter().rev() {
+                untrash_note_file_unlocked(trusted_root, trash_id, original_path);
+            }
+            rewrites.rollback(trusted_root);
+            return Err(error);
+        }
+    }
+
+    if let Some((failed_items, error)) = failure {
+        // A commit that reported an error may still have a journal to roll
+        // forward or back before its note can be moved back.
+        recover_pending_note_save_unlocked(trusted_root)?;
+        for (trash_id, original_path) in trash_moves.iter().rev() {
+            untrash_note_file_unlocked(trusted_root, trash_id, original_path);
+        }
+        let restored_entries = trashed
+            .iter()
+            .map(|(_, note_id, _, entry)| (note_id.clone(), entry.clone()))
+            .collect::<Vec<_>>();
+        undo_batch_commits_unlocked(trusted_root, committed, &restored_entries);
+        rewrites.rollback(trusted_root);
+        for item_index in failed_items {
+            items[item_index].status = NoteBatchItemStatus::Failed;
+            items[item_index].error = Some(error.clone());
+        }
+        return Ok(uncommitted_note_batch(items));
+    }
+
+    let mut search_updates = Vec::new();
+    for note in &committed {
+        let Some(entry) = next_index.entries.get(&note.note_id) else {
+            continue;
+        };
+        let stored_markdown = String::from_utf8_lossy(&note.note_bytes).to_string();
+        let document = if note_crypto::is_armored(&stored_markdown) {
+            SearchDocument::from_markdown(&entry.relative_path, None, "", note.updated_at)
+        } else {
+            let (metadata, markdown) = extract_note_metadata(&stored_markdown);
+            SearchDocument::from_markdown(
+                &entry.relative_path,
+                entry.manual_title.clone().or(metadata.title).as_deref(),
+                &markdown,
+                note.updated_at,
+            )
+        };
+        search_updates.push((note.note_id.clone(), document));
+        remember_note_digest(trusted_root, &note.note_id, sha256_hex(&note.note_bytes));
+        note_merge::prune_superseded_merge_base(
+            trusted_root,
+            &note.previous_entry.seen_by,
+            &next_index,
+        );
+        // A rewritten note was committed with new front matter on top of its
+        // rewrite, so the rewrite reports the bytes that landed.
+        if let Some(rewrite) = rewrites
+            .written
+            .iter_mut()
+            .find(|rewrite| rewrite.note_id == note.note_id)
+        {
+            rewrite.stored_markdown = stored_markdown;
+        }
+    }
+    let trashed_ids = trashed
+        .iter()
+        .map(|(_, note_id, _, _)| note_id.clone())
+        .collect::<Vec<_>>();
+    search_index::record_external_changes(
+        trusted_root,
+        index_snapshot_digest(&index_snapshot).as_deref(),
+        current_index_digest(trusted_root)?.as_deref(),
+        search_updates,
+        &trashed_ids,
+    );
+    note_links::record_notes_removed(trusted_root, &trashed_ids);
+    for note_id in &trashed_ids {
+        forget_note_digest(trusted_root, note_id);
+    }
+    let relinked_notes = rewrites.finish(trusted_root, &next_index);
+
+    Ok(NoteBatchResult {
+        committed: true,
+        items,
+        relinked_notes,
+    })
+}
+
+/// Reports a batch that left the library as it was.
+fn uncommitted_note_batch(mut items: Vec<NoteBatchItemResult>) -> NoteBatchResult {
+    for item in items.iter_mut() {
+        if item.status == NoteBatchItemStatus::Applied {
+            item.status = NoteBatchItemStatus::NotApplied;
+        }
+    }
+    NoteBatchResult {
+        committed: false,
+        items,
+        relinked_notes: Vec::new(),
+    }
+}
+
+/// Picks a free file path in `target_dir` that no earlier move in the same
+/// batch has claimed.
+fn unique_batch_file_path(
+    target_dir: &Path,
+    base_name: &str,
+    current_path: &Path,
+    reserved_paths: &HashSet<PathBuf>,
+) -> Result<PathBuf, String> {
+    let mut counter = 1;
+    loop {
+        let name = if counter == 1 {
+            base_name.to_string()
+        } else {
+            format!("{}-{}", base_name, counter)
+        };
+        let candidate = ensure_unique_file_path(target_dir, &name, Some(current_path))?;
+        if !reserved_paths.contains(&candidate) {
+            return Ok(candidate);
+        }
+        counter += 1;
+    }
+}
+
+fn batch_note_folder_path(relative_path: &str) -> PathBuf {
+    Path::new(relative_path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// The manual title a batch-changed note ends up with. A title the batch set
+/// or cleared wins over the one in the file's front matter.
+fn batch_note_manual_title(
+    entry: &NoteIndexEntry,
+    metadata: &FrontMatter,
+    title_changed: bool,
+) -> Option<String> {
+    let manual_title = entry
+        .manual_title
+        .as_deref()
+        .and_then(normalize_manual_title);
+    if title_changed {
+        manual_title
+    } else {
+        manual_title.or_else(|| metadata.title.clone())
+    }
+}
+
+/// The bytes a batch-changed note is committed with. A title or pin change
+/// rewrites the front matter through `embed_note_metadata`, as an editor save
+/// would, so the next load does not bring the old values back. Encrypted
+/// notes keep their ciphertext.
+fn batch_note_bytes(
+    previous_bytes: &[u8],
+    entry: &NoteIndexEntry,
+    change: &BatchNoteChange,
+) -> Vec<u8> {
+    if !change.rewrite_front_matter || note_crypto::is_armored_bytes(previous_bytes) {
+        return previous_bytes.to_vec();
+    }
+    let previous_markdown = String::from_utf8_lossy(previous_bytes).replace("\r\n", "\n");
+    let metadata = extract_note_metadata(&previous_markdown).0;
+    let manual_title = batch_note_manual_title(entry, &metadata, change.title_changed);
+    to_platform_line_endings(&embed_note_metadata(
+        &previous_markdown,
+        Some(&previous_markdown),
+        manual_title.as_deref(),
+        entry.is_pinned.or(metadata.pinned) == Some(true),
+        entry.created_at,
+    ))
+    .into_bytes()
+}
+
+/// Moves committed batch notes back to their previous paths and bytes, newest
+/// first, and puts `restored_entries` back into the index with them.
+fn undo_batch_commits_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    committed: Vec<CommittedBatchNote>,
+    restored_entries: &[(String, NoteIndexEntry)],
+) {
+    for note in committed.into_iter().rev() {
+        let previous_relative_path = relative_path(trusted_root.path(), &note.previous_path);
+        let undone = require_index_snapshot(trusted_root).and_then(|snapshot| {
+            let mut next_index = snapshot.index.clone();
+            next_index
+                .entries
+                .insert(note.note_id.clone(), note.previous_entry.clone());
+            for (note_id, entry) in restored_entries {
+                next_index.entries.insert(note_id.clone(), entry.clone());
+            }
+            commit_journaled_note_write_unlocked(
+                trusted_root,
+                &snapshot,
+                &next_index,
+                JournaledNoteWrite {
+                    note_id: &note.note_id,
+                    folder_path: &batch_note_folder_path(&previous_relative_path),
+                    existing_path: Some(&note.next_path),
+                    next_file_path: &note.previous_path,
+                    previous_relative_path: Some(relative_path(
+                        trusted_root.path(),
+                        &note.next_path,
+                    )),
+                    previous_note_digest: Some(sha256_hex(&note.note_bytes)),
+                    note_bytes: &note.previous_bytes,
+                },
+                &NoopAutosaveFaultInjector,
+            )
+        });
+        if let Err(error) = undone {
+            tracing::warn!(
+                "Failed to roll back batch change of {}: {error}",
+                note.next_path.display()
+            );
+        }
+    }
+}
+
+/// Tells whether the note at `path` is encrypted and has no session key. Such
+/// a note is not moved: its links cannot be read or rebased until it is
+/// unlocked.
+fn encrypted_note_is_locked(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+    path: &Path,
+) -> Result<bool, String> {
+    let bytes = read_trusted_file_bytes(trusted_root, path, "read_moved_note")?;
+    Ok(note_crypto::is_armored_bytes(&bytes)
+        && note_crypto::session_key(trusted_root.path(), note_id).is_none())
+}
+
+fn locked_note_move_error(path: &Path) -> String {
+    format!(
+        "move_note failed for {}: the note is encrypted and locked",
+        path.display()
+    )
+}
+
+/// Builds the link graph from a scan when this library has not been loaded in
+/// this process yet, so link-aware renames still see every linking note.
+fn ensure_link_graph_unlocked(trusted_root: &TrustedLibraryRoot, index: &NoteIndex) {
+    if note_links::has_link_graph(trusted_root) {
+        return;
+    }
+    let scan = scan_library_tree(&ProductionFileSystem, trusted_root, None, true);
+    if scan.is_complete() {
+        note_links::refresh_from_library(trusted_root, index, &materialize_notes(index, &scan));
+    }
+}
+
+/// Tells whether a journaled commit that reported an error has landed or will
+/// land: a journal left past its prepared phase is rolled forward by the next
+/// recovery. The caller then keeps the rewrites and bookkeeping for the note
+/// instead of undoing them.
+fn journaled_commit_landed_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+    next_relative_path: &str,
+) -> bool {
+    if let OptionalJournalState::Parsed(journal) =
+        read_optional_autosave_journal(&autosave_journal_path(trusted_root))
+    {
+        return journal.note_id == note_id && journal.phase != AutosaveTransactionPhase::Prepared;
+    }
+    require_index_snapshot(trusted_root).is_ok_and(|snapshot| {
+        snapshot
+            .index
+            .entries
+            .get(note_id)
+            .is_some_and(|entry| entry.relative_path == next_relative_path)
+    })
+}
+
+/// Where the files of moved notes are while their rewrites are staged.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MovedNoteFiles {
+    AtPreviousPaths,
+    AtNextPaths,
+}
+
+/// The wiki link and attachment link rewrites a move or rename needs. They are
+/// written before the operation publishes its index and rolled back when that
+/// publish fails, so the rewrites land together with the move or not at all.
+struct StagedNoteRewrites {
+    link_updates: Vec<(String, LinkNode)>,
+    written: Vec<StagedNoteRewrite>,
+}
+
+struct StagedNoteRewrite {
+    note_id: String,
+    path: PathBuf,
+    final_relative_path: String,
+    previous_bytes: Vec<u8>,
+    stored_markdown: String,
+}
+
+/// Plans and writes the rewrites for `link_updates` (notes whose path, title,
+/// or aliases change) and `moves` (note id, previous relative path, next
+/// relative path): linking notes get their wiki links retargeted, and moved
+/// notes that change folder depth get their attachment links rebased. Each
+/// rewrite is an atomic replace with a history revision; if any fails, the
+/// ones already written are restored and the error is returned.
+fn stage_note_rewrites_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    link_updates: Vec<(String, LinkNode)>,
+    skip_note_ids: &HashSet<String>,
+    moves: &[(String, String, String)],
+    moved_files: MovedNoteFiles,
+    history_retention: &NoteHistoryRetention,
+) -> Result<StagedNoteRewrites, String> {
+    let disk_paths = moves
+        .iter()
+        .filter(|_| moved_files == MovedNoteFiles::AtNextPaths)
+        .map(|(_, previous, next)| (previous.clone(), next.clone()))
+        .collect::<HashMap<_, _>>();
+    let disk_relative_path = |relative: &str| {
+        disk_paths
+            .get(relative)
+            .cloned()
+            .unwrap_or_else(|| relative.to_string())
+    };
+    let read_markdown = |relative: &str| -> Result<String, String> {
+        let path = validated_library_file_path(trusted_root, &disk_relative_path(relative))?;
+        let bytes = read_trusted_file_bytes(trusted_root, &path, "read_linking_note")?;
+        String::from_utf8(bytes)
+            .map_err(|error| format!("read_linking_note failed for {}: {error}", path.display()))
+    };
+
+    let mut rewrites =
+        note_links::relocate_notes(trusted_root, &link_updates, skip_note_ids, read_markdown)?;
+    for (note_id, previous_relative, next_relative) in moves {
+        let planned = rewrites.iter().position(|plan| plan.note_id == *note_id);
+        let markdown = match planned {
+            Some(position) => rewrites[position].stored_markdown.clone(),
+            None => {
+                let path = validated_library_file_path(
+                    trusted_root,
+                    &disk_relative_path(previous_relative),
+                )?;
+                let bytes = read_trusted_file_bytes(trusted_root, &path, "read_moved_note")?;
+                String::from_utf8_lossy(&bytes).to_string()
+            }
+        };
+        let Some(stored_markdown) =
+            attachments::rebase_attachment_links(&markdown, previous_relative, next_relative)
+        else {
+            continue;
+        };
+        match planned {
+            Some(position) => rewrites[position].stored_markdown = stored_markdown,
+            None => rewrites.push(PlannedRelink {
+                note_id: note_id.clone(),
+                relative_path: previous_relative.clone(),
+                stored_markdown,
+            }),
+        }
+    }
+
+    let mut staged = StagedNoteRewrites {
+        link_updates,
+        written: Vec::new(),
+    };
+    for plan in rewrites {
+        let final_relative_path = moves
+            .iter()
+            .find(|(note_id, _, _)| *note_id == plan.note_id)
+            .map(|(_, _, next)| next.clone())
+            .unwrap_or_else(|| plan.relative_path.clone());
+        let result = (|| -> Result<StagedNoteRewrite, String> {
+            let path = validated_library_file_path(
+                trusted_root,
+                &disk_relative_path(&plan.relative_path),
+            )?;
+            let previous_bytes = read_trusted_file_bytes(trusted_root, &path, "read_linking_note")?;
+            note_history::record_revision_unlocked(
+                trusted_root,
+                &plan.note_id,
+                &previous_bytes,
+                history_retention,
+            )?;
+            write_note_file_atomically(trusted_root, &path, &plan.stored_markdown)?;
+            Ok(StagedNoteRewrite {
+                note_id: plan.note_id.clone(),
+                path,
+                final_relative_path,
+                previous_bytes,
+                stored_markdown: plan.stored_markdown,
+            })
+        })();
+        match result {
+            Ok(rewrite) => staged.written.push(rewrite),
+            Err(error) => {
+                staged.rollback(trusted_root);
+                return Err(error);
+            }
+        }
+    }
+    Ok(staged)
+}
+
+impl StagedNoteRewrites {
+    /// Restores every rewritten note to its previous bytes. Only valid while
+    /// the files are still where they were staged.
+    fn rollback(self, trusted_root: &TrustedLibraryRoot) {
+        for rewrite in self.written.into_iter().rev() {
+            if let Err(error) = write_note_file_atomically_after_temp_hook(
+                trusted_root,
+                &rewrite.path,
+                &rewrite.previous_bytes,
+                || {},
+            ) {
+                tracing::warn!(
+                    "Failed to roll back link rewrite of {}: {error}",
+                    rewrite.path.display()
+                );
+            }
+        }
+    }
+
+    /// Records the committed rewrites in the link graph, the known digests,
+    /// and the search index, and returns the rewritten notes for the editor.
+    fn finish(self, trusted_root: &TrustedLibraryRoot, index: &NoteIndex) -> Vec<RelinkedNote> {
+        let mut graph_updates = self.link_updates;
+        let mut search_updates = Vec::new();
+        let mut relinked = Vec::new();
+        for rewrite in self.written {
+            remember_note_digest(
+                trusted_root,
+                &rewrite.note_id,
+                sha256_hex(rewrite.stored_markdown.as_bytes()),
+            );
+            let (metadata, markdown) = extract_note_metadata(&rewrite.stored_markdown);
+            let manual_title = index
+                .entries
+                .get(&rewrite.note_id)
+                .and_then(|entry| entry.manual_title.clone())
+                .or(metadata.title);
+            let title = manual_title
+                .clone()
+                .unwrap_or_else(|| derive_title(&markdown));
+            let path = trusted_root.path().join(&rewrite.final_relative_path);
+            let updated_at = fs::symlink_metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .map(system_time_to_millis)
+                .unwrap_or_else(|_| now_millis());
+            graph_updates.push((
+                rewrite.note_id.clone(),
+                LinkNode::from_markdown(
+                    &rewrite.final_relative_path,
+                    &title,
+                    &metadata.aliases,
+                    &markdown,
+                ),
+            ));
+            search_updates.push((
+                rewrite.note_id.clone(),
+                SearchDocument::from_markdown(
+                    &rewrite.final_relative_path,
+                    manual_title.as_deref(),
+                    &markdown,
+                    updated_at,
+                ),
+            ));
+            relinked.push(RelinkedNote {
+                note_id: rewrite.note_id,
+                file_path: path.to_string_lossy().to_string(),
+                markdown,
+            });
+        }
+
+        note_links::record_notes_saved(trusted_root, graph_updates);
+        if !search_updates.is_empty() {
+            if let Ok(index_digest) = current_index_digest(trusted_root) {
+                search_index::record_external_changes(
+                    trusted_root,
+                    index_digest.as_deref(),
+                    index_digest.as_deref(),
+                    search_updates,
+                    &[],
+                );
+            }
+        }
+        relinked
+    }
+}
+
+fn known_note_digests() -> MutexGuard<'static, HashMap<(PathBuf, String), String>> {
+    KNOWN_NOTE_DIGESTS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Remembers the bytes the app last read or wrote for a note so the library
+/// watcher can tell external edits apart from the app's own saves.
+fn remember_note_digest(trusted_root: &TrustedLibraryRoot, note_id: &str, digest: String) {
+    known_note_digests().insert(
+        (trusted_root.path().to_path_buf(), note_id.to_string()),
+        digest,
+    );
+}
+
+fn forget_note_digest(trusted_root: &TrustedLibraryRoot, note_id: &str) {
+    known_note_digests().remove(&(trusted_root.path().to_path_buf(), note_id.to_string()));
+}
+
+pub(crate) fn folder_path_for_relative_path(relative_path: &str) -> String {
+    relative_path
+        .rfind('/')
+        .map(|index| strip_inbox_root_alias(&relative_path[..index]))
+        .unwrap_or_default()
+}
+
+fn loaded_note(
+    note_id: &str,
+    entry: &NoteIndexEntry,
+    stored_markdown: &str,
+    full_path: &Path,
+    updated_at: u64,
+) -> LoadedNote {
+    let (metadata, markdown) = extract_note_metadata(stored_markdown);
+    let indexed_manual_title = entry
+        .manual_title
+        .as_deref()
+        .and_then(normalize_manual_title);
+    let effective_manual_title = indexed_manual_title.or(metadata.title);
+    let title = effective_manual_title
+        .clone()
+        .unwrap_or_else(|| derive_title(&markdown));
+
+    LoadedNote {
+        note_id: note_id.to_string(),
+        title,
+        is_title_manual: effective_manual_title.is_some(),
+        plain_text: markdown_to_plain_text(&markdown),
+        markdown,
+        folder_path: folder_path_for_relative_path(&entry.relative_path),
+        created_at: entry.created_at,
+        updated_at,
+        file_path: full_path.to_string_lossy().to_string(),
+        is_pinned: entry.is_pinned.or(metadata.pinned).unwrap_or(false),
+        tags: metadata.tags,
+        aliases: metadata.aliases,
+        is_encrypted: false,
+    }
+}
+
+fn locked_note(
+    note_id: &str,
+    entry: &NoteIndexEntry,
+    full_path: &Path,
+    updated_at: u64,
+) -> LoadedNote {
+    LoadedNote {
+        note_id: note_id.to_string(),
+        title: String::new(),
+        is_title_manual: false,
+        plain_text: String::new(),
+        markdown: String::new(),
+        folder_path: folder_path_for_relative_path(&entry.relative_path),
+        created_at: entry.created_at,
+        updated_at,
+        file_path: full_path.to_string_lossy().to_string(),
+        is_pinned: entry.is_pinned.unwrap_or(false),
+        tags: Vec::new(),
+        aliases: Vec::new(),
+        is_encrypted: true,
+    }
+}
+
+fn materialize_notes(index: &NoteIndex, scan: &LibraryScan) -> Vec<LoadedNote> {
+    let mut notes = Vec::new();
+
+    for (note_id, entry) in &index.entries {
+        let Some(scanned) = scan.files.get(&entry.relative_path) else {
+            continue;
+        };
+        notes.push(if note_crypto::is_armored(&scanned.markdown) {
+            locked_note(note_id, entry, &scanned.full_path, scanned.updated_at)
+        } else {
+            loaded_note(
+                note_id,
+                entry,
+                &scanned.markdown,
+                &scanned.full_path,
+                scanned.updated_at,
+            )
+        });
+    }
+
+    notes.sort_by(|left, right| {
+        right
+            .updated_at
+            .cmp(&left.updated_at)
+            .then_with(|| left.note_id.cmp(&right.note_id))
+    });
+    notes
+}
+
+fn incomplete_load_result(
+    trusted_root: &TrustedLibraryRoot,
+    index: &NoteIndex,
+    scan: LibraryScan,
+    issues: Vec<NoteLoadIssue>,
 ) -> MarkdownLibraryLoadResult {
     MarkdownLibraryLoadResult {
         notes: materialize_notes(index, &scan),
@@ -3523,586 +5239,3181 @@ fn incomplete_load_result(
                 .to_string(),
         ),
         index_backup_path: None,
+        conflicts: Vec::new(),
+    }
+}
+
+fn load_markdown_library_with_fs<F: LibraryFileSystem>(
+    auto_save_dir: &Path,
+    file_system: &F,
+) -> MarkdownLibraryLoadResult {
+    let trusted_root = match resolve_trusted_library_root(auto_save_dir) {
+        Ok(trusted_root) => trusted_root,
+        Err(error) => {
+            return MarkdownLibraryLoadResult {
+                notes: Vec::new(),
+                folders: Vec::new(),
+                load_state: NoteLoadState::Incomplete,
+                issues: vec![NoteLoadIssue::new(
+                    NoteLoadIssueKind::Scan,
+                    "validate_library_root",
+                    &error.path,
+                    error.reason,
+                )],
+                index_source_path: Some(
+                    get_index_path(auto_save_dir).to_string_lossy().to_string(),
+                ),
+                index_backup_path: None,
+                conflicts: Vec::new(),
+            };
+        }
+    };
+    let index_source_path = get_index_path(trusted_root.path());
+
+    let _index_guard = lock_note_index();
+    if let Err(reason) = recover_pending_note_save_unlocked(&trusted_root) {
+        return MarkdownLibraryLoadResult {
+            notes: Vec::new(),
+            folders: Vec::new(),
+            load_state: NoteLoadState::Incomplete,
+            issues: vec![NoteLoadIssue::new(
+                NoteLoadIssueKind::Index,
+                "recover_pending_note_save",
+                &autosave_journal_path(&trusted_root),
+                reason,
+            )],
+            index_source_path: Some(index_source_path.to_string_lossy().to_string()),
+            index_backup_path: None,
+            conflicts: Vec::new(),
+        };
+    }
+    let index_snapshot = match read_index_state(&trusted_root) {
+        IndexReadState::Ready(snapshot) => snapshot,
+        IndexReadState::Corrupt(state) => {
+            return MarkdownLibraryLoadResult {
+                notes: Vec::new(),
+                folders: Vec::new(),
+                load_state: NoteLoadState::IndexCorrupt,
+                issues: state.issues,
+                index_source_path: Some(index_source_path.to_string_lossy().to_string()),
+                index_backup_path: state
+                    .backup_path
+                    .map(|path| path.to_string_lossy().to_string()),
+                conflicts: Vec::new(),
+            };
+        }
+    };
+
+    let mut scan = scan_library_tree(file_system, &trusted_root, None, true);
+    if !scan.is_complete() {
+        let issues = scan.issues.clone();
+        return incomplete_load_result(&trusted_root, &index_snapshot.index, scan, issues);
+    }
+
+    let conflicts = split_sync_conflicts(&index_snapshot.index, &mut scan);
+    let (reconciled_index, index_changed) = reconcile_index_with_scan(&index_snapshot.index, &scan);
+    let notes = materialize_notes(&reconciled_index, &scan);
+    let mut index_digest = index_snapshot_digest(&index_snapshot);
+
+    if index_changed {
+        match write_index_from_snapshot(&trusted_root, &index_snapshot, &reconciled_index) {
+            Ok(()) => {
+                index_digest = current_index_digest(&trusted_root).ok().flatten();
+            }
+            Err(IndexWriteFailure::Corrupt(state)) => {
+                return MarkdownLibraryLoadResult {
+                    notes: materialize_notes(&index_snapshot.index, &scan),
+                    folders: scan.folders,
+                    load_state: NoteLoadState::IndexCorrupt,
+                    issues: state.issues,
+                    index_source_path: Some(index_source_path.to_string_lossy().to_string()),
+                    index_backup_path: state
+                        .backup_path
+                        .map(|path| path.to_string_lossy().to_string()),
+                    conflicts: Vec::new(),
+                };
+            }
+            Err(IndexWriteFailure::Issue(issue)) => {
+                return incomplete_load_result(
+                    &trusted_root,
+                    &index_snapshot.index,
+                    scan,
+                    vec![issue],
+                );
+            }
+        }
+    }
+    for (note_id, entry) in &reconciled_index.entries {
+        if let Some(scanned) = scan.files.get(&entry.relative_path) {
+            remember_note_digest(
+                &trusted_root,
+                note_id,
+                sha256_hex(scanned.markdown.as_bytes()),
+            );
+        }
+    }
+    search_index::refresh_from_library(
+        &trusted_root,
+        index_digest.as_deref(),
+        &reconciled_index,
+        &notes,
+    );
+    note_links::refresh_from_library(&trusted_root, &reconciled_index, &notes);
+
+    MarkdownLibraryLoadResult {
+        notes,
+        folders: scan.folders,
+        load_state: NoteLoadState::Ready,
+        issues: Vec::new(),
+        index_source_path: Some(index_source_path.to_string_lossy().to_string()),
+        index_backup_path: None,
+        conflicts,
+    }
+}
+
+pub fn load_markdown_library(auto_save_dir: &Path) -> MarkdownLibraryLoadResult {
+    load_markdown_library_with_fs(auto_save_dir, &ProductionFileSystem)
+}
+
+#[cfg(test)]
+pub fn load_markdown_notes(auto_save_dir: &Path) -> Result<Vec<LoadedNote>, String> {
+    let result = load_markdown_library(auto_save_dir);
+    if result.load_state == NoteLoadState::Ready {
+        Ok(result.notes)
+    } else {
+        let mut message = result
+            .issues
+            .iter()
+            .map(NoteLoadIssue::display)
+            .collect::<Vec<_>>()
+            .join("; ");
+        if let Some(path) = result.index_backup_path {
+            message.push_str(&format!("; corrupt index backup: {path}"));
+        }
+        Err(message)
+    }
+}
+
+fn is_watched_relative_path(relative: &str) -> bool {
+    let mut segments = relative.split('/');
+    let first = segments.next().unwrap_or_default();
+    if first.is_empty() || is_reserved_library_directory(first) {
+        return false;
+    }
+    let file_name = relative.rsplit('/').next().unwrap_or_default();
+    !file_name.starts_with(".hwan-note-")
+}
+
+fn path_is_within(relative: &str, affected: &str) -> bool {
+    relative == affected
+        || relative
+            .strip_prefix(affected)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Re-scans only the paths named in a watcher batch and folds them into the
+/// index with `reconcile_index_with_scan`. Rename pairs are applied to the
+/// index first so externally moved notes keep their IDs, pins, and titles.
+pub(crate) fn reconcile_external_changes(
+    auto_save_dir: &Path,
+    batch: &ExternalChangeBatch,
+) -> Result<ExternalLibraryChanges, String> {
+    reconcile_external_changes_with_fs(auto_save_dir, batch, &ProductionFileSystem)
+}
+
+fn reconcile_external_changes_with_fs<F: LibraryFileSystem>(
+    auto_save_dir: &Path,
+    batch: &ExternalChangeBatch,
+    file_system: &F,
+) -> Result<ExternalLibraryChanges, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let to_relative = |path: &Path| -> Option<String> {
+        let relative = path.strip_prefix(trusted_root.path()).ok()?;
+        let relative = to_posix(&relative.to_string_lossy());
+        is_watched_relative_path(&relative).then_some(relative)
+    };
+
+    let mut affected = Vec::new();
+    let mut renames = Vec::new();
+    for path in &batch.changed_paths {
+        affected.extend(to_relative(path));
+    }
+    for (from, to) in &batch.renamed_paths {
+        match (to_relative(from), to_relative(to)) {
+            (Some(from), Some(to)) => {
+                affected.push(from.clone());
+                affected.push(to.clone());
+                renames.push((from, to));
+            }
+            (from, to) => affected.extend(from.into_iter().chain(to)),
+        }
+    }
+    affected.sort();
+    affected.dedup();
+    if affected.is_empty() {
+        return Ok(ExternalLibraryChanges::default());
+    }
+
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let index_snapshot = require_index_snapshot(&trusted_root)?;
+    let mut working_index = index_snapshot.index.clone();
+
+    let mut previous_paths = HashMap::new();
+    for (from, to) in &renames {
+        for (note_id, entry) in working_index.entries.iter_mut() {
+            if path_is_within(&entry.relative_path, from) {
+                let moved_path = format!("{to}{}", &entry.relative_path[from.len()..]);
+                previous_paths
+                    .entry(note_id.clone())
+                    .or_insert_with(|| entry.relative_path.clone());
+                entry.relative_path = moved_path;
+            }
+        }
+    }
+
+    let mut scan = LibraryScan::default();
+    for (note_id, entry) in &working_index.entries {
+        let is_affected = affected
+            .iter()
+            .any(|path| path_is_within(&entry.relative_path, path))
+            || previous_paths.contains_key(note_id);
+        if !is_affected {
+            scan.files.insert(
+                entry.relative_path.clone(),
+                ScannedMarkdown {
+                    full_path: trusted_root.path().join(&entry.relative_path),
+                    markdown: String::new(),
+                    created_at: entry.created_at,
+                    updated_at: 0,
+                },
+            );
+        }
+    }
+    let mut rescanned = HashSet::new();
+    for relative in &affected {
+        let relative_path_buf = normalize_library_relative_path(trusted_root.path(), relative)
+            .map_err(|error| error.display("validate_watched_path"))?;
+        if let Err(error) = validate_no_symlink_beneath_root(&trusted_root, &relative_path_buf) {
+            scan.issues.push(NoteLoadIssue::new(
+                NoteLoadIssueKind::Scan,
+                "reject_symlink",
+                &error.path,
+                error.reason,
+            ));
+            continue;
+        }
+        let path = trusted_root.path().join(&relative_path_buf);
+        if fs::symlink_metadata(&path).is_err_and(|error| error.kind() == io::ErrorKind::NotFound) {
+            continue;
+        }
+        match file_system.entry_type(&path) {
+            Ok(LibraryEntryType::Directory) => {
+                let subtree = scan_library_subtree(file_system, &trusted_root, &path, true);
+                scan.issues.extend(subtree.issues);
+                for (relative, scanned) in subtree.files {
+                    rescanned.insert(relative.clone());
+                    scan.files.insert(relative, scanned);
+                }
+            }
+            Ok(LibraryEntryType::File) if is_markdown_path(&path) => {
+                scan_markdown_file(file_system, &trusted_root, &path, &mut scan);
+                rescanned.insert(relative.clone());
+            }
+            Ok(LibraryEntryType::Symlink) => scan.issues.push(NoteLoadIssue::new(
+                NoteLoadIssueKind::Scan,
+                "reject_symlink",
+                &path,
+                "symbolic links and reparse points beneath the note library are not allowed",
+            )),
+            Ok(LibraryEntryType::File | LibraryEntryType::Other) => {}
+            Err(error) => scan.issues.push(scan_issue(error)),
+        }
+    }
+    if !scan.is_complete() {
+        return Ok(ExternalLibraryChanges {
+            issues: scan.issues,
+            ..ExternalLibraryChanges::default()
+        });
+    }
+
+    let conflicts = split_sync_conflicts(&working_index, &mut scan);
+    let (reconciled_index, _) = reconcile_index_with_scan(&working_index, &scan);
+    if reconciled_index != index_snapshot.index {
+        write_index_from_snapshot(&trusted_root, &index_snapshot, &reconciled_index)
+            .map_err(index_write_failure_to_string)?;
+    }
+
+    let mut changes = ExternalLibraryChanges {
+        conflicts,
+        ..ExternalLibraryChanges::default()
+    };
+    let mut removed_ids = Vec::new();
+    for (note_id, entry) in &index_snapshot.index.entries {
+        if !reconciled_index.entries.contains_key(note_id) {
+            forget_note_digest(&trusted_root, note_id);
+            removed_ids.push(note_id.clone());
+            changes.removed.push(ExternalNoteRemoval {
+                note_id: note_id.clone(),
+                file_path: trusted_root
+                    .path()
+                    .join(&entry.relative_path)
+                    .to_string_lossy()
+                    .to_string(),
+            });
+        }
+    }
+
+    let mut search_updates = Vec::new();
+    let mut link_updates = Vec::new();
+    for note in materialize_notes(&reconciled_index, &scan) {
+        let Some(entry) = reconciled_index.entries.get(&note.note_id) else {
+            continue;
+        };
+        if !rescanned.contains(&entry.relative_path) {
+            continue;
+        }
+        let digest = sha256_hex(scan.files[&entry.relative_path].markdown.as_bytes());
+        let previous_digest = known_note_digests().insert(
+            (trusted_root.path().to_path_buf(), note.note_id.clone()),
+            digest.clone(),
+        );
+        search_updates.push((
+            note.note_id.clone(),
+            SearchDocument::from_loaded_note(&note, &entry.relative_path),
+        ));
+        link_updates.push((
+            note.note_id.clone(),
+            LinkNode::from_loaded_note(&note, &entry.relative_path),
+        ));
+
+        match index_snapshot.index.entries.get(&note.note_id) {
+            None => changes.added.push(note),
+            Some(previous) if previous.relative_path != entry.relative_path => {
+                changes.moved.push(ExternalNoteMove {
+                    previous_file_path: trusted_root
+                        .path()
+                        .join(&previous.relative_path)
+                        .to_string_lossy()
+                        .to_string(),
+                    note,
+                });
+            }
+            Some(_) if previous_digest.as_deref() != Some(digest.as_str()) => {
+                changes.changed.push(note);
+            }
+            Some(_) => {}
+        }
+    }
+
+    note_links::record_notes_removed(&trusted_root, &removed_ids);
+    note_links::record_notes_saved(&trusted_root, link_updates);
+    if reconciled_index != index_snapshot.index || !search_updates.is_empty() {
+        search_index::record_external_changes(
+            &trusted_root,
+            index_snapshot_digest(&index_snapshot).as_deref(),
+            current_index_digest(&trusted_root)?.as_deref(),
+            search_updates,
+            &removed_ids,
+        );
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+pub fn resolve_note_file_path(
+    auto_save_dir: &Path,
+    note_id: &str,
+) -> Result<Option<PathBuf>, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    resolve_note_file_path_unlocked(&trusted_root, note_id)
+}
+
+#[cfg(test)]
+fn resolve_note_file_path_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+) -> Result<Option<PathBuf>, String> {
+    let safe_id = sanitize_note_id(note_id);
+    if safe_id.is_empty() {
+        return Ok(None);
+    }
+
+    let index = require_index_snapshot(trusted_root)?.index;
+    let entry = match index.entries.get(&safe_id) {
+        Some(e) => e,
+        None => return Ok(None),
+    };
+
+    Ok(Some(validated_library_file_path(
+        trusted_root,
+        &entry.relative_path,
+    )?))
+}
+
+/// Returns a note's current index entry after replaying any pending autosave.
+pub(crate) fn note_index_entry(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+) -> Result<Option<NoteIndexEntry>, String> {
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(trusted_root)?;
+    let index = require_index_snapshot(trusted_root)?.index;
+    Ok(index.entries.get(&sanitize_note_id(note_id)).cloned())
+}
+
+/// Returns every index entry by note id after replaying any pending autosave.
+pub(crate) fn note_index_entries(
+    trusted_root: &TrustedLibraryRoot,
+) -> Result<HashMap<String, NoteIndexEntry>, String> {
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(trusted_root)?;
+    Ok(require_index_snapshot(trusted_root)?.index.entries)
+}
+
+/// Reads one note as `load_markdown_library` would list it, after replaying
+/// any pending autosave.
+pub(crate) fn read_loaded_note(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+) -> Result<Option<LoadedNote>, String> {
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(trusted_root)?;
+    let safe_id = sanitize_note_id(note_id);
+    let index = require_index_snapshot(trusted_root)?.index;
+    let Some(entry) = index.entries.get(&safe_id) else {
+        return Ok(None);
+    };
+    let path = validated_library_file_path(trusted_root, &entry.relative_path)?;
+    let bytes = read_trusted_file_bytes(trusted_root, &path, "read_note")?;
+    let stored_markdown = String::from_utf8(bytes)
+        .map_err(|error| format!("read_note failed for {}: {error}", path.display()))?;
+    let updated_at = fs::symlink_metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .map(system_time_to_millis)
+        .unwrap_or_else(|_| now_millis());
+    Ok(Some(if note_crypto::is_armored(&stored_markdown) {
+        locked_note(&safe_id, entry, &path, updated_at)
+    } else {
+        loaded_note(&safe_id, entry, &stored_markdown, &path, updated_at)
+    }))
+}
+
+#[cfg(test)]
+fn trash_note_file_or_accept_missing<F>(file_path: &Path, delete_file: F) -> Result<(), String>
+where
+    F: FnOnce(&Path) -> Result<(), String>,
+{
+    match delete_file(file_path) {
+        Ok(()) => Ok(()),
+        Err(delete_error) => match fs::symlink_metadata(file_path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Ok(_) => Err(delete_error),
+            Err(error) => Err(format!(
+                "Failed to recheck note file after trash error: {error}"
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+pub fn remove_note_from_index_if_path(
+    auto_save_dir: &Path,
+    note_id: &str,
+    expected_file_path: &Path,
+) -> Result<Option<PathBuf>, String> {
+    let safe_id = sanitize_note_id(note_id);
+    if safe_id.is_empty() {
+        return Ok(None);
+    }
+
+    let trusted_root = resolve_trusted_library_root(auto_save_dir)
+        .map_err(|error| error.display("validate_library_root"))?;
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let expected_file_path =
+        canonicalize_expected_library_path(&trusted_root, auto_save_dir, expected_file_path)?;
+    remove_note_from_index_if_path_unlocked(&trusted_root, note_id, &expected_file_path)
+}
+
+fn remove_note_from_index_if_path_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+    expected_file_path: &Path,
+) -> Result<Option<PathBuf>, String> {
+    let safe_id = sanitize_note_id(note_id);
+    if safe_id.is_empty() {
+        return Ok(None);
+    }
+
+    let index_snapshot = require_index_snapshot(trusted_root)?;
+    let mut index = index_snapshot.index.clone();
+    let entry = match index.entries.get(&safe_id) {
+        Some(e) => e.clone(),
+        None => return Ok(None),
+    };
+
+    let file_path = validated_library_file_path(trusted_root, &entry.relative_path)?;
+    if file_path != expected_file_path {
+        return Err("Note index changed before delete completed".to_string());
+    }
+    match fs::symlink_metadata(expected_file_path) {
+        Ok(_) => return Err("Note file changed before delete completed".to_string()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => {
+            return Err(format!(
+                "Failed to check note file before delete cleanup: {error}"
+            ));
+        }
+    }
+
+    index.entries.remove(&safe_id);
+    write_index_from_snapshot(trusted_root, &index_snapshot, &index)
+        .map_err(index_write_failure_to_string)?;
+    note_links::record_notes_removed(trusted_root, std::slice::from_ref(&safe_id));
+    search_index::record_notes_removed(
+        trusted_root,
+        index_snapshot_digest(&index_snapshot).as_deref(),
+        current_index_digest(trusted_root)?.as_deref(),
+        &[safe_id],
+    );
+    Ok(Some(file_path))
+}
+
+#[cfg(test)]
+pub fn delete_note_file_and_index<F>(
+    auto_save_dir: &Path,
+    note_id: &str,
+    delete_file: F,
+) -> Result<bool, String>
+where
+    F: FnOnce(&Path) -> Result<(), String>,
+{
+    let trusted_root = resolve_trusted_library_root(auto_save_dir)
+        .map_err(|error| error.display("validate_library_root"))?;
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let file_path = match resolve_note_file_path_unlocked(&trusted_root, note_id)? {
+        Some(p) => p,
+        None => return Ok(false),
+    };
+
+    match fs::symlink_metadata(&file_path) {
+        Ok(_) => {
+            validate_existing_trusted_file(&trusted_root, &file_path, "validate_note_delete")?;
+            validate_existing_trusted_file(&trusted_root, &file_path, "validate_note_delete")?;
+            trash_note_file_or_accept_missing(&file_path, delete_file)?;
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(format!("Failed to check note file before delete: {error}")),
+    }
+
+    let removed = remove_note_from_index_if_path_unlocked(&trusted_root, note_id, &file_path)?;
+    if removed.is_some() {
+        if let Err(error) = note_history::remove_note_history_unlocked(&trusted_root, note_id) {
+            tracing::warn!("remove_note_history failed for {note_id}: {error}");
+        }
+    }
+    Ok(removed.is_some())
+}
+
+/// Extensions that open as external text files when nothing is configured.
+pub const DEFAULT_TEXT_EXTENSIONS: &[&str] = &["txt"];
+const MARKDOWN_FILE_EXTENSIONS: &[&str] = &["md", "markdown"];
+const MAX_TEXT_EXTENSION_LENGTH: usize = 16;
+
+/// How an external document is edited: Markdown files keep their formatting,
+/// everything else is plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExternalFileFormat {
+    Md,
+    Txt,
+}
+
+/// Lowercases and deduplicates a configured extension list, dropping leading
+/// dots. Markdown extensions are always accepted and are not listed.
+pub fn normalize_text_extensions(extensions: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for extension in extensions {
+        let extension = extension
+            .trim()
+            .trim_start_matches('.')
+            .to_ascii_lowercase();
+        if extension.is_empty()
+            || extension.len() > MAX_TEXT_EXTENSION_LENGTH
+            || !extension.chars().all(|ch| ch.is_ascii_alphanumeric())
+        {
+            return Err(format!("Invalid text file extension: {extension}"));
+        }
+        if !MARKDOWN_FILE_EXTENSIONS.contains(&extension.as_str())
+            && !normalized.contains(&extension)
+        {
+            normalized.push(extension);
+        }
+    }
+    Ok(normalized)
+}
+
+pub fn external_file_format(path: &Path, text_extensions: &[String]) -> Option<ExternalFileFormat> {
+    let ext = path.extension().and_then(|e| e.to_str())?;
+    if MARKDOWN_FILE_EXTENSIONS
+        .iter()
+        .any(|markdown| markdown.eq_ignore_ascii_case(ext))
+    {
+        Some(ExternalFileFormat::Md)
+    } else if text_extensions
+        .iter()
+        .any(|text| text.eq_ignore_ascii_case(ext))
+    {
+        Some(ExternalFileFormat::Txt)
+    } else {
+        None
+    }
+}
+
+pub fn normalize_external_document_path(
+    raw_path: &str,
+    base_dir: Option<&Path>,
+    text_extensions: &[String],
+) -> Result<(PathBuf, ExternalFileFormat), String> {
+    let trimmed = raw_path.trim().trim_matches('"');
+    if trimmed.is_empty() {
+        return Err("File path is required.".to_string());
+    }
+
+    let candidate = PathBuf::from(trimmed);
+    let resolved = if candidate.is_absolute() {
+        candidate
+    } else if let Some(base) = base_dir {
+        base.join(candidate)
+    } else {
+        std::env::current_dir()
+            .map_err(|e| e.to_string())?
+            .join(candidate)
+    };
+
+    let normalized = fs::canonicalize(&resolved).map_err(|_| "Text file not found.".to_string())?;
+    let metadata = fs::metadata(&normalized).map_err(|e| e.to_string())?;
+
+    if !metadata.is_file() {
+        return Err("Only files can be opened.".to_string());
+    }
+
+    let format = external_file_format(&normalized, text_extensions)
+        .ok_or_else(|| "Only Markdown and text files are supported.".to_string())?;
+
+    Ok((normalized, format))
+}
+
+/// What an external file looked like when it was read or last saved, so a
+/// save can tell whether another program changed it in the meantime.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalFileVersion {
+    /// SHA-256 of the raw bytes on disk.
+    pub digest: String,
+    pub modified_at: u64,
+}
+
+impl ExternalFileVersion {
+    fn of(bytes: &[u8], metadata: &fs::Metadata) -> Self {
+        Self {
+            digest: sha256_hex(bytes),
+            modified_at: metadata
+                .modified()
+                .map(system_time_to_millis)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ExternalSaveResult {
+    Saved {
+        version: ExternalFileVersion,
+    },
+    /// The file no longer matches the version the editor started from and
+    /// was left untouched. `current` is `None` when the file was deleted.
+    #[serde(rename_all = "camelCase")]
+    Conflict {
+        current: Option<ExternalFileVersion>,
+    },
+}
+
+fn read_external_file_version(file_path: &Path) -> Result<Option<ExternalFileVersion>, String> {
+    let read = fs::read(file_path).and_then(|bytes| {
+        let metadata = fs::metadata(file_path)?;
+        Ok(ExternalFileVersion::of(&bytes, &metadata))
+    });
+    match read {
+        Ok(version) => Ok(Some(version)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!(
+            "read_external_file failed for {}: {error}",
+            file_path.display()
+        )),
+    }
+}
+
+/// Reads an external text file in its detected encoding, or in `encoding`
+/// when the user picked one.
+pub fn read_text_file(
+    file_path: &Path,
+    encoding: Option<&str>,
+) -> Result<(String, TextFormat, ExternalFileVersion), String> {
+    let encoding = encoding.map(encoding_for_label).transpose()?;
+    let bytes = fs::read(file_path).map_err(|e| e.to_string())?;
+    let metadata = fs::metadata(file_path).map_err(|e| e.to_string())?;
+    let version = ExternalFileVersion::of(&bytes, &metadata);
+    let (content, format) = decode_text(&bytes, encoding);
+    Ok((content, format, version))
+}
+
+fn unique_external_tmp_path(file_path: &Path) -> Result<(PathBuf, fs::File), String> {
+    let parent = file_path.parent().unwrap_or_else(|| Path::new("."));
+    let file_name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let timestamp = now_millis();
+
+    for counter in 0u32.. {
+        let candidate = parent.join(format!(".{file_name}.tmp-{timestamp}-{counter}"));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => return Ok((candidate, file)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => {
+                return Err(format!(
+                    "create_external_temp failed for {}: {error}",
+                    candidate.display()
+                ));
+            }
+        }
+    }
+
+    unreachable!("external temp counter is unbounded")
+}
+
+/// Writes an external text file through a temp file and an atomic replace.
+/// With `expected`, the save is refused when the file on disk no longer has
+/// that content, so edits made by another program are never overwritten.
+pub fn save_text_file(
+    file_path: &Path,
+    content: &str,
+    format: &TextFormat,
+    expected: Option<&ExternalFileVersion>,
+) -> Result<ExternalSaveResult, String> {
+    let bytes = encode_text(content, format)?;
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let (tmp_path, mut tmp_file) = unique_external_tmp_path(file_path)?;
+    if let Err(error) = tmp_file
+        .write_all(&bytes)
+        .and_then(|()| tmp_file.sync_all())
+    {
+        drop(tmp_file);
+        return Err(cleanup_file_with_reason(
+            &tmp_path,
+            format!(
+                "write_external_temp failed for {}: {error}",
+                tmp_path.display()
+            ),
+        ));
+    }
+    drop(tmp_file);
+
+    if let Some(expected) = expected {
+        let current = match read_external_file_version(file_path) {
+            Ok(current) => current,
+            Err(reason) => return Err(cleanup_file_with_reason(&tmp_path, reason)),
+        };
+        if current
+            .as_ref()
+            .is_none_or(|current| current.digest != expected.digest)
+        {
+            let _ = fs::remove_file(&tmp_path);
+            return Ok(ExternalSaveResult::Conflict { current });
+        }
+    }
+
+    if let Err(reason) = publish_temp_file(&tmp_path, file_path, "save_external_file") {
+        return Err(cleanup_file_with_reason(&tmp_path, reason));
+    }
+    let metadata = fs::metadata(file_path).map_err(|e| e.to_string())?;
+    Ok(ExternalSaveResult::Saved {
+        version: ExternalFileVersion::of(&bytes, &metadata),
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationResult {
+    pub files_copied: u32,
+    pub attachments_copied: u32,
+    pub index_copied: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<MigratedNote>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationOutcome {
+    Copied,
+    Renamed,
+    Skipped,
+}
+
+/// What happened to one source note during a merge. `Renamed` means the note
+/// landed under a new path or id because the original one was taken.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigratedNote {
+    pub note_id: String,
+    pub source_path: String,
+    pub destination_path: Option<String>,
+    pub outcome: MigrationOutcome,
+}
+
+fn copy_trusted_library_file(
+    src_root: &TrustedLibraryRoot,
+    src_path: &Path,
+    dst_root: &TrustedLibraryRoot,
+    dst_path: &Path,
+) -> Result<(), String> {
+    validate_existing_trusted_file(src_root, src_path, "validate_migration_source")?;
+    let mut src_file = fs::File::open(src_path).map_err(|error| {
+        format!(
+            "open_migration_source failed for {}: {}",
+            src_path.display(),
+            error
+        )
+    })?;
+    validate_existing_trusted_file(src_root, src_path, "validate_migration_source")?;
+    validate_note_destination_before_replace(dst_root, dst_path)?;
+    let mut dst_file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst_path)
+        .map_err(|error| {
+            format!(
+                "create_migration_destination failed for {}: {}",
+                dst_path.display(),
+                error
+            )
+        })?;
+
+    // The modification time is the note's updated time, so it moves with it.
+    let copied = io::copy(&mut src_file, &mut dst_file)
+        .and_then(
+            |_| match src_file.metadata().and_then(|meta| meta.modified()) {
+                Ok(modified) => dst_file.set_modified(modified),
+                Err(_) => Ok(()),
+            },
+        )
+        .and_then(|_| dst_file.sync_all());
+    if let Err(error) = copied {
+        drop(dst_file);
+        return Err(cleanup_file_with_reason(
+            dst_path,
+            format!(
+                "copy_migration_file failed from {} to {}: {}",
+                src_path.display(),
+                dst_path.display(),
+                error
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Merge all .md files from src_dir into dst_dir without overwriting
+/// existing destination notes or replacing the destination index.
+/// Preserves relative directory structure and creates empty folders.
+pub fn migrate_notes(src_dir: &Path, dst_dir: &Path) -> Result<MigrationResult, String> {
+    migrate_selected_notes(src_dir, dst_dir, None)
+}
+
+/// `migrate_notes` limited to the notes in `selected`, when given. Only the
+/// folders those notes live in are created.
+fn migrate_selected_notes(
+    src_dir: &Path,
+    dst_dir: &Path,
+    selected: Option<&HashSet<String>>,
+) -> Result<MigrationResult, String> {
+    let src_root = resolve_trusted_library_root(src_dir)
+        .map_err(|error| error.display("validate_source_library_root"))?;
+    let dst_root = resolve_trusted_library_root(dst_dir)
+        .map_err(|error| error.display("validate_destination_library_root"))?;
+
+    if src_root.path() == dst_root.path() {
+        return Ok(MigrationResult {
+            files_copied: 0,
+            attachments_copied: 0,
+            index_copied: false,
+            notes: Vec::new(),
+        });
+    }
+
+    // When the current local library root is the parent of the cloud library root
+    // (for example `.../HwanNote` -> `.../HwanNote/Notes`), skip the destination
+    // subtree while collecting source files so we do not recursively copy the
+    // cloud library back into itself.
+    let skip_src_subtree = dst_root
+        .path()
+        .starts_with(src_root.path())
+        .then_some(dst_root.path());
+
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&src_root)?;
+    if src_root.path() != dst_root.path() {
+        recover_pending_note_save_unlocked(&dst_root)?;
+    }
+    let src_snapshot = require_index_snapshot(&src_root)?;
+    let dst_snapshot = require_index_snapshot(&dst_root)?;
+    let src_scan = scan_library_tree(&ProductionFileSystem, &src_root, skip_src_subtree, true);
+    let dst_scan = scan_library_tree(&ProductionFileSystem, &dst_root, None, true);
+
+    if !src_scan.is_complete() || !dst_scan.is_complete() {
+        return Err(src_scan
+            .issues
+            .iter()
+            .chain(dst_scan.issues.iter())
+            .map(NoteLoadIssue::display)
+            .collect::<Vec<_>>()
+            .join("; "));
+    }
+
+    let (src_index, src_index_changed) = reconcile_index_with_scan(&src_snapshot.index, &src_scan);
+    let (mut dst_index, mut dst_index_changed) =
+        reconcile_index_with_scan(&dst_snapshot.index, &dst_scan);
+
+    for folder in src_scan.folders.iter().filter(|_| selected.is_none()) {
+        let relative_folder = normalize_library_relative_path(dst_root.path(), folder)
+            .map_err(|error| error.display("validate_migration_folder"))?;
+        ensure_library_subdirectory(&dst_root, &relative_folder)?;
+    }
+
+    let mut existing_dst_paths: HashSet<String> = dst_scan.files.keys().cloned().collect();
+    let mut existing_dst_ids: HashSet<String> = dst_index.entries.keys().cloned().collect();
+    let mut files_copied: u32 = 0;
+    let mut migrated_notes = Vec::new();
+
+    let mut source_entries: Vec<_> = src_index
+        .entries
+        .iter()
+        .filter(|(note_id, _)| selected.is_none_or(|selected| selected.contains(*note_id)))
+        .collect();
+    source_entries.sort_by(|(left_id, _), (right_id, _)| left_id.cmp(right_id));
+
+    for &(src_note_id, src_entry) in &source_entries {
+        let Some(src_file) = src_scan.files.get(&src_entry.relative_path) else {
+            continue;
+        };
+
+        if let Some(existing_entry) = dst_index.entries.get(src_note_id) {
+            if dst_scan.files.contains_key(&existing_entry.relative_path) {
+                migrated_notes.push(MigratedNote {
+                    note_id: src_note_id.clone(),
+                    source_path: src_entry.relative_path.clone(),
+                    destination_path: None,
+                    outcome: MigrationOutcome::Skipped,
+                });
+                continue;
+            }
+        }
+
+        let desired_relative =
+            normalize_library_relative_path(dst_root.path(), &src_entry.relative_path)
+                .map_err(|error| error.display("validate_migration_note_path"))?;
+        validate_no_symlink_beneath_root(&dst_root, &desired_relative)
+            .map_err(|error| error.display("validate_migration_note_path"))?;
+        let desired_path = dst_root.path().join(&desired_relative);
+        let parent_dir = desired_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| dst_root.path().to_path_buf());
+        let base_name = src_file
+            .full_path
+            .file_stem()
+            .and_then(|value| value.to_str())
+            .filter(|value| !value.is_empty())
+            .unwrap_or("untitled");
+
+        let desired_exists = match fs::symlink_metadata(&desired_path) {
+            Ok(_) => true,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => false,
+            Err(error) => return Err(error.to_string()),
+        };
+        let final_path = if existing_dst_paths.contains(&src_entry.relative_path) || desired_exists
+        {
+            ensure_unique_file_path(&parent_dir, base_name, None)?
+        } else {
+            desired_path
+        };
+
+        if let Some(parent) = final_path.parent() {
+            let parent_relative = parent.strip_prefix(dst_root.path()).map_err(|error| {
+                format!(
+                    "validate_migration_parent failed for {}: {}",
+                    parent.display(),
+                    error
+                )
+            })?;
+            ensure_library_subdirectory(&dst_root, parent_relative)?;
+        }
+
+        copy_trusted_library_file(&src_root, &src_file.full_path, &dst_root, &final_path)?;
+        files_copied += 1;
+
+        let final_rel = relative_path(dst_root.path(), &final_path);
+        let final_note_id = if existing_dst_ids.contains(src_note_id) {
+            ensure_unique_note_id(&existing_dst_ids, &final_rel)
+        } else {
+            src_note_id.clone()
+        };
+
+        dst_index.entries.insert(
+            final_note_id.clone(),
+            NoteIndexEntry {
+                relative_path: final_rel.clone(),
+                created_at: src_entry.created_at,
+                manual_title: src_entry.manual_title.clone(),
+                is_pinned: src_entry.is_pinned,
+                seen_by: BTreeMap::new(),
+            },
+        );
+        migrated_notes.push(MigratedNote {
+            note_id: final_note_id.clone(),
+            source_path: src_entry.relative_path.clone(),
+            destination_path: Some(final_rel.clone()),
+            outcome: if final_rel == src_entry.relative_path && final_note_id == *src_note_id {
+                MigrationOutcome::Copied
+            } else {
+                MigrationOutcome::Renamed
+            },
+        });
+        existing_dst_paths.insert(final_rel);
+        existing_dst_ids.insert(final_note_id);
+        dst_index_changed = true;
+    }
+
+    let source_markdown = source_entries
+        .iter()
+        .filter_map(|(_, entry)| src_scan.files.get(&entry.relative_path))
+        .map(|file| file.markdown.as_str())
+        .collect::<Vec<_>>();
+    let attachments_copied = copy_referenced_attachments(&src_root, &source_markdown, &dst_root)?;
+
+    if dst_index_changed {
+        write_index_from_snapshot(&dst_root, &dst_snapshot, &dst_index)
+            .map_err(index_write_failure_to_string)?;
+    }
+    if src_index_changed {
+        write_index_from_snapshot(&src_root, &src_snapshot, &src_index)
+            .map_err(index_write_failure_to_string)?;
     }
+
+    Ok(MigrationResult {
+        files_copied,
+        attachments_copied,
+        index_copied: dst_index_changed,
+        notes: migrated_notes,
+    })
 }
 
-fn load_markdown_library_with_fs<F: LibraryFileSystem>(
-    auto_save_dir: &Path,
-    file_system: &F,
-) -> MarkdownLibraryLoadResult {
-    let trusted_root = match resolve_trusted_library_root(auto_save_dir) {
-        Ok(trusted_root) => trusted_root,
-        Err(error) => {
-            return MarkdownLibraryLoadResult {
-                notes: Vec::new(),
-                folders: Vec::new(),
-                load_state: NoteLoadState::Incomplete,
-                issues: vec![NoteLoadIssue::new(
-                    NoteLoadIssueKind::Scan,
-                    "validate_library_root",
-                    &error.path,
-                    error.reason,
-                )],
-                index_source_path: Some(
-                    get_index_path(auto_save_dir).to_string_lossy().to_string(),
-                ),
-                index_backup_path: None,
-            };
+/// Copies every attachment a source note links to into the destination's
+/// attachments directory. Names are content hashes, so an attachment already
+/// present at the destination is the same file and is left alone. Notes keep
+/// their folder when migrated, so their relative links stay valid.
+fn copy_referenced_attachments(
+    src_root: &TrustedLibraryRoot,
+    source_markdown: &[&str],
+    dst_root: &TrustedLibraryRoot,
+) -> Result<u32, String> {
+    let referenced = source_markdown
+        .iter()
+        .flat_map(|markdown| attachments::referenced_attachments(markdown))
+        .collect::<BTreeSet<_>>();
+    let mut copied = 0;
+    for file_name in referenced {
+        let relative = format!("{}/{file_name}", attachments::ATTACHMENTS_DIRECTORY_NAME);
+        let src_path = src_root.path().join(&relative);
+        if fs::symlink_metadata(&src_path).is_err() {
+            continue;
         }
-    };
-    let index_source_path = get_index_path(trusted_root.path());
+        let dst_path = dst_root.path().join(&relative);
+        match fs::symlink_metadata(&dst_path) {
+            Ok(_) => continue,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error.to_string()),
+        }
+        ensure_library_subdirectory(dst_root, Path::new(attachments::ATTACHMENTS_DIRECTORY_NAME))?;
+        copy_trusted_library_file(src_root, &src_path, dst_root, &dst_path)?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+// ── Local fallback reconciliation ──
+
+/// Where a note's file is and the SHA-256 of its contents, `None` when the
+/// file is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NoteFileState {
+    pub relative_path: String,
+    pub digest: Option<String>,
+}
+
+fn note_file_state_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    entry: &NoteIndexEntry,
+) -> Result<NoteFileState, String> {
+    let path = validated_library_file_path(trusted_root, &entry.relative_path)?;
+    Ok(NoteFileState {
+        relative_path: entry.relative_path.clone(),
+        digest: read_existing_file_digest(trusted_root, &path, "read_note_digest")?,
+    })
+}
 
+/// The state of one note's file, or `None` when the note is not indexed.
+pub(crate) fn note_file_state(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+) -> Result<Option<NoteFileState>, String> {
     let _index_guard = lock_note_index();
-    if let Err(reason) = recover_pending_note_save_unlocked(&trusted_root) {
-        return MarkdownLibraryLoadResult {
-            notes: Vec::new(),
-            folders: Vec::new(),
-            load_state: NoteLoadState::Incomplete,
-            issues: vec![NoteLoadIssue::new(
-                NoteLoadIssueKind::Index,
-                "recover_pending_note_save",
-                &autosave_journal_path(&trusted_root),
-                reason,
-            )],
-            index_source_path: Some(index_source_path.to_string_lossy().to_string()),
-            index_backup_path: None,
+    let index = require_index_snapshot(trusted_root)?.index;
+    index
+        .entries
+        .get(note_id)
+        .map(|entry| note_file_state_unlocked(trusted_root, entry))
+        .transpose()
+}
+
+/// The state of every indexed note's file, by note id.
+pub(crate) fn note_file_states(
+    trusted_root: &TrustedLibraryRoot,
+) -> Result<BTreeMap<String, NoteFileState>, String> {
+    let _index_guard = lock_note_index();
+    let index = require_index_snapshot(trusted_root)?.index;
+    index
+        .entries
+        .iter()
+        .map(|(note_id, entry)| {
+            Ok((
+                note_id.clone(),
+                note_file_state_unlocked(trusted_root, entry)?,
+            ))
+        })
+        .collect()
+}
+
+/// Pushes notes changed in the local library while the cloud library was
+/// unreachable into `dst_dir`. Notes the cloud library lacks are copied as
+/// `migrate_notes` would. A cloud copy that still matches the note's
+/// pre-fallback version is replaced or trashed; one that changed meanwhile is
+/// kept, and the local version is added next to it as a separate note.
+pub(crate) fn reconcile_fallback_notes(
+    src_dir: &Path,
+    dst_dir: &Path,
+    changes: &BTreeMap<String, FallbackChange>,
+    history_retention: &NoteHistoryRetention,
+) -> Result<Vec<ReconciledNote>, String> {
+    let saved_ids = changes
+        .iter()
+        .filter(|(_, change)| !change.deleted)
+        .map(|(note_id, _)| note_id.clone())
+        .collect::<HashSet<_>>();
+    let mut reconciled = Vec::new();
+    let mut in_cloud = HashSet::new();
+    for note in migrate_selected_notes(src_dir, dst_dir, Some(&saved_ids))?.notes {
+        let outcome = match note.outcome {
+            MigrationOutcome::Copied => ReconcileOutcome::Copied,
+            MigrationOutcome::Renamed => ReconcileOutcome::Renamed,
+            MigrationOutcome::Skipped => {
+                in_cloud.insert(note.note_id);
+                continue;
+            }
         };
+        reconciled.push(ReconciledNote {
+            note_id: note.note_id,
+            destination_path: note.destination_path,
+            outcome,
+        });
     }
-    let index_snapshot = match read_index_state(&trusted_root) {
-        IndexReadState::Ready(snapshot) => snapshot,
-        IndexReadState::Corrupt(state) => {
-            return MarkdownLibraryLoadResult {
-                notes: Vec::new(),
-                folders: Vec::new(),
-                load_state: NoteLoadState::IndexCorrupt,
-                issues: state.issues,
-                index_source_path: Some(index_source_path.to_string_lossy().to_string()),
-                index_backup_path: state
-                    .backup_path
-                    .map(|path| path.to_string_lossy().to_string()),
+
+    let src_root = TrustedLibraryRoot::open(src_dir)?;
+    let dst_root = TrustedLibraryRoot::open(dst_dir)?;
+    let mut to_trash = Vec::new();
+    {
+        let _index_guard = lock_note_index();
+        recover_pending_note_save_unlocked(&src_root)?;
+        recover_pending_note_save_unlocked(&dst_root)?;
+        let src_index = require_index_snapshot(&src_root)?.index;
+        let dst_snapshot = require_index_snapshot(&dst_root)?;
+        let mut dst_index = dst_snapshot.index.clone();
+        let mut existing_ids: HashSet<String> = dst_index.entries.keys().cloned().collect();
+        let mut written_markdown = Vec::new();
+        let mut moved_from = Vec::new();
+
+        for (note_id, change) in changes {
+            if !change.deleted && !in_cloud.contains(note_id) {
+                continue;
+            }
+            let Some(dst_entry) = dst_index.entries.get(note_id).cloned() else {
+                continue;
+            };
+            let dst_path = validated_library_file_path(&dst_root, &dst_entry.relative_path)?;
+            let Some(dst_digest) =
+                read_existing_file_digest(&dst_root, &dst_path, "read_cloud_note")?
+            else {
+                continue;
+            };
+            let cloud_unchanged = change.base_digest.as_deref() == Some(dst_digest.as_str());
+            if change.deleted {
+                if cloud_unchanged {
+                    to_trash.push(note_id.clone());
+                } else {
+                    reconciled.push(ReconciledNote {
+                        note_id: note_id.clone(),
+                        destination_path: Some(dst_entry.relative_path.clone()),
+                        outcome: ReconcileOutcome::Conflict,
+                    });
+                }
+                continue;
+            }
+
+            let Some(src_entry) = src_index.entries.get(note_id) else {
+                continue;
             };
+            let src_path = validated_library_file_path(&src_root, &src_entry.relative_path)?;
+            let src_bytes = read_trusted_file_bytes(&src_root, &src_path, "read_fallback_note")?;
+            let same_bytes = sha256_hex(&src_bytes) == dst_digest;
+            // Moved locally while the cloud copy stayed where it was: move it
+            // along, unless that path is taken.
+            let moved_to = change
+                .base_path
+                .as_deref()
+                .filter(|base_path| {
+                    *base_path == dst_entry.relative_path
+                        && src_entry.relative_path != dst_entry.relative_path
+                })
+                .map(|_| src_entry.relative_path.as_str());
+            if same_bytes && moved_to.is_none() {
+                reconciled.push(ReconciledNote {
+                    note_id: note_id.clone(),
+                    destination_path: Some(dst_entry.relative_path.clone()),
+                    outcome: ReconcileOutcome::Unchanged,
+                });
+                continue;
+            }
+
+            if cloud_unchanged || same_bytes {
+                if !same_bytes {
+                    let dst_bytes =
+                        read_trusted_file_bytes(&dst_root, &dst_path, "read_cloud_note")?;
+                    note_history::record_revision_unlocked(
+                        &dst_root,
+                        note_id,
+                        &dst_bytes,
+                        history_retention,
+                    )?;
+                }
+                let target_path = match moved_to {
+                    Some(moved_to) => {
+                        let moved_to = Path::new(moved_to);
+                        let target_dir = ensure_library_subdirectory(
+                            &dst_root,
+                            moved_to.parent().unwrap_or(Path::new("")),
+                        )?;
+                        let stem = moved_to
+                            .file_stem()
+                            .and_then(|value| value.to_str())
+                            .filter(|value| !value.is_empty())
+                            .unwrap_or("untitled");
+                        ensure_unique_file_path(&target_dir, stem, None)?
+                    }
+                    None => dst_path.clone(),
+                };
+                write_library_file_atomically(&dst_root, &target_path, &src_bytes)?;
+                remember_note_digest(&dst_root, note_id, sha256_hex(&src_bytes));
+                let target_relative = relative_path(dst_root.path(), &target_path);
+                if target_path != dst_path {
+                    if let Some(entry) = dst_index.entries.get_mut(note_id) {
+                        entry.relative_path = target_relative.clone();
+                    }
+                    moved_from.push(dst_path);
+                }
+                reconciled.push(ReconciledNote {
+                    note_id: note_id.clone(),
+                    destination_path: Some(target_relative),
+                    outcome: ReconcileOutcome::Updated,
+                });
+            } else {
+                let parent_dir = dst_path
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| dst_root.path().to_path_buf());
+                let stem = src_path
+                    .file_stem()
+                    .and_then(|value| value.to_str())
+                    .filter(|value| !value.is_empty())
+                    .unwrap_or("untitled");
+                let copy_path =
+                    ensure_unique_file_path(&parent_dir, &format!("{stem} (local)"), None)?;
+                copy_trusted_library_file(&src_root, &src_path, &dst_root, &copy_path)?;
+                let copy_relative = relative_path(dst_root.path(), &copy_path);
+                let copy_id = ensure_unique_note_id(&existing_ids, &copy_relative);
+                dst_index.entries.insert(
+                    copy_id.clone(),
+                    NoteIndexEntry {
+                        relative_path: copy_relative.clone(),
+                        created_at: src_entry.created_at,
+                        manual_title: src_entry.manual_title.clone(),
+                        is_pinned: src_entry.is_pinned,
+                        seen_by: BTreeMap::new(),
+                    },
+                );
+                existing_ids.insert(copy_id);
+                reconciled.push(ReconciledNote {
+                    note_id: note_id.clone(),
+                    destination_path: Some(copy_relative),
+                    outcome: ReconcileOutcome::Conflict,
+                });
+            }
+            written_markdown.push(String::from_utf8_lossy(&src_bytes).into_owned());
+        }
+
+        let written_markdown = written_markdown
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        copy_referenced_attachments(&src_root, &written_markdown, &dst_root)?;
+        if dst_index != dst_snapshot.index {
+            write_index_from_snapshot(&dst_root, &dst_snapshot, &dst_index)
+                .map_err(index_write_failure_to_string)?;
+        }
+        // The index points at the moved copies now; a file left behind only
+        // shows up as a separate note on the next load.
+        for path in moved_from {
+            if let Err(error) = remove_regular_file_if_exists(&path, "remove_moved_cloud_note") {
+                tracing::warn!("{error}");
+            }
+        }
+    }
+
+    for note_id in to_trash {
+        if trash_note(dst_dir, &note_id)? {
+            reconciled.push(ReconciledNote {
+                note_id,
+                destination_path: None,
+                outcome: ReconcileOutcome::Deleted,
+            });
+        }
+    }
+    Ok(reconciled)
+}
+
+/// A consistent copy of everything a backup bundle carries, read under the
+/// index lock. The index is reconciled with the scan so every file has an
+/// entry, but the reconciled index is not written back.
+pub(crate) struct LibraryBackupSource {
+    pub(crate) notes: Vec<(String, Vec<u8>)>,
+    /// Attachment file names and bytes from `.hwan-note-assets`.
+    pub(crate) attachments: Vec<(String, Vec<u8>)>,
+    pub(crate) folders: Vec<String>,
+    pub(crate) index_bytes: Vec<u8>,
+    pub(crate) calendar_bytes: Option<Vec<u8>>,
+}
+
+pub(crate) fn read_library_backup_source(
+    auto_save_dir: &Path,
+) -> Result<LibraryBackupSource, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let snapshot = require_index_snapshot(&trusted_root)?;
+    let scan = scan_library_tree(&ProductionFileSystem, &trusted_root, None, true);
+    if !scan.is_complete() {
+        return Err(scan
+            .issues
+            .iter()
+            .map(NoteLoadIssue::display)
+            .collect::<Vec<_>>()
+            .join("; "));
+    }
+    let (index, _) = reconcile_index_with_scan(&snapshot.index, &scan);
+    // Going through `Value` sorts the entries, so an unchanged library always
+    // produces the same index bytes.
+    let index_bytes = serde_json::to_value(&index)
+        .and_then(|value| serde_json::to_vec_pretty(&value))
+        .map_err(|error| {
+            format!(
+                "serialize_backup_index failed for {}: {error}",
+                get_index_path(trusted_root.path()).display()
+            )
+        })?;
+
+    let mut relative_paths: Vec<&String> = scan.files.keys().collect();
+    relative_paths.sort();
+    let mut notes = Vec::with_capacity(relative_paths.len());
+    for relative in relative_paths {
+        let path = validated_library_file_path(&trusted_root, relative)?;
+        notes.push((
+            relative.clone(),
+            read_trusted_file_bytes(&trusted_root, &path, "read_backup_note")?,
+        ));
+    }
+
+    let calendar_path = trusted_root.path().join(CALENDAR_FILENAME);
+    let calendar_bytes = match fs::symlink_metadata(&calendar_path) {
+        Ok(_) => Some(read_trusted_file_bytes(
+            &trusted_root,
+            &calendar_path,
+            "read_backup_calendar",
+        )?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => {
+            return Err(format!(
+                "inspect_backup_calendar failed for {}: {error}",
+                calendar_path.display()
+            ))
         }
     };
 
-    let scan = scan_library_tree(file_system, &trusted_root, None, true);
+    Ok(LibraryBackupSource {
+        notes,
+        attachments: attachments::read_all_attachments(&trusted_root)?,
+        folders: scan.folders.clone(),
+        index_bytes,
+        calendar_bytes,
+    })
+}
+
+/// True when the library has neither indexed notes nor Markdown files.
+pub(crate) fn library_is_empty(auto_save_dir: &Path) -> Result<bool, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let snapshot = require_index_snapshot(&trusted_root)?;
+    let scan = scan_library_tree(&ProductionFileSystem, &trusted_root, None, true);
     if !scan.is_complete() {
-        let issues = scan.issues.clone();
-        return incomplete_load_result(&trusted_root, &index_snapshot.index, scan, issues);
+        return Err(scan
+            .issues
+            .iter()
+            .map(NoteLoadIssue::display)
+            .collect::<Vec<_>>()
+            .join("; "));
     }
+    Ok(snapshot.index.entries.is_empty() && scan.files.is_empty())
+}
 
-    let (reconciled_index, index_changed) = reconcile_index_with_scan(&index_snapshot.index, &scan);
-    let notes = materialize_notes(&reconciled_index, &scan);
+/// Makes the library match `contents` exactly: listed notes are written,
+/// Markdown files it does not list are removed, and its index replaces the
+/// current one. Listed attachments are added back when missing; others are
+/// left for attachment cleanup. The calendar is only replaced when `contents`
+/// carries one. Returns the number of notes written and removed.
+pub(crate) fn replace_library_contents(
+    auto_save_dir: &Path,
+    contents: &LibraryBackupSource,
+) -> Result<(usize, usize), String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let next_index: NoteIndex = serde_json::from_slice(&contents.index_bytes).map_err(|error| {
+        format!(
+            "parse_restore_index failed for {}: {error}",
+            get_index_path(trusted_root.path()).display()
+        )
+    })?;
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let snapshot = require_index_snapshot(&trusted_root)?;
+    let scan = scan_library_tree(&ProductionFileSystem, &trusted_root, None, true);
+    if !scan.is_complete() {
+        return Err(scan
+            .issues
+            .iter()
+            .map(NoteLoadIssue::display)
+            .collect::<Vec<_>>()
+            .join("; "));
+    }
+
+    for folder in &contents.folders {
+        ensure_library_subdirectory(
+            &trusted_root,
+            &normalize_library_relative_path(trusted_root.path(), folder)
+                .map_err(|error| error.display("validate_restore_folder"))?,
+        )?;
+    }
+    attachments::restore_attachments(&trusted_root, &contents.attachments)?;
+    let mut written = 0;
+    let mut restored_paths = HashSet::new();
+    for (relative, bytes) in &contents.notes {
+        let is_note_path = relative.to_lowercase().ends_with(".md")
+            && relative.split('/').all(|segment| {
+                !segment.starts_with('.') && !is_reserved_library_directory(segment)
+            });
+        if !is_note_path {
+            return Err(format!(
+                "validate_restore_note failed for {relative}: not a library note path"
+            ));
+        }
+        let markdown = String::from_utf8(bytes.clone())
+            .map_err(|error| format!("restore_note failed for {relative}: {error}"))?;
+        if let Some(parent) = Path::new(relative)
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            ensure_library_subdirectory(&trusted_root, parent)?;
+        }
+        let path = validated_library_file_path(&trusted_root, relative)?;
+        restored_paths.insert(relative.as_str());
+        if scan
+            .files
+            .get(relative)
+            .is_some_and(|scanned| scanned.markdown.as_bytes() == bytes.as_slice())
+        {
+            continue;
+        }
+        write_note_file_atomically(&trusted_root, &path, &markdown)?;
+        written += 1;
+    }
+
+    let mut removed = 0;
+    for relative in scan.files.keys() {
+        if restored_paths.contains(relative.as_str()) {
+            continue;
+        }
+        let path = validated_library_file_path(&trusted_root, relative)?;
+        validate_existing_trusted_file(&trusted_root, &path, "remove_unrestored_note")?;
+        fs::remove_file(&path).map_err(|error| {
+            format!(
+                "remove_unrestored_note failed for {}: {error}",
+                path.display()
+            )
+        })?;
+        removed += 1;
+    }
+
+    write_index_from_snapshot(&trusted_root, &snapshot, &next_index)
+        .map_err(index_write_failure_to_string)?;
+    if let Some(calendar) = &contents.calendar_bytes {
+        let calendar = String::from_utf8(calendar.clone())
+            .map_err(|error| format!("restore_calendar failed for {CALENDAR_FILENAME}: {error}"))?;
+        write_note_file_atomically(
+            &trusted_root,
+            &trusted_root.path().join(CALENDAR_FILENAME),
+            &calendar,
+        )?;
+    }
+
+    for note_id in snapshot.index.entries.keys() {
+        forget_note_digest(&trusted_root, note_id);
+    }
+    let restored_bytes: HashMap<&str, &Vec<u8>> = contents
+        .notes
+        .iter()
+        .map(|(relative, bytes)| (relative.as_str(), bytes))
+        .collect();
+    for (note_id, entry) in &next_index.entries {
+        if let Some(bytes) = restored_bytes.get(entry.relative_path.as_str()) {
+            remember_note_digest(&trusted_root, note_id, sha256_hex(bytes));
+        }
+    }
+    Ok((written, removed))
+}
+
+pub fn migrate_calendar_file(src_dir: &Path, dst_dir: &Path) -> Result<bool, String> {
+    let src_root = resolve_trusted_library_root(src_dir)
+        .map_err(|error| error.display("validate_source_library_root"))?;
+    let dst_root = resolve_trusted_library_root(dst_dir)
+        .map_err(|error| error.display("validate_destination_library_root"))?;
+    let src_path = src_root.path().join(CALENDAR_FILENAME);
+    let src_metadata = match fs::symlink_metadata(&src_path) {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => {
+            return Err(format!(
+                "inspect_calendar_source failed for {}: {}",
+                src_path.display(),
+                error
+            ));
+        }
+    };
+    if metadata_is_symlink_or_reparse_point(&src_metadata) || !src_metadata.is_file() {
+        return Err(format!(
+            "validate_calendar_source failed for {}: expected a trusted regular file",
+            src_path.display()
+        ));
+    }
+    validate_no_symlink_beneath_root(&src_root, Path::new(CALENDAR_FILENAME))
+        .map_err(|error| error.display("validate_calendar_source"))?;
+    ensure_path_within_canonical_root(&src_root, &src_path)
+        .map_err(|error| error.display("validate_calendar_source"))?;
 
-    if index_changed {
-        match write_index_from_snapshot(&trusted_root, &index_snapshot, &reconciled_index) {
-            Ok(()) => {}
-            Err(IndexWriteFailure::Corrupt(state)) => {
-                return MarkdownLibraryLoadResult {
-                    notes: materialize_notes(&index_snapshot.index, &scan),
-                    folders: scan.folders,
-                    load_state: NoteLoadState::IndexCorrupt,
-                    issues: state.issues,
-                    index_source_path: Some(index_source_path.to_string_lossy().to_string()),
-                    index_backup_path: state
-                        .backup_path
-                        .map(|path| path.to_string_lossy().to_string()),
-                };
-            }
-            Err(IndexWriteFailure::Issue(issue)) => {
-                return incomplete_load_result(
-                    &trusted_root,
-                    &index_snapshot.index,
-                    scan,
-                    vec![issue],
-                );
+    let dst_path = dst_root.path().join(CALENDAR_FILENAME);
+    match fs::symlink_metadata(&dst_path) {
+        Ok(metadata) => {
+            if metadata_is_symlink_or_reparse_point(&metadata) {
+                return Err(format!(
+                    "validate_calendar_destination failed for {}: symbolic links and reparse points are not allowed",
+                    dst_path.display()
+                ));
             }
+            return Ok(false);
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => {
+            return Err(format!(
+                "inspect_calendar_destination failed for {}: {}",
+                dst_path.display(),
+                error
+            ));
         }
     }
+    validate_no_symlink_beneath_root(&dst_root, Path::new(CALENDAR_FILENAME))
+        .map_err(|error| error.display("validate_calendar_destination"))?;
+    ensure_path_within_canonical_root(&dst_root, &dst_path)
+        .map_err(|error| error.display("validate_calendar_destination"))?;
+    copy_trusted_library_file(&src_root, &src_path, &dst_root, &dst_path)?;
+    Ok(true)
+}
 
-    MarkdownLibraryLoadResult {
-        notes,
-        folders: scan.folders,
-        load_state: NoteLoadState::Ready,
-        issues: Vec::new(),
-        index_source_path: Some(index_source_path.to_string_lossy().to_string()),
-        index_backup_path: None,
+pub fn title_from_filename(file_path: &Path) -> String {
+    let stem = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let title: String = stem.trim().chars().take(50).collect();
+    if title.is_empty() {
+        "\u{c81c}\u{baa9} \u{c5c6}\u{c74c}".to_string() // 제목 없음
+    } else {
+        title
     }
 }
 
-pub fn load_markdown_library(auto_save_dir: &Path) -> MarkdownLibraryLoadResult {
-    load_markdown_library_with_fs(auto_save_dir, &ProductionFileSystem)
+// ── Encrypted notes ──
+
+struct NoteFileRewrite<'a> {
+    entry: &'a NoteIndexEntry,
+    current_path: &'a Path,
+    current_bytes: &'a [u8],
+    base_name: &'a str,
+    manual_title: Option<String>,
+    note_bytes: &'a [u8],
+    search_markdown: &'a str,
 }
 
-#[cfg(test)]
-pub fn load_markdown_notes(auto_save_dir: &Path) -> Result<Vec<LoadedNote>, String> {
-    let result = load_markdown_library(auto_save_dir);
-    if result.load_state == NoteLoadState::Ready {
-        Ok(result.notes)
-    } else {
-        let mut message = result
-            .issues
-            .iter()
-            .map(NoteLoadIssue::display)
-            .collect::<Vec<_>>()
-            .join("; ");
-        if let Some(path) = result.index_backup_path {
-            message.push_str(&format!("; corrupt index backup: {path}"));
-        }
-        Err(message)
+/// Replaces one note's file through the autosave journal without moving it to
+/// another folder, and records the new index entry and search document.
+/// Returns the new path, index entry and write time.
+fn rewrite_note_in_folder_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    index_snapshot: &IndexSnapshot,
+    note_id: &str,
+    rewrite: NoteFileRewrite<'_>,
+) -> Result<(PathBuf, NoteIndexEntry, u64), String> {
+    let NoteFileRewrite {
+        entry,
+        current_path,
+        current_bytes,
+        base_name,
+        manual_title,
+        note_bytes,
+        search_markdown,
+    } = rewrite;
+    let folder_path = Path::new(&entry.relative_path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let target_dir = current_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| trusted_root.path().to_path_buf());
+    let next_file_path = ensure_unique_file_path(&target_dir, base_name, Some(current_path))?;
+    let next_entry = NoteIndexEntry {
+        relative_path: relative_path(trusted_root.path(), &next_file_path),
+        created_at: entry.created_at,
+        manual_title,
+        is_pinned: entry.is_pinned,
+        seen_by: note_merge::record_seen_version(trusted_root, &entry.seen_by, note_bytes),
+    };
+    let mut next_index = index_snapshot.index.clone();
+    next_index
+        .entries
+        .insert(note_id.to_string(), next_entry.clone());
+
+    let commit = commit_journaled_note_write_unlocked(
+        trusted_root,
+        index_snapshot,
+        &next_index,
+        JournaledNoteWrite {
+            note_id,
+            folder_path: &folder_path,
+            existing_path: Some(current_path),
+            next_file_path: &next_file_path,
+            previous_relative_path: Some(entry.relative_path.clone()),
+            previous_note_digest: Some(sha256_hex(current_bytes)),
+            note_bytes,
+        },
+        &NoopAutosaveFaultInjector,
+    )?;
+    remember_note_digest(trusted_root, note_id, sha256_hex(note_bytes));
+    note_merge::prune_superseded_merge_base(trusted_root, &entry.seen_by, &next_index);
+    search_index::record_note_saved(
+        trusted_root,
+        index_snapshot_digest(index_snapshot).as_deref(),
+        Some(&sha256_hex(&commit.index_bytes)),
+        note_id,
+        SearchDocument::from_markdown(
+            &next_entry.relative_path,
+            next_entry.manual_title.as_deref(),
+            search_markdown,
+            commit.updated_at,
+        ),
+    );
+    Ok((next_file_path, next_entry, commit.updated_at))
+}
+
+fn read_note_for_encryption_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    index: &NoteIndex,
+    note_id: &str,
+) -> Result<(NoteIndexEntry, PathBuf, Vec<u8>, String), String> {
+    let entry = index
+        .entries
+        .get(note_id)
+        .cloned()
+        .ok_or_else(|| "Note not found.".to_string())?;
+    let path = validated_library_file_path(trusted_root, &entry.relative_path)?;
+    let bytes = read_trusted_file_bytes(trusted_root, &path, "read_note")?;
+    let text = String::from_utf8(bytes.clone())
+        .map_err(|error| format!("read_note failed for {}: {error}", path.display()))?;
+    Ok((entry, path, bytes, text))
+}
+
+/// Encrypts a note with `passphrase` and leaves it unlocked for this session.
+/// The manual title moves from the index into the encrypted front matter, the
+/// file gets a neutral name, and plaintext history revisions are removed.
+pub fn encrypt_note(
+    auto_save_dir: &Path,
+    note_id: &str,
+    passphrase: &str,
+) -> Result<LoadedNote, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let safe_id = sanitize_note_id(note_id);
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let index_snapshot = require_index_snapshot(&trusted_root)?;
+    let (entry, path, bytes, text) =
+        read_note_for_encryption_unlocked(&trusted_root, &index_snapshot.index, &safe_id)?;
+    if note_crypto::is_armored(&text) {
+        return Err(format!(
+            "encrypt_note failed for {}: the note is already encrypted",
+            path.display()
+        ));
     }
+
+    if attachments::referenced_attachments(&text).next().is_some() {
+        return Err(format!(
+            "encrypt_note failed for {}: attachments are stored unencrypted, so remove them from the note first",
+            path.display()
+        ));
+    }
+
+    let key = note_crypto::NoteKey::generate(passphrase)?;
+    let metadata = extract_note_metadata(&text).0;
+    let manual_title = entry
+        .manual_title
+        .as_deref()
+        .and_then(normalize_manual_title)
+        .or(metadata.title);
+    let stored_markdown = to_platform_line_endings(&embed_note_metadata(
+        &text,
+        None,
+        manual_title.as_deref(),
+        entry.is_pinned.or(metadata.pinned) == Some(true),
+        entry.created_at,
+    ));
+    let armored = key.encrypt(&stored_markdown)?;
+    let (next_path, next_entry, updated_at) = rewrite_note_in_folder_unlocked(
+        &trusted_root,
+        &index_snapshot,
+        &safe_id,
+        NoteFileRewrite {
+            entry: &entry,
+            current_path: &path,
+            current_bytes: &bytes,
+            base_name: ENCRYPTED_NOTE_FILE_STEM,
+            manual_title: None,
+            note_bytes: armored.as_bytes(),
+            search_markdown: "",
+        },
+    )?;
+    note_history::remove_note_history_unlocked(&trusted_root, &safe_id)?;
+    note_merge::prune_merge_bases(&trusted_root, &require_index_snapshot(&trusted_root)?.index)?;
+    note_links::record_notes_saved(
+        &trusted_root,
+        vec![(
+            safe_id.clone(),
+            LinkNode::from_markdown(&next_entry.relative_path, "", &[], ""),
+        )],
+    );
+    note_crypto::remember_session_key(trusted_root.path(), &safe_id, key);
+
+    let mut note = loaded_note(
+        &safe_id,
+        &next_entry,
+        &stored_markdown,
+        &next_path,
+        updated_at,
+    );
+    note.is_encrypted = true;
+    Ok(note)
 }
 
-#[cfg_attr(not(test), allow(dead_code))]
-pub fn resolve_note_file_path(
+/// Removes encryption from a note for good, after checking `passphrase`.
+pub fn decrypt_note(
     auto_save_dir: &Path,
     note_id: &str,
-) -> Result<Option<PathBuf>, String> {
+    passphrase: &str,
+) -> Result<LoadedNote, String> {
     let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let safe_id = sanitize_note_id(note_id);
     let _index_guard = lock_note_index();
     recover_pending_note_save_unlocked(&trusted_root)?;
-    resolve_note_file_path_unlocked(&trusted_root, note_id)
+    let index_snapshot = require_index_snapshot(&trusted_root)?;
+    let (entry, path, bytes, text) =
+        read_note_for_encryption_unlocked(&trusted_root, &index_snapshot.index, &safe_id)?;
+    if !note_crypto::is_armored(&text) {
+        return Err(format!(
+            "decrypt_note failed for {}: the note is not encrypted",
+            path.display()
+        ));
+    }
+
+    let (_, plaintext) = note_crypto::NoteKey::unlock(passphrase, &text)
+        .map_err(|error| format!("decrypt_note failed for {}: {error}", path.display()))?;
+    let (metadata, markdown) = extract_note_metadata(&plaintext);
+    let manual_title = metadata.title.as_deref().and_then(normalize_manual_title);
+    let base_name = slugify_title(
+        &manual_title
+            .clone()
+            .unwrap_or_else(|| derive_title(&markdown)),
+    );
+    let (next_path, next_entry, updated_at) = rewrite_note_in_folder_unlocked(
+        &trusted_root,
+        &index_snapshot,
+        &safe_id,
+        NoteFileRewrite {
+            entry: &entry,
+            current_path: &path,
+            current_bytes: &bytes,
+            base_name: &base_name,
+            manual_title,
+            note_bytes: plaintext.as_bytes(),
+            search_markdown: &markdown,
+        },
+    )?;
+    note_crypto::forget_session_key(trusted_root.path(), &safe_id);
+
+    let note = loaded_note(&safe_id, &next_entry, &plaintext, &next_path, updated_at);
+    note_links::record_notes_saved(
+        &trusted_root,
+        vec![(
+            safe_id.clone(),
+            LinkNode::from_markdown(
+                &next_entry.relative_path,
+                &note.title,
+                &note.aliases,
+                &note.markdown,
+            ),
+        )],
+    );
+    Ok(note)
 }
 
-fn resolve_note_file_path_unlocked(
-    trusted_root: &TrustedLibraryRoot,
+/// Returns the decrypted contents of an encrypted note and keeps its key for
+/// the rest of the session so autosave can re-encrypt. Without a passphrase
+/// the key from an earlier unlock is used.
+pub fn unlock_note(
+    auto_save_dir: &Path,
     note_id: &str,
-) -> Result<Option<PathBuf>, String> {
+    passphrase: Option<&str>,
+) -> Result<LoadedNote, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
     let safe_id = sanitize_note_id(note_id);
-    if safe_id.is_empty() {
-        return Ok(None);
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let index = require_index_snapshot(&trusted_root)?.index;
+    let (entry, path, _, text) =
+        read_note_for_encryption_unlocked(&trusted_root, &index, &safe_id)?;
+    if !note_crypto::is_armored(&text) {
+        return Err(format!(
+            "unlock_note failed for {}: the note is not encrypted",
+            path.display()
+        ));
     }
 
-    let index = require_index_snapshot(trusted_root)?.index;
-    let entry = match index.entries.get(&safe_id) {
-        Some(e) => e,
-        None => return Ok(None),
+    let plaintext = match passphrase {
+        Some(passphrase) => {
+            let (key, plaintext) = note_crypto::NoteKey::unlock(passphrase, &text)
+                .map_err(|error| format!("unlock_note failed for {}: {error}", path.display()))?;
+            note_crypto::remember_session_key(trusted_root.path(), &safe_id, key);
+            plaintext
+        }
+        None => note_crypto::session_key(trusted_root.path(), &safe_id)
+            .ok_or_else(|| {
+                format!(
+                    "unlock_note failed for {}: the note is locked",
+                    path.display()
+                )
+            })?
+            .decrypt(&text)
+            .map_err(|error| format!("unlock_note failed for {}: {error}", path.display()))?,
     };
+    let updated_at = fs::symlink_metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .map(system_time_to_millis)
+        .unwrap_or_else(|_| now_millis());
 
-    Ok(Some(validated_library_file_path(
-        trusted_root,
-        &entry.relative_path,
-    )?))
+    let mut note = loaded_note(&safe_id, &entry, &plaintext, &path, updated_at);
+    note.is_encrypted = true;
+    Ok(note)
 }
 
-fn trash_note_file_or_accept_missing<F>(file_path: &Path, delete_file: F) -> Result<(), String>
-where
-    F: FnOnce(&Path) -> Result<(), String>,
-{
-    match delete_file(file_path) {
-        Ok(()) => Ok(()),
-        Err(delete_error) => match fs::symlink_metadata(file_path) {
-            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
-            Ok(_) => Err(delete_error),
-            Err(error) => Err(format!(
-                "Failed to recheck note file after trash error: {error}"
-            )),
-        },
+/// Forgets the session key of an unlocked note. Returns whether it was
+/// unlocked; later autosaves of the note fail until it is unlocked again.
+pub fn lock_note(auto_save_dir: &Path, note_id: &str) -> Result<bool, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    Ok(note_crypto::forget_session_key(
+        trusted_root.path(),
+        &sanitize_note_id(note_id),
+    ))
+}
+
+// ── Trash ──
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashRestoreResult {
+    pub restored_notes: Vec<LoadedNote>,
+    pub moved: Vec<MovedNote>,
+    pub folders: Vec<String>,
+    pub relinked_notes: Vec<RelinkedNote>,
+}
+
+/// Title shown for a note in the trash; encrypted notes stay untitled.
+fn trashed_note_title(
+    trusted_root: &TrustedLibraryRoot,
+    entry: &NoteIndexEntry,
+    path: &Path,
+) -> String {
+    let Ok(bytes) = read_trusted_file_bytes(trusted_root, path, "read_trashed_note") else {
+        return String::new();
+    };
+    if note_crypto::is_armored_bytes(&bytes) {
+        return String::new();
     }
+    let (metadata, markdown) = extract_note_metadata(&String::from_utf8_lossy(&bytes));
+    entry
+        .manual_title
+        .as_deref()
+        .and_then(normalize_manual_title)
+        .or(metadata.title)
+        .unwrap_or_else(|| derive_title(&markdown))
 }
 
-#[cfg_attr(not(test), allow(dead_code))]
-pub fn remove_note_from_index_if_path(
-    auto_save_dir: &Path,
-    note_id: &str,
-    expected_file_path: &Path,
-) -> Result<Option<PathBuf>, String> {
-    let safe_id = sanitize_note_id(note_id);
-    if safe_id.is_empty() {
-        return Ok(None);
+/// Moves a just-trashed note file back to `original_path` and drops its trash
+/// item. Used to roll back a delete whose index write did not happen.
+fn untrash_note_file_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    trash_id: &str,
+    original_path: &Path,
+) {
+    let result = note_trash::read_trash_item(trusted_root, trash_id).and_then(|item| {
+        let trashed_path = item
+            .note_path()
+            .ok_or_else(|| format!("trash item {trash_id} has no note file"))?;
+        fs::rename(&trashed_path, original_path).map_err(|error| error.to_string())?;
+        note_trash::remove_trash_item_unlocked(trusted_root, trash_id)
+    });
+    if let Err(error) = result {
+        tracing::warn!(
+            "Failed to move {} back out of the trash: {error}",
+            original_path.display()
+        );
     }
+}
 
+/// Moves a note into the library trash, keeping its index entry so it can be
+/// restored with its title, pin, and creation time. Its history is kept until
+/// the trash item is purged.
+pub fn trash_note(auto_save_dir: &Path, note_id: &str) -> Result<bool, String> {
     let trusted_root = resolve_trusted_library_root(auto_save_dir)
         .map_err(|error| error.display("validate_library_root"))?;
     let _index_guard = lock_note_index();
     recover_pending_note_save_unlocked(&trusted_root)?;
-    let expected_file_path =
-        canonicalize_expected_library_path(&trusted_root, auto_save_dir, expected_file_path)?;
-    remove_note_from_index_if_path_unlocked(&trusted_root, note_id, &expected_file_path)
+    let safe_id = sanitize_note_id(note_id);
+    let index = require_index_snapshot(&trusted_root)?.index;
+    let Some(entry) = index.entries.get(&safe_id).cloned() else {
+        return Ok(false);
+    };
+    let file_path = validated_library_file_path(&trusted_root, &entry.relative_path)?;
+
+    let trash_id = match fs::symlink_metadata(&file_path) {
+        Ok(_) => {
+            validate_existing_trusted_file(&trusted_root, &file_path, "validate_note_trash")?;
+            let title = trashed_note_title(&trusted_root, &entry, &file_path);
+            Some(note_trash::trash_note_unlocked(
+                &trusted_root,
+                &safe_id,
+                &entry,
+                &file_path,
+                &title,
+            )?)
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => return Err(format!("Failed to check note file before delete: {error}")),
+    };
+
+    match remove_note_from_index_if_path_unlocked(&trusted_root, &safe_id, &file_path) {
+        Ok(removed) => {
+            forget_note_digest(&trusted_root, &safe_id);
+            Ok(removed.is_some())
+        }
+        Err(error) => {
+            if let Some(trash_id) = trash_id {
+                untrash_note_file_unlocked(&trusted_root, &trash_id, &file_path);
+            }
+            Err(error)
+        }
+    }
 }
 
-fn remove_note_from_index_if_path_unlocked(
+// ── Sync conflicts ──
+
+/// Checks that `conflict_path` is an unindexed conflict copy of `note_id` and
+/// returns its library-relative path and the note's index entry.
+fn validate_sync_conflict(
     trusted_root: &TrustedLibraryRoot,
+    index: &NoteIndex,
     note_id: &str,
-    expected_file_path: &Path,
-) -> Result<Option<PathBuf>, String> {
+    conflict_path: &Path,
+) -> Result<(String, NoteIndexEntry), String> {
+    let relative_path = conflict_path
+        .strip_prefix(trusted_root.path())
+        .map(|relative| to_posix(&relative.to_string_lossy()))
+        .map_err(|_| {
+            format!(
+                "resolve_sync_conflict failed for {}: path is outside the note library",
+                conflict_path.display()
+            )
+        })?;
+    let entry = index
+        .entries
+        .get(note_id)
+        .cloned()
+        .ok_or_else(|| format!("Note not found: {note_id}"))?;
+    let is_copy_of_note = conflict_copy_originals(&relative_path).contains(&entry.relative_path);
+    let is_indexed = index
+        .entries
+        .values()
+        .any(|indexed| indexed.relative_path == relative_path);
+    if !is_copy_of_note || is_indexed {
+        return Err(format!(
+            "resolve_sync_conflict failed for {}: not a conflict copy of {}",
+            conflict_path.display(),
+            entry.relative_path
+        ));
+    }
+    Ok((relative_path, entry))
+}
+
+/// Moves a resolved conflict copy into the trash, where it can be restored as
+/// a note of its own.
+fn trash_sync_conflict_copy(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+    conflict_path: &Path,
+) -> Result<(), String> {
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(trusted_root)?;
+    let index = require_index_snapshot(trusted_root)?.index;
+    let (relative_path, _) = validate_sync_conflict(trusted_root, &index, note_id, conflict_path)?;
+    let file_path = validated_library_file_path(trusted_root, &relative_path)?;
+    validate_existing_trusted_file(trusted_root, &file_path, "validate_conflict_trash")?;
+
+    let existing_ids = index.entries.keys().cloned().collect::<HashSet<_>>();
+    let copy_id = ensure_unique_note_id(&existing_ids, &relative_path);
+    let copy_entry = NoteIndexEntry {
+        relative_path,
+        created_at: now_millis(),
+        manual_title: None,
+        is_pinned: None,
+        seen_by: BTreeMap::new(),
+    };
+    let title = trashed_note_title(trusted_root, &copy_entry, &file_path);
+    note_trash::trash_note_unlocked(trusted_root, &copy_id, &copy_entry, &file_path, &title)?;
+    Ok(())
+}
+
+/// Resolves a sync-client conflict copy reported by `load_markdown_library`.
+/// Replacing the note's body goes through a regular autosave, so the version
+/// it replaces stays in the note's history.
+pub fn resolve_sync_conflict(
+    auto_save_dir: &Path,
+    note_id: &str,
+    conflict_path: &Path,
+    resolution: &SyncConflictResolution,
+    history_retention: &NoteHistoryRetention,
+) -> Result<(), String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let conflict_path =
+        canonicalize_expected_library_path(&trusted_root, auto_save_dir, conflict_path)?;
     let safe_id = sanitize_note_id(note_id);
-    if safe_id.is_empty() {
-        return Ok(None);
-    }
 
-    let index_snapshot = require_index_snapshot(trusted_root)?;
-    let mut index = index_snapshot.index.clone();
-    let entry = match index.entries.get(&safe_id) {
-        Some(e) => e.clone(),
-        None => return Ok(None),
+    let replacement = match resolution {
+        SyncConflictResolution::KeepOriginal => None,
+        SyncConflictResolution::KeepConflict => {
+            let bytes =
+                read_trusted_file_bytes(&trusted_root, &conflict_path, "read_conflict_copy")?;
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        SyncConflictResolution::Merged { markdown } => Some(markdown.clone()),
+        SyncConflictResolution::KeepBoth => {
+            let _index_guard = lock_note_index();
+            recover_pending_note_save_unlocked(&trusted_root)?;
+            let snapshot = require_index_snapshot(&trusted_root)?;
+            let (relative_path, _) =
+                validate_sync_conflict(&trusted_root, &snapshot.index, &safe_id, &conflict_path)?;
+            let bytes =
+                read_trusted_file_bytes(&trusted_root, &conflict_path, "read_conflict_copy")?;
+            let metadata = extract_note_metadata(&String::from_utf8_lossy(&bytes)).0;
+            let mut index = snapshot.index.clone();
+            let existing_ids = index.entries.keys().cloned().collect::<HashSet<_>>();
+            index.entries.insert(
+                ensure_unique_note_id(&existing_ids, &relative_path),
+                NoteIndexEntry {
+                    relative_path,
+                    created_at: metadata.created.unwrap_or_else(now_millis),
+                    manual_title: metadata.title,
+                    is_pinned: metadata.pinned,
+                    seen_by: BTreeMap::new(),
+                },
+            );
+            return write_index_from_snapshot(&trusted_root, &snapshot, &index)
+                .map_err(index_write_failure_to_string);
+        }
     };
 
-    let file_path = validated_library_file_path(trusted_root, &entry.relative_path)?;
-    if file_path != expected_file_path {
-        return Err("Note index changed before delete completed".to_string());
-    }
-    match fs::symlink_metadata(expected_file_path) {
-        Ok(_) => return Err("Note file changed before delete completed".to_string()),
-        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
-        Err(error) => {
-            return Err(format!(
-                "Failed to check note file before delete cleanup: {error}"
-            ));
-        }
+    if let Some(markdown) = replacement {
+        let entry = {
+            let _index_guard = lock_note_index();
+            let index = require_index_snapshot(&trusted_root)?.index;
+            validate_sync_conflict(&trusted_root, &index, &safe_id, &conflict_path)?.1
+        };
+        let payload = AutoSavePayload {
+            note_id: safe_id.clone(),
+            title: entry.manual_title.clone().unwrap_or_default(),
+            content: markdown,
+            folder_path: Some(folder_path_for_relative_path(&entry.relative_path)),
+            is_title_manual: Some(entry.manual_title.is_some()),
+            is_pinned: entry.is_pinned,
+        };
+        auto_save_markdown_note_with_history(auto_save_dir, &payload, history_retention)?;
     }
-
-    index.entries.remove(&safe_id);
-    write_index_from_snapshot(trusted_root, &index_snapshot, &index)
-        .map_err(index_write_failure_to_string)?;
-    Ok(Some(file_path))
+    trash_sync_conflict_copy(&trusted_root, &safe_id, &conflict_path)
 }
 
-pub fn delete_note_file_and_index<F>(
+/// Puts a trashed note or folder back where it was deleted from. A note whose
+/// file name is taken gets a numbered name; a folder whose path is taken is
+/// restored beside it under a numbered name. Notes a folder delete moved to
+/// the library root go back into the folder unless they were moved since.
+pub fn restore_from_trash(
     auto_save_dir: &Path,
-    note_id: &str,
-    delete_file: F,
-) -> Result<bool, String>
-where
-    F: FnOnce(&Path) -> Result<(), String>,
-{
-    let trusted_root = resolve_trusted_library_root(auto_save_dir)
-        .map_err(|error| error.display("validate_library_root"))?;
-    let _index_guard = lock_note_index();
-    recover_pending_note_save_unlocked(&trusted_root)?;
-    let file_path = match resolve_note_file_path_unlocked(&trusted_root, note_id)? {
-        Some(p) => p,
-        None => return Ok(false),
+    trash_id: &str,
+    history_retention: &NoteHistoryRetention,
+) -> Result<TrashRestoreResult, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let kind = {
+        let _index_guard = lock_note_index();
+        note_trash::read_trash_item(&trusted_root, trash_id)?
+            .manifest
+            .kind
     };
-
-    match fs::symlink_metadata(&file_path) {
-        Ok(_) => {
-            validate_existing_trusted_file(&trusted_root, &file_path, "validate_note_delete")?;
-            validate_existing_trusted_file(&trusted_root, &file_path, "validate_note_delete")?;
-            trash_note_file_or_accept_missing(&file_path, delete_file)?;
+    match kind {
+        note_trash::TrashItemKind::Note => restore_trashed_note(&trusted_root, trash_id),
+        note_trash::TrashItemKind::Folder => {
+            restore_trashed_folder(&trusted_root, trash_id, history_retention)
         }
-        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
-        Err(error) => return Err(format!("Failed to check note file before delete: {error}")),
     }
-
-    let removed = remove_note_from_index_if_path_unlocked(&trusted_root, note_id, &file_path)?;
-    Ok(removed.is_some())
 }
 
-pub fn normalize_external_txt_path(
-    raw_path: &str,
-    base_dir: Option<&Path>,
-) -> Result<PathBuf, String> {
-    let trimmed = raw_path.trim().trim_matches('"');
-    if trimmed.is_empty() {
-        return Err("File path is required.".to_string());
+fn restore_trashed_note(
+    trusted_root: &TrustedLibraryRoot,
+    trash_id: &str,
+) -> Result<TrashRestoreResult, String> {
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(trusted_root)?;
+    let item = note_trash::read_trash_item(trusted_root, trash_id)?;
+    let (Some(note_id), Some(entry), Some(trashed_path)) = (
+        item.manifest.note_id.clone(),
+        item.manifest.entry.clone(),
+        item.note_path(),
+    ) else {
+        return Err(format!(
+            "restore_from_trash failed for {}: the trash item is incomplete",
+            item.item_dir.display()
+        ));
+    };
+    if sanitize_note_id(&note_id) != note_id {
+        return Err(format!("Invalid note id: {note_id}"));
     }
+    let index_snapshot = require_index_snapshot(trusted_root)?;
+    if index_snapshot.index.entries.contains_key(&note_id) {
+        return Err(format!(
+            "restore_from_trash failed for {}: note {note_id} is already in the library",
+            trashed_path.display()
+        ));
+    }
+    validate_existing_trusted_file(trusted_root, &trashed_path, "validate_trashed_note")?;
 
-    let candidate = PathBuf::from(trimmed);
-    let resolved = if candidate.is_absolute() {
-        candidate
-    } else if let Some(base) = base_dir {
-        base.join(candidate)
+    let folder = sanitize_folder_path(Some(&item.manifest.folder_path))?;
+    let folder_relative = if folder.is_empty() {
+        PathBuf::new()
     } else {
-        std::env::current_dir()
-            .map_err(|e| e.to_string())?
-            .join(candidate)
+        normalize_library_relative_path(trusted_root.path(), &folder)
+            .map_err(|error| error.display("validate_restore_folder"))?
     };
+    let target_dir = ensure_library_subdirectory(trusted_root, &folder_relative)?;
+    let base_name = trashed_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| slugify_title(""));
+    let next_path = ensure_unique_file_path(&target_dir, &base_name, None)?;
+    validate_note_destination_before_replace(trusted_root, &next_path)?;
+    fs::rename(&trashed_path, &next_path).map_err(|error| {
+        format!(
+            "restore_from_trash failed for {}: {error}",
+            trashed_path.display()
+        )
+    })?;
 
-    let normalized = fs::canonicalize(&resolved).map_err(|_| "Text file not found.".to_string())?;
-    let metadata = fs::metadata(&normalized).map_err(|e| e.to_string())?;
-
-    if !metadata.is_file() {
-        return Err("Only files can be opened.".to_string());
+    let next_entry = NoteIndexEntry {
+        relative_path: relative_path(trusted_root.path(), &next_path),
+        ..entry
+    };
+    let mut index = index_snapshot.index.clone();
+    index.entries.insert(note_id.clone(), next_entry.clone());
+    if let Err(error) = write_index_from_snapshot(trusted_root, &index_snapshot, &index)
+        .map_err(index_write_failure_to_string)
+    {
+        if let Err(rollback_error) = fs::rename(&next_path, &trashed_path) {
+            tracing::warn!(
+                "Failed to move {} back into the trash: {rollback_error}",
+                next_path.display()
+            );
+        }
+        return Err(error);
+    }
+
+    let bytes = read_trusted_file_bytes(trusted_root, &next_path, "read_restored_note")?;
+    remember_note_digest(trusted_root, &note_id, sha256_hex(&bytes));
+    let stored_markdown = String::from_utf8_lossy(&bytes).to_string();
+    let updated_at = fs::symlink_metadata(&next_path)
+        .and_then(|metadata| metadata.modified())
+        .map(system_time_to_millis)
+        .unwrap_or_else(|_| now_millis());
+    let encrypted = note_crypto::is_armored(&stored_markdown);
+    let note = if encrypted {
+        locked_note(&note_id, &next_entry, &next_path, updated_at)
+    } else {
+        loaded_note(
+            &note_id,
+            &next_entry,
+            &stored_markdown,
+            &next_path,
+            updated_at,
+        )
+    };
+    search_index::record_note_saved(
+        trusted_root,
+        index_snapshot_digest(&index_snapshot).as_deref(),
+        current_index_digest(trusted_root)?.as_deref(),
+        &note_id,
+        if encrypted {
+            SearchDocument::from_markdown(&next_entry.relative_path, None, "", updated_at)
+        } else {
+            SearchDocument::from_loaded_note(&note, &next_entry.relative_path)
+        },
+    );
+    note_links::record_notes_saved(
+        trusted_root,
+        vec![(
+            note_id,
+            LinkNode::from_markdown(
+                &next_entry.relative_path,
+                &note.title,
+                &note.aliases,
+                &note.markdown,
+            ),
+        )],
+    );
+    if let Err(error) = note_trash::remove_trash_item_unlocked(trusted_root, trash_id) {
+        tracing::warn!("remove_trash_item failed for {trash_id}: {error}");
     }
 
-    let ext = normalized
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
-    if !ext.eq_ignore_ascii_case("txt") {
-        return Err("Only .txt files are supported.".to_string());
-    }
+    Ok(TrashRestoreResult {
+        restored_notes: vec![note],
+        moved: Vec::new(),
+        folders: list_folders_with_root(trusted_root, &ProductionFileSystem)?,
+        relinked_notes: Vec::new(),
+    })
+}
 
-    Ok(normalized)
+fn unique_folder_path(trusted_root: &TrustedLibraryRoot, folder: &str) -> Result<String, String> {
+    let mut counter = 1;
+    loop {
+        let candidate = if counter == 1 {
+            folder.to_string()
+        } else {
+            format!("{folder}-{counter}")
+        };
+        let candidate_path = trusted_root.path().join(&candidate);
+        match fs::symlink_metadata(&candidate_path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(candidate),
+            Err(error) => {
+                return Err(format!(
+                    "inspect_restore_folder failed for {}: {error}",
+                    candidate_path.display()
+                ));
+            }
+            Ok(_) => counter += 1,
+        }
+    }
 }
 
-pub fn read_text_file(file_path: &Path) -> Result<String, String> {
-    fs::read_to_string(file_path).map_err(|e| e.to_string())
+fn restore_trashed_folder(
+    trusted_root: &TrustedLibraryRoot,
+    trash_id: &str,
+    history_retention: &NoteHistoryRetention,
+) -> Result<TrashRestoreResult, String> {
+    let moves = {
+        let _index_guard = lock_note_index();
+        recover_pending_note_save_unlocked(trusted_root)?;
+        let item = note_trash::read_trash_item(trusted_root, trash_id)?;
+        let original = sanitize_folder_path(Some(&item.manifest.folder_path))?;
+        if original.is_empty() {
+            return Err(format!(
+                "restore_from_trash failed for {}: the trash item has no folder path",
+                item.item_dir.display()
+            ));
+        }
+        let restored = unique_folder_path(trusted_root, &original)?;
+        let restored_relative = normalize_library_relative_path(trusted_root.path(), &restored)
+            .map_err(|error| error.display("validate_restore_folder"))?;
+        match item.folder_content_path() {
+            Some(content_dir) => {
+                if let Some(parent) = restored_relative.parent() {
+                    ensure_library_subdirectory(trusted_root, parent)?;
+                }
+                let destination = trusted_root.path().join(&restored_relative);
+                fs::rename(&content_dir, &destination).map_err(|error| {
+                    format!(
+                        "restore_from_trash failed for {}: {error}",
+                        content_dir.display()
+                    )
+                })?;
+                ensure_directory_tree_trusted(trusted_root, &restored_relative)?;
+            }
+            None => {
+                ensure_library_subdirectory(trusted_root, &restored_relative)?;
+            }
+        }
+
+        let index = require_index_snapshot(trusted_root)?.index;
+        let mut moves: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for moved in &item.manifest.moved_notes {
+            let still_at_root = index
+                .entries
+                .get(&moved.note_id)
+                .is_some_and(|entry| entry.relative_path == moved.moved_relative_path);
+            if !still_at_root {
+                continue;
+            }
+            let original_folder = folder_path_for_relative_path(&moved.original_relative_path);
+            let subfolder = original_folder.strip_prefix(&original).unwrap_or_default();
+            moves
+                .entry(format!("{restored}{subfolder}"))
+                .or_default()
+                .push(moved.note_id.clone());
+        }
+        if let Err(error) = note_trash::remove_trash_item_unlocked(trusted_root, trash_id) {
+            tracing::warn!("remove_trash_item failed for {trash_id}: {error}");
+        }
+        moves
+    };
+
+    let mut moved = Vec::new();
+    let mut relinked_notes = Vec::new();
+    for (folder, note_ids) in moves {
+        let result = move_notes_with_faults(
+            trusted_root,
+            &note_ids,
+            Some(&folder),
+            &NoopAutosaveFaultInjector,
+            history_retention,
+        )?;
+        moved.extend(result.moved);
+        relinked_notes.extend(result.relinked_notes);
+    }
+
+    Ok(TrashRestoreResult {
+        restored_notes: Vec::new(),
+        moved,
+        folders: list_folders_with_root(trusted_root, &ProductionFileSystem)?,
+        relinked_notes,
+    })
 }
 
-pub fn save_text_file(file_path: &Path, content: &str) -> Result<(), String> {
-    if let Some(parent) = file_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+fn purge_trash_items_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    trash_ids: &[String],
+) -> Result<Vec<String>, String> {
+    let index = require_index_snapshot(trusted_root)?.index;
+    let mut purged = Vec::new();
+    for trash_id in trash_ids {
+        let item = note_trash::read_trash_item(trusted_root, trash_id)?;
+        if let Some(note_id) = item
+            .manifest
+            .note_id
+            .as_deref()
+            .filter(|note_id| !index.entries.contains_key(*note_id))
+        {
+            note_history::remove_note_history_unlocked(trusted_root, note_id)?;
+        }
+        note_trash::remove_trash_item_unlocked(trusted_root, trash_id)?;
+        purged.push(trash_id.clone());
     }
-    fs::write(file_path, to_platform_line_endings(content)).map_err(|e| e.to_string())
+    Ok(purged)
 }
 
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct MigrationResult {
-    pub files_copied: u32,
-    pub index_copied: bool,
+/// Deletes trash items for good, along with the history of trashed notes.
+pub fn purge_trash(auto_save_dir: &Path, trash_ids: &[String]) -> Result<Vec<String>, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    purge_trash_items_unlocked(&trusted_root, trash_ids)
 }
 
-fn copy_trusted_library_file(
-    src_root: &TrustedLibraryRoot,
-    src_path: &Path,
-    dst_root: &TrustedLibraryRoot,
-    dst_path: &Path,
-) -> Result<(), String> {
-    validate_existing_trusted_file(src_root, src_path, "validate_migration_source")?;
-    let mut src_file = fs::File::open(src_path).map_err(|error| {
-        format!(
-            "open_migration_source failed for {}: {}",
-            src_path.display(),
-            error
-        )
-    })?;
-    validate_existing_trusted_file(src_root, src_path, "validate_migration_source")?;
-    validate_note_destination_before_replace(dst_root, dst_path)?;
-    let mut dst_file = OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(dst_path)
-        .map_err(|error| {
-            format!(
-                "create_migration_destination failed for {}: {}",
-                dst_path.display(),
-                error
-            )
-        })?;
-
-    if let Err(error) = io::copy(&mut src_file, &mut dst_file).and_then(|_| dst_file.sync_all()) {
-        drop(dst_file);
-        return Err(cleanup_file_with_reason(
-            dst_path,
-            format!(
-                "copy_migration_file failed from {} to {}: {}",
-                src_path.display(),
-                dst_path.display(),
-                error
-            ),
-        ));
-    }
-    Ok(())
+/// Purges the items that have been in the trash longer than `settings` allow.
+pub fn purge_expired_trash(
+    auto_save_dir: &Path,
+    settings: &note_trash::TrashSettings,
+) -> Result<Vec<String>, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _index_guard = lock_note_index();
+    recover_pending_note_save_unlocked(&trusted_root)?;
+    let items = note_trash::list_trash_items(&trusted_root)?;
+    let expired = note_trash::expired_trash_ids(&items, settings, now_millis());
+    purge_trash_items_unlocked(&trusted_root, &expired)
 }
 
-/// Merge all .md files from src_dir into dst_dir without overwriting
-/// existing destination notes or replacing the destination index.
-/// Preserves relative directory structure and creates empty folders.
-pub fn migrate_notes(src_dir: &Path, dst_dir: &Path) -> Result<MigrationResult, String> {
-    let src_root = resolve_trusted_library_root(src_dir)
-        .map_err(|error| error.display("validate_source_library_root"))?;
-    let dst_root = resolve_trusted_library_root(dst_dir)
-        .map_err(|error| error.display("validate_destination_library_root"))?;
+// ── Integrity check ──
 
-    if src_root.path() == dst_root.path() {
-        return Ok(MigrationResult {
-            files_copied: 0,
-            index_copied: false,
-        });
-    }
+/// Temp files younger than this may belong to a write still in progress
+/// (calendar and search-index writes do not take the index lock), so they are
+/// not treated as orphans.
+const ORPHAN_TEMP_MIN_AGE_MILLIS: u64 = 10 * 60 * 1000;
 
-    // When the current local library root is the parent of the cloud library root
-    // (for example `.../HwanNote` -> `.../HwanNote/Notes`), skip the destination
-    // subtree while collecting source files so we do not recursively copy the
-    // cloud library back into itself.
-    let skip_src_subtree = dst_root
-        .path()
-        .starts_with(src_root.path())
-        .then_some(dst_root.path());
+static INDEX_ENTRY_FRAGMENT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""([A-Za-z0-9_-]+)"\s*:\s*(\{[^{}]*\})"#).unwrap());
 
-    let _index_guard = lock_note_index();
-    recover_pending_note_save_unlocked(&src_root)?;
-    if src_root.path() != dst_root.path() {
-        recover_pending_note_save_unlocked(&dst_root)?;
-    }
-    let src_snapshot = require_index_snapshot(&src_root)?;
-    let dst_snapshot = require_index_snapshot(&dst_root)?;
-    let src_scan = scan_library_tree(&ProductionFileSystem, &src_root, skip_src_subtree, true);
-    let dst_scan = scan_library_tree(&ProductionFileSystem, &dst_root, None, true);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibraryIssueKind {
+    SymbolicLink,
+    UnreadableEntry,
+    PendingAutosaveJournal,
+    StaleJournalTemp,
+    OrphanTempFile,
+    CorruptIndex,
+    MissingNoteFile,
+    DuplicatePath,
+    InvalidNoteId,
+    UnindexedNoteFile,
+    CorruptIndexBackup,
+    CorruptCalendarCopy,
+}
 
-    if !src_scan.is_complete() || !dst_scan.is_complete() {
-        return Err(src_scan
-            .issues
-            .iter()
-            .chain(dst_scan.issues.iter())
-            .map(NoteLoadIssue::display)
-            .collect::<Vec<_>>()
-            .join("; "));
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryIssue {
+    pub kind: LibraryIssueKind,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_id: Option<String>,
+    pub detail: String,
+}
+
+impl LibraryIssue {
+    fn new(kind: LibraryIssueKind, path: &str, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            path: path.to_string(),
+            note_id: None,
+            detail: detail.into(),
+        }
     }
 
-    let (src_index, src_index_changed) = reconcile_index_with_scan(&src_snapshot.index, &src_scan);
-    let (mut dst_index, mut dst_index_changed) =
-        reconcile_index_with_scan(&dst_snapshot.index, &dst_scan);
+    fn for_note(
+        kind: LibraryIssueKind,
+        path: &str,
+        note_id: &str,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            note_id: Some(note_id.to_string()),
+            ..Self::new(kind, path, detail)
+        }
+    }
 
-    for folder in &src_scan.folders {
-        let relative_folder = normalize_library_relative_path(dst_root.path(), folder)
-            .map_err(|error| error.display("validate_migration_folder"))?;
-        ensure_library_subdirectory(&dst_root, &relative_folder)?;
+    fn same_problem(&self, other: &LibraryIssue) -> bool {
+        self.kind == other.kind && self.path == other.path && self.note_id == other.note_id
     }
+}
 
-    let mut existing_dst_paths: HashSet<String> = dst_scan.files.keys().cloned().collect();
-    let mut existing_dst_ids: HashSet<String> = dst_index.entries.keys().cloned().collect();
-    let mut files_copied: u32 = 0;
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryCheckReport {
+    pub issues: Vec<LibraryIssue>,
+}
 
-    let mut source_entries: Vec<_> = src_index.entries.iter().collect();
-    source_entries.sort_by(|(left_id, _), (right_id, _)| left_id.cmp(right_id));
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRepairResult {
+    pub repaired: Vec<LibraryIssue>,
+    pub remaining: Vec<LibraryIssue>,
+}
 
-    for (src_note_id, src_entry) in source_entries {
-        let Some(src_file) = src_scan.files.get(&src_entry.relative_path) else {
-            continue;
-        };
+/// App-managed files found by walking the whole library, including reserved
+/// directories the note scan skips.
+#[derive(Default)]
+struct LibraryArtifacts {
+    symlinks: Vec<String>,
+    unreadable: Vec<(String, String)>,
+    journal: bool,
+    journal_temp: bool,
+    temp_files: Vec<(String, u64)>,
+    index_backups: Vec<String>,
+    calendar_copies: Vec<String>,
+}
 
-        if let Some(existing_entry) = dst_index.entries.get(src_note_id) {
-            if dst_scan.files.contains_key(&existing_entry.relative_path) {
+struct SalvagedIndexEntry {
+    note_id: String,
+    manual_title: Option<String>,
+    is_pinned: Option<bool>,
+}
+
+fn is_library_temp_file_name(name: &str) -> bool {
+    (name.starts_with(".hwan-note-") && (name.ends_with(".tmp") || name.contains(".tmp-")))
+        || name.starts_with(".calendar.json.tmp")
+}
+
+fn is_calendar_copy_file_name(name: &str) -> bool {
+    let backup = format!("{CALENDAR_FILENAME}.bak");
+    let recovery = format!("{CALENDAR_FILENAME}.local-recovery.bak");
+    [backup, recovery].iter().any(|base| {
+        name == base
+            || name
+                .strip_prefix(base.as_str())
+                .and_then(|rest| rest.strip_prefix('.'))
+                .is_some_and(|sequence| sequence.chars().all(|ch| ch.is_ascii_digit()))
+    })
+}
+
+/// Walks the library without following links, sorting app-managed files into
+/// `artifacts`. Notes themselves come from `scan_library_tree`.
+fn collect_library_artifacts(
+    trusted_root: &TrustedLibraryRoot,
+    directory: &Path,
+    artifacts: &mut LibraryArtifacts,
+) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) => {
+            artifacts.unreadable.push((
+                relative_path(trusted_root.path(), directory),
+                error.to_string(),
+            ));
+            return;
+        }
+    };
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(error) => {
+                artifacts.unreadable.push((
+                    relative_path(trusted_root.path(), directory),
+                    error.to_string(),
+                ));
+                continue;
+            }
+        };
+        let relative = relative_path(trusted_root.path(), &path);
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                artifacts.unreadable.push((relative, error.to_string()));
                 continue;
             }
+        };
+        if metadata_is_symlink_or_reparse_point(&metadata) {
+            artifacts.symlinks.push(relative);
+            continue;
+        }
+        if metadata.is_dir() {
+            collect_library_artifacts(trusted_root, &path, artifacts);
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let at_root = path.parent() == Some(trusted_root.path());
+        if at_root && name == AUTOSAVE_JOURNAL_FILENAME {
+            artifacts.journal = true;
+        } else if at_root && name == AUTOSAVE_JOURNAL_TEMP_FILENAME {
+            artifacts.journal_temp = true;
+        } else if at_root
+            && name.starts_with(&format!("{INDEX_FILENAME}.corrupt-"))
+            && name.ends_with(".bak")
+        {
+            artifacts.index_backups.push(relative);
+        } else if at_root && is_calendar_copy_file_name(name) {
+            artifacts.calendar_copies.push(relative);
+        } else if is_library_temp_file_name(name) {
+            let modified = metadata
+                .modified()
+                .map(system_time_to_millis)
+                .unwrap_or_default();
+            artifacts.temp_files.push((relative, modified));
         }
+    }
+}
 
-        let desired_relative =
-            normalize_library_relative_path(dst_root.path(), &src_entry.relative_path)
-                .map_err(|error| error.display("validate_migration_note_path"))?;
-        validate_no_symlink_beneath_root(&dst_root, &desired_relative)
-            .map_err(|error| error.display("validate_migration_note_path"))?;
-        let desired_path = dst_root.path().join(&desired_relative);
-        let parent_dir = desired_path
-            .parent()
-            .map(Path::to_path_buf)
-            .unwrap_or_else(|| dst_root.path().to_path_buf());
-        let base_name = src_file
-            .full_path
-            .file_stem()
-            .and_then(|value| value.to_str())
-            .filter(|value| !value.is_empty())
-            .unwrap_or("untitled");
+/// Reads the index for inspection only. Unlike `read_index_state`, a corrupt
+/// index is reported without writing a backup of it.
+fn inspect_index_without_backup(trusted_root: &TrustedLibraryRoot) -> Result<NoteIndex, String> {
+    let index_path = get_index_path(trusted_root.path());
+    match fs::symlink_metadata(&index_path) {
+        Ok(metadata) if metadata_is_symlink_or_reparse_point(&metadata) => {
+            return Err("the index file is a symbolic link or reparse point".to_string());
+        }
+        Ok(_) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(empty_index()),
+        Err(error) => return Err(error.to_string()),
+    }
+    let bytes = fs::read(&index_path).map_err(|error| error.to_string())?;
+    let index = serde_json::from_slice::<NoteIndex>(&bytes).map_err(|error| error.to_string())?;
+    validate_index_paths(trusted_root, &index_path, &index).map_err(|issue| issue.reason)?;
+    Ok(index)
+}
 
-        let desired_exists = match fs::symlink_metadata(&desired_path) {
-            Ok(_) => true,
-            Err(error) if error.kind() == io::ErrorKind::NotFound => false,
-            Err(error) => return Err(error.to_string()),
+/// Recovers whatever entries survive in corrupt index backups, keyed by
+/// relative path. Entries are matched one object at a time so a truncated
+/// backup still yields the entries before the damage; newer backups win.
+fn salvage_index_backups(
+    trusted_root: &TrustedLibraryRoot,
+    backups: &[String],
+) -> HashMap<String, SalvagedIndexEntry> {
+    let mut backups = backups.to_vec();
+    backups.sort_by(|left, right| right.cmp(left));
+    let mut salvaged = HashMap::new();
+    for backup in backups {
+        let Ok(path) = trusted_root.file_path(&backup, true) else {
+            continue;
         };
-        let final_path = if existing_dst_paths.contains(&src_entry.relative_path) || desired_exists
-        {
-            ensure_unique_file_path(&parent_dir, base_name, None)?
-        } else {
-            desired_path
+        let Ok(bytes) = read_trusted_file_bytes(trusted_root, &path, "read_index_backup") else {
+            continue;
         };
-
-        if let Some(parent) = final_path.parent() {
-            let parent_relative = parent.strip_prefix(dst_root.path()).map_err(|error| {
-                format!(
-                    "validate_migration_parent failed for {}: {}",
-                    parent.display(),
-                    error
-                )
-            })?;
-            ensure_library_subdirectory(&dst_root, parent_relative)?;
+        let text = String::from_utf8_lossy(&bytes);
+        for captures in INDEX_ENTRY_FRAGMENT_RE.captures_iter(&text) {
+            let Ok(fields) = serde_json::from_str::<serde_json::Value>(&captures[2]) else {
+                continue;
+            };
+            let Some(relative_path) = fields.get("relativePath").and_then(|value| value.as_str())
+            else {
+                continue;
+            };
+            salvaged
+                .entry(relative_path.to_string())
+                .or_insert_with(|| SalvagedIndexEntry {
+                    note_id: captures[1].to_string(),
+                    manual_title: fields
+                        .get("manualTitle")
+                        .and_then(|value| value.as_str())
+                        .map(str::to_string),
+                    is_pinned: fields.get("isPinned").and_then(|value| value.as_bool()),
+                });
         }
+    }
+    salvaged
+}
 
-        copy_trusted_library_file(&src_root, &src_file.full_path, &dst_root, &final_path)?;
-        files_copied += 1;
+/// Among entries sharing one file, keeps the one whose id was derived from
+/// that path, then the oldest, then the lowest id.
+fn duplicate_path_keeper<'a>(
+    relative_path: &str,
+    entries: &[(&'a String, &NoteIndexEntry)],
+) -> &'a String {
+    let generated_id = generate_note_id(relative_path);
+    entries
+        .iter()
+        .min_by_key(|(note_id, entry)| (**note_id != generated_id, entry.created_at, *note_id))
+        .map(|(note_id, _)| *note_id)
+        .expect("duplicate groups have at least two entries")
+}
 
-        let final_rel = relative_path(dst_root.path(), &final_path);
-        let final_note_id = if existing_dst_ids.contains(src_note_id) {
-            ensure_unique_note_id(&existing_dst_ids, &final_rel)
-        } else {
-            src_note_id.clone()
-        };
+fn duplicate_path_groups(index: &NoteIndex) -> Vec<(String, Vec<(&String, &NoteIndexEntry)>)> {
+    let mut by_path: HashMap<&str, Vec<(&String, &NoteIndexEntry)>> = HashMap::new();
+    for (note_id, entry) in &index.entries {
+        by_path
+            .entry(entry.relative_path.as_str())
+            .or_default()
+            .push((note_id, entry));
+    }
+    let mut groups = by_path
+        .into_iter()
+        .filter(|(_, entries)| entries.len() > 1)
+        .map(|(relative_path, entries)| (relative_path.to_string(), entries))
+        .collect::<Vec<_>>();
+    groups.sort_by(|left, right| left.0.cmp(&right.0));
+    groups
+}
 
-        dst_index.entries.insert(
-            final_note_id.clone(),
-            NoteIndexEntry {
-                relative_path: final_rel.clone(),
-                created_at: src_entry.created_at,
-                manual_title: src_entry.manual_title.clone(),
-                is_pinned: src_entry.is_pinned,
-            },
-        );
-        existing_dst_paths.insert(final_rel);
-        existing_dst_ids.insert(final_note_id);
-        dst_index_changed = true;
+fn note_file_is_missing(
+    trusted_root: &TrustedLibraryRoot,
+    scan: &LibraryScan,
+    relative: &str,
+) -> bool {
+    !scan.files.contains_key(relative)
+        && matches!(
+            fs::symlink_metadata(trusted_root.path().join(relative)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound
+        )
+}
+
+fn is_invalid_note_id(note_id: &str) -> bool {
+    note_id.is_empty() || sanitize_note_id(note_id) != note_id
+}
+
+/// Audits the library without changing it. Must be called while holding the
+/// note-index lock.
+fn check_library_unlocked(trusted_root: &TrustedLibraryRoot) -> Vec<LibraryIssue> {
+    use LibraryIssueKind as Kind;
+
+    let mut artifacts = LibraryArtifacts::default();
+    collect_library_artifacts(trusted_root, trusted_root.path(), &mut artifacts);
+    let mut issues = Vec::new();
+
+    for relative in &artifacts.symlinks {
+        issues.push(LibraryIssue::new(
+            Kind::SymbolicLink,
+            relative,
+            "symbolic links and reparse points beneath the note library are not allowed",
+        ));
+    }
+    for (relative, reason) in &artifacts.unreadable {
+        issues.push(LibraryIssue::new(
+            Kind::UnreadableEntry,
+            relative,
+            reason.as_str(),
+        ));
+    }
+    if artifacts.journal {
+        issues.push(LibraryIssue::new(
+            Kind::PendingAutosaveJournal,
+            AUTOSAVE_JOURNAL_FILENAME,
+            "an interrupted autosave has not been recovered",
+        ));
+    }
+    if artifacts.journal_temp {
+        issues.push(LibraryIssue::new(
+            Kind::StaleJournalTemp,
+            AUTOSAVE_JOURNAL_TEMP_FILENAME,
+            "an autosave journal was never published",
+        ));
+    }
+    let now = now_millis();
+    for (relative, modified) in &artifacts.temp_files {
+        if now.saturating_sub(*modified) >= ORPHAN_TEMP_MIN_AGE_MILLIS {
+            issues.push(LibraryIssue::new(
+                Kind::OrphanTempFile,
+                relative,
+                "temporary file left by an interrupted write",
+            ));
+        }
+    }
+    for relative in &artifacts.index_backups {
+        let recoverable = salvage_index_backups(trusted_root, std::slice::from_ref(relative)).len();
+        issues.push(LibraryIssue::new(
+            Kind::CorruptIndexBackup,
+            relative,
+            format!("backup of a corrupt index; {recoverable} entries recoverable"),
+        ));
+    }
+    for relative in &artifacts.calendar_copies {
+        let parsed = trusted_root
+            .file_path(relative, true)
+            .and_then(|path| read_trusted_file_bytes(trusted_root, &path, "read_calendar_copy"))
+            .and_then(|bytes| {
+                serde_json::from_slice::<serde_json::Value>(&bytes)
+                    .map_err(|error| error.to_string())
+            });
+        if let Err(reason) = parsed {
+            issues.push(LibraryIssue::new(
+                Kind::CorruptCalendarCopy,
+                relative,
+                reason,
+            ));
+        }
     }
 
-    if dst_index_changed {
-        write_index_from_snapshot(&dst_root, &dst_snapshot, &dst_index)
-            .map_err(index_write_failure_to_string)?;
+    let scan = scan_library_tree(&ProductionFileSystem, trusted_root, None, true);
+    for issue in &scan.issues {
+        if issue.operation != "reject_symlink" {
+            issues.push(LibraryIssue::new(
+                Kind::UnreadableEntry,
+                &relative_path(trusted_root.path(), Path::new(&issue.path)),
+                issue.reason.as_str(),
+            ));
+        }
     }
-    if src_index_changed {
-        write_index_from_snapshot(&src_root, &src_snapshot, &src_index)
-            .map_err(index_write_failure_to_string)?;
+
+    match inspect_index_without_backup(trusted_root) {
+        Err(reason) => issues.push(LibraryIssue::new(
+            Kind::CorruptIndex,
+            INDEX_FILENAME,
+            reason,
+        )),
+        Ok(index) => {
+            let mut entries = index.entries.iter().collect::<Vec<_>>();
+            entries.sort_by_key(|(note_id, _)| *note_id);
+            for (note_id, entry) in &entries {
+                if note_file_is_missing(trusted_root, &scan, &entry.relative_path) {
+                    issues.push(LibraryIssue::for_note(
+                        Kind::MissingNoteFile,
+                        &entry.relative_path,
+                        note_id,
+                        "index entry points at a file that does not exist",
+                    ));
+                }
+                if is_invalid_note_id(note_id) {
+                    issues.push(LibraryIssue::for_note(
+                        Kind::InvalidNoteId,
+                        &entry.relative_path,
+                        note_id,
+                        "note id contains characters the app cannot address",
+                    ));
+                }
+            }
+            for (relative, group) in duplicate_path_groups(&index) {
+                let keeper = duplicate_path_keeper(&relative, &group);
+                for (note_id, _) in group.iter().filter(|(note_id, _)| *note_id != keeper) {
+                    issues.push(LibraryIssue::for_note(
+                        Kind::DuplicatePath,
+                        &relative,
+                        note_id,
+                        format!("shares its file with {keeper}"),
+                    ));
+                }
+            }
+            let indexed_paths = index
+                .entries
+                .values()
+                .map(|entry| entry.relative_path.as_str())
+                .collect::<HashSet<_>>();
+            let mut unindexed = scan
+                .files
+                .keys()
+                .filter(|relative| !indexed_paths.contains(relative.as_str()))
+                .collect::<Vec<_>>();
+            unindexed.sort();
+            for relative in unindexed {
+                issues.push(LibraryIssue::new(
+                    Kind::UnindexedNoteFile,
+                    relative,
+                    "Markdown file has no index entry",
+                ));
+            }
+        }
     }
 
-    Ok(MigrationResult {
-        files_copied,
-        index_copied: dst_index_changed,
+    issues.sort_by(|left, right| {
+        (left.kind, &left.path, &left.note_id).cmp(&(right.kind, &right.path, &right.note_id))
+    });
+    issues
+}
+
+pub fn check_library(auto_save_dir: &Path) -> Result<LibraryCheckReport, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _index_guard = lock_note_index();
+    Ok(LibraryCheckReport {
+        issues: check_library_unlocked(&trusted_root),
     })
 }
 
-pub fn migrate_calendar_file(src_dir: &Path, dst_dir: &Path) -> Result<bool, String> {
-    let src_root = resolve_trusted_library_root(src_dir)
-        .map_err(|error| error.display("validate_source_library_root"))?;
-    let dst_root = resolve_trusted_library_root(dst_dir)
-        .map_err(|error| error.display("validate_destination_library_root"))?;
-    let src_path = src_root.path().join(CALENDAR_FILENAME);
-    let src_metadata = match fs::symlink_metadata(&src_path) {
-        Ok(metadata) => metadata,
-        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
-        Err(error) => {
-            return Err(format!(
-                "inspect_calendar_source failed for {}: {}",
-                src_path.display(),
-                error
-            ));
+/// Applies the index-level repairs selected in `kinds` and writes the index
+/// once. A corrupt index is set aside as a backup and rebuilt from the files.
+/// Must be called while holding the note-index lock.
+fn repair_library_index_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    kinds: &[LibraryIssueKind],
+    index_backups: &mut Vec<String>,
+) -> Result<(), String> {
+    use LibraryIssueKind as Kind;
+
+    let index_path = get_index_path(trusted_root.path());
+    let mut rebuild = false;
+    if inspect_index_without_backup(trusted_root).is_err() {
+        if !kinds.contains(&Kind::CorruptIndex) {
+            return Ok(());
         }
-    };
-    if metadata_is_symlink_or_reparse_point(&src_metadata) || !src_metadata.is_file() {
-        return Err(format!(
-            "validate_calendar_source failed for {}: expected a trusted regular file",
-            src_path.display()
-        ));
+        let bytes = read_trusted_file_bytes(trusted_root, &index_path, "read_corrupt_index")?;
+        let backup_path =
+            backup_corrupt_index(&index_path, &bytes).map_err(|issue| issue.display())?;
+        index_backups.push(relative_path(trusted_root.path(), &backup_path));
+        fs::remove_file(&index_path).map_err(|error| {
+            format!(
+                "remove_corrupt_index failed for {}: {error}",
+                index_path.display()
+            )
+        })?;
+        rebuild = true;
+    }
+
+    let snapshot = require_index_snapshot(trusted_root)?;
+    let scan = scan_library_tree(&ProductionFileSystem, trusted_root, None, true);
+    let salvaged = salvage_index_backups(trusted_root, index_backups);
+    let mut index = snapshot.index.clone();
+    let mut dropped_ids = Vec::new();
+
+    if kinds.contains(&Kind::MissingNoteFile) {
+        let missing = index
+            .entries
+            .iter()
+            .filter(|(_, entry)| note_file_is_missing(trusted_root, &scan, &entry.relative_path))
+            .map(|(note_id, _)| note_id.clone())
+            .collect::<Vec<_>>();
+        for note_id in missing {
+            index.entries.remove(&note_id);
+            dropped_ids.push(note_id);
+        }
+    }
+    if kinds.contains(&Kind::DuplicatePath) {
+        let duplicates = duplicate_path_groups(&index)
+            .into_iter()
+            .flat_map(|(relative, group)| {
+                let keeper = duplicate_path_keeper(&relative, &group).clone();
+                group
+                    .into_iter()
+                    .map(|(note_id, _)| note_id.clone())
+                    .filter(move |note_id| *note_id != keeper)
+            })
+            .collect::<Vec<_>>();
+        for note_id in duplicates {
+            index.entries.remove(&note_id);
+            dropped_ids.push(note_id);
+        }
+    }
+    if kinds.contains(&Kind::InvalidNoteId) {
+        let mut invalid = index
+            .entries
+            .keys()
+            .filter(|note_id| is_invalid_note_id(note_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        invalid.sort();
+        for note_id in invalid {
+            let entry = index
+                .entries
+                .remove(&note_id)
+                .expect("id came from the index");
+            let existing = index.entries.keys().cloned().collect::<HashSet<_>>();
+            let cleaned = sanitize_note_id(&note_id);
+            let next_id = if cleaned.is_empty() || existing.contains(&cleaned) {
+                ensure_unique_note_id(&existing, &entry.relative_path)
+            } else {
+                cleaned
+            };
+            index.entries.insert(next_id, entry);
+            dropped_ids.push(note_id);
+        }
+    }
+    if rebuild || kinds.contains(&Kind::UnindexedNoteFile) {
+        let indexed_paths = index
+            .entries
+            .values()
+            .map(|entry| entry.relative_path.clone())
+            .collect::<HashSet<_>>();
+        let mut unindexed = scan
+            .files
+            .keys()
+            .filter(|relative| !indexed_paths.contains(*relative))
+            .cloned()
+            .collect::<Vec<_>>();
+        unindexed.sort();
+        for relative in unindexed {
+            let scanned = &scan.files[&relative];
+            let metadata = extract_note_metadata(&scanned.markdown).0;
+            let existing = index.entries.keys().cloned().collect::<HashSet<_>>();
+            let recovered = salvaged.get(&relative);
+            let note_id = recovered
+                .map(|entry| entry.note_id.clone())
+                .filter(|note_id| !is_invalid_note_id(note_id) && !existing.contains(note_id))
+                .unwrap_or_else(|| ensure_unique_note_id(&existing, &relative));
+            index.entries.insert(
+                note_id,
+                NoteIndexEntry {
+                    relative_path: relative.clone(),
+                    created_at: metadata.created.unwrap_or(scanned.created_at),
+                    manual_title: recovered
+                        .and_then(|entry| entry.manual_title.clone())
+                        .or(metadata.title),
+                    is_pinned: recovered
+                        .and_then(|entry| entry.is_pinned)
+                        .or(metadata.pinned),
+                    seen_by: BTreeMap::new(),
+                },
+            );
+        }
+    }
+    if kinds.contains(&Kind::CorruptIndexBackup) {
+        for entry in index.entries.values_mut() {
+            let Some(recovered) = salvaged.get(&entry.relative_path) else {
+                continue;
+            };
+            if entry.manual_title.is_none() {
+                entry.manual_title = recovered.manual_title.clone();
+            }
+            if entry.is_pinned.is_none() {
+                entry.is_pinned = recovered.is_pinned;
+            }
+        }
+    }
+
+    if index != snapshot.index {
+        write_index_from_snapshot(trusted_root, &snapshot, &index)
+            .map_err(index_write_failure_to_string)?;
     }
-    validate_no_symlink_beneath_root(&src_root, Path::new(CALENDAR_FILENAME))
-        .map_err(|error| error.display("validate_calendar_source"))?;
-    ensure_path_within_canonical_root(&src_root, &src_path)
-        .map_err(|error| error.display("validate_calendar_source"))?;
+    for note_id in &dropped_ids {
+        forget_note_digest(trusted_root, note_id);
+    }
+    Ok(())
+}
 
-    let dst_path = dst_root.path().join(CALENDAR_FILENAME);
-    match fs::symlink_metadata(&dst_path) {
-        Ok(metadata) => {
-            if metadata_is_symlink_or_reparse_point(&metadata) {
-                return Err(format!(
-                    "validate_calendar_destination failed for {}: symbolic links and reparse points are not allowed",
-                    dst_path.display()
-                ));
+/// Repairs the issue classes named in `kinds`, then checks the library again.
+/// Links, index backups, and calendar copies go through `delete_file` so they
+/// can be recovered from the trash; app temp files are removed outright. Temp
+/// files are left alone while an autosave journal is still pending.
+pub fn repair_library<F>(
+    auto_save_dir: &Path,
+    kinds: &[LibraryIssueKind],
+    mut delete_file: F,
+) -> Result<LibraryRepairResult, String>
+where
+    F: FnMut(&Path) -> Result<(), String>,
+{
+    use LibraryIssueKind as Kind;
+
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _index_guard = lock_note_index();
+    let before = check_library_unlocked(&trusted_root);
+    let selected = |kind: Kind| kinds.contains(&kind);
+    let paths_of = |kind: Kind| {
+        before
+            .iter()
+            .filter(move |issue| issue.kind == kind)
+            .map(|issue| issue.path.clone())
+    };
+
+    if selected(Kind::SymbolicLink) {
+        for relative in paths_of(Kind::SymbolicLink) {
+            let path = trusted_root.path().join(&relative);
+            let still_link = fs::symlink_metadata(&path)
+                .is_ok_and(|metadata| metadata_is_symlink_or_reparse_point(&metadata));
+            if still_link {
+                delete_file(&path)?;
             }
-            return Ok(false);
         }
-        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
-        Err(error) => {
-            return Err(format!(
-                "inspect_calendar_destination failed for {}: {}",
-                dst_path.display(),
-                error
-            ));
+    }
+    if selected(Kind::PendingAutosaveJournal) {
+        recover_pending_note_save_unlocked(&trusted_root)?;
+    }
+    let journal_pending =
+        fs::symlink_metadata(trusted_root.path().join(AUTOSAVE_JOURNAL_FILENAME)).is_ok();
+    if !journal_pending {
+        let mut stale = Vec::new();
+        if selected(Kind::StaleJournalTemp) {
+            stale.extend(paths_of(Kind::StaleJournalTemp));
+        }
+        if selected(Kind::OrphanTempFile) {
+            stale.extend(paths_of(Kind::OrphanTempFile));
+        }
+        for relative in stale {
+            let path = trusted_root.path().join(&relative);
+            match fs::symlink_metadata(&path) {
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                _ => validate_existing_trusted_file(&trusted_root, &path, "remove_orphan_temp")?,
+            }
+            fs::remove_file(&path).map_err(|error| {
+                format!("remove_orphan_temp failed for {}: {error}", path.display())
+            })?;
         }
     }
-    validate_no_symlink_beneath_root(&dst_root, Path::new(CALENDAR_FILENAME))
-        .map_err(|error| error.display("validate_calendar_destination"))?;
-    ensure_path_within_canonical_root(&dst_root, &dst_path)
-        .map_err(|error| error.display("validate_calendar_destination"))?;
-    copy_trusted_library_file(&src_root, &src_path, &dst_root, &dst_path)?;
-    Ok(true)
-}
 
-pub fn title_from_filename(file_path: &Path) -> String {
-    let stem = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
-    let title: String = stem.trim().chars().take(50).collect();
-    if title.is_empty() {
-        "\u{c81c}\u{baa9} \u{c5c6}\u{c74c}".to_string() // 제목 없음
-    } else {
-        title
+    let mut index_backups = paths_of(Kind::CorruptIndexBackup).collect::<Vec<_>>();
+    let index_kinds = [
+        Kind::CorruptIndex,
+        Kind::MissingNoteFile,
+        Kind::DuplicatePath,
+        Kind::InvalidNoteId,
+        Kind::UnindexedNoteFile,
+        Kind::CorruptIndexBackup,
+    ];
+    if index_kinds.iter().any(|kind| selected(*kind)) {
+        repair_library_index_unlocked(&trusted_root, kinds, &mut index_backups)?;
+    }
+    if selected(Kind::CorruptIndexBackup) {
+        for relative in &index_backups {
+            let path = trusted_root.file_path(relative, true)?;
+            delete_file(&path)?;
+        }
+    }
+    if selected(Kind::CorruptCalendarCopy) {
+        for relative in paths_of(Kind::CorruptCalendarCopy) {
+            let path = trusted_root.file_path(&relative, true)?;
+            delete_file(&path)?;
+        }
     }
+
+    let remaining = check_library_unlocked(&trusted_root);
+    let repaired = before
+        .into_iter()
+        .filter(|issue| selected(issue.kind))
+        .filter(|issue| !remaining.iter().any(|left| left.same_problem(issue)))
+        .collect();
+    Ok(LibraryRepairResult {
+        repaired,
+        remaining,
+    })
 }
 
 #[cfg(test)]
@@ -4111,6 +8422,7 @@ mod tests {
     use std::process;
     #[cfg(windows)]
     use std::process::Command;
+    use std::time::Duration;
 
     fn make_temp_dir(name: &str) -> PathBuf {
         let dir = std::env::temp_dir().join(format!(
@@ -4379,7 +8691,12 @@ mod tests {
     ) -> Result<AutoSaveResult, String> {
         let trusted_root = TrustedLibraryRoot::open(root)?;
         let injector = FailOnceAutosaveFaultInjector::fail_on(point, nth_hit);
-        auto_save_markdown_note_with_faults(&trusted_root, payload, &injector)
+        auto_save_markdown_note_with_faults(
+            &trusted_root,
+            payload,
+            &NoteHistoryRetention::default(),
+            &injector,
+        )
     }
 
     #[cfg(unix)]
@@ -4536,33 +8853,388 @@ mod tests {
     }
 
     #[test]
-    fn autosave_note_temp_write_failure_reconciles_cleanly_on_restart() {
-        let dir = make_temp_dir("autosave-note-temp-write-failure");
+    fn autosave_note_temp_write_failure_reconciles_cleanly_on_restart() {
+        let dir = make_temp_dir("autosave-note-temp-write-failure");
+        let result = (|| -> Result<(), String> {
+            let trusted_root = TrustedLibraryRoot::open(&dir)?;
+            let injector =
+                FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::NoteTempWrite, 1);
+
+            let error = auto_save_markdown_note_with_faults(
+                &trusted_root,
+                &autosave_payload("temp-fail", "Temp fail", "# Temp fail", None),
+                &NoteHistoryRetention::default(),
+                &injector,
+            )
+            .unwrap_err();
+            assert!(error.contains("NoteTempWrite") || error.contains("write_note_temp"));
+
+            let load = load_markdown_library(&dir);
+            assert_eq!(load.load_state, NoteLoadState::Ready);
+            assert!(load.notes.is_empty());
+            assert_eq!(list_markdown_files(&dir, &dir)?.len(), 0);
+            assert!(!autosave_journal_path(&trusted_root).exists());
+            assert!(!autosave_journal_next_path(&trusted_root).exists());
+
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("temp-fail", "Recovered", "# Recovered", None),
+            )?;
+            assert_eq!(count_markdown_files_recursively(&dir)?, 1);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn autosave_note_publish_failure_recovers_folder_move_without_duplicates() {
+        let dir = make_temp_dir("autosave-note-publish-recovery");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("stable-note", "Alpha", "# Alpha", Some("alpha")),
+            )?;
+            let before = load_markdown_notes(&dir)?
+                .into_iter()
+                .find(|note| note.note_id == "stable-note")
+                .ok_or_else(|| "baseline note missing".to_string())?;
+            let trusted_root = TrustedLibraryRoot::open(&dir)?;
+            let injector =
+                FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::NotePublish, 1);
+
+            let error = auto_save_markdown_note_with_faults(
+                &trusted_root,
+                &autosave_payload("stable-note", "Beta", "# Beta", Some("beta")),
+                &NoteHistoryRetention::default(),
+                &injector,
+            )
+            .unwrap_err();
+            assert!(error.contains("NotePublish") || error.contains("publish_note"));
+            assert!(
+                autosave_journal_path(&trusted_root).exists()
+                    || autosave_journal_next_path(&trusted_root).exists()
+            );
+
+            let load = load_markdown_library(&dir);
+            assert_eq!(load.load_state, NoteLoadState::Ready);
+            let note = load
+                .notes
+                .iter()
+                .find(|note| note.note_id == "stable-note")
+                .ok_or_else(|| "recovered note missing".to_string())?;
+            assert_eq!(note.folder_path, "beta");
+            assert_eq!(note.markdown, "# Beta");
+            assert_eq!(note.created_at, before.created_at);
+            assert_eq!(count_markdown_files_recursively(&dir)?, 1);
+            assert_eq!(count_autosave_artifacts_recursively(&dir)?, 0);
+            assert!(!autosave_journal_path(&trusted_root).exists());
+            assert!(!autosave_journal_next_path(&trusted_root).exists());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn move_notes_keeps_bytes_and_suffixes_colliding_names() {
+        let dir = make_temp_dir("move-notes-collisions");
+        let result = (|| -> Result<(), String> {
+            let first = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("move-a", "Plan", "# Plan\nalpha", Some("alpha")),
+            )?;
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("move-b", "Plan", "# Plan\nbeta", Some("beta")),
+            )?;
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("stay", "Plan", "# Plan\ntarget", Some("target")),
+            )?;
+            let first_bytes = fs::read(&first.file_path).map_err(|e| e.to_string())?;
+
+            let moved = move_notes(
+                &dir,
+                &[
+                    "move-a".to_string(),
+                    "move-b".to_string(),
+                    "stay".to_string(),
+                ],
+                Some("target"),
+                &NoteHistoryRetention::default(),
+            )?;
+            assert_eq!(moved.moved.len(), 2);
+            assert!(moved.moved[0].file_path.ends_with("Plan-2.md"));
+            assert!(moved.moved[1].file_path.ends_with("Plan-3.md"));
+            assert_eq!(
+                fs::read(&moved.moved[0].file_path).map_err(|e| e.to_string())?,
+                first_bytes
+            );
+            assert!(!Path::new(&first.file_path).exists());
+
+            let load = load_markdown_library(&dir);
+            assert_eq!(load.load_state, NoteLoadState::Ready);
+            assert_eq!(load.notes.len(), 3);
+            assert!(load.notes.iter().all(|note| note.folder_path == "target"));
+            assert_eq!(count_markdown_files_recursively(&dir)?, 3);
+
+            let error = move_notes(
+                &dir,
+                &["missing".to_string()],
+                None,
+                &NoteHistoryRetention::default(),
+            )
+            .unwrap_err();
+            assert!(error.contains("missing"));
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn move_notes_publish_failure_recovers_without_duplicates() {
+        let dir = make_temp_dir("move-notes-recovery");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("first", "First", "# First", Some("alpha")),
+            )?;
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("second", "Second", "# Second", Some("alpha")),
+            )?;
+            let trusted_root = TrustedLibraryRoot::open(&dir)?;
+            let injector =
+                FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::NotePublishReported, 2);
+
+            let error = move_notes_with_faults(
+                &trusted_root,
+                &["first".to_string(), "second".to_string()],
+                Some("beta"),
+                &injector,
+                &NoteHistoryRetention::default(),
+            )
+            .unwrap_err();
+            assert!(error.contains("NotePublishReported"));
+
+            let load = load_markdown_library(&dir);
+            assert_eq!(load.load_state, NoteLoadState::Ready);
+            assert_eq!(load.notes.len(), 2);
+            assert!(load.notes.iter().all(|note| note.folder_path == "beta"));
+            assert_eq!(count_markdown_files_recursively(&dir)?, 2);
+            assert_eq!(count_autosave_artifacts_recursively(&dir)?, 0);
+            assert!(!autosave_journal_path(&trusted_root).exists());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn move_notes_failure_keeps_bookkeeping_for_notes_already_moved() {
+        let dir = make_temp_dir("move-notes-partial-bookkeeping");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("first", "First", "# First", Some("alpha")),
+            )?;
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("second", "Second", "# Second", Some("alpha")),
+            )?;
+            let hub = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("hub", "Hub", "[[alpha/First]] [[alpha/Second]]", None),
+            )?;
+            let search = crate::search_index::NoteSearchQuery {
+                query: "first".to_string(),
+                ..Default::default()
+            };
+            crate::search_index::search_notes(&dir, &search)?;
+            let trusted_root = TrustedLibraryRoot::open(&dir)?;
+            let injector =
+                FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::NotePublishReported, 2);
+
+            let error = move_notes_with_faults(
+                &trusted_root,
+                &["first".to_string(), "second".to_string()],
+                Some("beta"),
+                &injector,
+                &NoteHistoryRetention::default(),
+            )
+            .unwrap_err();
+            assert!(error.contains("NotePublishReported"));
+
+            let stored = fs::read_to_string(&hub.file_path).map_err(|e| e.to_string())?;
+            assert!(
+                stored.contains("[[beta/First]] [[beta/Second]]"),
+                "{stored}"
+            );
+            let links = note_links::note_links(&dir, "hub")?;
+            assert!(links.unresolved.is_empty());
+            let found = crate::search_index::search_notes(&dir, &search)?;
+            assert!(!found.rebuilt);
+            assert_eq!(found.hits[0].folder_path, "beta");
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn note_batch_applies_operations_with_per_item_results() {
+        let dir = make_temp_dir("note-batch-applies");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &autosave_payload("pin-me", "Pin", "# Pin", None))?;
+            auto_save_markdown_note(&dir, &autosave_payload("move-me", "Move", "# Move", None))?;
+            let trashed = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("trash-me", "Trash", "# Trash", None),
+            )?;
+            let operations: Vec<NoteBatchOperation> = serde_json::from_str(
+                r#"[
+                    {"kind": "setPinned", "noteId": "pin-me", "isPinned": true},
+                    {"kind": "setManualTitle", "noteId": "pin-me", "title": "Renamed"},
+                    {"kind": "move", "noteId": "move-me", "folderPath": "archive"},
+                    {"kind": "trash", "noteId": "trash-me"},
+                    {"kind": "setPinned", "noteId": "missing", "isPinned": true}
+                ]"#,
+            )
+            .map_err(|e| e.to_string())?;
+
+            let batch = apply_note_batch(&dir, &operations, &NoteHistoryRetention::default())?;
+            assert!(batch.committed);
+            let statuses: Vec<_> = batch.items.iter().map(|item| item.status).collect();
+            assert_eq!(
+                statuses,
+                vec![
+                    NoteBatchItemStatus::Applied,
+                    NoteBatchItemStatus::Applied,
+                    NoteBatchItemStatus::Applied,
+                    NoteBatchItemStatus::Applied,
+                    NoteBatchItemStatus::Failed,
+                ]
+            );
+            assert!(!Path::new(&trashed.file_path).exists());
+            let trash = note_trash::list_trash(&dir)?;
+            assert_eq!(trash.len(), 1);
+            assert_eq!(trash[0].note_id.as_deref(), Some("trash-me"));
+            assert_eq!(trash[0].title, "Trash");
+
+            let load = load_markdown_library(&dir);
+            assert_eq!(load.load_state, NoteLoadState::Ready);
+            assert_eq!(load.notes.len(), 2);
+            let pinned = load
+                .notes
+                .iter()
+                .find(|note| note.note_id == "pin-me")
+                .ok_or_else(|| "pinned note missing".to_string())?;
+            assert!(pinned.is_pinned);
+            assert_eq!(pinned.title, "Renamed");
+            let moved = load
+                .notes
+                .iter()
+                .find(|note| note.note_id == "move-me")
+                .ok_or_else(|| "moved note missing".to_string())?;
+            assert_eq!(moved.folder_path, "archive");
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn note_batch_file_failure_rolls_back_moves_and_keeps_index() {
+        let dir = make_temp_dir("note-batch-rollback");
+        let result = (|| -> Result<(), String> {
+            let moved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("move-me", "Move", "# Move", None),
+            )?;
+            auto_save_markdown_note(&dir, &autosave_payload("keep-me", "Keep", "# Keep", None))?;
+            let index_before = fs::read(get_index_path(&dir)).map_err(|e| e.to_string())?;
+            fs::write(
+                dir.join(note_trash::TRASH_DIRECTORY_NAME),
+                "not a directory",
+            )
+            .map_err(|e| e.to_string())?;
+
+            let batch = apply_note_batch(
+                &dir,
+                &[
+                    NoteBatchOperation::Move {
+                        note_id: "move-me".to_string(),
+                        folder_path: Some("archive".to_string()),
+                    },
+                    NoteBatchOperation::Trash {
+                        note_id: "keep-me".to_string(),
+                    },
+                    NoteBatchOperation::SetPinned {
+                        note_id: "move-me".to_string(),
+                        is_pinned: true,
+                    },
+                ],
+                &NoteHistoryRetention::default(),
+            )?;
+            assert!(!batch.committed);
+            assert_eq!(batch.items[0].status, NoteBatchItemStatus::NotApplied);
+            assert_eq!(batch.items[1].status, NoteBatchItemStatus::Failed);
+            assert_eq!(batch.items[2].status, NoteBatchItemStatus::NotApplied);
+            assert!(Path::new(&moved.file_path).exists());
+            assert_eq!(
+                fs::read(get_index_path(&dir)).map_err(|e| e.to_string())?,
+                index_before
+            );
+            assert_eq!(count_markdown_files_recursively(&dir)?, 2);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn note_batch_clearing_title_and_pin_rewrites_front_matter() {
+        let dir = make_temp_dir("note-batch-front-matter");
         let result = (|| -> Result<(), String> {
-            let trusted_root = TrustedLibraryRoot::open(&dir)?;
-            let injector =
-                FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::NoteTempWrite, 1);
+            let saved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("plain-me", "Kept", "# Body title", None),
+            )?;
+            let before = fs::read_to_string(&saved.file_path).map_err(|e| e.to_string())?;
+            assert!(before.contains("title: Kept"));
+            assert!(before.contains("pinned: true"));
 
-            let error = auto_save_markdown_note_with_faults(
-                &trusted_root,
-                &autosave_payload("temp-fail", "Temp fail", "# Temp fail", None),
-                &injector,
-            )
-            .unwrap_err();
-            assert!(error.contains("NoteTempWrite") || error.contains("write_note_temp"));
+            let batch = apply_note_batch(
+                &dir,
+                &[
+                    NoteBatchOperation::SetManualTitle {
+                        note_id: "plain-me".to_string(),
+                        title: None,
+                    },
+                    NoteBatchOperation::SetPinned {
+                        note_id: "plain-me".to_string(),
+                        is_pinned: false,
+                    },
+                ],
+                &NoteHistoryRetention::default(),
+            )?;
+            assert!(batch.committed);
+            let after = fs::read_to_string(&saved.file_path).map_err(|e| e.to_string())?;
+            assert!(!after.contains("title:"));
+            assert!(!after.contains("pinned:"));
+            assert!(after.contains("# Body title"));
 
             let load = load_markdown_library(&dir);
             assert_eq!(load.load_state, NoteLoadState::Ready);
-            assert!(load.notes.is_empty());
-            assert_eq!(list_markdown_files(&dir, &dir)?.len(), 0);
-            assert!(!autosave_journal_path(&trusted_root).exists());
-            assert!(!autosave_journal_next_path(&trusted_root).exists());
-
-            auto_save_markdown_note(
-                &dir,
-                &autosave_payload("temp-fail", "Recovered", "# Recovered", None),
-            )?;
-            assert_eq!(count_markdown_files_recursively(&dir)?, 1);
+            let note = load
+                .notes
+                .iter()
+                .find(|note| note.note_id == "plain-me")
+                .ok_or_else(|| "note missing".to_string())?;
+            assert_eq!(note.title, "Body title");
+            assert!(!note.is_pinned);
             Ok(())
         })();
         cleanup_temp_dir(&dir);
@@ -4570,47 +9242,70 @@ mod tests {
     }
 
     #[test]
-    fn autosave_note_publish_failure_recovers_folder_move_without_duplicates() {
-        let dir = make_temp_dir("autosave-note-publish-recovery");
+    fn note_batch_commit_failure_undoes_earlier_commits_and_trash() {
+        let dir = make_temp_dir("note-batch-commit-failure");
         let result = (|| -> Result<(), String> {
+            let moved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("move-me", "Move", "# Move", None),
+            )?;
+            let pinned =
+                auto_save_markdown_note(&dir, &autosave_payload("pin-me", "Pin", "# Pin", None))?;
+            let pinned_before = fs::read(&pinned.file_path).map_err(|e| e.to_string())?;
             auto_save_markdown_note(
                 &dir,
-                &autosave_payload("stable-note", "Alpha", "# Alpha", Some("alpha")),
+                &autosave_payload("trash-me", "Trash", "# Trash", None),
             )?;
-            let before = load_markdown_notes(&dir)?
-                .into_iter()
-                .find(|note| note.note_id == "stable-note")
-                .ok_or_else(|| "baseline note missing".to_string())?;
             let trusted_root = TrustedLibraryRoot::open(&dir)?;
             let injector =
-                FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::NotePublish, 1);
+                FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::NotePublish, 2);
 
-            let error = auto_save_markdown_note_with_faults(
+            let batch = apply_note_batch_with_faults(
                 &trusted_root,
-                &autosave_payload("stable-note", "Beta", "# Beta", Some("beta")),
+                &[
+                    NoteBatchOperation::Move {
+                        note_id: "move-me".to_string(),
+                        folder_path: Some("archive".to_string()),
+                    },
+                    NoteBatchOperation::Trash {
+                        note_id: "trash-me".to_string(),
+                    },
+                    NoteBatchOperation::SetPinned {
+                        note_id: "pin-me".to_string(),
+                        is_pinned: false,
+                    },
+                ],
+                &NoteHistoryRetention::default(),
                 &injector,
-            )
-            .unwrap_err();
-            assert!(error.contains("NotePublish") || error.contains("publish_note"));
-            assert!(
-                autosave_journal_path(&trusted_root).exists()
-                    || autosave_journal_next_path(&trusted_root).exists()
+            )?;
+            assert!(!batch.committed);
+            let statuses: Vec<_> = batch.items.iter().map(|item| item.status).collect();
+            assert_eq!(
+                statuses,
+                vec![
+                    NoteBatchItemStatus::NotApplied,
+                    NoteBatchItemStatus::NotApplied,
+                    NoteBatchItemStatus::Failed,
+                ]
             );
+            assert!(Path::new(&moved.file_path).exists());
+            assert_eq!(
+                fs::read(&pinned.file_path).map_err(|e| e.to_string())?,
+                pinned_before
+            );
+            assert!(note_trash::list_trash(&dir)?.is_empty());
+            assert!(!autosave_journal_path(&trusted_root).exists());
 
             let load = load_markdown_library(&dir);
             assert_eq!(load.load_state, NoteLoadState::Ready);
-            let note = load
+            assert_eq!(load.notes.len(), 3);
+            let moved_note = load
                 .notes
                 .iter()
-                .find(|note| note.note_id == "stable-note")
-                .ok_or_else(|| "recovered note missing".to_string())?;
-            assert_eq!(note.folder_path, "beta");
-            assert_eq!(note.markdown, "# Beta");
-            assert_eq!(note.created_at, before.created_at);
-            assert_eq!(count_markdown_files_recursively(&dir)?, 1);
-            assert_eq!(count_autosave_artifacts_recursively(&dir)?, 0);
-            assert!(!autosave_journal_path(&trusted_root).exists());
-            assert!(!autosave_journal_next_path(&trusted_root).exists());
+                .find(|note| note.note_id == "move-me")
+                .ok_or_else(|| "moved note missing".to_string())?;
+            assert_eq!(moved_note.folder_path, "");
+            assert_eq!(count_markdown_files_recursively(&dir)?, 3);
             Ok(())
         })();
         cleanup_temp_dir(&dir);
@@ -4632,6 +9327,7 @@ mod tests {
             let error = auto_save_markdown_note_with_faults(
                 &trusted_root,
                 &autosave_payload("cleanup-note", "Beta", "# Beta", Some("beta")),
+                &NoteHistoryRetention::default(),
                 &injector,
             )
             .unwrap_err();
@@ -4671,6 +9367,7 @@ mod tests {
             let error = auto_save_markdown_note_with_faults(
                 &trusted_root,
                 &autosave_payload("index-note", "Beta", "# Beta", Some("beta")),
+                &NoteHistoryRetention::default(),
                 &injector,
             )
             .unwrap_err();
@@ -4794,6 +9491,7 @@ mod tests {
                         created_at: 1,
                         manual_title: Some("Planned".to_string()),
                         is_pinned: Some(false),
+                        seen_by: BTreeMap::new(),
                     },
                 )]),
             };
@@ -4874,6 +9572,7 @@ mod tests {
                             created_at: 1,
                             manual_title: Some("Journal".to_string()),
                             is_pinned: Some(false),
+                            seen_by: BTreeMap::new(),
                         },
                     )]),
                 },
@@ -5077,6 +9776,7 @@ mod tests {
             let error = auto_save_markdown_note_with_faults(
                 &trusted_root,
                 &autosave_payload("cleanup-journal", "Beta", "# Beta", Some("beta")),
+                &NoteHistoryRetention::default(),
                 &injector,
             )
             .unwrap_err();
@@ -5213,6 +9913,7 @@ mod tests {
             let index_error = auto_save_markdown_note_with_faults(
                 &trusted_root,
                 &autosave_payload("conflict-note", "Beta", "# Beta", Some("beta")),
+                &NoteHistoryRetention::default(),
                 &FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::IndexPublish, 1),
             )
             .unwrap_err();
@@ -5228,6 +9929,7 @@ mod tests {
                         created_at: 1,
                         manual_title: Some("Other".to_string()),
                         is_pinned: Some(false),
+                        seen_by: BTreeMap::new(),
                     },
                 )]),
             };
@@ -5246,6 +9948,7 @@ mod tests {
             let old_cleanup_error = auto_save_markdown_note_with_faults(
                 &trusted_root,
                 &autosave_payload("conflict-note", "Beta", "# Beta", Some("beta")),
+                &NoteHistoryRetention::default(),
                 &FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::OldFileCleanup, 1),
             )
             .unwrap_err();
@@ -5269,6 +9972,7 @@ mod tests {
             let note_error = auto_save_markdown_note_with_faults(
                 &trusted_root,
                 &autosave_payload("conflict-note", "Beta", "# Beta", Some("beta")),
+                &NoteHistoryRetention::default(),
                 &FailOnceAutosaveFaultInjector::fail_on(AutosaveFaultPoint::IndexPublish, 1),
             )
             .unwrap_err();
@@ -5307,6 +10011,7 @@ mod tests {
                             created_at: 1,
                             manual_title: Some("Unsafe".to_string()),
                             is_pinned: Some(false),
+                            seen_by: BTreeMap::new(),
                         },
                     )]),
                 },
@@ -5348,6 +10053,23 @@ mod tests {
         assert!(sanitize_folder_path(Some("bad|name")).is_err());
     }
 
+    #[test]
+    fn sanitize_folder_path_rejects_reserved_library_directories() {
+        assert!(sanitize_folder_path(Some(".hwan-note-history")).is_err());
+        assert!(sanitize_folder_path(Some("inbox/.HWAN-NOTE-HISTORY/notes")).is_err());
+        assert_eq!(
+            sanitize_folder_path(Some("projects/.hwan-note-history")).unwrap(),
+            "projects/.hwan-note-history"
+        );
+    }
+
+    #[test]
+    fn derive_title_and_plain_text_ignore_front_matter() {
+        let markdown = "---\ntitle: Stored\ntags: [a]\n---\n- [ ] First task\nSecond";
+        assert_eq!(derive_title(markdown), "First task");
+        assert_eq!(markdown_to_plain_text(markdown), "First task\nSecond");
+    }
+
     #[test]
     fn derive_title_strips_all_markdown_heading_levels() {
         for level in 1..=6 {
@@ -5396,7 +10118,8 @@ mod tests {
                 },
             )?;
 
-            let folders = rename_folder(&dir, "alpha", "beta")?;
+            let folders =
+                rename_folder(&dir, "alpha", "beta", &NoteHistoryRetention::default())?.folders;
             assert!(folders.contains(&"beta".to_string()));
             assert!(!folders.contains(&"alpha".to_string()));
 
@@ -5440,7 +10163,7 @@ mod tests {
                 },
             )?;
 
-            let result = delete_folder(&dir, "alpha")?;
+            let result = delete_folder(&dir, "alpha", &NoteHistoryRetention::default())?;
             assert_eq!(result.moved_note_ids.len(), 2);
             assert!(!dir.join("alpha").exists());
 
@@ -5457,7 +10180,7 @@ mod tests {
     }
 
     #[test]
-    fn auto_save_embeds_manual_title_metadata_and_load_hides_it() {
+    fn auto_save_writes_front_matter_and_load_hides_it() {
         let dir = make_temp_dir("manual-title-meta");
         let result = (|| -> Result<(), String> {
             auto_save_markdown_note(
@@ -5476,7 +10199,8 @@ mod tests {
             assert_eq!(files.len(), 1);
 
             let raw_markdown = fs::read_to_string(&files[0]).unwrap();
-            assert!(raw_markdown.starts_with(MANUAL_TITLE_META_PREFIX));
+            assert!(raw_markdown.starts_with("---"));
+            assert!(raw_markdown.contains("title: Project Launch"));
             assert!(raw_markdown.contains("Body first line"));
 
             #[cfg(windows)]
@@ -5490,7 +10214,7 @@ mod tests {
             assert_eq!(notes[0].title, "Project Launch");
             assert!(notes[0].is_title_manual);
             assert_eq!(notes[0].plain_text, "Body first line\nSecond line");
-            assert!(!notes[0].markdown.contains("hwan-note:manual-title"));
+            assert_eq!(notes[0].markdown, "Body first line\nSecond line");
             Ok(())
         })();
         cleanup_temp_dir(&dir);
@@ -5823,8 +10547,10 @@ mod tests {
             fs::write(
                 dir.join(&relative_path),
                 to_platform_line_endings(&format!(
-                    "{}\nBody first line",
-                    embed_manual_title_metadata("", Some(manual_title))
+                    "{}{}{}\nBody first line",
+                    MANUAL_TITLE_META_PREFIX,
+                    encode_manual_title_hex(manual_title),
+                    MANUAL_TITLE_META_SUFFIX
                 )),
             )
             .unwrap();
@@ -5839,6 +10565,7 @@ mod tests {
                             created_at: now_millis(),
                             manual_title: None,
                             is_pinned: None,
+                            seen_by: BTreeMap::new(),
                         },
                     )]),
                 },
@@ -5858,6 +10585,88 @@ mod tests {
         result.unwrap();
     }
 
+    #[test]
+    fn auto_save_migrates_legacy_title_comment_and_keeps_foreign_metadata() {
+        let dir = make_temp_dir("front-matter-migrate");
+        let result = (|| -> Result<(), String> {
+            let relative_path = "legacy.md".to_string();
+            let note_id = generate_note_id(&relative_path);
+            fs::write(
+                dir.join(&relative_path),
+                format!(
+                    "{}{}{}\n---\ntags: [work]\nsource: obsidian\n---\nOld body",
+                    MANUAL_TITLE_META_PREFIX,
+                    encode_manual_title_hex("Legacy Title"),
+                    MANUAL_TITLE_META_SUFFIX
+                ),
+            )
+            .map_err(|e| e.to_string())?;
+
+            let loaded = load_ready_note_by_id(&dir, &note_id)?;
+            assert_eq!(loaded.title, "Legacy Title");
+            assert_eq!(loaded.tags, vec!["work".to_string()]);
+            assert_eq!(loaded.markdown, "Old body");
+
+            let saved = auto_save_markdown_note(
+                &dir,
+                &AutoSavePayload {
+                    note_id: note_id.clone(),
+                    title: loaded.title,
+                    content: "New body".to_string(),
+                    folder_path: None,
+                    is_title_manual: Some(true),
+                    is_pinned: Some(true),
+                },
+            )?;
+            let raw = fs::read_to_string(&saved.file_path)
+                .map_err(|e| e.to_string())?
+                .replace("\r\n", "\n");
+            assert!(!raw.contains("hwan-note:manual-title"));
+            let (front_matter, body) = split_front_matter(&raw);
+            let front_matter = front_matter.ok_or("expected front matter")?;
+            assert_eq!(body, "New body");
+            assert_eq!(front_matter.title.as_deref(), Some("Legacy Title"));
+            assert_eq!(front_matter.pinned, Some(true));
+            assert_eq!(front_matter.created, Some(saved.created_at / 1_000 * 1_000));
+            assert_eq!(front_matter.tags, vec!["work".to_string()]);
+            assert!(front_matter.extra.contains_key("source"));
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn rebuilt_index_recovers_title_pin_and_created_from_front_matter() {
+        let dir = make_temp_dir("front-matter-rebuild");
+        let result = (|| -> Result<(), String> {
+            let saved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("pinned-note", "Pinned Plan", "# Body heading", None),
+            )?;
+            fs::remove_file(get_index_path(&dir)).map_err(|e| e.to_string())?;
+
+            let notes = load_markdown_notes(&dir)?;
+            assert_eq!(notes.len(), 1);
+            assert_eq!(notes[0].title, "Pinned Plan");
+            assert!(notes[0].is_title_manual);
+            assert!(notes[0].is_pinned);
+            assert_eq!(notes[0].created_at, saved.created_at / 1_000 * 1_000);
+            assert_eq!(notes[0].markdown, "# Body heading");
+
+            let entry = read_index(&dir)?
+                .entries
+                .into_values()
+                .next()
+                .ok_or("expected rebuilt index entry")?;
+            assert_eq!(entry.manual_title.as_deref(), Some("Pinned Plan"));
+            assert_eq!(entry.is_pinned, Some(true));
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
     #[test]
     fn migrate_notes_preserves_cloud_state_and_imports_local_conflicts_safely() {
         let src = make_temp_dir("migrate-src");
@@ -6058,9 +10867,15 @@ mod tests {
                 },
             )?;
 
-            let renamed = rename_folder(&dir, "projects", "archive")?;
+            let renamed = rename_folder(
+                &dir,
+                "projects",
+                "archive",
+                &NoteHistoryRetention::default(),
+            )?
+            .folders;
             assert!(renamed.contains(&"archive/rust/security".to_string()));
-            let deleted = delete_folder(&dir, "archive")?;
+            let deleted = delete_folder(&dir, "archive", &NoteHistoryRetention::default())?;
             assert_eq!(deleted.moved_note_ids, vec!["nested-note".to_string()]);
 
             let notes = load_markdown_notes(&dir)?;
@@ -6090,9 +10905,16 @@ mod tests {
 
             let create_error = create_folder(&dir, "container/linked/new").unwrap_err();
             assert!(create_error.contains("symbolic links and reparse points"));
-            let rename_error = rename_folder(&dir, "container", "renamed").unwrap_err();
+            let rename_error = rename_folder(
+                &dir,
+                "container",
+                "renamed",
+                &NoteHistoryRetention::default(),
+            )
+            .unwrap_err();
             assert!(rename_error.contains("reject_symlink"));
-            let delete_error = delete_folder(&dir, "container").unwrap_err();
+            let delete_error =
+                delete_folder(&dir, "container", &NoteHistoryRetention::default()).unwrap_err();
             assert!(delete_error.contains("reject_symlink"));
 
             assert!(container.exists());
@@ -6227,15 +11049,364 @@ mod tests {
         let dir = make_temp_dir("save-text-line-endings");
         let path = dir.join("note.txt");
         let result = (|| -> Result<(), String> {
-            save_text_file(&path, "line 1\r\nline 2\nline 3")?;
+            save_text_file(
+                &path,
+                "line 1\r\nline 2\nline 3",
+                &TextFormat::default(),
+                None,
+            )?;
             let raw = fs::read_to_string(&path).unwrap();
 
-            #[cfg(windows)]
-            assert!(raw.contains("\r\n"));
+            #[cfg(windows)]
+            assert!(raw.contains("\r\n"));
+
+            #[cfg(not(windows))]
+            assert!(!raw.contains("\r\n"));
+
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn save_text_file_refuses_to_overwrite_external_changes() {
+        let dir = make_temp_dir("save-text-conflict");
+        let path = dir.join("note.txt");
+        let result = (|| -> Result<(), String> {
+            fs::write(&path, "original").unwrap();
+            let (_, format, opened) = read_text_file(&path, None)?;
+
+            let ExternalSaveResult::Saved { version: saved } =
+                save_text_file(&path, "mine", &format, Some(&opened))?
+            else {
+                panic!("unchanged file should save");
+            };
+            assert_eq!(fs::read_to_string(&path).unwrap(), "mine");
+            assert_ne!(saved.digest, opened.digest);
+
+            fs::write(&path, "theirs").unwrap();
+            let ExternalSaveResult::Conflict { current } =
+                save_text_file(&path, "mine again", &format, Some(&saved))?
+            else {
+                panic!("changed file should conflict");
+            };
+            assert_eq!(current.unwrap().digest, sha256_hex(b"theirs"));
+            assert_eq!(fs::read_to_string(&path).unwrap(), "theirs");
+
+            fs::remove_file(&path).unwrap();
+            assert_eq!(
+                save_text_file(&path, "mine again", &format, Some(&saved))?,
+                ExternalSaveResult::Conflict { current: None }
+            );
+            assert!(!path.exists());
+
+            save_text_file(&path, "forced", &format, None)?;
+            assert_eq!(fs::read_to_string(&path).unwrap(), "forced");
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn external_document_path_accepts_markdown_and_configured_text_extensions() {
+        let dir = make_temp_dir("external-document-path");
+        let extensions = normalize_text_extensions(&[
+            "txt".to_string(),
+            ".LOG".to_string(),
+            "md".to_string(),
+            "log".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(extensions, vec!["txt".to_string(), "log".to_string()]);
+        assert!(normalize_text_extensions(&["t/xt".to_string()]).is_err());
+
+        let result = (|| -> Result<(), String> {
+            for name in [
+                "Readme.MD",
+                "notes.markdown",
+                "build.log",
+                "todo.txt",
+                "data.csv",
+            ] {
+                fs::write(dir.join(name), "hello").unwrap();
+            }
+            let format = |name: &str| {
+                normalize_external_document_path(name, Some(&dir), &extensions)
+                    .map(|(_, format)| format)
+            };
+            assert_eq!(format("Readme.MD")?, ExternalFileFormat::Md);
+            assert_eq!(format("notes.markdown")?, ExternalFileFormat::Md);
+            assert_eq!(format("build.log")?, ExternalFileFormat::Txt);
+            assert_eq!(format("todo.txt")?, ExternalFileFormat::Txt);
+            assert_eq!(
+                format("data.csv").unwrap_err(),
+                "Only Markdown and text files are supported."
+            );
+            assert!(format("missing.md").is_err());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn sync_conflict_copies_are_reported_and_resolved() {
+        let dir = make_temp_dir("sync-conflicts");
+        let result = (|| -> Result<(), String> {
+            let saved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("plan-note", "Plan", "# Plan\n\nmine\n", None),
+            )?;
+            let original = PathBuf::from(&saved.file_path);
+            let stem = original.file_stem().unwrap().to_string_lossy().to_string();
+            let drive_copy = original.with_file_name(format!("{stem} (1).md"));
+            let onedrive_copy = original.with_file_name(format!("{stem}-DESKTOP-AB12.md"));
+            fs::write(&drive_copy, "# Plan\n\ntheirs\n").unwrap();
+            fs::write(&onedrive_copy, "# Plan\n\nlaptop\n").unwrap();
+
+            let loaded = load_markdown_library(&dir);
+            assert_eq!(loaded.load_state, NoteLoadState::Ready);
+            assert_eq!(loaded.notes.len(), 1);
+            assert_eq!(loaded.conflicts.len(), 2);
+            assert!(loaded
+                .conflicts
+                .iter()
+                .all(|conflict| conflict.note_id == "plan-note"));
+            let drive_conflict = loaded
+                .conflicts
+                .iter()
+                .find(|conflict| conflict.conflict_markdown.contains("theirs"))
+                .unwrap();
+            assert!(drive_conflict.conflict_path.ends_with(" (1).md"));
+
+            assert!(resolve_sync_conflict(
+                &dir,
+                "other-note",
+                &drive_copy,
+                &SyncConflictResolution::KeepOriginal,
+                &NoteHistoryRetention::default(),
+            )
+            .is_err());
+            resolve_sync_conflict(
+                &dir,
+                "plan-note",
+                &drive_copy,
+                &SyncConflictResolution::Merged {
+                    markdown: "# Plan\n\nmine\ntheirs\n".to_string(),
+                },
+                &NoteHistoryRetention::default(),
+            )?;
+            assert!(!drive_copy.exists());
+            assert!(fs::read_to_string(&original)
+                .unwrap()
+                .ends_with("# Plan\n\nmine\ntheirs\n"));
+
+            resolve_sync_conflict(
+                &dir,
+                "plan-note",
+                &onedrive_copy,
+                &SyncConflictResolution::KeepBoth,
+                &NoteHistoryRetention::default(),
+            )?;
+            let reloaded = load_markdown_library(&dir);
+            assert!(reloaded.conflicts.is_empty());
+            assert_eq!(reloaded.notes.len(), 2);
+            assert_eq!(note_trash::list_trash(&dir)?.len(), 1);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn fallback_changes_reconcile_into_the_cloud_library() {
+        let local = make_temp_dir("fallback-local");
+        let cloud = make_temp_dir("fallback-cloud");
+        let result = (|| -> Result<(), String> {
+            for (note_id, body) in [("shared", "one"), ("contested", "two"), ("gone", "three")] {
+                auto_save_markdown_note(&local, &autosave_payload(note_id, note_id, body, None))?;
+            }
+            migrate_notes(&local, &cloud)?;
+
+            for note_id in ["shared", "contested", "fresh"] {
+                crate::fallback_sync::record_note_change(&local, note_id, false)?;
+                auto_save_markdown_note(
+                    &local,
+                    &autosave_payload(note_id, note_id, "edited offline", None),
+                )?;
+            }
+            crate::fallback_sync::record_note_change(&local, "gone", false)?;
+            assert!(trash_note(&local, "gone")?);
+            crate::fallback_sync::record_note_change(&local, "gone", true)?;
+            auto_save_markdown_note(
+                &cloud,
+                &autosave_payload("contested", "contested", "edited elsewhere", None),
+            )?;
+
+            let changes = crate::fallback_sync::read_changes(&local)?;
+            let mut outcomes = reconcile_fallback_notes(
+                &local,
+                &cloud,
+                &changes.notes,
+                &NoteHistoryRetention::default(),
+            )?
+            .into_iter()
+            .map(|note| (note.note_id, note.outcome))
+            .collect::<Vec<_>>();
+            outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(
+                outcomes,
+                vec![
+                    ("contested".to_string(), ReconcileOutcome::Conflict),
+                    ("fresh".to_string(), ReconcileOutcome::Copied),
+                    ("gone".to_string(), ReconcileOutcome::Deleted),
+                    ("shared".to_string(), ReconcileOutcome::Updated),
+                ]
+            );
+
+            let notes = load_markdown_notes(&cloud)?;
+            assert_eq!(notes.len(), 4);
+            let count = |body: &str| notes.iter().filter(|note| note.markdown == body).count();
+            assert_eq!(count("edited offline"), 3);
+            assert_eq!(count("edited elsewhere"), 1);
+            Ok(())
+        })();
+        cleanup_temp_dir(&local);
+        cleanup_temp_dir(&cloud);
+        result.unwrap();
+    }
+
+    #[test]
+    fn fallback_moves_reconcile_into_the_cloud_library() {
+        let local = make_temp_dir("fallback-move-local");
+        let cloud = make_temp_dir("fallback-move-cloud");
+        let result = (|| -> Result<(), String> {
+            let retention = NoteHistoryRetention::default();
+            let moved = auto_save_markdown_note(
+                &local,
+                &autosave_payload("moved", "Moved", "going places", Some("Projects")),
+            )?;
+            let moved_name = Path::new(&moved.file_path).file_name().unwrap().to_owned();
+            auto_save_markdown_note(
+                &local,
+                &autosave_payload("loose", "Loose", "no folder left", Some("Old")),
+            )?;
+            migrate_notes(&local, &cloud)?;
+
+            let before = crate::fallback_sync::capture_library_state(&local)?;
+            move_notes(&local, &["moved".to_string()], Some("Archive"), &retention)?;
+            delete_folder(&local, "Old", &retention)?;
+            crate::fallback_sync::record_library_changes(&local, &before)?;
+
+            let changes = crate::fallback_sync::read_changes(&local)?;
+            assert_eq!(
+                changes.notes["moved"].base_path.as_deref(),
+                Some(format!("Projects/{}", moved_name.to_string_lossy()).as_str())
+            );
+            assert_eq!(
+                changes.notes["loose"].base_path.as_deref(),
+                Some("Old/Loose.md")
+            );
+            let mut outcomes =
+                reconcile_fallback_notes(&local, &cloud, &changes.notes, &retention)?
+                    .into_iter()
+                    .map(|note| (note.note_id, note.outcome))
+                    .collect::<Vec<_>>();
+            outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(
+                outcomes,
+                vec![
+                    ("loose".to_string(), ReconcileOutcome::Updated),
+                    ("moved".to_string(), ReconcileOutcome::Updated),
+                ]
+            );
+
+            let index = read_index(&cloud)?;
+            assert_eq!(index.entries["loose"].relative_path, "Loose.md");
+            let entry = &index.entries["moved"];
+            assert!(entry.relative_path.starts_with("Archive/"));
+            assert!(cloud.join(&entry.relative_path).exists());
+            assert!(!cloud.join("Projects").join(&moved_name).exists());
+            assert!(!cloud.join("Old/Loose.md").exists());
+            let notes = load_markdown_notes(&cloud)?;
+            assert_eq!(notes.len(), 2);
+            Ok(())
+        })();
+        cleanup_temp_dir(&local);
+        cleanup_temp_dir(&cloud);
+        result.unwrap();
+    }
+
+    #[test]
+    fn autosave_merges_edits_saved_by_another_machine() {
+        let dir = make_temp_dir("three-way-merge");
+        let result = (|| -> Result<(), String> {
+            let saved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-1", "Plan", "alpha\nbeta\ngamma", None),
+            )?;
+            assert_eq!(saved.merge, None);
+            let edit_on_disk = |from: &str, to: &str| {
+                let raw = fs::read_to_string(&saved.file_path).unwrap();
+                fs::write(&saved.file_path, raw.replace(from, to)).unwrap();
+            };
+
+            edit_on_disk("gamma", "gamma (other)");
+            let merged = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-1", "Plan", "alpha (mine)\nbeta\ngamma", None),
+            )?;
+            assert_eq!(
+                merged.merge,
+                Some(NoteMerge {
+                    markdown: "alpha (mine)\nbeta\ngamma (other)".to_string(),
+                    conflicts: 0,
+                })
+            );
+            let notes = load_markdown_notes(&dir)?;
+            assert_eq!(notes[0].markdown, "alpha (mine)\nbeta\ngamma (other)");
 
-            #[cfg(not(windows))]
-            assert!(!raw.contains("\r\n"));
+            edit_on_disk("beta", "beta (other)");
+            let conflicted = auto_save_markdown_note(
+                &dir,
+                &autosave_payload(
+                    "note-1",
+                    "Plan",
+                    "alpha (mine)\nbeta (mine)\ngamma (other)",
+                    None,
+                ),
+            )?
+            .merge
+            .unwrap();
+            assert_eq!(conflicted.conflicts, 1);
+            assert_eq!(
+                conflicted.markdown,
+                "alpha (mine)\n<<<<<<< this device\nbeta (mine)\n=======\nbeta (other)\n>>>>>>> other device\ngamma (other)"
+            );
 
+            let unchanged = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-1", "Plan", &conflicted.markdown, None),
+            )?;
+            assert_eq!(unchanged.merge, None);
+            // Each save replaces the base this machine saw before it.
+            let bases = fs::read_dir(dir.join(note_merge::MERGE_BASES_DIRECTORY_NAME))
+                .map_err(|e| e.to_string())?
+                .count();
+            assert_eq!(bases, 1);
+
+            // Loading a changed note leaves the bases to the save paths.
+            let seen_by = read_index(&dir)?.entries["note-1"].seen_by.clone();
+            edit_on_disk("alpha (mine)", "alpha (other)");
+            load_markdown_notes(&dir)?;
+            assert_eq!(read_index(&dir)?.entries["note-1"].seen_by, seen_by);
+            let bases = fs::read_dir(dir.join(note_merge::MERGE_BASES_DIRECTORY_NAME))
+                .map_err(|e| e.to_string())?
+                .count();
+            assert_eq!(bases, 1);
             Ok(())
         })();
         cleanup_temp_dir(&dir);
@@ -6647,6 +11818,7 @@ mod tests {
                         created_at: 123,
                         manual_title: Some("Escape".to_string()),
                         is_pinned: Some(true),
+                        seen_by: BTreeMap::new(),
                     },
                 )]),
             };
@@ -6788,6 +11960,7 @@ mod tests {
                         created_at: 1,
                         manual_title: None,
                         is_pinned: Some(false),
+                        seen_by: BTreeMap::new(),
                     },
                 )]),
             };
@@ -6799,6 +11972,7 @@ mod tests {
                         created_at: 2,
                         manual_title: Some("Concurrent".to_string()),
                         is_pinned: Some(true),
+                        seen_by: BTreeMap::new(),
                     },
                 )]),
             };
@@ -6936,6 +12110,7 @@ mod tests {
                         created_at: 1,
                         manual_title: Some("Linked".to_string()),
                         is_pinned: Some(false),
+                        seen_by: BTreeMap::new(),
                     },
                 )]),
             };
@@ -7008,7 +12183,7 @@ mod tests {
             let write_result = write_note_file_atomically_after_temp_hook(
                 &trusted_root,
                 &destination,
-                "must-not-escape",
+                b"must-not-escape",
                 || {
                     std::os::unix::fs::symlink(&outside_file, &destination).unwrap();
                 },
@@ -7088,4 +12263,613 @@ mod tests {
         cleanup_temp_dir(&outside);
         result.unwrap();
     }
+
+    #[test]
+    fn external_changes_report_added_changed_and_removed_notes_but_not_own_saves() {
+        let dir = make_temp_dir("external-changes");
+        let result = (|| -> Result<(), String> {
+            let saved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Alpha", "# Alpha", None),
+            )?;
+            let saved_path = PathBuf::from(&saved.file_path);
+            let own_save = ExternalChangeBatch {
+                changed_paths: vec![saved_path.clone()],
+                renamed_paths: Vec::new(),
+            };
+            assert!(reconcile_external_changes(&dir, &own_save)?.is_empty());
+
+            fs::write(&saved_path, "# Alpha\nedited elsewhere").map_err(|e| e.to_string())?;
+            let added_path = dir.join("dropped.md");
+            fs::write(&added_path, "# Dropped").map_err(|e| e.to_string())?;
+            let changes = reconcile_external_changes(
+                &dir,
+                &ExternalChangeBatch {
+                    changed_paths: vec![
+                        saved_path.clone(),
+                        added_path.clone(),
+                        dir.join(AUTOSAVE_JOURNAL_FILENAME),
+                    ],
+                    renamed_paths: Vec::new(),
+                },
+            )?;
+            assert_eq!(changes.changed.len(), 1);
+            assert_eq!(changes.changed[0].note_id, "note-a");
+            assert!(changes.changed[0].is_pinned);
+            assert_eq!(changes.added.len(), 1);
+            assert_eq!(changes.added[0].title, "Dropped");
+            assert!(reconcile_external_changes(&dir, &own_save)?.is_empty());
+
+            fs::remove_file(&saved_path).map_err(|e| e.to_string())?;
+            let changes = reconcile_external_changes(&dir, &own_save)?;
+            assert_eq!(changes.removed.len(), 1);
+            assert_eq!(changes.removed[0].note_id, "note-a");
+            assert_eq!(load_markdown_library(&dir).notes.len(), 1);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn external_rename_keeps_note_id_pin_and_manual_title() {
+        let dir = make_temp_dir("external-rename");
+        let result = (|| -> Result<(), String> {
+            let saved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Manual", "# Body heading", Some("projects")),
+            )?;
+            fs::create_dir_all(dir.join("archive")).map_err(|e| e.to_string())?;
+            fs::rename(dir.join("projects"), dir.join("archive/projects"))
+                .map_err(|e| e.to_string())?;
+
+            let changes = reconcile_external_changes(
+                &dir,
+                &ExternalChangeBatch {
+                    changed_paths: vec![dir.join("archive")],
+                    renamed_paths: vec![(dir.join("projects"), dir.join("archive/projects"))],
+                },
+            )?;
+            assert!(changes.added.is_empty());
+            assert!(changes.removed.is_empty());
+            assert_eq!(changes.moved.len(), 1);
+            assert!(test_paths_equal(
+                Path::new(&changes.moved[0].previous_file_path),
+                Path::new(&saved.file_path)
+            ));
+
+            let note = load_ready_note_by_id(&dir, "note-a")?;
+            assert_eq!(note.folder_path, "archive/projects");
+            assert_eq!(note.title, "Manual");
+            assert!(note.is_title_manual);
+            assert!(note.is_pinned);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn external_changes_reject_symlinks_without_touching_the_index() {
+        let dir = make_temp_dir("external-symlink");
+        let outside = make_temp_dir("external-symlink-outside");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &autosave_payload("note-a", "Alpha", "# Alpha", None))?;
+            let outside_file = outside.join("secret.md");
+            fs::write(&outside_file, "# Secret").map_err(|e| e.to_string())?;
+            let link = dir.join("linked.md");
+            if !create_file_link(&link, &outside_file)? {
+                return Ok(());
+            }
+            let index_before = fs::read(dir.join(INDEX_FILENAME)).map_err(|e| e.to_string())?;
+
+            let changes = reconcile_external_changes(
+                &dir,
+                &ExternalChangeBatch {
+                    changed_paths: vec![link.clone()],
+                    renamed_paths: Vec::new(),
+                },
+            )?;
+            assert!(changes.added.is_empty());
+            assert!(changes
+                .issues
+                .iter()
+                .any(|issue| issue.operation == "reject_symlink"));
+            assert_eq!(
+                fs::read(dir.join(INDEX_FILENAME)).map_err(|e| e.to_string())?,
+                index_before
+            );
+            remove_test_link(&link, false).map_err(|e| e.to_string())?;
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        cleanup_temp_dir(&outside);
+        result.unwrap();
+    }
+
+    fn plain_index_entry(relative_path: impl Into<String>, created_at: u64) -> NoteIndexEntry {
+        NoteIndexEntry {
+            relative_path: relative_path.into(),
+            created_at,
+            manual_title: None,
+            is_pinned: None,
+            seen_by: BTreeMap::new(),
+        }
+    }
+
+    fn write_aged_test_file(path: &Path, contents: &str, age: Duration) -> Result<(), String> {
+        fs::write(path, contents).map_err(|e| e.to_string())?;
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(|e| e.to_string())?;
+        file.set_modified(SystemTime::now() - age)
+            .map_err(|e| e.to_string())
+    }
+
+    fn issue_kinds(issues: &[LibraryIssue]) -> HashSet<LibraryIssueKind> {
+        issues.iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn library_check_reports_each_issue_class_without_changing_files() {
+        let dir = make_temp_dir("library-check");
+        let outside = make_temp_dir("library-check-outside");
+        let result = (|| -> Result<(), String> {
+            let saved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Alpha", "# Alpha", None),
+            )?;
+            let alpha = relative_path(&dir, Path::new(&saved.file_path));
+            let mut index = read_index(&dir)?;
+            index.entries.insert(
+                "note-ghost".to_string(),
+                plain_index_entry("ghost.md".to_string(), 1),
+            );
+            index.entries.insert(
+                "bad id".to_string(),
+                plain_index_entry(alpha.clone(), u64::MAX),
+            );
+            write_index(&dir, &index)?;
+            fs::write(dir.join("loose.md"), "# Loose").map_err(|e| e.to_string())?;
+            fs::write(dir.join(AUTOSAVE_JOURNAL_FILENAME), "{}").map_err(|e| e.to_string())?;
+            fs::write(dir.join(AUTOSAVE_JOURNAL_TEMP_FILENAME), "{").map_err(|e| e.to_string())?;
+            write_aged_test_file(
+                &dir.join(".hwan-note-write-old.tmp"),
+                "partial",
+                Duration::from_secs(3600),
+            )?;
+            fs::write(dir.join(".hwan-note-write-fresh.tmp"), "partial")
+                .map_err(|e| e.to_string())?;
+            fs::write(
+                dir.join(format!("{INDEX_FILENAME}.corrupt-1.bak")),
+                r#"{"entries":{"note-a":{"relativePath":"Alpha.md","createdAt":1},"#,
+            )
+            .map_err(|e| e.to_string())?;
+            fs::write(dir.join("calendar.json.bak.2"), "{not json").map_err(|e| e.to_string())?;
+            fs::write(dir.join("calendar.json.local-recovery.bak"), "{}")
+                .map_err(|e| e.to_string())?;
+            let outside_file = outside.join("secret.md");
+            fs::write(&outside_file, "# Secret").map_err(|e| e.to_string())?;
+            let linked = create_file_link(&dir.join("linked.md"), &outside_file)?;
+            let index_before = fs::read(dir.join(INDEX_FILENAME)).map_err(|e| e.to_string())?;
+
+            let report = check_library(&dir)?;
+            let mut expected = HashSet::from([
+                LibraryIssueKind::PendingAutosaveJournal,
+                LibraryIssueKind::StaleJournalTemp,
+                LibraryIssueKind::OrphanTempFile,
+                LibraryIssueKind::MissingNoteFile,
+                LibraryIssueKind::DuplicatePath,
+                LibraryIssueKind::InvalidNoteId,
+                LibraryIssueKind::UnindexedNoteFile,
+                LibraryIssueKind::CorruptIndexBackup,
+                LibraryIssueKind::CorruptCalendarCopy,
+            ]);
+            if linked {
+                expected.insert(LibraryIssueKind::SymbolicLink);
+            }
+            assert_eq!(issue_kinds(&report.issues), expected);
+            let by_kind = |kind| {
+                report
+                    .issues
+                    .iter()
+                    .filter(|issue| issue.kind == kind)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(by_kind(LibraryIssueKind::OrphanTempFile).len(), 1);
+            assert_eq!(
+                by_kind(LibraryIssueKind::DuplicatePath)[0]
+                    .note_id
+                    .as_deref(),
+                Some("bad id")
+            );
+            assert_eq!(
+                by_kind(LibraryIssueKind::CorruptCalendarCopy)[0].path,
+                "calendar.json.bak.2"
+            );
+            assert_eq!(
+                by_kind(LibraryIssueKind::UnindexedNoteFile)[0].path,
+                "loose.md"
+            );
+            assert!(dir.join(AUTOSAVE_JOURNAL_FILENAME).exists());
+            assert_eq!(
+                fs::read(dir.join(INDEX_FILENAME)).map_err(|e| e.to_string())?,
+                index_before
+            );
+            if linked {
+                remove_test_link(&dir.join("linked.md"), false).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        cleanup_temp_dir(&outside);
+        result.unwrap();
+    }
+
+    #[test]
+    fn library_repair_fixes_selected_classes_and_salvages_index_backups() {
+        let dir = make_temp_dir("library-repair");
+        let result = (|| -> Result<(), String> {
+            let saved = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Alpha", "# Alpha", None),
+            )?;
+            let alpha = relative_path(&dir, Path::new(&saved.file_path));
+            let mut index = read_index(&dir)?;
+            index.entries.insert(
+                "note-ghost".to_string(),
+                plain_index_entry("ghost.md".to_string(), 1),
+            );
+            index.entries.insert(
+                "note/b".to_string(),
+                plain_index_entry("b.md".to_string(), 1),
+            );
+            index.entries.insert(
+                "note-dup".to_string(),
+                plain_index_entry(alpha.clone(), u64::MAX),
+            );
+            write_index(&dir, &index)?;
+            fs::write(dir.join("b.md"), "# B").map_err(|e| e.to_string())?;
+            fs::write(dir.join("loose.md"), "# Loose").map_err(|e| e.to_string())?;
+            fs::write(
+                dir.join(format!("{INDEX_FILENAME}.corrupt-1.bak")),
+                r#"{"entries":{"note-loose":{"relativePath":"loose.md","createdAt":1,"manualTitle":"Recovered","isPinned":true},"note-x":{"rel"#,
+            )
+            .map_err(|e| e.to_string())?;
+            write_aged_test_file(
+                &dir.join(".hwan-note-write-old.tmp"),
+                "partial",
+                Duration::from_secs(3600),
+            )?;
+            fs::write(dir.join("calendar.json.bak"), "{not json").map_err(|e| e.to_string())?;
+
+            let mut deleted = Vec::new();
+            let repair = repair_library(
+                &dir,
+                &[
+                    LibraryIssueKind::MissingNoteFile,
+                    LibraryIssueKind::DuplicatePath,
+                    LibraryIssueKind::InvalidNoteId,
+                    LibraryIssueKind::UnindexedNoteFile,
+                    LibraryIssueKind::OrphanTempFile,
+                    LibraryIssueKind::CorruptIndexBackup,
+                ],
+                |path| {
+                    deleted.push(relative_path(&dir, path));
+                    fs::remove_file(path).map_err(|e| e.to_string())
+                },
+            )?;
+            assert_eq!(
+                issue_kinds(&repair.remaining),
+                HashSet::from([LibraryIssueKind::CorruptCalendarCopy])
+            );
+            assert_eq!(repair.repaired.len(), 6);
+            assert_eq!(deleted, vec![format!("{INDEX_FILENAME}.corrupt-1.bak")]);
+            assert!(!dir.join(".hwan-note-write-old.tmp").exists());
+
+            let index = read_index(&dir)?;
+            assert_eq!(index.entries.len(), 3);
+            assert_eq!(index.entries["note-a"].relative_path, alpha);
+            assert_eq!(index.entries["noteb"].relative_path, "b.md");
+            let loose = &index.entries["note-loose"];
+            assert_eq!(loose.relative_path, "loose.md");
+            assert_eq!(loose.manual_title.as_deref(), Some("Recovered"));
+            assert_eq!(loose.is_pinned, Some(true));
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn library_repair_rebuilds_a_truncated_index_from_its_own_backup() {
+        let dir = make_temp_dir("library-repair-index");
+        let result = (|| -> Result<(), String> {
+            fs::write(dir.join("Alpha.md"), "# Alpha").map_err(|e| e.to_string())?;
+            fs::write(dir.join("Beta.md"), "# Beta").map_err(|e| e.to_string())?;
+            fs::write(
+                dir.join(INDEX_FILENAME),
+                r#"{"entries":{"note-a":{"relativePath":"Alpha.md","createdAt":1,"manualTitle":"Kept","isPinned":true},"note-b":{"relativePath":"Be"#,
+            )
+            .map_err(|e| e.to_string())?;
+
+            let report = check_library(&dir)?;
+            assert_eq!(
+                issue_kinds(&report.issues),
+                HashSet::from([LibraryIssueKind::CorruptIndex])
+            );
+            let backups = |dir: &Path| -> Result<usize, String> {
+                Ok(fs::read_dir(dir)
+                    .map_err(|e| e.to_string())?
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.file_name().to_string_lossy().contains(".corrupt-"))
+                    .count())
+            };
+            assert_eq!(backups(&dir)?, 0);
+
+            let repair = repair_library(&dir, &[LibraryIssueKind::CorruptIndex], |path| {
+                fs::remove_file(path).map_err(|e| e.to_string())
+            })?;
+            assert_eq!(repair.repaired.len(), 1);
+            assert_eq!(
+                issue_kinds(&repair.remaining),
+                HashSet::from([LibraryIssueKind::CorruptIndexBackup])
+            );
+            assert_eq!(backups(&dir)?, 1);
+
+            let index = read_index(&dir)?;
+            assert_eq!(index.entries.len(), 2);
+            let alpha = &index.entries["note-a"];
+            assert_eq!(alpha.relative_path, "Alpha.md");
+            assert_eq!(alpha.manual_title.as_deref(), Some("Kept"));
+            assert_eq!(alpha.is_pinned, Some(true));
+            assert!(index
+                .entries
+                .values()
+                .any(|entry| entry.relative_path == "Beta.md"));
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    fn library_files_containing(root: &Path, needle: &str) -> Result<Vec<PathBuf>, String> {
+        fn visit(dir: &Path, needle: &str, found: &mut Vec<PathBuf>) -> Result<(), String> {
+            for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+                let path = entry.map_err(|e| e.to_string())?.path();
+                if path.is_dir() {
+                    visit(&path, needle, found)?;
+                } else if String::from_utf8_lossy(&fs::read(&path).map_err(|e| e.to_string())?)
+                    .contains(needle)
+                {
+                    found.push(path);
+                }
+            }
+            Ok(())
+        }
+
+        let mut found = Vec::new();
+        visit(root, needle, &mut found)?;
+        Ok(found)
+    }
+
+    #[test]
+    fn encrypt_note_leaves_no_plaintext_in_library_and_hides_title_on_load() {
+        let dir = make_temp_dir("encrypt-note");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Launch codes", "first draft", Some("Vault")),
+            )?;
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Launch codes", "zebra quartz", Some("Vault")),
+            )?;
+            assert!(!note_history::list_note_revisions(&dir, "note-a")?.is_empty());
+
+            let unlocked = encrypt_note(&dir, "note-a", "correct horse")?;
+            assert!(unlocked.is_encrypted);
+            assert_eq!(unlocked.title, "Launch codes");
+            assert!(unlocked.markdown.contains("zebra quartz"));
+            assert!(unlocked.file_path.ends_with("encrypted-note.md"));
+            for needle in ["Launch codes", "zebra quartz", "first draft"] {
+                assert_eq!(
+                    library_files_containing(&dir, needle)?,
+                    Vec::<PathBuf>::new()
+                );
+            }
+            assert!(note_history::list_note_revisions(&dir, "note-a")?.is_empty());
+            assert!(read_index(&dir)?.entries["note-a"].manual_title.is_none());
+
+            let load = load_markdown_library(&dir);
+            assert_eq!(load.notes.len(), 1);
+            let listed = &load.notes[0];
+            assert!(listed.is_encrypted);
+            assert!(listed.title.is_empty() && listed.markdown.is_empty());
+            assert_eq!(listed.folder_path, "Vault");
+            assert!(encrypt_note(&dir, "note-a", "again").is_err());
+
+            let decrypted = decrypt_note(&dir, "note-a", "correct horse")?;
+            assert!(!decrypted.is_encrypted);
+            assert_eq!(decrypted.title, "Launch codes");
+            assert!(decrypted.is_title_manual);
+            assert!(fs::read_to_string(&decrypted.file_path)
+                .map_err(|e| e.to_string())?
+                .contains("zebra quartz"));
+            assert!(!lock_note(&dir, "note-a")?);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn locked_encrypted_notes_cannot_be_moved() {
+        let dir = make_temp_dir("encrypt-move");
+        let result = (|| -> Result<(), String> {
+            let retention = NoteHistoryRetention::default();
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Diary", "zebra quartz", Some("Vault")),
+            )?;
+            encrypt_note(&dir, "note-a", "correct horse")?;
+            assert!(lock_note(&dir, "note-a")?);
+
+            let ids = vec!["note-a".to_string()];
+            assert!(move_notes(&dir, &ids, Some("Archive"), &retention)
+                .unwrap_err()
+                .contains("locked"));
+            assert!(rename_folder(&dir, "Vault", "Safe", &retention)
+                .unwrap_err()
+                .contains("locked"));
+            assert!(read_index(&dir)?.entries["note-a"]
+                .relative_path
+                .starts_with("Vault/"));
+
+            unlock_note(&dir, "note-a", Some("correct horse"))?;
+            let moved = move_notes(&dir, &ids, Some("Archive"), &retention)?;
+            assert_eq!(moved.moved.len(), 1);
+            assert!(read_index(&dir)?.entries["note-a"]
+                .relative_path
+                .starts_with("Archive/"));
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn autosave_of_encrypted_note_requires_unlock_and_writes_ciphertext() {
+        let dir = make_temp_dir("encrypt-autosave");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Diary", "zebra quartz", None),
+            )?;
+            encrypt_note(&dir, "note-a", "correct horse")?;
+            assert!(lock_note(&dir, "note-a")?);
+
+            let edited = autosave_payload("note-a", "Diary", "violet harbor", None);
+            let locked_error = auto_save_markdown_note(&dir, &edited).unwrap_err();
+            assert!(locked_error.contains("locked"));
+            assert!(unlock_note(&dir, "note-a", None).is_err());
+            assert!(unlock_note(&dir, "note-a", Some("wrong")).is_err());
+
+            let unlocked = unlock_note(&dir, "note-a", Some("correct horse"))?;
+            assert_eq!(unlocked.title, "Diary");
+            let saved = auto_save_markdown_note(&dir, &edited)?;
+            assert!(saved.file_path.ends_with("encrypted-note.md"));
+            assert_eq!(
+                library_files_containing(&dir, "violet harbor")?,
+                Vec::<PathBuf>::new()
+            );
+            assert_eq!(
+                library_files_containing(&dir, "Diary")?,
+                Vec::<PathBuf>::new()
+            );
+            assert!(unlock_note(&dir, "note-a", None)?
+                .markdown
+                .contains("violet harbor"));
+
+            assert!(lock_note(&dir, "note-a")?);
+            assert!(auto_save_markdown_note(&dir, &edited).is_err());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn trashed_note_restores_with_index_metadata_beside_a_name_collision() {
+        let dir = make_temp_dir("trash-note");
+        let result = (|| -> Result<(), String> {
+            let payload = autosave_payload("note-a", "Plan", "first", Some("work"));
+            let saved = auto_save_markdown_note(&dir, &payload)?;
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-a", "Plan", "second", Some("work")),
+            )?;
+
+            assert!(trash_note(&dir, "note-a")?);
+            assert!(!Path::new(&saved.file_path).exists());
+            assert!(load_markdown_library(&dir).notes.is_empty());
+            let trash = note_trash::list_trash(&dir)?;
+            assert_eq!(trash.len(), 1);
+            assert_eq!(trash[0].title, "Plan");
+            assert_eq!(trash[0].folder_path, "work");
+            assert!(!note_history::list_note_revisions(&dir, "note-a")?.is_empty());
+
+            let occupant = auto_save_markdown_note(
+                &dir,
+                &autosave_payload("note-b", "Plan", "other", Some("work")),
+            )?;
+            assert_eq!(occupant.file_path, saved.file_path);
+            let restored =
+                restore_from_trash(&dir, &trash[0].trash_id, &NoteHistoryRetention::default())?;
+            let note = &restored.restored_notes[0];
+            assert_eq!(note.note_id, "note-a");
+            assert_eq!(note.title, "Plan");
+            assert!(note.is_title_manual && note.is_pinned);
+            assert_eq!(note.folder_path, "work");
+            assert_eq!(note.created_at, saved.created_at);
+            assert_ne!(note.file_path, occupant.file_path);
+            assert!(note.markdown.contains("second"));
+            assert!(note_trash::list_trash(&dir)?.is_empty());
+
+            assert!(trash_note(&dir, "note-a")?);
+            let trash_id = note_trash::list_trash(&dir)?[0].trash_id.clone();
+            assert!(purge_expired_trash(&dir, &note_trash::TrashSettings::default())?.is_empty());
+            assert_eq!(
+                purge_trash(&dir, std::slice::from_ref(&trash_id))?,
+                vec![trash_id]
+            );
+            assert!(note_trash::list_trash(&dir)?.is_empty());
+            assert!(note_history::list_note_revisions(&dir, "note-a")?.is_empty());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn deleted_folder_restores_its_tree_and_moves_its_notes_back() {
+        let dir = make_temp_dir("trash-folder");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("top", "Top", "# Top", Some("alpha")),
+            )?;
+            auto_save_markdown_note(
+                &dir,
+                &autosave_payload("deep", "Deep", "# Deep", Some("alpha/sub")),
+            )?;
+            create_folder(&dir, "alpha/empty")?;
+
+            let deleted = delete_folder(&dir, "alpha", &NoteHistoryRetention::default())?;
+            assert_eq!(deleted.moved_note_ids.len(), 2);
+            assert!(!dir.join("alpha").exists());
+            let trash = note_trash::list_trash(&dir)?;
+            assert_eq!(trash[0].trash_id, deleted.trash_id);
+            assert_eq!(trash[0].kind, note_trash::TrashItemKind::Folder);
+            assert_eq!(trash[0].note_count, 2);
+
+            fs::create_dir(dir.join("alpha")).map_err(|e| e.to_string())?;
+            let restored =
+                restore_from_trash(&dir, &deleted.trash_id, &NoteHistoryRetention::default())?;
+            assert_eq!(restored.moved.len(), 2);
+            assert!(restored.folders.contains(&"alpha-2/empty".to_string()));
+            let folders: HashMap<_, _> = load_markdown_library(&dir)
+                .notes
+                .into_iter()
+                .map(|note| (note.note_id, note.folder_path))
+                .collect();
+            assert_eq!(folders["top"], "alpha-2");
+            assert_eq!(folders["deep"], "alpha-2/sub");
+            assert!(note_trash::list_trash(&dir)?.is_empty());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
 }
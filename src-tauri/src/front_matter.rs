@@ -0,0 +1,371 @@
+use serde_yaml::{Mapping, Value};
+
+const FRONT_MATTER_FENCE: &str = "---";
+const FRONT_MATTER_END_ALT: &str = "...";
+
+const TITLE_KEY: &str = "title";
+const TAGS_KEY: &str = "tags";
+const PINNED_KEY: &str = "pinned";
+const CREATED_KEY: &str = "created";
+const ALIASES_KEY: &str = "aliases";
+
+const MILLIS_PER_SECOND: u64 = 1_000;
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// ── Types ──
+
+/// Note metadata stored in a leading YAML block. Keys hwanNote does not know
+/// about are kept in `extra` so other tools' metadata survives a save.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct FrontMatter {
+    pub(crate) title: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) pinned: Option<bool>,
+    pub(crate) created: Option<u64>,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) extra: Mapping,
+}
+
+// ── Parsing ──
+
+/// Splits a leading front matter block from `markdown`. The input must use
+/// `\n` line endings. Blocks that are not a YAML mapping are left in the body
+/// untouched, so a note that merely starts with a horizontal rule stays intact.
+pub(crate) fn split_front_matter(markdown: &str) -> (Option<FrontMatter>, &str) {
+    let Some(after_open) = markdown
+        .strip_prefix(FRONT_MATTER_FENCE)
+        .and_then(|rest| rest.strip_prefix('\n'))
+    else {
+        return (None, markdown);
+    };
+
+    let mut offset = 0;
+    for line in after_open.split_inclusive('\n') {
+        let content = line.strip_suffix('\n').unwrap_or(line).trim_end();
+        if content == FRONT_MATTER_FENCE || content == FRONT_MATTER_END_ALT {
+            let yaml = &after_open[..offset];
+            let body = &after_open[offset + line.len()..];
+            return match parse_front_matter_yaml(yaml) {
+                Some(front_matter) => (Some(front_matter), body),
+                None => (None, markdown),
+            };
+        }
+        offset += line.len();
+    }
+
+    (None, markdown)
+}
+
+fn parse_front_matter_yaml(yaml: &str) -> Option<FrontMatter> {
+    let mut mapping = if yaml.trim().is_empty() {
+        Mapping::new()
+    } else {
+        match serde_yaml::from_str::<Value>(yaml).ok()? {
+            Value::Mapping(mapping) => mapping,
+            Value::Null => Mapping::new(),
+            _ => return None,
+        }
+    };
+
+    let title = mapping
+        .remove(TITLE_KEY)
+        .and_then(|value| scalar_to_string(&value));
+    let tags = mapping
+        .remove(TAGS_KEY)
+        .map(|value| string_list(&value, true))
+        .unwrap_or_default();
+    let pinned = mapping.remove(PINNED_KEY).and_then(|value| value.as_bool());
+    let created = mapping
+        .remove(CREATED_KEY)
+        .and_then(|value| parse_created(&value));
+    let aliases = mapping
+        .remove(ALIASES_KEY)
+        .map(|value| string_list(&value, false))
+        .unwrap_or_default();
+
+    Some(FrontMatter {
+        title,
+        tags,
+        pinned,
+        created,
+        aliases,
+        extra: mapping,
+    })
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(flag) => flag.to_string(),
+        _ => return None,
+    };
+    let trimmed = text.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+/// Accepts either a YAML sequence or a single comma-separated string, which is
+/// how most Markdown tools let users write short lists.
+fn string_list(value: &Value, strip_hash: bool) -> Vec<String> {
+    let raw: Vec<String> = match value {
+        Value::Sequence(items) => items.iter().filter_map(scalar_to_string).collect(),
+        other => scalar_to_string(other)
+            .map(|text| text.split(',').map(str::to_string).collect())
+            .unwrap_or_default(),
+    };
+
+    let mut values: Vec<String> = Vec::new();
+    for item in raw {
+        let item = item.trim();
+        let item = if strip_hash {
+            item.trim_start_matches('#')
+        } else {
+            item
+        };
+        if !item.is_empty() && !values.iter().any(|existing| existing == item) {
+            values.push(item.to_string());
+        }
+    }
+    values
+}
+
+fn parse_created(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(text) => parse_timestamp(text.trim()),
+        _ => None,
+    }
+}
+
+// ── Rendering ──
+
+/// Prepends `front_matter` to `body` as a fenced YAML block using `\n` line
+/// endings. Known keys come first in a fixed order, followed by unknown keys
+/// in their original order.
+pub(crate) fn render_front_matter(front_matter: &FrontMatter, body: &str) -> String {
+    let mut mapping = Mapping::new();
+    if let Some(title) = &front_matter.title {
+        mapping.insert(TITLE_KEY.into(), title.clone().into());
+    }
+    if front_matter.pinned == Some(true) {
+        mapping.insert(PINNED_KEY.into(), true.into());
+    }
+    if let Some(created) = front_matter.created {
+        mapping.insert(CREATED_KEY.into(), format_timestamp(created).into());
+    }
+    if !front_matter.tags.is_empty() {
+        mapping.insert(TAGS_KEY.into(), string_sequence(&front_matter.tags));
+    }
+    if !front_matter.aliases.is_empty() {
+        mapping.insert(ALIASES_KEY.into(), string_sequence(&front_matter.aliases));
+    }
+    for (key, value) in &front_matter.extra {
+        if !mapping.contains_key(key) {
+            mapping.insert(key.clone(), value.clone());
+        }
+    }
+
+    if mapping.is_empty() {
+        return body.to_string();
+    }
+    let yaml = serde_yaml::to_string(&Value::Mapping(mapping)).unwrap_or_default();
+    format!("{FRONT_MATTER_FENCE}\n{yaml}{FRONT_MATTER_FENCE}\n{body}")
+}
+
+fn string_sequence(values: &[String]) -> Value {
+    Value::Sequence(values.iter().cloned().map(Value::String).collect())
+}
+
+// ── Timestamps ──
+
+/// Formats epoch milliseconds as an RFC 3339 UTC timestamp with second
+/// precision, the form other Markdown tools read as a date.
+pub(crate) fn format_timestamp(millis: u64) -> String {
+    let seconds = millis / MILLIS_PER_SECOND;
+    let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY) as i64);
+    let seconds_of_day = seconds % SECONDS_PER_DAY;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3_600,
+        (seconds_of_day / 60) % 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Parses `YYYY-MM-DD`, `YYYY-MM-DDTHH:MM[:SS[.fff]]`, optionally followed by
+/// `Z` or a `±HH:MM` offset, into epoch milliseconds.
+pub(crate) fn parse_timestamp(text: &str) -> Option<u64> {
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time.trim())),
+        None => (text, None),
+    };
+
+    let mut date_parts = date.splitn(3, '-');
+    let year = date_parts.next()?.parse::<i64>().ok()?;
+    let month = date_parts.next()?.parse::<u32>().ok()?;
+    let day = date_parts.next()?.parse::<u32>().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut millis =
+        days_from_civil(year, month, day) * (SECONDS_PER_DAY * MILLIS_PER_SECOND) as i64;
+
+    if let Some(time) = time {
+        let (clock, offset_minutes) = split_utc_offset(time)?;
+        let mut clock_parts = clock.splitn(3, ':');
+        let hours = clock_parts.next()?.parse::<i64>().ok()?;
+        let minutes = clock_parts.next()?.parse::<i64>().ok()?;
+        let (seconds, fraction_millis) = match clock_parts.next() {
+            Some(seconds) => match seconds.split_once('.') {
+                Some((whole, fraction)) => {
+                    if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                    }
+                    let digits = format!("{fraction:0<3}");
+                    (whole.parse::<i64>().ok()?, digits[..3].parse::<i64>().ok()?)
+                }
+                None => (seconds.parse::<i64>().ok()?, 0),
+            },
+            None => (0, 0),
+        };
+        if hours > 23 || minutes > 59 || seconds > 60 {
+            return None;
+        }
+        millis +=
+            ((hours * 60 + minutes - offset_minutes) * 60 + seconds) * 1_000 + fraction_millis;
+    }
+
+    u64::try_from(millis).ok()
+}
+
+fn split_utc_offset(time: &str) -> Option<(&str, i64)> {
+    if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        return Some((clock, 0));
+    }
+    let Some(sign_index) = time.rfind(['+', '-']) else {
+        return Some((time, 0));
+    };
+    let (clock, offset) = time.split_at(sign_index);
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let (hours, minutes) = offset[1..].split_once(':').unwrap_or((&offset[1..], "0"));
+    let hours = hours.parse::<i64>().ok()?;
+    let minutes = minutes.parse::<i64>().ok()?;
+    Some((clock, sign * (hours * 60 + minutes)))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Howard Hinnant's days-from-civil algorithms, valid for the proleptic
+// Gregorian calendar.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_front_matter_reads_known_keys_and_keeps_unknown_ones() {
+        let markdown = "---\ntitle: Plan\npinned: true\ncreated: 2024-03-05T06:07:08Z\ntags: [work, \"#urgent\"]\naliases: Roadmap\nsource: obsidian\n---\n# Body\n";
+        let (front_matter, body) = split_front_matter(markdown);
+        let front_matter = front_matter.unwrap();
+
+        assert_eq!(body, "# Body\n");
+        assert_eq!(front_matter.title.as_deref(), Some("Plan"));
+        assert_eq!(front_matter.pinned, Some(true));
+        assert_eq!(front_matter.created, Some(1_709_618_828_000));
+        assert_eq!(front_matter.tags, vec!["work", "urgent"]);
+        assert_eq!(front_matter.aliases, vec!["Roadmap"]);
+        assert_eq!(
+            front_matter.extra.get("source"),
+            Some(&Value::String("obsidian".to_string()))
+        );
+
+        let rendered = render_front_matter(&front_matter, body);
+        let (reparsed, reparsed_body) = split_front_matter(&rendered);
+        assert_eq!(reparsed.as_ref(), Some(&front_matter));
+        assert_eq!(reparsed_body, body);
+    }
+
+    #[test]
+    fn split_front_matter_leaves_non_mapping_blocks_in_the_body() {
+        for markdown in [
+            "---\nnot: [closed\n---\nbody",
+            "---\n- a\n- b\n---\nbody",
+            "---\nno closing fence",
+            "--- \ntitle: x\n---\n",
+        ] {
+            let (front_matter, body) = split_front_matter(markdown);
+            assert!(front_matter.is_none(), "{markdown:?}");
+            assert_eq!(body, markdown);
+        }
+    }
+
+    #[test]
+    fn timestamps_round_trip_and_accept_common_forms() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400_000), "2000-02-29T00:00:00Z");
+        assert_eq!(parse_timestamp("2000-02-29"), Some(951_782_400_000));
+        assert_eq!(
+            parse_timestamp("2000-02-29T09:00+09:00"),
+            Some(951_782_400_000)
+        );
+        assert_eq!(
+            parse_timestamp("2000-02-29 00:00:01.5"),
+            Some(951_782_401_500)
+        );
+        assert_eq!(parse_timestamp("2001-02-29"), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+
+        let millis = 1_735_689_599_000;
+        assert_eq!(parse_timestamp(&format_timestamp(millis)), Some(millis));
+    }
+
+    #[test]
+    fn timestamps_with_non_digit_fractions_are_rejected_without_panicking() {
+        assert_eq!(parse_timestamp("2024-01-01T10:00:00.éé"), None);
+        assert_eq!(parse_timestamp("2024-01-01T10:00:00.1é"), None);
+        assert_eq!(parse_timestamp("2024-01-01T10:00:00."), None);
+        assert_eq!(
+            parse_timestamp("2024-01-01T10:00:00.12345Z"),
+            parse_timestamp("2024-01-01T10:00:00.123Z")
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use crate::file_manager::{
+    migrate_notes, now_millis, write_index, MigrationResult, NoteIndex, NoteIndexEntry,
+};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Hands out library-relative note paths that are unique within one import.
+/// Names are compared case-insensitively, as on Windows and macOS.
+#[derive(Default)]
+pub(crate) struct UniqueNotePaths {
+    used: HashSet<String>,
+}
+
+impl UniqueNotePaths {
+    /// Path for a note named `file_stem` in `folder`, with `-2`, `-3`, ...
+    /// appended when an earlier note took the name.
+    pub(crate) fn allocate(&mut self, folder: &str, file_stem: &str) -> String {
+        let prefix = if folder.is_empty() {
+            String::new()
+        } else {
+            format!("{folder}/")
+        };
+        let mut candidate = format!("{prefix}{file_stem}.md");
+        let mut suffix = 2;
+        while !self.used.insert(candidate.to_lowercase()) {
+            candidate = format!("{prefix}{file_stem}-{suffix}.md");
+            suffix += 1;
+        }
+        candidate
+    }
+}
+
+/// A note an importer has converted, ready to be written into staging.
+pub(crate) struct StagedNote<'a> {
+    pub markdown: &'a str,
+    pub created_at: u64,
+    /// Becomes the file's modification time, which is the note's updated time.
+    pub updated_at: Option<u64>,
+    pub manual_title: Option<String>,
+}
+
+/// A private library in the temp directory that importers fill with converted
+/// notes and then merge into the real library with `migrate_notes`, so every
+/// import follows the same trusted-root and no-overwrite rules. The directory
+/// is removed when the staging is dropped.
+pub(crate) struct ImportStaging {
+    dir: PathBuf,
+    index: NoteIndex,
+    id_prefix: String,
+    next_id: u64,
+}
+
+impl ImportStaging {
+    pub(crate) fn create(kind: &str) -> Result<Self, String> {
+        let now = now_millis();
+        let dir =
+            std::env::temp_dir().join(format!("hwan-note-{kind}-{}-{now}", std::process::id()));
+        fs::create_dir(&dir).map_err(|error| {
+            format!(
+                "create_import_staging failed for {}: {error}",
+                dir.display()
+            )
+        })?;
+        Ok(Self {
+            dir,
+            index: NoteIndex {
+                entries: HashMap::new(),
+            },
+            id_prefix: format!("note-{now}-{kind}"),
+            next_id: 0,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.index.entries.is_empty()
+    }
+
+    fn full_path(&self, relative_path: &str) -> PathBuf {
+        relative_path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .fold(self.dir.clone(), |path, segment| path.join(segment))
+    }
+
+    pub(crate) fn create_folder(&self, folder: &str) -> Result<(), String> {
+        let path = self.full_path(folder);
+        fs::create_dir_all(&path).map_err(|error| {
+            format!(
+                "create_import_staging failed for {}: {error}",
+                path.display()
+            )
+        })
+    }
+
+    /// Writes a note at a path from `UniqueNotePaths` and indexes it under a
+    /// fresh id.
+    pub(crate) fn add_note(
+        &mut self,
+        relative_path: &str,
+        note: StagedNote<'_>,
+    ) -> Result<(), String> {
+        if let Some((folder, _)) = relative_path.rsplit_once('/') {
+            self.create_folder(folder)?;
+        }
+        let path = self.full_path(relative_path);
+        let file = fs::File::create(&path)
+            .and_then(|mut file| {
+                file.write_all(note.markdown.as_bytes())?;
+                Ok(file)
+            })
+            .map_err(|error| {
+                format!(
+                    "write_import_staging failed for {}: {error}",
+                    path.display()
+                )
+            })?;
+        if let Some(updated_at) = note.updated_at {
+            let modified = UNIX_EPOCH + Duration::from_millis(updated_at);
+            if let Err(error) = file.set_modified(modified) {
+                tracing::warn!(
+                    "set_import_note_time failed for {}: {error}",
+                    path.display()
+                );
+            }
+        }
+
+        self.next_id += 1;
+        self.index.entries.insert(
+            format!("{}{}", self.id_prefix, self.next_id),
+            NoteIndexEntry {
+                relative_path: relative_path.to_string(),
+                created_at: note.created_at,
+                manual_title: note.manual_title,
+                is_pinned: None,
+                seen_by: BTreeMap::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Merges the staged notes, folders and referenced attachments into the
+    /// library without overwriting anything there.
+    pub(crate) fn merge_into(&self, auto_save_dir: &Path) -> Result<MigrationResult, String> {
+        write_index(&self.dir, &self.index)?;
+        migrate_notes(&self.dir, auto_save_dir)
+    }
+}
+
+impl Drop for ImportStaging {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_dir_all(&self.dir) {
+            tracing::warn!(
+                "Failed to remove import staging {}: {error}",
+                self.dir.display()
+            );
+        }
+    }
+}
@@ -1,7 +1,27 @@
 mod atomic_file;
+mod attachments;
 mod commands;
 mod config_manager;
+mod daily_notes;
+mod enex_import;
+mod fallback_sync;
 mod file_manager;
+mod front_matter;
+mod import_staging;
+mod library_backup;
+mod library_snapshot;
+mod library_watcher;
+mod note_crypto;
+mod note_history;
+mod note_links;
+mod note_merge;
+mod note_templates;
+mod note_trash;
+mod search_index;
+mod site_export;
+mod sync_conflicts;
+mod text_encoding;
+mod vault_import;
 
 use std::collections::HashSet;
 use std::path::PathBuf;
@@ -9,14 +29,22 @@ use std::path::PathBuf;
 use commands::*;
 use tauri::{Emitter, Manager};
 
-fn collect_txt_open_intents(argv: &[String], cwd: Option<&str>) -> Vec<PathBuf> {
+fn collect_open_intents(
+    argv: &[String],
+    cwd: Option<&str>,
+    text_extensions: &[String],
+) -> Vec<PathBuf> {
     let base_dir = cwd.map(PathBuf::from);
     let mut seen = HashSet::new();
     let mut intents = Vec::new();
 
     for arg in argv.iter().skip(1) {
-        let normalized = match file_manager::normalize_external_txt_path(arg, base_dir.as_deref()) {
-            Ok(path) => path,
+        let normalized = match file_manager::normalize_external_document_path(
+            arg,
+            base_dir.as_deref(),
+            text_extensions,
+        ) {
+            Ok((path, _)) => path,
             Err(_) => continue,
         };
 
@@ -48,22 +76,91 @@ fn enqueue_open_intents(app: &tauri::AppHandle, paths: Vec<PathBuf>, emit_event:
     }
 }
 
+/// Release builds use the Windows GUI subsystem and start without a console;
+/// attach to the invoking shell's so a command-line export can report there.
+#[cfg(windows)]
+fn attach_parent_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // Fails harmlessly when started without a parent console, as from Explorer.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+/// Runs a command-line site export and reports the outcome on the console.
+/// Returns the process exit code.
+fn report_cli_site_export(
+    config_dir: &std::path::Path,
+    request: Result<site_export::SiteExportCliRequest, String>,
+) -> i32 {
+    match request.and_then(|request| export_site_from_cli(config_dir, request)) {
+        Ok(result) => {
+            println!(
+                "Exported {} notes in {} folders with {} attachments to {}",
+                result.note_count, result.folder_count, result.attachment_count, result.output_dir
+            );
+            if result.unresolved_link_count > 0 {
+                println!(
+                    "{} links point at notes or attachments outside the export",
+                    result.unresolved_link_count
+                );
+            }
+            0
+        }
+        Err(error) => {
+            eprintln!("Site export failed: {error}");
+            1
+        }
+    }
+}
+
 pub fn run() {
     tracing_subscriber::fmt::init();
 
+    // A command-line export runs in the invoking process before the app is
+    // built, so the caller sees its output and exit code and no window opens;
+    // it never hands off to a running instance.
+    let context = tauri::generate_context!();
+    let startup_args: Vec<String> = std::env::args().collect();
+    let cli_export = std::env::current_dir()
+        .map_err(|error| format!("read_current_dir failed: {error}"))
+        .map(|cwd| site_export::parse_cli_request(&startup_args, &cwd))
+        .unwrap_or_else(|error| Some(Err(error)));
+    if let Some(request) = cli_export {
+        attach_parent_console();
+        // Where Tauri keeps the app config: the identifier under the
+        // platform config directory.
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(&context.config().identifier);
+        std::process::exit(report_cli_site_export(&config_dir, request));
+    }
+
     tauri::Builder::default()
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
             focus_main_window(app);
-            let intents = collect_txt_open_intents(&argv, Some(cwd.as_str()));
+            let text_extensions = config_manager::get_text_extensions(app);
+            let intents = collect_open_intents(&argv, Some(cwd.as_str()), &text_extensions);
             enqueue_open_intents(app, intents, true);
         }))
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .register_asynchronous_uri_scheme_protocol("hwan-asset", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            std::thread::spawn(move || {
+                responder.respond(commands::serve_attachment(&app, &request));
+            });
+        })
         .manage(PendingUpdate::default())
         .manage(DownloadedUpdate::default())
         .manage(PendingOpenIntents::default())
         .manage(CalendarWriteGuard::default())
+        .manage(LibraryWatcherState::default())
         .invoke_handler(tauri::generate_handler![
             cmd_window_minimize,
             cmd_window_toggle_maximize,
@@ -74,7 +171,42 @@ pub fn run() {
             cmd_note_list,
             cmd_note_auto_save,
             cmd_note_load_all,
+            cmd_note_search,
+            cmd_note_links,
             cmd_note_delete,
+            cmd_note_move,
+            cmd_note_batch,
+            cmd_note_encrypt,
+            cmd_note_decrypt,
+            cmd_note_unlock,
+            cmd_note_lock,
+            cmd_template_list,
+            cmd_template_save,
+            cmd_note_create_from_template,
+            cmd_daily_note_open,
+            cmd_daily_note_adjacent,
+            cmd_trash_list,
+            cmd_trash_restore,
+            cmd_trash_purge,
+            cmd_note_history_list,
+            cmd_note_history_read,
+            cmd_note_history_restore,
+            cmd_snapshot_list,
+            cmd_snapshot_create,
+            cmd_snapshot_restore_library,
+            cmd_snapshot_restore_notes,
+            cmd_site_export,
+            cmd_library_backup_export,
+            cmd_library_backup_import,
+            cmd_library_import_enex,
+            cmd_library_import_vault,
+            cmd_library_resolve_sync_conflict,
+            cmd_library_check,
+            cmd_library_repair,
+            cmd_attachment_save,
+            cmd_attachment_import,
+            cmd_attachment_find_unreferenced,
+            cmd_attachment_remove_unreferenced,
             cmd_folder_list,
             cmd_folder_create,
             cmd_folder_rename,
@@ -87,15 +219,27 @@ pub fn run() {
             cmd_settings_browse_autosave_dir,
             cmd_settings_set_autosave_dir,
             cmd_settings_get_autosave_dir,
+            cmd_settings_get_note_history,
+            cmd_settings_set_note_history,
+            cmd_settings_get_trash,
+            cmd_settings_set_trash,
+            cmd_settings_get_daily_notes,
+            cmd_settings_set_daily_notes,
+            cmd_settings_get_text_extensions,
+            cmd_settings_set_text_extensions,
+            cmd_settings_get_snapshots,
+            cmd_settings_set_snapshots,
             cmd_updater_check,
             cmd_updater_download,
             cmd_updater_install,
             cmd_shell_open_external,
             cmd_cloud_detect_providers,
+            cmd_cloud_set_custom_folder,
             cmd_cloud_sync_enable,
             cmd_cloud_sync_disable,
             cmd_cloud_sync_status,
             cmd_cloud_sync_set_active_source,
+            cmd_cloud_reconcile_fallback,
             cmd_calendar_load,
             cmd_calendar_backup,
             cmd_calendar_preserve_recovery_copy,
@@ -105,7 +249,7 @@ pub fn run() {
             cmd_session_save,
             cmd_session_load,
         ])
-        .setup(|app| {
+        .setup(move |app| {
             let handle = app.handle().clone();
 
             // Migrate legacy Electron config on first launch
@@ -116,10 +260,15 @@ pub fn run() {
                 tracing::warn!("Failed to migrate legacy cloud sync config: {}", e);
             }
 
-            let startup_args: Vec<String> = std::env::args().collect();
-            let startup_intents = collect_txt_open_intents(&startup_args, None);
+            note_merge::set_machine_id(config_manager::get_or_create_machine_id(&handle));
+
+            let text_extensions = config_manager::get_text_extensions(&handle);
+            let startup_intents = collect_open_intents(&startup_args, None, &text_extensions);
             enqueue_open_intents(&handle, startup_intents, false);
 
+            let snapshot_handle = handle.clone();
+            std::thread::spawn(move || commands::run_snapshot_scheduler(snapshot_handle));
+
             // Check for updates after 3-second delay (production only)
             std::thread::spawn(move || {
                 std::thread::sleep(std::time::Duration::from_secs(3));
@@ -130,6 +279,6 @@ pub fn run() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
 }
@@ -0,0 +1,741 @@
+use crate::attachments::{self, validate_attachment_contents};
+use crate::file_manager::{
+    library_is_empty, migrate_calendar_file, migrate_notes, now_millis, read_library_backup_source,
+    sha256_hex, MigratedNote, MigrationOutcome, NoteIndex, TrustedLibraryRoot, CALENDAR_FILENAME,
+    INDEX_FILENAME,
+};
+use crate::front_matter::civil_from_days;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const BACKUP_FORMAT: &str = "hwan-note-backup";
+const BACKUP_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const INDEX_ENTRY: &str = "index.json";
+const CALENDAR_ENTRY: &str = "calendar.json";
+const NOTES_PREFIX: &str = "notes/";
+const ATTACHMENTS_PREFIX: &str = "attachments/";
+/// Refuse entries that would inflate beyond this, so a hostile bundle cannot
+/// exhaust memory during validation.
+const MAX_ENTRY_SIZE: u64 = 512 * 1024 * 1024;
+
+// ── Types ──
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BackupFileKind {
+    Note,
+    Attachment,
+    Index,
+    Calendar,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupManifestFile {
+    path: String,
+    kind: BackupFileKind,
+    size: u64,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupManifest {
+    format: String,
+    version: u32,
+    created_at: u64,
+    folders: Vec<String>,
+    files: Vec<BackupManifestFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupExportResult {
+    pub bundle_path: String,
+    pub note_count: usize,
+    pub folder_count: usize,
+    pub calendar_included: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupImportMode {
+    Restore,
+    Merge,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupImportResult {
+    pub mode: BackupImportMode,
+    pub restored: Vec<MigratedNote>,
+    pub renamed: Vec<MigratedNote>,
+    pub skipped: Vec<MigratedNote>,
+    pub calendar_restored: bool,
+    pub calendar_skipped: bool,
+}
+
+struct ZipEntry {
+    name: String,
+    data: Vec<u8>,
+}
+
+// ── Zip ──
+
+/// Converts epoch milliseconds (UTC) to a zip entry timestamp.
+fn zip_date_time(millis: u64) -> zip::DateTime {
+    let seconds = (millis / 1000) as i64;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let second_of_day = seconds.rem_euclid(86_400);
+    zip::DateTime::from_date_and_time(
+        year.clamp(1980, 2107) as u16,
+        month as u8,
+        day as u8,
+        (second_of_day / 3600) as u8,
+        ((second_of_day % 3600) / 60) as u8,
+        (second_of_day % 60) as u8,
+    )
+    .unwrap_or_default()
+}
+
+/// Streams `entries` into `file` as deflated zip members and syncs it.
+fn write_zip<'a>(
+    file: fs::File,
+    entries: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+    modified_at: u64,
+) -> Result<(), String> {
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(zip_date_time(modified_at));
+    let mut writer = ZipWriter::new(file);
+    for (name, data) in entries {
+        writer
+            .start_file(name, options)
+            .map_err(|error| error.to_string())
+            .and_then(|_| writer.write_all(data).map_err(|error| error.to_string()))
+            .map_err(|error| format!("write_backup_bundle failed for {name}: {error}"))?;
+    }
+    writer
+        .finish()
+        .map_err(|error| error.to_string())
+        .and_then(|file| file.sync_all().map_err(|error| error.to_string()))
+        .map_err(|error| format!("write_backup_bundle failed: {error}"))
+}
+
+/// Reads every entry of a zip archive; the zip reader checks each entry's CRC
+/// as it is read. Encrypted entries are refused.
+fn read_zip(bundle_path: &Path) -> Result<Vec<ZipEntry>, String> {
+    let file = fs::File::open(bundle_path).map_err(|error| {
+        format!(
+            "read_backup_bundle failed for {}: {error}",
+            bundle_path.display()
+        )
+    })?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|error| format!("read_backup_bundle failed: {error}"))?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|error| format!("read_backup_bundle failed: {error}"))?;
+        let name = entry.name().to_string();
+        if entry.size() > MAX_ENTRY_SIZE {
+            return Err(format!(
+                "read_backup_bundle failed for {name}: entry is too large"
+            ));
+        }
+        let mut data = Vec::new();
+        entry
+            .by_ref()
+            .take(MAX_ENTRY_SIZE + 1)
+            .read_to_end(&mut data)
+            .map_err(|error| format!("read_backup_bundle failed for {name}: {error}"))?;
+        if data.len() as u64 != entry.size() {
+            return Err(format!(
+                "read_backup_bundle failed for {name}: entry data is corrupt"
+            ));
+        }
+        entries.push(ZipEntry { name, data });
+    }
+    Ok(entries)
+}
+
+// ── Export ──
+
+/// Writes the library to a single zip bundle: every note with its folder
+/// path, the note index, `calendar.json`, and a manifest with a SHA-256 for
+/// each file. The bundle is written beside its destination and renamed into
+/// place once complete.
+pub fn export_library_backup(
+    auto_save_dir: &Path,
+    bundle_path: &Path,
+) -> Result<BackupExportResult, String> {
+    let source = read_library_backup_source(auto_save_dir)?;
+    let mut entries = Vec::new();
+    let mut files = Vec::new();
+    let mut push = |name: String, kind: BackupFileKind, data: Vec<u8>| {
+        files.push(BackupManifestFile {
+            path: name.clone(),
+            kind,
+            size: data.len() as u64,
+            sha256: sha256_hex(&data),
+        });
+        entries.push(ZipEntry { name, data });
+    };
+
+    let note_count = source.notes.len();
+    for (relative_path, data) in source.notes {
+        push(
+            format!("{NOTES_PREFIX}{relative_path}"),
+            BackupFileKind::Note,
+            data,
+        );
+    }
+    for (file_name, data) in source.attachments {
+        push(
+            format!("{ATTACHMENTS_PREFIX}{file_name}"),
+            BackupFileKind::Attachment,
+            data,
+        );
+    }
+    push(
+        INDEX_ENTRY.to_string(),
+        BackupFileKind::Index,
+        source.index_bytes,
+    );
+    let calendar_included = source.calendar_bytes.is_some();
+    if let Some(calendar) = source.calendar_bytes {
+        push(
+            CALENDAR_ENTRY.to_string(),
+            BackupFileKind::Calendar,
+            calendar,
+        );
+    }
+
+    let created_at = now_millis();
+    let manifest = BackupManifest {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at,
+        folders: source.folders.clone(),
+        files,
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)
+        .map_err(|error| format!("serialize_backup_manifest failed: {error}"))?;
+
+    let file_name = bundle_path.file_name().ok_or_else(|| {
+        format!(
+            "write_backup_bundle failed for {}: a file name is required",
+            bundle_path.display()
+        )
+    })?;
+    let temp_path = bundle_path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let written = fs::File::create(&temp_path)
+        .map_err(|error| error.to_string())
+        .and_then(|file| {
+            write_zip(
+                file,
+                std::iter::once((MANIFEST_ENTRY, manifest_bytes.as_slice())).chain(
+                    entries
+                        .iter()
+                        .map(|entry| (entry.name.as_str(), entry.data.as_slice())),
+                ),
+                created_at,
+            )
+        })
+        .and_then(|_| fs::rename(&temp_path, bundle_path).map_err(|error| error.to_string()));
+    if let Err(error) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!(
+            "write_backup_bundle failed for {}: {error}",
+            bundle_path.display()
+        ));
+    }
+
+    Ok(BackupExportResult {
+        bundle_path: bundle_path.to_string_lossy().to_string(),
+        note_count,
+        folder_count: source.folders.len(),
+        calendar_included,
+    })
+}
+
+// ── Import ──
+
+/// Accepts a bundle-relative library path only if it stays inside the library
+/// and avoids hidden app directories.
+fn validate_bundle_relative_path(path: &str) -> Result<(), String> {
+    let valid = !path.is_empty()
+        && !path.contains(['\\', ':', '\0'])
+        && path.split('/').all(|segment| {
+            !segment.is_empty() && !segment.starts_with('.') && segment.trim() == segment
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "validate_backup_bundle failed for {path}: unsafe path"
+        ))
+    }
+}
+
+/// Checks the manifest against the archive contents and returns the files by
+/// bundle path. Every archive entry must be listed, and every listed file must
+/// be present with the recorded size and SHA-256.
+fn validate_bundle(
+    entries: Vec<ZipEntry>,
+) -> Result<(BackupManifest, BTreeMap<String, Vec<u8>>), String> {
+    let mut contents = BTreeMap::new();
+    for entry in entries {
+        if contents.insert(entry.name.clone(), entry.data).is_some() {
+            return Err(format!(
+                "validate_backup_bundle failed for {}: duplicate entry",
+                entry.name
+            ));
+        }
+    }
+    let manifest_bytes = contents
+        .remove(MANIFEST_ENTRY)
+        .ok_or_else(|| "validate_backup_bundle failed: manifest.json is missing".to_string())?;
+    let manifest: BackupManifest = serde_json::from_slice(&manifest_bytes)
+        .map_err(|error| format!("validate_backup_bundle failed for {MANIFEST_ENTRY}: {error}"))?;
+    if manifest.format != BACKUP_FORMAT || manifest.version != BACKUP_VERSION {
+        return Err(format!(
+            "validate_backup_bundle failed: unsupported bundle format {} version {}",
+            manifest.format, manifest.version
+        ));
+    }
+
+    let mut listed = HashSet::new();
+    for file in &manifest.files {
+        if !listed.insert(file.path.as_str()) {
+            return Err(format!(
+                "validate_backup_bundle failed for {}: listed twice",
+                file.path
+            ));
+        }
+        match file.kind {
+            BackupFileKind::Index if file.path == INDEX_ENTRY => {}
+            BackupFileKind::Calendar if file.path == CALENDAR_ENTRY => {}
+            BackupFileKind::Note => {
+                let relative = file
+                    .path
+                    .strip_prefix(NOTES_PREFIX)
+                    .filter(|relative| relative.to_lowercase().ends_with(".md"))
+                    .ok_or_else(|| {
+                        format!(
+                            "validate_backup_bundle failed for {}: not a note path",
+                            file.path
+                        )
+                    })?;
+                validate_bundle_relative_path(relative)?;
+            }
+            BackupFileKind::Attachment => {
+                let file_name = file.path.strip_prefix(ATTACHMENTS_PREFIX).ok_or_else(|| {
+                    format!(
+                        "validate_backup_bundle failed for {}: not an attachment path",
+                        file.path
+                    )
+                })?;
+                if let Some(data) = contents.get(&file.path) {
+                    validate_attachment_contents(file_name, data)?;
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "validate_backup_bundle failed for {}: unexpected file kind",
+                    file.path
+                ))
+            }
+        }
+        let data = contents.get(&file.path).ok_or_else(|| {
+            format!(
+                "validate_backup_bundle failed for {}: file is missing",
+                file.path
+            )
+        })?;
+        if data.len() as u64 != file.size || sha256_hex(data) != file.sha256 {
+            return Err(format!(
+                "validate_backup_bundle failed for {}: checksum mismatch",
+                file.path
+            ));
+        }
+    }
+    if let Some(extra) = contents.keys().find(|name| !listed.contains(name.as_str())) {
+        return Err(format!(
+            "validate_backup_bundle failed for {extra}: file is not in the manifest"
+        ));
+    }
+    for folder in &manifest.folders {
+        validate_bundle_relative_path(folder)?;
+    }
+
+    let index_bytes = contents
+        .get(INDEX_ENTRY)
+        .ok_or_else(|| "validate_backup_bundle failed: index.json is missing".to_string())?;
+    let index: NoteIndex = serde_json::from_slice(index_bytes)
+        .map_err(|error| format!("validate_backup_bundle failed for {INDEX_ENTRY}: {error}"))?;
+    for entry in index.entries.values() {
+        if !contents.contains_key(&format!("{NOTES_PREFIX}{}", entry.relative_path)) {
+            return Err(format!(
+                "validate_backup_bundle failed for {INDEX_ENTRY}: {} is not in the bundle",
+                entry.relative_path
+            ));
+        }
+    }
+    if let Some(calendar) = contents.get(CALENDAR_ENTRY) {
+        serde_json::from_slice::<serde_json::Value>(calendar).map_err(|error| {
+            format!("validate_backup_bundle failed for {CALENDAR_ENTRY}: {error}")
+        })?;
+    }
+    Ok((manifest, contents))
+}
+
+fn write_staging_library(
+    staging_dir: &Path,
+    manifest: &BackupManifest,
+    contents: &BTreeMap<String, Vec<u8>>,
+) -> Result<(), String> {
+    let write = |path: PathBuf, data: &[u8]| -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| {
+                format!(
+                    "create_restore_staging failed for {}: {error}",
+                    parent.display()
+                )
+            })?;
+        }
+        fs::write(&path, data).map_err(|error| {
+            format!(
+                "write_restore_staging failed for {}: {error}",
+                path.display()
+            )
+        })
+    };
+
+    for folder in &manifest.folders {
+        let path = folder
+            .split('/')
+            .fold(staging_dir.to_path_buf(), |path, segment| {
+                path.join(segment)
+            });
+        fs::create_dir_all(&path).map_err(|error| {
+            format!(
+                "create_restore_staging failed for {}: {error}",
+                path.display()
+            )
+        })?;
+    }
+    for (name, data) in contents {
+        let path = if let Some(relative) = name.strip_prefix(NOTES_PREFIX) {
+            relative
+                .split('/')
+                .fold(staging_dir.to_path_buf(), |path, segment| {
+                    path.join(segment)
+                })
+        } else if name == INDEX_ENTRY {
+            staging_dir.join(INDEX_FILENAME)
+        } else if name == CALENDAR_ENTRY {
+            staging_dir.join(CALENDAR_FILENAME)
+        } else {
+            continue;
+        };
+        write(path, data)?;
+    }
+    Ok(())
+}
+
+/// Validates a backup bundle and brings it into the library. An empty library
+/// gets an exact restore; a non-empty one gets a merge that never overwrites,
+/// following the same rules as moving a library between storage locations.
+pub fn import_library_backup(
+    bundle_path: &Path,
+    auto_save_dir: &Path,
+) -> Result<BackupImportResult, String> {
+    let (manifest, contents) = validate_bundle(read_zip(bundle_path)?)?;
+    // Attachments go in first, so no restored note links to a missing file.
+    let bundled_attachments = contents
+        .iter()
+        .filter_map(|(name, data)| {
+            name.strip_prefix(ATTACHMENTS_PREFIX)
+                .map(|file_name| (file_name.to_string(), data.clone()))
+        })
+        .collect::<Vec<_>>();
+    attachments::restore_attachments(
+        &TrustedLibraryRoot::open(auto_save_dir)?,
+        &bundled_attachments,
+    )?;
+    let mode = if library_is_empty(auto_save_dir)? {
+        BackupImportMode::Restore
+    } else {
+        BackupImportMode::Merge
+    };
+
+    let staging_dir = std::env::temp_dir().join(format!(
+        "hwan-note-restore-{}-{}",
+        std::process::id(),
+        now_millis()
+    ));
+    fs::create_dir(&staging_dir).map_err(|error| {
+        format!(
+            "create_restore_staging failed for {}: {error}",
+            staging_dir.display()
+        )
+    })?;
+    let result = write_staging_library(&staging_dir, &manifest, &contents).and_then(|_| {
+        let migration = migrate_notes(&staging_dir, auto_save_dir)?;
+        let calendar_restored = migrate_calendar_file(&staging_dir, auto_save_dir)?;
+        Ok((migration, calendar_restored))
+    });
+    if let Err(error) = fs::remove_dir_all(&staging_dir) {
+        tracing::warn!(
+            "Failed to remove restore staging {}: {error}",
+            staging_dir.display()
+        );
+    }
+    let (migration, calendar_restored) = result?;
+
+    let mut imported = BackupImportResult {
+        mode,
+        restored: Vec::new(),
+        renamed: Vec::new(),
+        skipped: Vec::new(),
+        calendar_restored,
+        calendar_skipped: contents.contains_key(CALENDAR_ENTRY) && !calendar_restored,
+    };
+    for note in migration.notes {
+        match note.outcome {
+            MigrationOutcome::Copied => imported.restored.push(note),
+            MigrationOutcome::Renamed => imported.renamed.push(note),
+            MigrationOutcome::Skipped => imported.skipped.push(note),
+        }
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{auto_save_markdown_note, load_markdown_library, AutoSavePayload};
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-backup-{}-{}-{}",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_temp_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn save(
+        dir: &Path,
+        note_id: &str,
+        title: &str,
+        folder: Option<&str>,
+    ) -> Result<PathBuf, String> {
+        auto_save_markdown_note(
+            dir,
+            &AutoSavePayload {
+                note_id: note_id.to_string(),
+                title: title.to_string(),
+                content: format!("# {title}\nbody of {note_id}"),
+                folder_path: folder.map(str::to_string),
+                is_title_manual: Some(true),
+                is_pinned: Some(note_id == "alpha"),
+            },
+        )
+        .map(|saved| PathBuf::from(saved.file_path))
+    }
+
+    #[test]
+    fn backup_round_trips_into_an_empty_library() {
+        let dir = make_temp_dir("round-trip");
+        let result = (|| -> Result<(), String> {
+            let source = dir.join("source");
+            let target = dir.join("target");
+            fs::create_dir_all(source.join("empty").join("nested")).map_err(|e| e.to_string())?;
+            fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+            let alpha = save(&source, "alpha", "Alpha", Some("work"))?;
+            save(&source, "beta", "Beta", None)?;
+            fs::write(source.join(CALENDAR_FILENAME), "{\"events\":[]}")
+                .map_err(|e| e.to_string())?;
+            let attachment = attachments::save_attachment(
+                &source,
+                Some("alpha"),
+                None,
+                "diagram.png",
+                b"png bytes",
+            )?;
+
+            let bundle = dir.join("backup.zip");
+            let exported = export_library_backup(&source, &bundle)?;
+            assert_eq!(exported.note_count, 2);
+            assert!(exported.calendar_included);
+
+            let imported = import_library_backup(&bundle, &target)?;
+            assert_eq!(imported.mode, BackupImportMode::Restore);
+            assert_eq!(imported.restored.len(), 2);
+            assert!(imported.renamed.is_empty() && imported.skipped.is_empty());
+            assert!(imported.calendar_restored);
+            assert!(target.join("empty").join("nested").is_dir());
+            assert_eq!(
+                fs::read(
+                    target
+                        .join(attachments::ATTACHMENTS_DIRECTORY_NAME)
+                        .join(&attachment.file_name)
+                )
+                .map_err(|e| e.to_string())?,
+                b"png bytes"
+            );
+            assert_eq!(
+                fs::read(target.join("work").join(alpha.file_name().unwrap()))
+                    .map_err(|e| e.to_string())?,
+                fs::read(&alpha).map_err(|e| e.to_string())?
+            );
+
+            let load = load_markdown_library(&target);
+            let restored_alpha = load
+                .notes
+                .iter()
+                .find(|note| note.note_id == "alpha")
+                .ok_or_else(|| "alpha was not restored".to_string())?;
+            assert!(restored_alpha.is_pinned);
+            assert_eq!(restored_alpha.title, "Alpha");
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn backup_merge_keeps_existing_notes_and_reports_conflicts() {
+        let dir = make_temp_dir("merge");
+        let result = (|| -> Result<(), String> {
+            let source = dir.join("source");
+            let target = dir.join("target");
+            fs::create_dir_all(&source).map_err(|e| e.to_string())?;
+            fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+            save(&source, "alpha", "Alpha", None)?;
+            save(&source, "beta", "Shared", None)?;
+            save(&source, "gamma", "Gamma", None)?;
+            let existing_alpha = save(&target, "alpha", "Alpha", None)?;
+            fs::write(&existing_alpha, "# Alpha\nlocal edit").map_err(|e| e.to_string())?;
+            save(&target, "other", "Shared", None)?;
+            fs::write(target.join(CALENDAR_FILENAME), "{}").map_err(|e| e.to_string())?;
+            fs::write(source.join(CALENDAR_FILENAME), "{\"events\":[1]}")
+                .map_err(|e| e.to_string())?;
+
+            let bundle = dir.join("backup.zip");
+            export_library_backup(&source, &bundle)?;
+            let imported = import_library_backup(&bundle, &target)?;
+            assert_eq!(imported.mode, BackupImportMode::Merge);
+            assert_eq!(imported.skipped.len(), 1);
+            assert_eq!(imported.skipped[0].note_id, "alpha");
+            assert_eq!(imported.renamed.len(), 1);
+            assert_eq!(
+                imported.renamed[0].destination_path.as_deref(),
+                Some("Shared-2.md")
+            );
+            assert_eq!(imported.restored.len(), 1);
+            assert!(imported.calendar_skipped);
+            assert_eq!(
+                fs::read_to_string(&existing_alpha).map_err(|e| e.to_string())?,
+                "# Alpha\nlocal edit"
+            );
+            assert_eq!(
+                fs::read_to_string(target.join(CALENDAR_FILENAME)).map_err(|e| e.to_string())?,
+                "{}"
+            );
+            assert_eq!(load_markdown_library(&target).notes.len(), 4);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn backup_import_rejects_tampered_and_unsafe_bundles() {
+        let dir = make_temp_dir("tampered");
+        let result = (|| -> Result<(), String> {
+            let target = dir.join("target");
+            fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+            let note = b"# Note".to_vec();
+            let bundle_with =
+                |path: &str, data: Vec<u8>, listed: &[u8]| -> Result<PathBuf, String> {
+                    let manifest = BackupManifest {
+                        format: BACKUP_FORMAT.to_string(),
+                        version: BACKUP_VERSION,
+                        created_at: 0,
+                        folders: Vec::new(),
+                        files: vec![
+                            BackupManifestFile {
+                                path: path.to_string(),
+                                kind: BackupFileKind::Note,
+                                size: listed.len() as u64,
+                                sha256: sha256_hex(listed),
+                            },
+                            BackupManifestFile {
+                                path: INDEX_ENTRY.to_string(),
+                                kind: BackupFileKind::Index,
+                                size: 14,
+                                sha256: sha256_hex(b"{\"entries\":{}}"),
+                            },
+                        ],
+                    };
+                    let bundle = dir.join("bundle.zip");
+                    let manifest_bytes =
+                        serde_json::to_vec(&manifest).map_err(|e| e.to_string())?;
+                    write_zip(
+                        fs::File::create(&bundle).map_err(|e| e.to_string())?,
+                        [
+                            (MANIFEST_ENTRY, manifest_bytes.as_slice()),
+                            (path, data.as_slice()),
+                            (INDEX_ENTRY, b"{\"entries\":{}}".as_slice()),
+                        ],
+                        0,
+                    )?;
+                    Ok(bundle)
+                };
+
+            let tampered = bundle_with("notes/a.md", b"# Changed".to_vec(), &note)?;
+            let error = import_library_backup(&tampered, &target).unwrap_err();
+            assert!(error.contains("checksum mismatch"), "{error}");
+
+            let traversal = bundle_with("notes/../escape.md", note.clone(), &note)?;
+            let error = import_library_backup(&traversal, &target).unwrap_err();
+            assert!(error.contains("unsafe path"), "{error}");
+
+            let valid = bundle_with("notes/a.md", note.clone(), &note)?;
+            let mut corrupt = fs::read(&valid).map_err(|e| e.to_string())?;
+            let manifest_data = corrupt.len() / 3;
+            corrupt[manifest_data] ^= 0xff;
+            fs::write(&valid, corrupt).map_err(|e| e.to_string())?;
+            assert!(import_library_backup(&valid, &target).is_err());
+
+            assert!(!target.join("a.md").exists());
+            assert!(!dir.join("escape.md").exists());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+}
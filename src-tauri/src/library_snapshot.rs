@@ -0,0 +1,964 @@
+use crate::file_manager::{
+    auto_save_markdown_note_with_history, ensure_library_subdirectory, extract_note_metadata,
+    folder_path_for_relative_path, metadata_is_symlink_or_reparse_point, note_index_entry,
+    now_millis, read_library_backup_source, read_trusted_file_bytes, remove_trusted_directory_tree,
+    replace_library_contents, sanitize_note_id, sha256_hex, AutoSavePayload, AutoSaveResult,
+    LibraryBackupSource, NoteIndex, TrustedLibraryRoot,
+};
+use crate::note_crypto;
+use crate::note_history::NoteHistoryRetention;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+const SNAPSHOT_VERSION: u32 = 1;
+const MANIFEST_FILENAME: &str = "manifest.json";
+const INDEX_FILE: &str = "index.json";
+const CALENDAR_FILE: &str = "calendar.json";
+const NOTES_PREFIX: &str = "notes/";
+const ATTACHMENTS_PREFIX: &str = "attachments/";
+const PARTIAL_SUFFIX: &str = ".partial";
+const LIBRARY_KEY_LENGTH: usize = 16;
+const MILLIS_PER_MINUTE: u64 = 60 * 1000;
+const MILLIS_PER_HOUR: u64 = 60 * MILLIS_PER_MINUTE;
+const MILLIS_PER_DAY: u64 = 24 * MILLIS_PER_HOUR;
+const MILLIS_PER_WEEK: u64 = 7 * MILLIS_PER_DAY;
+/// 1970-01-01 was a Thursday; shifting by three days starts weeks on Monday.
+const WEEK_BUCKET_OFFSET: u64 = 3 * MILLIS_PER_DAY;
+
+static SNAPSHOT_ID_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d{13}$").unwrap());
+static SNAPSHOT_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+fn lock_snapshots() -> MutexGuard<'static, ()> {
+    SNAPSHOT_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// ── Types ──
+
+/// Snapshot schedule and retention. Each `keep_*` count keeps the newest
+/// snapshot from that many distinct hours, days, or weeks (UTC); the newest
+/// snapshot is always kept. `target_dir` defaults to the app data directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SnapshotSettings {
+    pub enabled: bool,
+    pub target_dir: Option<String>,
+    pub interval_minutes: u64,
+    pub keep_hourly: usize,
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            target_dir: None,
+            interval_minutes: 60,
+            keep_hourly: 24,
+            keep_daily: 14,
+            keep_weekly: 8,
+        }
+    }
+}
+
+impl SnapshotSettings {
+    pub(crate) fn interval_millis(&self) -> u64 {
+        self.interval_minutes
+            .max(1)
+            .saturating_mul(MILLIS_PER_MINUTE)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotSummary {
+    pub snapshot_id: String,
+    pub created_at: u64,
+    pub note_count: usize,
+    pub folder_count: usize,
+    pub calendar_included: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotRestoreResult {
+    pub safety_snapshot_id: String,
+    pub notes_written: usize,
+    pub notes_removed: usize,
+}
+
+pub(crate) enum ScheduledSnapshot {
+    NotDue,
+    Unchanged,
+    Created(SnapshotSummary),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotFile {
+    path: String,
+    size: u64,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotManifest {
+    version: u32,
+    created_at: u64,
+    library_path: String,
+    folders: Vec<String>,
+    files: Vec<SnapshotFile>,
+}
+
+impl SnapshotManifest {
+    fn summary(&self, snapshot_id: &str) -> SnapshotSummary {
+        SnapshotSummary {
+            snapshot_id: snapshot_id.to_string(),
+            created_at: self.created_at,
+            note_count: self
+                .files
+                .iter()
+                .filter(|file| file.path.starts_with(NOTES_PREFIX))
+                .count(),
+            folder_count: self.folders.len(),
+            calendar_included: self.files.iter().any(|file| file.path == CALENDAR_FILE),
+        }
+    }
+
+    fn file(&self, path: &str) -> Option<&SnapshotFile> {
+        self.files.iter().find(|file| file.path == path)
+    }
+}
+
+// ── Paths ──
+
+/// Opens the per-library directory beneath the snapshot target. The target is
+/// held to the same trusted-root rules as a library and may not overlap the
+/// library it protects.
+fn open_snapshot_root(
+    library_root: &TrustedLibraryRoot,
+    target_dir: &Path,
+) -> Result<TrustedLibraryRoot, String> {
+    let reject_overlap = |target: &Path| {
+        if target.starts_with(library_root.path()) || library_root.path().starts_with(target) {
+            return Err(format!(
+                "validate_snapshot_target failed for {}: snapshots cannot overlap the library at {}",
+                target.display(),
+                library_root.path().display()
+            ));
+        }
+        Ok(())
+    };
+    // Check before the target is created so a bad setting never adds a
+    // directory inside the library, then again on its canonical identity.
+    reject_overlap(&resolve_through_existing_ancestor(target_dir))?;
+    let target_root = TrustedLibraryRoot::open(target_dir)?;
+    reject_overlap(target_root.path())?;
+    let library_key =
+        &sha256_hex(library_root.path().to_string_lossy().as_bytes())[..LIBRARY_KEY_LENGTH];
+    let snapshot_dir = ensure_library_subdirectory(&target_root, Path::new(library_key))?;
+    TrustedLibraryRoot::open(&snapshot_dir)
+}
+
+/// Canonicalizes the deepest existing ancestor of `path` and re-appends the
+/// components that do not exist yet.
+fn resolve_through_existing_ancestor(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while fs::symlink_metadata(existing).is_err() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = fs::canonicalize(existing).unwrap_or_else(|_| existing.to_path_buf());
+    resolved.extend(missing.iter().rev());
+    resolved
+}
+
+/// Lists complete snapshot ids newest first and removes partial directories
+/// left by an interrupted snapshot. Must be called under the snapshot lock.
+fn list_snapshot_ids(snapshot_root: &TrustedLibraryRoot) -> Result<Vec<String>, String> {
+    let entries = fs::read_dir(snapshot_root.path()).map_err(|error| {
+        format!(
+            "read_snapshots failed for {}: {error}",
+            snapshot_root.path().display()
+        )
+    })?;
+    let mut ids = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|error| {
+            format!(
+                "read_snapshot_entry failed for {}: {error}",
+                snapshot_root.path().display()
+            )
+        })?;
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|value| value.to_str()) else {
+            continue;
+        };
+        let metadata = fs::symlink_metadata(&path)
+            .map_err(|error| format!("inspect_snapshot failed for {}: {error}", path.display()))?;
+        if metadata_is_symlink_or_reparse_point(&metadata) || !metadata.is_dir() {
+            continue;
+        }
+        if name.starts_with('.') && name.ends_with(PARTIAL_SUFFIX) {
+            remove_trusted_directory_tree(snapshot_root, &path)?;
+        } else if SNAPSHOT_ID_RE.is_match(name) {
+            ids.push(name.to_string());
+        }
+    }
+    ids.sort_by(|left, right| right.cmp(left));
+    Ok(ids)
+}
+
+fn read_snapshot_manifest(
+    snapshot_root: &TrustedLibraryRoot,
+    snapshot_id: &str,
+) -> Result<SnapshotManifest, String> {
+    if !SNAPSHOT_ID_RE.is_match(snapshot_id) {
+        return Err(format!("Invalid snapshot id: {snapshot_id}"));
+    }
+    let path = snapshot_root.file_path(&format!("{snapshot_id}/{MANIFEST_FILENAME}"), true)?;
+    let bytes = read_trusted_file_bytes(snapshot_root, &path, "read_snapshot_manifest")?;
+    let manifest: SnapshotManifest = serde_json::from_slice(&bytes).map_err(|error| {
+        format!(
+            "read_snapshot_manifest failed for {}: {error}",
+            path.display()
+        )
+    })?;
+    if manifest.version != SNAPSHOT_VERSION {
+        return Err(format!(
+            "read_snapshot_manifest failed for {}: unsupported version {}",
+            path.display(),
+            manifest.version
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Reads one snapshot file and checks it against the manifest, so a file
+/// damaged through a shared hard link is never restored.
+fn read_snapshot_file(
+    snapshot_root: &TrustedLibraryRoot,
+    snapshot_id: &str,
+    file: &SnapshotFile,
+) -> Result<Vec<u8>, String> {
+    let path = snapshot_root.file_path(&format!("{snapshot_id}/{}", file.path), true)?;
+    let bytes = read_trusted_file_bytes(snapshot_root, &path, "read_snapshot_file")?;
+    if bytes.len() as u64 != file.size || sha256_hex(&bytes) != file.sha256 {
+        return Err(format!(
+            "read_snapshot_file failed for {}: checksum mismatch",
+            path.display()
+        ));
+    }
+    Ok(bytes)
+}
+
+fn read_snapshot_contents(
+    snapshot_root: &TrustedLibraryRoot,
+    snapshot_id: &str,
+    manifest: &SnapshotManifest,
+) -> Result<LibraryBackupSource, String> {
+    let mut contents = LibraryBackupSource {
+        notes: Vec::new(),
+        attachments: Vec::new(),
+        folders: manifest.folders.clone(),
+        index_bytes: Vec::new(),
+        calendar_bytes: None,
+    };
+    for file in &manifest.files {
+        let bytes = read_snapshot_file(snapshot_root, snapshot_id, file)?;
+        if let Some(relative) = file.path.strip_prefix(NOTES_PREFIX) {
+            contents.notes.push((relative.to_string(), bytes));
+        } else if let Some(file_name) = file.path.strip_prefix(ATTACHMENTS_PREFIX) {
+            contents.attachments.push((file_name.to_string(), bytes));
+        } else if file.path == INDEX_FILE {
+            contents.index_bytes = bytes;
+        } else if file.path == CALENDAR_FILE {
+            contents.calendar_bytes = Some(bytes);
+        }
+    }
+    Ok(contents)
+}
+
+// ── Taking snapshots ──
+
+fn snapshot_files(source: &LibraryBackupSource) -> Vec<(SnapshotFile, &[u8])> {
+    let describe = |path: String, bytes: &[u8]| SnapshotFile {
+        path,
+        size: bytes.len() as u64,
+        sha256: sha256_hex(bytes),
+    };
+    let mut files = source
+        .notes
+        .iter()
+        .map(|(relative, bytes)| {
+            (
+                describe(format!("{NOTES_PREFIX}{relative}"), bytes),
+                bytes.as_slice(),
+            )
+        })
+        .collect::<Vec<_>>();
+    files.extend(source.attachments.iter().map(|(file_name, bytes)| {
+        (
+            describe(format!("{ATTACHMENTS_PREFIX}{file_name}"), bytes),
+            bytes.as_slice(),
+        )
+    }));
+    files.push((
+        describe(INDEX_FILE.to_string(), &source.index_bytes),
+        &source.index_bytes,
+    ));
+    if let Some(calendar) = &source.calendar_bytes {
+        files.push((describe(CALENDAR_FILE.to_string(), calendar), calendar));
+    }
+    files
+}
+
+/// Writes a snapshot into a hidden partial directory and renames it into place
+/// once its manifest is written. Files that match the previous snapshot are
+/// hard-linked to it, falling back to a copy where links are unsupported.
+fn write_snapshot(
+    library_root: &TrustedLibraryRoot,
+    snapshot_root: &TrustedLibraryRoot,
+    source: &LibraryBackupSource,
+    previous: Option<(&str, &SnapshotManifest)>,
+    now: u64,
+) -> Result<SnapshotManifest, String> {
+    let mut created_at = now;
+    while fs::symlink_metadata(snapshot_root.path().join(format!("{created_at:013}"))).is_ok() {
+        created_at += 1;
+    }
+    let snapshot_id = format!("{created_at:013}");
+    let partial_name = format!(".{snapshot_id}{PARTIAL_SUFFIX}");
+    let partial_dir = ensure_library_subdirectory(snapshot_root, Path::new(&partial_name))?;
+
+    let result = (|| -> Result<SnapshotManifest, String> {
+        let mut files = Vec::new();
+        for (file, bytes) in snapshot_files(source) {
+            let relative = format!("{partial_name}/{}", file.path);
+            if let Some(parent) = Path::new(&relative).parent() {
+                ensure_library_subdirectory(snapshot_root, parent)?;
+            }
+            let destination = snapshot_root.file_path(&relative, false)?;
+            let linked = previous
+                .filter(|(_, manifest)| manifest.file(&file.path) == Some(&file))
+                .and_then(|(previous_id, _)| {
+                    snapshot_root
+                        .file_path(&format!("{previous_id}/{}", file.path), true)
+                        .ok()
+                })
+                .is_some_and(|existing| fs::hard_link(existing, &destination).is_ok());
+            if !linked {
+                fs::write(&destination, bytes).map_err(|error| {
+                    format!(
+                        "write_snapshot_file failed for {}: {error}",
+                        destination.display()
+                    )
+                })?;
+            }
+            files.push(file);
+        }
+
+        let manifest = SnapshotManifest {
+            version: SNAPSHOT_VERSION,
+            created_at,
+            library_path: library_root.path().to_string_lossy().to_string(),
+            folders: source.folders.clone(),
+            files,
+        };
+        let manifest_path = partial_dir.join(MANIFEST_FILENAME);
+        let manifest_bytes = serde_json::to_vec_pretty(&manifest).map_err(|error| {
+            format!(
+                "serialize_snapshot_manifest failed for {}: {error}",
+                manifest_path.display()
+            )
+        })?;
+        fs::write(&manifest_path, manifest_bytes).map_err(|error| {
+            format!(
+                "write_snapshot_manifest failed for {}: {error}",
+                manifest_path.display()
+            )
+        })?;
+        let final_dir = snapshot_root.path().join(&snapshot_id);
+        fs::rename(&partial_dir, &final_dir).map_err(|error| {
+            format!(
+                "publish_snapshot failed for {}: {error}",
+                final_dir.display()
+            )
+        })?;
+        Ok(manifest)
+    })();
+    if result.is_err() {
+        if let Err(error) = remove_trusted_directory_tree(snapshot_root, &partial_dir) {
+            tracing::warn!("Failed to remove partial snapshot: {error}");
+        }
+    }
+    result
+}
+
+/// Indexes of the snapshots retention keeps, given creation times sorted
+/// newest first.
+fn retained_snapshots(created_at_desc: &[u64], settings: &SnapshotSettings) -> HashSet<usize> {
+    let mut keep = HashSet::new();
+    if !created_at_desc.is_empty() {
+        keep.insert(0);
+    }
+    for (period, offset, count) in [
+        (MILLIS_PER_HOUR, 0, settings.keep_hourly),
+        (MILLIS_PER_DAY, 0, settings.keep_daily),
+        (MILLIS_PER_WEEK, WEEK_BUCKET_OFFSET, settings.keep_weekly),
+    ] {
+        let mut last_bucket = None;
+        let mut buckets = 0;
+        for (position, created_at) in created_at_desc.iter().enumerate() {
+            let bucket = (created_at + offset) / period;
+            if last_bucket == Some(bucket) {
+                continue;
+            }
+            if buckets == count {
+                break;
+            }
+            last_bucket = Some(bucket);
+            buckets += 1;
+            keep.insert(position);
+        }
+    }
+    keep
+}
+
+fn prune_snapshots(
+    snapshot_root: &TrustedLibraryRoot,
+    settings: &SnapshotSettings,
+) -> Result<(), String> {
+    let ids = list_snapshot_ids(snapshot_root)?;
+    let created_at = ids
+        .iter()
+        .map(|id| id.parse::<u64>().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let keep = retained_snapshots(&created_at, settings);
+    for (position, id) in ids.iter().enumerate() {
+        if !keep.contains(&position) {
+            remove_trusted_directory_tree(snapshot_root, &snapshot_root.path().join(id))?;
+        }
+    }
+    Ok(())
+}
+
+fn latest_snapshot(
+    snapshot_root: &TrustedLibraryRoot,
+) -> Result<Option<(String, SnapshotManifest)>, String> {
+    for id in list_snapshot_ids(snapshot_root)? {
+        match read_snapshot_manifest(snapshot_root, &id) {
+            Ok(manifest) => return Ok(Some((id, manifest))),
+            Err(error) => tracing::warn!("Skipping unreadable snapshot {id}: {error}"),
+        }
+    }
+    Ok(None)
+}
+
+fn take_snapshot_locked(
+    library_root: &TrustedLibraryRoot,
+    snapshot_root: &TrustedLibraryRoot,
+    settings: &SnapshotSettings,
+    skip_if_unchanged: bool,
+    now: u64,
+) -> Result<Option<SnapshotSummary>, String> {
+    let source = read_library_backup_source(library_root.path())?;
+    let previous = latest_snapshot(snapshot_root)?;
+    if skip_if_unchanged {
+        if let Some((_, manifest)) = &previous {
+            let files = snapshot_files(&source)
+                .into_iter()
+                .map(|(file, _)| file)
+                .collect::<Vec<_>>();
+            if manifest.files == files && manifest.folders == source.folders {
+                return Ok(None);
+            }
+        }
+    }
+    let manifest = write_snapshot(
+        library_root,
+        snapshot_root,
+        &source,
+        previous
+            .as_ref()
+            .map(|(id, manifest)| (id.as_str(), manifest)),
+        now,
+    )?;
+    prune_snapshots(snapshot_root, settings)?;
+    Ok(Some(
+        manifest.summary(&format!("{:013}", manifest.created_at)),
+    ))
+}
+
+// ── Public API ──
+
+pub fn create_snapshot(
+    auto_save_dir: &Path,
+    target_dir: &Path,
+    settings: &SnapshotSettings,
+) -> Result<SnapshotSummary, String> {
+    let library_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _snapshot_guard = lock_snapshots();
+    let snapshot_root = open_snapshot_root(&library_root, target_dir)?;
+    take_snapshot_locked(&library_root, &snapshot_root, settings, false, now_millis())?
+        .ok_or_else(|| "create_snapshot failed: no snapshot was written".to_string())
+}
+
+/// Takes a snapshot when the newest one is at least one interval old and the
+/// library has changed since it was taken.
+pub(crate) fn take_scheduled_snapshot(
+    auto_save_dir: &Path,
+    target_dir: &Path,
+    settings: &SnapshotSettings,
+    now: u64,
+) -> Result<ScheduledSnapshot, String> {
+    let library_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _snapshot_guard = lock_snapshots();
+    let snapshot_root = open_snapshot_root(&library_root, target_dir)?;
+    let latest_created_at = list_snapshot_ids(&snapshot_root)?
+        .first()
+        .and_then(|id| id.parse::<u64>().ok());
+    if latest_created_at
+        .is_some_and(|created_at| now.saturating_sub(created_at) < settings.interval_millis())
+    {
+        return Ok(ScheduledSnapshot::NotDue);
+    }
+    Ok(
+        match take_snapshot_locked(&library_root, &snapshot_root, settings, true, now)? {
+            Some(summary) => ScheduledSnapshot::Created(summary),
+            None => ScheduledSnapshot::Unchanged,
+        },
+    )
+}
+
+pub fn list_snapshots(
+    auto_save_dir: &Path,
+    target_dir: &Path,
+) -> Result<Vec<SnapshotSummary>, String> {
+    let library_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _snapshot_guard = lock_snapshots();
+    let snapshot_root = open_snapshot_root(&library_root, target_dir)?;
+    let mut summaries = Vec::new();
+    for id in list_snapshot_ids(&snapshot_root)? {
+        match read_snapshot_manifest(&snapshot_root, &id) {
+            Ok(manifest) => summaries.push(manifest.summary(&id)),
+            Err(error) => tracing::warn!("Skipping unreadable snapshot {id}: {error}"),
+        }
+    }
+    Ok(summaries)
+}
+
+/// Replaces the whole library with a snapshot. The current library is
+/// snapshotted first so the restore itself can be undone.
+pub fn restore_library_snapshot(
+    auto_save_dir: &Path,
+    target_dir: &Path,
+    snapshot_id: &str,
+    settings: &SnapshotSettings,
+) -> Result<SnapshotRestoreResult, String> {
+    let library_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let _snapshot_guard = lock_snapshots();
+    let snapshot_root = open_snapshot_root(&library_root, target_dir)?;
+    let manifest = read_snapshot_manifest(&snapshot_root, snapshot_id)?;
+    let contents = read_snapshot_contents(&snapshot_root, snapshot_id, &manifest)?;
+
+    let safety =
+        take_snapshot_locked(&library_root, &snapshot_root, settings, false, now_millis())?
+            .ok_or_else(|| {
+                "restore_snapshot failed: the current library was not snapshotted".to_string()
+            })?;
+    let (notes_written, notes_removed) = replace_library_contents(library_root.path(), &contents)?;
+    Ok(SnapshotRestoreResult {
+        safety_snapshot_id: safety.snapshot_id,
+        notes_written,
+        notes_removed,
+    })
+}
+
+/// Restores individual notes from a snapshot as regular autosaves, so each is
+/// journaled and recorded in history. A note that still exists keeps its
+/// current folder; a deleted note is recreated where the snapshot had it.
+pub fn restore_snapshot_notes(
+    auto_save_dir: &Path,
+    target_dir: &Path,
+    snapshot_id: &str,
+    note_ids: &[String],
+    retention: &NoteHistoryRetention,
+) -> Result<Vec<AutoSaveResult>, String> {
+    let library_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let payloads = {
+        let _snapshot_guard = lock_snapshots();
+        let snapshot_root = open_snapshot_root(&library_root, target_dir)?;
+        let manifest = read_snapshot_manifest(&snapshot_root, snapshot_id)?;
+        let index_file = manifest
+            .file(INDEX_FILE)
+            .ok_or_else(|| format!("read_snapshot failed for {snapshot_id}: index is missing"))?;
+        let index: NoteIndex = serde_json::from_slice(&read_snapshot_file(
+            &snapshot_root,
+            snapshot_id,
+            index_file,
+        )?)
+        .map_err(|error| format!("read_snapshot_index failed for {snapshot_id}: {error}"))?;
+
+        let mut payloads = Vec::with_capacity(note_ids.len());
+        for note_id in note_ids {
+            let note_id = sanitize_note_id(note_id);
+            let entry = index
+                .entries
+                .get(&note_id)
+                .ok_or_else(|| format!("Note not found in snapshot {snapshot_id}: {note_id}"))?;
+            let file = manifest
+                .file(&format!("{NOTES_PREFIX}{}", entry.relative_path))
+                .ok_or_else(|| format!("Note not found in snapshot {snapshot_id}: {note_id}"))?;
+            let bytes = read_snapshot_file(&snapshot_root, snapshot_id, file)?;
+            let stored = String::from_utf8(bytes)
+                .map_err(|error| format!("read_snapshot_note failed for {}: {error}", file.path))?;
+            // A note encrypted when the snapshot was taken is stored armored;
+            // autosave expects plaintext and encrypts it again if needed.
+            let stored = if note_crypto::is_armored(&stored) {
+                note_crypto::session_key(library_root.path(), &note_id)
+                    .ok_or_else(|| {
+                        format!(
+                            "read_snapshot_note failed for {}: the note is encrypted and locked",
+                            file.path
+                        )
+                    })?
+                    .decrypt(&stored)
+                    .map_err(|error| {
+                        format!("read_snapshot_note failed for {}: {error}", file.path)
+                    })?
+            } else {
+                stored
+            };
+            let (_, markdown) = extract_note_metadata(&stored);
+            let folder_relative_path = note_index_entry(&library_root, &note_id)?
+                .map(|current| current.relative_path)
+                .unwrap_or_else(|| entry.relative_path.clone());
+
+            payloads.push(AutoSavePayload {
+                note_id,
+                title: entry.manual_title.clone().unwrap_or_default(),
+                content: markdown,
+                folder_path: Some(folder_path_for_relative_path(&folder_relative_path)),
+                is_title_manual: Some(entry.manual_title.is_some()),
+                is_pinned: entry.is_pinned,
+            });
+        }
+        payloads
+    };
+
+    payloads
+        .iter()
+        .map(|payload| {
+            auto_save_markdown_note_with_history(library_root.path(), payload, retention)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{
+        auto_save_markdown_note, decrypt_note, delete_note_file_and_index, encrypt_note,
+        load_markdown_library, unlock_note, CALENDAR_FILENAME,
+    };
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-snapshot-{}-{}-{}",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_temp_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn save(dir: &Path, note_id: &str, content: &str) -> Result<AutoSaveResult, String> {
+        auto_save_markdown_note(
+            dir,
+            &AutoSavePayload {
+                note_id: note_id.to_string(),
+                title: note_id.to_uppercase(),
+                content: content.to_string(),
+                folder_path: Some("work".to_string()),
+                is_title_manual: Some(true),
+                is_pinned: Some(true),
+            },
+        )
+    }
+
+    fn snapshot_note_path(target: &Path, snapshot_id: &str, file_name: &str) -> PathBuf {
+        let library_dir = fs::read_dir(target)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        library_dir
+            .join(snapshot_id)
+            .join("notes")
+            .join("work")
+            .join(file_name)
+    }
+
+    #[test]
+    fn snapshots_reuse_unchanged_files_and_restore_the_whole_library() {
+        let dir = make_temp_dir("whole");
+        let result = (|| -> Result<(), String> {
+            let library = dir.join("library");
+            let target = dir.join("snapshots");
+            let settings = SnapshotSettings::default();
+            let kept = save(&library, "kept", "# Kept")?;
+            save(&library, "edited", "# Edited v1")?;
+            fs::write(library.join(CALENDAR_FILENAME), "{\"v\":1}").map_err(|e| e.to_string())?;
+
+            let first = match take_scheduled_snapshot(&library, &target, &settings, 1_000)? {
+                ScheduledSnapshot::Created(summary) => summary,
+                _ => return Err("first snapshot was not taken".to_string()),
+            };
+            assert_eq!(first.note_count, 2);
+            assert!(first.calendar_included);
+            assert!(matches!(
+                take_scheduled_snapshot(&library, &target, &settings, 2_000)?,
+                ScheduledSnapshot::NotDue
+            ));
+            assert!(matches!(
+                take_scheduled_snapshot(&library, &target, &settings, 1_000 + MILLIS_PER_HOUR)?,
+                ScheduledSnapshot::Unchanged
+            ));
+
+            save(&library, "edited", "# Edited v2")?;
+            save(&library, "added", "# Added")?;
+            fs::write(library.join(CALENDAR_FILENAME), "{\"v\":2}").map_err(|e| e.to_string())?;
+            let second = create_snapshot(&library, &target, &settings)?;
+            assert_eq!(second.note_count, 3);
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+
+                let file_name = Path::new(&kept.file_path).file_name().unwrap();
+                let first_inode = fs::metadata(snapshot_note_path(
+                    &target,
+                    &first.snapshot_id,
+                    &file_name.to_string_lossy(),
+                ))
+                .map_err(|e| e.to_string())?
+                .ino();
+                let second_inode = fs::metadata(snapshot_note_path(
+                    &target,
+                    &second.snapshot_id,
+                    &file_name.to_string_lossy(),
+                ))
+                .map_err(|e| e.to_string())?
+                .ino();
+                assert_eq!(first_inode, second_inode);
+            }
+
+            let restored =
+                restore_library_snapshot(&library, &target, &first.snapshot_id, &settings)?;
+            assert_eq!(restored.notes_written, 1);
+            assert_eq!(restored.notes_removed, 1);
+            let notes = load_markdown_library(&library).notes;
+            assert_eq!(notes.len(), 2);
+            let edited = notes.iter().find(|note| note.note_id == "edited").unwrap();
+            assert!(edited.markdown.contains("Edited v1"));
+            assert!(edited.is_pinned);
+            assert_eq!(
+                fs::read_to_string(library.join(CALENDAR_FILENAME)).map_err(|e| e.to_string())?,
+                "{\"v\":1}"
+            );
+            let snapshots = list_snapshots(&library, &target)?;
+            // The safety snapshot shares an hour with the second one, so
+            // hourly retention keeps only the newer of the two.
+            assert_eq!(snapshots.len(), 2);
+            assert_eq!(snapshots[0].snapshot_id, restored.safety_snapshot_id);
+            assert_eq!(snapshots[0].note_count, 3);
+            assert_eq!(snapshots[1].snapshot_id, first.snapshot_id);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn snapshot_restore_brings_back_deleted_attachments() {
+        let dir = make_temp_dir("attachments");
+        let result = (|| -> Result<(), String> {
+            let library = dir.join("library");
+            let target = dir.join("snapshots");
+            let settings = SnapshotSettings::default();
+            save(&library, "pictured", "# Pictured")?;
+            let attachment = crate::attachments::save_attachment(
+                &library,
+                Some("pictured"),
+                None,
+                "photo.jpg",
+                b"jpeg bytes",
+            )?;
+            let attachment_path = library
+                .join(crate::attachments::ATTACHMENTS_DIRECTORY_NAME)
+                .join(&attachment.file_name);
+
+            let snapshot = create_snapshot(&library, &target, &settings)?;
+            fs::remove_file(&attachment_path).map_err(|e| e.to_string())?;
+            restore_library_snapshot(&library, &target, &snapshot.snapshot_id, &settings)?;
+            assert_eq!(
+                fs::read(&attachment_path).map_err(|e| e.to_string())?,
+                b"jpeg bytes"
+            );
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn snapshot_restores_individual_notes_and_rejects_damaged_files() {
+        let dir = make_temp_dir("notes");
+        let result = (|| -> Result<(), String> {
+            let library = dir.join("library");
+            let target = dir.join("snapshots");
+            let settings = SnapshotSettings::default();
+            let deleted = save(&library, "deleted", "# Deleted")?;
+            let edited = save(&library, "edited", "# Original")?;
+            let snapshot = create_snapshot(&library, &target, &settings)?;
+
+            delete_note_file_and_index(&library, "deleted", |path| {
+                fs::remove_file(path).map_err(|e| e.to_string())
+            })?;
+            save(&library, "edited", "# Rewritten")?;
+
+            let restored = restore_snapshot_notes(
+                &library,
+                &target,
+                &snapshot.snapshot_id,
+                &["deleted".to_string(), "edited".to_string()],
+                &NoteHistoryRetention::default(),
+            )?;
+            assert_eq!(restored.len(), 2);
+            assert_eq!(restored[0].file_path, deleted.file_path);
+            let notes = load_markdown_library(&library).notes;
+            let recreated = notes.iter().find(|note| note.note_id == "deleted").unwrap();
+            assert!(recreated.is_pinned);
+            assert_eq!(recreated.title, "DELETED");
+            assert!(notes
+                .iter()
+                .any(|note| note.note_id == "edited" && note.markdown.contains("Original")));
+
+            let file_name = Path::new(&edited.file_path).file_name().unwrap();
+            fs::write(
+                snapshot_note_path(&target, &snapshot.snapshot_id, &file_name.to_string_lossy()),
+                "# Tampered",
+            )
+            .map_err(|e| e.to_string())?;
+            let error = restore_snapshot_notes(
+                &library,
+                &target,
+                &snapshot.snapshot_id,
+                &["edited".to_string()],
+                &NoteHistoryRetention::default(),
+            )
+            .unwrap_err();
+            assert!(error.contains("checksum mismatch"), "{error}");
+            assert!(
+                restore_library_snapshot(&library, &target, &snapshot.snapshot_id, &settings)
+                    .is_err()
+            );
+            assert!(create_snapshot(&library, &library.join("nested"), &settings).is_err());
+            assert!(!library.join("nested").exists());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn snapshot_restores_encrypted_notes_as_plaintext_for_autosave() {
+        let dir = make_temp_dir("encrypted");
+        let result = (|| -> Result<(), String> {
+            let library = dir.join("library");
+            let target = dir.join("snapshots");
+            save(&library, "secret", "# Secret\nfirst")?;
+            encrypt_note(&library, "secret", "passphrase")?;
+            let snapshot = create_snapshot(&library, &target, &SnapshotSettings::default())?;
+            save(&library, "secret", "# Secret\nsecond")?;
+
+            let restored = restore_snapshot_notes(
+                &library,
+                &target,
+                &snapshot.snapshot_id,
+                &["secret".to_string()],
+                &NoteHistoryRetention::default(),
+            )?;
+            let stored = fs::read_to_string(&restored[0].file_path).map_err(|e| e.to_string())?;
+            assert!(note_crypto::is_armored(&stored));
+            let unlocked = unlock_note(&library, "secret", None)?.markdown;
+            assert!(unlocked.contains("first"));
+            assert!(!note_crypto::is_armored(&unlocked));
+
+            decrypt_note(&library, "secret", "passphrase")?;
+            let error = restore_snapshot_notes(
+                &library,
+                &target,
+                &snapshot.snapshot_id,
+                &["secret".to_string()],
+                &NoteHistoryRetention::default(),
+            )
+            .unwrap_err();
+            assert!(error.contains("encrypted and locked"), "{error}");
+            let note = load_markdown_library(&library).notes.remove(0);
+            assert!(note.markdown.contains("first"));
+            assert!(!note.is_encrypted);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn retention_keeps_newest_snapshot_per_period() {
+        let settings = SnapshotSettings {
+            keep_hourly: 2,
+            keep_daily: 2,
+            keep_weekly: 1,
+            ..SnapshotSettings::default()
+        };
+        let base = 100 * MILLIS_PER_WEEK;
+        let created_at = [
+            base + 3 * MILLIS_PER_DAY + 2 * MILLIS_PER_HOUR + 10,
+            base + 3 * MILLIS_PER_DAY + 2 * MILLIS_PER_HOUR,
+            base + 3 * MILLIS_PER_DAY + MILLIS_PER_HOUR,
+            base + 3 * MILLIS_PER_DAY,
+            base + 2 * MILLIS_PER_DAY + 5,
+            base + 2 * MILLIS_PER_DAY,
+            base + MILLIS_PER_DAY,
+        ];
+        let mut keep = retained_snapshots(&created_at, &settings)
+            .into_iter()
+            .collect::<Vec<_>>();
+        keep.sort();
+        assert_eq!(keep, vec![0, 2, 4]);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::file_manager::{
+    reconcile_external_changes, ExternalChangeBatch, ExternalLibraryChanges, TrustedLibraryRoot,
+};
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+const QUIET_PERIOD: Duration = Duration::from_millis(400);
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(3);
+
+enum WatchMessage {
+    Event(Event),
+    Stop,
+}
+
+/// Collects raw watcher events into one debounced batch, pairing rename
+/// halves so moved notes can keep their IDs.
+#[derive(Default)]
+struct BatchCollector {
+    batch: ExternalChangeBatch,
+    pending_rename_from: Option<PathBuf>,
+}
+
+impl BatchCollector {
+    fn push(&mut self, event: Event) {
+        match event.kind {
+            EventKind::Access(_) => {}
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                self.pending_rename_from = None;
+                self.push_rename(event.paths[0].clone(), event.paths[1].clone());
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                if let Some(path) = event.paths.into_iter().next() {
+                    self.batch.changed_paths.push(path.clone());
+                    self.pending_rename_from = Some(path);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                let Some(to) = event.paths.into_iter().next() else {
+                    return;
+                };
+                match self.pending_rename_from.take() {
+                    Some(from) => self.push_rename(from, to),
+                    None => self.batch.changed_paths.push(to),
+                }
+            }
+            _ => self.batch.changed_paths.extend(event.paths),
+        }
+    }
+
+    fn push_rename(&mut self, from: PathBuf, to: PathBuf) {
+        let pair = (from, to);
+        if !self.batch.renamed_paths.contains(&pair) {
+            self.batch.renamed_paths.push(pair);
+        }
+    }
+
+    fn finish(mut self) -> ExternalChangeBatch {
+        self.batch.changed_paths.sort();
+        self.batch.changed_paths.dedup();
+        self.batch
+    }
+}
+
+/// Watches a trusted library root and reports reconciled external changes.
+/// Dropping the watcher stops both the OS watch and the debounce thread.
+pub struct LibraryWatcher {
+    root: PathBuf,
+    stop: Sender<WatchMessage>,
+    watcher: Option<RecommendedWatcher>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl LibraryWatcher {
+    pub fn start<F>(library_dir: &Path, on_changes: F) -> Result<Self, String>
+    where
+        F: Fn(ExternalLibraryChanges) + Send + 'static,
+    {
+        let trusted_root = TrustedLibraryRoot::open(library_dir)?;
+        let root = trusted_root.path().to_path_buf();
+        let (sender, receiver) = mpsc::channel();
+
+        let event_sender = sender.clone();
+        let mut watcher = RecommendedWatcher::new(
+            move |result: notify::Result<Event>| match result {
+                Ok(event) => {
+                    let _ = event_sender.send(WatchMessage::Event(event));
+                }
+                Err(error) => tracing::warn!("library watcher error: {error}"),
+            },
+            Config::default().with_follow_symlinks(false),
+        )
+        .map_err(|error| {
+            format!(
+                "create_library_watcher failed for {}: {error}",
+                root.display()
+            )
+        })?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|error| format!("watch_library failed for {}: {error}", root.display()))?;
+
+        let worker_root = root.clone();
+        let worker = thread::Builder::new()
+            .name("hwan-note-library-watcher".to_string())
+            .spawn(move || loop {
+                let mut collector = BatchCollector::default();
+                match receiver.recv() {
+                    Ok(WatchMessage::Event(event)) => collector.push(event),
+                    Ok(WatchMessage::Stop) | Err(_) => return,
+                }
+
+                let deadline = Instant::now() + MAX_BATCH_DELAY;
+                loop {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        break;
+                    }
+                    match receiver.recv_timeout(QUIET_PERIOD.min(remaining)) {
+                        Ok(WatchMessage::Event(event)) => collector.push(event),
+                        Ok(WatchMessage::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                        Err(RecvTimeoutError::Timeout) => break,
+                    }
+                }
+
+                match reconcile_external_changes(&worker_root, &collector.finish()) {
+                    Ok(changes) if changes.is_empty() => {}
+                    Ok(changes) => on_changes(changes),
+                    Err(error) => tracing::warn!("reconcile_external_changes failed: {error}"),
+                }
+            })
+            .map_err(|error| format!("spawn_library_watcher failed: {error}"))?;
+
+        Ok(Self {
+            root,
+            stop: sender,
+            watcher: Some(watcher),
+            worker: Some(worker),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for LibraryWatcher {
+    fn drop(&mut self) {
+        self.watcher.take();
+        let _ = self.stop.send(WatchMessage::Stop);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{auto_save_markdown_note, load_markdown_library, AutoSavePayload};
+
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-watcher-{}-{}-{}",
+            name,
+            std::process::id(),
+            crate::file_manager::now_millis()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_temp_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn rename_event(mode: RenameMode, paths: &[&str]) -> Event {
+        paths.iter().fold(
+            Event::new(EventKind::Modify(ModifyKind::Name(mode))),
+            |event, path| event.add_path(PathBuf::from(path)),
+        )
+    }
+
+    #[test]
+    fn batch_collector_pairs_rename_halves_and_deduplicates() {
+        let mut collector = BatchCollector::default();
+        collector.push(rename_event(RenameMode::From, &["/lib/a.md"]));
+        collector.push(rename_event(RenameMode::To, &["/lib/b.md"]));
+        collector.push(rename_event(RenameMode::Both, &["/lib/a.md", "/lib/b.md"]));
+        collector.push(
+            Event::new(EventKind::Create(notify::event::CreateKind::File))
+                .add_path(PathBuf::from("/lib/c.md")),
+        );
+        collector.push(
+            Event::new(EventKind::Access(notify::event::AccessKind::Any))
+                .add_path(PathBuf::from("/lib/d.md")),
+        );
+        collector.push(rename_event(RenameMode::To, &["/lib/e.md"]));
+
+        let batch = collector.finish();
+        assert_eq!(
+            batch.renamed_paths,
+            vec![(PathBuf::from("/lib/a.md"), PathBuf::from("/lib/b.md"))]
+        );
+        assert_eq!(
+            batch.changed_paths,
+            vec![
+                PathBuf::from("/lib/a.md"),
+                PathBuf::from("/lib/c.md"),
+                PathBuf::from("/lib/e.md")
+            ]
+        );
+    }
+
+    #[test]
+    fn watcher_reports_external_edits_but_not_own_saves() {
+        let dir = make_temp_dir("reports-external");
+        let result = (|| -> Result<(), String> {
+            let saved = auto_save_markdown_note(
+                &dir,
+                &AutoSavePayload {
+                    note_id: "note-a".to_string(),
+                    title: String::new(),
+                    content: "# Alpha".to_string(),
+                    folder_path: None,
+                    is_title_manual: Some(false),
+                    is_pinned: None,
+                },
+            )?;
+            assert_eq!(load_markdown_library(&dir).notes.len(), 1);
+
+            let received = Arc::new(Mutex::new(Vec::<ExternalLibraryChanges>::new()));
+            let sink = Arc::clone(&received);
+            let watcher = LibraryWatcher::start(&dir, move |changes| {
+                sink.lock().unwrap().push(changes);
+            })?;
+
+            auto_save_markdown_note(
+                &dir,
+                &AutoSavePayload {
+                    note_id: "note-a".to_string(),
+                    title: String::new(),
+                    content: "# Alpha\nown edit".to_string(),
+                    folder_path: None,
+                    is_title_manual: Some(false),
+                    is_pinned: None,
+                },
+            )?;
+            thread::sleep(QUIET_PERIOD * 3);
+            assert!(received.lock().unwrap().is_empty());
+
+            fs::write(&saved.file_path, "# Alpha\nexternal edit").map_err(|e| e.to_string())?;
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while received.lock().unwrap().is_empty() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(50));
+            }
+            drop(watcher);
+
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 1);
+            assert_eq!(received[0].changed.len(), 1);
+            assert_eq!(received[0].changed[0].note_id, "note-a");
+            assert_eq!(received[0].changed[0].markdown, "# Alpha\nexternal edit");
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+}
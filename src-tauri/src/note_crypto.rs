@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, OsRng, Payload};
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
+
+pub(crate) const ARMOR_BEGIN: &str = "-----BEGIN HWAN NOTE ENCRYPTED-----";
+const ARMOR_END: &str = "-----END HWAN NOTE ENCRYPTED-----";
+const ARMOR_VERSION: &str = "1";
+const CIPHER_NAME: &str = "XChaCha20-Poly1305";
+const KDF_NAME: &str = "Argon2id";
+const KEY_LENGTH: usize = 32;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const ARMOR_LINE_WIDTH: usize = 76;
+
+/// Argon2id cost for new notes: 64 MiB, three passes, one lane.
+#[cfg(not(test))]
+const DEFAULT_KDF: KdfParams = KdfParams {
+    memory_kib: 64 * 1024,
+    iterations: 3,
+    parallelism: 1,
+};
+/// Tests use the smallest cost Argon2 accepts so they stay fast.
+#[cfg(test)]
+const DEFAULT_KDF: KdfParams = KdfParams {
+    memory_kib: 8,
+    iterations: 1,
+    parallelism: 1,
+};
+/// Upper bounds for costs read from a file, so a crafted note cannot make
+/// unlocking exhaust memory or spin for minutes.
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 16;
+const MAX_KDF_PARALLELISM: u32 = 8;
+
+static SESSION_KEYS: LazyLock<Mutex<HashMap<(PathBuf, String), NoteKey>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// ── Types ──
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+/// A derived note key together with the salt and cost it came from, so the
+/// note can be re-encrypted on every save without running the KDF again.
+#[derive(Clone)]
+pub(crate) struct NoteKey {
+    key: Zeroizing<[u8; KEY_LENGTH]>,
+    salt: [u8; SALT_LENGTH],
+    kdf: KdfParams,
+}
+
+struct ArmoredNote {
+    header: String,
+    kdf: KdfParams,
+    salt: [u8; SALT_LENGTH],
+    nonce: [u8; NONCE_LENGTH],
+    ciphertext: Vec<u8>,
+}
+
+// ── Armor ──
+
+pub(crate) fn is_armored(text: &str) -> bool {
+    text.trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with(ARMOR_BEGIN)
+}
+
+pub(crate) fn is_armored_bytes(bytes: &[u8]) -> bool {
+    is_armored(&String::from_utf8_lossy(&bytes[..bytes.len().min(256)]))
+}
+
+fn armor_header(kdf: KdfParams, salt: &[u8], nonce: &[u8]) -> String {
+    format!(
+        "{ARMOR_BEGIN}\nVersion: {ARMOR_VERSION}\nCipher: {CIPHER_NAME}\nKDF: {KDF_NAME}\nKDF-Params: m={},t={},p={}\nSalt: {}\nNonce: {}\n",
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        BASE64.encode(salt),
+        BASE64.encode(nonce)
+    )
+}
+
+fn decode_fixed<const N: usize>(value: &str, field: &str) -> Result<[u8; N], String> {
+    BASE64
+        .decode(value.trim())
+        .ok()
+        .and_then(|bytes| <[u8; N]>::try_from(bytes).ok())
+        .ok_or_else(|| format!("invalid {field}"))
+}
+
+fn parse_kdf_params(value: &str) -> Result<KdfParams, String> {
+    let mut kdf = KdfParams {
+        memory_kib: 0,
+        iterations: 0,
+        parallelism: 0,
+    };
+    for part in value.split(',') {
+        let (name, number) = part
+            .trim()
+            .split_once('=')
+            .ok_or_else(|| "invalid KDF-Params".to_string())?;
+        let number = number
+            .parse::<u32>()
+            .map_err(|_| "invalid KDF-Params".to_string())?;
+        match name {
+            "m" => kdf.memory_kib = number,
+            "t" => kdf.iterations = number,
+            "p" => kdf.parallelism = number,
+            _ => return Err(format!("unknown KDF parameter {name}")),
+        }
+    }
+    if kdf.memory_kib == 0
+        || kdf.iterations == 0
+        || kdf.parallelism == 0
+        || kdf.memory_kib > MAX_KDF_MEMORY_KIB
+        || kdf.iterations > MAX_KDF_ITERATIONS
+        || kdf.parallelism > MAX_KDF_PARALLELISM
+    {
+        return Err("KDF-Params are out of range".to_string());
+    }
+    Ok(kdf)
+}
+
+fn parse_armor(text: &str) -> Result<ArmoredNote, String> {
+    let normalized = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut lines = normalized.trim().lines();
+    if lines.next() != Some(ARMOR_BEGIN) {
+        return Err("missing armor header".to_string());
+    }
+
+    let mut fields = HashMap::new();
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line
+            .split_once(": ")
+            .ok_or_else(|| format!("invalid armor header line {line:?}"))?;
+        fields.insert(name.to_string(), value.to_string());
+    }
+    let field = |name: &str| {
+        fields
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| format!("missing {name}"))
+    };
+    if field("Version")? != ARMOR_VERSION
+        || field("Cipher")? != CIPHER_NAME
+        || field("KDF")? != KDF_NAME
+    {
+        return Err("unsupported encryption format".to_string());
+    }
+    let kdf = parse_kdf_params(field("KDF-Params")?)?;
+    let salt = decode_fixed::<SALT_LENGTH>(field("Salt")?, "Salt")?;
+    let nonce = decode_fixed::<NONCE_LENGTH>(field("Nonce")?, "Nonce")?;
+
+    let mut body = String::new();
+    let mut closed = false;
+    for line in lines {
+        if line == ARMOR_END {
+            closed = true;
+            break;
+        }
+        body.push_str(line.trim());
+    }
+    if !closed {
+        return Err("missing armor footer".to_string());
+    }
+    let ciphertext = BASE64
+        .decode(body)
+        .map_err(|_| "invalid ciphertext encoding".to_string())?;
+
+    Ok(ArmoredNote {
+        // The header is authenticated as associated data, so it is rebuilt
+        // canonically rather than taken from the file byte for byte.
+        header: armor_header(kdf, &salt, &nonce),
+        kdf,
+        salt,
+        nonce,
+        ciphertext,
+    })
+}
+
+// ── Keys ──
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8; SALT_LENGTH],
+    kdf: KdfParams,
+) -> Result<NoteKey, String> {
+    let params = Params::new(
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        Some(KEY_LENGTH),
+    )
+    .map_err(|error| format!("derive_note_key failed: {error}"))?;
+    let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|error| format!("derive_note_key failed: {error}"))?;
+    Ok(NoteKey {
+        key,
+        salt: *salt,
+        kdf,
+    })
+}
+
+impl NoteKey {
+    /// Derives a key for a note being encrypted for the first time.
+    pub(crate) fn generate(passphrase: &str) -> Result<Self, String> {
+        if passphrase.is_empty() {
+            return Err("A passphrase is required to encrypt a note.".to_string());
+        }
+        let mut salt = [0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        derive_key(passphrase, &salt, DEFAULT_KDF)
+    }
+
+    /// Derives the key for an armored note and proves it by decrypting.
+    /// Returns the key and the note's plaintext.
+    pub(crate) fn unlock(passphrase: &str, armored: &str) -> Result<(Self, String), String> {
+        let note = parse_armor(armored)?;
+        let key = derive_key(passphrase, &note.salt, note.kdf)?;
+        let plaintext = key.open(&note)?;
+        Ok((key, plaintext))
+    }
+
+    fn open(&self, note: &ArmoredNote) -> Result<String, String> {
+        if note.salt != self.salt || note.kdf != self.kdf {
+            return Err("the note was re-encrypted with a different passphrase".to_string());
+        }
+        let cipher = XChaCha20Poly1305::new(self.key.as_ref().into());
+        let plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(&note.nonce),
+                Payload {
+                    msg: &note.ciphertext,
+                    aad: note.header.as_bytes(),
+                },
+            )
+            .map_err(|_| "wrong passphrase or damaged note".to_string())?;
+        String::from_utf8(plaintext).map_err(|_| "decrypted note is not UTF-8".to_string())
+    }
+
+    pub(crate) fn decrypt(&self, armored: &str) -> Result<String, String> {
+        self.open(&parse_armor(armored)?)
+    }
+
+    /// Encrypts `markdown` under a fresh random nonce and returns the armored
+    /// file text. Only this text is ever handed to the file writers.
+    pub(crate) fn encrypt(&self, markdown: &str) -> Result<String, String> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        OsRng.fill_bytes(&mut nonce);
+        let header = armor_header(self.kdf, &self.salt, &nonce);
+        let cipher = XChaCha20Poly1305::new(self.key.as_ref().into());
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: markdown.as_bytes(),
+                    aad: header.as_bytes(),
+                },
+            )
+            .map_err(|_| "encrypt_note failed".to_string())?;
+
+        let encoded = BASE64.encode(ciphertext);
+        let mut armored = header;
+        armored.push('\n');
+        for chunk in encoded.as_bytes().chunks(ARMOR_LINE_WIDTH) {
+            armored.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+            armored.push('\n');
+        }
+        armored.push_str(ARMOR_END);
+        armored.push('\n');
+        Ok(armored)
+    }
+}
+
+// ── Sessions ──
+
+fn session_keys() -> MutexGuard<'static, HashMap<(PathBuf, String), NoteKey>> {
+    SESSION_KEYS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub(crate) fn session_key(library_root: &Path, note_id: &str) -> Option<NoteKey> {
+    session_keys()
+        .get(&(library_root.to_path_buf(), note_id.to_string()))
+        .cloned()
+}
+
+pub(crate) fn remember_session_key(library_root: &Path, note_id: &str, key: NoteKey) {
+    session_keys().insert((library_root.to_path_buf(), note_id.to_string()), key);
+}
+
+/// Drops the unlocked key for a note. Returns whether it was unlocked.
+pub(crate) fn forget_session_key(library_root: &Path, note_id: &str) -> bool {
+    session_keys()
+        .remove(&(library_root.to_path_buf(), note_id.to_string()))
+        .is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn armored_notes_round_trip_and_reject_tampering() {
+        let key = NoteKey::generate("correct horse").unwrap();
+        let armored = key
+            .encrypt("---\ntitle: Secret\n---\n# Secret\nbody")
+            .unwrap();
+        assert!(is_armored(&armored));
+        assert!(!armored.contains("Secret"));
+        assert_ne!(key.encrypt("same").unwrap(), key.encrypt("same").unwrap());
+
+        let (unlocked, plaintext) = NoteKey::unlock("correct horse", &armored).unwrap();
+        assert!(plaintext.ends_with("# Secret\nbody"));
+        assert_eq!(
+            unlocked.decrypt(&armored.replace('\n', "\r\n")).unwrap(),
+            plaintext
+        );
+        assert!(NoteKey::unlock("wrong", &armored).is_err());
+
+        let tampered = armored.replace("t=1", "t=2");
+        assert!(NoteKey::unlock("correct horse", &tampered).is_err());
+        let oversized = armored.replace("m=8", "m=99999999");
+        assert!(NoteKey::unlock("correct horse", &oversized)
+            .err()
+            .is_some_and(|error| error.contains("out of range")));
+        assert!(NoteKey::generate("").is_err());
+    }
+}
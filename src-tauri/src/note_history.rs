@@ -0,0 +1,593 @@
+use crate::file_manager::{
+    auto_save_markdown_note_with_history, derive_title, ensure_library_subdirectory,
+    extract_note_metadata, folder_path_for_relative_path, metadata_is_symlink_or_reparse_point,
+    note_index_entry, now_millis, read_trusted_file_bytes, remove_trusted_directory_tree,
+    sanitize_note_id, sha256_hex, write_note_file_atomically, AutoSavePayload, AutoSaveResult,
+    TrustedLibraryRoot,
+};
+use crate::note_crypto;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+pub(crate) const HISTORY_DIRECTORY_NAME: &str = ".hwan-note-history";
+const REVISION_EXTENSION: &str = "rev";
+const REVISION_DIGEST_LENGTH: usize = 16;
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+static REVISION_ID_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d{13})-([0-9a-f]{16})$").unwrap());
+
+// ── Types ──
+
+/// Per-note retention limits. A zero value disables that limit; the newest
+/// revision is always kept so a single large note still has one undo step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NoteHistoryRetention {
+    pub max_revisions: usize,
+    pub max_age_days: u64,
+    pub max_total_bytes: u64,
+}
+
+impl Default for NoteHistoryRetention {
+    fn default() -> Self {
+        Self {
+            max_revisions: 50,
+            max_age_days: 30,
+            max_total_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRevisionSummary {
+    pub revision_id: String,
+    pub note_id: String,
+    pub saved_at: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRevision {
+    pub revision_id: String,
+    pub note_id: String,
+    pub saved_at: u64,
+    pub title: String,
+    pub is_title_manual: bool,
+    pub markdown: String,
+}
+
+struct RevisionFile {
+    revision_id: String,
+    path: PathBuf,
+    saved_at: u64,
+    digest_prefix: String,
+    size: u64,
+}
+
+// ── Paths ──
+
+fn safe_note_id(note_id: &str) -> Result<String, String> {
+    let safe_id = sanitize_note_id(note_id);
+    if safe_id.is_empty() || safe_id != note_id {
+        return Err(format!("Invalid note id: {note_id}"));
+    }
+    Ok(safe_id)
+}
+
+fn note_history_relative_dir(note_id: &str) -> PathBuf {
+    Path::new(HISTORY_DIRECTORY_NAME).join(note_id)
+}
+
+fn revision_file_name(revision_id: &str) -> String {
+    format!("{revision_id}.{REVISION_EXTENSION}")
+}
+
+fn parse_revision_id(revision_id: &str) -> Option<(u64, String)> {
+    let captures = REVISION_ID_RE.captures(revision_id)?;
+    let saved_at = captures[1].parse::<u64>().ok()?;
+    Some((saved_at, captures[2].to_string()))
+}
+
+/// Returns the note's history directory when it exists, validated as a trusted
+/// directory beneath the library root.
+fn existing_history_dir(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+) -> Result<Option<PathBuf>, String> {
+    let relative = note_history_relative_dir(note_id);
+    match fs::symlink_metadata(trusted_root.path().join(&relative)) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!(
+            "inspect_note_history failed for {}: {error}",
+            trusted_root.path().join(&relative).display()
+        )),
+        Ok(_) => ensure_library_subdirectory(trusted_root, &relative).map(Some),
+    }
+}
+
+/// Lists revision files newest first. Entries that do not look like revisions
+/// (including in-flight temp files) are ignored.
+fn list_revision_files(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+) -> Result<Vec<RevisionFile>, String> {
+    let Some(history_dir) = existing_history_dir(trusted_root, note_id)? else {
+        return Ok(Vec::new());
+    };
+    let entries = fs::read_dir(&history_dir).map_err(|error| {
+        format!(
+            "read_note_history failed for {}: {error}",
+            history_dir.display()
+        )
+    })?;
+
+    let mut revisions = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|error| {
+            format!(
+                "read_note_history_entry failed for {}: {error}",
+                history_dir.display()
+            )
+        })?;
+        let path = entry.path();
+        if path.extension().and_then(|value| value.to_str()) != Some(REVISION_EXTENSION) {
+            continue;
+        }
+        let Some(revision_id) = path.file_stem().and_then(|value| value.to_str()) else {
+            continue;
+        };
+        let Some((saved_at, digest_prefix)) = parse_revision_id(revision_id) else {
+            continue;
+        };
+        let metadata = fs::symlink_metadata(&path).map_err(|error| {
+            format!(
+                "inspect_note_revision failed for {}: {error}",
+                path.display()
+            )
+        })?;
+        if metadata_is_symlink_or_reparse_point(&metadata) || !metadata.is_file() {
+            continue;
+        }
+        revisions.push(RevisionFile {
+            revision_id: revision_id.to_string(),
+            path,
+            saved_at,
+            digest_prefix,
+            size: metadata.len(),
+        });
+    }
+
+    revisions.sort_by(|left, right| right.revision_id.cmp(&left.revision_id));
+    Ok(revisions)
+}
+
+// ── Recording and retention ──
+
+fn prune_revisions(
+    revisions: Vec<RevisionFile>,
+    retention: &NoteHistoryRetention,
+    now: u64,
+) -> Result<(), String> {
+    let max_age = retention.max_age_days.saturating_mul(MILLIS_PER_DAY);
+    let mut total_bytes = 0u64;
+
+    for (position, revision) in revisions.into_iter().enumerate() {
+        total_bytes = total_bytes.saturating_add(revision.size);
+        if position == 0 {
+            continue;
+        }
+        let over_count = retention.max_revisions > 0 && position >= retention.max_revisions;
+        let over_age = max_age > 0 && now.saturating_sub(revision.saved_at) > max_age;
+        let over_size = retention.max_total_bytes > 0 && total_bytes > retention.max_total_bytes;
+        if over_count || over_age || over_size {
+            match fs::remove_file(&revision.path) {
+                Ok(()) => {}
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => {
+                    return Err(format!(
+                        "prune_note_revision failed for {}: {error}",
+                        revision.path.display()
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores the previous body of a note before autosave replaces it, then
+/// applies retention. Must be called while holding the note-index lock.
+pub(crate) fn record_revision_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+    previous_bytes: &[u8],
+    retention: &NoteHistoryRetention,
+) -> Result<(), String> {
+    let note_id = safe_note_id(note_id)?;
+    let digest = sha256_hex(previous_bytes);
+    let digest_prefix = &digest[..REVISION_DIGEST_LENGTH];
+    let existing = list_revision_files(trusted_root, &note_id)?;
+    if existing
+        .first()
+        .is_some_and(|latest| latest.digest_prefix == digest_prefix)
+    {
+        return Ok(());
+    }
+
+    let previous_markdown = String::from_utf8(previous_bytes.to_vec()).map_err(|error| {
+        format!("record_note_revision failed for {note_id}: previous note is not UTF-8: {error}")
+    })?;
+    let history_dir =
+        ensure_library_subdirectory(trusted_root, &note_history_relative_dir(&note_id))?;
+    let now = now_millis();
+    let revision_id = format!("{now:013}-{digest_prefix}");
+    let destination = history_dir.join(revision_file_name(&revision_id));
+    write_note_file_atomically(trusted_root, &destination, &previous_markdown)?;
+
+    prune_revisions(list_revision_files(trusted_root, &note_id)?, retention, now)
+}
+
+/// Removes every stored revision of a note. Must be called while holding the
+/// note-index lock.
+pub(crate) fn remove_note_history_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+) -> Result<(), String> {
+    let note_id = safe_note_id(note_id)?;
+    match existing_history_dir(trusted_root, &note_id)? {
+        Some(history_dir) => remove_trusted_directory_tree(trusted_root, &history_dir),
+        None => Ok(()),
+    }
+}
+
+// ── Public API ──
+
+pub fn list_note_revisions(
+    auto_save_dir: &Path,
+    note_id: &str,
+) -> Result<Vec<NoteRevisionSummary>, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let note_id = safe_note_id(note_id)?;
+    Ok(list_revision_files(&trusted_root, &note_id)?
+        .into_iter()
+        .map(|revision| NoteRevisionSummary {
+            revision_id: revision.revision_id,
+            note_id: note_id.clone(),
+            saved_at: revision.saved_at,
+            size: revision.size,
+        })
+        .collect())
+}
+
+pub fn read_note_revision(
+    auto_save_dir: &Path,
+    note_id: &str,
+    revision_id: &str,
+) -> Result<NoteRevision, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let note_id = safe_note_id(note_id)?;
+    let Some((saved_at, _)) = parse_revision_id(revision_id) else {
+        return Err(format!("Invalid revision id: {revision_id}"));
+    };
+    let relative = note_history_relative_dir(&note_id).join(revision_file_name(revision_id));
+    let path = trusted_root.file_path(&relative.to_string_lossy().replace('\\', "/"), true)?;
+    let bytes = read_trusted_file_bytes(&trusted_root, &path, "read_note_revision")?;
+    let stored = String::from_utf8(bytes)
+        .map_err(|error| format!("read_note_revision failed for {}: {error}", path.display()))?;
+    // Revisions saved while the note was encrypted hold its armored text.
+    let stored = if note_crypto::is_armored(&stored) {
+        note_crypto::session_key(trusted_root.path(), &note_id)
+            .ok_or_else(|| {
+                format!(
+                    "read_note_revision failed for {}: the revision is encrypted and the note is locked",
+                    path.display()
+                )
+            })?
+            .decrypt(&stored)
+            .map_err(|error| format!("read_note_revision failed for {}: {error}", path.display()))?
+    } else {
+        stored
+    };
+    let (metadata, markdown) = extract_note_metadata(&stored);
+    let manual_title = metadata.title;
+
+    Ok(NoteRevision {
+        revision_id: revision_id.to_string(),
+        note_id,
+        saved_at,
+        title: manual_title
+            .clone()
+            .unwrap_or_else(|| derive_title(&markdown)),
+        is_title_manual: manual_title.is_some(),
+        markdown,
+    })
+}
+
+/// Restores a revision as a regular autosave so it is journaled, indexed, and
+/// itself recorded in history. The note keeps its folder, pin state, and
+/// current manual title. Encrypted revisions are restored as plaintext, which
+/// autosave encrypts again if the note still is.
+pub fn restore_note_revision(
+    auto_save_dir: &Path,
+    note_id: &str,
+    revision_id: &str,
+    retention: &NoteHistoryRetention,
+) -> Result<AutoSaveResult, String> {
+    let revision = read_note_revision(auto_save_dir, note_id, revision_id)?;
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let entry = note_index_entry(&trusted_root, &revision.note_id)?
+        .ok_or_else(|| format!("Note not found: {}", revision.note_id))?;
+
+    let payload = AutoSavePayload {
+        note_id: revision.note_id,
+        title: entry.manual_title.clone().unwrap_or_default(),
+        content: revision.markdown,
+        folder_path: Some(folder_path_for_relative_path(&entry.relative_path)),
+        is_title_manual: Some(entry.manual_title.is_some()),
+        is_pinned: entry.is_pinned,
+    };
+    auto_save_markdown_note_with_history(auto_save_dir, &payload, retention)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{
+        auto_save_markdown_note, delete_note_file_and_index, encrypt_note, load_markdown_library,
+        lock_note, rename_folder, unlock_note,
+    };
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-history-{}-{}-{}",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_temp_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn payload(note_id: &str, content: &str, folder: Option<&str>) -> AutoSavePayload {
+        AutoSavePayload {
+            note_id: note_id.to_string(),
+            title: String::new(),
+            content: content.to_string(),
+            folder_path: folder.map(str::to_string),
+            is_title_manual: Some(false),
+            is_pinned: Some(true),
+        }
+    }
+
+    fn revision_file(saved_at: u64, size: u64) -> RevisionFile {
+        RevisionFile {
+            revision_id: format!("{saved_at:013}-0000000000000000"),
+            path: PathBuf::from(format!("missing-{saved_at}.rev")),
+            saved_at,
+            digest_prefix: "0000000000000000".to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn autosave_records_previous_body_once_per_distinct_content() {
+        let dir = make_temp_dir("records-previous");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &payload("note-a", "# First\nbody", None))?;
+            assert!(list_note_revisions(&dir, "note-a")?.is_empty());
+
+            auto_save_markdown_note(&dir, &payload("note-a", "# First\nedited", None))?;
+            auto_save_markdown_note(&dir, &payload("note-a", "# First\nedited", None))?;
+            let revisions = list_note_revisions(&dir, "note-a")?;
+            assert_eq!(revisions.len(), 1);
+
+            let revision = read_note_revision(&dir, "note-a", &revisions[0].revision_id)?;
+            assert_eq!(revision.markdown, "# First\nbody");
+            assert_eq!(revision.title, "First");
+
+            let load = load_markdown_library(&dir);
+            assert_eq!(load.notes.len(), 1);
+            assert!(!load
+                .folders
+                .iter()
+                .any(|folder| folder.starts_with(HISTORY_DIRECTORY_NAME)));
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn history_follows_note_across_title_and_folder_moves() {
+        let dir = make_temp_dir("follows-moves");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &payload("note-a", "# Alpha\none", Some("work")))?;
+            auto_save_markdown_note(&dir, &payload("note-a", "# Beta\ntwo", Some("work")))?;
+            rename_folder(&dir, "work", "office", &NoteHistoryRetention::default())?;
+            auto_save_markdown_note(&dir, &payload("note-a", "# Gamma\nthree", Some("office")))?;
+
+            let revisions = list_note_revisions(&dir, "note-a")?;
+            assert_eq!(revisions.len(), 2);
+            let oldest = read_note_revision(&dir, "note-a", &revisions[1].revision_id)?;
+            assert_eq!(oldest.markdown, "# Alpha\none");
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn restore_publishes_revision_and_keeps_current_body_in_history() {
+        let dir = make_temp_dir("restore");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &payload("note-a", "# Plan\noriginal", Some("work")))?;
+            auto_save_markdown_note(&dir, &payload("note-a", "# Oops\n", Some("work")))?;
+            let original = list_note_revisions(&dir, "note-a")?[0].revision_id.clone();
+
+            let restored =
+                restore_note_revision(&dir, "note-a", &original, &NoteHistoryRetention::default())?;
+            assert!(restored.file_path.ends_with("Plan.md"));
+
+            let load = load_markdown_library(&dir);
+            let note = &load.notes[0];
+            assert_eq!(note.markdown, "# Plan\noriginal");
+            assert_eq!(note.folder_path, "work");
+            assert!(note.is_pinned);
+
+            let revisions = list_note_revisions(&dir, "note-a")?;
+            assert_eq!(revisions.len(), 2);
+            let latest = read_note_revision(&dir, "note-a", &revisions[0].revision_id)?;
+            assert_eq!(latest.markdown, "# Oops\n");
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn encrypted_revisions_restore_once_encrypted_and_need_an_unlocked_note() {
+        let dir = make_temp_dir("restore-encrypted");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &payload("note-a", "# Plan\nfirst", None))?;
+            encrypt_note(&dir, "note-a", "secret")?;
+            auto_save_markdown_note(&dir, &payload("note-a", "# Plan\nsecond", None))?;
+            auto_save_markdown_note(&dir, &payload("note-a", "# Plan\nthird", None))?;
+
+            let mut encrypted_revision = None;
+            for revision in list_note_revisions(&dir, "note-a")? {
+                if read_note_revision(&dir, "note-a", &revision.revision_id)?.markdown
+                    == "# Plan\nsecond"
+                {
+                    encrypted_revision = Some(revision.revision_id);
+                }
+            }
+            let encrypted_revision =
+                encrypted_revision.ok_or("the encrypted revision was not recorded")?;
+
+            let restored = restore_note_revision(
+                &dir,
+                "note-a",
+                &encrypted_revision,
+                &NoteHistoryRetention::default(),
+            )?;
+            let stored = fs::read_to_string(&restored.file_path).map_err(|e| e.to_string())?;
+            assert!(note_crypto::is_armored(&stored));
+            assert_eq!(
+                unlock_note(&dir, "note-a", None)?.markdown,
+                "# Plan\nsecond"
+            );
+
+            assert!(lock_note(&dir, "note-a")?);
+            let locked = read_note_revision(&dir, "note-a", &encrypted_revision).unwrap_err();
+            assert!(locked.contains("locked"));
+            assert!(restore_note_revision(
+                &dir,
+                "note-a",
+                &encrypted_revision,
+                &NoteHistoryRetention::default()
+            )
+            .is_err());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn retention_prunes_by_count_age_and_size_but_keeps_newest() {
+        let dir = make_temp_dir("retention");
+        let result = (|| -> Result<(), String> {
+            let now = 100 * MILLIS_PER_DAY;
+            let write_revisions = |specs: &[(u64, u64)]| -> Result<Vec<RevisionFile>, String> {
+                let mut revisions = Vec::new();
+                for (index, (age_days, size)) in specs.iter().enumerate() {
+                    let mut revision = revision_file(now - age_days * MILLIS_PER_DAY, *size);
+                    revision.path = dir.join(format!("{index}.rev"));
+                    fs::write(&revision.path, b"x").map_err(|e| e.to_string())?;
+                    revisions.push(revision);
+                }
+                Ok(revisions)
+            };
+            let remaining = || {
+                (0..4)
+                    .filter(|index| dir.join(format!("{index}.rev")).exists())
+                    .collect::<Vec<_>>()
+            };
+
+            let revisions = write_revisions(&[(0, 10), (1, 10), (2, 10), (3, 10)])?;
+            let by_count = NoteHistoryRetention {
+                max_revisions: 2,
+                max_age_days: 0,
+                max_total_bytes: 0,
+            };
+            prune_revisions(revisions, &by_count, now)?;
+            assert_eq!(remaining(), vec![0, 1]);
+
+            let revisions = write_revisions(&[(0, 600), (1, 300), (2, 300), (40, 10)])?;
+            let by_age_and_size = NoteHistoryRetention {
+                max_revisions: 0,
+                max_age_days: 30,
+                max_total_bytes: 1000,
+            };
+            prune_revisions(revisions, &by_age_and_size, now)?;
+            assert_eq!(remaining(), vec![0, 1]);
+
+            let revisions = write_revisions(&[(90, 5000)])?;
+            let strict = NoteHistoryRetention {
+                max_revisions: 1,
+                max_age_days: 1,
+                max_total_bytes: 1,
+            };
+            prune_revisions(revisions, &strict, now)?;
+            assert!(dir.join("0.rev").exists());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn deleting_a_note_removes_its_history() {
+        let dir = make_temp_dir("delete");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &payload("note-a", "# One", None))?;
+            auto_save_markdown_note(&dir, &payload("note-a", "# Two", None))?;
+            assert_eq!(list_note_revisions(&dir, "note-a")?.len(), 1);
+
+            delete_note_file_and_index(&dir, "note-a", |path| {
+                fs::remove_file(path).map_err(|e| e.to_string())
+            })?;
+            assert!(list_note_revisions(&dir, "note-a")?.is_empty());
+            assert!(!dir.join(HISTORY_DIRECTORY_NAME).join("note-a").exists());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn read_note_revision_rejects_traversal_ids() {
+        let dir = make_temp_dir("traversal");
+        let traversal_revision = read_note_revision(&dir, "note-a", "../../secret");
+        let traversal_note =
+            read_note_revision(&dir, "../note-a", "0000000000001-0000000000000000");
+        cleanup_temp_dir(&dir);
+        assert!(traversal_revision.is_err());
+        assert!(traversal_note.is_err());
+    }
+}
@@ -0,0 +1,858 @@
+use crate::file_manager::{
+    folder_path_for_relative_path, load_markdown_library, LoadedNote, NoteIndex, NoteLoadState,
+    TrustedLibraryRoot,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static WIKI_LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[\[([^\[\]|\r\n]+?)(?:\|([^\[\]\r\n]*))?\]\]").expect("valid wiki link regex")
+});
+
+static LINK_GRAPHS: LazyLock<Mutex<HashMap<PathBuf, LinkGraph>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+const MARKDOWN_EXTENSIONS: &[&str] = &[".md", ".markdown"];
+
+// ── Types ──
+
+/// One `[[target#anchor|label]]` occurrence. `target` keeps the anchor so a
+/// rewrite can put it back unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WikiLink {
+    pub(crate) target: String,
+    pub(crate) label: Option<String>,
+}
+
+/// What the link graph knows about a note: enough to resolve links to it and
+/// to list the links it contains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinkNode {
+    pub(crate) relative_path: String,
+    pub(crate) title: String,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) links: Vec<WikiLink>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct LinkGraph {
+    nodes: HashMap<String, LinkNode>,
+}
+
+/// A note whose file was rewritten so its wiki links follow a moved note.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelinkedNote {
+    pub note_id: String,
+    pub file_path: String,
+    pub markdown: String,
+}
+
+/// A planned relink: the linking note and its full rewritten file text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PlannedRelink {
+    pub(crate) note_id: String,
+    pub(crate) relative_path: String,
+    pub(crate) stored_markdown: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedNote {
+    pub note_id: String,
+    pub title: String,
+    pub folder_path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedLink {
+    pub target: String,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteLinksResult {
+    pub note_id: String,
+    pub outgoing: Vec<LinkedNote>,
+    pub backlinks: Vec<LinkedNote>,
+    pub unresolved: Vec<UnresolvedLink>,
+}
+
+// ── Parsing ──
+
+/// Calls `visit` with the byte range of each wiki link in `markdown`, skipping
+/// fenced code blocks and inline code spans.
+fn for_each_wiki_link<F>(markdown: &str, mut visit: F)
+where
+    F: FnMut(usize, regex::Captures<'_>),
+{
+    let mut fence: Option<&str> = None;
+    let mut line_start = 0;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            (None, Some(marker)) => fence = Some(marker),
+            (None, None) => {
+                let code_spans = inline_code_spans(line);
+                for captures in WIKI_LINK_RE.captures_iter(line) {
+                    let start = captures.get(0).map_or(0, |m| m.start());
+                    if !code_spans
+                        .iter()
+                        .any(|(span_start, span_end)| (*span_start..*span_end).contains(&start))
+                    {
+                        visit(line_start, captures);
+                    }
+                }
+            }
+            _ => {}
+        }
+        line_start += line.len();
+    }
+}
+
+fn inline_code_spans(line: &str) -> Vec<(usize, usize)> {
+    let bytes = line.as_bytes();
+    let run_length = |from: usize| bytes[from..].iter().take_while(|b| **b == b'`').count();
+    let mut spans = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'`' {
+            index += 1;
+            continue;
+        }
+        let open_length = run_length(index);
+        let mut search = index + open_length;
+        let mut close = None;
+        while search < bytes.len() {
+            if bytes[search] == b'`' {
+                let length = run_length(search);
+                if length == open_length {
+                    close = Some(search + length);
+                    break;
+                }
+                search += length;
+            } else {
+                search += 1;
+            }
+        }
+        match close {
+            Some(end) => {
+                spans.push((index, end));
+                index = end;
+            }
+            None => index += open_length,
+        }
+    }
+    spans
+}
+
+pub(crate) fn parse_wiki_links(markdown: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    for_each_wiki_link(markdown, |_, captures| {
+        let target = captures[1].trim();
+        if target.is_empty() {
+            return;
+        }
+        links.push(WikiLink {
+            target: target.to_string(),
+            label: captures
+                .get(2)
+                .map(|label| label.as_str().trim().to_string())
+                .filter(|label| !label.is_empty()),
+        });
+    });
+    links
+}
+
+/// Rewrites link targets for which `rewrite` returns a replacement, keeping
+/// anchors, labels, and every other byte of `markdown` as it was. Returns
+/// `None` when nothing changed.
+pub(crate) fn rewrite_wiki_links<F>(markdown: &str, mut rewrite: F) -> Option<String>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut replacements = Vec::new();
+    for_each_wiki_link(markdown, |line_start, captures| {
+        let Some(target) = captures.get(1) else {
+            return;
+        };
+        let (name, _) = split_anchor(target.as_str());
+        let name = name.trim();
+        if let Some(replacement) = rewrite(name) {
+            let name_start = line_start + target.start() + target.as_str().find(name).unwrap_or(0);
+            replacements.push((name_start, name_start + name.len(), replacement));
+        }
+    });
+    if replacements.is_empty() {
+        return None;
+    }
+
+    let mut rewritten = String::with_capacity(markdown.len());
+    let mut cursor = 0;
+    for (start, end, replacement) in replacements {
+        rewritten.push_str(&markdown[cursor..start]);
+        rewritten.push_str(&replacement);
+        cursor = end;
+    }
+    rewritten.push_str(&markdown[cursor..]);
+    Some(rewritten)
+}
+
+fn split_anchor(target: &str) -> (&str, Option<&str>) {
+    match target.split_once('#') {
+        Some((name, anchor)) => (name, Some(anchor)),
+        None => (target, None),
+    }
+}
+
+fn strip_markdown_extension(path: &str) -> &str {
+    let lower = path.to_lowercase();
+    MARKDOWN_EXTENSIONS
+        .iter()
+        .find(|extension| lower.ends_with(*extension))
+        .map_or(path, |extension| &path[..path.len() - extension.len()])
+}
+
+fn normalize_link_key(value: &str) -> String {
+    let value = value.trim().replace('\\', "/");
+    strip_markdown_extension(value.trim_start_matches('/')).to_lowercase()
+}
+
+// ── Graph ──
+
+impl LinkNode {
+    pub(crate) fn from_markdown(
+        relative_path: &str,
+        title: &str,
+        aliases: &[String],
+        markdown: &str,
+    ) -> Self {
+        Self {
+            relative_path: relative_path.to_string(),
+            title: title.to_string(),
+            aliases: aliases.to_vec(),
+            links: parse_wiki_links(markdown),
+        }
+    }
+
+    pub(crate) fn from_loaded_note(note: &LoadedNote, relative_path: &str) -> Self {
+        Self::from_markdown(relative_path, &note.title, &note.aliases, &note.markdown)
+    }
+
+    fn path_key(&self) -> String {
+        normalize_link_key(&self.relative_path)
+    }
+
+    fn stem_key(&self) -> String {
+        let path_key = self.path_key();
+        path_key
+            .rsplit_once('/')
+            .map_or(path_key.clone(), |(_, stem)| stem.to_string())
+    }
+}
+
+/// Lookup tables for link resolution. A path-style target (`folder/note`)
+/// matches a note's library path; a bare target matches a file name, then a
+/// title, then an alias. Ties go to the note with the smallest path so the
+/// answer is stable.
+struct LinkResolver<'a> {
+    by_path: HashMap<String, &'a str>,
+    by_stem: HashMap<String, &'a str>,
+    by_title: HashMap<String, &'a str>,
+    by_alias: HashMap<String, &'a str>,
+}
+
+impl<'a> LinkResolver<'a> {
+    fn new(graph: &'a LinkGraph) -> Self {
+        let mut nodes = graph.nodes.iter().collect::<Vec<_>>();
+        nodes.sort_by(|(_, left), (_, right)| left.relative_path.cmp(&right.relative_path));
+
+        let mut resolver = Self {
+            by_path: HashMap::new(),
+            by_stem: HashMap::new(),
+            by_title: HashMap::new(),
+            by_alias: HashMap::new(),
+        };
+        for (note_id, node) in nodes {
+            let note_id = note_id.as_str();
+            resolver.by_path.entry(node.path_key()).or_insert(note_id);
+            resolver.by_stem.entry(node.stem_key()).or_insert(note_id);
+            resolver
+                .by_title
+                .entry(node.title.trim().to_lowercase())
+                .or_insert(note_id);
+            for alias in &node.aliases {
+                resolver
+                    .by_alias
+                    .entry(alias.trim().to_lowercase())
+                    .or_insert(note_id);
+            }
+        }
+        resolver
+    }
+
+    fn resolve(&self, target: &str) -> Option<&'a str> {
+        let (name, _) = split_anchor(target);
+        if name.contains('/') || name.contains('\\') {
+            return self.by_path.get(&normalize_link_key(name)).copied();
+        }
+        let key = normalize_link_key(name);
+        self.by_stem
+            .get(&key)
+            .or_else(|| self.by_title.get(&name.trim().to_lowercase()))
+            .or_else(|| self.by_alias.get(&name.trim().to_lowercase()))
+            .copied()
+    }
+}
+
+impl LinkGraph {
+    /// Picks a target that resolves to `note_id`. Path-style links stay
+    /// path-style; bare links use the file name when it is unambiguous and
+    /// fall back to the library path otherwise.
+    fn link_target_for(
+        &self,
+        resolver: &LinkResolver<'_>,
+        note_id: &str,
+        prefer_path: bool,
+    ) -> Option<String> {
+        let node = self.nodes.get(note_id)?;
+        let path_target = strip_markdown_extension(&node.relative_path).to_string();
+        let stem_target = path_target
+            .rsplit_once('/')
+            .map_or(path_target.as_str(), |(_, stem)| stem);
+        if !prefer_path && resolver.resolve(stem_target) == Some(note_id) {
+            Some(stem_target.to_string())
+        } else {
+            Some(path_target)
+        }
+    }
+}
+
+fn link_graphs() -> MutexGuard<'static, HashMap<PathBuf, LinkGraph>> {
+    LINK_GRAPHS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn graph_key(trusted_root: &TrustedLibraryRoot) -> PathBuf {
+    trusted_root.path().to_path_buf()
+}
+
+pub(crate) fn has_link_graph(trusted_root: &TrustedLibraryRoot) -> bool {
+    link_graphs().contains_key(&graph_key(trusted_root))
+}
+
+// ── Library hooks ──
+
+pub(crate) fn refresh_from_library(
+    trusted_root: &TrustedLibraryRoot,
+    note_index: &NoteIndex,
+    notes: &[LoadedNote],
+) {
+    let mut graph = LinkGraph::default();
+    for note in notes {
+        if let Some(entry) = note_index.entries.get(&note.note_id) {
+            graph.nodes.insert(
+                note.note_id.clone(),
+                LinkNode::from_loaded_note(note, &entry.relative_path),
+            );
+        }
+    }
+    link_graphs().insert(graph_key(trusted_root), graph);
+}
+
+pub(crate) fn record_notes_saved(
+    trusted_root: &TrustedLibraryRoot,
+    nodes: Vec<(String, LinkNode)>,
+) {
+    if let Some(graph) = link_graphs().get_mut(&graph_key(trusted_root)) {
+        graph.nodes.extend(nodes);
+    }
+}
+
+pub(crate) fn record_notes_removed(trusted_root: &TrustedLibraryRoot, note_ids: &[String]) {
+    if let Some(graph) = link_graphs().get_mut(&graph_key(trusted_root)) {
+        for note_id in note_ids {
+            graph.nodes.remove(note_id);
+        }
+    }
+}
+
+/// Returns the graph's nodes for `moves` with their new library paths, ready
+/// to pass to `relocate_notes`.
+pub(crate) fn moved_nodes(
+    trusted_root: &TrustedLibraryRoot,
+    moves: &[(String, String)],
+) -> Vec<(String, LinkNode)> {
+    let graphs = link_graphs();
+    let Some(graph) = graphs.get(&graph_key(trusted_root)) else {
+        return Vec::new();
+    };
+    moves
+        .iter()
+        .filter_map(|(note_id, relative_path)| {
+            let mut node = graph.nodes.get(note_id)?.clone();
+            node.relative_path = relative_path.clone();
+            Some((note_id.clone(), node))
+        })
+        .collect()
+}
+
+/// Resolves every wiki link target that appears in `nodes` against `nodes`
+/// themselves, for callers that work from a library snapshot rather than the
+/// cached graph. Returns target text → note id; unresolved targets are absent.
+pub(crate) fn resolve_link_targets(nodes: HashMap<String, LinkNode>) -> HashMap<String, String> {
+    let graph = LinkGraph { nodes };
+    let resolver = LinkResolver::new(&graph);
+    graph
+        .nodes
+        .values()
+        .flat_map(|node| node.links.iter())
+        .filter_map(|link| {
+            resolver
+                .resolve(&link.target)
+                .map(|note_id| (link.target.clone(), note_id.to_string()))
+        })
+        .collect()
+}
+
+/// Retargets links among notes that all move at once, as in an import:
+/// `before` and `after` hold the same note ids under their old and new paths.
+/// Returns lowercased link name → replacement for every name that resolved to
+/// a note in `before` but would miss that note in `after`.
+pub(crate) fn retarget_moved_links(
+    before: HashMap<String, LinkNode>,
+    after: HashMap<String, LinkNode>,
+) -> HashMap<String, String> {
+    let before = LinkGraph { nodes: before };
+    let after = LinkGraph { nodes: after };
+    let before_resolver = LinkResolver::new(&before);
+    let after_resolver = LinkResolver::new(&after);
+    let mut replacements = HashMap::new();
+    for link in before.nodes.values().flat_map(|node| node.links.iter()) {
+        let (name, _) = split_anchor(&link.target);
+        let name = name.trim();
+        let Some(target_id) = before_resolver.resolve(name) else {
+            continue;
+        };
+        if after_resolver.resolve(name) == Some(target_id) {
+            continue;
+        }
+        let prefer_path = name.contains('/') || name.contains('\\');
+        if let Some(replacement) = after.link_target_for(&after_resolver, target_id, prefer_path) {
+            replacements.insert(name.to_lowercase(), replacement);
+        }
+    }
+    replacements
+}
+
+/// Plans rewrites for every link that resolved to an updated note (new path,
+/// title, or aliases) before the change but would not resolve to it
+/// afterwards. The graph itself is left alone until the caller commits and
+/// records `updates` with `record_notes_saved`. `read_markdown` returns the
+/// current file text for a library-relative path, and each plan names the
+/// linking note's path before the change. Notes in `skip_note_ids` are never
+/// rewritten.
+pub(crate) fn relocate_notes<F>(
+    trusted_root: &TrustedLibraryRoot,
+    updates: &[(String, LinkNode)],
+    skip_note_ids: &HashSet<String>,
+    mut read_markdown: F,
+) -> Result<Vec<PlannedRelink>, String>
+where
+    F: FnMut(&str) -> Result<String, String>,
+{
+    let graphs = link_graphs();
+    let Some(graph) = graphs.get(&graph_key(trusted_root)) else {
+        return Ok(Vec::new());
+    };
+
+    let relocated = updates
+        .iter()
+        .filter(|(note_id, node)| {
+            graph.nodes.get(note_id).is_some_and(|previous| {
+                previous.relative_path != node.relative_path
+                    || previous.title != node.title
+                    || previous.aliases != node.aliases
+            })
+        })
+        .map(|(note_id, _)| note_id.clone())
+        .collect::<HashSet<_>>();
+    if relocated.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut affected_links: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    {
+        let resolver = LinkResolver::new(graph);
+        for (note_id, node) in &graph.nodes {
+            if skip_note_ids.contains(note_id) {
+                continue;
+            }
+            for link in &node.links {
+                if let Some(target_id) = resolver.resolve(&link.target) {
+                    if relocated.contains(target_id) {
+                        let (name, _) = split_anchor(&link.target);
+                        affected_links
+                            .entry(note_id.clone())
+                            .or_default()
+                            .push((name.trim().to_string(), target_id.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    if affected_links.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut after = graph.clone();
+    after.nodes.extend(updates.iter().cloned());
+    let resolver = LinkResolver::new(&after);
+    let mut linking_note_ids = affected_links.keys().cloned().collect::<Vec<_>>();
+    linking_note_ids.sort();
+    let mut plans = Vec::new();
+    for note_id in linking_note_ids {
+        let Some(node) = graph.nodes.get(&note_id) else {
+            continue;
+        };
+        let mut replacements = HashMap::new();
+        for (name, target_id) in &affected_links[&note_id] {
+            if resolver.resolve(name) == Some(target_id.as_str()) {
+                continue;
+            }
+            let prefer_path = name.contains('/') || name.contains('\\');
+            if let Some(replacement) = after.link_target_for(&resolver, target_id, prefer_path) {
+                replacements.insert(name.to_lowercase(), replacement);
+            }
+        }
+        if replacements.is_empty() {
+            continue;
+        }
+
+        let markdown = read_markdown(&node.relative_path)?;
+        let rewritten = rewrite_wiki_links(&markdown, |name| {
+            replacements.get(&name.to_lowercase()).cloned()
+        });
+        if let Some(stored_markdown) = rewritten {
+            plans.push(PlannedRelink {
+                note_id,
+                relative_path: node.relative_path.clone(),
+                stored_markdown,
+            });
+        }
+    }
+    Ok(plans)
+}
+
+// ── Queries ──
+
+/// Returns the notes `note_id` links to, the notes linking to it, and its
+/// links that match no note. Builds the graph from a library load when this
+/// library has not been loaded yet.
+pub fn note_links(auto_save_dir: &Path, note_id: &str) -> Result<NoteLinksResult, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    if !has_link_graph(&trusted_root) {
+        let load = load_markdown_library(auto_save_dir);
+        if load.load_state != NoteLoadState::Ready {
+            let reason = load
+                .issues
+                .first()
+                .map(|issue| issue.display())
+                .unwrap_or_else(|| "library is not ready".to_string());
+            return Err(format!(
+                "note_links failed for {}: {reason}",
+                trusted_root.path().display()
+            ));
+        }
+    }
+
+    let graphs = link_graphs();
+    let graph = graphs.get(&graph_key(&trusted_root)).ok_or_else(|| {
+        format!(
+            "Link graph unavailable for {}",
+            trusted_root.path().display()
+        )
+    })?;
+    let node = graph
+        .nodes
+        .get(note_id)
+        .ok_or_else(|| format!("Note not found: {note_id}"))?;
+    let resolver = LinkResolver::new(graph);
+    let linked_note = |id: &str| {
+        graph.nodes.get(id).map(|node| LinkedNote {
+            note_id: id.to_string(),
+            title: node.title.clone(),
+            folder_path: folder_path_for_relative_path(&node.relative_path),
+        })
+    };
+
+    let mut outgoing = Vec::new();
+    let mut seen_outgoing = HashSet::new();
+    let mut unresolved = Vec::new();
+    for link in &node.links {
+        match resolver.resolve(&link.target) {
+            Some(target_id) => {
+                if seen_outgoing.insert(target_id) {
+                    outgoing.extend(linked_note(target_id));
+                }
+            }
+            None => unresolved.push(UnresolvedLink {
+                target: link.target.clone(),
+                label: link.label.clone(),
+            }),
+        }
+    }
+
+    let mut backlinks = graph
+        .nodes
+        .iter()
+        .filter(|(source_id, source)| {
+            source_id.as_str() != note_id
+                && source
+                    .links
+                    .iter()
+                    .any(|link| resolver.resolve(&link.target) == Some(note_id))
+        })
+        .filter_map(|(source_id, _)| linked_note(source_id))
+        .collect::<Vec<_>>();
+    backlinks.sort_by(|left, right| {
+        left.title
+            .cmp(&right.title)
+            .then_with(|| left.note_id.cmp(&right.note_id))
+    });
+
+    Ok(NoteLinksResult {
+        note_id: note_id.to_string(),
+        outgoing,
+        backlinks,
+        unresolved,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{
+        auto_save_markdown_note, now_millis, rename_folder, AutoSavePayload,
+    };
+    use crate::note_history::NoteHistoryRetention;
+
+    use std::fs;
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-links-{}-{}-{}",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_temp_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn payload(note_id: &str, title: &str, content: &str, folder: Option<&str>) -> AutoSavePayload {
+        AutoSavePayload {
+            note_id: note_id.to_string(),
+            title: title.to_string(),
+            content: content.to_string(),
+            folder_path: folder.map(str::to_string),
+            is_title_manual: Some(true),
+            is_pinned: None,
+        }
+    }
+
+    fn node(relative_path: &str, title: &str, markdown: &str) -> LinkNode {
+        LinkNode::from_markdown(relative_path, title, &[], markdown)
+    }
+
+    #[test]
+    fn parse_wiki_links_reads_labels_and_skips_code() {
+        let markdown = "See [[Plan]] and [[work/Roadmap#Q3|the roadmap]].\n`[[Inline]]` [[ ]]\n```\n[[Fenced]]\n```\n- [[Tail|]]";
+        assert_eq!(
+            parse_wiki_links(markdown),
+            vec![
+                WikiLink {
+                    target: "Plan".to_string(),
+                    label: None
+                },
+                WikiLink {
+                    target: "work/Roadmap#Q3".to_string(),
+                    label: Some("the roadmap".to_string())
+                },
+                WikiLink {
+                    target: "Tail".to_string(),
+                    label: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rewrite_wiki_links_keeps_anchor_label_and_other_text() {
+        let markdown = "a [[Old#Part|label]] b [[old]]\r\n`[[Old]]` [[Other]]";
+        let rewritten = rewrite_wiki_links(markdown, |name| {
+            name.eq_ignore_ascii_case("old")
+                .then(|| "archive/New".to_string())
+        });
+        assert_eq!(
+            rewritten.as_deref(),
+            Some("a [[archive/New#Part|label]] b [[archive/New]]\r\n`[[Old]]` [[Other]]")
+        );
+        assert_eq!(rewrite_wiki_links("[[Other]]", |_| None), None);
+    }
+
+    #[test]
+    fn resolver_prefers_paths_then_names_then_titles_then_aliases() {
+        let mut graph = LinkGraph::default();
+        graph
+            .nodes
+            .insert("a".to_string(), node("work/Plan.md", "Quarter plan", ""));
+        graph
+            .nodes
+            .insert("b".to_string(), node("home/Plan.md", "Home", ""));
+        graph.nodes.insert(
+            "c".to_string(),
+            LinkNode::from_markdown("Notes.md", "Inbox notes", &["Scratch".to_string()], ""),
+        );
+        let resolver = LinkResolver::new(&graph);
+
+        assert_eq!(resolver.resolve("work/plan"), Some("a"));
+        assert_eq!(resolver.resolve("Plan"), Some("b"));
+        assert_eq!(resolver.resolve("quarter plan#Goals"), Some("a"));
+        assert_eq!(resolver.resolve("scratch"), Some("c"));
+        assert_eq!(resolver.resolve("Missing"), None);
+        assert_eq!(
+            graph.link_target_for(&resolver, "a", false).as_deref(),
+            Some("work/Plan")
+        );
+        assert_eq!(
+            graph.link_target_for(&resolver, "c", false).as_deref(),
+            Some("Notes")
+        );
+    }
+
+    #[test]
+    fn note_links_reports_backlinks_and_unresolved_links() {
+        let dir = make_temp_dir("query");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &payload("plan", "Plan", "# Plan", None))?;
+            auto_save_markdown_note(
+                &dir,
+                &payload("hub", "Hub", "[[Plan|the plan]] and [[Missing#Part]]", None),
+            )?;
+
+            let hub = note_links(&dir, "hub")?;
+            assert_eq!(hub.outgoing.len(), 1);
+            assert_eq!(hub.outgoing[0].note_id, "plan");
+            assert_eq!(hub.unresolved.len(), 1);
+            assert_eq!(hub.unresolved[0].target, "Missing#Part");
+
+            let plan = note_links(&dir, "plan")?;
+            assert_eq!(plan.backlinks.len(), 1);
+            assert_eq!(plan.backlinks[0].note_id, "hub");
+            assert!(plan.unresolved.is_empty());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn title_rename_rewrites_links_in_other_notes() {
+        let dir = make_temp_dir("title-rename");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &payload("plan", "Plan", "# Plan", None))?;
+            let hub = auto_save_markdown_note(
+                &dir,
+                &payload("hub", "Hub", "See [[Plan#Goals|goals]] and [[plan]].", None),
+            )?;
+
+            let renamed =
+                auto_save_markdown_note(&dir, &payload("plan", "Roadmap", "# Plan", None))?;
+            assert!(renamed.file_path.ends_with("Roadmap.md"));
+            assert_eq!(renamed.relinked_notes.len(), 1);
+            assert_eq!(renamed.relinked_notes[0].note_id, "hub");
+            assert_eq!(
+                renamed.relinked_notes[0].markdown,
+                "See [[Roadmap#Goals|goals]] and [[Roadmap]]."
+            );
+
+            let stored = fs::read_to_string(&hub.file_path).map_err(|e| e.to_string())?;
+            assert!(stored.contains("See [[Roadmap#Goals|goals]] and [[Roadmap]]."));
+            assert_eq!(
+                note_links(&dir, "roadmap").err(),
+                Some("Note not found: roadmap".to_string())
+            );
+            assert_eq!(note_links(&dir, "plan")?.backlinks.len(), 1);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn folder_rename_rewrites_path_links_and_keeps_name_links() {
+        let dir = make_temp_dir("folder-rename");
+        let result = (|| -> Result<(), String> {
+            auto_save_markdown_note(&dir, &payload("spec", "Spec", "# Spec", Some("work")))?;
+            let hub = auto_save_markdown_note(
+                &dir,
+                &payload("hub", "Hub", "[[work/Spec|spec]] [[Spec]]", None),
+            )?;
+
+            let renamed = rename_folder(&dir, "work", "office", &NoteHistoryRetention::default())?;
+            assert_eq!(renamed.relinked_notes.len(), 1);
+            let stored = fs::read_to_string(&hub.file_path).map_err(|e| e.to_string())?;
+            assert!(stored.contains("[[office/Spec|spec]] [[Spec]]"));
+            assert!(note_links(&dir, "hub")?.unresolved.is_empty());
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn folder_rename_fails_and_is_undone_when_a_link_rewrite_fails() {
+        let dir = make_temp_dir("folder-rename-undo");
+        let result = (|| -> Result<(), String> {
+            let spec =
+                auto_save_markdown_note(&dir, &payload("spec", "Spec", "# Spec", Some("work")))?;
+            let hub =
+                auto_save_markdown_note(&dir, &payload("hub", "Hub", "[[work/Spec|spec]]", None))?;
+            let mut unreadable = fs::read(&hub.file_path).map_err(|e| e.to_string())?;
+            unreadable.extend_from_slice(&[0xff, 0xfe]);
+            fs::write(&hub.file_path, &unreadable).map_err(|e| e.to_string())?;
+
+            let error = rename_folder(&dir, "work", "office", &NoteHistoryRetention::default())
+                .expect_err("an unreadable linking note must fail the rename");
+            assert!(error.contains("read_linking_note"), "{error}");
+            assert!(Path::new(&spec.file_path).exists());
+            assert!(!dir.join("office").exists());
+            assert_eq!(
+                fs::read(&hub.file_path).map_err(|e| e.to_string())?,
+                unreadable
+            );
+            assert_eq!(note_links(&dir, "hub")?.outgoing[0].note_id, "spec");
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+}
@@ -0,0 +1,463 @@
+use crate::file_manager::{
+    ensure_library_subdirectory, read_trusted_file_bytes, sha256_hex,
+    write_library_file_atomically, NoteIndex, TrustedLibraryRoot,
+};
+use crate::front_matter::{render_front_matter, split_front_matter, FrontMatter};
+use crate::note_crypto;
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Library directory holding the note versions machines last saw, named by
+/// their SHA-256 digest so machines that saw the same version share a file.
+pub(crate) const MERGE_BASES_DIRECTORY_NAME: &str = ".hwan-note-merge-bases";
+const MERGE_BASE_EXTENSION: &str = "base";
+/// Machine id used before `set_machine_id` runs, as in tests.
+const DEFAULT_MACHINE_ID: &str = "local";
+/// Largest line-comparison table a merge builds. Bigger edits are compared as
+/// one changed block.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+const OURS_MARKER: &str = "<<<<<<< this device\n";
+const SEPARATOR_MARKER: &str = "=======\n";
+const THEIRS_MARKER: &str = ">>>>>>> other device\n";
+
+static MACHINE_ID: OnceLock<String> = OnceLock::new();
+
+// ── Types ──
+
+/// What an autosave published when the note had changed on disk since this
+/// machine last saw it. `markdown` is the merged note body for the editor;
+/// with `conflicts` above zero it contains conflict markers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteMerge {
+    pub markdown: String,
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MergedText {
+    pub text: String,
+    pub conflicts: usize,
+}
+
+// ── Machine identity ──
+
+pub(crate) fn set_machine_id(machine_id: String) {
+    if MACHINE_ID.set(machine_id).is_err() {
+        tracing::warn!("Machine id was already set");
+    }
+}
+
+pub(crate) fn machine_id() -> &'static str {
+    MACHINE_ID
+        .get()
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_MACHINE_ID)
+}
+
+// ── Merge bases ──
+
+fn merge_base_relative_path(digest: &str) -> String {
+    format!("{MERGE_BASES_DIRECTORY_NAME}/{digest}.{MERGE_BASE_EXTENSION}")
+}
+
+fn is_digest(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Records that this machine now has `note_bytes` and returns the updated
+/// `seen_by` map. Encrypted notes are never merged and keep no plaintext
+/// bases, so their map is cleared instead.
+pub(crate) fn record_seen_version(
+    trusted_root: &TrustedLibraryRoot,
+    seen_by: &BTreeMap<String, String>,
+    note_bytes: &[u8],
+) -> BTreeMap<String, String> {
+    if note_crypto::is_armored_bytes(note_bytes) {
+        return BTreeMap::new();
+    }
+    let mut seen_by = seen_by.clone();
+    seen_by.remove(machine_id());
+    let digest = sha256_hex(note_bytes);
+    match store_merge_base(trusted_root, &digest, note_bytes) {
+        Ok(()) => {
+            seen_by.insert(machine_id().to_string(), digest);
+        }
+        Err(error) => tracing::warn!("store_merge_base failed: {error}"),
+    }
+    seen_by
+}
+
+fn store_merge_base(
+    trusted_root: &TrustedLibraryRoot,
+    digest: &str,
+    note_bytes: &[u8],
+) -> Result<(), String> {
+    let path = trusted_root.file_path(&merge_base_relative_path(digest), false)?;
+    if path.exists() {
+        return Ok(());
+    }
+    ensure_library_subdirectory(trusted_root, Path::new(MERGE_BASES_DIRECTORY_NAME))?;
+    write_library_file_atomically(trusted_root, &path, note_bytes)
+}
+
+/// The text of the version this machine last saw, when it is known and still
+/// stored.
+pub(crate) fn read_merge_base(
+    trusted_root: &TrustedLibraryRoot,
+    seen_by: &BTreeMap<String, String>,
+) -> Result<Option<(String, String)>, String> {
+    let Some(digest) = seen_by.get(machine_id()).filter(|digest| is_digest(digest)) else {
+        return Ok(None);
+    };
+    let path = match trusted_root.file_path(&merge_base_relative_path(digest), true) {
+        Ok(path) => path,
+        Err(_) => return Ok(None),
+    };
+    let bytes = read_trusted_file_bytes(trusted_root, &path, "read_merge_base")?;
+    if sha256_hex(&bytes) != *digest {
+        return Ok(None);
+    }
+    Ok(Some((
+        digest.clone(),
+        String::from_utf8_lossy(&bytes).into_owned(),
+    )))
+}
+
+/// Removes the base this machine saw before `previous_seen_by` was replaced,
+/// once `index`, the index just committed, no longer refers to it.
+pub(crate) fn prune_superseded_merge_base(
+    trusted_root: &TrustedLibraryRoot,
+    previous_seen_by: &BTreeMap<String, String>,
+    index: &NoteIndex,
+) {
+    let Some(digest) = previous_seen_by
+        .get(machine_id())
+        .filter(|digest| is_digest(digest))
+    else {
+        return;
+    };
+    if index
+        .entries
+        .values()
+        .any(|entry| entry.seen_by.values().any(|seen| seen == digest))
+    {
+        return;
+    }
+    let path = match trusted_root.file_path(&merge_base_relative_path(digest), true) {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => tracing::warn!(
+            "prune_superseded_merge_base failed for {}: {error}",
+            path.display()
+        ),
+    }
+}
+
+/// Removes stored bases that no machine's `seen_by` entry refers to anymore.
+pub(crate) fn prune_merge_bases(
+    trusted_root: &TrustedLibraryRoot,
+    index: &NoteIndex,
+) -> Result<(), String> {
+    let referenced = index
+        .entries
+        .values()
+        .flat_map(|entry| entry.seen_by.values())
+        .collect::<HashSet<_>>();
+    let dir = trusted_root.path().join(MERGE_BASES_DIRECTORY_NAME);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => {
+            return Err(format!(
+                "prune_merge_bases failed for {}: {error}",
+                dir.display()
+            ))
+        }
+    };
+    for entry in entries {
+        let entry = entry
+            .map_err(|error| format!("prune_merge_bases failed for {}: {error}", dir.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(digest) = name.strip_suffix(&format!(".{MERGE_BASE_EXTENSION}")) else {
+            continue;
+        };
+        if !is_digest(digest) || referenced.contains(&digest.to_string()) {
+            continue;
+        }
+        fs::remove_file(entry.path()).map_err(|error| {
+            format!(
+                "prune_merge_bases failed for {}: {error}",
+                entry.path().display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+// ── Line merge ──
+
+/// Pairs of equal line positions in `a` and `b` along a longest common
+/// subsequence, in order.
+fn matching_lines(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_middle = &a[prefix..a.len() - suffix];
+    let b_middle = &b[prefix..b.len() - suffix];
+
+    let mut pairs = (0..prefix).map(|i| (i, i)).collect::<Vec<_>>();
+    let (n, m) = (a_middle.len(), b_middle.len());
+    if n > 0 && m > 0 && (n + 1) * (m + 1) <= MAX_DIFF_CELLS {
+        // lengths[i * (m + 1) + j] is the LCS length of a_middle[i..] and b_middle[j..].
+        let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i * (m + 1) + j] = if a_middle[i] == b_middle[j] {
+                    lengths[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * (m + 1) + j].max(lengths[i * (m + 1) + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if a_middle[i] == b_middle[j] {
+                pairs.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    pairs.extend((0..suffix).map(|k| (a.len() - suffix + k, b.len() - suffix + k)));
+    pairs
+}
+
+fn push_lines(out: &mut String, lines: &[&str]) {
+    for line in lines {
+        out.push_str(line);
+    }
+}
+
+fn push_conflict_side(out: &mut String, lines: &[&str]) {
+    push_lines(out, lines);
+    if lines.last().is_some_and(|line| !line.ends_with('\n')) {
+        out.push('\n');
+    }
+}
+
+/// Line-based three-way merge. Changes made on only one side since `base`
+/// are combined; blocks both sides changed differently are written between
+/// `<<<<<<<` / `=======` / `>>>>>>>` markers, ours first.
+pub(crate) fn merge_three_way(base: &str, ours: &str, theirs: &str) -> MergedText {
+    // Compare every last line with its newline so appending below it is not
+    // an edit of that line; the result ends the way `ours` does.
+    let terminated = |text: &str| {
+        if text.is_empty() || text.ends_with('\n') {
+            text.to_string()
+        } else {
+            format!("{text}\n")
+        }
+    };
+    let (base, their_text) = (terminated(base), terminated(theirs));
+    let our_text = terminated(ours);
+    let base_lines = base.split_inclusive('\n').collect::<Vec<_>>();
+    let our_lines = our_text.split_inclusive('\n').collect::<Vec<_>>();
+    let their_lines = their_text.split_inclusive('\n').collect::<Vec<_>>();
+
+    let mut in_ours = vec![None; base_lines.len()];
+    for (base_index, our_index) in matching_lines(&base_lines, &our_lines) {
+        in_ours[base_index] = Some(our_index);
+    }
+    let mut in_theirs = vec![None; base_lines.len()];
+    for (base_index, their_index) in matching_lines(&base_lines, &their_lines) {
+        in_theirs[base_index] = Some(their_index);
+    }
+
+    let mut text = String::with_capacity(ours.len().max(theirs.len()));
+    let mut conflicts = 0;
+    let (mut o, mut a, mut b) = (0, 0, 0);
+    loop {
+        let stable = (o..base_lines.len())
+            .find_map(|index| Some((index, in_ours[index]?, in_theirs[index]?)));
+        let (o_end, a_end, b_end) =
+            stable.unwrap_or((base_lines.len(), our_lines.len(), their_lines.len()));
+        if (o_end, a_end, b_end) == (o, a, b) {
+            if stable.is_none() {
+                break;
+            }
+            text.push_str(base_lines[o]);
+            o += 1;
+            a += 1;
+            b += 1;
+            continue;
+        }
+
+        let base_block = &base_lines[o..o_end];
+        let our_block = &our_lines[a..a_end];
+        let their_block = &their_lines[b..b_end];
+        if our_block == their_block || their_block == base_block {
+            push_lines(&mut text, our_block);
+        } else if our_block == base_block {
+            push_lines(&mut text, their_block);
+        } else {
+            conflicts += 1;
+            text.push_str(OURS_MARKER);
+            push_conflict_side(&mut text, our_block);
+            text.push_str(SEPARATOR_MARKER);
+            push_conflict_side(&mut text, their_block);
+            text.push_str(THEIRS_MARKER);
+        }
+        (o, a, b) = (o_end, a_end, b_end);
+    }
+
+    if !ours.is_empty() && !ours.ends_with('\n') {
+        text.pop();
+    }
+    MergedText { text, conflicts }
+}
+
+// ── Note merge ──
+
+/// Keeps `ours` when this device changed a value since `base`, otherwise
+/// takes whatever the other device has.
+fn pick<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> T {
+    if ours == base {
+        theirs.clone()
+    } else {
+        ours.clone()
+    }
+}
+
+fn merge_extra_fields(base: &Mapping, ours: &Mapping, theirs: &Mapping) -> Mapping {
+    let mut merged = Mapping::new();
+    for key in ours.keys().chain(theirs.keys()) {
+        if merged.contains_key(key) {
+            continue;
+        }
+        let value = pick(
+            &base.get(key).cloned(),
+            &ours.get(key).cloned(),
+            &theirs.get(key).cloned(),
+        );
+        if let Some(value) = value {
+            merged.insert(key.clone(), value);
+        }
+    }
+    merged
+}
+
+/// Three-way merge of a whole note. Bodies are merged line by line; front
+/// matter is merged field by field so conflict markers never land in the
+/// YAML. Title, pin and creation time always come from `ours`, which the
+/// index entry written alongside mirrors.
+pub(crate) fn merge_note(base: &str, ours: &str, theirs: &str) -> MergedText {
+    let (base_metadata, base_body) = split_front_matter(base);
+    let (our_metadata, our_body) = split_front_matter(ours);
+    let (their_metadata, their_body) = split_front_matter(theirs);
+    let body = merge_three_way(base_body, our_body, their_body);
+    if base_metadata.is_none() && our_metadata.is_none() && their_metadata.is_none() {
+        return body;
+    }
+
+    let base_metadata = base_metadata.unwrap_or_default();
+    let our_metadata = our_metadata.unwrap_or_default();
+    let their_metadata = their_metadata.unwrap_or_default();
+    let metadata = FrontMatter {
+        tags: pick(
+            &base_metadata.tags,
+            &our_metadata.tags,
+            &their_metadata.tags,
+        ),
+        aliases: pick(
+            &base_metadata.aliases,
+            &our_metadata.aliases,
+            &their_metadata.aliases,
+        ),
+        extra: merge_extra_fields(
+            &base_metadata.extra,
+            &our_metadata.extra,
+            &their_metadata.extra,
+        ),
+        ..our_metadata
+    };
+    MergedText {
+        text: render_front_matter(&metadata, &body.text),
+        conflicts: body.conflicts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_changes_to_different_lines() {
+        let base = "# Plan\n\nalpha\nbeta\ngamma\n";
+        let ours = "# Plan\n\nalpha (mine)\nbeta\ngamma\n";
+        let theirs = "# Plan\n\nalpha\nbeta\ngamma\ndelta\n";
+        assert_eq!(
+            merge_three_way(base, ours, theirs),
+            MergedText {
+                text: "# Plan\n\nalpha (mine)\nbeta\ngamma\ndelta\n".to_string(),
+                conflicts: 0,
+            }
+        );
+        assert_eq!(merge_three_way(base, ours, ours).text, ours);
+        assert_eq!(merge_three_way(base, base, theirs).text, theirs);
+    }
+
+    #[test]
+    fn marks_overlapping_changes_as_conflicts() {
+        let base = "one\ntwo\nthree";
+        let merged = merge_three_way(base, "one\nTWO\nthree", "one\n2\nthree\nfour");
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "one\n<<<<<<< this device\nTWO\n=======\n2\n>>>>>>> other device\nthree\nfour"
+        );
+    }
+
+    #[test]
+    fn merges_front_matter_by_field_and_keeps_markers_in_the_body() {
+        let base = "---\ntitle: Plan\ntags:\n- work\nstatus: draft\n---\none\ntwo\n";
+        let ours = "---\ntitle: Plan v2\ntags:\n- work\nstatus: draft\n---\none\nTWO\n";
+        let theirs = "---\ntitle: Plan v3\ntags:\n- work\n- urgent\nstatus: review\n---\none\n2\n";
+        let merged = merge_note(base, ours, theirs);
+        assert_eq!(merged.conflicts, 1);
+        let (metadata, body) = split_front_matter(&merged.text);
+        let metadata = metadata.unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Plan v2"));
+        assert_eq!(metadata.tags, vec!["work", "urgent"]);
+        assert_eq!(
+            metadata.extra.get("status").unwrap().as_str(),
+            Some("review")
+        );
+        assert_eq!(
+            body,
+            "one\n<<<<<<< this device\nTWO\n=======\n2\n>>>>>>> other device\n"
+        );
+
+        let plain = merge_note("a\nb\nc\n", "A\nb\nc\n", "a\nb\nC\n");
+        assert_eq!(plain.text, "A\nb\nC\n");
+    }
+}
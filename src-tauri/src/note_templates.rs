@@ -0,0 +1,352 @@
+use crate::file_manager::{
+    auto_save_markdown_note_with_history, ensure_library_subdirectory,
+    metadata_is_symlink_or_reparse_point, note_index_entry, now_millis, read_loaded_note,
+    read_trusted_file_bytes, sanitize_folder_path, sanitize_note_id, system_time_to_millis,
+    write_note_file_atomically, AutoSavePayload, LoadedNote, TrustedLibraryRoot,
+};
+use crate::front_matter::civil_from_days;
+use crate::note_history::NoteHistoryRetention;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+pub(crate) const TEMPLATES_DIRECTORY_NAME: &str = ".hwan-note-templates";
+const TEMPLATE_EXTENSION: &str = "md";
+const MILLIS_PER_MINUTE: i64 = 60 * 1000;
+const MILLIS_PER_DAY: i64 = 24 * 60 * MILLIS_PER_MINUTE;
+
+static TEMPLATE_VARIABLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z]+)(?:\s*:\s*([^{}]*?))?\s*\}\}").unwrap());
+
+// ── Types ──
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteTemplate {
+    pub name: String,
+    pub markdown: String,
+    /// Labels of the `{{prompt:Label}}` variables, in first-use order.
+    pub prompts: Vec<String>,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateNoteRequest {
+    pub template_name: String,
+    pub note_id: String,
+    #[serde(default)]
+    pub folder_path: Option<String>,
+    /// Manual title for the new note; without one the title is derived from
+    /// the rendered content.
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub prompts: HashMap<String, String>,
+    /// Offset of the user's local time from UTC, for `{{date}}` and `{{time}}`.
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+/// Values available to a template while it is rendered.
+pub(crate) struct TemplateContext<'a> {
+    pub now: u64,
+    pub utc_offset_minutes: i32,
+    pub title: &'a str,
+    pub folder: &'a str,
+    pub prompts: &'a HashMap<String, String>,
+}
+
+// ── Rendering ──
+
+/// Local calendar date and wall-clock time as `YYYY-MM-DD` and `HH:MM`.
+pub(crate) fn local_date_time(now: u64, utc_offset_minutes: i32) -> (String, String) {
+    let local = now as i64 + i64::from(utc_offset_minutes) * MILLIS_PER_MINUTE;
+    let (year, month, day) = civil_from_days(local.div_euclid(MILLIS_PER_DAY));
+    let minutes_of_day = local.rem_euclid(MILLIS_PER_DAY) / MILLIS_PER_MINUTE;
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!("{:02}:{:02}", minutes_of_day / 60, minutes_of_day % 60),
+    )
+}
+
+/// Replaces `{{date}}`, `{{time}}`, `{{datetime}}`, `{{title}}`, `{{folder}}`
+/// and `{{prompt:Label}}`. Unknown variables are left as written so templates
+/// can still contain literal braces.
+pub(crate) fn render_template(markdown: &str, context: &TemplateContext<'_>) -> String {
+    let (date, time) = local_date_time(context.now, context.utc_offset_minutes);
+    TEMPLATE_VARIABLE_RE
+        .replace_all(markdown, |captures: &Captures<'_>| {
+            let argument = captures.get(2).map(|value| value.as_str());
+            match (captures[1].to_ascii_lowercase().as_str(), argument) {
+                ("date", None) => date.clone(),
+                ("time", None) => time.clone(),
+                ("datetime", None) => format!("{date} {time}"),
+                ("title", None) => context.title.to_string(),
+                ("folder", None) => context.folder.to_string(),
+                ("prompt", Some(label)) => context.prompts.get(label).cloned().unwrap_or_default(),
+                _ => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+fn template_prompts(markdown: &str) -> Vec<String> {
+    let mut prompts: Vec<String> = Vec::new();
+    for captures in TEMPLATE_VARIABLE_RE.captures_iter(markdown) {
+        if !captures[1].eq_ignore_ascii_case("prompt") {
+            continue;
+        }
+        if let Some(label) = captures.get(2).map(|value| value.as_str().to_string()) {
+            if !label.is_empty() && !prompts.contains(&label) {
+                prompts.push(label);
+            }
+        }
+    }
+    prompts
+}
+
+// ── Storage ──
+
+fn template_relative_path(name: &str) -> Result<String, String> {
+    let trimmed = name.trim();
+    let unsafe_name = trimmed.starts_with('.')
+        || trimmed.ends_with('.')
+        || trimmed.chars().any(|c| {
+            c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+        });
+    if trimmed.is_empty() || unsafe_name {
+        return Err(format!("Invalid template name: {name}"));
+    }
+    Ok(format!(
+        "{TEMPLATES_DIRECTORY_NAME}/{trimmed}.{TEMPLATE_EXTENSION}"
+    ))
+}
+
+pub(crate) fn read_template(
+    trusted_root: &TrustedLibraryRoot,
+    name: &str,
+) -> Result<NoteTemplate, String> {
+    let path = trusted_root.file_path(&template_relative_path(name)?, true)?;
+    let bytes = read_trusted_file_bytes(trusted_root, &path, "read_template")?;
+    let markdown = String::from_utf8(bytes)
+        .map_err(|error| format!("read_template failed for {}: {error}", path.display()))?;
+    let updated_at = fs::symlink_metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .map(system_time_to_millis)
+        .unwrap_or_default();
+    Ok(NoteTemplate {
+        name: name.trim().to_string(),
+        prompts: template_prompts(&markdown),
+        markdown,
+        updated_at,
+    })
+}
+
+// ── Public API ──
+
+/// Lists the templates in the library's templates folder by name. Files that
+/// are not Markdown or not readable as UTF-8 are skipped.
+pub fn list_templates(auto_save_dir: &Path) -> Result<Vec<NoteTemplate>, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let templates_dir = trusted_root.path().join(TEMPLATES_DIRECTORY_NAME);
+    let entries = match fs::symlink_metadata(&templates_dir) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(format!(
+                "inspect_templates failed for {}: {error}",
+                templates_dir.display()
+            ));
+        }
+        Ok(metadata) if metadata_is_symlink_or_reparse_point(&metadata) || !metadata.is_dir() => {
+            return Err(format!(
+                "inspect_templates failed for {}: the templates folder is not a trusted directory",
+                templates_dir.display()
+            ));
+        }
+        Ok(_) => fs::read_dir(&templates_dir).map_err(|error| {
+            format!(
+                "read_templates failed for {}: {error}",
+                templates_dir.display()
+            )
+        })?,
+    };
+
+    let mut templates = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|value| value.to_str()) != Some(TEMPLATE_EXTENSION) {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|value| value.to_str()) else {
+            continue;
+        };
+        match read_template(&trusted_root, name) {
+            Ok(template) => templates.push(template),
+            Err(error) => tracing::warn!("Skipping template {}: {error}", path.display()),
+        }
+    }
+    templates.sort_by(|left, right| left.name.cmp(&right.name));
+    Ok(templates)
+}
+
+/// Creates or replaces a template. Names are used as file names, so they must
+/// already be valid ones.
+pub fn save_template(
+    auto_save_dir: &Path,
+    name: &str,
+    markdown: &str,
+) -> Result<NoteTemplate, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let relative = template_relative_path(name)?;
+    ensure_library_subdirectory(&trusted_root, Path::new(TEMPLATES_DIRECTORY_NAME))?;
+    let path = trusted_root.file_path(&relative, false)?;
+    write_note_file_atomically(&trusted_root, &path, markdown)?;
+    read_template(&trusted_root, name)
+}
+
+/// Renders a template and saves the result as a new note through autosave,
+/// so the note is journaled and indexed like any other.
+pub fn create_note_from_template(
+    auto_save_dir: &Path,
+    request: &TemplateNoteRequest,
+    history_retention: &NoteHistoryRetention,
+) -> Result<LoadedNote, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let template = read_template(&trusted_root, &request.template_name)?;
+    let note_id = sanitize_note_id(&request.note_id);
+    if note_id.is_empty() || note_id != request.note_id {
+        return Err(format!("Invalid note id: {}", request.note_id));
+    }
+    if note_index_entry(&trusted_root, &note_id)?.is_some() {
+        return Err(format!("Note already exists: {note_id}"));
+    }
+
+    let folder = sanitize_folder_path(request.folder_path.as_deref())?;
+    let manual_title = request
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|title| !title.is_empty());
+    let content = render_template(
+        &template.markdown,
+        &TemplateContext {
+            now: now_millis(),
+            utc_offset_minutes: request.utc_offset_minutes,
+            title: manual_title.unwrap_or(&template.name),
+            folder: &folder,
+            prompts: &request.prompts,
+        },
+    );
+
+    auto_save_markdown_note_with_history(
+        auto_save_dir,
+        &AutoSavePayload {
+            note_id: note_id.clone(),
+            title: manual_title.unwrap_or_default().to_string(),
+            content,
+            folder_path: Some(folder),
+            is_title_manual: Some(manual_title.is_some()),
+            is_pinned: None,
+        },
+        history_retention,
+    )?;
+    read_loaded_note(&trusted_root, &note_id)?
+        .ok_or_else(|| format!("Note not found after save: {note_id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::load_markdown_library;
+    use std::path::PathBuf;
+    use std::process;
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-{}-{}-{}",
+            name,
+            process::id(),
+            now_millis()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn render_template_substitutes_local_time_and_prompts() {
+        let prompts = HashMap::from([("Attendees".to_string(), "Kim, Lee".to_string())]);
+        let markdown = "# {{title}} {{ date }}\n{{time}} in {{folder}}\n\
+                        With: {{prompt: Attendees}}\nNotes: {{prompt:Notes}}\n{{unknown}}";
+        let rendered = render_template(
+            markdown,
+            &TemplateContext {
+                // 2024-02-29T23:30:00Z, shown in UTC+09:00.
+                now: 1_709_249_400_000,
+                utc_offset_minutes: 9 * 60,
+                title: "Standup",
+                folder: "work/meetings",
+                prompts: &prompts,
+            },
+        );
+        assert_eq!(
+            rendered,
+            "# Standup 2024-03-01\n08:30 in work/meetings\nWith: Kim, Lee\nNotes: \n{{unknown}}"
+        );
+        assert_eq!(template_prompts(markdown), vec!["Attendees", "Notes"]);
+        assert_eq!(
+            local_date_time(1_709_249_400_000, -60),
+            ("2024-02-29".to_string(), "22:30".to_string())
+        );
+    }
+
+    #[test]
+    fn note_from_template_is_saved_in_folder_and_templates_stay_unlisted() {
+        let dir = make_temp_dir("note-template");
+        let result = (|| -> Result<(), String> {
+            save_template(
+                &dir,
+                "Incident report",
+                "# {{title}}\nSeverity: {{prompt:Severity}}",
+            )?;
+            assert!(save_template(&dir, "../escape", "x").is_err());
+            let templates = list_templates(&dir)?;
+            assert_eq!(templates.len(), 1);
+            assert_eq!(templates[0].prompts, vec!["Severity"]);
+
+            let request = TemplateNoteRequest {
+                template_name: "Incident report".to_string(),
+                note_id: "note-1".to_string(),
+                folder_path: Some("ops".to_string()),
+                title: Some("DB outage".to_string()),
+                prompts: HashMap::from([("Severity".to_string(), "high".to_string())]),
+                utc_offset_minutes: 0,
+            };
+            let note = create_note_from_template(&dir, &request, &NoteHistoryRetention::default())?;
+            assert_eq!(note.title, "DB outage");
+            assert!(note.is_title_manual);
+            assert_eq!(note.folder_path, "ops");
+            assert!(note.markdown.contains("# DB outage\nSeverity: high"));
+            assert!(
+                create_note_from_template(&dir, &request, &NoteHistoryRetention::default())
+                    .is_err()
+            );
+
+            let load = load_markdown_library(&dir);
+            assert_eq!(load.notes.len(), 1);
+            assert!(!load
+                .folders
+                .iter()
+                .any(|folder| folder.contains(TEMPLATES_DIRECTORY_NAME)));
+            Ok(())
+        })();
+        let _ = fs::remove_dir_all(&dir);
+        result.unwrap();
+    }
+}
@@ -0,0 +1,429 @@
+use crate::file_manager::{
+    ensure_library_subdirectory, folder_path_for_relative_path,
+    metadata_is_symlink_or_reparse_point, now_millis, read_trusted_file_bytes,
+    remove_trusted_directory_tree, sha256_hex, write_library_file_atomically, NoteIndexEntry,
+    TrustedLibraryRoot,
+};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+pub(crate) const TRASH_DIRECTORY_NAME: &str = ".hwan-note-trash";
+const MANIFEST_FILENAME: &str = "item.json";
+const FOLDER_CONTENT_NAME: &str = "folder";
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+static TRASH_ID_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\d{13}-[0-9a-f]{8}$").unwrap());
+
+// ── Types ──
+
+/// How long trashed items are kept. Zero keeps them until purged by hand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrashSettings {
+    pub auto_purge_days: u64,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self {
+            auto_purge_days: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrashItemKind {
+    Note,
+    Folder,
+}
+
+/// A note that was taken out of a deleted folder: it moved to the library
+/// root, and restoring the folder moves it back if it is still there.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FolderNoteMove {
+    pub note_id: String,
+    pub original_relative_path: String,
+    pub moved_relative_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashManifest {
+    pub kind: TrashItemKind,
+    pub deleted_at: u64,
+    pub title: String,
+    /// Folder the note lived in, or the deleted folder itself.
+    pub folder_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<NoteIndexEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub moved_notes: Vec<FolderNoteMove>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedItem {
+    pub trash_id: String,
+    pub kind: TrashItemKind,
+    pub title: String,
+    pub folder_path: String,
+    pub deleted_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_id: Option<String>,
+    pub note_count: usize,
+}
+
+impl TrashedItem {
+    fn from_manifest(trash_id: String, manifest: TrashManifest) -> Self {
+        Self {
+            trash_id,
+            kind: manifest.kind,
+            title: manifest.title,
+            folder_path: manifest.folder_path,
+            deleted_at: manifest.deleted_at,
+            note_id: manifest.note_id,
+            note_count: match manifest.kind {
+                TrashItemKind::Note => 1,
+                TrashItemKind::Folder => manifest.moved_notes.len(),
+            },
+        }
+    }
+}
+
+/// A trashed item read back from disk, with the paths restore needs.
+pub(crate) struct TrashItem {
+    pub manifest: TrashManifest,
+    pub item_dir: PathBuf,
+}
+
+impl TrashItem {
+    /// The trashed note file, for note items.
+    pub fn note_path(&self) -> Option<PathBuf> {
+        self.manifest
+            .file_name
+            .as_deref()
+            .map(|file_name| self.item_dir.join(file_name))
+    }
+
+    /// The leftover folder tree, for folder items that had one.
+    pub fn folder_content_path(&self) -> Option<PathBuf> {
+        let path = self.item_dir.join(FOLDER_CONTENT_NAME);
+        fs::symlink_metadata(&path)
+            .is_ok_and(|metadata| metadata.is_dir())
+            .then_some(path)
+    }
+}
+
+// ── Paths ──
+
+fn validate_trash_id(trash_id: &str) -> Result<(), String> {
+    if TRASH_ID_RE.is_match(trash_id) {
+        Ok(())
+    } else {
+        Err(format!("Invalid trash id: {trash_id}"))
+    }
+}
+
+fn trash_item_relative_dir(trash_id: &str) -> PathBuf {
+    Path::new(TRASH_DIRECTORY_NAME).join(trash_id)
+}
+
+/// Creates a fresh, empty item directory. The id sorts by deletion time and
+/// the suffix keeps two deletions within the same millisecond apart.
+fn create_trash_item_dir(
+    trusted_root: &TrustedLibraryRoot,
+    key: &str,
+) -> Result<(String, PathBuf, u64), String> {
+    let deleted_at = now_millis();
+    let trash_dir = ensure_library_subdirectory(trusted_root, Path::new(TRASH_DIRECTORY_NAME))?;
+    for attempt in 0u32.. {
+        let digest = sha256_hex(format!("{key}\n{deleted_at}\n{attempt}").as_bytes());
+        let trash_id = format!("{deleted_at:013}-{}", &digest[..8]);
+        let item_dir = trash_dir.join(&trash_id);
+        match fs::create_dir(&item_dir) {
+            Ok(()) => return Ok((trash_id, item_dir, deleted_at)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => {
+                return Err(format!(
+                    "create_trash_item failed for {}: {error}",
+                    item_dir.display()
+                ));
+            }
+        }
+    }
+    unreachable!("trash id attempts are unbounded")
+}
+
+fn write_manifest(
+    trusted_root: &TrustedLibraryRoot,
+    item_dir: &Path,
+    manifest: &TrashManifest,
+) -> Result<(), String> {
+    let bytes = serde_json::to_vec_pretty(manifest).map_err(|error| {
+        format!(
+            "write_trash_manifest failed for {}: {error}",
+            item_dir.display()
+        )
+    })?;
+    write_library_file_atomically(trusted_root, &item_dir.join(MANIFEST_FILENAME), &bytes)
+}
+
+/// Moves `source` into a new trash item and records `manifest` beside it. On
+/// failure the source is moved back and the item directory is removed.
+fn move_into_trash(
+    trusted_root: &TrustedLibraryRoot,
+    key: &str,
+    source: Option<(&Path, &str)>,
+    build_manifest: impl FnOnce(u64) -> TrashManifest,
+) -> Result<String, String> {
+    let (trash_id, item_dir, deleted_at) = create_trash_item_dir(trusted_root, key)?;
+    let moved = match source {
+        Some((source_path, name)) => {
+            let destination = item_dir.join(name);
+            if let Err(error) = fs::rename(source_path, &destination) {
+                let _ = fs::remove_dir(&item_dir);
+                return Err(format!(
+                    "move_to_trash failed for {}: {error}",
+                    source_path.display()
+                ));
+            }
+            Some((source_path, destination))
+        }
+        None => None,
+    };
+
+    if let Err(error) = write_manifest(trusted_root, &item_dir, &build_manifest(deleted_at)) {
+        if let Some((source_path, destination)) = moved {
+            if let Err(rollback_error) = fs::rename(&destination, source_path) {
+                tracing::warn!(
+                    "Failed to move {} back out of the trash: {rollback_error}",
+                    destination.display()
+                );
+                return Err(error);
+            }
+        }
+        let _ = remove_trusted_directory_tree(trusted_root, &item_dir);
+        return Err(error);
+    }
+    Ok(trash_id)
+}
+
+// ── Trashing ──
+
+/// Moves a note file into the trash together with its index entry. Must be
+/// called while holding the note-index lock, before the entry is removed.
+pub(crate) fn trash_note_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    note_id: &str,
+    entry: &NoteIndexEntry,
+    file_path: &Path,
+    title: &str,
+) -> Result<String, String> {
+    let file_name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("move_to_trash failed for {}", file_path.display()))?;
+    move_into_trash(
+        trusted_root,
+        note_id,
+        Some((file_path, &file_name)),
+        |deleted_at| TrashManifest {
+            kind: TrashItemKind::Note,
+            deleted_at,
+            title: title.to_string(),
+            folder_path: folder_path_for_relative_path(&entry.relative_path),
+            note_id: Some(note_id.to_string()),
+            entry: Some(entry.clone()),
+            file_name: Some(file_name.clone()),
+            moved_notes: Vec::new(),
+        },
+    )
+}
+
+/// Records a deleted folder: what is left of its directory tree (subfolders
+/// and non-note files) moves into the trash, and `moved_notes` remembers where
+/// its notes went. Must be called while holding the note-index lock.
+pub(crate) fn trash_folder_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    folder_path: &str,
+    source_dir: Option<&Path>,
+    moved_notes: Vec<FolderNoteMove>,
+) -> Result<String, String> {
+    let title = folder_path
+        .rsplit('/')
+        .next()
+        .unwrap_or(folder_path)
+        .to_string();
+    move_into_trash(
+        trusted_root,
+        folder_path,
+        source_dir.map(|dir| (dir, FOLDER_CONTENT_NAME)),
+        |deleted_at| TrashManifest {
+            kind: TrashItemKind::Folder,
+            deleted_at,
+            title,
+            folder_path: folder_path.to_string(),
+            note_id: None,
+            entry: None,
+            file_name: None,
+            moved_notes,
+        },
+    )
+}
+
+// ── Reading and removal ──
+
+pub(crate) fn read_trash_item(
+    trusted_root: &TrustedLibraryRoot,
+    trash_id: &str,
+) -> Result<TrashItem, String> {
+    validate_trash_id(trash_id)?;
+    let relative = trash_item_relative_dir(trash_id);
+    let manifest_path = trusted_root.file_path(
+        &relative
+            .join(MANIFEST_FILENAME)
+            .to_string_lossy()
+            .replace('\\', "/"),
+        true,
+    )?;
+    let bytes = read_trusted_file_bytes(trusted_root, &manifest_path, "read_trash_item")?;
+    let manifest = serde_json::from_slice(&bytes).map_err(|error| {
+        format!(
+            "read_trash_item failed for {}: {error}",
+            manifest_path.display()
+        )
+    })?;
+    Ok(TrashItem {
+        manifest,
+        item_dir: trusted_root.path().join(relative),
+    })
+}
+
+/// Lists trashed items newest first. Directories without a readable manifest
+/// are skipped.
+pub(crate) fn list_trash_items(
+    trusted_root: &TrustedLibraryRoot,
+) -> Result<Vec<TrashedItem>, String> {
+    let trash_dir = trusted_root.path().join(TRASH_DIRECTORY_NAME);
+    match fs::symlink_metadata(&trash_dir) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(format!(
+                "inspect_trash failed for {}: {error}",
+                trash_dir.display()
+            ));
+        }
+        Ok(metadata) if metadata_is_symlink_or_reparse_point(&metadata) || !metadata.is_dir() => {
+            return Err(format!(
+                "inspect_trash failed for {}: the trash is not a trusted directory",
+                trash_dir.display()
+            ));
+        }
+        Ok(_) => {}
+    }
+
+    let entries = fs::read_dir(&trash_dir)
+        .map_err(|error| format!("read_trash failed for {}: {error}", trash_dir.display()))?;
+    let mut items = Vec::new();
+    for entry in entries.flatten() {
+        let trash_id = entry.file_name().to_string_lossy().to_string();
+        if validate_trash_id(&trash_id).is_err() {
+            continue;
+        }
+        match read_trash_item(trusted_root, &trash_id) {
+            Ok(item) => items.push(TrashedItem::from_manifest(trash_id, item.manifest)),
+            Err(error) => tracing::warn!("Skipping trash item {trash_id}: {error}"),
+        }
+    }
+    items.sort_by(|left, right| right.trash_id.cmp(&left.trash_id));
+    Ok(items)
+}
+
+/// Deletes a trash item for good. Must be called while holding the note-index
+/// lock.
+pub(crate) fn remove_trash_item_unlocked(
+    trusted_root: &TrustedLibraryRoot,
+    trash_id: &str,
+) -> Result<(), String> {
+    validate_trash_id(trash_id)?;
+    let item_dir = trusted_root.path().join(trash_item_relative_dir(trash_id));
+    match fs::symlink_metadata(&item_dir) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(format!(
+            "inspect_trash_item failed for {}: {error}",
+            item_dir.display()
+        )),
+        Ok(_) => remove_trusted_directory_tree(trusted_root, &item_dir),
+    }
+}
+
+/// Ids of items older than the configured age, oldest first.
+pub(crate) fn expired_trash_ids(
+    items: &[TrashedItem],
+    settings: &TrashSettings,
+    now: u64,
+) -> Vec<String> {
+    if settings.auto_purge_days == 0 {
+        return Vec::new();
+    }
+    let max_age = settings.auto_purge_days.saturating_mul(MILLIS_PER_DAY);
+    items
+        .iter()
+        .rev()
+        .filter(|item| now.saturating_sub(item.deleted_at) > max_age)
+        .map(|item| item.trash_id.clone())
+        .collect()
+}
+
+// ── Public API ──
+
+pub fn list_trash(auto_save_dir: &Path) -> Result<Vec<TrashedItem>, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    list_trash_items(&trusted_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(trash_id: &str, deleted_at: u64) -> TrashedItem {
+        TrashedItem {
+            trash_id: trash_id.to_string(),
+            kind: TrashItemKind::Note,
+            title: String::new(),
+            folder_path: String::new(),
+            deleted_at,
+            note_id: None,
+            note_count: 1,
+        }
+    }
+
+    #[test]
+    fn expired_trash_ids_respects_age_and_zero_disables() {
+        let now = 40 * MILLIS_PER_DAY;
+        let items = vec![
+            item("0000000000003-00000000", now - MILLIS_PER_DAY),
+            item("0000000000002-00000000", now - 31 * MILLIS_PER_DAY),
+            item("0000000000001-00000000", 0),
+        ];
+        assert_eq!(
+            expired_trash_ids(&items, &TrashSettings::default(), now),
+            vec!["0000000000001-00000000", "0000000000002-00000000"]
+        );
+        assert!(expired_trash_ids(&items, &TrashSettings { auto_purge_days: 0 }, now).is_empty());
+        assert!(validate_trash_id("../escape").is_err());
+    }
+}
@@ -0,0 +1,1077 @@
+use crate::atomic_file::publish_temp_file;
+use crate::file_manager::{
+    current_index_digest, derive_title, folder_path_for_relative_path, load_markdown_library,
+    markdown_to_plain_text, now_millis, validate_trusted_publish_destination, LoadedNote,
+    NoteIndex, NoteLoadIssue, NoteLoadState, TrustedLibraryRoot,
+};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const SEARCH_INDEX_FILENAME: &str = ".hwan-note-search.json";
+const SEARCH_INDEX_VERSION: u32 = 2;
+const DEFAULT_RESULT_LIMIT: usize = 50;
+const TITLE_WEIGHT: f64 = 3.0;
+const EXACT_TERM_BONUS: f64 = 0.5;
+const SNIPPET_LEADING_CHARS: usize = 40;
+const SNIPPET_MAX_CHARS: usize = 160;
+
+// ── Types ──
+
+/// A note as the search index sees it: enough text to rank, filter, and build
+/// snippets without reopening the Markdown file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SearchDocument {
+    pub(crate) relative_path: String,
+    pub(crate) folder_path: String,
+    pub(crate) title: String,
+    pub(crate) plain_text: String,
+    pub(crate) updated_at: u64,
+    /// The note file as it was when indexed, so edits made behind the note
+    /// index's back are noticed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) file_stamp: Option<FileStamp>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileStamp {
+    modified_nanos: u64,
+    len: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TermPosting {
+    note_id: String,
+    title_hits: u32,
+    body_hits: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchIndex {
+    version: u32,
+    note_index_digest: Option<String>,
+    documents: HashMap<String, SearchDocument>,
+    terms: BTreeMap<String, Vec<TermPosting>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchScope {
+    #[default]
+    All,
+    Title,
+    Content,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteSearchQuery {
+    pub query: String,
+    #[serde(default)]
+    pub scope: SearchScope,
+    #[serde(default)]
+    pub folder_path: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Half-open range in UTF-16 code units, matching JavaScript string offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteSearchHit {
+    pub note_id: String,
+    pub title: String,
+    pub folder_path: String,
+    pub updated_at: u64,
+    pub score: f64,
+    pub title_matches: Vec<SearchMatchRange>,
+    pub snippet: String,
+    pub snippet_matches: Vec<SearchMatchRange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteSearchResult {
+    pub hits: Vec<NoteSearchHit>,
+    pub total_hits: usize,
+    pub rebuilt: bool,
+}
+
+struct Token {
+    term: String,
+    start: usize,
+    end: usize,
+}
+
+// ── Tokenizing ──
+
+/// Splits text into lowercase alphanumeric runs. Offsets are char indices so
+/// Hangul and other multi-byte text can be sliced safely afterwards.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+
+    for (index, ch) in text.chars().enumerate() {
+        if ch.is_alphanumeric() {
+            if current.is_empty() {
+                start = index;
+            }
+            current.extend(ch.to_lowercase());
+        } else if !current.is_empty() {
+            tokens.push(Token {
+                term: std::mem::take(&mut current),
+                start,
+                end: index,
+            });
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(Token {
+            term: current,
+            start,
+            end: text.chars().count(),
+        });
+    }
+
+    tokens
+}
+
+fn query_terms(query: &str) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    tokenize(query)
+        .into_iter()
+        .filter_map(|token| seen.insert(token.term.clone()).then_some(token.term))
+        .collect()
+}
+
+fn term_counts(text: &str) -> HashMap<String, u32> {
+    let mut counts = HashMap::new();
+    for token in tokenize(text) {
+        *counts.entry(token.term).or_insert(0) += 1;
+    }
+    counts
+}
+
+// ── Index maintenance ──
+
+impl SearchDocument {
+    pub(crate) fn from_markdown(
+        relative_path: &str,
+        title: Option<&str>,
+        markdown: &str,
+        updated_at: u64,
+    ) -> Self {
+        Self {
+            relative_path: relative_path.to_string(),
+            folder_path: folder_path_for_relative_path(relative_path),
+            title: title
+                .map(str::to_string)
+                .unwrap_or_else(|| derive_title(markdown)),
+            plain_text: markdown_to_plain_text(markdown),
+            updated_at,
+            file_stamp: None,
+        }
+    }
+
+    pub(crate) fn from_loaded_note(note: &LoadedNote, relative_path: &str) -> Self {
+        Self {
+            relative_path: relative_path.to_string(),
+            folder_path: note.folder_path.clone(),
+            title: note.title.clone(),
+            plain_text: note.plain_text.clone(),
+            updated_at: note.updated_at,
+            file_stamp: None,
+        }
+    }
+
+    fn stamped(mut self, trusted_root: &TrustedLibraryRoot) -> Self {
+        self.file_stamp = file_stamp(trusted_root, &self.relative_path);
+        self
+    }
+}
+
+fn file_stamp(trusted_root: &TrustedLibraryRoot, relative_path: &str) -> Option<FileStamp> {
+    let metadata = fs::symlink_metadata(trusted_root.path().join(relative_path)).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let modified_nanos = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    Some(FileStamp {
+        modified_nanos: u64::try_from(modified_nanos).unwrap_or(u64::MAX),
+        len: metadata.len(),
+    })
+}
+
+impl SearchIndex {
+    fn empty(note_index_digest: Option<String>) -> Self {
+        Self {
+            version: SEARCH_INDEX_VERSION,
+            note_index_digest,
+            documents: HashMap::new(),
+            terms: BTreeMap::new(),
+        }
+    }
+
+    fn remove_document(&mut self, note_id: &str) -> bool {
+        let Some(document) = self.documents.remove(note_id) else {
+            return false;
+        };
+        let mut terms = term_counts(&document.title)
+            .into_keys()
+            .collect::<BTreeSet<_>>();
+        terms.extend(term_counts(&document.plain_text).into_keys());
+        for term in terms {
+            if let Some(postings) = self.terms.get_mut(&term) {
+                postings.retain(|posting| posting.note_id != note_id);
+                if postings.is_empty() {
+                    self.terms.remove(&term);
+                }
+            }
+        }
+        true
+    }
+
+    fn upsert_document(&mut self, note_id: &str, document: SearchDocument) -> bool {
+        if self.documents.get(note_id) == Some(&document) {
+            return false;
+        }
+        self.remove_document(note_id);
+
+        let title_counts = term_counts(&document.title);
+        let body_counts = term_counts(&document.plain_text);
+        let terms = title_counts
+            .keys()
+            .chain(body_counts.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for term in terms {
+            let postings = self.terms.entry(term.clone()).or_default();
+            let position = postings
+                .binary_search_by(|posting| posting.note_id.as_str().cmp(note_id))
+                .unwrap_or_else(|position| position);
+            postings.insert(
+                position,
+                TermPosting {
+                    note_id: note_id.to_string(),
+                    title_hits: title_counts.get(&term).copied().unwrap_or(0),
+                    body_hits: body_counts.get(&term).copied().unwrap_or(0),
+                },
+            );
+        }
+        self.documents.insert(note_id.to_string(), document);
+        true
+    }
+
+    fn move_document(
+        &mut self,
+        trusted_root: &TrustedLibraryRoot,
+        note_id: &str,
+        relative_path: &str,
+    ) -> bool {
+        let Some(document) = self.documents.get_mut(note_id) else {
+            return false;
+        };
+        if document.relative_path == relative_path {
+            return false;
+        }
+        document.relative_path = relative_path.to_string();
+        document.folder_path = folder_path_for_relative_path(relative_path);
+        document.file_stamp = file_stamp(trusted_root, relative_path);
+        true
+    }
+
+    /// Whether every indexed note file is still the one that was indexed.
+    fn matches_files(&self, trusted_root: &TrustedLibraryRoot) -> bool {
+        self.documents.values().all(|document| {
+            document.file_stamp == file_stamp(trusted_root, &document.relative_path)
+        })
+    }
+}
+
+// ── Persistence ──
+
+fn get_search_index_path(root: &Path) -> PathBuf {
+    root.join(SEARCH_INDEX_FILENAME)
+}
+
+/// Reads the persisted index. A missing, unreadable, or outdated file is
+/// treated as absent: the search index is derived data and is rebuilt from the
+/// library instead of failing the caller.
+fn read_search_index(trusted_root: &TrustedLibraryRoot) -> Option<SearchIndex> {
+    let path = get_search_index_path(trusted_root.path());
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return None,
+        Err(error) => {
+            tracing::warn!("read_search_index failed for {}: {error}", path.display());
+            return None;
+        }
+    };
+    match serde_json::from_slice::<SearchIndex>(&bytes) {
+        Ok(index) if index.version == SEARCH_INDEX_VERSION => Some(index),
+        Ok(index) => {
+            tracing::info!(
+                "ignoring search index {} with unsupported version {}",
+                path.display(),
+                index.version
+            );
+            None
+        }
+        Err(error) => {
+            tracing::warn!("parse_search_index failed for {}: {error}", path.display());
+            None
+        }
+    }
+}
+
+fn unique_search_index_tmp_path(index_path: &Path) -> Result<(PathBuf, fs::File), String> {
+    let parent = index_path.parent().unwrap_or_else(|| Path::new("."));
+    let timestamp = now_millis();
+
+    for counter in 0u32.. {
+        let candidate = parent.join(format!(
+            "{}.tmp-{}-{}",
+            SEARCH_INDEX_FILENAME, timestamp, counter
+        ));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => return Ok((candidate, file)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => {
+                return Err(format!(
+                    "create_search_index_temp failed for {}: {error}",
+                    candidate.display()
+                ));
+            }
+        }
+    }
+
+    unreachable!("search index temp counter is unbounded")
+}
+
+fn write_search_index(
+    trusted_root: &TrustedLibraryRoot,
+    index: &SearchIndex,
+) -> Result<(), String> {
+    let index_path = get_search_index_path(trusted_root.path());
+    let json = serde_json::to_vec(index).map_err(|error| {
+        format!(
+            "serialize_search_index failed for {}: {error}",
+            index_path.display()
+        )
+    })?;
+    let (tmp_path, mut tmp_file) = unique_search_index_tmp_path(&index_path)?;
+    let written = tmp_file.write_all(&json).and_then(|()| tmp_file.sync_all());
+    drop(tmp_file);
+
+    let result = written
+        .map_err(|error| {
+            format!(
+                "write_search_index_temp failed for {}: {error}",
+                tmp_path.display()
+            )
+        })
+        .and_then(|()| {
+            validate_trusted_publish_destination(trusted_root, &index_path, "replace_search_index")
+        })
+        .and_then(|()| publish_temp_file(&tmp_path, &index_path, "replace_search_index"));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Applies an incremental change after a library mutation. The change is only
+/// applied when the stored index was built from `previous_index_digest`;
+/// otherwise the file is left stale and the next query rebuilds it.
+fn apply_library_change<F>(
+    trusted_root: &TrustedLibraryRoot,
+    previous_index_digest: Option<&str>,
+    next_index_digest: Option<&str>,
+    apply: F,
+) where
+    F: FnOnce(&mut SearchIndex),
+{
+    let Some(mut index) = read_search_index(trusted_root) else {
+        return;
+    };
+    if index.note_index_digest.as_deref() != previous_index_digest {
+        return;
+    }
+
+    apply(&mut index);
+    index.note_index_digest = next_index_digest.map(str::to_string);
+    if let Err(error) = write_search_index(trusted_root, &index) {
+        tracing::warn!("update_search_index failed: {error}");
+    }
+}
+
+pub(crate) fn record_note_saved(
+    trusted_root: &TrustedLibraryRoot,
+    previous_index_digest: Option<&str>,
+    next_index_digest: Option<&str>,
+    note_id: &str,
+    document: SearchDocument,
+) {
+    apply_library_change(
+        trusted_root,
+        previous_index_digest,
+        next_index_digest,
+        |index| {
+            index.upsert_document(note_id, document.stamped(trusted_root));
+        },
+    );
+}
+
+pub(crate) fn record_notes_removed(
+    trusted_root: &TrustedLibraryRoot,
+    previous_index_digest: Option<&str>,
+    next_index_digest: Option<&str>,
+    note_ids: &[String],
+) {
+    apply_library_change(
+        trusted_root,
+        previous_index_digest,
+        next_index_digest,
+        |index| {
+            for note_id in note_ids {
+                index.remove_document(note_id);
+            }
+        },
+    );
+}
+
+pub(crate) fn record_notes_moved(
+    trusted_root: &TrustedLibraryRoot,
+    previous_index_digest: Option<&str>,
+    next_index_digest: Option<&str>,
+    moves: &[(String, String)],
+) {
+    apply_library_change(
+        trusted_root,
+        previous_index_digest,
+        next_index_digest,
+        |index| {
+            for (note_id, relative_path) in moves {
+                index.move_document(trusted_root, note_id, relative_path);
+            }
+        },
+    );
+}
+
+pub(crate) fn record_external_changes(
+    trusted_root: &TrustedLibraryRoot,
+    previous_index_digest: Option<&str>,
+    next_index_digest: Option<&str>,
+    upserts: Vec<(String, SearchDocument)>,
+    removed_note_ids: &[String],
+) {
+    apply_library_change(
+        trusted_root,
+        previous_index_digest,
+        next_index_digest,
+        |index| {
+            for note_id in removed_note_ids {
+                index.remove_document(note_id);
+            }
+            for (note_id, document) in upserts {
+                index.upsert_document(&note_id, document.stamped(trusted_root));
+            }
+        },
+    );
+}
+
+/// Brings the index in line with a complete library load. Unchanged notes keep
+/// their postings; only added, edited, moved, or vanished notes are touched.
+pub(crate) fn refresh_from_library(
+    trusted_root: &TrustedLibraryRoot,
+    note_index_digest: Option<&str>,
+    note_index: &NoteIndex,
+    notes: &[LoadedNote],
+) {
+    let mut index = read_search_index(trusted_root).unwrap_or_else(|| SearchIndex::empty(None));
+    let mut changed = index.note_index_digest.as_deref() != note_index_digest
+        || !get_search_index_path(trusted_root.path()).exists();
+
+    let live_ids = notes
+        .iter()
+        .map(|note| note.note_id.as_str())
+        .collect::<BTreeSet<_>>();
+    let stale_ids = index
+        .documents
+        .keys()
+        .filter(|note_id| !live_ids.contains(note_id.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    for note_id in stale_ids {
+        changed |= index.remove_document(&note_id);
+    }
+
+    for note in notes {
+        let Some(entry) = note_index.entries.get(&note.note_id) else {
+            continue;
+        };
+        let document =
+            SearchDocument::from_loaded_note(note, &entry.relative_path).stamped(trusted_root);
+        changed |= index.upsert_document(&note.note_id, document);
+    }
+
+    if !changed {
+        return;
+    }
+    index.note_index_digest = note_index_digest.map(str::to_string);
+    if let Err(error) = write_search_index(trusted_root, &index) {
+        tracing::warn!("refresh_search_index failed: {error}");
+    }
+}
+
+// ── Querying ──
+
+struct Candidate {
+    score: f64,
+    title_terms: usize,
+    body_terms: usize,
+}
+
+fn utf16_offsets(chars: &[char]) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(chars.len() + 1);
+    let mut offset = 0;
+    offsets.push(0);
+    for ch in chars {
+        offset += ch.len_utf16();
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// Finds tokens that start with a query term and returns the matched prefix of
+/// each as a char range.
+fn matching_ranges(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    for token in tokenize(text) {
+        let matched = terms
+            .iter()
+            .filter(|term| token.term.starts_with(term.as_str()))
+            .map(|term| term.chars().count())
+            .max();
+        if let Some(length) = matched {
+            ranges.push((token.start, (token.start + length).min(token.end)));
+        }
+    }
+    ranges
+}
+
+fn to_utf16_ranges(
+    chars: &[char],
+    ranges: &[(usize, usize)],
+    char_offset: usize,
+    unit_offset: usize,
+) -> Vec<SearchMatchRange> {
+    let offsets = utf16_offsets(chars);
+    ranges
+        .iter()
+        .map(|(start, end)| SearchMatchRange {
+            start: unit_offset + offsets[start - char_offset],
+            end: unit_offset + offsets[end - char_offset],
+        })
+        .collect()
+}
+
+fn build_snippet(plain_text: &str, terms: &[String]) -> (String, Vec<SearchMatchRange>) {
+    let chars = plain_text
+        .chars()
+        .map(|ch| if ch.is_whitespace() { ' ' } else { ch })
+        .collect::<Vec<_>>();
+    let flattened = chars.iter().collect::<String>();
+    let ranges = matching_ranges(&flattened, terms);
+
+    let anchor = ranges.first().map(|(start, _)| *start).unwrap_or(0);
+    let start = anchor.saturating_sub(SNIPPET_LEADING_CHARS);
+    let end = (start + SNIPPET_MAX_CHARS).min(chars.len());
+    let window = &chars[start..end];
+
+    let mut snippet = String::new();
+    let mut unit_offset = 0;
+    if start > 0 {
+        snippet.push('…');
+        unit_offset = '…'.len_utf16();
+    }
+    snippet.extend(window.iter());
+    if end < chars.len() {
+        snippet.push('…');
+    }
+
+    let visible = ranges
+        .into_iter()
+        .filter(|(range_start, range_end)| *range_start >= start && *range_end <= end)
+        .collect::<Vec<_>>();
+    let matches = to_utf16_ranges(window, &visible, start, unit_offset);
+    (snippet, matches)
+}
+
+fn folder_matches(document: &SearchDocument, folder_filter: Option<&str>) -> bool {
+    match folder_filter {
+        None => true,
+        Some(folder) => {
+            document.folder_path == folder
+                || document.folder_path.starts_with(&format!("{folder}/"))
+        }
+    }
+}
+
+fn run_query(index: &SearchIndex, query: &NoteSearchQuery) -> (Vec<NoteSearchHit>, usize) {
+    let terms = query_terms(&query.query);
+    if terms.is_empty() {
+        return (Vec::new(), 0);
+    }
+    let folder_filter = query
+        .folder_path
+        .as_deref()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty());
+    let document_count = index.documents.len().max(1) as f64;
+
+    let mut candidates: Option<HashMap<&str, Candidate>> = None;
+    for term in &terms {
+        let mut per_note: HashMap<&str, (f64, bool, bool)> = HashMap::new();
+        for (indexed_term, postings) in index.terms.range(term.clone()..) {
+            if !indexed_term.starts_with(term.as_str()) {
+                break;
+            }
+            let idf = (1.0 + document_count / postings.len() as f64).ln();
+            let exact = if indexed_term == term {
+                EXACT_TERM_BONUS
+            } else {
+                0.0
+            };
+            for posting in postings {
+                let title_hits = match query.scope {
+                    SearchScope::Content => 0,
+                    _ => posting.title_hits,
+                };
+                let body_hits = match query.scope {
+                    SearchScope::Title => 0,
+                    _ => posting.body_hits,
+                };
+                if title_hits == 0 && body_hits == 0 {
+                    continue;
+                }
+                let saturate = |hits: u32| hits as f64 / (hits as f64 + 1.2);
+                let score =
+                    idf * (TITLE_WEIGHT * saturate(title_hits) + saturate(body_hits) + exact);
+                let slot = per_note
+                    .entry(posting.note_id.as_str())
+                    .or_insert((0.0, false, false));
+                slot.0 = slot.0.max(score);
+                slot.1 |= title_hits > 0;
+                slot.2 |= body_hits > 0;
+            }
+        }
+
+        candidates = Some(match candidates {
+            None => per_note
+                .into_iter()
+                .map(|(note_id, (score, in_title, in_body))| {
+                    (
+                        note_id,
+                        Candidate {
+                            score,
+                            title_terms: usize::from(in_title),
+                            body_terms: usize::from(in_body),
+                        },
+                    )
+                })
+                .collect(),
+            Some(mut previous) => {
+                previous.retain(|note_id, candidate| match per_note.get(note_id) {
+                    Some((score, in_title, in_body)) => {
+                        candidate.score += score;
+                        candidate.title_terms += usize::from(*in_title);
+                        candidate.body_terms += usize::from(*in_body);
+                        true
+                    }
+                    None => false,
+                });
+                previous
+            }
+        });
+    }
+
+    let mut ranked = candidates
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(note_id, candidate)| {
+            let document = index.documents.get(note_id)?;
+            folder_matches(document, folder_filter).then_some((note_id, document, candidate))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(left_id, left_doc, left), (right_id, right_doc, right)| {
+        right
+            .score
+            .total_cmp(&left.score)
+            .then_with(|| right_doc.updated_at.cmp(&left_doc.updated_at))
+            .then_with(|| left_id.cmp(right_id))
+    });
+
+    let total_hits = ranked.len();
+    let limit = query.limit.unwrap_or(DEFAULT_RESULT_LIMIT);
+    let hits = ranked
+        .into_iter()
+        .take(limit)
+        .map(|(note_id, document, candidate)| {
+            let title_chars = document.title.chars().collect::<Vec<_>>();
+            let title_matches = if candidate.title_terms > 0 {
+                to_utf16_ranges(
+                    &title_chars,
+                    &matching_ranges(&document.title, &terms),
+                    0,
+                    0,
+                )
+            } else {
+                Vec::new()
+            };
+            let (snippet, snippet_matches) = if candidate.body_terms > 0 {
+                build_snippet(&document.plain_text, &terms)
+            } else {
+                build_snippet(&document.plain_text, &[])
+            };
+            NoteSearchHit {
+                note_id: note_id.to_string(),
+                title: document.title.clone(),
+                folder_path: document.folder_path.clone(),
+                updated_at: document.updated_at,
+                score: candidate.score,
+                title_matches,
+                snippet,
+                snippet_matches,
+            }
+        })
+        .collect();
+
+    (hits, total_hits)
+}
+
+fn load_issues_error(root: &Path, issues: &[NoteLoadIssue]) -> String {
+    format!(
+        "rebuild_search_index failed for {}: {}",
+        root.display(),
+        issues
+            .iter()
+            .map(NoteLoadIssue::display)
+            .collect::<Vec<_>>()
+            .join("; ")
+    )
+}
+
+/// Runs a query against the persisted index, rebuilding it from a full library
+/// scan first when it is missing or no longer matches the note index or the
+/// note files.
+pub fn search_notes(
+    auto_save_dir: &Path,
+    query: &NoteSearchQuery,
+) -> Result<NoteSearchResult, String> {
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let current_digest = current_index_digest(&trusted_root)?;
+    let mut rebuilt = false;
+
+    let index = match read_search_index(&trusted_root) {
+        Some(index)
+            if index.note_index_digest == current_digest && index.matches_files(&trusted_root) =>
+        {
+            index
+        }
+        _ => {
+            let load = load_markdown_library(trusted_root.path());
+            if load.load_state != NoteLoadState::Ready {
+                return Err(load_issues_error(trusted_root.path(), &load.issues));
+            }
+            rebuilt = true;
+            read_search_index(&trusted_root).unwrap_or_else(|| SearchIndex::empty(None))
+        }
+    };
+
+    let (hits, total_hits) = run_query(&index, query);
+    Ok(NoteSearchResult {
+        hits,
+        total_hits,
+        rebuilt,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{
+        auto_save_markdown_note, delete_folder, delete_note_file_and_index, rename_folder,
+        AutoSavePayload,
+    };
+    use crate::note_history::NoteHistoryRetention;
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-search-{}-{}-{}",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_temp_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    fn save(dir: &Path, note_id: &str, content: &str, folder: Option<&str>) -> Result<(), String> {
+        auto_save_markdown_note(
+            dir,
+            &AutoSavePayload {
+                note_id: note_id.to_string(),
+                title: String::new(),
+                content: content.to_string(),
+                folder_path: folder.map(str::to_string),
+                is_title_manual: Some(false),
+                is_pinned: None,
+            },
+        )
+        .map(|_| ())
+    }
+
+    fn query(text: &str) -> NoteSearchQuery {
+        NoteSearchQuery {
+            query: text.to_string(),
+            ..NoteSearchQuery::default()
+        }
+    }
+
+    fn hit_ids(result: &NoteSearchResult) -> Vec<String> {
+        result.hits.iter().map(|hit| hit.note_id.clone()).collect()
+    }
+
+    #[test]
+    fn tokenize_lowercases_and_tracks_char_offsets() {
+        let tokens = tokenize("Hello, 세계! v2");
+        let terms = tokens
+            .iter()
+            .map(|token| token.term.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(terms, vec!["hello", "세계", "v2"]);
+        assert_eq!((tokens[1].start, tokens[1].end), (7, 9));
+    }
+
+    #[test]
+    fn search_ranks_title_matches_and_requires_every_term() {
+        let mut index = SearchIndex::empty(None);
+        index.upsert_document(
+            "body",
+            SearchDocument::from_markdown(
+                "body.md",
+                Some("Groceries"),
+                "# Groceries\nbuy rust book",
+                2,
+            ),
+        );
+        index.upsert_document(
+            "title",
+            SearchDocument::from_markdown(
+                "title.md",
+                Some("Rust book notes"),
+                "# Rust book notes\nchapters",
+                1,
+            ),
+        );
+        index.upsert_document(
+            "partial",
+            SearchDocument::from_markdown("partial.md", Some("Rust"), "# Rust\nonly one term", 3),
+        );
+
+        let (hits, total) = run_query(&index, &query("rust boo"));
+        assert_eq!(total, 2);
+        assert_eq!(hits[0].note_id, "title");
+        assert_eq!(hits[1].note_id, "body");
+        assert_eq!(
+            hits[0].title_matches,
+            vec![
+                SearchMatchRange { start: 0, end: 4 },
+                SearchMatchRange { start: 5, end: 8 }
+            ]
+        );
+    }
+
+    #[test]
+    fn search_scope_and_folder_filter_narrow_results() {
+        let mut index = SearchIndex::empty(None);
+        index.upsert_document(
+            "a",
+            SearchDocument::from_markdown("work/a.md", Some("Meeting"), "# Meeting\nagenda", 1),
+        );
+        index.upsert_document(
+            "b",
+            SearchDocument::from_markdown("work/sub/b.md", Some("Agenda"), "# Agenda\nitems", 1),
+        );
+        index.upsert_document(
+            "c",
+            SearchDocument::from_markdown("home/c.md", Some("Agenda"), "# Agenda\nchores", 1),
+        );
+
+        let mut title_query = query("agenda");
+        title_query.scope = SearchScope::Title;
+        title_query.folder_path = Some("work".to_string());
+        let (hits, _) = run_query(&index, &title_query);
+        assert_eq!(
+            hits.iter()
+                .map(|hit| hit.note_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+
+        let mut content_query = query("agenda");
+        content_query.scope = SearchScope::Content;
+        let (hits, _) = run_query(&index, &content_query);
+        let ids = hits
+            .iter()
+            .map(|hit| hit.note_id.as_str())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(ids, BTreeSet::from(["a", "b", "c"]));
+    }
+
+    #[test]
+    fn snippet_offsets_are_utf16_and_relative_to_snippet() {
+        let prefix = "가".repeat(60);
+        let text = format!("{prefix} 😀 target word");
+        let (snippet, matches) = build_snippet(&text, &["target".to_string()]);
+        assert!(snippet.starts_with('…'));
+        let units = snippet.encode_utf16().collect::<Vec<_>>();
+        let matched = String::from_utf16(&units[matches[0].start..matches[0].end]).unwrap();
+        assert_eq!(matched, "target");
+    }
+
+    #[test]
+    fn removing_a_document_drops_its_postings() {
+        let mut index = SearchIndex::empty(None);
+        index.upsert_document(
+            "a",
+            SearchDocument::from_markdown("a.md", None, "# Unique\nzebra", 1),
+        );
+        assert!(index.terms.contains_key("zebra"));
+        assert!(index.remove_document("a"));
+        assert!(index.terms.is_empty());
+    }
+
+    #[test]
+    fn search_notes_builds_index_and_follows_library_mutations() {
+        let dir = make_temp_dir("follows-mutations");
+        let result = (|| -> Result<(), String> {
+            save(&dir, "alpha", "# Alpha\nquarterly planning", Some("work"))?;
+            save(&dir, "beta", "# Beta\nholiday planning", None)?;
+
+            let first = search_notes(&dir, &query("planning"))?;
+            assert!(first.rebuilt);
+            assert_eq!(first.total_hits, 2);
+
+            save(&dir, "beta", "# Beta\nholiday packing", None)?;
+            let after_edit = search_notes(&dir, &query("planning"))?;
+            assert!(!after_edit.rebuilt);
+            assert_eq!(hit_ids(&after_edit), vec!["alpha".to_string()]);
+
+            rename_folder(&dir, "work", "office", &NoteHistoryRetention::default())?;
+            let mut office = query("quarterly");
+            office.folder_path = Some("office".to_string());
+            let after_rename = search_notes(&dir, &office)?;
+            assert!(!after_rename.rebuilt);
+            assert_eq!(after_rename.hits[0].folder_path, "office");
+
+            delete_folder(&dir, "office", &NoteHistoryRetention::default())?;
+            let after_folder_delete = search_notes(&dir, &query("quarterly"))?;
+            assert!(!after_folder_delete.rebuilt);
+            assert_eq!(after_folder_delete.hits[0].folder_path, "");
+
+            delete_note_file_and_index(&dir, "alpha", |path| {
+                fs::remove_file(path).map_err(|e| e.to_string())
+            })?;
+            let after_delete = search_notes(&dir, &query("quarterly"))?;
+            assert!(!after_delete.rebuilt);
+            assert_eq!(after_delete.total_hits, 0);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn search_notes_rebuilds_when_index_changes_externally() {
+        let dir = make_temp_dir("rebuilds-stale");
+        let result = (|| -> Result<(), String> {
+            save(&dir, "alpha", "# Alpha\nkeyword", None)?;
+            assert!(search_notes(&dir, &query("keyword"))?.rebuilt);
+
+            fs::write(dir.join("external.md"), "# External\nkeyword from sync")
+                .map_err(|e| e.to_string())?;
+            let unchanged = search_notes(&dir, &query("sync"))?;
+            assert!(!unchanged.rebuilt);
+            assert_eq!(unchanged.total_hits, 0);
+
+            crate::file_manager::load_markdown_library(&dir);
+            let refreshed = search_notes(&dir, &query("sync"))?;
+            assert_eq!(refreshed.total_hits, 1);
+
+            fs::write(get_search_index_path(&dir), b"not json").map_err(|e| e.to_string())?;
+            let rebuilt = search_notes(&dir, &query("keyword"))?;
+            assert!(rebuilt.rebuilt);
+            assert_eq!(rebuilt.total_hits, 2);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+
+    #[test]
+    fn search_notes_rebuilds_after_an_outside_edit_to_a_note_body() {
+        let dir = make_temp_dir("outside-body-edit");
+        let result = (|| -> Result<(), String> {
+            save(&dir, "alpha", "# Alpha\nkeyword", None)?;
+            assert!(search_notes(&dir, &query("keyword"))?.rebuilt);
+            assert!(!search_notes(&dir, &query("keyword"))?.rebuilt);
+
+            let index = read_search_index(&TrustedLibraryRoot::open(&dir)?).unwrap();
+            let relative_path = &index.documents["alpha"].relative_path;
+            fs::write(
+                dir.join(relative_path),
+                "# Alpha\nreplaced by another editor",
+            )
+            .map_err(|e| e.to_string())?;
+            let edited = search_notes(&dir, &query("editor"))?;
+            assert!(edited.rebuilt);
+            assert_eq!(hit_ids(&edited), vec!["alpha".to_string()]);
+            assert_eq!(search_notes(&dir, &query("keyword"))?.total_hits, 0);
+            Ok(())
+        })();
+        cleanup_temp_dir(&dir);
+        result.unwrap();
+    }
+}
@@ -0,0 +1,1815 @@
+use crate::attachments::{self, ATTACHMENTS_DIRECTORY_NAME};
+use crate::file_manager::{
+    list_folders, load_markdown_library, sanitize_folder_path, LoadedNote, NoteLoadState,
+    TrustedLibraryRoot,
+};
+use crate::note_links::{self, LinkNode};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static TOGGLE_START_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^:::toggle\[(open|closed)\](?:\s+(.*))?\s*$").expect("valid toggle regex")
+});
+static HEADING_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^ {0,3}(#{1,6})(?:[ \t]+(.*?))?[ \t]*$").expect("valid heading regex")
+});
+static LIST_ITEM_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^( *)([-*+]|\d{1,9}[.)])(?:[ \t]+(.*)|$)").expect("valid list item regex")
+});
+static TASK_MARKER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[([ xX])\](?:[ \t]+|$)").expect("valid task marker regex"));
+static TABLE_DELIMITER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*\|?\s*:?-+:?\s*(?:\|\s*:?-+:?\s*)*\|?\s*$").expect("valid table regex")
+});
+pub(crate) static URL_SCHEME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*:").expect("valid url scheme regex"));
+static BREAK_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^<br\s*/?\s*>").expect("valid break tag regex"));
+
+/// Marks a folder as a previous export so a re-export may replace it.
+const SITE_MARKER_FILE: &str = ".hwan-note-site";
+/// Site folder that receives the attachments exported pages link to.
+const SITE_ATTACHMENTS_DIR: &str = "assets/files";
+const SEARCH_TEXT_LIMIT: usize = 4000;
+const DEFAULT_SITE_TITLE: &str = "HwanNote";
+/// URL schemes an exported page may link to; links with any other scheme
+/// (`javascript:`, `data:`, `file:` …) are dropped.
+const SAFE_LINK_SCHEMES: &[&str] = &["http:", "https:", "mailto:"];
+
+const SITE_STYLESHEET: &str = r#":root { color-scheme: light dark; --accent: #2f6fde; --muted: #6b7280; --line: #d9dde3; }
+body { margin: 0; font-family: "Segoe UI", system-ui, -apple-system, sans-serif; line-height: 1.65; }
+header.site-header { display: flex; gap: 1rem; align-items: center; padding: 0.75rem 1.5rem; border-bottom: 1px solid var(--line); }
+header.site-header .site-title { font-weight: 600; text-decoration: none; color: inherit; }
+header.site-header nav { flex: 1; color: var(--muted); font-size: 0.9rem; }
+main { max-width: 52rem; margin: 0 auto; padding: 1.5rem; }
+a { color: var(--accent); }
+.missing-link { color: var(--muted); text-decoration: line-through; }
+pre { padding: 0.75rem 1rem; overflow-x: auto; border: 1px solid var(--line); border-radius: 6px; }
+code { font-family: Consolas, "Cascadia Mono", monospace; font-size: 0.92em; }
+blockquote { margin: 0; padding-left: 1rem; border-left: 3px solid var(--line); color: var(--muted); }
+table { border-collapse: collapse; }
+th, td { border: 1px solid var(--line); padding: 0.3rem 0.6rem; }
+ul.task-list { list-style: none; padding-left: 1.2rem; }
+li.task-list-item input { margin-right: 0.4rem; }
+details { border: 1px solid var(--line); border-radius: 6px; padding: 0.25rem 0.75rem; margin: 0.75rem 0; }
+summary { cursor: pointer; font-weight: 600; }
+.tags { color: var(--muted); font-size: 0.9rem; }
+.folder-list, .note-list, #search-results { padding-left: 1.2rem; }
+.search-snippet { color: var(--muted); font-size: 0.9rem; }
+#search-input { width: 100%; padding: 0.5rem; font-size: 1rem; }
+"#;
+
+const SEARCH_SCRIPT: &str = r#"(function () {
+  var notes = window.HWAN_NOTE_SEARCH || [];
+  var input = document.getElementById("search-input");
+  var results = document.getElementById("search-results");
+
+  function snippet(text, term) {
+    var index = text.toLowerCase().indexOf(term);
+    if (index < 0) {
+      return text.slice(0, 160);
+    }
+    return text.slice(Math.max(0, index - 60), index + 100);
+  }
+
+  function render(query) {
+    var terms = query.toLowerCase().split(/\s+/).filter(Boolean);
+    results.textContent = "";
+    if (terms.length === 0) {
+      return;
+    }
+    notes
+      .filter(function (note) {
+        var haystack = (note.title + "\n" + note.tags.join(" ") + "\n" + note.text).toLowerCase();
+        return terms.every(function (term) { return haystack.indexOf(term) >= 0; });
+      })
+      .forEach(function (note) {
+        var item = document.createElement("li");
+        var link = document.createElement("a");
+        link.href = note.path;
+        link.textContent = note.title;
+        var detail = document.createElement("div");
+        detail.className = "search-snippet";
+        detail.textContent = snippet(note.text, terms[0]);
+        item.appendChild(link);
+        item.appendChild(detail);
+        results.appendChild(item);
+      });
+  }
+
+  input.addEventListener("input", function () { render(input.value); });
+  render(input.value);
+})();
+"#;
+
+// ── Types ──
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteExportOptions {
+    /// Library folder whose subtree becomes the site root. `None` exports the
+    /// whole library.
+    pub folder_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteExportResult {
+    pub output_dir: String,
+    pub note_count: usize,
+    pub folder_count: usize,
+    pub attachment_count: usize,
+    pub unresolved_link_count: usize,
+}
+
+/// A `--export-site` invocation parsed from the process arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteExportCliRequest {
+    pub output_dir: PathBuf,
+    pub library_dir: Option<PathBuf>,
+    pub folder_path: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    title: &'a str,
+    path: &'a str,
+    folder: &'a str,
+    tags: &'a [String],
+    text: String,
+}
+
+/// Rewrites link and image destinations while rendering one page. `None` means
+/// the link points at a note or attachment that is not part of the export.
+trait LinkRewriter {
+    fn wiki_link(&self, target: &str) -> Option<String>;
+    fn markdown_link(&self, href: &str) -> Option<String>;
+}
+
+// ── Markdown rendering ──
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        push_escaped_char(&mut escaped, character);
+    }
+    escaped
+}
+
+fn push_escaped_char(out: &mut String, character: char) {
+    match character {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        '\'' => out.push_str("&#39;"),
+        _ => out.push(character),
+    }
+}
+
+/// Builds a heading anchor the way most Markdown sites do: lowercase words
+/// joined by hyphens, punctuation dropped, non-ASCII letters kept.
+fn heading_slug(text: &str) -> String {
+    let mut slug = String::new();
+    for character in text.trim().chars() {
+        if character.is_alphanumeric() {
+            slug.extend(character.to_lowercase());
+        } else if (character.is_whitespace() || character == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+fn count_run(chars: &[char], start: usize, target: char) -> usize {
+    chars[start..]
+        .iter()
+        .take_while(|character| **character == target)
+        .count()
+}
+
+fn find_code_span_end(chars: &[char], from: usize, run: usize) -> Option<usize> {
+    let mut index = from;
+    while index < chars.len() {
+        if chars[index] == '`' {
+            let length = count_run(chars, index, '`');
+            if length == run {
+                return Some(index);
+            }
+            index += length;
+        } else {
+            index += 1;
+        }
+    }
+    None
+}
+
+fn find_closing_delimiter(
+    chars: &[char],
+    from: usize,
+    delimiter: char,
+    run: usize,
+) -> Option<usize> {
+    let mut index = from;
+    while index + run <= chars.len() {
+        if chars[index] == '`' {
+            let length = count_run(chars, index, '`');
+            index = find_code_span_end(chars, index + length, length)
+                .map_or(index + length, |end| end + length);
+            continue;
+        }
+        let length = count_run(chars, index, delimiter);
+        if length == 0 {
+            index += 1;
+            continue;
+        }
+        if length == run && index > from && !chars[index - 1].is_whitespace() {
+            return Some(index);
+        }
+        index += length;
+    }
+    None
+}
+
+/// Parses `[label](destination "title")` starting at the opening bracket and
+/// returns the label, the destination, and the index just past the link.
+fn parse_inline_link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let mut index = start;
+    let label_end = loop {
+        match chars.get(index)? {
+            '\\' => index += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break index;
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    };
+    if chars.get(label_end + 1) != Some(&'(') {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut index = label_end + 2;
+    let destination_end = loop {
+        match chars.get(index)? {
+            '\\' => index += 1,
+            '(' => depth += 1,
+            ')' if depth == 0 => break index,
+            ')' => depth -= 1,
+            '\n' => return None,
+            _ => {}
+        }
+        index += 1;
+    };
+
+    let label: String = chars[start + 1..label_end].iter().collect();
+    let raw_destination: String = chars[label_end + 2..destination_end].iter().collect();
+    let raw_destination = raw_destination.trim();
+    let destination = if let Some(rest) = raw_destination.strip_prefix('<') {
+        rest.split_once('>').map_or(rest, |(inner, _)| inner)
+    } else {
+        raw_destination
+            .split_once(char::is_whitespace)
+            .map_or(raw_destination, |(url, _)| url)
+    };
+    Some((label, destination.to_string(), destination_end + 1))
+}
+
+fn render_inline(text: &str, links: &dyn LinkRewriter) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let character = chars[index];
+        let next = chars.get(index + 1).copied();
+        match character {
+            '\\' if next.is_some_and(|next| next.is_ascii_punctuation()) => {
+                push_escaped_char(&mut out, chars[index + 1]);
+                index += 2;
+            }
+            '\\' if next == Some('\n') => {
+                out.push_str("<br>\n");
+                index += 2;
+            }
+            '`' => {
+                let run = count_run(&chars, index, '`');
+                match find_code_span_end(&chars, index + run, run) {
+                    Some(end) => {
+                        let code: String = chars[index + run..end].iter().collect();
+                        let code = if code.len() > 1 && code.starts_with(' ') && code.ends_with(' ')
+                        {
+                            &code[1..code.len() - 1]
+                        } else {
+                            code.as_str()
+                        };
+                        out.push_str("<code>");
+                        out.push_str(&escape_html(&code.replace('\n', " ")));
+                        out.push_str("</code>");
+                        index = end + run;
+                    }
+                    None => {
+                        out.push_str(&"`".repeat(run));
+                        index += run;
+                    }
+                }
+            }
+            '[' if next == Some('[') => {
+                let rest: String = chars[index + 2..].iter().collect();
+                let closing = rest
+                    .find("]]")
+                    .filter(|end| !rest[..*end].contains(['[', ']', '\n']));
+                match closing {
+                    Some(end) => {
+                        let inner = &rest[..end];
+                        let (target, label) = match inner.split_once('|') {
+                            Some((target, label)) => (target.trim(), label.trim()),
+                            None => (inner.trim(), inner.trim()),
+                        };
+                        push_link(&mut out, links.wiki_link(target), &escape_html(label));
+                        index += 2 + inner.chars().count() + 2;
+                    }
+                    None => {
+                        out.push('[');
+                        index += 1;
+                    }
+                }
+            }
+            '!' if next == Some('[') => match parse_inline_link(&chars, index + 1) {
+                Some((alt, source, end)) => {
+                    match links.markdown_link(&source) {
+                        Some(source) => out.push_str(&format!(
+                            "<img src=\"{}\" alt=\"{}\">",
+                            escape_html(&source),
+                            escape_html(&alt)
+                        )),
+                        None => out.push_str(&format!(
+                            "<span class=\"missing-link\">{}</span>",
+                            escape_html(&alt)
+                        )),
+                    }
+                    index = end;
+                }
+                None => {
+                    out.push('!');
+                    index += 1;
+                }
+            },
+            '[' => match parse_inline_link(&chars, index) {
+                Some((label, href, end)) => {
+                    push_link(
+                        &mut out,
+                        links.markdown_link(&href),
+                        &render_inline(&label, links),
+                    );
+                    index = end;
+                }
+                None => {
+                    out.push('[');
+                    index += 1;
+                }
+            },
+            '<' => {
+                let rest: String = chars[index..].iter().take(8).collect();
+                match BREAK_TAG_RE.find(&rest) {
+                    Some(found) => {
+                        out.push_str("<br>");
+                        index += found.as_str().chars().count();
+                    }
+                    None => {
+                        out.push_str("&lt;");
+                        index += 1;
+                    }
+                }
+            }
+            '*' | '_' | '~' => {
+                let run = count_run(&chars, index, character);
+                let intraword = character == '_' && index > 0 && chars[index - 1].is_alphanumeric();
+                let (tag, width) = match (character, run) {
+                    ('~', 2) => ("del", 2),
+                    ('*' | '_', 2) => ("strong", 2),
+                    ('*' | '_', 1) => ("em", 1),
+                    _ => ("", 0),
+                };
+                let opens = width > 0
+                    && !intraword
+                    && chars
+                        .get(index + width)
+                        .is_some_and(|next| !next.is_whitespace());
+                match opens
+                    .then(|| find_closing_delimiter(&chars, index + width, character, width))
+                    .flatten()
+                {
+                    Some(end) => {
+                        let inner: String = chars[index + width..end].iter().collect();
+                        out.push_str(&format!("<{tag}>{}</{tag}>", render_inline(&inner, links)));
+                        index = end + width;
+                    }
+                    None => {
+                        for _ in 0..run {
+                            out.push(character);
+                        }
+                        index += run;
+                    }
+                }
+            }
+            '\n' => {
+                if out.ends_with("  ") {
+                    out.truncate(out.trim_end_matches(' ').len());
+                    out.push_str("<br>\n");
+                } else {
+                    out.push('\n');
+                }
+                index += 1;
+            }
+            _ => {
+                push_escaped_char(&mut out, character);
+                index += 1;
+            }
+        }
+    }
+    out
+}
+
+fn push_link(out: &mut String, href: Option<String>, label_html: &str) {
+    match href {
+        Some(href) => out.push_str(&format!(
+            "<a href=\"{}\">{label_html}</a>",
+            escape_html(&href)
+        )),
+        None => out.push_str(&format!("<span class=\"missing-link\">{label_html}</span>")),
+    }
+}
+
+fn fence_marker(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let trimmed = &line[indent..];
+    let character = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let run = trimmed.chars().take_while(|c| *c == character).count();
+    (run >= 3).then(|| &trimmed[..run])
+}
+
+fn closes_fence(line: &str, opening: &str) -> bool {
+    fence_marker(line).is_some_and(|marker| {
+        marker.starts_with(&opening[..1])
+            && marker.len() >= opening.len()
+            && line.trim()[marker.len()..].trim().is_empty()
+    })
+}
+
+fn toggle_syntax(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    (indent <= 3 && !line.starts_with('\t')).then(|| line.trim())
+}
+
+/// Finds the `:::` line that closes the toggle opened at `start`, skipping
+/// fenced code and nested toggles, mirroring the editor's parser.
+fn find_toggle_end(lines: &[&str], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut fence: Option<&str> = None;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        if let Some(opening) = fence {
+            if closes_fence(line, opening) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = fence_marker(line) {
+            fence = Some(marker);
+            continue;
+        }
+        match toggle_syntax(line) {
+            Some(syntax) if TOGGLE_START_RE.is_match(syntax) => depth += 1,
+            Some(":::") => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    let Some(first) = compact.chars().next() else {
+        return false;
+    };
+    line.len() - line.trim_start_matches(' ').len() <= 3
+        && matches!(first, '-' | '*' | '_')
+        && compact.len() >= 3
+        && compact.chars().all(|c| c == first)
+}
+
+fn blockquote_content(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let rest = trimmed.strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+fn split_table_row(line: &str) -> Vec<String> {
+    let trimmed = line.trim();
+    let trimmed = trimmed.strip_prefix('|').unwrap_or(trimmed);
+    let trimmed = trimmed.strip_suffix('|').unwrap_or(trimmed);
+    let mut cells = Vec::new();
+    let mut current = String::new();
+    let mut chars = trimmed.chars().peekable();
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' if chars.peek() == Some(&'|') => {
+                current.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(character),
+        }
+    }
+    cells.push(current.trim().to_string());
+    cells
+}
+
+fn starts_block(lines: &[&str], index: usize) -> bool {
+    let line = lines[index];
+    fence_marker(line).is_some()
+        || HEADING_RE.is_match(line)
+        || blockquote_content(line).is_some()
+        || is_thematic_break(line)
+        || LIST_ITEM_RE.is_match(line)
+        || toggle_syntax(line).is_some_and(|syntax| TOGGLE_START_RE.is_match(syntax))
+}
+
+struct BlockRenderer<'a> {
+    links: &'a dyn LinkRewriter,
+    heading_ids: HashMap<String, usize>,
+}
+
+impl BlockRenderer<'_> {
+    fn heading_id(&mut self, text: &str) -> String {
+        let plain: String = text
+            .chars()
+            .filter(|c| !matches!(c, '*' | '_' | '~' | '`' | '[' | ']'))
+            .collect();
+        let slug = heading_slug(&plain);
+        let slug = if slug.is_empty() {
+            "section".to_string()
+        } else {
+            slug
+        };
+        let seen = self.heading_ids.entry(slug.clone()).or_insert(0);
+        *seen += 1;
+        if *seen == 1 {
+            slug
+        } else {
+            format!("{slug}-{}", *seen - 1)
+        }
+    }
+
+    /// Renders `lines` as block HTML. `tight` drops the `<p>` around plain
+    /// paragraphs, as in a list item without blank lines.
+    fn render_blocks(&mut self, lines: &[&str], tight: bool) -> String {
+        let mut out = String::new();
+        let mut index = 0;
+        while index < lines.len() {
+            let line = lines[index];
+            if line.trim().is_empty() {
+                index += 1;
+                continue;
+            }
+
+            if let Some(opening) = fence_marker(line) {
+                let info = line.trim()[opening.len()..].trim();
+                let language = info.split_whitespace().next().unwrap_or("");
+                let mut end = index + 1;
+                while end < lines.len() && !closes_fence(lines[end], opening) {
+                    end += 1;
+                }
+                let code = lines[index + 1..end.min(lines.len())].join("\n");
+                if language.is_empty() {
+                    out.push_str("<pre><code>");
+                } else {
+                    out.push_str(&format!(
+                        "<pre><code class=\"language-{}\">",
+                        escape_html(language)
+                    ));
+                }
+                out.push_str(&escape_html(&code));
+                if !code.is_empty() {
+                    out.push('\n');
+                }
+                out.push_str("</code></pre>\n");
+                index = end + 1;
+                continue;
+            }
+
+            if let Some(captures) =
+                toggle_syntax(line).and_then(|syntax| TOGGLE_START_RE.captures(syntax))
+            {
+                if let Some(end) = find_toggle_end(lines, index) {
+                    let open = captures[1].eq_ignore_ascii_case("open");
+                    let summary = captures.get(2).map_or("", |m| m.as_str()).trim();
+                    out.push_str(if open { "<details open>" } else { "<details>" });
+                    out.push_str(&format!(
+                        "<summary>{}</summary>\n",
+                        render_inline(summary, self.links)
+                    ));
+                    out.push_str(&self.render_blocks(&lines[index + 1..end], false));
+                    out.push_str("</details>\n");
+                    index = end + 1;
+                    continue;
+                }
+            }
+
+            if let Some(captures) = HEADING_RE.captures(line) {
+                let level = captures[1].len();
+                let text = captures.get(2).map_or("", |m| m.as_str());
+                let text = text.trim_end_matches('#').trim_end();
+                let id = self.heading_id(text);
+                out.push_str(&format!(
+                    "<h{level} id=\"{}\">{}</h{level}>\n",
+                    escape_html(&id),
+                    render_inline(text, self.links)
+                ));
+                index += 1;
+                continue;
+            }
+
+            if is_thematic_break(line) {
+                out.push_str("<hr>\n");
+                index += 1;
+                continue;
+            }
+
+            if blockquote_content(line).is_some() {
+                let mut quoted = Vec::new();
+                while let Some(content) = lines.get(index).and_then(|line| blockquote_content(line))
+                {
+                    quoted.push(content);
+                    index += 1;
+                }
+                out.push_str("<blockquote>\n");
+                out.push_str(&self.render_blocks(&quoted, false));
+                out.push_str("</blockquote>\n");
+                continue;
+            }
+
+            if LIST_ITEM_RE.is_match(line) {
+                index = self.render_list(lines, index, &mut out);
+                continue;
+            }
+
+            if line.contains('|')
+                && lines
+                    .get(index + 1)
+                    .is_some_and(|next| next.contains('-') && TABLE_DELIMITER_RE.is_match(next))
+            {
+                index = self.render_table(lines, index, &mut out);
+                continue;
+            }
+
+            let start = index;
+            index += 1;
+            while index < lines.len()
+                && !lines[index].trim().is_empty()
+                && !starts_block(lines, index)
+            {
+                index += 1;
+            }
+            let text = lines[start..index]
+                .iter()
+                .map(|line| line.trim_start())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let rendered = render_inline(text.trim_end(), self.links);
+            if tight {
+                out.push_str(&rendered);
+                out.push('\n');
+            } else {
+                out.push_str(&format!("<p>{rendered}</p>\n"));
+            }
+        }
+        out
+    }
+
+    fn render_list(&mut self, lines: &[&str], start: usize, out: &mut String) -> usize {
+        let captures = LIST_ITEM_RE
+            .captures(lines[start])
+            .expect("list start was matched by the caller");
+        let indent = captures[1].len();
+        let ordered = captures[2].ends_with(['.', ')']);
+        let first_number = captures[2]
+            .trim_end_matches(['.', ')'])
+            .parse::<u64>()
+            .unwrap_or(1);
+
+        let mut items: Vec<Vec<&str>> = Vec::new();
+        let mut loose = false;
+        let mut index = start;
+        while index < lines.len() {
+            let line = lines[index];
+            let item_start = LIST_ITEM_RE.captures(line).filter(|captures| {
+                captures[1].len() == indent && captures[2].ends_with(['.', ')']) == ordered
+            });
+            if let Some(captures) = item_start {
+                let content_offset = captures.get(3).map_or(line.len(), |m| m.start());
+                items.push(vec![&line[content_offset.min(line.len())..]]);
+                index += 1;
+                continue;
+            }
+
+            let continuation_indent = indent + 2;
+            let leading = line.len() - line.trim_start_matches(' ').len();
+            if line.trim().is_empty() {
+                let continues = lines[index + 1..]
+                    .iter()
+                    .find(|next| !next.trim().is_empty())
+                    .is_some_and(|next| {
+                        let next_leading = next.len() - next.trim_start_matches(' ').len();
+                        next_leading >= continuation_indent
+                            || LIST_ITEM_RE.captures(next).is_some_and(|captures| {
+                                captures[1].len() == indent
+                                    && captures[2].ends_with(['.', ')']) == ordered
+                            })
+                    });
+                if !continues {
+                    break;
+                }
+                loose = true;
+                if let Some(item) = items.last_mut() {
+                    item.push("");
+                }
+                index += 1;
+                continue;
+            }
+            if leading >= continuation_indent {
+                if let Some(item) = items.last_mut() {
+                    item.push(&line[continuation_indent.min(leading)..]);
+                }
+                index += 1;
+                continue;
+            }
+            if leading > indent && !starts_block(lines, index) {
+                if let Some(item) = items.last_mut() {
+                    item.push(line.trim_start());
+                }
+                index += 1;
+                continue;
+            }
+            break;
+        }
+
+        let is_task = |item: &Vec<&str>| !ordered && TASK_MARKER_RE.is_match(item[0]);
+        let has_tasks = items.iter().any(is_task);
+        if ordered {
+            if first_number == 1 {
+                out.push_str("<ol>\n");
+            } else {
+                out.push_str(&format!("<ol start=\"{first_number}\">\n"));
+            }
+        } else if has_tasks {
+            out.push_str("<ul class=\"task-list\">\n");
+        } else {
+            out.push_str("<ul>\n");
+        }
+        for mut item in items {
+            let task = TASK_MARKER_RE
+                .captures(item[0])
+                .filter(|_| !ordered)
+                .map(|captures| {
+                    let checked = !captures[1].trim().is_empty();
+                    (checked, captures[0].len())
+                });
+            match task {
+                Some((checked, marker_length)) => {
+                    item[0] = &item[0][marker_length..];
+                    out.push_str(if checked {
+                        "<li class=\"task-list-item\"><input type=\"checkbox\" disabled checked>"
+                    } else {
+                        "<li class=\"task-list-item\"><input type=\"checkbox\" disabled>"
+                    });
+                }
+                None => out.push_str("<li>"),
+            }
+            while item.last().is_some_and(|line| line.trim().is_empty()) {
+                item.pop();
+            }
+            let body = self.render_blocks(&item, !loose);
+            out.push_str(body.trim_end());
+            out.push_str("</li>\n");
+        }
+        out.push_str(if ordered { "</ol>\n" } else { "</ul>\n" });
+        index
+    }
+
+    fn render_table(&mut self, lines: &[&str], start: usize, out: &mut String) -> usize {
+        let header = split_table_row(lines[start]);
+        let alignments: Vec<&str> = split_table_row(lines[start + 1])
+            .iter()
+            .map(|cell| match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => " style=\"text-align: center\"",
+                (false, true) => " style=\"text-align: right\"",
+                (true, false) => " style=\"text-align: left\"",
+                (false, false) => "",
+            })
+            .collect();
+        let alignment = |column: usize| alignments.get(column).copied().unwrap_or("");
+
+        out.push_str("<table>\n<thead><tr>");
+        for (column, cell) in header.iter().enumerate() {
+            out.push_str(&format!(
+                "<th{}>{}</th>",
+                alignment(column),
+                render_inline(cell, self.links)
+            ));
+        }
+        out.push_str("</tr></thead>\n<tbody>\n");
+        let mut index = start + 2;
+        while index < lines.len() && lines[index].contains('|') && !lines[index].trim().is_empty() {
+            out.push_str("<tr>");
+            let cells = split_table_row(lines[index]);
+            for column in 0..header.len() {
+                let cell = cells.get(column).map_or("", String::as_str);
+                out.push_str(&format!(
+                    "<td{}>{}</td>",
+                    alignment(column),
+                    render_inline(cell, self.links)
+                ));
+            }
+            out.push_str("</tr>\n");
+            index += 1;
+        }
+        out.push_str("</tbody>\n</table>\n");
+        index
+    }
+}
+
+fn render_markdown(markdown: &str, links: &dyn LinkRewriter) -> String {
+    let normalized = markdown
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n");
+    let lines: Vec<&str> = normalized.split('\n').collect();
+    BlockRenderer {
+        links,
+        heading_ids: HashMap::new(),
+    }
+    .render_blocks(&lines, false)
+}
+
+// ── Site layout ──
+
+struct SitePage {
+    note: LoadedNote,
+    library_path: String,
+    site_folder: String,
+    site_path: String,
+}
+
+struct SitePlan {
+    title: String,
+    pages: Vec<SitePage>,
+    folders: BTreeSet<String>,
+    page_by_note_id: HashMap<String, usize>,
+    page_by_library_path: HashMap<String, usize>,
+    link_targets: HashMap<String, String>,
+    /// Names of the attachments stored in the library.
+    attachments: HashSet<String>,
+}
+
+fn join_site_path(folder: &str, name: &str) -> String {
+    if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{folder}/{name}")
+    }
+}
+
+fn folder_index_path(folder: &str) -> String {
+    join_site_path(folder, "index.html")
+}
+
+fn encode_href_segment(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+pub(crate) fn decode_percent(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Returns the href from the page at `from` to the site file at `to`, both
+/// site-relative paths.
+fn relative_href(from: &str, to: &str) -> String {
+    let from_dirs: Vec<&str> = from.split('/').collect::<Vec<_>>();
+    let from_dirs = &from_dirs[..from_dirs.len() - 1];
+    let to_parts: Vec<&str> = to.split('/').collect();
+    let common = from_dirs
+        .iter()
+        .zip(&to_parts[..to_parts.len() - 1])
+        .take_while(|(left, right)| left == right)
+        .count();
+    let mut parts: Vec<String> = vec!["..".to_string(); from_dirs.len() - common];
+    parts.extend(
+        to_parts[common..]
+            .iter()
+            .map(|part| encode_href_segment(part)),
+    );
+    parts.join("/")
+}
+
+/// Resolves a relative Markdown link against the folder of the note it appears
+/// in and returns a normalized library path, or `None` if it climbs out of the
+/// library.
+pub(crate) fn resolve_library_path(note_library_path: &str, href_path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = if href_path.starts_with('/') {
+        Vec::new()
+    } else {
+        let mut parts: Vec<&str> = note_library_path.split('/').collect();
+        parts.pop();
+        parts
+    };
+    for segment in href_path.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            segment => parts.push(segment),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+struct PageLinks<'a> {
+    plan: &'a SitePlan,
+    page: &'a SitePage,
+    unresolved: std::cell::Cell<usize>,
+    attachments: std::cell::RefCell<BTreeSet<String>>,
+}
+
+impl PageLinks<'_> {
+    /// Points a link into the library's attachments directory at the copy the
+    /// export writes under `SITE_ATTACHMENTS_DIR`.
+    fn attachment_link(&self, file_name: &str) -> Option<String> {
+        if !self.plan.attachments.contains(file_name) {
+            self.unresolved.set(self.unresolved.get() + 1);
+            return None;
+        }
+        self.attachments.borrow_mut().insert(file_name.to_string());
+        Some(relative_href(
+            &self.page.site_path,
+            &join_site_path(SITE_ATTACHMENTS_DIR, file_name),
+        ))
+    }
+}
+
+impl LinkRewriter for PageLinks<'_> {
+    fn wiki_link(&self, target: &str) -> Option<String> {
+        let (_, anchor) = target
+            .split_once('#')
+            .map_or((target, None), |(name, anchor)| (name, Some(anchor)));
+        let href = self
+            .plan
+            .link_targets
+            .get(target)
+            .and_then(|note_id| self.plan.page_by_note_id.get(note_id))
+            .map(|page| relative_href(&self.page.site_path, &self.plan.pages[*page].site_path))
+            .map(|href| match anchor {
+                Some(anchor) => format!("{href}#{}", heading_slug(anchor)),
+                None => href,
+            });
+        if href.is_none() {
+            self.unresolved.set(self.unresolved.get() + 1);
+        }
+        href
+    }
+
+    fn markdown_link(&self, href: &str) -> Option<String> {
+        // Browsers skip whitespace and control characters inside a scheme, so
+        // they are dropped before the scheme is checked.
+        let probe: String = href
+            .chars()
+            .filter(|c| !c.is_whitespace() && !c.is_control())
+            .collect();
+        if let Some(scheme) = URL_SCHEME_RE.find(&probe) {
+            let scheme = scheme.as_str().to_ascii_lowercase();
+            return SAFE_LINK_SCHEMES
+                .contains(&scheme.as_str())
+                .then(|| href.to_string());
+        }
+        if href.starts_with('#') || probe.starts_with("//") {
+            return Some(href.to_string());
+        }
+        let (path, anchor) = href
+            .split_once('#')
+            .map_or((href, None), |(path, anchor)| (path, Some(anchor)));
+        let decoded = decode_percent(path);
+        let library_path = resolve_library_path(&self.page.library_path, &decoded);
+        if let Some(file_name) = library_path.as_deref().and_then(|library_path| {
+            library_path
+                .strip_prefix(ATTACHMENTS_DIRECTORY_NAME)?
+                .strip_prefix('/')
+        }) {
+            return self.attachment_link(file_name);
+        }
+        let lower = decoded.to_lowercase();
+        if !(lower.ends_with(".md") || lower.ends_with(".markdown")) {
+            return Some(href.to_string());
+        }
+        let page = library_path.and_then(|library_path| {
+            self.plan
+                .page_by_library_path
+                .get(&library_path.to_lowercase())
+        });
+        match page {
+            Some(page) => {
+                let target = relative_href(&self.page.site_path, &self.plan.pages[*page].site_path);
+                Some(match anchor {
+                    Some(anchor) => format!("{target}#{anchor}"),
+                    None => target,
+                })
+            }
+            None => {
+                self.unresolved.set(self.unresolved.get() + 1);
+                None
+            }
+        }
+    }
+}
+
+fn strip_subtree<'a>(folder: &'a str, subtree: &str) -> Option<&'a str> {
+    if subtree.is_empty() {
+        return Some(folder);
+    }
+    if folder == subtree {
+        return Some("");
+    }
+    folder.strip_prefix(subtree)?.strip_prefix('/')
+}
+
+fn plan_site(library_dir: &Path, subtree: &str) -> Result<SitePlan, String> {
+    let trusted_root = TrustedLibraryRoot::open(library_dir)?;
+    let load = load_markdown_library(trusted_root.path());
+    if load.load_state != NoteLoadState::Ready {
+        return Err(format!(
+            "export_site failed for {}: the library did not load completely",
+            trusted_root.path().display()
+        ));
+    }
+
+    let mut folders = BTreeSet::from([String::new()]);
+    for folder in list_folders(trusted_root.path())? {
+        if let Some(site_folder) = strip_subtree(&folder, subtree) {
+            let mut current = String::new();
+            for segment in site_folder.split('/').filter(|segment| !segment.is_empty()) {
+                current = join_site_path(&current, segment);
+                folders.insert(current.clone());
+            }
+        }
+    }
+
+    let mut notes: Vec<(String, LoadedNote)> = load
+        .notes
+        .into_iter()
+        // Encrypted notes only exist as ciphertext on disk; never publish them.
+        .filter(|note| !note.is_encrypted)
+        .filter_map(|note| {
+            let library_path = Path::new(&note.file_path)
+                .strip_prefix(trusted_root.path())
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            strip_subtree(&note.folder_path, subtree)?;
+            Some((library_path, note))
+        })
+        .collect();
+    notes.sort_by(|left, right| left.0.cmp(&right.0));
+
+    let mut taken: HashSet<String> = folders
+        .iter()
+        .map(|folder| folder_index_path(folder).to_lowercase())
+        .collect();
+    taken.insert("search.html".to_string());
+    let mut pages = Vec::new();
+    for (library_path, note) in notes {
+        let site_folder = strip_subtree(&note.folder_path, subtree)
+            .unwrap_or_default()
+            .to_string();
+        let mut current = String::new();
+        for segment in site_folder.split('/').filter(|segment| !segment.is_empty()) {
+            current = join_site_path(&current, segment);
+            if folders.insert(current.clone()) {
+                taken.insert(folder_index_path(&current).to_lowercase());
+            }
+        }
+        let stem = Path::new(&library_path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| note.note_id.clone());
+        let mut site_path = join_site_path(&site_folder, &format!("{stem}.html"));
+        let mut counter = 2;
+        while !taken.insert(site_path.to_lowercase()) {
+            site_path = join_site_path(&site_folder, &format!("{stem}-{counter}.html"));
+            counter += 1;
+        }
+        pages.push(SitePage {
+            note,
+            library_path,
+            site_folder,
+            site_path,
+        });
+    }
+
+    let link_targets = note_links::resolve_link_targets(
+        pages
+            .iter()
+            .map(|page| {
+                (
+                    page.note.note_id.clone(),
+                    LinkNode::from_loaded_note(&page.note, &page.library_path),
+                )
+            })
+            .collect(),
+    );
+    let attachments = attachments::list_attachments(&trusted_root)?
+        .into_iter()
+        .map(|attachment| attachment.file_name)
+        .collect();
+    let title = subtree
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_SITE_TITLE)
+        .to_string();
+
+    Ok(SitePlan {
+        title,
+        page_by_note_id: pages
+            .iter()
+            .enumerate()
+            .map(|(index, page)| (page.note.note_id.clone(), index))
+            .collect(),
+        page_by_library_path: pages
+            .iter()
+            .enumerate()
+            .map(|(index, page)| (page.library_path.to_lowercase(), index))
+            .collect(),
+        pages,
+        folders,
+        link_targets,
+        attachments,
+    })
+}
+
+// ── Page templates ──
+
+fn breadcrumbs(plan: &SitePlan, page_path: &str, folder: &str) -> String {
+    let mut crumbs = vec![format!(
+        "<a href=\"{}\">{}</a>",
+        relative_href(page_path, "index.html"),
+        escape_html(&plan.title)
+    )];
+    let mut current = String::new();
+    for segment in folder.split('/').filter(|segment| !segment.is_empty()) {
+        current = join_site_path(&current, segment);
+        crumbs.push(format!(
+            "<a href=\"{}\">{}</a>",
+            relative_href(page_path, &folder_index_path(&current)),
+            escape_html(segment)
+        ));
+    }
+    crumbs.join(" / ")
+}
+
+fn page_html(plan: &SitePlan, page_path: &str, title: &str, crumbs: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{title} · {site}</title>\n<link rel=\"stylesheet\" href=\"{style}\">\n</head>\n<body>\n<header class=\"site-header\"><a class=\"site-title\" href=\"{home}\">{site}</a><nav>{crumbs}</nav><a href=\"{search}\">Search</a></header>\n<main>\n{body}</main>\n</body>\n</html>\n",
+        title = escape_html(title),
+        site = escape_html(&plan.title),
+        style = relative_href(page_path, "assets/style.css"),
+        home = relative_href(page_path, "index.html"),
+        search = relative_href(page_path, "search.html"),
+    )
+}
+
+fn folder_page_html(plan: &SitePlan, folder: &str) -> String {
+    let page_path = folder_index_path(folder);
+    let mut body = String::new();
+    let name = folder
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(&plan.title);
+    body.push_str(&format!("<h1>{}</h1>\n", escape_html(name)));
+
+    let children: Vec<&String> = plan
+        .folders
+        .iter()
+        .filter(|candidate| {
+            !candidate.is_empty()
+                && candidate.rsplit_once('/').map_or("", |(parent, _)| parent) == folder
+        })
+        .collect();
+    if !children.is_empty() {
+        body.push_str("<ul class=\"folder-list\">\n");
+        for child in children {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}/</a></li>\n",
+                relative_href(&page_path, &folder_index_path(child)),
+                escape_html(child.rsplit('/').next().unwrap_or(child))
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    let mut notes: Vec<&SitePage> = plan
+        .pages
+        .iter()
+        .filter(|page| page.site_folder == folder)
+        .collect();
+    notes.sort_by(|left, right| {
+        right
+            .note
+            .is_pinned
+            .cmp(&left.note.is_pinned)
+            .then_with(|| {
+                left.note
+                    .title
+                    .to_lowercase()
+                    .cmp(&right.note.title.to_lowercase())
+            })
+    });
+    if !notes.is_empty() {
+        body.push_str("<ul class=\"note-list\">\n");
+        for page in notes {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                relative_href(&page_path, &page.site_path),
+                escape_html(&page.note.title)
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    page_html(
+        plan,
+        &page_path,
+        name,
+        &breadcrumbs(plan, &page_path, folder),
+        &body,
+    )
+}
+
+/// Renders one note page. Returns the page, its unresolved link count, and the
+/// attachments it links to.
+fn note_page_html(plan: &SitePlan, page: &SitePage) -> (String, usize, BTreeSet<String>) {
+    let links = PageLinks {
+        plan,
+        page,
+        unresolved: std::cell::Cell::new(0),
+        attachments: std::cell::RefCell::new(BTreeSet::new()),
+    };
+    let mut body = String::from("<article>\n");
+    if page.note.is_title_manual {
+        body.push_str(&format!(
+            "<h1 class=\"note-title\">{}</h1>\n",
+            escape_html(&page.note.title)
+        ));
+    }
+    if !page.note.tags.is_empty() {
+        let tags: Vec<String> = page
+            .note
+            .tags
+            .iter()
+            .map(|tag| format!("#{}", escape_html(tag)))
+            .collect();
+        body.push_str(&format!("<p class=\"tags\">{}</p>\n", tags.join(" ")));
+    }
+    body.push_str(&render_markdown(&page.note.markdown, &links));
+    body.push_str("</article>\n");
+    let html = page_html(
+        plan,
+        &page.site_path,
+        &page.note.title,
+        &breadcrumbs(plan, &page.site_path, &page.site_folder),
+        &body,
+    );
+    (html, links.unresolved.get(), links.attachments.take())
+}
+
+fn search_page_html(plan: &SitePlan) -> String {
+    let body = "<h1>Search</h1>\n<input id=\"search-input\" type=\"search\" autofocus>\n<ul id=\"search-results\"></ul>\n<script src=\"search-index.js\"></script>\n<script src=\"assets/search.js\"></script>\n";
+    page_html(plan, "search.html", "Search", "", body)
+}
+
+fn search_index_json(plan: &SitePlan) -> Result<String, String> {
+    let entries: Vec<SearchEntry<'_>> = plan
+        .pages
+        .iter()
+        .map(|page| SearchEntry {
+            title: &page.note.title,
+            path: &page.site_path,
+            folder: &page.site_folder,
+            tags: &page.note.tags,
+            text: page
+                .note
+                .plain_text
+                .chars()
+                .take(SEARCH_TEXT_LIMIT)
+                .collect(),
+        })
+        .collect();
+    serde_json::to_string(&entries)
+        .map_err(|error| format!("serialize_search_index failed: {error}"))
+}
+
+// ── Export ──
+
+/// Makes `output_dir` ready for a fresh export. The folder must be new, empty,
+/// or a previous export, and it may not overlap the library.
+fn prepare_output_dir(library_root: &Path, output_dir: &Path) -> Result<PathBuf, String> {
+    match fs::read_dir(output_dir) {
+        Ok(entries) => {
+            let entries: Vec<PathBuf> = entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<_, _>>()
+                .map_err(|error| {
+                    format!(
+                        "read_export_dir failed for {}: {error}",
+                        output_dir.display()
+                    )
+                })?;
+            let previous_export = output_dir.join(SITE_MARKER_FILE).is_file();
+            if !entries.is_empty() && !previous_export {
+                return Err(format!(
+                    "prepare_export_dir failed for {}: the folder is not empty and is not a previous export",
+                    output_dir.display()
+                ));
+            }
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+        Err(error) => {
+            return Err(format!(
+                "read_export_dir failed for {}: {error}",
+                output_dir.display()
+            ))
+        }
+    }
+    fs::create_dir_all(output_dir).map_err(|error| {
+        format!(
+            "create_export_dir failed for {}: {error}",
+            output_dir.display()
+        )
+    })?;
+    let output = fs::canonicalize(output_dir).map_err(|error| {
+        format!(
+            "resolve_export_dir failed for {}: {error}",
+            output_dir.display()
+        )
+    })?;
+    if output.starts_with(library_root) || library_root.starts_with(&output) {
+        return Err(format!(
+            "prepare_export_dir failed for {}: the export folder may not overlap the note library",
+            output.display()
+        ));
+    }
+
+    for entry in fs::read_dir(&output)
+        .map_err(|error| format!("read_export_dir failed for {}: {error}", output.display()))?
+    {
+        let path = entry
+            .map_err(|error| format!("read_export_dir failed for {}: {error}", output.display()))?
+            .path();
+        let metadata = fs::symlink_metadata(&path).map_err(|error| {
+            format!(
+                "inspect_export_entry failed for {}: {error}",
+                path.display()
+            )
+        })?;
+        let removed = if metadata.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        removed.map_err(|error| {
+            format!("clear_export_entry failed for {}: {error}", path.display())
+        })?;
+    }
+    Ok(output)
+}
+
+fn write_site_file(output_dir: &Path, site_path: &str, contents: &str) -> Result<(), String> {
+    write_site_bytes(output_dir, site_path, contents.as_bytes())
+}
+
+fn write_site_bytes(output_dir: &Path, site_path: &str, contents: &[u8]) -> Result<(), String> {
+    let path = site_path
+        .split('/')
+        .fold(output_dir.to_path_buf(), |path, segment| path.join(segment));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("create_site_dir failed for {}: {error}", parent.display()))?;
+    }
+    fs::write(&path, contents)
+        .map_err(|error| format!("write_site_file failed for {}: {error}", path.display()))
+}
+
+/// Renders the library (or one folder subtree of it) to a static site in
+/// `output_dir`: one page per note, an index page per folder, and a search
+/// page backed by a generated index. Attachments the pages link to are copied
+/// along. The site works straight from disk.
+pub fn export_static_site(
+    library_dir: &Path,
+    output_dir: &Path,
+    options: &SiteExportOptions,
+) -> Result<SiteExportResult, String> {
+    let subtree = sanitize_folder_path(options.folder_path.as_deref())?;
+    let library_root = TrustedLibraryRoot::open(library_dir)?;
+    if !subtree.is_empty() && !list_folders(library_root.path())?.contains(&subtree) {
+        return Err("Folder not found.".to_string());
+    }
+    let plan = plan_site(library_root.path(), &subtree)?;
+    let output = prepare_output_dir(library_root.path(), output_dir)?;
+
+    write_site_file(&output, SITE_MARKER_FILE, "")?;
+    write_site_file(&output, "assets/style.css", SITE_STYLESHEET)?;
+    write_site_file(&output, "assets/search.js", SEARCH_SCRIPT)?;
+    let search_index = search_index_json(&plan)?;
+    write_site_file(&output, "search-index.json", &search_index)?;
+    write_site_file(
+        &output,
+        "search-index.js",
+        &format!("window.HWAN_NOTE_SEARCH = {search_index};\n"),
+    )?;
+    write_site_file(&output, "search.html", &search_page_html(&plan))?;
+
+    for folder in &plan.folders {
+        write_site_file(
+            &output,
+            &folder_index_path(folder),
+            &folder_page_html(&plan, folder),
+        )?;
+    }
+    let mut unresolved_link_count = 0;
+    let mut linked_attachments = BTreeSet::new();
+    for page in &plan.pages {
+        let (html, unresolved, page_attachments) = note_page_html(&plan, page);
+        unresolved_link_count += unresolved;
+        linked_attachments.extend(page_attachments);
+        write_site_file(&output, &page.site_path, &html)?;
+    }
+    for file_name in &linked_attachments {
+        let bytes = attachments::read_attachment(library_root.path(), file_name)?;
+        write_site_bytes(
+            &output,
+            &join_site_path(SITE_ATTACHMENTS_DIR, file_name),
+            &bytes,
+        )?;
+    }
+
+    Ok(SiteExportResult {
+        output_dir: output.to_string_lossy().to_string(),
+        note_count: plan.pages.len(),
+        folder_count: plan.folders.len(),
+        attachment_count: linked_attachments.len(),
+        unresolved_link_count,
+    })
+}
+
+/// Reads `--export-site <dir> [--folder <path>] [--library <dir>]` from the
+/// process arguments, resolving relative directories against `base_dir`.
+/// Returns `None` when no export was requested.
+pub fn parse_cli_request(
+    argv: &[String],
+    base_dir: &Path,
+) -> Option<Result<SiteExportCliRequest, String>> {
+    let position = argv.iter().position(|arg| arg == "--export-site")?;
+    let mut values: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut index = position;
+    while index < argv.len() {
+        let flag = argv[index].as_str();
+        if matches!(flag, "--export-site" | "--folder" | "--library") {
+            match argv.get(index + 1).filter(|value| !value.starts_with("--")) {
+                Some(value) => {
+                    values.insert(flag, value);
+                    index += 2;
+                    continue;
+                }
+                None => return Some(Err(format!("{flag} requires a value."))),
+            }
+        }
+        index += 1;
+    }
+    Some(Ok(SiteExportCliRequest {
+        output_dir: base_dir.join(values["--export-site"]),
+        library_dir: values.get("--library").map(|dir| base_dir.join(dir)),
+        folder_path: values.get("--folder").map(|folder| folder.to_string()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::{auto_save_markdown_note, AutoSavePayload};
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-site-{}-{}-{}",
+            name,
+            std::process::id(),
+            crate::file_manager::now_millis()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cleanup_temp_dir(dir: &Path) {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    struct NoLinks;
+
+    impl LinkRewriter for NoLinks {
+        fn wiki_link(&self, _target: &str) -> Option<String> {
+            None
+        }
+
+        fn markdown_link(&self, href: &str) -> Option<String> {
+            Some(href.to_string())
+        }
+    }
+
+    fn save(dir: &Path, note_id: &str, content: &str, folder: Option<&str>) -> Result<(), String> {
+        auto_save_markdown_note(
+            dir,
+            &AutoSavePayload {
+                note_id: note_id.to_string(),
+                title: String::new(),
+                content: content.to_string(),
+                folder_path: folder.map(str::to_string),
+                is_title_manual: Some(false),
+                is_pinned: None,
+            },
+        )
+        .map(|_| ())
+    }
+
+    #[test]
+    fn renders_task_lists_toggles_tables_and_code() {
+        let html = render_markdown(
+            "# Plan & *goals*\n\n- [x] done\n- [ ] todo with `code`\n\n:::toggle[closed] **More**\nhidden <b>text</b>\n\n```rust\nlet a = 1 < 2;\n```\n:::\n\n| A | B |\n|:--|--:|\n| 1 | [[Missing]] |\n\n1. one\n2. two\n\n> quote\n",
+            &NoLinks,
+        );
+        assert!(html.contains("<h1 id=\"plan-goals\">Plan &amp; <em>goals</em></h1>"));
+        assert!(html.contains("<ul class=\"task-list\">"));
+        assert!(html.contains("<input type=\"checkbox\" disabled checked>done</li>"));
+        assert!(html.contains("<input type=\"checkbox\" disabled>todo with <code>code</code></li>"));
+        assert!(html.contains("<details><summary><strong>More</strong></summary>"));
+        assert!(html.contains("<p>hidden &lt;b&gt;text&lt;/b&gt;</p>"));
+        assert!(
+            html.contains("<pre><code class=\"language-rust\">let a = 1 &lt; 2;\n</code></pre>")
+        );
+        assert!(html.contains("<th style=\"text-align: left\">A</th>"));
+        assert!(html.contains(
+            "<td style=\"text-align: right\"><span class=\"missing-link\">Missing</span></td>"
+        ));
+        assert!(html.contains("<ol>\n<li>one</li>\n<li>two</li>\n</ol>"));
+        assert!(html.contains("<blockquote>\n<p>quote</p>\n</blockquote>"));
+    }
+
+    #[test]
+    fn exports_subtree_with_relative_links_folder_pages_and_search_index() {
+        let library = make_temp_dir("library");
+        let output = make_temp_dir("output").join("site");
+        let result = (|| -> Result<(), String> {
+            save(
+                &library,
+                "guide",
+                "# Guide\nSee [[Setup#First steps|setup]] and [intro](../Intro.md).",
+                Some("docs"),
+            )?;
+            save(
+                &library,
+                "setup",
+                "# Setup\n## First steps\nbody",
+                Some("docs/install"),
+            )?;
+            save(&library, "intro", "# Intro\nroot note", Some("docs"))?;
+            save(&library, "private", "# Private\nhidden", Some("personal"))?;
+
+            let exported = export_static_site(
+                &library,
+                &output,
+                &SiteExportOptions {
+                    folder_path: Some("docs".to_string()),
+                },
+            )?;
+            assert_eq!(exported.note_count, 3);
+            assert_eq!(exported.folder_count, 2);
+            assert_eq!(exported.unresolved_link_count, 1);
+
+            let guide = fs::read_to_string(output.join("Guide.html")).map_err(|e| e.to_string())?;
+            assert!(guide.contains("<a href=\"install/Setup.html#first-steps\">setup</a>"));
+            assert!(guide.contains("<span class=\"missing-link\">intro</span>"));
+            assert!(guide.contains("href=\"assets/style.css\""));
+            let setup = fs::read_to_string(output.join("install").join("Setup.html"))
+                .map_err(|e| e.to_string())?;
+            assert!(setup.contains("<h2 id=\"first-steps\">First steps</h2>"));
+            assert!(setup.contains("href=\"../assets/style.css\""));
+
+            let root_index =
+                fs::read_to_string(output.join("index.html")).map_err(|e| e.to_string())?;
+            assert!(root_index.contains("<a href=\"install/index.html\">install/</a>"));
+            assert!(root_index.contains("<a href=\"Guide.html\">Guide</a>"));
+            assert!(output.join("install").join("index.html").is_file());
+            assert!(!output.join("Private.html").exists());
+
+            let search =
+                fs::read_to_string(output.join("search-index.json")).map_err(|e| e.to_string())?;
+            let entries: Vec<serde_json::Value> =
+                serde_json::from_str(&search).map_err(|e| e.to_string())?;
+            assert_eq!(entries.len(), 3);
+            assert!(entries
+                .iter()
+                .any(|entry| entry["path"] == "install/Setup.html"));
+
+            export_static_site(&library, &output, &SiteExportOptions::default())?;
+            assert!(output.join("personal").join("Private.html").is_file());
+            let library_output = library.join("site");
+            assert!(
+                export_static_site(&library, &library_output, &SiteExportOptions::default())
+                    .is_err()
+            );
+            Ok(())
+        })();
+        cleanup_temp_dir(&library);
+        cleanup_temp_dir(output.parent().unwrap());
+        result.unwrap();
+    }
+
+    #[test]
+    fn exported_links_keep_only_safe_url_schemes() {
+        let library = make_temp_dir("link-schemes");
+        let output = make_temp_dir("link-schemes-output").join("site");
+        let result = (|| -> Result<(), String> {
+            save(
+                &library,
+                "links",
+                "# Links\n[web](https://example.com/a) [mail](mailto:me@example.com) [script](javascript:alert) [hidden](\u{1}javascript:alert) [data](DATA:text/html,x)",
+                None,
+            )?;
+            export_static_site(&library, &output, &SiteExportOptions::default())?;
+
+            let page = fs::read_to_string(output.join("Links.html")).map_err(|e| e.to_string())?;
+            assert!(page.contains("<a href=\"https://example.com/a\">web</a>"));
+            assert!(page.contains("<a href=\"mailto:me@example.com\">mail</a>"));
+            assert!(page.contains("<span class=\"missing-link\">script</span>"));
+            assert!(page.contains("<span class=\"missing-link\">hidden</span>"));
+            assert!(page.contains("<span class=\"missing-link\">data</span>"));
+            assert!(!page.to_lowercase().contains("script:"));
+            Ok(())
+        })();
+        cleanup_temp_dir(&library);
+        cleanup_temp_dir(output.parent().unwrap());
+        result.unwrap();
+    }
+
+    #[test]
+    fn exports_linked_attachments_next_to_the_pages() {
+        let library = make_temp_dir("attachments");
+        let output = make_temp_dir("attachments-output").join("site");
+        let result = (|| -> Result<(), String> {
+            let image = attachments::save_attachment(
+                &library,
+                None,
+                Some("docs/guide"),
+                "diagram.png",
+                b"diagram",
+            )?;
+            let spec =
+                attachments::save_attachment(&library, None, Some("docs"), "spec.pdf", b"spec")?;
+            let unlinked =
+                attachments::save_attachment(&library, None, None, "other.txt", b"other")?;
+            let missing = format!("../../{ATTACHMENTS_DIRECTORY_NAME}/{}.png", "0".repeat(64));
+            save(
+                &library,
+                "guide",
+                &format!(
+                    "# Guide\n![diagram]({}) [spec](../{}) ![gone]({missing})",
+                    image.link_target, spec.link_target
+                ),
+                Some("docs/guide"),
+            )?;
+
+            let exported = export_static_site(&library, &output, &SiteExportOptions::default())?;
+            assert_eq!(exported.attachment_count, 2);
+            assert_eq!(exported.unresolved_link_count, 1);
+
+            let page = fs::read_to_string(output.join("docs").join("guide").join("Guide.html"))
+                .map_err(|e| e.to_string())?;
+            assert!(page.contains(&format!(
+                "<img src=\"../../assets/files/{}\" alt=\"diagram\">",
+                image.file_name
+            )));
+            assert!(page.contains(&format!(
+                "<a href=\"../../assets/files/{}\">spec</a>",
+                spec.file_name
+            )));
+            assert!(page.contains("<span class=\"missing-link\">gone</span>"));
+            let files = output.join("assets").join("files");
+            assert_eq!(
+                fs::read(files.join(&image.file_name)).map_err(|e| e.to_string())?,
+                b"diagram"
+            );
+            assert!(files.join(&spec.file_name).is_file());
+            assert!(!files.join(&unlinked.file_name).exists());
+            Ok(())
+        })();
+        cleanup_temp_dir(&library);
+        cleanup_temp_dir(output.parent().unwrap());
+        result.unwrap();
+    }
+
+    #[test]
+    fn parses_cli_export_arguments() {
+        let argv = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let base_dir = Path::new("/work");
+        assert_eq!(
+            parse_cli_request(&argv(&["hwan-note", "notes.txt"]), base_dir),
+            None
+        );
+        assert_eq!(
+            parse_cli_request(
+                &argv(&[
+                    "hwan-note",
+                    "--export-site",
+                    "out",
+                    "--folder",
+                    "docs",
+                    "--library",
+                    "lib"
+                ]),
+                base_dir
+            ),
+            Some(Ok(SiteExportCliRequest {
+                output_dir: base_dir.join("out"),
+                library_dir: Some(base_dir.join("lib")),
+                folder_path: Some("docs".to_string()),
+            }))
+        );
+        assert!(matches!(
+            parse_cli_request(
+                &argv(&["hwan-note", "--export-site", "out", "--folder"]),
+                base_dir
+            ),
+            Some(Err(_))
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+
+/// Longest numbered suffix Google Drive adds, as in `note (12).md`.
+const MAX_COPY_NUMBER_DIGITS: usize = 3;
+/// Dropbox names copies `note (conflicted copy 2024-03-05).md`, or with the
+/// other user's name as in `note (Sam's conflicted copy 2024-03-05).md`.
+const DROPBOX_CONFLICT_MARKER: &str = "conflicted copy";
+/// Nextcloud and ownCloud name copies `note_conflict-20240305-120000.md`.
+const SYNC_CLIENT_CONFLICT_MARKER: &str = "_conflict-";
+/// Syncthing names copies `note.sync-conflict-20240305-120000-ABCDEFG.md`,
+/// ending in the first seven characters of the other device's id.
+const SYNCTHING_CONFLICT_MARKER: &str = ".sync-conflict-";
+const SYNCTHING_DEVICE_ID_LENGTH: usize = 7;
+/// Windows generates computer names such as `DESKTOP-AB12CD3`.
+const GENERATED_MACHINE_NAME_PREFIXES: &[&str] = &["DESKTOP-", "LAPTOP-", "WIN-"];
+/// Other computer names must be this long and mix letters and digits, so a
+/// note like `Report-Q3.md` or `API-FAQ.md` is not taken for a copy.
+const MIN_CUSTOM_MACHINE_NAME_LENGTH: usize = 5;
+/// Windows computer names are at most 15 characters.
+const MAX_MACHINE_NAME_LENGTH: usize = 15;
+
+// ── Types ──
+
+/// A file a sync client created next to a note when two machines edited it at
+/// once. It is kept out of the index until the user resolves it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub note_id: String,
+    /// The note the copy was made from.
+    pub file_path: String,
+    pub conflict_path: String,
+    pub conflict_markdown: String,
+    pub conflict_updated_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SyncConflictResolution {
+    /// Keep the note as it is and trash the copy.
+    KeepOriginal,
+    /// Replace the note's body with the copy's and trash the copy.
+    KeepConflict,
+    /// Index the copy as a separate note.
+    KeepBoth,
+    /// Save `markdown` as the note's body and trash the copy.
+    Merged { markdown: String },
+}
+
+// ── Detection ──
+
+fn is_machine_name(suffix: &str) -> bool {
+    let well_formed = !suffix.is_empty()
+        && suffix.len() <= MAX_MACHINE_NAME_LENGTH
+        && !suffix.starts_with('-')
+        && !suffix.ends_with('-')
+        && suffix
+            .chars()
+            .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '-');
+    if !well_formed {
+        return false;
+    }
+    if GENERATED_MACHINE_NAME_PREFIXES
+        .iter()
+        .any(|prefix| suffix.len() > prefix.len() && suffix.starts_with(prefix))
+    {
+        return true;
+    }
+    suffix.len() >= MIN_CUSTOM_MACHINE_NAME_LENGTH
+        && suffix.chars().any(|ch| ch.is_ascii_uppercase())
+        && suffix.chars().any(|ch| ch.is_ascii_digit())
+}
+
+fn copy_number_base(stem: &str) -> Option<&str> {
+    let (base, number) = stem.strip_suffix(')')?.rsplit_once(" (")?;
+    let is_number = !number.is_empty()
+        && number.len() <= MAX_COPY_NUMBER_DIGITS
+        && number.chars().all(|ch| ch.is_ascii_digit());
+    (is_number && !base.is_empty()).then_some(base)
+}
+
+fn dropbox_copy_base(stem: &str) -> Option<&str> {
+    let (base, label) = stem.strip_suffix(')')?.rsplit_once(" (")?;
+    (label.contains(DROPBOX_CONFLICT_MARKER) && !base.is_empty()).then_some(base)
+}
+
+fn all_digits(text: &str, length: usize) -> bool {
+    text.len() == length && text.chars().all(|ch| ch.is_ascii_digit())
+}
+
+/// `YYYYMMDD-HHMMSS`, the time stamp sync clients put in conflict names.
+fn is_conflict_time_stamp(text: &str) -> bool {
+    text.split_once('-')
+        .is_some_and(|(date, time)| all_digits(date, 8) && all_digits(time, 6))
+}
+
+fn sync_client_copy_base(stem: &str) -> Option<&str> {
+    let (base, stamp) = stem.rsplit_once(SYNC_CLIENT_CONFLICT_MARKER)?;
+    (is_conflict_time_stamp(stamp) && !base.is_empty()).then_some(base)
+}
+
+fn syncthing_copy_base(stem: &str) -> Option<&str> {
+    let (base, suffix) = stem.rsplit_once(SYNCTHING_CONFLICT_MARKER)?;
+    let (stamp, device) = suffix.rsplit_once('-')?;
+    let is_device = device.len() == SYNCTHING_DEVICE_ID_LENGTH
+        && device
+            .chars()
+            .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit());
+    (is_conflict_time_stamp(stamp) && is_device && !base.is_empty()).then_some(base)
+}
+
+/// Library-relative paths of the notes `relative_path` may be a conflict copy
+/// of, most likely first. Google Drive names copies `note (1).md`; OneDrive
+/// appends the computer name, as in `note-DESKTOP-AB12CD3.md`, which may itself
+/// contain hyphens. Dropbox, Nextcloud, ownCloud and Syncthing names are
+/// described with their markers above.
+pub(crate) fn conflict_copy_originals(relative_path: &str) -> Vec<String> {
+    let (folder, file_name) = match relative_path.rsplit_once('/') {
+        Some((folder, file_name)) => (format!("{folder}/"), file_name),
+        None => (String::new(), relative_path),
+    };
+    let Some((stem, extension)) = file_name.rsplit_once('.') else {
+        return Vec::new();
+    };
+    if !extension.eq_ignore_ascii_case("md") {
+        return Vec::new();
+    }
+
+    // These names are unambiguous, so the OneDrive guess below is skipped;
+    // it would also read the tail of a Syncthing name as a computer name.
+    let named_copy = dropbox_copy_base(stem)
+        .or_else(|| sync_client_copy_base(stem))
+        .or_else(|| syncthing_copy_base(stem));
+    if let Some(base) = named_copy {
+        return vec![format!("{folder}{base}.{extension}")];
+    }
+
+    let mut bases = Vec::new();
+    if let Some(base) = copy_number_base(stem) {
+        bases.push(base);
+    }
+    for (index, _) in stem.rmatch_indices('-') {
+        let (base, suffix) = (&stem[..index], &stem[index + 1..]);
+        if !base.is_empty() && is_machine_name(suffix) {
+            bases.push(base);
+        }
+    }
+    bases
+        .into_iter()
+        .map(|base| format!("{folder}{base}.{extension}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_google_drive_and_onedrive_copy_names() {
+        assert_eq!(
+            conflict_copy_originals("Work/plan (1).md"),
+            vec!["Work/plan.md"]
+        );
+        assert_eq!(
+            conflict_copy_originals("daily-log-DESKTOP-AB12CD3.md"),
+            vec!["daily-log-DESKTOP.md", "daily-log.md"]
+        );
+        assert_eq!(conflict_copy_originals("Todo-LAPTOP7.md"), vec!["Todo.md"]);
+        assert_eq!(
+            conflict_copy_originals("Work/plan-WIN-4K2.md"),
+            vec!["Work/plan.md"]
+        );
+    }
+
+    #[test]
+    fn recognizes_dropbox_nextcloud_and_syncthing_copy_names() {
+        assert_eq!(
+            conflict_copy_originals("Work/plan (conflicted copy 2024-03-05).md"),
+            vec!["Work/plan.md"]
+        );
+        assert_eq!(
+            conflict_copy_originals("plan (Sam's conflicted copy 2024-03-05).md"),
+            vec!["plan.md"]
+        );
+        assert_eq!(
+            conflict_copy_originals("Work/plan_conflict-20240305-120000.md"),
+            vec!["Work/plan.md"]
+        );
+        assert_eq!(
+            conflict_copy_originals("plan.sync-conflict-20240305-120000-ABC1DEF.md"),
+            vec!["plan.md"]
+        );
+    }
+
+    #[test]
+    fn ignores_ordinary_note_names() {
+        for path in [
+            "plan.md",
+            "plan (draft).md",
+            "plan (1234).md",
+            " (1).md",
+            "road-trip.md",
+            "release-v2.md",
+            "-ABC.md",
+            "Report-Q3.md",
+            "API-FAQ.md",
+            "Plan-V2.md",
+            "Notes-SUMMARY.md",
+            "Budget-FY2024-DRAFT-REVISED-FINAL.md",
+            "plan (1).txt",
+            "plan (copy).md",
+            "plan_conflict-notes.md",
+            "plan_conflict-2024-0305.md",
+            "plan.sync-conflict-20240305-120000.md",
+            "plan.sync-conflict-20240305-120000-abc1def.md",
+        ] {
+            assert!(conflict_copy_originals(path).is_empty(), "{path}");
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::{Deserialize, Serialize};
+
+/// How many leading bytes the UTF-16 sniffing looks at.
+const UTF16_SNIFF_LENGTH: usize = 4096;
+
+// ── Types ──
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn platform_default() -> Self {
+        if cfg!(windows) {
+            Self::Crlf
+        } else {
+            Self::Lf
+        }
+    }
+}
+
+/// The on-disk shape of an external text file, so a save can write it back
+/// the way it was read. `encoding` is a WHATWG encoding name such as `UTF-8`,
+/// `EUC-KR` (read as CP949), `UTF-16LE` or `Shift_JIS`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextFormat {
+    pub encoding: String,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+impl Default for TextFormat {
+    /// UTF-8 without a BOM and with the host's line endings, which is what
+    /// new text files get.
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8.name().to_string(),
+            bom: false,
+            line_ending: LineEnding::platform_default(),
+        }
+    }
+}
+
+// ── Detection ──
+
+pub fn encoding_for_label(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("Unknown text encoding: {label}"))
+}
+
+/// Guesses UTF-16 without a BOM from where the zero bytes of ASCII characters
+/// fall. Legacy multi-byte encodings never contain zero bytes in text.
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SNIFF_LENGTH) & !1];
+    let pairs = sample.len() / 2;
+    if pairs == 0 {
+        return None;
+    }
+    let even_zeros = sample.iter().step_by(2).filter(|byte| **byte == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|byte| **byte == 0)
+        .count();
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 20 <= pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 20 <= pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return encoding;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+fn detect_line_ending(text: &str) -> LineEnding {
+    match text.find('\n') {
+        Some(index) if text[..index].ends_with('\r') => LineEnding::Crlf,
+        Some(_) => LineEnding::Lf,
+        None => LineEnding::platform_default(),
+    }
+}
+
+/// Decodes a text file. A BOM always wins; otherwise valid UTF-8 is taken as
+/// UTF-8 and anything else goes through UTF-16 sniffing and then `chardetng`.
+/// Passing `encoding` skips detection, for "reopen with encoding".
+pub fn decode_text(bytes: &[u8], encoding: Option<&'static Encoding>) -> (String, TextFormat) {
+    let (encoding, bom) = match encoding {
+        Some(encoding) => {
+            let bom = Encoding::for_bom(bytes)
+                .filter(|(bom_encoding, _)| *bom_encoding == encoding)
+                .map(|(_, length)| length);
+            (encoding, bom)
+        }
+        None => match Encoding::for_bom(bytes) {
+            Some((encoding, length)) => (encoding, Some(length)),
+            None => (detect_encoding(bytes), None),
+        },
+    };
+    let body = &bytes[bom.unwrap_or(0)..];
+    let (text, _) = encoding.decode_without_bom_handling(body);
+    let text = text.into_owned();
+    let format = TextFormat {
+        encoding: encoding.name().to_string(),
+        bom: bom.is_some(),
+        line_ending: detect_line_ending(&text),
+    };
+    (text, format)
+}
+
+// ── Encoding ──
+
+/// Encodes `text` in `format`. Fails instead of writing HTML character
+/// references when the encoding cannot represent a character.
+pub fn encode_text(text: &str, format: &TextFormat) -> Result<Vec<u8>, String> {
+    let encoding = encoding_for_label(&format.encoding)?;
+    let normalized = text.replace("\r\n", "\n");
+    let text = match format.line_ending {
+        LineEnding::Lf => normalized,
+        LineEnding::Crlf => normalized.replace('\n', "\r\n"),
+    };
+
+    let mut bytes = Vec::with_capacity(text.len() + 3);
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        if format.bom {
+            bytes.extend_from_slice(if encoding == UTF_16LE {
+                b"\xFF\xFE"
+            } else {
+                b"\xFE\xFF"
+            });
+        }
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&if encoding == UTF_16LE {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            });
+        }
+        return Ok(bytes);
+    }
+
+    let (encoded, output_encoding, unmappable) = encoding.encode(&text);
+    if unmappable || output_encoding != encoding {
+        return Err(format!(
+            "The text has characters that {} cannot store.",
+            encoding.name()
+        ));
+    }
+    if format.bom && encoding == UTF_8 {
+        bytes.extend_from_slice(b"\xEF\xBB\xBF");
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{EUC_KR, SHIFT_JIS};
+
+    #[test]
+    fn decode_detects_bom_utf16_and_legacy_korean() {
+        let (text, format) = decode_text(b"\xEF\xBB\xBFhello\r\nworld", None);
+        assert_eq!(text, "hello\r\nworld");
+        assert_eq!(format.encoding, "UTF-8");
+        assert!(format.bom);
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+
+        let utf16: Vec<u8> = "메모\nnote"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let (text, format) = decode_text(&utf16, None);
+        assert_eq!(text, "메모\nnote");
+        assert_eq!(format.encoding, "UTF-16LE");
+        assert!(!format.bom);
+
+        let korean = "안녕하세요. 오늘은 메모장을 정리하는 날입니다.\n";
+        let (euc_kr, _, _) = EUC_KR.encode(korean);
+        let (text, format) = decode_text(&euc_kr, None);
+        assert_eq!(text, korean);
+        assert_eq!(format.encoding, "EUC-KR");
+        assert_eq!(format.line_ending, LineEnding::Lf);
+
+        let (text, format) = decode_text(&euc_kr, Some(SHIFT_JIS));
+        assert_ne!(text, korean);
+        assert_eq!(format.encoding, "Shift_JIS");
+    }
+
+    #[test]
+    fn encode_round_trips_format_and_rejects_unmappable_text() {
+        let format = TextFormat {
+            encoding: "UTF-16BE".to_string(),
+            bom: true,
+            line_ending: LineEnding::Crlf,
+        };
+        let bytes = encode_text("a\nb", &format).unwrap();
+        assert_eq!(bytes, b"\xFE\xFF\0a\0\r\0\n\0b");
+        assert_eq!(decode_text(&bytes, None), ("a\r\nb".to_string(), format));
+
+        let format = TextFormat {
+            encoding: "EUC-KR".to_string(),
+            bom: false,
+            line_ending: LineEnding::Lf,
+        };
+        let bytes = encode_text("한글\r\n", &format).unwrap();
+        assert_eq!(bytes, EUC_KR.encode("한글\n").0.as_ref());
+        assert!(encode_text("🙂", &format).is_err());
+    }
+}
@@ -0,0 +1,647 @@
+use crate::file_manager::{
+    extract_note_metadata, now_millis, sanitize_folder_path, system_time_to_millis, MigratedNote,
+    TrustedLibraryRoot,
+};
+use crate::import_staging::{ImportStaging, StagedNote, UniqueNotePaths};
+use crate::note_links::{retarget_moved_links, rewrite_wiki_links, LinkNode};
+use crate::site_export::{decode_percent, resolve_library_path, URL_SCHEME_RE};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+/// Notion appends the page id to every exported file and folder name.
+static NOTION_ID_SUFFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.*\S) [0-9a-fA-F]{32}$").expect("valid notion id suffix regex")
+});
+
+static MARKDOWN_LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(!?)\[([^\[\]\r\n]*)\]\((?:<([^<>\r\n]+)>|([^()\s<>]+))\)")
+        .expect("valid markdown link regex")
+});
+
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown"];
+const CODE_FENCES: &[&str] = &["```", "~~~"];
+
+// ── Types ──
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VaultLinkMode {
+    /// Leave every link exactly as written.
+    #[default]
+    Keep,
+    /// Point wiki links at the imported notes' new names and turn relative
+    /// Markdown links between notes into wiki links.
+    Convert,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VaultImportOptions {
+    pub folder_path: Option<String>,
+    pub link_mode: VaultLinkMode,
+    /// Report what would be imported without touching the library.
+    pub dry_run: bool,
+}
+
+/// One note the import writes, or would write in a dry run. `source_path` is
+/// relative to the vault, `destination_path` to the library.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedVaultNote {
+    pub source_path: String,
+    pub destination_path: String,
+    pub title: Option<String>,
+    pub created_at: u64,
+    pub links_converted: u32,
+    /// The library already has a file there, so the note will be renamed.
+    pub destination_exists: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedVaultEntry {
+    pub source_path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultImportResult {
+    pub dry_run: bool,
+    pub notes: Vec<PlannedVaultNote>,
+    /// Merge outcome per note; empty for a dry run.
+    pub imported: Vec<MigratedNote>,
+    pub skipped: Vec<SkippedVaultEntry>,
+}
+
+struct VaultNote {
+    source_path: String,
+    destination_path: String,
+    markdown: String,
+    title: Option<String>,
+    aliases: Vec<String>,
+    created_at: u64,
+    updated_at: Option<u64>,
+    links_converted: u32,
+}
+
+#[derive(Default)]
+struct VaultScan {
+    notes: Vec<VaultNote>,
+    folders: Vec<String>,
+    skipped: Vec<SkippedVaultEntry>,
+    paths: UniqueNotePaths,
+}
+
+// ── Names ──
+
+/// Turns a vault file or folder name into one the library accepts: drops a
+/// Notion id suffix and characters that are invalid in file names or would
+/// break a wiki link.
+fn clean_vault_name(name: &str) -> String {
+    let name = NOTION_ID_SUFFIX_RE
+        .captures(name)
+        .and_then(|captures| captures.get(1))
+        .map_or(name, |stem| stem.as_str());
+    let cleaned: String = name
+        .chars()
+        .filter(|ch| {
+            !ch.is_control()
+                && !matches!(
+                    ch,
+                    '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' | '[' | ']' | '#' | '^'
+                )
+        })
+        .collect();
+    cleaned
+        .trim()
+        .trim_end_matches(['.', ' '])
+        .trim()
+        .to_string()
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{parent}/{name}")
+    }
+}
+
+fn markdown_stem(name: &str) -> Option<&str> {
+    let (stem, extension) = name.rsplit_once('.')?;
+    MARKDOWN_EXTENSIONS
+        .iter()
+        .any(|markdown| markdown.eq_ignore_ascii_case(extension))
+        .then_some(stem)
+}
+
+// ── Scanning ──
+
+impl VaultScan {
+    fn skip(&mut self, source_path: &str, reason: &str) {
+        self.skipped.push(SkippedVaultEntry {
+            source_path: source_path.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Walks `dir` in name order. Dot entries belong to tools (`.obsidian`,
+    /// `.trash`, `.git`) and symbolic links may leave the vault, so both are
+    /// skipped, as is `library_dir` when the library lives inside the vault.
+    fn walk(
+        &mut self,
+        dir: &Path,
+        source_folder: &str,
+        destination_folder: &str,
+        library_dir: Option<&Path>,
+    ) -> Result<(), String> {
+        let mut entries = fs::read_dir(dir)
+            .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+            .map_err(|error| format!("read_vault_folder failed for {}: {error}", dir.display()))?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let file_name = entry.file_name();
+            let Some(name) = file_name.to_str() else {
+                let source_path = join_path(source_folder, &file_name.to_string_lossy());
+                self.skip(&source_path, "the name is not valid Unicode");
+                continue;
+            };
+            let source_path = join_path(source_folder, name);
+            if name.starts_with('.') {
+                self.skip(
+                    &source_path,
+                    "hidden files and tool folders are not imported",
+                );
+                continue;
+            }
+            let path = entry.path();
+            let metadata = fs::symlink_metadata(&path).map_err(|error| {
+                format!("read_vault_entry failed for {}: {error}", path.display())
+            })?;
+            if metadata.file_type().is_symlink() {
+                self.skip(&source_path, "symbolic links are not followed");
+                continue;
+            }
+
+            if metadata.is_dir() {
+                if library_dir == Some(path.as_path()) {
+                    self.skip(&source_path, "this folder is the note library");
+                    continue;
+                }
+                let folder_name = clean_vault_name(name);
+                let folder = if folder_name.is_empty() {
+                    Err("the folder name has no usable characters".to_string())
+                } else {
+                    sanitize_folder_path(Some(&join_path(destination_folder, &folder_name)))
+                };
+                match folder {
+                    Ok(folder) => {
+                        self.folders.push(folder.clone());
+                        self.walk(&path, &source_path, &folder, library_dir)?;
+                    }
+                    Err(reason) => self.skip(&source_path, &reason),
+                }
+                continue;
+            }
+
+            let Some(stem) = markdown_stem(name) else {
+                self.skip(&source_path, "not a Markdown file");
+                continue;
+            };
+            let bytes = fs::read(&path).map_err(|error| {
+                format!("read_vault_note failed for {}: {error}", path.display())
+            })?;
+            let Ok(text) = String::from_utf8(bytes) else {
+                self.skip(&source_path, "the file is not UTF-8 text");
+                continue;
+            };
+            let markdown = text
+                .strip_prefix('\u{feff}')
+                .unwrap_or(&text)
+                .replace("\r\n", "\n");
+            let (front_matter, _) = extract_note_metadata(&markdown);
+            let updated_at = metadata.modified().ok().map(system_time_to_millis);
+            let created_at = front_matter
+                .created
+                .or_else(|| metadata.created().ok().map(system_time_to_millis))
+                .or(updated_at)
+                .unwrap_or_else(now_millis);
+
+            let file_stem = match clean_vault_name(stem) {
+                stem if stem.is_empty() => "untitled".to_string(),
+                stem => stem,
+            };
+            let destination_path = self.paths.allocate(destination_folder, &file_stem);
+            self.notes.push(VaultNote {
+                source_path,
+                destination_path,
+                markdown,
+                title: front_matter.title,
+                aliases: front_matter.aliases,
+                created_at,
+                updated_at,
+                links_converted: 0,
+            });
+        }
+        Ok(())
+    }
+}
+
+// ── Links ──
+
+fn link_node(note: &VaultNote, relative_path: &str) -> LinkNode {
+    let stem = relative_path
+        .rsplit('/')
+        .next()
+        .and_then(markdown_stem)
+        .unwrap_or(relative_path);
+    LinkNode::from_markdown(
+        relative_path,
+        note.title.as_deref().unwrap_or(stem),
+        &note.aliases,
+        &note.markdown,
+    )
+}
+
+/// Rewrites `[text](Other%20note.md#anchor)` links that point at a note of
+/// the vault into `[[Other note#anchor|text]]`. `note_paths` maps lowercased
+/// vault paths to imported library paths; the target is the imported file
+/// name when that is unique and the library path otherwise. Code is left
+/// alone.
+fn convert_markdown_links(
+    markdown: &str,
+    source_path: &str,
+    note_paths: &HashMap<String, &str>,
+    stem_counts: &HashMap<String, usize>,
+) -> (String, u32) {
+    let mut converted = 0;
+    let mut convert = |captures: &Captures<'_>| -> Option<String> {
+        if !captures[1].is_empty() {
+            return None;
+        }
+        let text = captures[2].trim();
+        let href = captures.get(3).or_else(|| captures.get(4))?.as_str();
+        if href.starts_with('#') || href.starts_with("//") || URL_SCHEME_RE.is_match(href) {
+            return None;
+        }
+        let (path, anchor) = href.split_once('#').map_or((href, None), |(path, anchor)| {
+            (path, Some(decode_percent(anchor)))
+        });
+        let library_path = resolve_library_path(source_path, &decode_percent(path))?;
+        let target_path = *note_paths.get(&library_path.to_lowercase())?;
+
+        let path_target = target_path
+            .rsplit_once('.')
+            .map_or(target_path, |(stem, _)| stem);
+        let stem_target = path_target
+            .rsplit_once('/')
+            .map_or(path_target, |(_, stem)| stem);
+        let target = if stem_counts.get(&stem_target.to_lowercase()) == Some(&1) {
+            stem_target.to_string()
+        } else if path_target.contains('/') {
+            path_target.to_string()
+        } else {
+            format!("/{path_target}")
+        };
+        let unsafe_text = |value: &str| value.contains(['|', '[', ']']);
+        if unsafe_text(&target) || target.contains('#') || unsafe_text(text) {
+            return None;
+        }
+        let anchor = match anchor {
+            Some(anchor) if unsafe_text(&anchor) => return None,
+            Some(anchor) => format!("#{anchor}"),
+            None => String::new(),
+        };
+        converted += 1;
+        Some(if text.is_empty() || text == target {
+            format!("[[{target}{anchor}]]")
+        } else {
+            format!("[[{target}{anchor}|{text}]]")
+        })
+    };
+
+    let mut output = String::with_capacity(markdown.len());
+    let mut open_fence: Option<&str> = None;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(fence) = CODE_FENCES
+            .iter()
+            .find(|fence| trimmed.starts_with(**fence))
+        {
+            match open_fence {
+                None => open_fence = Some(fence),
+                Some(open) if open == *fence => open_fence = None,
+                Some(_) => {}
+            }
+            output.push_str(line);
+            continue;
+        }
+        if open_fence.is_some() {
+            output.push_str(line);
+            continue;
+        }
+        // Odd segments between backticks are inline code.
+        for (index, segment) in line.split('`').enumerate() {
+            if index > 0 {
+                output.push('`');
+            }
+            if index % 2 == 1 {
+                output.push_str(segment);
+            } else {
+                output.push_str(&MARKDOWN_LINK_RE.replace_all(
+                    segment,
+                    |captures: &Captures<'_>| {
+                        convert(captures).unwrap_or_else(|| captures[0].to_string())
+                    },
+                ));
+            }
+        }
+    }
+    (output, converted)
+}
+
+/// Points every wiki link that would lose its note at the note's imported
+/// name, then turns Markdown links between vault notes into wiki links.
+fn convert_links(notes: &mut [VaultNote]) {
+    let before = notes
+        .iter()
+        .enumerate()
+        .map(|(index, note)| (index.to_string(), link_node(note, &note.source_path)))
+        .collect();
+    let after = notes
+        .iter()
+        .enumerate()
+        .map(|(index, note)| (index.to_string(), link_node(note, &note.destination_path)))
+        .collect();
+    let replacements = retarget_moved_links(before, after);
+    for note in notes.iter_mut() {
+        let mut count = 0;
+        let rewritten = rewrite_wiki_links(&note.markdown, |name| {
+            let replacement = replacements.get(&name.to_lowercase()).cloned();
+            count += u32::from(replacement.is_some());
+            replacement
+        });
+        if let Some(markdown) = rewritten {
+            note.markdown = markdown;
+            note.links_converted = count;
+        }
+    }
+
+    let note_paths: HashMap<String, &str> = notes
+        .iter()
+        .map(|note| {
+            (
+                note.source_path.to_lowercase(),
+                note.destination_path.as_str(),
+            )
+        })
+        .collect();
+    let mut stem_counts: HashMap<String, usize> = HashMap::new();
+    for note in notes.iter() {
+        let file_name = note.destination_path.rsplit('/').next().unwrap_or_default();
+        let stem = markdown_stem(file_name).unwrap_or(file_name);
+        *stem_counts.entry(stem.to_lowercase()).or_default() += 1;
+    }
+    let converted: Vec<(String, u32)> = notes
+        .iter()
+        .map(|note| {
+            convert_markdown_links(&note.markdown, &note.source_path, &note_paths, &stem_counts)
+        })
+        .collect();
+    for (note, (markdown, count)) in notes.iter_mut().zip(converted) {
+        note.markdown = markdown;
+        note.links_converted += count;
+    }
+}
+
+// ── Public API ──
+
+/// Imports a folder of Markdown files, such as an Obsidian vault or a Notion
+/// export, into `folder_path`. The folder tree is kept, front matter titles
+/// become manual titles, and creation times come from front matter or the
+/// file system. Notes are merged through a staging library, so nothing in
+/// the library is overwritten; a dry run only reports the plan.
+pub fn import_vault(
+    source_dir: &Path,
+    options: &VaultImportOptions,
+    auto_save_dir: &Path,
+) -> Result<VaultImportResult, String> {
+    let parent_folder = sanitize_folder_path(options.folder_path.as_deref())?;
+    let trusted_root = TrustedLibraryRoot::open(auto_save_dir)?;
+    let source_root = fs::canonicalize(source_dir)
+        .map_err(|error| format!("import_vault failed for {}: {error}", source_dir.display()))?;
+    if !source_root.is_dir() {
+        return Err(format!(
+            "import_vault failed for {}: not a folder",
+            source_dir.display()
+        ));
+    }
+    if source_root.starts_with(trusted_root.path()) {
+        return Err("Cannot import a folder from inside the note library.".to_string());
+    }
+    let library_dir = trusted_root
+        .path()
+        .starts_with(&source_root)
+        .then(|| trusted_root.path());
+
+    let mut scan = VaultScan::default();
+    scan.walk(&source_root, "", &parent_folder, library_dir)?;
+    if options.link_mode == VaultLinkMode::Convert {
+        convert_links(&mut scan.notes);
+    }
+
+    let notes = scan
+        .notes
+        .iter()
+        .map(|note| PlannedVaultNote {
+            source_path: note.source_path.clone(),
+            destination_path: note.destination_path.clone(),
+            title: note.title.clone(),
+            created_at: note.created_at,
+            links_converted: note.links_converted,
+            destination_exists: fs::symlink_metadata(
+                note.destination_path
+                    .split('/')
+                    .fold(trusted_root.path().to_path_buf(), |path, segment| {
+                        path.join(segment)
+                    }),
+            )
+            .is_ok(),
+        })
+        .collect();
+
+    let mut imported = Vec::new();
+    let has_content = !scan.notes.is_empty() || !scan.folders.is_empty();
+    if !options.dry_run && has_content {
+        let mut staging = ImportStaging::create("vault")?;
+        for folder in &scan.folders {
+            staging.create_folder(folder)?;
+        }
+        for note in scan.notes {
+            staging.add_note(
+                &note.destination_path,
+                StagedNote {
+                    markdown: &note.markdown,
+                    created_at: note.created_at,
+                    updated_at: note.updated_at,
+                    manual_title: note.title,
+                },
+            )?;
+        }
+        imported = staging.merge_into(auto_save_dir)?.notes;
+    }
+
+    Ok(VaultImportResult {
+        dry_run: options.dry_run,
+        notes,
+        imported,
+        skipped: scan.skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::read_index;
+    use std::path::PathBuf;
+
+    const PROJECTS_ID: &str = "0123456789abcdef0123456789abcdef";
+    const ROADMAP_ID: &str = "fedcba9876543210fedcba9876543210";
+
+    fn make_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hwan-note-{}-{}-{}",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn dry_run_plans_notion_export_with_converted_links() {
+        let src = make_temp_dir("vault-notion-src");
+        let dst = make_temp_dir("vault-notion-dst");
+        let result = (|| -> Result<(), String> {
+            let subpages = src.join(format!("Projects {PROJECTS_ID}"));
+            fs::create_dir_all(&subpages).unwrap();
+            fs::create_dir_all(src.join(".obsidian")).unwrap();
+            fs::write(
+                src.join(format!("Projects {PROJECTS_ID}.md")),
+                format!(
+                    "See [the roadmap](Projects%20{PROJECTS_ID}/Roadmap%20{ROADMAP_ID}.md#Goals), \
+                     [[Roadmap {ROADMAP_ID}]] and `[code](Projects%20{PROJECTS_ID}.md)`.\n"
+                ),
+            )
+            .unwrap();
+            fs::write(
+                subpages.join(format!("Roadmap {ROADMAP_ID}.md")),
+                "---\ntitle: Roadmap 2025\n---\n# Goals\n",
+            )
+            .unwrap();
+            fs::write(subpages.join("chart.png"), b"png").unwrap();
+
+            let options = VaultImportOptions {
+                folder_path: Some("notion".to_string()),
+                link_mode: VaultLinkMode::Convert,
+                dry_run: true,
+            };
+            let result = import_vault(&src, &options, &dst)?;
+            assert!(result.imported.is_empty());
+            let planned: Vec<_> = result
+                .notes
+                .iter()
+                .map(|note| (note.destination_path.as_str(), note.links_converted))
+                .collect();
+            assert_eq!(
+                planned,
+                vec![("notion/Projects/Roadmap.md", 0), ("notion/Projects.md", 2)]
+            );
+            assert_eq!(result.notes[0].title.as_deref(), Some("Roadmap 2025"));
+            let skipped: Vec<_> = result
+                .skipped
+                .iter()
+                .map(|entry| entry.source_path.as_str())
+                .collect();
+            assert_eq!(
+                skipped,
+                vec![
+                    ".obsidian".to_string(),
+                    format!("Projects {PROJECTS_ID}/chart.png")
+                ]
+            );
+            assert!(!dst.join("notion").exists());
+
+            let mut notes = VaultScan::default();
+            notes.walk(&src, "", "notion", None)?;
+            convert_links(&mut notes.notes);
+            assert_eq!(
+                notes.notes[1].markdown,
+                format!(
+                    "See [[Roadmap#Goals|the roadmap]], [[Roadmap]] and \
+                     `[code](Projects%20{PROJECTS_ID}.md)`.\n"
+                )
+            );
+            Ok(())
+        })();
+        let _ = fs::remove_dir_all(&src);
+        let _ = fs::remove_dir_all(&dst);
+        result.unwrap();
+    }
+
+    #[test]
+    fn import_keeps_folders_titles_created_times_and_links() {
+        let src = make_temp_dir("vault-obsidian-src");
+        let dst = make_temp_dir("vault-obsidian-dst");
+        let result = (|| -> Result<(), String> {
+            fs::create_dir_all(src.join("Areas").join("Empty")).unwrap();
+            fs::create_dir_all(dst.join("vault").join("Areas")).unwrap();
+            fs::write(dst.join("vault").join("Areas").join("Home.md"), "taken").unwrap();
+            let home = "---\ntitle: Home base\ncreated: 2024-03-01T12:00:00Z\n---\nGo to [[Inbox note]].\n";
+            fs::write(src.join("Areas").join("Home.md"), home).unwrap();
+            fs::write(src.join("Inbox note.markdown"), "plain").unwrap();
+            fs::write(src.join("latin1.md"), [0x63, 0x61, 0x66, 0xe9]).unwrap();
+
+            let options = VaultImportOptions {
+                folder_path: Some("vault".to_string()),
+                ..VaultImportOptions::default()
+            };
+            let result = import_vault(&src, &options, &dst)?;
+            assert_eq!(result.notes.len(), 2);
+            assert!(result.notes[0].destination_exists);
+            assert_eq!(result.imported.len(), 2);
+            assert_eq!(result.skipped[0].source_path, "latin1.md");
+
+            let index = read_index(&dst)?;
+            let home_entry = index
+                .entries
+                .values()
+                .find(|entry| entry.manual_title.as_deref() == Some("Home base"))
+                .unwrap();
+            assert_eq!(home_entry.relative_path, "vault/Areas/Home-2.md");
+            assert_eq!(home_entry.created_at, 1_709_294_400_000);
+            assert_eq!(
+                fs::read_to_string(dst.join("vault").join("Areas").join("Home-2.md")).unwrap(),
+                home
+            );
+            assert!(index
+                .entries
+                .values()
+                .any(|entry| entry.relative_path == "vault/Inbox note.md"));
+            assert!(dst.join("vault").join("Areas").join("Empty").is_dir());
+            Ok(())
+        })();
+        let _ = fs::remove_dir_all(&src);
+        let _ = fs::remove_dir_all(&dst);
+        result.unwrap();
+    }
+}
@@ -72,6 +72,7 @@ vi.mock("./lib/tauriApi", () => ({
     cloud: {
       status: mocks.cloudStatus,
       detectProviders: mocks.detectCloudProviders,
+      setCustomFolder: vi.fn(),
       enable: vi.fn(),
       disable: vi.fn(),
       setActiveSource: mocks.setActiveSource,
@@ -254,6 +255,7 @@ function createLoadResult(
     issues: [],
     indexSourcePath: "C:/notes/.hwan-note-index.json",
     indexBackupPath: null,
+    conflicts: [],
   };
 }
 
@@ -416,6 +418,7 @@ describe("App locale changes", () => {
       activeSource: "local",
       resolvedSource: "local",
       cloudUnavailable: false,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
     mocks.detectCloudProviders.mockReset().mockResolvedValue([]);
     mocks.onFolderMissing.mockReset().mockReturnValue(() => undefined);
@@ -607,6 +610,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "local_fallback",
       cloudUnavailable: true,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
 
     await renderApp(root);
@@ -626,6 +630,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "cloud",
       cloudUnavailable: false,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
 
     await act(async () => {
@@ -677,6 +682,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "local_fallback",
       cloudUnavailable: true,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
 
     await renderApp(root);
@@ -695,6 +701,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "cloud",
       cloudUnavailable: false,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
 
     await act(async () => {
@@ -887,6 +894,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "cloud",
       cloudUnavailable: false,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
 
     await renderApp(root);
@@ -943,6 +951,7 @@ describe("App locale changes", () => {
       activeSource: "local",
       resolvedSource: null,
       cloudUnavailable: false,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
     mocks.loadAll.mockRejectedValue(new Error("custom path unavailable"));
 
@@ -1158,6 +1167,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "local_fallback",
       cloudUnavailable: true,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
 
     await renderApp(root);
@@ -1169,6 +1179,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "cloud",
       cloudUnavailable: false,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
     mocks.recoverCalendarDataFromCloud.mockReturnValueOnce(calendarRecovery.promise);
 
@@ -1226,6 +1237,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "local_fallback",
       cloudUnavailable: true,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
 
     await renderApp(root);
@@ -1237,6 +1249,7 @@ describe("App locale changes", () => {
       activeSource: "cloud",
       resolvedSource: "cloud",
       cloudUnavailable: false,
+      fallbackChanges: { notes: 0, deletedNotes: 0, calendar: false },
     });
     mocks.recoverCalendarDataFromCloud.mockResolvedValueOnce({
       status: "blocked",
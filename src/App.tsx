@@ -8,11 +8,16 @@ import {
   type AutoSaveDirInfo,
   type CloudProviderInfo,
   type CloudSyncSource,
+  type ExternalFileFormat,
+  type ImportedFile,
   type LoadedNote,
   type NoteLoadResult,
-  type NoteStorageSource
+  type NoteStorageSource,
+  type SavedAttachment,
+  type SyncConflict,
+  type SyncConflictResolution
 } from "./lib/tauriApi";
-import Editor, { restoreEditorFocus } from "./components/Editor";
+import Editor, { restoreEditorFocus, type EditorAttachments } from "./components/Editor";
 import SettingsPanel, { type ThemeMode } from "./components/SettingsPanel";
 import Sidebar, { type AppView, type SidebarTag } from "./components/Sidebar";
 import CalendarPage from "./components/calendar/CalendarPage";
@@ -135,9 +140,17 @@ function toStoredNoteDocument(
   return toMarkdownDocument(tab.title, tab.content, fallbackTitle);
 }
 
-function extractTags(plainText: string) {
+function externalFileToEditorContent(content: string, fileFormat: ExternalFileFormat) {
+  if (fileFormat === "md") {
+    const parsed = markdownToTiptapDocument(content);
+    return { content: parsed, plainText: tiptapDocumentToPlainText(parsed) };
+  }
+  return { content: plainTextToTiptapDocument(content), plainText: content.replace(/\r?\n/g, "\n") };
+}
+
+function extractTags(plainText: string, frontMatterTags: string[] = []) {
   const matcher = /(^|\s)#([\p{L}\p{N}_-]+)/gu;
-  const tags = new Set<string>();
+  const tags = new Set<string>(frontMatterTags.map((tag) => tag.toLowerCase()));
 
   for (const match of plainText.matchAll(matcher)) {
     tags.add(match[2].toLowerCase());
@@ -302,12 +315,20 @@ export default function App() {
   const toggleFileFormat = useNoteStore((state) => state.toggleFileFormat);
   const toggleSidebar = useNoteStore((state) => state.toggleSidebar);
   const addImportedTab = useNoteStore((state) => state.addImportedTab);
+  const setTabTextFormat = useNoteStore((state) => state.setTabTextFormat);
+  const reloadExternalTab = useNoteStore((state) => state.reloadExternalTab);
+  const applyMergedNote = useNoteStore((state) => state.applyMergedNote);
+  const applyExternalNotes = useNoteStore((state) => state.applyExternalNotes);
+  const removeExternalNotes = useNoteStore((state) => state.removeExternalNotes);
+  const adoptExternalTab = useNoteStore((state) => state.adoptExternalTab);
 
   const [activeView, setActiveView] = useState<AppView>("notes");
   const [noteStorageSource, setNoteStorageSource] = useState<NoteStorageSource>("local");
   const [noteRecoveryPending, setNoteRecoveryPending] = useState(false);
   const [noteLoadRecovery, setNoteLoadRecovery] = useState<NoteLoadRecoveryState | null>(null);
   const [noteLoadRetrying, setNoteLoadRetrying] = useState(false);
+  const [syncConflicts, setSyncConflicts] = useState<SyncConflict[]>([]);
+  const [resolvingConflictPath, setResolvingConflictPath] = useState<string | null>(null);
 
   const [isSplit, setIsSplit] = useState(false);
   const [splitRatio, setSplitRatio] = useState(() => {
@@ -642,6 +663,34 @@ export default function App() {
     armAutoSaveForTab(targetTabId);
   }, [armAutoSaveForTab, isSplit, primaryTabId, secondaryTabId, updateTabContent]);
 
+  const editorAttachmentsFor = useCallback((tab: NoteTab): EditorAttachments | undefined => {
+    if (tab.persistence === "external") {
+      return undefined;
+    }
+
+    const store = async (write: (loadedFrom: NoteStorageSource) => Promise<SavedAttachment>) => {
+      const loadedFrom = noteStorageSourceRef.current;
+      if (!(await ensureNoteLibraryMutationAllowed(loadedFrom))) {
+        return null;
+      }
+      return write(loadedFrom);
+    };
+    return {
+      save: (file) =>
+        store(async (loadedFrom) =>
+          hwanNote.attachment.save(
+            file.name,
+            new Uint8Array(await file.arrayBuffer()),
+            tab.id,
+            tab.folderPath,
+            loadedFrom
+          )
+        ),
+      import: (sourcePath) =>
+        store((loadedFrom) => hwanNote.attachment.import(sourcePath, tab.id, tab.folderPath, loadedFrom))
+    };
+  }, [ensureNoteLibraryMutationAllowed]);
+
   useEffect(() => {
     return () => {
       clearAutoSaveTimer();
@@ -672,7 +721,7 @@ export default function App() {
   const noteTags = useMemo(() => {
     const map = new Map<string, string[]>();
     allNotes.forEach((tab) => {
-      map.set(tab.id, extractTags(tab.plainText));
+      map.set(tab.id, extractTags(tab.plainText, tab.frontMatterTags));
     });
     return map;
   }, [allNotes]);
@@ -775,6 +824,7 @@ export default function App() {
         plainText,
         isDirty: false,
         isPinned: note.isPinned ?? false,
+        frontMatterTags: note.tags,
         folderPath,
         createdAt: note.createdAt,
         updatedAt: note.updatedAt,
@@ -918,6 +968,7 @@ export default function App() {
 
       loadedSuccessfully = true;
       updateNoteLoadRecovery(null);
+      setSyncConflicts(result.conflicts);
       noteRecoveryPendingRef.current = false;
       setNoteRecoveryPending(false);
       recoveryFailureNotifiedRef.current = false;
@@ -943,6 +994,35 @@ export default function App() {
     }
   }, [armAutoSaveForTab, clearAutoSaveTimer, hydrateLoadedNotes, saveDirtyLibraryTabsBeforeReload, updateNoteLoadRecovery]);
 
+  const reconcileFallbackChanges = useCallback(async () => {
+    const status = await hwanNote.cloud.status();
+    const pending = status.fallbackChanges;
+    if (
+      status.resolvedSource !== "cloud" ||
+      (pending.notes === 0 && pending.deletedNotes === 0 && !pending.calendar)
+    ) {
+      return;
+    }
+    if (!window.confirm(t("settings.cloudFallbackReconcileConfirm", { notes: pending.notes, deleted: pending.deletedNotes }))) {
+      return;
+    }
+
+    try {
+      const result = await hwanNote.cloud.reconcileFallback();
+      const outcomes = [...result.notes.map((note) => note.outcome), ...(result.calendar ? [result.calendar] : [])];
+      const conflicts = outcomes.filter((outcome) => outcome === "conflict").length;
+      const merged = outcomes.filter((outcome) => outcome !== "conflict" && outcome !== "unchanged").length;
+      const summary = [t("settings.cloudFallbackReconcileDone", { merged })];
+      if (conflicts > 0) {
+        summary.push(t("settings.cloudFallbackReconcileConflicts", { count: conflicts }));
+      }
+      window.alert(summary.join("\n"));
+    } catch (error) {
+      console.error("Failed to reconcile local fallback changes:", error);
+      window.alert(t("settings.cloudFallbackReconcileFailed"));
+    }
+  }, [t]);
+
   const recoverCloudLibrary = useCallback(async () => {
     const recoverySource = noteStorageSourceRef.current;
     if (
@@ -961,6 +1041,9 @@ export default function App() {
 
     try {
       await saveQueueRef.current.waitForIdle();
+      if (recoverySource === "local_fallback") {
+        await reconcileFallbackChanges();
+      }
       const result = await hwanNote.note.loadAll();
       noteStorageSourceRef.current = result.loadedFrom;
       setNoteStorageSource(result.loadedFrom);
@@ -1055,7 +1138,7 @@ export default function App() {
     } finally {
       recoveryInFlightRef.current = false;
     }
-  }, [clearAutoSaveTimer, hydrateTabs, mapLoadedNoteToTab, updateNoteLoadRecovery]);
+  }, [clearAutoSaveTimer, hydrateTabs, mapLoadedNoteToTab, reconcileFallbackChanges, updateNoteLoadRecovery]);
 
   const refreshLocalAutoSaveDir = useCallback(async () => {
     const settingsApi = hwanNote.settings;
@@ -1101,12 +1184,16 @@ export default function App() {
     return null;
   }, []);
 
-  const ingestImportedTextFile = useCallback((title: string, content: string, filePath: string) => {
+  const ingestImportedTextFile = useCallback((imported: ImportedFile) => {
+    const editorContent = externalFileToEditorContent(imported.content, imported.fileFormat);
     addImportedTab(
-      title,
-      plainTextToTiptapDocument(content),
-      content.replace(/\r?\n/g, "\n"),
-      filePath
+      imported.title,
+      editorContent.content,
+      editorContent.plainText,
+      imported.filePath,
+      imported.textFormat,
+      imported.fileFormat,
+      imported.version
     );
   }, [addImportedTab]);
 
@@ -1137,9 +1224,9 @@ export default function App() {
         return;
       }
 
-      ingestImportedTextFile(imported.title, imported.content, imported.filePath);
+      ingestImportedTextFile(imported);
     } catch (error) {
-      console.error("Failed to open external .txt file:", error);
+      console.error("Failed to open external file:", error);
     } finally {
       inFlightIntentKeysRef.current.delete(dedupeKey);
     }
@@ -1369,6 +1456,64 @@ export default function App() {
     return () => stopListening?.();
   }, [ingestExternalTxtIntent]);
 
+  useEffect(() => {
+    const noteApi = hwanNote.note;
+    const reportConflicts = (conflictedIds: string[]) => {
+      if (conflictedIds.length === 0) {
+        return;
+      }
+      conflictedIds.forEach(armAutoSaveForTab);
+      const titles = conflictedIds
+        .map((id) => getTabById(id)?.title.trim() || t("common.untitled"))
+        .join(", ");
+      window.alert(t("dialog.noteChangedOutside", { titles }));
+    };
+    const applyLoadedNotes = (notes: LoadedNote[]) => {
+      if (!hydrationCompleteRef.current || notes.length === 0) {
+        return;
+      }
+      reportConflicts(applyExternalNotes(notes.map(mapLoadedNoteToTab)));
+    };
+
+    const stopListening = [
+      noteApi.onExternalAdded?.(applyLoadedNotes),
+      noteApi.onExternalChanged?.(applyLoadedNotes),
+      noteApi.onExternalMoved?.((moves) => applyLoadedNotes(moves.map((move) => move.note))),
+      noteApi.onExternalRemoved?.((removals) => {
+        if (!hydrationCompleteRef.current || removals.length === 0) {
+          return;
+        }
+        reportConflicts(removeExternalNotes(removals.map((removal) => removal.noteId)));
+      }),
+      noteApi.onLinksRewritten?.((notes) => {
+        if (!hydrationCompleteRef.current) {
+          return;
+        }
+        const now = Date.now();
+        const rewrittenTabs = notes.flatMap((note): NoteTab[] => {
+          const current = getTabById(note.noteId);
+          if (!current || current.persistence !== "library") {
+            return [];
+          }
+          const content = markdownToTiptapDocument(note.markdown);
+          const rewritten: NoteTab = {
+            ...current,
+            content,
+            plainText: tiptapDocumentToPlainText(content),
+            isDirty: false,
+            updatedAt: now,
+            lastSavedAt: now
+          };
+          rewritten.savedSnapshot = createSavedSnapshot(rewritten);
+          return [rewritten];
+        });
+        reportConflicts(applyExternalNotes(rewrittenTabs));
+      })
+    ];
+
+    return () => stopListening.forEach((stop) => stop?.());
+  }, [applyExternalNotes, armAutoSaveForTab, getTabById, mapLoadedNoteToTab, removeExternalNotes, t]);
+
   useEffect(() => {
     const noteApi = hwanNote.note;
     if (!noteApi?.loadAll || initialHydrationFinalizedRef.current) {
@@ -1645,8 +1790,8 @@ export default function App() {
     const imported = await noteApi.importTxt();
     if (!imported || imported.length === 0) return;
 
-    for (const { title, content, filePath } of imported) {
-      ingestImportedTextFile(title, content, filePath);
+    for (const file of imported) {
+      ingestImportedTextFile(file);
     }
   }, [ingestImportedTextFile]);
 
@@ -1709,20 +1854,36 @@ export default function App() {
         lastSavedAt: Date.now()
       });
 
-    if (tab.fileFormat === "txt" && tab.sourceFilePath) {
+    if (tab.sourceFilePath) {
       if (!noteApi?.saveTxt) {
         return false;
       }
 
       try {
-        await noteApi.saveTxt(tab.sourceFilePath, tab.plainText);
+        const fileText = tab.fileFormat === "md"
+          ? toMarkdownDocument(tab.title, tab.content, t("common.untitled"))
+          : tab.plainText;
+        const textFormat = tab.textFormat ?? null;
+        let result = await noteApi.saveTxt(tab.sourceFilePath, fileText, textFormat, tab.fileVersion ?? null);
+        if (result.status === "conflict") {
+          const title = tab.title.trim() || t("common.untitled");
+          const messageKey = result.current ? "dialog.externalFileChanged" : "dialog.externalFileDeleted";
+          if (!window.confirm(t(messageKey, { title }))) {
+            return false;
+          }
+          result = await noteApi.saveTxt(tab.sourceFilePath, fileText, textFormat, null);
+        }
+        if (result.status !== "saved") {
+          return false;
+        }
         return markTabSaved(tab.id, {
           savedSnapshot: createCompletedSavedSnapshot(),
           persistence,
-          sourceFilePath: tab.sourceFilePath
+          sourceFilePath: tab.sourceFilePath,
+          fileVersion: result.version
         });
       } catch (error) {
-        console.error("Save txt failed:", error);
+        console.error("Save external file failed:", error);
         return false;
       }
     }
@@ -1742,7 +1903,7 @@ export default function App() {
     try {
       const markdown = toStoredNoteDocument(tab, t("common.untitled"));
 
-      await noteApi.autoSave(
+      const result = await noteApi.autoSave(
         tab.id,
         tab.title,
         markdown,
@@ -1752,20 +1913,81 @@ export default function App() {
         noteStorageSourceRef.current
       );
 
-      return markTabSaved(tab.id, {
+      const saved = markTabSaved(tab.id, {
         savedSnapshot: createCompletedSavedSnapshot(),
         persistence: "library"
       });
+      if (result.merge) {
+        // Edits typed while the save ran are kept; the merged text stays in note history.
+        const merged = markdownToTiptapDocument(result.merge.markdown);
+        applyMergedNote(tab.id, tab.revision, merged, tiptapDocumentToPlainText(merged));
+        if (result.merge.conflicts > 0) {
+          const title = tab.title.trim() || t("common.untitled");
+          window.alert(t("dialog.noteMergeConflicts", { title, count: result.merge.conflicts }));
+        }
+      }
+      return saved;
     } catch (error) {
       console.error("Save failed:", error);
       return false;
     }
-  }, [clearAutoSaveTimer, flushTitleDraft, getTabById, markTabSaved, t]);
+  }, [applyMergedNote, clearAutoSaveTimer, flushTitleDraft, getTabById, markTabSaved, t]);
 
   const handleSaveTab = useCallback((tabId: string) => {
     return saveQueueRef.current.run(tabId, () => performSaveTab(tabId));
   }, [performSaveTab]);
 
+  const handleReopenWithEncoding = useCallback(async (tabId: string, encoding: string) => {
+    const tab = getTabById(tabId);
+    if (!tab?.sourceFilePath || !hwanNote.note?.readExternalTxt) {
+      return;
+    }
+    if (tab.isDirty && !window.confirm(t("status.confirmReopenWithEncoding"))) {
+      return;
+    }
+
+    try {
+      const reopened = await hwanNote.note.readExternalTxt(tab.sourceFilePath, encoding);
+      const editorContent = externalFileToEditorContent(reopened.content, reopened.fileFormat);
+      reloadExternalTab(
+        tabId,
+        editorContent.content,
+        editorContent.plainText,
+        reopened.textFormat,
+        reopened.version
+      );
+    } catch (error) {
+      console.error("Reopen with encoding failed:", error);
+    }
+  }, [getTabById, reloadExternalTab, t]);
+
+  const handleSaveWithEncoding = useCallback(async (tabId: string, encoding: string) => {
+    const tab = getTabById(tabId);
+    if (!tab?.sourceFilePath || !tab.textFormat) {
+      return;
+    }
+
+    const previousFormat = tab.textFormat;
+    const keepsBom = encoding === "UTF-8" || encoding.startsWith("UTF-16");
+    setTabTextFormat(tabId, { ...previousFormat, encoding, bom: keepsBom && previousFormat.bom });
+    if (!(await handleSaveTab(tabId))) {
+      setTabTextFormat(tabId, previousFormat);
+      window.alert(t("status.saveWithEncodingFailed", { encoding }));
+    }
+  }, [getTabById, handleSaveTab, setTabTextFormat, t]);
+
+  const handleAdoptExternalTab = useCallback(async (tabId: string) => {
+    const tab = getTabById(tabId);
+    if (!tab?.sourceFilePath || !window.confirm(t("status.confirmAddToLibrary", { title: tab.title }))) {
+      return;
+    }
+
+    adoptExternalTab(tabId);
+    if (!(await handleSaveTab(tabId))) {
+      window.alert(t("status.addToLibraryFailed"));
+    }
+  }, [adoptExternalTab, getTabById, handleSaveTab, t]);
+
   useEffect(() => {
     saveTabRef.current = handleSaveTab;
   }, [handleSaveTab]);
@@ -2055,6 +2277,31 @@ export default function App() {
     }
   }, [noteLoadRetrying, reloadCurrentStorage]);
 
+  const handleResolveSyncConflict = useCallback(async (
+    conflict: SyncConflict,
+    resolution: SyncConflictResolution
+  ) => {
+    if (resolvingConflictPath) {
+      return;
+    }
+
+    setResolvingConflictPath(conflict.conflictPath);
+    try {
+      await hwanNote.library.resolveSyncConflict(
+        conflict.noteId,
+        conflict.conflictPath,
+        resolution,
+        noteStorageSourceRef.current
+      );
+      await reloadCurrentStorage();
+    } catch (error) {
+      console.error("Failed to resolve sync conflict:", error);
+      window.alert(t("notes.conflictResolveFailed", { reason: String(error) }));
+    } finally {
+      setResolvingConflictPath(null);
+    }
+  }, [reloadCurrentStorage, resolvingConflictPath, t]);
+
   const handleBrowseAutoSaveDir = useCallback(async () => {
     const settingsApi = hwanNote.settings;
     if (!settingsApi) return;
@@ -2159,6 +2406,21 @@ export default function App() {
     }
   }, [autoSaveDirInfo, prepareLocalStorageTransition, refreshCloudSyncState, refreshLocalAutoSaveDir, reloadCurrentStorage]);
 
+  const updateCustomSyncFolder = useCallback(async (dir: string | null) => {
+    try {
+      setCloudProviders(await hwanNote.cloud.setCustomFolder(dir));
+    } catch (error) {
+      console.error("Failed to set custom sync folder:", error);
+      window.alert(t("settings.cloudSyncCustomFolderFailed", { error: String(error) }));
+    }
+  }, [t]);
+
+  const handleBrowseCustomSyncFolder = useCallback(async () => {
+    const selected = await hwanNote.settings?.browseAutoSaveDir();
+    if (!selected) return;
+    await updateCustomSyncFolder(selected);
+  }, [updateCustomSyncFolder]);
+
   const handleInstallUpdate = useCallback(async () => {
     const isReadyToInstall = await runGuardedFlow(async () => {
       const state = useNoteStore.getState();
@@ -2761,6 +3023,48 @@ export default function App() {
         </section>
       ) : null}
 
+      {syncConflicts.length > 0 && !noteLoadRecovery ? (
+        <section className="note-recovery-panel no-drag" role="status" aria-live="polite">
+          <div className="note-recovery-copy">
+            <strong>{t("notes.conflictTitle", { count: syncConflicts.length })}</strong>
+            <span>{t("notes.conflictDescription")}</span>
+            <ul className="note-recovery-issues note-conflict-list">
+              {syncConflicts.map((conflict) => (
+                <li key={conflict.conflictPath}>
+                  <span className="note-recovery-operation">
+                    {getTabById(conflict.noteId)?.title || t("common.untitled")}
+                  </span>
+                  <span className="note-recovery-path">{conflict.conflictPath}</span>
+                  <span className="note-recovery-actions">
+                    <button
+                      type="button"
+                      disabled={resolvingConflictPath !== null}
+                      onClick={() => void handleResolveSyncConflict(conflict, { kind: "keepOriginal" })}
+                    >
+                      {t("notes.conflictKeepOriginal")}
+                    </button>
+                    <button
+                      type="button"
+                      disabled={resolvingConflictPath !== null}
+                      onClick={() => void handleResolveSyncConflict(conflict, { kind: "keepConflict" })}
+                    >
+                      {t("notes.conflictKeepCopy")}
+                    </button>
+                    <button
+                      type="button"
+                      disabled={resolvingConflictPath !== null}
+                      onClick={() => void handleResolveSyncConflict(conflict, { kind: "keepBoth" })}
+                    >
+                      {t("notes.conflictKeepBoth")}
+                    </button>
+                  </span>
+                </li>
+              ))}
+            </ul>
+          </div>
+        </section>
+      ) : null}
+
       <div className="workspace">
         <Sidebar
           visible={sidebarVisible}
@@ -2906,6 +3210,7 @@ export default function App() {
                     }}
                     onChange={(content, plainText) => handleEditorChange("primary", content, plainText)}
                     onCursorChange={(line, column, chars) => handleCursorChange("primary", line, column, chars)}
+                    attachments={editorAttachmentsFor(primaryTab)}
                   />
                 </section>
 
@@ -2929,6 +3234,7 @@ export default function App() {
                     }}
                     onChange={(content, plainText) => handleEditorChange("secondary", content, plainText)}
                     onCursorChange={(line, column, chars) => handleCursorChange("secondary", line, column, chars)}
+                    attachments={editorAttachmentsFor(secondaryTab)}
                   />
                 </section>
               </>
@@ -2946,6 +3252,7 @@ export default function App() {
                   }}
                   onChange={(content, plainText) => handleEditorChange("primary", content, plainText)}
                   onCursorChange={(line, column, chars) => handleCursorChange("primary", line, column, chars)}
+                  attachments={editorAttachmentsFor(primaryTab)}
                 />
               </section>
             ) : null}
@@ -2990,6 +3297,16 @@ export default function App() {
         }}
         cloudSyncProvider={cloudSyncProvider}
         cloudSyncSource={cloudSyncSource}
+        textFormat={focusedTab?.persistence === "external" ? (focusedTab.textFormat ?? null) : null}
+        onReopenWithEncoding={(encoding) => {
+          if (focusedTab) void handleReopenWithEncoding(focusedTab.id, encoding);
+        }}
+        onSaveWithEncoding={(encoding) => {
+          if (focusedTab) void handleSaveWithEncoding(focusedTab.id, encoding);
+        }}
+        onAddToLibrary={focusedTab?.persistence === "external" ? () => {
+          void handleAdoptExternalTab(focusedTab.id);
+        } : undefined}
       />
       )}
 
@@ -3010,6 +3327,8 @@ export default function App() {
         noteCount={allNotes.length}
         onCloudSyncChange={handleCloudSyncChange}
         onCloudSyncSourceChange={handleCloudSyncSourceChange}
+        onBrowseCustomSyncFolder={() => void handleBrowseCustomSyncFolder()}
+        onClearCustomSyncFolder={() => void updateCustomSyncFolder(null)}
         shortcuts={shortcuts}
         onThemeModeChange={setThemeMode}
         onEditorLineHeightChange={(value) => setEditorLineHeight(normalizeEditorLineHeight(value))}
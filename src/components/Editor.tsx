@@ -1,4 +1,4 @@
-﻿import { hwanShell } from "../lib/tauriApi";
+﻿import { hwanNote, hwanShell, type SavedAttachment } from "../lib/tauriApi";
 import Bold from "@tiptap/extension-bold";
 import Placeholder from "@tiptap/extension-placeholder";
 import Table from "@tiptap/extension-table";
@@ -14,11 +14,23 @@ import { getMarkRange, type JSONContent } from "@tiptap/core";
 import { Editor as TiptapEditor, EditorContent, useEditor } from "@tiptap/react";
 import { type MouseEvent as ReactMouseEvent, useCallback, useEffect, useRef, useState } from "react";
 import { TabIndent } from "../extensions/tabIndent";
+import { AttachmentImage } from "../extensions/attachmentImage";
 import { LinkWithTitle } from "../extensions/linkWithTitle";
 import { ToggleBlock, ToggleContent, ToggleSummary } from "../extensions/toggleBlock";
 import { useI18n } from "../i18n/context";
 import LinkBubble from "./LinkBubble";
 
+/** Stores files pasted or dropped into the note. Resolves to null when the library refused the write. */
+export interface EditorAttachments {
+  save: (file: File) => Promise<SavedAttachment | null>;
+  import: (sourcePath: string) => Promise<SavedAttachment | null>;
+}
+
+interface PendingAttachment {
+  name: string;
+  store: () => Promise<SavedAttachment | null>;
+}
+
 interface EditorProps {
   content: JSONContent;
   tabSize: number;
@@ -28,6 +40,8 @@ interface EditorProps {
   onCursorChange: (line: number, column: number, chars: number) => void;
   onEditorReady: (editor: TiptapEditor | null) => void;
   onFocus?: () => void;
+  /** Absent for notes outside the library, which cannot hold attachments. */
+  attachments?: EditorAttachments;
 }
 
 function collectPlainText(editor: TiptapEditor) {
@@ -50,6 +64,40 @@ function collectCursor(editor: TiptapEditor, existingPlainText?: string) {
   };
 }
 
+function fileBaseName(path: string) {
+  return path.split(/[\\/]/).pop() ?? path;
+}
+
+function attachmentContent(saved: SavedAttachment, name: string): JSONContent {
+  if (saved.mimeType.startsWith("image/")) {
+    return { type: "image", attrs: { src: saved.linkTarget, alt: name } };
+  }
+  return { type: "text", text: name, marks: [{ type: "link", attrs: { href: saved.linkTarget } }] };
+}
+
+/** Stores each file and inserts an image or link for it. Returns whether any file failed. */
+async function insertAttachments(editor: TiptapEditor, pending: PendingAttachment[], position?: number) {
+  const content: JSONContent[] = [];
+  let failed = false;
+  for (const item of pending) {
+    try {
+      const saved = await item.store();
+      if (saved) {
+        content.push(attachmentContent(saved, item.name), { type: "text", text: " " });
+      }
+    } catch (error) {
+      console.error("Failed to attach file:", error);
+      failed = true;
+    }
+  }
+
+  if (content.length > 0 && !editor.isDestroyed) {
+    const chain = editor.chain().focus();
+    (position === undefined ? chain.insertContent(content) : chain.insertContentAt(position, content)).run();
+  }
+  return failed;
+}
+
 const BoldWithoutShortcut = Bold.extend({
   addKeyboardShortcuts() {
     return {};
@@ -102,7 +150,8 @@ export default function Editor({
   onChange,
   onCursorChange,
   onEditorReady,
-  onFocus
+  onFocus,
+  attachments
 }: EditorProps) {
   const { t } = useI18n();
   const placeholderText = t("editor.placeholder");
@@ -122,6 +171,9 @@ export default function Editor({
 
   const initialAutofocusRef = useRef(autofocus);
 
+  // Pasted and dropped files are File objects; files dropped from the OS arrive as paths.
+  const attachRef = useRef<(sources: Array<File | string>, position?: number) => boolean>(() => false);
+
   const editor = useEditor({
     extensions: [
       StarterKit.configure({
@@ -142,6 +194,7 @@ export default function Editor({
       ToggleBlock,
       ToggleSummary,
       ToggleContent,
+      AttachmentImage,
       LinkWithTitle.configure({
         openOnClick: false,
         autolink: true,
@@ -171,6 +224,11 @@ export default function Editor({
       attributes: {
         class: "note-editor",
         spellcheck: spellcheck ? "true" : "false"
+      },
+      handlePaste: (_view, event) => attachRef.current(Array.from(event.clipboardData?.files ?? [])),
+      handleDrop: (view, event) => {
+        const position = view.posAtCoords({ left: event.clientX, top: event.clientY })?.pos;
+        return attachRef.current(Array.from(event.dataTransfer?.files ?? []), position);
       }
     },
     onUpdate: ({ editor }) => {
@@ -188,6 +246,40 @@ export default function Editor({
     }
   }, [placeholderText]);
 
+  attachRef.current = (sources, position) => {
+    if (!editor || !attachments || sources.length === 0) {
+      return false;
+    }
+    const pending = sources.map((source): PendingAttachment =>
+      typeof source === "string"
+        ? { name: fileBaseName(source), store: () => attachments.import(source) }
+        : { name: source.name, store: () => attachments.save(source) }
+    );
+    void insertAttachments(editor, pending, position).then((failed) => {
+      if (failed) {
+        window.alert(t("editor.attachmentFailed"));
+      }
+    });
+    return true;
+  };
+
+  // The window takes OS file drops itself, so the drop is matched to this editor by position.
+  const canAttach = Boolean(attachments);
+  useEffect(() => {
+    if (!editor || !canAttach) {
+      return;
+    }
+
+    return hwanNote.attachment.onFileDrop((paths, point) => {
+      const target = document.elementFromPoint(point.x, point.y);
+      if (!target || !editor.view.dom.contains(target)) {
+        return;
+      }
+      const position = editor.view.posAtCoords({ left: point.x, top: point.y })?.pos;
+      attachRef.current(paths, position);
+    });
+  }, [canAttach, editor]);
+
   const closeLinkBubble = useCallback(() => {
     setLinkBubble(null);
     restoreEditorFocus(editor);
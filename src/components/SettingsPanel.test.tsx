@@ -57,6 +57,8 @@ describe("SettingsPanel custom storage status", () => {
             noteCount={0}
             onCloudSyncChange={vi.fn().mockResolvedValue(undefined)}
             onCloudSyncSourceChange={vi.fn().mockResolvedValue(undefined)}
+            onBrowseCustomSyncFolder={vi.fn()}
+            onClearCustomSyncFolder={vi.fn()}
             shortcuts={createDefaultShortcuts()}
             onThemeModeChange={vi.fn()}
             onEditorLineHeightChange={vi.fn()}
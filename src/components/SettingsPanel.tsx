@@ -33,6 +33,8 @@ interface SettingsPanelProps {
   noteCount: number;
   onCloudSyncChange: (provider: string | null, options?: { copyLocalNotes: boolean }) => Promise<void>;
   onCloudSyncSourceChange: (source: CloudSyncSource) => Promise<void>;
+  onBrowseCustomSyncFolder: () => void;
+  onClearCustomSyncFolder: () => void;
   shortcuts: ShortcutMap;
   onThemeModeChange: (mode: ThemeMode) => void;
   onEditorLineHeightChange: (value: number) => void;
@@ -63,6 +65,8 @@ export default function SettingsPanel({
   noteCount,
   onCloudSyncChange,
   onCloudSyncSourceChange,
+  onBrowseCustomSyncFolder,
+  onClearCustomSyncFolder,
   shortcuts,
   onThemeModeChange,
   onEditorLineHeightChange,
@@ -81,7 +85,15 @@ export default function SettingsPanel({
   const providerLabels: Record<string, string> = {
     onedrive: "OneDrive",
     google_drive: "Google Drive",
+    dropbox: "Dropbox",
+    nextcloud: "Nextcloud",
+    owncloud: "ownCloud",
+    syncthing: "Syncthing",
+    custom: t("settings.cloudSyncCustom"),
   };
+  const providerName = (provider: CloudProviderInfo) =>
+    provider.id === "custom" ? providerLabels.custom : provider.name;
+  const customProvider = cloudProviders.find((provider) => provider.id === "custom");
 
   const activeProvider = cloudSyncProvider
     ? cloudProviders.find((provider) => provider.id === cloudSyncProvider) ?? {
@@ -317,7 +329,7 @@ export default function SettingsPanel({
                   const provider = cloudProviders.find((p) => p.id === value);
                   if (!provider?.available) return;
                   const msg = t("settings.cloudSyncConfirmEnable", {
-                    provider: provider.name,
+                    provider: providerName(provider),
                   });
                   if (!window.confirm(msg)) {
                     event.target.value = cloudSyncProvider ?? "";
@@ -328,7 +340,7 @@ export default function SettingsPanel({
                       ? window.confirm(
                           t("settings.cloudSyncConfirmCopy", {
                             count: noteCount,
-                            provider: provider.name,
+                            provider: providerName(provider),
                           })
                         )
                       : false;
@@ -346,8 +358,12 @@ export default function SettingsPanel({
               {providerOptions.map((provider) => (
                 <option key={provider.id} value={provider.id} disabled={!provider.available}>
                   {provider.available || provider.id === cloudSyncProvider
-                    ? provider.name
-                    : t("settings.cloudSyncNotInstalled", { provider: provider.name })}
+                    ? providerName(provider)
+                    : provider.syncFolder
+                      ? t("settings.cloudSyncFolderUnavailable", { provider: providerName(provider) })
+                      : provider.id === "custom"
+                        ? t("settings.cloudSyncCustomUnset")
+                        : t("settings.cloudSyncNotInstalled", { provider: provider.name })}
                 </option>
               ))}
             </select>
@@ -362,6 +378,34 @@ export default function SettingsPanel({
             <div className="settings-subtext">{t("settings.cloudSyncHelp")}</div>
           </div>
 
+          <div className="settings-item">
+            <label>{t("settings.cloudSyncCustomFolder")}</label>
+            <div className="settings-autosave-row">
+              <div className="settings-readonly settings-autosave-path">
+                {customProvider?.syncFolder || t("settings.cloudSyncCustomFolderUnset")}
+              </div>
+              <button
+                type="button"
+                className="settings-autosave-btn"
+                onClick={onBrowseCustomSyncFolder}
+                disabled={cloudSyncProvider === "custom"}
+              >
+                {t("settings.autoSaveBrowse")}
+              </button>
+              {customProvider?.syncFolder && (
+                <button
+                  type="button"
+                  className="settings-autosave-btn settings-autosave-reset"
+                  onClick={onClearCustomSyncFolder}
+                  disabled={cloudSyncProvider === "custom"}
+                >
+                  {t("settings.autoSaveReset")}
+                </button>
+              )}
+            </div>
+            <div className="settings-subtext">{t("settings.cloudSyncCustomFolderHelp")}</div>
+          </div>
+
           {cloudSyncProvider && (
             <div className="settings-item">
               <label htmlFor="cloud-sync-source">{t("settings.cloudSyncSource")}</label>
@@ -377,7 +421,7 @@ export default function SettingsPanel({
                 }}
               >
                 <option value="cloud">
-                  {activeProvider?.name ?? t("settings.cloudSyncSourceCloud")}
+                  {activeProvider ? providerName(activeProvider) : t("settings.cloudSyncSourceCloud")}
                 </option>
                 <option value="local">{t("settings.cloudSyncSourceLocal")}</option>
               </select>
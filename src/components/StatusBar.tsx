@@ -1,5 +1,5 @@
 import { useI18n } from "../i18n/context";
-import type { CloudSyncSource } from "../lib/tauriApi";
+import type { CloudSyncSource, TextFormat } from "../lib/tauriApi";
 
 interface StatusBarProps {
   line: number;
@@ -11,14 +11,45 @@ interface StatusBarProps {
   onToggleFileFormat: () => void;
   cloudSyncProvider: string | null;
   cloudSyncSource: CloudSyncSource;
+  /** Set for external text files, which can be reopened or saved in another encoding. */
+  textFormat: TextFormat | null;
+  onReopenWithEncoding: (encoding: string) => void;
+  onSaveWithEncoding: (encoding: string) => void;
+  /** Set for external files, which are edited in place until added to the library. */
+  onAddToLibrary?: () => void;
+}
+
+const TEXT_ENCODINGS = ["UTF-8", "UTF-16LE", "UTF-16BE", "EUC-KR", "Shift_JIS", "windows-1252"];
+
+function formatLabel(textFormat: TextFormat) {
+  const bom = textFormat.bom ? " BOM" : "";
+  return `${textFormat.encoding}${bom} | ${textFormat.lineEnding.toUpperCase()}`;
 }
 
 const PROVIDER_LABELS: Record<string, string> = {
   onedrive: "OneDrive",
   google_drive: "Google Drive",
+  dropbox: "Dropbox",
+  nextcloud: "Nextcloud",
+  owncloud: "ownCloud",
+  syncthing: "Syncthing",
 };
 
-export default function StatusBar({ line, column, chars, themeLabel, zoomPercent, fileFormat, onToggleFileFormat, cloudSyncProvider, cloudSyncSource }: StatusBarProps) {
+export default function StatusBar({
+  line,
+  column,
+  chars,
+  themeLabel,
+  zoomPercent,
+  fileFormat,
+  onToggleFileFormat,
+  cloudSyncProvider,
+  cloudSyncSource,
+  textFormat,
+  onReopenWithEncoding,
+  onSaveWithEncoding,
+  onAddToLibrary
+}: StatusBarProps) {
   const { t } = useI18n();
 
   const storageLabel = cloudSyncProvider && cloudSyncSource === "cloud"
@@ -37,10 +68,49 @@ export default function StatusBar({ line, column, chars, themeLabel, zoomPercent
         >
           {fileFormat === "md" ? t("status.markdown") : t("status.plainText")}
         </button>
+        {onAddToLibrary && (
+          <button
+            type="button"
+            className="statusbar-format-toggle"
+            onClick={onAddToLibrary}
+            title={t("status.addToLibraryHint")}
+          >
+            {t("status.addToLibrary")}
+          </button>
+        )}
       </div>
       <div className="statusbar-right">
         <span className="statusbar-cloud">{storageLabel} | </span>
-        {t("status.rightInfo", { theme: themeLabel, zoom: zoomPercent })}
+        {t("status.rightInfo", { theme: themeLabel, zoom: zoomPercent })} |{" "}
+        {textFormat ? (
+          <select
+            className="statusbar-encoding"
+            value=""
+            title={t("status.encodingMenu")}
+            onChange={(event) => {
+              const [action, encoding] = event.target.value.split(":");
+              if (action === "reopen") {
+                onReopenWithEncoding(encoding);
+              } else if (action === "save") {
+                onSaveWithEncoding(encoding);
+              }
+            }}
+          >
+            <option value="" disabled>{formatLabel(textFormat)}</option>
+            <optgroup label={t("status.reopenWithEncoding")}>
+              {TEXT_ENCODINGS.map((encoding) => (
+                <option key={`reopen:${encoding}`} value={`reopen:${encoding}`}>{encoding}</option>
+              ))}
+            </optgroup>
+            <optgroup label={t("status.saveWithEncoding")}>
+              {TEXT_ENCODINGS.map((encoding) => (
+                <option key={`save:${encoding}`} value={`save:${encoding}`}>{encoding}</option>
+              ))}
+            </optgroup>
+          </select>
+        ) : (
+          "UTF-8"
+        )}
       </div>
     </footer>
   );
@@ -393,6 +393,9 @@ export default function TitleBar({
                 {isSplitMember ? <span className="tab-split-badge">{t("titlebar.splitBadge")}</span> : null}
                 <span className="tab-title">{tab.title}</span>
                 {tab.isDirty ? <span className="tab-dirty">*</span> : null}
+                {tab.hasExternalChanges ? (
+                  <span className="tab-external-changes" title={t("titlebar.externalChanges")}>!</span>
+                ) : null}
                 <span
                   role="button"
                   className="tab-close"
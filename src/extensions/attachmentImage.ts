@@ -0,0 +1,40 @@
+import { mergeAttributes, Node } from "@tiptap/core";
+import { hwanNote } from "../lib/tauriApi";
+
+export const AttachmentImage = Node.create({
+  name: "image",
+  inline: true,
+  group: "inline",
+  draggable: true,
+
+  addAttributes() {
+    return {
+      src: {
+        default: null,
+        // The rendered src is a webview URL; the Markdown link target lives in data-src.
+        parseHTML: (element: HTMLElement) => element.getAttribute("data-src") ?? element.getAttribute("src")
+      },
+      alt: {
+        default: null
+      },
+      title: {
+        default: null
+      }
+    };
+  },
+
+  parseHTML() {
+    return [{ tag: "img[src]" }];
+  },
+
+  renderHTML({ HTMLAttributes }) {
+    const src = typeof HTMLAttributes.src === "string" ? HTMLAttributes.src : "";
+    return [
+      "img",
+      mergeAttributes(HTMLAttributes, {
+        src: hwanNote.attachment.resolveUrl(src) ?? src,
+        "data-src": src
+      })
+    ];
+  }
+});
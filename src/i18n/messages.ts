@@ -14,6 +14,10 @@ const messages = {
     "common.cancel": "취소",
     "dialog.unsavedChangesTitle": "저장하지 않은 변경 내용",
     "dialog.unsavedChangesMessage": "\"{{title}}\"의 변경 내용을 저장할까요?\n저장하지 않으면 변경 내용이 사라집니다.",
+    "dialog.externalFileChanged": "\"{{title}}\" 파일이 다른 프로그램에서 변경되었습니다.\n덮어쓰면 그 변경 내용이 사라집니다. 덮어쓸까요?",
+    "dialog.externalFileDeleted": "\"{{title}}\" 파일이 다른 프로그램에서 삭제되었거나 이동되었습니다.\n원래 위치에 다시 저장할까요?",
+    "dialog.noteMergeConflicts": "\"{{title}}\" 메모가 다른 기기에서도 수정되어 변경 내용을 합쳤습니다.\n겹치는 부분 {{count}}곳은 충돌 표시(<<<<<<< / >>>>>>>) 사이에 두 버전을 모두 남겼습니다.",
+    "dialog.noteChangedOutside": "다른 앱이나 기기에서 바뀐 메모가 있습니다: {{titles}}\n저장하지 않은 변경 내용은 유지되며, 다음 저장 때 바깥 변경과 합칩니다.",
     "titlebar.menu": "Menu",
     "titlebar.toggleSidebar": "사이드바 토글",
     "titlebar.closeTab": "{{title}} 닫기",
@@ -28,6 +32,7 @@ const messages = {
     "titlebar.context.unpin": "고정 해제",
     "titlebar.context.unsplit": "분할 해제",
     "titlebar.splitBadge": "분할",
+    "titlebar.externalChanges": "다른 곳에서 바뀜 — 다음 저장 때 합칩니다",
     "titlebar.closeDirty.saveAsClose": "다른 이름으로 저장 후 닫기",
     "titlebar.closeDirty.temporaryClose": "임시 닫기",
     "titlebar.closeDirty.cancel": "취소",
@@ -55,6 +60,7 @@ const messages = {
     "linkBubble.edit": "링크 편집",
     "linkBubble.remove": "링크 제거",
     "editor.placeholder": "메모를 입력하세요...",
+    "editor.attachmentFailed": "파일을 첨부하지 못했습니다.",
     "sidebar.search": "검색",
     "sidebar.searchPlaceholder": "검색어를 입력하세요",
     "sidebar.searchAll": "전체",
@@ -81,7 +87,7 @@ const messages = {
     "sidebar.notePin": "고정",
     "sidebar.noteUnpin": "고정 해제",
     "sidebar.noteDelete": "삭제",
-    "sidebar.noteDeleteConfirm": "'{{title}}' 메모를 삭제할까요?\n삭제된 메모는 휴지통으로 이동됩니다.",
+    "sidebar.noteDeleteConfirm": "'{{title}}' 메모를 삭제할까요?\n삭제된 메모는 휴지통으로 이동되며 복원할 수 있습니다.",
     "sidebar.noteDeleteFailed": "메모를 삭제하지 못했습니다. 메모가 목록에 남아 있으면 다시 시도해 주세요.",
     "settings.title": "설정",
     "settings.closeAria": "설정 닫기",
@@ -120,6 +126,13 @@ const messages = {
     "settings.cloudSyncHelp": "연동 후에는 로컬 라이브러리와 클라우드 라이브러리를 전환해서 볼 수 있습니다.",
     "settings.cloudSyncFolder": "동기화 폴더: {{path}}",
     "settings.cloudSyncNotInstalled": "{{provider}} Desktop이 설치되지 않았습니다.",
+    "settings.cloudSyncFolderUnavailable": "{{provider}} 폴더에 접근할 수 없습니다.",
+    "settings.cloudSyncCustom": "사용자 지정 폴더",
+    "settings.cloudSyncCustomUnset": "사용자 지정 폴더 (아래에서 폴더를 선택하세요)",
+    "settings.cloudSyncCustomFolder": "사용자 지정 동기화 폴더",
+    "settings.cloudSyncCustomFolderUnset": "선택된 폴더 없음",
+    "settings.cloudSyncCustomFolderHelp": "NAS 공유 폴더나 다른 동기화 도구가 관리하는 폴더를 클라우드 라이브러리로 사용할 수 있습니다. 사용 중에는 변경할 수 없습니다.",
+    "settings.cloudSyncCustomFolderFailed": "사용자 지정 동기화 폴더를 설정하지 못했습니다.\n\n{{error}}",
     "settings.cloudSyncConfirmEnable": "{{provider}} 라이브러리를 연결하고 기본 보기를 클라우드로 전환할까요?",
     "settings.cloudSyncConfirmCopy": "현재 로컬 메모 {{count}}개를 {{provider}} 라이브러리로 복사할까요?\n\n취소를 누르면 복사하지 않고 클라우드 내용만 엽니다.",
     "settings.cloudSyncConfirmDisable": "클라우드 연동을 해제하고 로컬 보기로 돌아갈까요?\n\n클라우드 폴더의 메모는 그대로 보존됩니다.",
@@ -129,6 +142,10 @@ const messages = {
     "settings.cloudSyncDisable": "동기화 해제",
     "settings.cloudSyncFolderMissing": "클라우드 동기화 폴더에 접근할 수 없습니다. 로컬 저장소를 사용 중입니다.",
     "settings.cloudSyncFolderMissingDetail": "경로: {{path}}",
+    "settings.cloudFallbackReconcileConfirm": "클라우드 동기화 폴더에 다시 연결되었습니다.\n연결이 끊긴 동안 로컬에서 수정한 메모 {{notes}}개와 삭제한 메모 {{deleted}}개를 클라우드 라이브러리에 반영할까요?",
+    "settings.cloudFallbackReconcileDone": "{{merged}}개 항목을 클라우드 라이브러리에 반영했습니다.",
+    "settings.cloudFallbackReconcileConflicts": "{{count}}개 항목은 클라우드에서도 변경되어 로컬 버전을 별도 사본으로 남겼습니다.",
+    "settings.cloudFallbackReconcileFailed": "로컬 변경 내용을 클라우드 라이브러리에 반영하지 못했습니다. 다음에 다시 시도합니다.",
     "settings.cloudSyncRecoveredTitle": "클라우드 저장소 복구 완료",
     "settings.cloudSyncRecovered": "클라우드 저장소가 다시 연결되어 메모를 안전하게 다시 불러왔습니다.",
     "settings.cloudSyncRecoveredWithCopies": "클라우드 저장소가 다시 연결되었습니다. 로컬과 다른 메모 {{count}}개는 덮어쓰지 않고 저장되지 않은 복구 탭으로 열었습니다. 내용을 확인한 뒤 저장해 주세요.",
@@ -152,6 +169,12 @@ const messages = {
     "notes.recoveryUnknownCause": "구체적인 오류 원인을 확인하지 못했습니다. 저장 경로와 권한을 확인해 주세요.",
     "notes.recoveryRetry": "노트 라이브러리 다시 스캔",
     "notes.recoveryRetrying": "다시 스캔하는 중…",
+    "notes.conflictTitle": "동기화 충돌 사본 {{count}}개",
+    "notes.conflictDescription": "클라우드 동기화가 두 기기에서 동시에 수정된 노트의 사본을 만들었습니다. 사본은 라이브러리에 추가하지 않았습니다. 노트마다 남길 내용을 선택해 주세요.",
+    "notes.conflictKeepOriginal": "현재 노트 유지",
+    "notes.conflictKeepCopy": "사본으로 바꾸기",
+    "notes.conflictKeepBoth": "둘 다 유지",
+    "notes.conflictResolveFailed": "충돌을 해결하지 못했습니다: {{reason}}",
     "settings.cloudSyncSource": "현재 보기",
     "settings.cloudSyncSourceCloud": "클라우드",
     "settings.cloudSyncSourceLocal": "로컬",
@@ -185,7 +208,16 @@ const messages = {
     "status.switchToMd": "마크다운 형식으로 전환",
     "status.confirmSwitchToTxt": "텍스트 형식으로 전환하면 체크리스트, 토글, 굵게 등 서식 정보가 사라집니다.\n\n계속하시겠습니까?",
     "status.localStorage": "로컬",
-    "status.rightInfo": "{{theme}} | {{zoom}}% | UTF-8",
+    "status.rightInfo": "{{theme}} | {{zoom}}%",
+    "status.encodingMenu": "인코딩으로 다시 열기 또는 저장",
+    "status.reopenWithEncoding": "인코딩으로 다시 열기",
+    "status.saveWithEncoding": "인코딩으로 저장",
+    "status.confirmReopenWithEncoding": "저장하지 않은 변경 내용이 사라집니다.\n\n다른 인코딩으로 다시 여시겠습니까?",
+    "status.saveWithEncodingFailed": "{{encoding}}(으)로 저장하지 못했습니다. 이 인코딩으로 표현할 수 없는 문자가 있을 수 있습니다.",
+    "status.addToLibrary": "라이브러리에 추가",
+    "status.addToLibraryHint": "이 파일을 원본 위치가 아닌 노트 라이브러리에 저장합니다",
+    "status.confirmAddToLibrary": "\"{{title}}\"을(를) 라이브러리 노트로 추가할까요? 이후 변경 사항은 원본 파일에 저장되지 않습니다.",
+    "status.addToLibraryFailed": "라이브러리에 추가하지 못했습니다.",
     "theme.light": "테마: 라이트",
     "theme.dark": "테마: 다크",
     "theme.system": "테마: 시스템",
@@ -290,6 +322,10 @@ const messages = {
     "common.cancel": "Cancel",
     "dialog.unsavedChangesTitle": "Unsaved Changes",
     "dialog.unsavedChangesMessage": "Do you want to save changes to \"{{title}}\"?\nIf you don't save, your changes will be lost.",
+    "dialog.externalFileChanged": "\"{{title}}\" was changed by another program.\nOverwriting it will discard those changes. Overwrite the file?",
+    "dialog.externalFileDeleted": "\"{{title}}\" was deleted or moved by another program.\nSave it again at its original location?",
+    "dialog.noteMergeConflicts": "\"{{title}}\" was also edited on another device, so the changes were merged.\nBoth versions of {{count}} overlapping section(s) were kept between conflict markers (<<<<<<< / >>>>>>>).",
+    "dialog.noteChangedOutside": "These notes were changed outside the app: {{titles}}\nYour unsaved edits are kept and will be merged with the outside changes on the next save.",
     "titlebar.menu": "Menu",
     "titlebar.toggleSidebar": "Toggle sidebar",
     "titlebar.closeTab": "Close {{title}}",
@@ -304,6 +340,7 @@ const messages = {
     "titlebar.context.unpin": "Unpin",
     "titlebar.context.unsplit": "Unsplit",
     "titlebar.splitBadge": "Split",
+    "titlebar.externalChanges": "Changed elsewhere — merged on the next save",
     "titlebar.closeDirty.saveAsClose": "Save As and Close",
     "titlebar.closeDirty.temporaryClose": "Temporary Close",
     "titlebar.closeDirty.cancel": "Cancel",
@@ -331,6 +368,7 @@ const messages = {
     "linkBubble.edit": "Edit link",
     "linkBubble.remove": "Remove link",
     "editor.placeholder": "Start writing your note...",
+    "editor.attachmentFailed": "Could not attach the file.",
     "sidebar.search": "Search",
     "sidebar.searchPlaceholder": "Enter search term",
     "sidebar.searchAll": "All",
@@ -357,7 +395,7 @@ const messages = {
     "sidebar.notePin": "Pin",
     "sidebar.noteUnpin": "Unpin",
     "sidebar.noteDelete": "Delete",
-    "sidebar.noteDeleteConfirm": "Delete '{{title}}'?\nThe note will be moved to the Recycle Bin.",
+    "sidebar.noteDeleteConfirm": "Delete '{{title}}'?\nThe note will be moved to the trash, where it can be restored.",
     "sidebar.noteDeleteFailed": "The note could not be deleted. If it remains in the list, try again.",
     "settings.title": "Settings",
     "settings.closeAria": "Close settings",
@@ -396,6 +434,13 @@ const messages = {
     "settings.cloudSyncHelp": "Once linked, you can switch between the local library and the cloud library.",
     "settings.cloudSyncFolder": "Sync folder: {{path}}",
     "settings.cloudSyncNotInstalled": "{{provider}} Desktop is not installed.",
+    "settings.cloudSyncFolderUnavailable": "{{provider}} folder is not accessible.",
+    "settings.cloudSyncCustom": "Custom folder",
+    "settings.cloudSyncCustomUnset": "Custom folder (choose a folder below)",
+    "settings.cloudSyncCustomFolder": "Custom sync folder",
+    "settings.cloudSyncCustomFolderUnset": "No folder selected",
+    "settings.cloudSyncCustomFolderHelp": "Use a NAS share or a folder another sync tool manages as the cloud library. It cannot change while it is in use.",
+    "settings.cloudSyncCustomFolderFailed": "Could not set the custom sync folder.\n\n{{error}}",
     "settings.cloudSyncConfirmEnable": "Link {{provider}} and switch the default view to the cloud library?",
     "settings.cloudSyncConfirmCopy": "Copy {{count}} local notes into the {{provider}} library?\n\nPress Cancel to open the cloud library without copying.",
     "settings.cloudSyncConfirmDisable": "Disable cloud linking and return to the local library?\n\nNotes in the cloud folder will be preserved.",
@@ -405,6 +450,10 @@ const messages = {
     "settings.cloudSyncDisable": "Disable sync",
     "settings.cloudSyncFolderMissing": "Cloud sync folder is not accessible. Using local storage.",
     "settings.cloudSyncFolderMissingDetail": "Path: {{path}}",
+    "settings.cloudFallbackReconcileConfirm": "The cloud sync folder is back.\nApply {{notes}} note(s) edited and {{deleted}} note(s) deleted locally while it was unreachable to the cloud library?",
+    "settings.cloudFallbackReconcileDone": "Applied {{merged}} change(s) to the cloud library.",
+    "settings.cloudFallbackReconcileConflicts": "{{count}} item(s) had also changed in the cloud, so the local versions were kept as separate copies.",
+    "settings.cloudFallbackReconcileFailed": "Could not apply local changes to the cloud library. They will be offered again next time.",
     "settings.cloudSyncRecoveredTitle": "Cloud storage restored",
     "settings.cloudSyncRecovered": "Cloud storage is available again, and notes were safely reloaded.",
     "settings.cloudSyncRecoveredWithCopies": "Cloud storage is available again. {{count}} local notes that differed from the cloud were opened as unsaved recovery tabs instead of overwriting cloud files. Review and save them when ready.",
@@ -428,6 +477,12 @@ const messages = {
     "notes.recoveryUnknownCause": "No specific cause was reported. Check the storage path and permissions.",
     "notes.recoveryRetry": "Rescan note library",
     "notes.recoveryRetrying": "Rescanning…",
+    "notes.conflictTitle": "{{count}} sync conflict copies",
+    "notes.conflictDescription": "Cloud sync made copies of notes that were edited on two computers at once. The copies were not added to the library. Choose what to keep for each note.",
+    "notes.conflictKeepOriginal": "Keep current note",
+    "notes.conflictKeepCopy": "Use the copy",
+    "notes.conflictKeepBoth": "Keep both",
+    "notes.conflictResolveFailed": "Could not resolve the conflict: {{reason}}",
     "settings.cloudSyncSource": "Current library",
     "settings.cloudSyncSourceCloud": "Cloud",
     "settings.cloudSyncSourceLocal": "Local",
@@ -461,7 +516,16 @@ const messages = {
     "status.confirmSwitchToTxt": "Switching to plain text will remove formatting such as checklists, toggles, and bold.\n\nDo you want to continue?",
     "status.switchToMd": "Switch to Markdown",
     "status.localStorage": "Local",
-    "status.rightInfo": "{{theme}} | {{zoom}}% | UTF-8",
+    "status.rightInfo": "{{theme}} | {{zoom}}%",
+    "status.encodingMenu": "Reopen or save with an encoding",
+    "status.reopenWithEncoding": "Reopen with encoding",
+    "status.saveWithEncoding": "Save with encoding",
+    "status.confirmReopenWithEncoding": "Unsaved changes will be lost.\n\nReopen the file in another encoding?",
+    "status.saveWithEncodingFailed": "Could not save as {{encoding}}. The text may contain characters this encoding cannot store.",
+    "status.addToLibrary": "Add to library",
+    "status.addToLibraryHint": "Save this file as a note in the library instead of its original location",
+    "status.confirmAddToLibrary": "Add \"{{title}}\" to the library as a note? Later edits will no longer be saved to the original file.",
+    "status.addToLibraryFailed": "Could not add the file to the library.",
     "theme.light": "Theme: Light",
     "theme.dark": "Theme: Dark",
     "theme.system": "Theme: System",
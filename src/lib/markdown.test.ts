@@ -6,6 +6,7 @@ import TableRow from "@tiptap/extension-table-row";
 import TaskList from "@tiptap/extension-task-list";
 import StarterKit from "@tiptap/starter-kit";
 import { describe, expect, it } from "vitest";
+import { AttachmentImage } from "../extensions/attachmentImage";
 import { TaskItemExtended } from "../extensions/taskItemExtended";
 import { LinkWithTitle } from "../extensions/linkWithTitle";
 import { ToggleBlock, ToggleContent, ToggleSummary } from "../extensions/toggleBlock";
@@ -20,6 +21,7 @@ import {
 const editorSchema = getSchema([
   StarterKit.configure({ heading: { levels: [1, 2, 3, 4, 5, 6] } }),
   LinkWithTitle,
+  AttachmentImage,
   Table,
   TableRow,
   TableHeader,
@@ -415,4 +417,18 @@ describe("Tiptap JSON and Markdown round trips", () => {
     expect(walk(document).filter((node) => node.type === "heading").map((node) => node.attrs?.level)).toEqual([4, 5, 6]);
     expect(tiptapDocumentToMarkdown(document)).toBe(markdown);
   });
+
+  it("keeps images as inline nodes so attachment links round-trip", () => {
+    const src = `../.hwan-note-assets/${"a".repeat(64)}.png`;
+    const markdown = `See ![Diagram](${src} "Plan") and ![](${src})`;
+    const document = markdownToTiptapDocument(markdown);
+
+    expectSchemaValid(document);
+    expect(walk(document).filter((node) => node.type === "image").map((node) => node.attrs)).toEqual([
+      { src, alt: "Diagram", title: "Plan" },
+      { src, alt: "", title: null }
+    ]);
+    expect(hasRichTextFormatting(document)).toBe(true);
+    expect(tiptapDocumentToMarkdown(document)).toBe(markdown);
+  });
 });
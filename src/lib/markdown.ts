@@ -131,11 +131,13 @@ function parseInlineTokens(tokens: MarkdownToken[], parseHtmlBreaks = false): JS
         break;
 
       case "image": {
-        const href = token.attrGet("src") ?? "";
-        const imageMarks = href
-          ? [{ type: "link", attrs: { href } }]
-          : marks;
-        appendText(content, token.content || token.attrGet("alt") || href, imageMarks);
+        const src = token.attrGet("src") ?? "";
+        const alt = token.content || token.attrGet("alt") || "";
+        if (src) {
+          content.push({ type: "image", attrs: { src, alt, title: token.attrGet("title") } });
+        } else {
+          appendText(content, alt, marks);
+        }
         break;
       }
 
@@ -556,6 +558,18 @@ function escapeLinkDestination(value: string) {
     .replace(/\s/g, (character) => encodeURIComponent(character));
 }
 
+function serializeLinkTitle(rawTitle: unknown) {
+  return typeof rawTitle === "string" && rawTitle
+    ? ` "${rawTitle.replace(/\\/g, "\\\\").replace(/"/g, '\\"')}"`
+    : "";
+}
+
+function serializeImage(node: JSONContent) {
+  const src = typeof node.attrs?.src === "string" ? node.attrs.src : "";
+  const alt = typeof node.attrs?.alt === "string" ? node.attrs.alt : "";
+  return `![${escapeInlineText(alt)}](${escapeLinkDestination(src)}${serializeLinkTitle(node.attrs?.title)})`;
+}
+
 function serializeTextNode(node: JSONContent) {
   const marks = node.marks ?? [];
   const code = marks.some((mark) => mark.type === "code");
@@ -587,9 +601,7 @@ function serializeTextNode(node: JSONContent) {
   const link = marks.find((mark) => mark.type === "link");
   const href = typeof link?.attrs?.href === "string" ? link.attrs.href : "";
   if (href) {
-    const rawTitle = typeof link?.attrs?.title === "string" ? link.attrs.title : "";
-    const title = rawTitle ? ` "${rawTitle.replace(/\\/g, "\\\\").replace(/"/g, '\\"')}"` : "";
-    value = `[${value}](${escapeLinkDestination(href)}${title})`;
+    value = `[${value}](${escapeLinkDestination(href)}${serializeLinkTitle(link?.attrs?.title)})`;
   }
 
   return `${escapeInlineText(leadingWhitespace)}${value}${escapeInlineText(trailingWhitespace)}`;
@@ -604,6 +616,9 @@ function serializeInline(content: JSONContent[] = [], hardBreak = "  \n"): strin
       if (node.type === "hardBreak") {
         return hardBreak;
       }
+      if (node.type === "image") {
+        return serializeImage(node);
+      }
       return serializeInline(node.content ?? [], hardBreak);
     })
     .join("");
@@ -1,8 +1,11 @@
 import { beforeEach, describe, expect, it, vi } from "vitest";
 
 const invokeMock = vi.hoisted(() => vi.fn());
+const convertFileSrcMock = vi.hoisted(() =>
+  vi.fn((filePath: string, protocol: string) => `http://${protocol}.localhost/${filePath}`)
+);
 
-vi.mock("@tauri-apps/api/core", () => ({ invoke: invokeMock }));
+vi.mock("@tauri-apps/api/core", () => ({ invoke: invokeMock, convertFileSrc: convertFileSrcMock }));
 vi.mock("@tauri-apps/api/event", () => ({ listen: vi.fn() }));
 vi.mock("@tauri-apps/api/window", () => ({ getCurrentWindow: vi.fn() }));
 
@@ -56,3 +59,19 @@ describe("note library mutation IPC", () => {
     });
   });
 });
+
+describe("attachment links", () => {
+  it("maps only attachment link targets to webview URLs", () => {
+    const fileName = `${"0".repeat(64)}.png`;
+
+    expect(hwanNote.attachment.resolveUrl(`../../.hwan-note-assets/${fileName}`)).toBe(
+      `http://hwan-asset.localhost/${fileName}`
+    );
+    expect(hwanNote.attachment.resolveUrl(`.hwan-note-assets/${fileName}`)).toBe(
+      `http://hwan-asset.localhost/${fileName}`
+    );
+    expect(hwanNote.attachment.resolveUrl("https://example.com/a.png")).toBeNull();
+    expect(hwanNote.attachment.resolveUrl(`.hwan-note-assets/../${fileName}`)).toBeNull();
+    expect(hwanNote.attachment.resolveUrl(".hwan-note-assets/photo.png")).toBeNull();
+  });
+});
@@ -1,14 +1,28 @@
-import { invoke } from "@tauri-apps/api/core";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 
 // -- Types (matching Rust serde output) --
 
+export interface RelinkedNote {
+  noteId: string;
+  filePath: string;
+  markdown: string;
+}
+
+export interface NoteMerge {
+  markdown: string;
+  conflicts: number;
+}
+
 export interface AutoSaveResult {
   filePath: string;
   noteId: string;
   createdAt: number;
   updatedAt: number;
+  relinkedNotes?: RelinkedNote[];
+  /** Present when another device saved the note first and both edits were merged. */
+  merge?: NoteMerge;
 }
 
 export interface LoadedNote {
@@ -22,6 +36,19 @@ export interface LoadedNote {
   updatedAt: number;
   filePath: string;
   isPinned: boolean;
+  tags: string[];
+  aliases: string[];
+  isEncrypted: boolean;
+}
+
+export interface ExternalNoteRemoval {
+  noteId: string;
+  filePath: string;
+}
+
+export interface ExternalNoteMove {
+  note: LoadedNote;
+  previousFilePath: string;
 }
 
 export type NoteStorageSource = "local" | "cloud" | "local_fallback";
@@ -45,17 +72,58 @@ export interface NoteLoadResult {
   issues: NoteLoadIssue[];
   indexSourcePath: string | null;
   indexBackupPath: string | null;
+  conflicts: SyncConflict[];
+}
+
+/** A copy a sync client made of a note edited on two machines, kept out of the library until resolved. */
+export interface SyncConflict {
+  noteId: string;
+  filePath: string;
+  conflictPath: string;
+  conflictMarkdown: string;
+  conflictUpdatedAt: number;
 }
 
+export type SyncConflictResolution =
+  | { kind: "keepOriginal" }
+  | { kind: "keepConflict" }
+  | { kind: "keepBoth" }
+  | { kind: "merged"; markdown: string };
+
 export interface SessionData {
   openTabIds: string[];
   activeTabId: string | null;
 }
 
+export type LineEnding = "lf" | "crlf";
+
+/** On-disk encoding of an external text file; `encoding` is a WHATWG name such as "EUC-KR". */
+export interface TextFormat {
+  encoding: string;
+  bom: boolean;
+  lineEnding: LineEnding;
+}
+
+/** Markdown files keep their formatting; other text extensions open as plain text. */
+export type ExternalFileFormat = "md" | "txt";
+
+/** Digest and modification time of an external file as the editor last saw it. */
+export interface ExternalFileVersion {
+  digest: string;
+  modifiedAt: number;
+}
+
+export type ExternalSaveResult =
+  | { status: "saved"; version: ExternalFileVersion }
+  | { status: "conflict"; current: ExternalFileVersion | null };
+
 export interface ImportedFile {
   title: string;
   content: string;
   filePath: string;
+  fileFormat: ExternalFileFormat;
+  textFormat: TextFormat;
+  version: ExternalFileVersion;
 }
 
 export type AutoSaveDirStatus = "unset" | "available" | "unavailable";
@@ -99,6 +167,28 @@ export interface CloudSyncStatus {
   activeSource: CloudSyncSource;
   resolvedSource: NoteStorageSource | null;
   cloudUnavailable: boolean;
+  fallbackChanges: FallbackChangesSummary;
+}
+
+/** Changes written to the local library while the cloud library was unreachable. */
+export interface FallbackChangesSummary {
+  notes: number;
+  deletedNotes: number;
+  calendar: boolean;
+}
+
+export type ReconcileOutcome = "copied" | "renamed" | "updated" | "unchanged" | "deleted" | "conflict";
+
+export interface ReconciledNote {
+  noteId: string;
+  destinationPath: string | null;
+  outcome: ReconcileOutcome;
+}
+
+export interface FallbackReconcileResult {
+  notes: ReconciledNote[];
+  calendar?: ReconcileOutcome;
+  calendarCopyPath?: string;
 }
 
 export interface CloudFolderMissingData {
@@ -122,18 +212,260 @@ export interface CalendarLoadResult {
 export interface FolderDeleteResult {
   folders: string[];
   movedNoteIds: string[];
+  relinkedNotes: RelinkedNote[];
+  trashId: string;
+}
+
+export interface MovedNote {
+  noteId: string;
+  previousFilePath: string;
+  filePath: string;
+}
+
+export interface NoteMoveResult {
+  moved: MovedNote[];
+  relinkedNotes: RelinkedNote[];
+}
+
+export interface NoteTemplate {
+  name: string;
+  markdown: string;
+  prompts: string[];
+  updatedAt: number;
+}
+
+export interface TemplateNoteRequest {
+  templateName: string;
+  noteId: string;
+  folderPath?: string | null;
+  title?: string | null;
+  prompts?: Record<string, string>;
+  utcOffsetMinutes?: number;
+}
+
+export interface DailyNoteSettings {
+  folderPath: string;
+  filenamePattern: string;
+  templateName: string | null;
+}
+
+export type DailyNoteDirection = "previous" | "next";
+
+export interface DailyNote {
+  dateKey: string;
+  created: boolean;
+  calendarLinked: boolean;
+  note: LoadedNote;
+}
+
+export type TrashItemKind = "note" | "folder";
+
+export interface TrashedItem {
+  trashId: string;
+  kind: TrashItemKind;
+  title: string;
+  folderPath: string;
+  deletedAt: number;
+  noteId?: string;
+  noteCount: number;
+}
+
+export interface TrashRestoreResult {
+  restoredNotes: LoadedNote[];
+  moved: MovedNote[];
+  folders: string[];
+  relinkedNotes: RelinkedNote[];
+}
+
+export interface TrashSettings {
+  autoPurgeDays: number;
+}
+
+export type NoteBatchOperation =
+  | { kind: "setPinned"; noteId: string; isPinned: boolean }
+  | { kind: "move"; noteId: string; folderPath: string | null }
+  | { kind: "trash"; noteId: string }
+  | { kind: "setManualTitle"; noteId: string; title: string | null };
+
+export type NoteBatchItemStatus = "applied" | "failed" | "not_applied";
+
+export interface NoteBatchItemResult {
+  noteId: string;
+  status: NoteBatchItemStatus;
+  filePath: string | null;
+  error: string | null;
+}
+
+export interface NoteBatchResult {
+  committed: boolean;
+  items: NoteBatchItemResult[];
+  relinkedNotes: RelinkedNote[];
+}
+
+export interface SiteExportOptions {
+  folderPath: string | null;
+}
+
+export interface SiteExportResult {
+  outputDir: string;
+  noteCount: number;
+  folderCount: number;
+  attachmentCount: number;
+  unresolvedLinkCount: number;
+}
+
+export interface BackupExportResult {
+  bundlePath: string;
+  noteCount: number;
+  folderCount: number;
+  calendarIncluded: boolean;
+}
+
+export type MigrationOutcome = "copied" | "renamed" | "skipped";
+
+export interface MigratedNote {
+  noteId: string;
+  sourcePath: string;
+  destinationPath: string | null;
+  outcome: MigrationOutcome;
+}
+
+export interface BackupImportResult {
+  mode: "restore" | "merge";
+  restored: MigratedNote[];
+  renamed: MigratedNote[];
+  skipped: MigratedNote[];
+  calendarRestored: boolean;
+  calendarSkipped: boolean;
+}
+
+export interface EnexImportFailure {
+  sourcePath: string;
+  title: string | null;
+  reason: string;
+}
+
+export interface EnexImportResult {
+  imported: MigratedNote[];
+  attachmentsImported: number;
+  failures: EnexImportFailure[];
+}
+
+export type VaultLinkMode = "keep" | "convert";
+
+export interface VaultImportOptions {
+  folderPath: string | null;
+  linkMode: VaultLinkMode;
+  dryRun: boolean;
+}
+
+export interface PlannedVaultNote {
+  sourcePath: string;
+  destinationPath: string;
+  title: string | null;
+  createdAt: number;
+  linksConverted: number;
+  destinationExists: boolean;
+}
+
+export interface SkippedVaultEntry {
+  sourcePath: string;
+  reason: string;
+}
+
+export interface VaultImportResult {
+  dryRun: boolean;
+  notes: PlannedVaultNote[];
+  imported: MigratedNote[];
+  skipped: SkippedVaultEntry[];
+}
+
+export interface SnapshotSettings {
+  enabled: boolean;
+  targetDir: string | null;
+  intervalMinutes: number;
+  keepHourly: number;
+  keepDaily: number;
+  keepWeekly: number;
+}
+
+export interface SnapshotSummary {
+  snapshotId: string;
+  createdAt: number;
+  noteCount: number;
+  folderCount: number;
+  calendarIncluded: boolean;
+}
+
+export interface SnapshotRestoreResult {
+  safetySnapshotId: string;
+  notesWritten: number;
+  notesRemoved: number;
+}
+
+export type LibraryIssueKind =
+  | "symbolic_link"
+  | "unreadable_entry"
+  | "pending_autosave_journal"
+  | "stale_journal_temp"
+  | "orphan_temp_file"
+  | "corrupt_index"
+  | "missing_note_file"
+  | "duplicate_path"
+  | "invalid_note_id"
+  | "unindexed_note_file"
+  | "corrupt_index_backup"
+  | "corrupt_calendar_copy";
+
+export interface LibraryIssue {
+  kind: LibraryIssueKind;
+  path: string;
+  noteId?: string;
+  detail: string;
 }
 
+export interface LibraryCheckReport {
+  issues: LibraryIssue[];
+}
+
+export interface LibraryRepairResult {
+  repaired: LibraryIssue[];
+  remaining: LibraryIssue[];
+}
+
+export interface SavedAttachment {
+  fileName: string;
+  linkTarget: string;
+  mimeType: string;
+  size: number;
+  created: boolean;
+}
+
+export interface AttachmentInfo {
+  fileName: string;
+  size: number;
+  modifiedAt: number;
+}
+
+/** Link target a note uses for a file in the library's attachments folder. */
+const ATTACHMENT_LINK_TARGET = /^(?:\.\.\/)*\.hwan-note-assets\/([0-9a-f]{64}\.[a-z0-9]{1,10})$/;
+
 // -- IPC abstraction layer --
 // Replaces window.hwanNote and window.hwanShell with Tauri invoke() calls.
 
 function wrapListener<T>(eventName: string, callback: (payload: T) => void): (() => void) {
+  return wrapUnlisten(
+    listen<T>(eventName, (event) => {
+      callback(event.payload);
+    })
+  );
+}
+
+function wrapUnlisten(pending: Promise<UnlistenFn>): (() => void) {
   let unlisten: UnlistenFn | null = null;
   let cancelled = false;
 
-  listen<T>(eventName, (event) => {
-    callback(event.payload);
-  }).then((fn_) => {
+  pending.then((fn_) => {
     if (cancelled) {
       fn_();
     } else {
@@ -184,8 +516,8 @@ export const hwanNote = {
     importTxt: () =>
       invoke<ImportedFile[] | null>("cmd_note_import_txt"),
 
-    readExternalTxt: (filePath: string) =>
-      invoke<ImportedFile>("cmd_note_read_external_txt", { filePath }),
+    readExternalTxt: (filePath: string, encoding: string | null = null) =>
+      invoke<ImportedFile>("cmd_note_read_external_txt", { filePath, encoding }),
 
     drainOpenIntents: () =>
       invoke<string[]>("cmd_note_drain_open_intents"),
@@ -193,6 +525,21 @@ export const hwanNote = {
     onOpenIntent: (callback: (filePath: string) => void): (() => void) =>
       wrapListener<string>("note:open-intent", callback),
 
+    onExternalAdded: (callback: (notes: LoadedNote[]) => void): (() => void) =>
+      wrapListener<LoadedNote[]>("note:external-added", callback),
+
+    onExternalChanged: (callback: (notes: LoadedNote[]) => void): (() => void) =>
+      wrapListener<LoadedNote[]>("note:external-changed", callback),
+
+    onExternalRemoved: (callback: (removals: ExternalNoteRemoval[]) => void): (() => void) =>
+      wrapListener<ExternalNoteRemoval[]>("note:external-removed", callback),
+
+    onExternalMoved: (callback: (moves: ExternalNoteMove[]) => void): (() => void) =>
+      wrapListener<ExternalNoteMove[]>("note:external-moved", callback),
+
+    onLinksRewritten: (callback: (notes: RelinkedNote[]) => void): (() => void) =>
+      wrapListener<RelinkedNote[]>("note:links-rewritten", callback),
+
     pickSavePath: (
       dialogTitle: string,
       defaultFileName: string,
@@ -204,11 +551,39 @@ export const hwanNote = {
         extension,
       }),
 
-    saveTxt: (filePath: string, content: string) =>
-      invoke<boolean>("cmd_note_save_txt", { filePath, content }),
+    saveTxt: (
+      filePath: string,
+      content: string,
+      textFormat: TextFormat | null = null,
+      expectedVersion: ExternalFileVersion | null = null
+    ) =>
+      invoke<ExternalSaveResult>("cmd_note_save_txt", { filePath, content, textFormat, expectedVersion }),
 
     delete: (noteId: string, loadedFrom: NoteStorageSource) =>
       invoke<boolean>("cmd_note_delete", { noteId, loadedFrom }),
+
+    move: (noteIds: string[], folderPath: string, loadedFrom: NoteStorageSource) =>
+      invoke<NoteMoveResult>("cmd_note_move", { noteIds, folderPath, loadedFrom }),
+
+    batch: (operations: NoteBatchOperation[], loadedFrom: NoteStorageSource) =>
+      invoke<NoteBatchResult>("cmd_note_batch", { operations, loadedFrom }),
+
+    encrypt: (noteId: string, passphrase: string, loadedFrom: NoteStorageSource) =>
+      invoke<LoadedNote>("cmd_note_encrypt", { noteId, passphrase, loadedFrom }),
+
+    decrypt: (noteId: string, passphrase: string, loadedFrom: NoteStorageSource) =>
+      invoke<LoadedNote>("cmd_note_decrypt", { noteId, passphrase, loadedFrom }),
+
+    unlock: (noteId: string, passphrase?: string) =>
+      invoke<LoadedNote>("cmd_note_unlock", { noteId, passphrase: passphrase ?? null }),
+
+    lock: (noteId: string) => invoke<boolean>("cmd_note_lock", { noteId }),
+
+    createFromTemplate: (request: TemplateNoteRequest, loadedFrom: NoteStorageSource) =>
+      invoke<LoadedNote>("cmd_note_create_from_template", {
+        request: { utcOffsetMinutes: -new Date().getTimezoneOffset(), ...request },
+        loadedFrom,
+      }),
   },
 
   folder: {
@@ -225,6 +600,129 @@ export const hwanNote = {
       invoke<FolderDeleteResult>("cmd_folder_delete", { folderPath, loadedFrom }),
   },
 
+  site: {
+    export: (outputDir: string, options: SiteExportOptions) =>
+      invoke<SiteExportResult>("cmd_site_export", { outputDir, options }),
+  },
+
+  backup: {
+    export: (bundlePath: string) =>
+      invoke<BackupExportResult>("cmd_library_backup_export", { bundlePath }),
+    import: (bundlePath: string, loadedFrom: NoteStorageSource) =>
+      invoke<BackupImportResult>("cmd_library_backup_import", { bundlePath, loadedFrom }),
+  },
+
+  library: {
+    importEnex: (
+      enexPaths: string[],
+      folderPath: string | null,
+      loadedFrom: NoteStorageSource
+    ) =>
+      invoke<EnexImportResult>("cmd_library_import_enex", { enexPaths, folderPath, loadedFrom }),
+    importVault: (sourceDir: string, options: VaultImportOptions, loadedFrom: NoteStorageSource) =>
+      invoke<VaultImportResult>("cmd_library_import_vault", { sourceDir, options, loadedFrom }),
+    resolveSyncConflict: (
+      noteId: string,
+      conflictPath: string,
+      resolution: SyncConflictResolution,
+      loadedFrom: NoteStorageSource
+    ) =>
+      invoke<void>("cmd_library_resolve_sync_conflict", { noteId, conflictPath, resolution, loadedFrom }),
+
+    check: () => invoke<LibraryCheckReport>("cmd_library_check"),
+    repair: (kinds: LibraryIssueKind[], loadedFrom: NoteStorageSource) =>
+      invoke<LibraryRepairResult>("cmd_library_repair", { kinds, loadedFrom }),
+  },
+
+  attachment: {
+    save: (
+      fileName: string,
+      data: Uint8Array,
+      noteId: string | null,
+      folderPath: string | null,
+      loadedFrom: NoteStorageSource
+    ) =>
+      invoke<SavedAttachment>("cmd_attachment_save", {
+        fileName,
+        data: Array.from(data),
+        noteId,
+        folderPath,
+        loadedFrom,
+      }),
+    import: (
+      sourcePath: string,
+      noteId: string | null,
+      folderPath: string | null,
+      loadedFrom: NoteStorageSource
+    ) =>
+      invoke<SavedAttachment>("cmd_attachment_import", {
+        sourcePath,
+        noteId,
+        folderPath,
+        loadedFrom,
+      }),
+    findUnreferenced: () => invoke<AttachmentInfo[]>("cmd_attachment_find_unreferenced"),
+    removeUnreferenced: (fileNames: string[], loadedFrom: NoteStorageSource) =>
+      invoke<string[]>("cmd_attachment_remove_unreferenced", { fileNames, loadedFrom }),
+    /** Webview URL for an attachment file name (the last segment of a link target). */
+    url: (fileName: string) => convertFileSrc(fileName, "hwan-asset"),
+    /** Webview URL for a link target into the attachments folder, or null for any other link. */
+    resolveUrl: (linkTarget: string) => {
+      const match = ATTACHMENT_LINK_TARGET.exec(linkTarget);
+      return match ? convertFileSrc(match[1], "hwan-asset") : null;
+    },
+    /** Files dropped onto the window from the OS, with the drop point in CSS pixels. */
+    onFileDrop: (callback: (paths: string[], point: { x: number; y: number }) => void): (() => void) =>
+      wrapUnlisten(
+        getCurrentWindow().onDragDropEvent((event) => {
+          if (event.payload.type !== "drop") {
+            return;
+          }
+          const scale = window.devicePixelRatio || 1;
+          callback(event.payload.paths, {
+            x: event.payload.position.x / scale,
+            y: event.payload.position.y / scale,
+          });
+        })
+      ),
+  },
+
+  template: {
+    list: () => invoke<NoteTemplate[]>("cmd_template_list"),
+
+    save: (name: string, markdown: string, loadedFrom: NoteStorageSource) =>
+      invoke<NoteTemplate>("cmd_template_save", { name, markdown, loadedFrom }),
+  },
+
+  dailyNote: {
+    open: (dateKey: string, loadedFrom: NoteStorageSource) =>
+      invoke<DailyNote>("cmd_daily_note_open", {
+        payload: { dateKey, loadedFrom, utcOffsetMinutes: -new Date().getTimezoneOffset() },
+      }),
+
+    adjacent: (dateKey: string, direction: DailyNoteDirection) =>
+      invoke<DailyNote | null>("cmd_daily_note_adjacent", { dateKey, direction }),
+  },
+
+  trash: {
+    list: () => invoke<TrashedItem[]>("cmd_trash_list"),
+
+    restore: (trashId: string, loadedFrom: NoteStorageSource) =>
+      invoke<TrashRestoreResult>("cmd_trash_restore", { trashId, loadedFrom }),
+
+    purge: (trashIds: string[], loadedFrom: NoteStorageSource) =>
+      invoke<string[]>("cmd_trash_purge", { trashIds, loadedFrom }),
+  },
+
+  snapshot: {
+    list: () => invoke<SnapshotSummary[]>("cmd_snapshot_list"),
+    create: () => invoke<SnapshotSummary>("cmd_snapshot_create"),
+    restoreLibrary: (snapshotId: string, loadedFrom: NoteStorageSource) =>
+      invoke<SnapshotRestoreResult>("cmd_snapshot_restore_library", { snapshotId, loadedFrom }),
+    restoreNotes: (snapshotId: string, noteIds: string[], loadedFrom: NoteStorageSource) =>
+      invoke<AutoSaveResult[]>("cmd_snapshot_restore_notes", { snapshotId, noteIds, loadedFrom }),
+  },
+
   updater: {
     check: () => invoke("cmd_updater_check"),
     download: () => invoke("cmd_updater_download"),
@@ -242,6 +740,30 @@ export const hwanNote = {
 
     getAutoSaveDir: () =>
       invoke<AutoSaveDirInfo>("cmd_settings_get_autosave_dir"),
+
+    getSnapshots: () =>
+      invoke<SnapshotSettings>("cmd_settings_get_snapshots"),
+
+    setSnapshots: (settings: SnapshotSettings) =>
+      invoke<SnapshotSettings>("cmd_settings_set_snapshots", { settings }),
+
+    getTrash: () =>
+      invoke<TrashSettings>("cmd_settings_get_trash"),
+
+    setTrash: (settings: TrashSettings) =>
+      invoke<TrashSettings>("cmd_settings_set_trash", { settings }),
+
+    getDailyNotes: () =>
+      invoke<DailyNoteSettings>("cmd_settings_get_daily_notes"),
+
+    setDailyNotes: (settings: DailyNoteSettings) =>
+      invoke<DailyNoteSettings>("cmd_settings_set_daily_notes", { settings }),
+
+    getTextExtensions: () =>
+      invoke<string[]>("cmd_settings_get_text_extensions"),
+
+    setTextExtensions: (extensions: string[]) =>
+      invoke<string[]>("cmd_settings_set_text_extensions", { extensions }),
   },
 
   calendar: {
@@ -276,6 +798,9 @@ export const hwanNote = {
     detectProviders: () =>
       invoke<CloudProviderInfo[]>("cmd_cloud_detect_providers"),
 
+    setCustomFolder: (dir: string | null) =>
+      invoke<CloudProviderInfo[]>("cmd_cloud_set_custom_folder", { dir }),
+
     enable: (provider: string, copyExisting: boolean) =>
       invoke<CloudSyncResult>("cmd_cloud_sync_enable", { provider, copyExisting }),
 
@@ -288,6 +813,9 @@ export const hwanNote = {
     setActiveSource: (source: CloudSyncSource) =>
       invoke<CloudSyncStatus>("cmd_cloud_sync_set_active_source", { source }),
 
+    reconcileFallback: () =>
+      invoke<FallbackReconcileResult>("cmd_cloud_reconcile_fallback"),
+
     onFolderMissing: (callback: (data: CloudFolderMissingData) => void): (() => void) =>
       wrapListener<CloudFolderMissingData>("cloud:folder-missing", callback),
   },
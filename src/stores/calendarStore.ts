@@ -1,5 +1,5 @@
 import { create, type UseBoundStore, type StoreApi } from "zustand";
-import { hwanNote, type CalendarStorageSource } from "../lib/tauriApi";
+import { hwanNote, type CalendarStorageSource, type DailyNote } from "../lib/tauriApi";
 import {
   compareCalendarTodoRows,
   createEmptyCalendarData,
@@ -66,6 +66,7 @@ export interface CalendarStore {
   addNoteLink: (dateKey: string, noteId: string) => void;
   removeNoteLink: (dateKey: string, noteId: string) => void;
   removeNoteLinks: (noteId: string) => void;
+  openDailyNote: (dateKey: string) => Promise<DailyNote>;
   cleanOrphanNoteLinks: (authoritative: boolean) => void;
 }
 
@@ -776,6 +777,16 @@ export const useCalendarStore: UseBoundStore<StoreApi<CalendarStore>> = create<C
     });
   },
 
+  openDailyNote: async (dateKey) => {
+    const dailyNote = await hwanNote.dailyNote.open(
+      dateKey,
+      useCalendarStore.getState().loadedFrom
+    );
+    // The backend already wrote the link; mirror it so a later save keeps it.
+    useCalendarStore.getState().addNoteLink(dailyNote.dateKey, dailyNote.note.noteId);
+    return dailyNote;
+  },
+
   cleanOrphanNoteLinks: (authoritative) => {
     if (!authoritative || useCalendarStore.getState().loadState !== "ready") {
       return;
@@ -172,3 +172,41 @@ describe("note save revisions", () => {
     expect(current.savedSnapshot?.revision).toBe(0);
   });
 });
+
+describe("external library changes", () => {
+  it("reloads clean tabs and flags dirty tabs without overwriting their edits", () => {
+    const dirtyId = "note-dirty";
+    hydrate([createLibraryTab(TAB_ID), createLibraryTab(dirtyId, "draft")]);
+    useNoteStore.getState().updateTabContent(dirtyId, documentWithText("unsaved"), "unsaved");
+
+    const conflicted = useNoteStore.getState().applyExternalNotes([
+      createLibraryTab(TAB_ID, "outside"),
+      createLibraryTab(dirtyId, "outside"),
+      createLibraryTab("note-added", "new")
+    ]);
+
+    const state = useNoteStore.getState();
+    expect(conflicted).toEqual([dirtyId]);
+    expect(state.notesById[TAB_ID].plainText).toBe("outside");
+    expect(state.notesById[TAB_ID].revision).toBe(1);
+    expect(state.notesById[TAB_ID].isDirty).toBe(false);
+    expect(state.notesById[dirtyId].plainText).toBe("unsaved");
+    expect(state.notesById[dirtyId].hasExternalChanges).toBe(true);
+    expect(state.noteIds).toContain("note-added");
+  });
+
+  it("closes removed clean tabs and keeps removed dirty tabs for the next save", () => {
+    const dirtyId = "note-dirty";
+    hydrate([createLibraryTab(TAB_ID), createLibraryTab(dirtyId, "draft")]);
+    useNoteStore.getState().updateTabContent(dirtyId, documentWithText("unsaved"), "unsaved");
+
+    const conflicted = useNoteStore.getState().removeExternalNotes([TAB_ID, dirtyId]);
+
+    const state = useNoteStore.getState();
+    expect(conflicted).toEqual([dirtyId]);
+    expect(state.notesById[TAB_ID]).toBeUndefined();
+    expect(state.openTabIds).toEqual([dirtyId]);
+    expect(state.activeTabId).toBe(dirtyId);
+    expect(state.notesById[dirtyId].hasExternalChanges).toBe(true);
+  });
+});
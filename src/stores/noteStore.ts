@@ -1,6 +1,6 @@
 import type { JSONContent } from "@tiptap/core";
 import { create } from "zustand";
-import { hwanNote } from "../lib/tauriApi";
+import { hwanNote, type ExternalFileVersion, type TextFormat } from "../lib/tauriApi";
 import { normalizeFolderPath } from "../lib/folderPaths";
 
 export const OPEN_TAB_IDS_KEY = "hwan-note:open-tab-ids";
@@ -30,14 +30,18 @@ export interface NoteTab {
   plainText: string;
   isDirty: boolean;
   isPinned: boolean;
+  frontMatterTags?: string[];
   folderPath: string;
   createdAt: number;
   updatedAt: number;
   lastSavedAt: number;
   sourceFilePath?: string;
+  textFormat?: TextFormat;
+  fileVersion?: ExternalFileVersion;
   fileFormat: "md" | "txt";
   persistence: NotePersistence;
   savedSnapshot: SavedNoteSnapshot | null;
+  hasExternalChanges?: boolean;
 }
 
 export interface PersistedTabSession {
@@ -49,6 +53,7 @@ export interface SaveTabOptions {
   savedSnapshot: SavedNoteSnapshot;
   persistence?: NotePersistence;
   sourceFilePath?: string;
+  fileVersion?: ExternalFileVersion;
 }
 
 export type DiscardTabResult = "none" | "reverted" | "removed";
@@ -64,7 +69,15 @@ interface NoteStore {
   sidebarVisible: boolean;
   hydrateTabs: (tabs: NoteTab[], persistedSession?: PersistedTabSession) => void;
   createTab: () => void;
-  addImportedTab: (title: string, content: JSONContent, plainText: string, sourceFilePath?: string) => void;
+  addImportedTab: (
+    title: string,
+    content: JSONContent,
+    plainText: string,
+    sourceFilePath?: string,
+    textFormat?: TextFormat,
+    fileFormat?: "md" | "txt",
+    fileVersion?: ExternalFileVersion
+  ) => void;
   openNote: (id: string) => void;
   setActiveTab: (id: string) => void;
   closeTab: (id: string) => void;
@@ -84,6 +97,18 @@ interface NoteStore {
   markTabSaved: (id: string, options: SaveTabOptions) => boolean;
   discardTabChanges: (id: string) => DiscardTabResult;
   toggleFileFormat: (id: string) => void;
+  setTabTextFormat: (id: string, textFormat: TextFormat) => void;
+  reloadExternalTab: (
+    id: string,
+    content: JSONContent,
+    plainText: string,
+    textFormat: TextFormat,
+    fileVersion: ExternalFileVersion
+  ) => void;
+  applyMergedNote: (id: string, revision: number, content: JSONContent, plainText: string) => void;
+  adoptExternalTab: (id: string) => void;
+  applyExternalNotes: (tabs: NoteTab[]) => string[];
+  removeExternalNotes: (ids: string[]) => string[];
   toggleSidebar: () => void;
 }
 
@@ -332,7 +357,7 @@ export const useNoteStore = create<NoteStore>((set, get) => {
         return buildStateSlice(nextNotesById, state.noteIds, nextOpenTabIds, tab.id);
       });
     },
-    addImportedTab: (title, content, plainText, sourceFilePath) => {
+    addImportedTab: (title, content, plainText, sourceFilePath, textFormat, fileFormat, fileVersion) => {
       const now = Date.now();
       const tab: NoteTab = {
         id: createId(),
@@ -348,7 +373,9 @@ export const useNoteStore = create<NoteStore>((set, get) => {
         updatedAt: now,
         lastSavedAt: 0,
         sourceFilePath,
-        fileFormat: sourceFilePath ? "txt" : "md",
+        textFormat,
+        fileVersion,
+        fileFormat: fileFormat ?? (sourceFilePath ? "txt" : "md"),
         persistence: sourceFilePath ? "external" : "transient",
         savedSnapshot: null
       };
@@ -803,7 +830,9 @@ export const useNoteStore = create<NoteStore>((set, get) => {
           ...target,
           persistence: nextPersistence,
           sourceFilePath: nextSourceFilePath,
+          fileVersion: options.fileVersion ?? target.fileVersion,
           isDirty: !savedCurrentRevision,
+          hasExternalChanges: savedCurrentRevision ? false : target.hasExternalChanges,
           lastSavedAt: nextSavedSnapshot.lastSavedAt,
           savedSnapshot: nextSavedSnapshot
         };
@@ -909,6 +938,213 @@ export const useNoteStore = create<NoteStore>((set, get) => {
         };
       });
     },
+    setTabTextFormat: (id, textFormat) => {
+      set((state) => {
+        const target = state.notesById[id];
+        if (!target) {
+          return state;
+        }
+
+        const nextNotesById: Record<string, NoteTab> = {
+          ...state.notesById,
+          [id]: {
+            ...target,
+            textFormat,
+            revision: target.revision + 1,
+            isDirty: true,
+            updatedAt: Date.now()
+          }
+        };
+
+        const nextCollections = buildCollections(nextNotesById, state.noteIds, state.openTabIds, state.activeTabId);
+        return {
+          notesById: nextNotesById,
+          allNotes: nextCollections.allNotes,
+          openTabs: nextCollections.openTabs,
+          activeOpenTab: nextCollections.activeOpenTab
+        };
+      });
+    },
+    reloadExternalTab: (id, content, plainText, textFormat, fileVersion) => {
+      set((state) => {
+        const target = state.notesById[id];
+        if (!target) {
+          return state;
+        }
+
+        const now = Date.now();
+        const nextTab: NoteTab = {
+          ...target,
+          content,
+          plainText,
+          textFormat,
+          fileVersion,
+          revision: target.revision + 1,
+          isDirty: false,
+          updatedAt: now,
+          lastSavedAt: now
+        };
+        nextTab.savedSnapshot = createSavedSnapshot(nextTab);
+        const nextNotesById = { ...state.notesById, [id]: nextTab };
+
+        const nextCollections = buildCollections(nextNotesById, state.noteIds, state.openTabIds, state.activeTabId);
+        return {
+          notesById: nextNotesById,
+          allNotes: nextCollections.allNotes,
+          openTabs: nextCollections.openTabs,
+          activeOpenTab: nextCollections.activeOpenTab
+        };
+      });
+    },
+    applyMergedNote: (id, revision, content, plainText) => {
+      set((state) => {
+        const target = state.notesById[id];
+        if (!target || target.revision !== revision) {
+          return state;
+        }
+
+        const nextTab: NoteTab = {
+          ...target,
+          content,
+          plainText,
+          revision: target.revision + 1,
+          isDirty: false,
+          updatedAt: Date.now()
+        };
+        nextTab.savedSnapshot = createSavedSnapshot(nextTab);
+        const nextNotesById = { ...state.notesById, [id]: nextTab };
+
+        const nextCollections = buildCollections(nextNotesById, state.noteIds, state.openTabIds, state.activeTabId);
+        return {
+          notesById: nextNotesById,
+          allNotes: nextCollections.allNotes,
+          openTabs: nextCollections.openTabs,
+          activeOpenTab: nextCollections.activeOpenTab
+        };
+      });
+    },
+    applyExternalNotes: (tabs) => {
+      const conflictedIds: string[] = [];
+
+      set((state) => {
+        if (tabs.length === 0) {
+          return state;
+        }
+
+        const nextNotesById = { ...state.notesById };
+        const nextNoteIds = [...state.noteIds];
+        tabs.forEach((loaded) => {
+          const current = nextNotesById[loaded.id];
+          if (!current) {
+            nextNotesById[loaded.id] = loaded;
+            if (!nextNoteIds.includes(loaded.id)) {
+              nextNoteIds.push(loaded.id);
+            }
+            return;
+          }
+
+          if (current.persistence !== "library") {
+            return;
+          }
+
+          if (current.isDirty) {
+            // Unsaved edits win for now; the next autosave merges them with the outside change.
+            conflictedIds.push(current.id);
+            nextNotesById[current.id] = {
+              ...current,
+              folderPath: loaded.folderPath,
+              hasExternalChanges: true
+            };
+            return;
+          }
+
+          const revision = current.revision + 1;
+          nextNotesById[current.id] = {
+            ...loaded,
+            revision,
+            savedSnapshot: loaded.savedSnapshot ? { ...loaded.savedSnapshot, revision } : null
+          };
+        });
+
+        const nextCollections = buildCollections(nextNotesById, nextNoteIds, state.openTabIds, state.activeTabId);
+        return {
+          notesById: nextNotesById,
+          noteIds: nextCollections.noteIds,
+          allNotes: nextCollections.allNotes,
+          openTabs: nextCollections.openTabs,
+          activeOpenTab: nextCollections.activeOpenTab
+        };
+      });
+
+      return conflictedIds;
+    },
+    removeExternalNotes: (ids) => {
+      const conflictedIds: string[] = [];
+
+      set((state) => {
+        const nextNotesById = { ...state.notesById };
+        const removedIds = new Set<string>();
+        ids.forEach((id) => {
+          const current = nextNotesById[id];
+          if (!current || current.persistence !== "library") {
+            return;
+          }
+
+          if (current.isDirty) {
+            conflictedIds.push(id);
+            nextNotesById[id] = { ...current, hasExternalChanges: true };
+            return;
+          }
+
+          delete nextNotesById[id];
+          removedIds.add(id);
+        });
+
+        if (removedIds.size === 0 && conflictedIds.length === 0) {
+          return state;
+        }
+
+        const nextNoteIds = state.noteIds.filter((noteId) => !removedIds.has(noteId));
+        const nextOpenTabIds = state.openTabIds.filter((openId) => !removedIds.has(openId));
+        const preferredActiveTabId =
+          state.activeTabId && removedIds.has(state.activeTabId) ? (nextOpenTabIds[0] ?? null) : state.activeTabId;
+
+        return buildStateSlice(nextNotesById, nextNoteIds, nextOpenTabIds, preferredActiveTabId);
+      });
+
+      return conflictedIds;
+    },
+    adoptExternalTab: (id) => {
+      set((state) => {
+        const target = state.notesById[id];
+        if (!target || target.persistence !== "external") {
+          return state;
+        }
+
+        const nextNotesById: Record<string, NoteTab> = {
+          ...state.notesById,
+          [id]: {
+            ...target,
+            sourceFilePath: undefined,
+            textFormat: undefined,
+            fileVersion: undefined,
+            persistence: "transient",
+            revision: target.revision + 1,
+            isDirty: true,
+            updatedAt: Date.now(),
+            savedSnapshot: null
+          }
+        };
+
+        const nextCollections = buildCollections(nextNotesById, state.noteIds, state.openTabIds, state.activeTabId);
+        return {
+          notesById: nextNotesById,
+          allNotes: nextCollections.allNotes,
+          openTabs: nextCollections.openTabs,
+          activeOpenTab: nextCollections.activeOpenTab
+        };
+      });
+    },
     toggleSidebar: () => {
       set((state) => ({ sidebarVisible: !state.sidebarVisible }));
     }